@@ -0,0 +1,22 @@
+//! The audit log of a push.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use pwpush_cli::args::PushKind;
+use pwpush_cli::{audit, pwpush_api};
+use pwpush_cli_fuzz::{date_formatters, instance, Canned};
+
+fuzz_target!(|data: &[u8]| {
+    let canned = Canned::from_bytes(data);
+    let Ok(log) = pwpush_api::audit(&canned, &instance(), PushKind::Text, "abc") else {
+        return;
+    };
+
+    for dates in date_formatters() {
+        for view in &log.views {
+            let _ = audit::format_view(view, &dates);
+            let _ = (view.created(), view.ip_addr(), audit::kind_of(view));
+        }
+    }
+});
@@ -0,0 +1,23 @@
+//! The lists of active and expired pushes of the account.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use pwpush_cli::args::{PushKind, PushState};
+use pwpush_cli::{info, pwpush_api};
+use pwpush_cli_fuzz::{date_formatters, instance, Canned};
+
+fuzz_target!(|data: &[u8]| {
+    let canned = Canned::from_bytes(data);
+    let Ok(pushes) = pwpush_api::list(&canned, &instance(), PushKind::Text, PushState::Active)
+    else {
+        return;
+    };
+
+    for dates in date_formatters() {
+        for push in &pushes {
+            let _ = info::format_info(push, &dates);
+            let _ = push.limits();
+        }
+    }
+});
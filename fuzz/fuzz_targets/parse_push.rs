@@ -0,0 +1,26 @@
+//! A single push, as the answer to a new push or to a lookup.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use pwpush_cli::models::{Preview, Push};
+use pwpush_cli::{api_body, info, localized, pwpush_api, redact};
+use pwpush_cli_fuzz::{date_formatters, Canned};
+
+fuzz_target!(|data: &[u8]| {
+    let canned = Canned::from_bytes(data);
+    let _ = api_body::parse_json::<Preview>(&canned.0, "preview");
+    let _ = (
+        redact::sanitize_json(&canned.0),
+        localized::classify_body(&canned.0),
+    );
+
+    if let Ok(push) = pwpush_api::parse_push(canned.response()) {
+        for dates in date_formatters() {
+            let _ = info::format_info(&push, &dates);
+            let _ = info::expired_error(&push, &dates);
+            let _ = push.limits();
+        }
+        let _ = api_body::parse_json::<Push>(&canned.0, "push");
+    }
+});
@@ -0,0 +1,55 @@
+//! Shared parts of the fuzz targets.
+//!
+//! Every target answers the requests of the CLI with the fuzz input, so the
+//! input goes through the same parsing as a response of an instance, and then
+//! shows what was parsed the way the commands do. Neither must panic.
+
+use chrono::Utc;
+use pwpush_cli::args::InstanceProtocol;
+use pwpush_cli::dates::{DateFormat, DateFormatter};
+use pwpush_cli::errors::PPCError;
+use pwpush_cli::http::{PPCRequest, PPCResponse, Transport};
+use pwpush_cli::instance::Instance;
+
+/// Answers every request with the same body.
+pub struct Canned(pub String);
+
+impl Canned {
+    pub fn from_bytes(data: &[u8]) -> Self {
+        Self(String::from_utf8_lossy(data).into_owned())
+    }
+
+    pub fn response(&self) -> PPCResponse {
+        PPCResponse {
+            status: 200,
+            headers: Vec::new(),
+            body: self.0.clone(),
+            not_modified: false,
+        }
+    }
+}
+
+impl Transport for Canned {
+    fn send(&self, _request: &PPCRequest) -> Result<PPCResponse, PPCError> {
+        Ok(self.response())
+    }
+}
+
+pub fn instance() -> Instance {
+    Instance {
+        protocol: InstanceProtocol::Https,
+        url: String::from("pwpush.example.com"),
+        email: Some(String::from("user@example.com")),
+        token: Some("t0k3n".into()),
+        auth_scheme: None,
+    }
+}
+
+/// Formatters for all kinds of dates, as they parse the dates of the
+/// response.
+pub fn date_formatters() -> Vec<DateFormatter> {
+    [DateFormat::Iso, DateFormat::Relative, DateFormat::Local]
+        .into_iter()
+        .map(|format| DateFormatter::with(format, Utc::now()))
+        .collect()
+}
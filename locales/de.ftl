@@ -0,0 +1,234 @@
+# Messages of pwpush-cli in German.
+#
+# Messages that are missing here are shown in English. See `src/i18n.rs`.
+
+## Dates, see `dates`
+
+date-just-now = gerade eben
+date-minutes-ago = { $amount ->
+    [one] vor { $amount } Minute
+   *[other] vor { $amount } Minuten
+}
+date-hours-ago = { $amount ->
+    [one] vor { $amount } Stunde
+   *[other] vor { $amount } Stunden
+}
+date-days-ago = { $amount ->
+    [one] vor { $amount } Tag
+   *[other] vor { $amount } Tagen
+}
+date-in-minutes = { $amount ->
+    [one] in { $amount } Minute
+   *[other] in { $amount } Minuten
+}
+date-in-hours = { $amount ->
+    [one] in { $amount } Stunde
+   *[other] in { $amount } Stunden
+}
+date-in-days = { $amount ->
+    [one] in { $amount } Tag
+   *[other] in { $amount } Tagen
+}
+
+## Remaining lifetime of a push, see `info`
+
+countdown-expired = abgelaufen
+countdown-expired-on = abgelaufen am { $date }
+countdown-expired-relative = abgelaufen { $date }
+countdown-today = heute
+countdown-days = { $days ->
+    [one] in { $days } Tag
+   *[other] in { $days } Tagen
+}
+countdown-views = { $views ->
+    [one] { $views } weiteren Abruf
+   *[other] { $views } weiteren Abrufen
+}
+countdown-days-or-views = läuft { $days } oder nach { $views } ab
+countdown-days-only = läuft { $days } ab
+countdown-views-only = läuft nach { $views } ab
+countdown-unknown = Ablauf unbekannt
+
+## Audit logs
+
+audit-no-views = keine Abrufe aufgezeichnet
+audit-successful = erfolgreich
+audit-failed = fehlgeschlagen
+audit-report-views = { $views } Abrufe, { $failed } fehlgeschlagen
+audit-report-last-access = zuletzt abgerufen { $date }
+audit-report-never = nie abgerufen
+audit-report-suspicious = wiederholt fehlgeschlagene Abrufe von { $addresses }
+audit-report-no-pushes = keine aktiven Pushes
+
+## Summary
+
+summary-pushes = Pushes
+summary-active = aktiv
+summary-expired = abgelaufen
+summary-expiring-soon = läuft bald ab
+summary-most-viewed = am häufigsten abgerufen
+summary-none = keine
+summary-remaining = noch { $days } Tage, { $views } Abrufe
+summary-views = { $views } Abrufe
+
+## Prompts
+
+prompt-yes-no = [j/N]
+prompt-yes = j, ja
+expire-question = { $kind }-Push { $url_token } auf { $url } ablaufen lassen?
+expire-aborted = abgebrochen, es ist nichts abgelaufen
+expire-done = { $kind }-Push { $url_token } ist abgelaufen
+push-aborted = abgebrochen, es wurde nichts gepusht
+history-clear-question = Lokalen Verlauf der Pushes löschen?
+history-clear-aborted = abgebrochen, der Verlauf bleibt erhalten
+
+## Errors
+
+error-confirm-non-interactive = im nicht-interaktiven Modus kann nicht nachgefragt werden ({ $question }), --yes verwenden
+error-unauthorized = ungültige Zugangsdaten: die Instanz hat das Token abgelehnt (Status 401)
+error-rate-limited = die Instanz begrenzt die Anfragen (Status 429)
+error-status = die Instanz antwortete mit Status { $status }: { $body }
+error-status-page = die Instanz antwortete mit Status { $status }
+error-too-large = die Instanz hat die Anfrage als zu groß abgelehnt (Status 413)
+error-file-pushes-disabled = Datei-Pushes sind auf dieser Instanz nicht aktiviert
+error-timeout = die Anfrage an { $host } hat zu lange gedauert
+error-dns = der Host { $host } wurde nicht gefunden
+error-connection-refused = { $host } hat die Verbindung abgelehnt
+error-certificate = dem TLS-Zertifikat von { $host } wird nicht vertraut
+error-connection = keine Verbindung zu { $host }: { $cause }
+error-redirect = die Instanz hat die { $method }-Anfrage an { $target } umgeleitet, der Umleitung wurde nicht gefolgt, damit Zugangsdaten und Inhalt nicht dorthin gesendet werden
+
+## Hinweise zur Behebung von Fehlern, unter ihnen angezeigt
+
+hint-unauthorized = --email und --token vergessen? Sonst diese und --auth-scheme prüfen
+hint-rate-limited = später erneut versuchen, oder die Rate mit --max-rate senken
+hint-not-found = der Push wurde vielleicht gelöscht, oder die URL der Instanz verweist nicht auf Password Pusher
+hint-too-large = große Geheimnisse mit `push file` pushen, oder den Administrator bitten, das Limit zu erhöhen
+hint-server-error = die Instanz hat ein Problem, später erneut versuchen oder den Administrator informieren
+hint-file-pushes-disabled = den Administrator bitten, Datei-Pushes zu aktivieren, und mit --email und --token anmelden
+hint-timeout = die Instanz oder das Netzwerk ist vielleicht nicht erreichbar, später erneut versuchen
+hint-dns = die URL der Instanz in --url oder im Profil auf Tippfehler prüfen
+hint-connection-refused = den Port in der URL der Instanz prüfen, und ob die Instanz läuft
+hint-certificate = die Instanz nutzt vielleicht ein Zertifikat einer internen CA, die CA dem Zertifikatsspeicher des Systems hinzufügen
+hint-connection = die Netzwerkverbindung prüfen, und den Proxy in HTTPS_PROXY, falls einer nötig ist
+hint-redirect = { $target } mit --url oder als URL des Profils verwenden
+
+## Pushes
+
+push-passphrase = Passphrase: { $passphrase }
+push-queued = Push { $id } ist in der Warteschlange, später mit `queue flush` senden
+push-unreachable = die Instanz ist nicht erreichbar: { $error }
+push-qr-link-plain = --qr-link wird mit --plain ignoriert
+push-secret-argument = das Geheimnis wurde als Argument auf der Kommandozeile übergeben, wo es in der Prozessliste und im Verlauf der Shell sichtbar ist. Stattdessen --stdin oder --prompt verwenden, oder die Warnung mit --no-warn abschalten
+push-option-argument = { $option } wurde als Argument auf der Kommandozeile übergeben, wo es in der Prozessliste und im Verlauf der Shell sichtbar ist. Weglassen, um danach gefragt zu werden, --stdin verwenden, oder die Warnung mit --no-warn abschalten
+push-note-left-out = auf { $url } sind Anmeldungen deaktiviert, daher wird die Notiz weggelassen, da nur Konten Notizen sehen
+push-whole-days = Instanzen lassen Pushes nur nach ganzen Tagen ablaufen, daher läuft der Push nach { $days ->
+    [one] { $days } Tag
+   *[other] { $days } Tagen
+} statt nach { $requested } ab
+push-whole-days-on = Instanzen lassen Pushes nur nach ganzen Tagen ablaufen, daher läuft der Push nach { $days ->
+    [one] { $days } Tag
+   *[other] { $days } Tagen
+} am { $date } statt am { $requested } ab. Mit --exact läuft er pünktlich ab
+push-weak-passphrase = die Passphrase ist schwach, da { $weakness }. Mit einer stärkeren sind die Empfänger sicherer
+push-duplicate = dasselbe Geheimnis wurde bereits am { $date } gepusht
+push-duplicate-refused = dasselbe Geheimnis wurde bereits am { $date } gepusht. Mit --dedupe warn wird es trotzdem gepusht
+push-not-deduplicated = der Push konnte für --dedupe nicht vermerkt werden: { $error }
+push-not-in-history = Push { $url_token } konnte nicht im Verlauf vermerkt werden: { $error }
+push-not-shortened = der Link konnte nicht gekürzt werden: { $error }
+push-not-printed = der Push konnte nicht ausgegeben werden: { $error }
+push-symlink-skipped = der symbolische Link { $path } wird nicht archiviert
+push-expire-unverified = es ist nicht sicher, dass { $kind }-Push { $url_token } abgelaufen ist, die Antwort der Instanz hat es nicht bestätigt
+
+## Warum eine Passphrase schwach ist, siehe `passphrase`
+
+passphrase-common = sie ein häufig verwendetes Passwort ist
+passphrase-common-variant = sie ein häufig verwendetes Passwort mit wenigen weiteren Zeichen ist
+passphrase-sequences = sie größtenteils aus wiederholten Zeichen oder Folgen besteht
+passphrase-short = sie zu kurz ist, mehr Zeichen oder mehrere Wörter verwenden
+
+## Halten und Beobachten von Pushes
+
+hold-until = Strg-C
+hold-until-timeout = Strg-C oder nach { $duration }
+hold-ephemeral = Push { $url_token } läuft ab bei { $until }
+hold-waiting = warten bis { $until }
+hold-no-interrupt = es wird nicht mehr auf Strg-C gewartet
+hold-expired = Push { $url_token } ist abgelaufen
+first-view-waiting = Push { $url_token } läuft nach dem ersten bestätigten Abruf ab, dafür weiterlaufen lassen
+first-view-missed = Push { $url_token } ist ohne bestätigten Abruf abgelaufen
+first-view-expired = Push { $url_token } ist abgelaufen, nachdem er am { $date } abgerufen wurde
+poll-failed = { $what } konnte nicht abgefragt werden, neuer Versuch: { $error }
+poll-audit-log = das Audit-Log
+poll-push-list = die Liste der Pushes
+poll-expired-pushes = abgelaufene Pushes
+info-unchanged = unverändert
+interrupted = unterbrochen
+interrupted-again = erneut unterbrochen, wird beendet
+interrupted-finishing = unterbrochen, wird abgeschlossen (erneut Strg-C drücken, um sofort zu beenden)
+interrupt-unavailable = Strg-C kann nicht abgefangen werden: { $error }
+
+## Befehle
+
+get-wrong-passphrase = falsche Passphrase (Versuch { $attempt } von { $attempts })
+get-stored-in-vault = Inhalt von Push { $url_token } in Vault unter { $secret } gespeichert
+update-done = { $kind }-Push { $url_token } aktualisiert
+copy-done = { $url_token } als { $copy } auf { $url } kopiert
+rotate-question = Text-Push { $url_token } auf { $url } ersetzen? Der alte Link funktioniert dann nicht mehr.
+rotate-aborted = abgebrochen, es wurde nichts ersetzt
+rotate-done = { $url_token } ist abgelaufen, neuer Push: { $url }
+import-skipped = { $url_token } wird übersprungen: { $reason }
+import-not-text = nur Text-Pushes können importiert werden
+import-expired = der Push ist abgelaufen
+import-no-payload = kein Inhalt im Export, export --include-payloads verwenden
+import-done = { $imported } von { $total } Pushes importiert
+presets-none = keine Vorlagen konfiguriert
+bulk-none-in-file = keine URL-Tokens in { $path }
+bulk-none-tagged = keine URL-Tokens im Verlauf mit Tag { $tags }
+bulk-question = { $count } { $kind }-Pushes auf { $url } ablaufen lassen?
+job-discard-question = Auftrag { $id } mit { $open } offenen Einträgen verwerfen?
+job-discard-aborted = abgebrochen, der Auftrag bleibt erhalten
+history-disabled = der Verlauf ist deaktiviert, mit `enabled = true` im Abschnitt [history] der Konfigurationsdatei aktivieren
+
+## Warteschlange
+
+queue-unreachable = { $id }: die Instanz ist weiterhin nicht erreichbar: { $error }
+queue-kept = { $id }: die Instanz antwortete mit Status { $status }
+queue-rejected = { $id }: mit Status { $status } abgelehnt, wird aus der Warteschlange entfernt: { $body }
+
+## Hooks
+
+hook-on-view-command-failed = Befehl für Abrufe fehlgeschlagen: { $error }
+hook-on-view-webhook-failed = Webhook für Abrufe fehlgeschlagen: { $error }
+hook-post-push-failed = post-push-Hook fehlgeschlagen: { $error }
+hook-post-expire-failed = post-expire-Hook fehlgeschlagen: { $error }
+hook-notify-failed = Webhook für Benachrichtigungen fehlgeschlagen: { $error }
+hook-event-failed = Webhook für Ereignisse fehlgeschlagen: { $error }
+
+## Zugangsdaten
+
+auth-stored = Token von Profil `{ $profile }` gespeichert, verschlüsselt mit { $protection ->
+    [passphrase] einer Passphrase
+   *[local] dem Schlüssel dieses Rechners
+}
+auth-nothing-to-encrypt = keine unverschlüsselten Tokens in { $path }
+auth-encrypted = Tokens von { $profiles } verschlüsselt
+auth-exported = { $count ->
+    [one] { $count } Profil exportiert, mit `auth import` und derselben Passphrase importieren
+   *[other] { $count } Profile exportiert, mit `auth import` und derselben Passphrase importieren
+}
+auth-imported = { $profiles } importiert
+auth-readable = { $path } enthält unverschlüsselte Tokens und kann von anderen Benutzern gelesen werden, sie mit `auth encrypt` verschlüsseln
+smtp-delete-question = SMTP-Passwort aus dem Schlüsselbund löschen?
+smtp-delete-aborted = abgebrochen, das Passwort bleibt erhalten
+
+## Konfiguration und Instanz
+
+config-missing = die Konfigurationsdatei { $path } existiert nicht
+cache-reset = der Cache wird geleert: { $error }
+cache-not-saved = der Cache konnte nicht gespeichert werden: { $error }
+policy-lowered = { $name } wurde von { $value } auf { $max } gesenkt, wie von der Richtlinie in { $path } verlangt
+clock-behind = die Uhr dieses Rechners geht { $skew } nach gegenüber der Instanz, Zeiten werden nach der Uhr der Instanz berechnet
+clock-ahead = die Uhr dieses Rechners geht { $skew } vor gegenüber der Instanz, Zeiten werden nach der Uhr der Instanz berechnet
+retry-budget-used = die Instanz begrenzt die Anfragen, das Budget von { $seconds }s für Wiederholungen ist aufgebraucht
+redirect-moved = { $from } ist dauerhaft nach { $to } umgezogen, { $hint }
@@ -0,0 +1,236 @@
+# Messages of pwpush-cli in English.
+#
+# English is the fallback for messages that are missing in other languages,
+# so every message is defined here first. See `src/i18n.rs`.
+
+## Dates, see `dates`
+
+date-just-now = just now
+date-minutes-ago = { $amount ->
+    [one] { $amount } minute ago
+   *[other] { $amount } minutes ago
+}
+date-hours-ago = { $amount ->
+    [one] { $amount } hour ago
+   *[other] { $amount } hours ago
+}
+date-days-ago = { $amount ->
+    [one] { $amount } day ago
+   *[other] { $amount } days ago
+}
+date-in-minutes = { $amount ->
+    [one] in { $amount } minute
+   *[other] in { $amount } minutes
+}
+date-in-hours = { $amount ->
+    [one] in { $amount } hour
+   *[other] in { $amount } hours
+}
+date-in-days = { $amount ->
+    [one] in { $amount } day
+   *[other] in { $amount } days
+}
+
+## Remaining lifetime of a push, see `info`
+
+countdown-expired = expired
+countdown-expired-on = expired on { $date }
+countdown-expired-relative = expired { $date }
+countdown-today = today
+countdown-days = { $days ->
+    [one] in { $days } day
+   *[other] in { $days } days
+}
+countdown-views = { $views ->
+    [one] { $views } more view
+   *[other] { $views } more views
+}
+countdown-days-or-views = expires { $days } or after { $views }
+countdown-days-only = expires { $days }
+countdown-views-only = expires after { $views }
+countdown-unknown = expiration unknown
+
+## Audit logs
+
+audit-no-views = no views recorded
+audit-successful = successful
+audit-failed = failed
+audit-report-views = { $views } views, { $failed } failed
+audit-report-last-access = last access { $date }
+audit-report-never = never accessed
+audit-report-suspicious = repeated failed views from { $addresses }
+audit-report-no-pushes = no active pushes
+
+## Summary
+
+summary-pushes = pushes
+summary-active = active
+summary-expired = expired
+summary-expiring-soon = expiring soon
+summary-most-viewed = most viewed
+summary-none = none
+summary-remaining = { $days } days, { $views } views left
+summary-views = { $views } views
+
+## Prompts
+
+prompt-yes-no = [y/N]
+# answers that confirm, separated by commas. "y" and "yes" always work
+prompt-yes = y, yes
+expire-question = Expire { $kind } push { $url_token } on { $url }?
+expire-aborted = aborted, nothing was expired
+expire-done = expired { $kind } push { $url_token }
+push-aborted = aborted, nothing was pushed
+history-clear-question = Delete the local push history?
+history-clear-aborted = aborted, the history was kept
+
+## Errors
+
+error-confirm-non-interactive = cannot ask for confirmation in non-interactive mode ({ $question }), use --yes
+error-unauthorized = invalid credentials: the instance rejected the token (status 401)
+error-rate-limited = rate limited by the instance (status 429)
+error-status = instance responded with status { $status }: { $body }
+error-status-page = instance responded with status { $status }
+error-too-large = the instance refused the request as too large (status 413)
+error-file-pushes-disabled = file pushes are not enabled on this instance
+error-timeout = the request to { $host } timed out
+error-dns = could not find the host { $host }
+error-connection-refused = { $host } refused the connection
+error-certificate = the TLS certificate of { $host } is not trusted
+error-connection = could not connect to { $host }: { $cause }
+error-redirect = the instance redirected the { $method } request to { $target }, which was not followed to keep credentials and payload from being sent there
+
+## Hints how to fix errors, shown below them
+
+hint-unauthorized = did you forget --email and --token? Otherwise check them and --auth-scheme
+hint-rate-limited = try again later, or lower the request rate with --max-rate
+hint-not-found = the push may have been deleted, or the instance URL does not point to Password Pusher
+hint-too-large = push large secrets with `push file`, or ask the administrator to raise the limit
+hint-server-error = the instance has a problem, try again later or tell its administrator
+hint-file-pushes-disabled = ask the administrator to enable file pushes, and log in with --email and --token
+hint-timeout = the instance or the network may be down, try again later
+hint-dns = check the instance URL given with --url or in the profile for typos
+hint-connection-refused = check the port of the instance URL, and that the instance is running
+hint-certificate = the instance may use a certificate of an internal CA, add the CA to the trust store of the system
+hint-connection = check the network connection, and the proxy in HTTPS_PROXY if one is needed
+hint-redirect = use { $target } with --url or as URL of the profile
+
+## Pushes
+
+push-passphrase = passphrase: { $passphrase }
+push-queued = queued push { $id }, send it later with `queue flush`
+push-unreachable = could not reach the instance: { $error }
+push-qr-link-plain = --qr-link is ignored with --plain
+push-secret-argument = the secret was given as command line argument, where it is visible in the process list and shell history. Use --stdin or --prompt instead, or --no-warn to silence this warning
+push-option-argument = { $option } was given as command line argument, where it is visible in the process list and shell history. Leave it out to be asked for it, use --stdin, or --no-warn to silence this warning
+push-note-left-out = { $url } has logins disabled, so the note is left out, as only accounts can see notes
+push-whole-days = instances only expire pushes after whole days, so the push expires after { $days ->
+    [one] { $days } day
+   *[other] { $days } days
+} instead of { $requested }
+push-whole-days-on = instances only expire pushes after whole days, so the push expires after { $days ->
+    [one] { $days } day
+   *[other] { $days } days
+} on { $date } instead of { $requested }. Use --exact to expire it on time
+push-weak-passphrase = the passphrase is weak, as { $weakness }. Recipients are safer with a stronger one
+push-duplicate = the same secret was already pushed at { $date }
+push-duplicate-refused = the same secret was already pushed at { $date }. Use --dedupe warn to push it anyway
+push-not-deduplicated = could not record the push for --dedupe: { $error }
+push-not-in-history = could not record push { $url_token } in history: { $error }
+push-not-shortened = could not shorten the link: { $error }
+push-not-printed = could not print the push: { $error }
+push-symlink-skipped = not archiving symbolic link { $path }
+push-expire-unverified = could not verify that { $kind } push { $url_token } expired, the answer of the instance did not confirm it
+
+## Why a passphrase is weak, see `passphrase`
+
+passphrase-common = it is a commonly used password
+passphrase-common-variant = it is a commonly used password with a few more characters
+passphrase-sequences = it mostly consists of repeated characters or sequences
+passphrase-short = it is too short, use more characters or several words
+
+## Holding and watching pushes
+
+hold-until = Ctrl-C
+hold-until-timeout = Ctrl-C or after { $duration }
+hold-ephemeral = push { $url_token } is expired on { $until }
+hold-waiting = holding until { $until }
+hold-no-interrupt = no longer waiting for Ctrl-C
+hold-expired = expired push { $url_token }
+first-view-waiting = push { $url_token } is expired after the first confirmed view, keep this running
+first-view-missed = push { $url_token } expired without a confirmed view
+first-view-expired = expired push { $url_token } after it was viewed at { $date }
+poll-failed = could not poll { $what }, retrying: { $error }
+poll-audit-log = the audit log
+poll-push-list = the push list
+poll-expired-pushes = expired pushes
+info-unchanged = unchanged
+interrupted = interrupted
+interrupted-again = interrupted again, exiting
+interrupted-finishing = interrupted, finishing up (press Ctrl-C again to exit at once)
+interrupt-unavailable = could not install signal handler: { $error }
+
+## Commands
+
+get-wrong-passphrase = incorrect passphrase ({ $attempt } of { $attempts } attempts)
+get-stored-in-vault = stored payload of push { $url_token } in Vault at { $secret }
+update-done = updated { $kind } push { $url_token }
+copy-done = copied { $url_token } to { $copy } on { $url }
+rotate-question = Replace text push { $url_token } on { $url }? The old link stops working.
+rotate-aborted = aborted, nothing was rotated
+rotate-done = expired { $url_token }, new push: { $url }
+import-skipped = skipping { $url_token }: { $reason }
+import-not-text = only text pushes can be imported
+import-expired = push is expired
+import-no-payload = no payload in export, use export --include-payloads
+import-done = imported { $imported } of { $total } pushes
+presets-none = no presets configured
+bulk-none-in-file = no URL tokens in { $path }
+bulk-none-tagged = no URL tokens in the history with tag { $tags }
+bulk-question = Expire { $count } { $kind } pushes on { $url }?
+job-discard-question = Discard job { $id } with { $open } open items?
+job-discard-aborted = aborted, the job was kept
+history-disabled = the history is disabled, enable it with `enabled = true` in the [history] section of the configuration file
+
+## Queue
+
+queue-unreachable = { $id }: instance still unreachable: { $error }
+queue-kept = { $id }: instance responded with status { $status }
+queue-rejected = { $id }: rejected with status { $status }, removing it from the queue: { $body }
+
+## Hooks
+
+hook-on-view-command-failed = on-view command failed: { $error }
+hook-on-view-webhook-failed = on-view webhook failed: { $error }
+hook-post-push-failed = post-push hook failed: { $error }
+hook-post-expire-failed = post-expire hook failed: { $error }
+hook-notify-failed = notify webhook failed: { $error }
+hook-event-failed = event webhook failed: { $error }
+
+## Credentials
+
+auth-stored = stored the token of profile `{ $profile }`, encrypted with { $protection ->
+    [passphrase] a passphrase
+   *[local] the key of this machine
+}
+auth-nothing-to-encrypt = no tokens in plain text in { $path }
+auth-encrypted = encrypted the tokens of { $profiles }
+auth-exported = { $count ->
+    [one] exported { $count } profile, import it with `auth import` and the same passphrase
+   *[other] exported { $count } profiles, import them with `auth import` and the same passphrase
+}
+auth-imported = imported { $profiles }
+auth-readable = { $path } contains tokens in plain text and can be read by other users, encrypt them with `auth encrypt`
+smtp-delete-question = Delete the SMTP password from the keyring?
+smtp-delete-aborted = aborted, the password was kept
+
+## Configuration and instance
+
+config-missing = configuration file { $path } does not exist
+cache-reset = starting over with an empty cache: { $error }
+cache-not-saved = could not save the cache: { $error }
+policy-lowered = lowered { $name } from { $value } to { $max } as required by the policy in { $path }
+clock-behind = the clock of this machine is { $skew } behind the instance, times are computed by the clock of the instance
+clock-ahead = the clock of this machine is { $skew } ahead of the instance, times are computed by the clock of the instance
+retry-budget-used = rate limited by the instance, retry budget of { $seconds }s used up
+redirect-moved = { $from } moved permanently to { $to }, { $hint }
@@ -0,0 +1,161 @@
+//! Details of the account the credentials belong to.
+//!
+//! The API has no endpoint that describes the account itself, so `account`
+//! shows what the CLI knows about the credentials, and checks them with an
+//! authenticated request for the list of active text pushes. This only reads
+//! metadata, no push is viewed.
+//!
+//! Rejected credentials fail with `EXIT_UNAUTHORIZED`, so they can be told
+//! apart from an instance that cannot be reached, which fails with 1 like any
+//! other error.
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::args::{AuthScheme, PushKind, PushState};
+use crate::errors::{PPCError, EXIT_UNAUTHORIZED};
+use crate::http::Transport;
+use crate::instance::Instance;
+use crate::pwpush_api;
+use crate::render::CommandOutput;
+
+#[derive(Debug, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct Account {
+    pub instance: String,
+    pub email: Option<String>,
+    pub auth_scheme: Option<AuthScheme>,
+    pub credentials_valid: bool,
+    pub active_text_pushes: usize,
+}
+
+impl CommandOutput for Account {
+    fn json(&self) -> Value {
+        // the account only consists of strings, numbers and bools.
+        serde_json::to_value(self).unwrap()
+    }
+
+    fn human(&self, _width: Option<usize>) -> String {
+        format_account(self)
+    }
+
+    fn quiet(&self) -> String {
+        self.email
+            .as_ref()
+            .map(|email| format!("{}\n", email))
+            .unwrap_or_default()
+    }
+}
+
+/// Entry point for the `account` command.
+pub fn check(transport: &dyn Transport, instance: &Instance) -> Result<Account, PPCError> {
+    if instance.token.is_none() {
+        return Err(PPCError::with_exit_code(
+            String::from("no credentials given, use --email and --token or a profile with both"),
+            EXIT_UNAUTHORIZED,
+        ));
+    }
+
+    let pushes =
+        pwpush_api::list(transport, instance, PushKind::Text, PushState::Active).map_err(|e| {
+            if e.code() == EXIT_UNAUTHORIZED {
+                e
+            } else {
+                PPCError::from(format!(
+                    "could not check the credentials with {}: {}",
+                    instance.base_url(),
+                    e
+                ))
+            }
+        })?;
+
+    Ok(Account {
+        instance: instance.base_url(),
+        email: instance.email.clone(),
+        auth_scheme: instance.auth_scheme,
+        credentials_valid: true,
+        active_text_pushes: pushes.len(),
+    })
+}
+
+fn format_account(account: &Account) -> String {
+    let scheme = match account.auth_scheme {
+        Some(AuthScheme::Bearer) => "bearer token",
+        Some(AuthScheme::HeaderPair) | None => "email and token",
+    };
+    format!(
+        "instance: {}\nemail: {}\nauthentication: {}, valid\nactive text pushes: {}\n",
+        account.instance,
+        account.email.as_deref().unwrap_or("-"),
+        scheme,
+        account.active_text_pushes
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+
+    use super::{check, format_account};
+    use crate::args::{AuthScheme, InstanceProtocol, PPCArgs};
+    use crate::config::PPCConfig;
+    use crate::errors::EXIT_UNAUTHORIZED;
+    use crate::http::mock::MockTransport;
+    use crate::instance::Instance;
+
+    fn instance() -> Instance {
+        Instance {
+            protocol: InstanceProtocol::Https,
+            url: String::from("pwpush.example.com"),
+            email: Some(String::from("user@example.com")),
+            token: Some("t0k3n".into()),
+            auth_scheme: Some(AuthScheme::HeaderPair),
+        }
+    }
+
+    #[test]
+    fn valid_credentials() {
+        let transport =
+            MockTransport::new().respond(200, r#"[{"url_token":"abc"},{"url_token":"def"}]"#);
+
+        let account = check(&transport, &instance()).unwrap();
+
+        assert!(account.credentials_valid);
+        assert_eq!(account.active_text_pushes, 2);
+        assert_eq!(
+            format_account(&account),
+            "instance: https://pwpush.example.com\nemail: user@example.com\n\
+             authentication: email and token, valid\nactive text pushes: 2\n"
+        );
+    }
+
+    #[test]
+    fn invalid_credentials() {
+        let transport = MockTransport::new().respond(401, r#"{"error":"Not authorized"}"#);
+
+        let err = check(&transport, &instance()).unwrap_err();
+
+        assert_eq!(err.code(), EXIT_UNAUTHORIZED);
+        assert!(err.to_string().contains("invalid credentials"));
+    }
+
+    #[test]
+    fn unreachable_instance() {
+        let transport = MockTransport::new().fail("failed to lookup address");
+
+        let err = check(&transport, &instance()).unwrap_err();
+
+        assert_eq!(err.code(), 1);
+        assert!(err.to_string().contains("could not check the credentials"));
+    }
+
+    #[test]
+    fn missing_credentials() {
+        let args = PPCArgs::parse_from(["pwpush-cli", "account"]);
+        let anonymous = Instance::resolve(&args, &PPCConfig::default()).unwrap();
+
+        let err = check(&MockTransport::new(), &anonymous).unwrap_err();
+
+        assert_eq!(err.code(), EXIT_UNAUTHORIZED);
+    }
+}
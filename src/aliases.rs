@@ -0,0 +1,267 @@
+//! Short forms of commands, and commands defined by the user.
+//!
+//! The kinds of pushes have short forms, e.g. `push t` for `push text` or
+//! `info f` for `info file`, which clap knows as aliases of the subcommands.
+//! A few frequent commands have a short form of their own, which expands to
+//! the command with its kind:
+//!   - `p` -> `push text`
+//!   - `x` -> `expire text`
+//!
+//! More aliases can be defined in the `[aliases]` section of the configuration
+//! file, as command line or as list of arguments. They may contain options,
+//! which makes them handy for pushes with the same limits every time:
+//!
+//! ```toml
+//! [aliases]
+//! once = "push text --expire-after-views 1 --retrieval-step"
+//! handoff = ["push", "text", "--note", "customer handoff"]
+//! ```
+//!
+//! `pwpush-cli once s3cret` then runs `pwpush-cli push text
+//! --expire-after-views 1 --retrieval-step s3cret`. Global options stay where
+//! they are, and arguments after the alias are appended to its expansion. An
+//! alias may start with another alias, but not with itself. Aliases of the
+//! configuration file take precedence over the built-in short forms, but never
+//! over commands, so scripts behave the same on every machine.
+//!
+//! Aliases are expanded before the command line is parsed, and only if the
+//! command is not known, so the configuration file is only read early for
+//! aliases. They do not apply to `--stdin-json`, which names full commands.
+
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use clap::{Command, CommandFactory};
+
+use crate::args::PPCArgs;
+use crate::config::{self, Alias};
+use crate::errors::PPCError;
+
+/// The built-in short forms of commands, see the module docs.
+const BUILTIN: [(&str, &str); 2] = [("p", "push text"), ("x", "expire text")];
+
+/// The command line with the alias in place of the command replaced by its
+/// expansion. Command lines without an alias are returned unchanged.
+pub fn expand(cmdline: Vec<OsString>) -> Result<Vec<OsString>, PPCError> {
+    let mut root = PPCArgs::command();
+    root.build();
+    let Some((position, config_file)) = find_command(&root, &cmdline) else {
+        return Ok(cmdline);
+    };
+    if is_command(&root, &cmdline[position]) {
+        return Ok(cmdline);
+    }
+
+    let config = config::load(config_file.as_deref())?;
+    expand_with(&root, cmdline, position, &config.aliases)
+}
+
+fn expand_with(
+    root: &Command,
+    mut cmdline: Vec<OsString>,
+    position: usize,
+    aliases: &BTreeMap<String, Alias>,
+) -> Result<Vec<OsString>, PPCError> {
+    let mut seen = Vec::new();
+    loop {
+        let name = cmdline[position].to_string_lossy().into_owned();
+        let expansion = match aliases.get(&name) {
+            Some(alias) => alias.args(),
+            None => match BUILTIN.iter().find(|(builtin, _)| *builtin == name) {
+                Some((_, command)) => command.split_whitespace().map(String::from).collect(),
+                // unknown commands are left to clap, which suggests similar
+                // ones.
+                None => return Ok(cmdline),
+            },
+        };
+
+        if seen.contains(&name) {
+            return Err(PPCError::from(format!(
+                "alias `{}` expands to itself: {} -> {}",
+                name,
+                seen.join(" -> "),
+                name
+            )));
+        }
+        if expansion.is_empty() {
+            return Err(PPCError::from(format!("alias `{}` is empty", name)));
+        }
+        seen.push(name);
+
+        cmdline.splice(
+            position..=position,
+            expansion.into_iter().map(OsString::from),
+        );
+        if is_command(root, &cmdline[position]) {
+            return Ok(cmdline);
+        }
+    }
+}
+
+fn is_command(root: &Command, name: &OsString) -> bool {
+    name.to_str()
+        .is_some_and(|name| root.find_subcommand(name).is_some())
+}
+
+/// Position of the command on the command line, after the global options,
+/// and the value of `--config`, which tells where the aliases are defined.
+/// Options that take a value are looked up in the definition of the command,
+/// so their values are not mistaken for the command.
+fn find_command(root: &Command, cmdline: &[OsString]) -> Option<(usize, Option<PathBuf>)> {
+    let takes_value = |matches: &dyn Fn(&clap::Arg) -> bool| {
+        root.get_arguments()
+            .find(|arg| matches(arg))
+            .is_some_and(|arg| arg.get_action().takes_values())
+    };
+
+    let mut config_file = None;
+    let mut position = 1;
+    while position < cmdline.len() {
+        let arg = cmdline[position].to_string_lossy();
+        if arg == "--" {
+            return None;
+        }
+
+        if let Some(long) = arg.strip_prefix("--") {
+            match long.split_once('=') {
+                Some(("config", value)) => config_file = Some(PathBuf::from(value)),
+                Some(_) => {}
+                None if takes_value(&|a| a.get_long() == Some(long)) => {
+                    if long == "config" {
+                        config_file = cmdline.get(position + 1).map(PathBuf::from);
+                    }
+                    position += 1;
+                }
+                None => {}
+            }
+        } else if let Some(shorts) = arg.strip_prefix('-').filter(|s| !s.is_empty()) {
+            // in `-ju URL` only the last option takes a value, in `-uURL` the
+            // value is attached.
+            let mut chars = shorts.chars();
+            while let Some(short) = chars.next() {
+                if takes_value(&|a| a.get_short() == Some(short)) {
+                    if chars.as_str().is_empty() {
+                        position += 1;
+                    }
+                    break;
+                }
+            }
+        } else {
+            return Some((position, config_file));
+        }
+        position += 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+    use std::ffi::OsString;
+    use std::path::PathBuf;
+
+    use clap::{Command, CommandFactory};
+
+    use super::{expand_with, find_command};
+    use crate::args::PPCArgs;
+    use crate::config::Alias;
+
+    fn root() -> Command {
+        let mut root = PPCArgs::command();
+        root.build();
+        root
+    }
+
+    fn cmdline(args: &str) -> Vec<OsString> {
+        args.split_whitespace().map(OsString::from).collect()
+    }
+
+    fn expand(args: &str, aliases: &BTreeMap<String, Alias>) -> Result<String, String> {
+        let root = root();
+        let cmdline = cmdline(args);
+        let (position, _) = find_command(&root, &cmdline).unwrap();
+        expand_with(&root, cmdline, position, aliases)
+            .map(|cmdline| {
+                cmdline
+                    .iter()
+                    .map(|arg| arg.to_string_lossy().into_owned())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn command_comes_after_the_global_options() {
+        let root = root();
+        let find = |args: &str| find_command(&root, &cmdline(args));
+
+        assert_eq!(find("pwpush-cli p s3cret"), Some((1, None)));
+        assert_eq!(
+            find("pwpush-cli -u pwpush.example.com --json -l info --config c.toml once"),
+            Some((8, Some(PathBuf::from("c.toml"))))
+        );
+        assert_eq!(
+            find("pwpush-cli -ju pwpush.example.com -H X-A:1 --config=c.toml once"),
+            Some((6, Some(PathBuf::from("c.toml"))))
+        );
+        assert_eq!(
+            find("pwpush-cli -upwpush.example.com x abc"),
+            Some((2, None))
+        );
+        assert_eq!(find("pwpush-cli --json"), None);
+        assert_eq!(find("pwpush-cli --stdin-json -- p"), None);
+    }
+
+    #[test]
+    fn aliases_expand_to_commands() {
+        let aliases = BTreeMap::from([
+            (
+                String::from("once"),
+                Alias::Line(String::from("push text --expire-after-views 1")),
+            ),
+            (
+                String::from("handoff"),
+                Alias::Args(vec![
+                    String::from("once"),
+                    String::from("--note"),
+                    String::from("customer handoff"),
+                ]),
+            ),
+            (String::from("x"), Alias::Line(String::from("expire url"))),
+        ]);
+
+        assert_eq!(
+            expand("pwpush-cli --json p s3cret", &aliases).unwrap(),
+            "pwpush-cli --json push text s3cret"
+        );
+        assert_eq!(
+            expand("pwpush-cli handoff s3cret", &aliases).unwrap(),
+            "pwpush-cli push text --expire-after-views 1 --note customer handoff s3cret"
+        );
+        assert_eq!(
+            expand("pwpush-cli x abc", &aliases).unwrap(),
+            "pwpush-cli expire url abc"
+        );
+        assert_eq!(
+            expand("pwpush-cli pusj text", &aliases).unwrap(),
+            "pwpush-cli pusj text"
+        );
+    }
+
+    #[test]
+    fn broken_aliases_fail() {
+        let aliases = BTreeMap::from([
+            (String::from("a"), Alias::Line(String::from("b --json"))),
+            (String::from("b"), Alias::Line(String::from("a"))),
+            (String::from("empty"), Alias::Args(Vec::new())),
+        ]);
+
+        assert_eq!(
+            expand("pwpush-cli a", &aliases).unwrap_err(),
+            "alias `a` expands to itself: a -> b -> a"
+        );
+        assert!(expand("pwpush-cli empty", &aliases).is_err());
+    }
+}
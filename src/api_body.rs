@@ -0,0 +1,886 @@
+//! Bodies of API requests and responses.
+//!
+//! The API does not take JSON for new pushes, but form fields in the shape of
+//! a serialized Ruby hash, e.g. `password[payload]=...&password[note]=...`.
+//! `push_form` builds the body of a text push from the options, `field` a
+//! single field of it. Responses are JSON and parsed into the types of
+//! `models` by `parse_json`.
+//!
+//! The payload goes through these functions unchanged apart from the
+//! encoding, whatever it contains, so they are tested with generated input
+//! as well, see the property tests below.
+
+use std::fmt;
+
+use secrecy::ExposeSecret;
+use serde::de::DeserializeOwned;
+use tracing::debug;
+
+use crate::archive::Archive;
+use crate::args::{PPCFile, PPCText, PPCUpdate, PushKind};
+use crate::errors::PPCError;
+use crate::multipart::Multipart;
+use crate::redact;
+
+/// Build the form body of a text push, in the format the API expects.
+/// For the request bodies, the API does not use a standard format like JSON,
+/// but rather some kind of serialized version of a ruby hash, encoded similar
+/// to URL parameters.
+///
+/// Apart from the payload itself, all of the parameters are optional and only
+/// included in the request if the user specified them. This is important, as
+/// each instance has some server-side defaults, and the user might want to
+/// accept these defaults. If we sent all of the parameters for each request,
+/// we would not enable users to do so.
+///
+/// In addition, the API requires us to send strings, notably payload,
+/// passphrase and note, in an urlencoded format. The strings in the parameter
+/// are plain strings that are encoded on the fly here. We cannot enocde the
+/// final resulting string with urlencoding, because this would encode the
+/// square brackets as well, but we need them literally. Remember, this string
+/// will go into the body, so we encoding things that are explicitly required
+/// by the API to be encoded.
+///
+/// The order of the parameters is not strictly specified and could probably be
+/// changed, but tests (see below) can assume that the order of the parameters
+/// will be the same as the fields in the `PPCText` struct.
+pub fn push_form(ppc_text: &PPCText) -> String {
+    // at the moment there are 8 possible parameters, so might as well use
+    // the idiomatic `with_capacity`.
+    // As most of the parameters are optional, we build them in a vec and join
+    // them in the end.
+    let mut args = Vec::with_capacity(8);
+
+    add_option(
+        &mut args,
+        "payload",
+        &ppc_text
+            .password_payload
+            .as_ref()
+            .map(|p| p.expose_secret()),
+    );
+    add_option(
+        &mut args,
+        "passphrase",
+        &ppc_text.passphrase.as_ref().map(|p| p.expose_secret()),
+    );
+    // QR code pushes are text pushes that are shown differently, see --qr.
+    add_option(&mut args, "kind", &ppc_text.qr.then_some("qr"));
+    add_option(&mut args, "note", &ppc_text.note);
+    add_option(&mut args, "expire_after_days", &ppc_text.expire_after_days);
+    add_option(
+        &mut args,
+        "expire_after_views",
+        &ppc_text.expire_after_views,
+    );
+    add_option(
+        &mut args,
+        "deletable_by_viewer",
+        &ppc_text.deletable_by_viewer,
+    );
+    add_option(&mut args, "retrieval_step", &ppc_text.retrieval_step);
+
+    // `join` makes the args into a single string, and we do not have to
+    // bother an extra separator at the start or the end of the result.
+    let final_body = args.join("&");
+    debug!("final body string: {}", redact::sanitize_body(&final_body));
+
+    final_body
+}
+
+/// Helper for the helper - push the field to the args if data is not None, see
+/// `field`. This function is only called for strings, for bools and for some
+/// numbers (usize), so everything is made into a string and the urlencoding is
+/// applied, and nothing should break.
+fn add_option<T: fmt::Display>(args: &mut Vec<String>, key: &str, data: &Option<T>) {
+    if let Some(d) = data.as_ref() {
+        let formatted = field(key, &d.to_string());
+        debug!(
+            "appending `{}` request parameters",
+            redact::sanitize_body(&formatted)
+        );
+        args.push(formatted);
+    }
+}
+
+/// A single field of a form body, `password[<key>]=<value>`. The value is
+/// URL encoded as required by the API, the key is used literally, so it must
+/// only consist of letters and underscores.
+pub fn field(key: &str, value: &str) -> String {
+    format!("password[{}]={}", key, urlencoding::encode(value))
+}
+
+/// Build the multipart body of a file push, see `multipart`. Like with
+/// `push_form`, options are only sent if given. The files are not read here,
+/// only when the request is sent.
+pub fn file_push_form(
+    ppc_file: &PPCFile,
+    archive: Option<&Archive>,
+) -> Result<Multipart, PPCError> {
+    let fields = [
+        (
+            "passphrase",
+            ppc_file
+                .passphrase
+                .as_ref()
+                .map(|p| String::from(p.expose_secret())),
+        ),
+        ("note", ppc_file.note.clone()),
+        (
+            "expire_after_days",
+            ppc_file.expire_after_days.map(|v| v.to_string()),
+        ),
+        (
+            "expire_after_views",
+            ppc_file.expire_after_views.map(|v| v.to_string()),
+        ),
+        (
+            "deletable_by_viewer",
+            ppc_file.deletable_by_viewer.map(|v| v.to_string()),
+        ),
+        (
+            "retrieval_step",
+            ppc_file.retrieval_step.map(|v| v.to_string()),
+        ),
+    ];
+    let form = fields
+        .into_iter()
+        .fold(Multipart::new(), |form, (key, value)| match value {
+            Some(value) => form.text(&format!("file_push[{}]", key), &value),
+            None => form,
+        });
+
+    // the archive of --dir replaces the files.
+    if let Some(archive) = archive {
+        return form.file("file_push[files][]", archive.path(), Some(archive.name()));
+    }
+    ppc_file.files.iter().try_fold(form, |form, path| {
+        form.file("file_push[files][]", path, None)
+    })
+}
+
+/// Build the form body that changes an existing push, see `update`. Like with
+/// `push_form`, only the attributes that were given are sent, the others keep
+/// their values.
+pub fn update_form(ppc_update: &PPCUpdate) -> String {
+    let prefix = match ppc_update.kind {
+        PushKind::Text => "password",
+        PushKind::File => "file_push",
+        PushKind::Url => "url",
+    };
+    let fields = [
+        ("note", ppc_update.note.clone()),
+        (
+            "deletable_by_viewer",
+            ppc_update.deletable_by_viewer.map(|v| v.to_string()),
+        ),
+        (
+            "retrieval_step",
+            ppc_update.retrieval_step.map(|v| v.to_string()),
+        ),
+    ];
+
+    fields
+        .into_iter()
+        .filter_map(|(key, value)| {
+            value.map(|value| format!("{}[{}]={}", prefix, key, urlencoding::encode(&value)))
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Parse the body of a successful response. `what` is only used to give the
+/// user a hint in the error message.
+pub fn parse_json<T: DeserializeOwned>(body: &str, what: &str) -> Result<T, PPCError> {
+    serde_json::from_str(body)
+        .map_err(|e| PPCError::from(format!("unexpected {} format: {}", what, e)))
+}
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+    use secrecy::SecretString;
+
+    use super::{field, parse_json, push_form, update_form};
+    use crate::args::{PPCText, PPCUpdate, PushKind};
+    use crate::dedupe;
+    use crate::models::Push;
+
+    // not super useful in practice, but what does the push_form
+    // function care?
+    #[test]
+    fn push_form_empty_pw() {
+        let text = PPCText {
+            password_payload: Some(SecretString::from("")),
+            payload_file: None,
+            stdin: false,
+            prompt: false,
+            from_op: None,
+            from_bw: None,
+            from_vault: None,
+            from_env: Vec::new(),
+            base64: false,
+            normalize_newlines: false,
+            encrypt_for: None,
+            no_warn: false,
+            passphrase: None,
+            qr: false,
+            qr_link: false,
+            note: None,
+            expire_after_days: None,
+            expire_after_views: None,
+            deletable_by_viewer: None,
+            retrieval_step: None,
+            no_defaults: false,
+            preset: None,
+            queue_on_failure: false,
+            paranoid: false,
+            enforce_strong_passphrase: false,
+            hold: false,
+            hold_for: None,
+            ephemeral: false,
+            expire_at: None,
+            expire_after: None,
+            exact: false,
+            to_profiles: Vec::new(),
+            recipients: Vec::new(),
+            notify_webhook: None,
+            email_to: None,
+            shorten: false,
+            validate: false,
+            tags: Vec::new(),
+            expire_after_first_view_confirmed: false,
+            dedupe: None,
+            dedupe_window: dedupe::DEFAULT_WINDOW,
+            idempotency_key: None,
+        };
+
+        let actual = push_form(&text);
+        let expected = String::from("password[payload]=");
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn push_form_simple_pw() {
+        let text = PPCText {
+            password_payload: Some(SecretString::from("password")),
+            payload_file: None,
+            stdin: false,
+            prompt: false,
+            from_op: None,
+            from_bw: None,
+            from_vault: None,
+            from_env: Vec::new(),
+            base64: false,
+            normalize_newlines: false,
+            encrypt_for: None,
+            no_warn: false,
+            passphrase: None,
+            qr: false,
+            qr_link: false,
+            note: None,
+            expire_after_days: None,
+            expire_after_views: None,
+            deletable_by_viewer: None,
+            retrieval_step: None,
+            no_defaults: false,
+            preset: None,
+            queue_on_failure: false,
+            paranoid: false,
+            enforce_strong_passphrase: false,
+            hold: false,
+            hold_for: None,
+            ephemeral: false,
+            expire_at: None,
+            expire_after: None,
+            exact: false,
+            to_profiles: Vec::new(),
+            recipients: Vec::new(),
+            notify_webhook: None,
+            email_to: None,
+            shorten: false,
+            validate: false,
+            tags: Vec::new(),
+            expire_after_first_view_confirmed: false,
+            dedupe: None,
+            dedupe_window: dedupe::DEFAULT_WINDOW,
+            idempotency_key: None,
+        };
+
+        let actual = push_form(&text);
+        let expected = String::from("password[payload]=password");
+
+        assert_eq!(actual, expected);
+    }
+
+    // see <https://docs.rs/urlencoding/latest/urlencoding/fn.encode.html>
+    // for details on the URL encoding. There it explicitly says that some
+    // characters (including `_`) are not encoded, so they are also not expected
+    // to be here in the test case.
+    // Obviously, we do not want to write tests for the urlencoding crate, we
+    // just want to make sure that our helper function applies it at the
+    // correct places.
+    #[test]
+    fn push_form_pw_urlencoded() {
+        let text = PPCText {
+            password_payload: Some(SecretString::from("random_§$%&%$_characters with spaces")),
+            payload_file: None,
+            stdin: false,
+            prompt: false,
+            from_op: None,
+            from_bw: None,
+            from_vault: None,
+            from_env: Vec::new(),
+            base64: false,
+            normalize_newlines: false,
+            encrypt_for: None,
+            no_warn: false,
+            passphrase: None,
+            qr: false,
+            qr_link: false,
+            note: None,
+            expire_after_days: None,
+            expire_after_views: None,
+            deletable_by_viewer: None,
+            retrieval_step: None,
+            no_defaults: false,
+            preset: None,
+            queue_on_failure: false,
+            paranoid: false,
+            enforce_strong_passphrase: false,
+            hold: false,
+            hold_for: None,
+            ephemeral: false,
+            expire_at: None,
+            expire_after: None,
+            exact: false,
+            to_profiles: Vec::new(),
+            recipients: Vec::new(),
+            notify_webhook: None,
+            email_to: None,
+            shorten: false,
+            validate: false,
+            tags: Vec::new(),
+            expire_after_first_view_confirmed: false,
+            dedupe: None,
+            dedupe_window: dedupe::DEFAULT_WINDOW,
+            idempotency_key: None,
+        };
+
+        let actual = push_form(&text);
+        let expected = String::from(
+            "password[payload]=random_%C2%A7%24%25%26%25%24_characters%20with%20spaces",
+        );
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn push_form_pw_with_passphrase() {
+        let text = PPCText {
+            password_payload: Some(SecretString::from("password")),
+            payload_file: None,
+            stdin: false,
+            prompt: false,
+            from_op: None,
+            from_bw: None,
+            from_vault: None,
+            from_env: Vec::new(),
+            base64: false,
+            normalize_newlines: false,
+            encrypt_for: None,
+            no_warn: false,
+            passphrase: Some(SecretString::from("passphrase")),
+            qr: false,
+            qr_link: false,
+            note: None,
+            expire_after_days: None,
+            expire_after_views: None,
+            deletable_by_viewer: None,
+            retrieval_step: None,
+            no_defaults: false,
+            preset: None,
+            queue_on_failure: false,
+            paranoid: false,
+            enforce_strong_passphrase: false,
+            hold: false,
+            hold_for: None,
+            ephemeral: false,
+            expire_at: None,
+            expire_after: None,
+            exact: false,
+            to_profiles: Vec::new(),
+            recipients: Vec::new(),
+            notify_webhook: None,
+            email_to: None,
+            shorten: false,
+            validate: false,
+            tags: Vec::new(),
+            expire_after_first_view_confirmed: false,
+            dedupe: None,
+            dedupe_window: dedupe::DEFAULT_WINDOW,
+            idempotency_key: None,
+        };
+
+        let actual = push_form(&text);
+        let expected = String::from("password[payload]=password&password[passphrase]=passphrase");
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn push_form_with_multiple_options() {
+        let text = PPCText {
+            password_payload: Some(SecretString::from("password")),
+            payload_file: None,
+            stdin: false,
+            prompt: false,
+            from_op: None,
+            from_bw: None,
+            from_vault: None,
+            from_env: Vec::new(),
+            base64: false,
+            normalize_newlines: false,
+            encrypt_for: None,
+            no_warn: false,
+            passphrase: Some(SecretString::from("passphrase")),
+            qr: false,
+            qr_link: false,
+            note: Some(String::from("this is a note")),
+            expire_after_days: Some(5),
+            expire_after_views: Some(2),
+            deletable_by_viewer: None,
+            retrieval_step: None,
+            no_defaults: false,
+            preset: None,
+            queue_on_failure: false,
+            paranoid: false,
+            enforce_strong_passphrase: false,
+            hold: false,
+            hold_for: None,
+            ephemeral: false,
+            expire_at: None,
+            expire_after: None,
+            exact: false,
+            to_profiles: Vec::new(),
+            recipients: Vec::new(),
+            notify_webhook: None,
+            email_to: None,
+            shorten: false,
+            validate: false,
+            tags: Vec::new(),
+            expire_after_first_view_confirmed: false,
+            dedupe: None,
+            dedupe_window: dedupe::DEFAULT_WINDOW,
+            idempotency_key: None,
+        };
+
+        let actual = push_form(&text);
+        let expected = String::from("password[payload]=password&password[passphrase]=passphrase&\
+                                             password[note]=this%20is%20a%20note&password[expire_after_days]=5&\
+                                             password[expire_after_views]=2");
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn push_form_with_all_options() {
+        let text = PPCText {
+            password_payload: Some(SecretString::from("password")),
+            payload_file: None,
+            stdin: false,
+            prompt: false,
+            from_op: None,
+            from_bw: None,
+            from_vault: None,
+            from_env: Vec::new(),
+            base64: false,
+            normalize_newlines: false,
+            encrypt_for: None,
+            no_warn: false,
+            passphrase: Some(SecretString::from("passphrase")),
+            qr: true,
+            qr_link: false,
+            note: Some(String::from("this is a note")),
+            expire_after_days: Some(5),
+            expire_after_views: Some(2),
+            deletable_by_viewer: Some(true),
+            retrieval_step: Some(false),
+            no_defaults: false,
+            preset: None,
+            queue_on_failure: false,
+            paranoid: false,
+            enforce_strong_passphrase: false,
+            hold: false,
+            hold_for: None,
+            ephemeral: false,
+            expire_at: None,
+            expire_after: None,
+            exact: false,
+            to_profiles: Vec::new(),
+            recipients: Vec::new(),
+            notify_webhook: None,
+            email_to: None,
+            shorten: false,
+            validate: false,
+            tags: Vec::new(),
+            expire_after_first_view_confirmed: false,
+            dedupe: None,
+            dedupe_window: dedupe::DEFAULT_WINDOW,
+            idempotency_key: None,
+        };
+
+        let actual = push_form(&text);
+        let expected = String::from("password[payload]=password&password[passphrase]=passphrase&\
+                                             password[kind]=qr&\
+                                             password[note]=this%20is%20a%20note&password[expire_after_days]=5&\
+                                             password[expire_after_views]=2&password[deletable_by_viewer]=true&\
+                                             password[retrieval_step]=false");
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn update_form_only_sends_given_attributes() {
+        let update = PPCUpdate {
+            url_token: String::from("abc"),
+            kind: PushKind::File,
+            note: Some(String::from("new note")),
+            deletable_by_viewer: None,
+            retrieval_step: Some(true),
+        };
+
+        assert_eq!(
+            update_form(&update),
+            "file_push[note]=new%20note&file_push[retrieval_step]=true"
+        );
+    }
+
+    fn text(payload: &str, passphrase: Option<&str>, note: Option<&str>) -> PPCText {
+        PPCText {
+            password_payload: Some(SecretString::from(payload)),
+            payload_file: None,
+            stdin: false,
+            prompt: false,
+            from_op: None,
+            from_bw: None,
+            from_vault: None,
+            from_env: Vec::new(),
+            base64: false,
+            normalize_newlines: false,
+            encrypt_for: None,
+            no_warn: false,
+            passphrase: passphrase.map(SecretString::from),
+            qr: false,
+            qr_link: false,
+            note: note.map(String::from),
+            expire_after_days: None,
+            expire_after_views: None,
+            deletable_by_viewer: None,
+            retrieval_step: None,
+            no_defaults: false,
+            preset: None,
+            queue_on_failure: false,
+            paranoid: false,
+            enforce_strong_passphrase: false,
+            hold: false,
+            hold_for: None,
+            ephemeral: false,
+            expire_at: None,
+            expire_after: None,
+            exact: false,
+            to_profiles: Vec::new(),
+            recipients: Vec::new(),
+            notify_webhook: None,
+            email_to: None,
+            shorten: false,
+            validate: false,
+            tags: Vec::new(),
+            expire_after_first_view_confirmed: false,
+            dedupe: None,
+            dedupe_window: dedupe::DEFAULT_WINDOW,
+            idempotency_key: None,
+        }
+    }
+
+    /// Split a form body into keys and decoded values, the way the server
+    /// does.
+    fn decode(body: &str) -> Vec<(String, String)> {
+        body.split('&')
+            .map(|pair| {
+                let (key, value) = pair.split_once('=').expect("fields have a value");
+                let key = key
+                    .strip_prefix("password[")
+                    .and_then(|key| key.strip_suffix(']'))
+                    .expect("fields are below password");
+                let value = urlencoding::decode(value).expect("values are UTF-8");
+                (String::from(key), value.into_owned())
+            })
+            .collect()
+    }
+
+    /// Characters that have no special meaning in a form body.
+    fn is_unreserved(c: char) -> bool {
+        c.is_ascii_alphanumeric() || "-._~%".contains(c)
+    }
+
+    fn push() -> impl Strategy<Value = Push> {
+        (
+            any::<String>(),
+            any::<Option<String>>(),
+            any::<Option<u64>>(),
+            any::<Option<i64>>(),
+            any::<Option<bool>>(),
+            any::<Option<String>>(),
+        )
+            .prop_map(
+                |(url_token, note, expire_after_views, views_remaining, expired, payload)| Push {
+                    url_token,
+                    note,
+                    expire_after_views,
+                    views_remaining,
+                    expired,
+                    payload,
+                    ..Push::default()
+                },
+            )
+    }
+
+    proptest! {
+        #[test]
+        fn form_values_survive_encoding(
+            payload in any::<String>(),
+            passphrase in any::<Option<String>>(),
+            note in any::<Option<String>>(),
+        ) {
+            let body = push_form(&text(&payload, passphrase.as_deref(), note.as_deref()));
+
+            let mut expected = vec![(String::from("payload"), payload)];
+            expected.extend(passphrase.map(|p| (String::from("passphrase"), p)));
+            expected.extend(note.map(|n| (String::from("note"), n)));
+            prop_assert_eq!(decode(&body), expected);
+        }
+
+        #[test]
+        fn values_cannot_break_out_of_their_field(value in any::<String>()) {
+            let field = field("note", &value);
+
+            let encoded = field.strip_prefix("password[note]=").unwrap();
+            prop_assert!(encoded.chars().all(is_unreserved), "{}", encoded);
+        }
+
+        #[test]
+        fn responses_never_panic(body in any::<String>()) {
+            let _ = parse_json::<Push>(&body, "push");
+            let _ = parse_json::<Vec<Push>>(&body, "push list");
+        }
+
+        #[test]
+        fn pushes_are_parsed_as_sent(push in push()) {
+            let body = serde_json::to_string(&push).unwrap();
+            prop_assert_eq!(parse_json::<Push>(&body, "push").unwrap(), push);
+        }
+    }
+
+    proptest! {
+        // long payloads are slow to generate, so there are fewer cases.
+        #![proptest_config(ProptestConfig::with_cases(8))]
+
+        #[test]
+        fn long_payloads_are_encoded_completely(
+            payload in prop::collection::vec(any::<char>(), 100_000..200_000)
+                .prop_map(|chars| chars.into_iter().collect::<String>())
+        ) {
+            let body = push_form(&text(&payload, None, None));
+            prop_assert_eq!(decode(&body), vec![(String::from("payload"), payload)]);
+        }
+    }
+
+    /// Timestamps in and beyond the range of the API, and things that only
+    /// look like timestamps.
+    const DATES: [&str; 7] = [
+        "2023-12-01T10:15:23.121Z",
+        "9999-12-31T23:59:59+23:59",
+        "0000-01-01T00:00:00-23:59",
+        "2023-12-01",
+        "+262143-12-31",
+        "2023-02-30T25:61:61Z",
+        "",
+    ];
+
+    /// Fields of all responses, so generated objects hit the typed models.
+    const FIELDS: [&str; 24] = [
+        "url_token",
+        "created_at",
+        "expire_after_days",
+        "expire_after_views",
+        "days_remaining",
+        "views_remaining",
+        "expired",
+        "expired_on",
+        "deleted",
+        "deletable_by_viewer",
+        "retrieval_step",
+        "note",
+        "payload",
+        "files",
+        "filename",
+        "url",
+        "views",
+        "ip",
+        "user_agent",
+        "referrer",
+        "successful",
+        "kind",
+        "application_version",
+        "error",
+    ];
+
+    /// Values of every JSON type, with the extremes of each.
+    fn json_leaf() -> impl Strategy<Value = serde_json::Value> {
+        use serde_json::Value;
+        prop_oneof![
+            Just(Value::Null),
+            any::<bool>().prop_map(Value::from),
+            any::<i64>().prop_map(Value::from),
+            prop::sample::select(vec![i64::MIN, -1, 0, 1, i64::MAX]).prop_map(Value::from),
+            prop::sample::select(vec![u64::MAX, 1 << 63]).prop_map(Value::from),
+            any::<f64>().prop_map(|f| serde_json::json!(f)),
+            any::<String>().prop_map(Value::from),
+            prop::sample::select(DATES.to_vec()).prop_map(Value::from),
+        ]
+    }
+
+    /// Responses of any shape: objects with the fields of the models, lists,
+    /// and nested values of the wrong type.
+    fn response() -> impl Strategy<Value = serde_json::Value> {
+        use serde_json::Value;
+        json_leaf().prop_recursive(4, 64, 8, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..6).prop_map(Value::Array),
+                prop::collection::btree_map(
+                    prop::sample::select(FIELDS.to_vec()).prop_map(String::from),
+                    inner,
+                    0..12,
+                )
+                .prop_map(|fields| Value::Object(fields.into_iter().collect())),
+            ]
+        })
+    }
+
+    /// Pushes as the models accept them, with extreme numbers and dates.
+    fn extreme_push() -> impl Strategy<Value = Push> {
+        let number = || {
+            prop_oneof![
+                any::<i64>(),
+                prop::sample::select(vec![i64::MIN, 0, i64::MAX])
+            ]
+        };
+        let date = || prop::option::of(prop::sample::select(DATES.to_vec()).prop_map(String::from));
+        (
+            (push(), any::<Option<u64>>(), prop::option::of(number())),
+            (
+                prop::option::of(number()),
+                date(),
+                date(),
+                any::<Option<bool>>(),
+            ),
+        )
+            .prop_map(
+                |(
+                    (push, expire_after_days, days_remaining),
+                    (views_remaining, created_at, expired_on, deleted),
+                )| Push {
+                    expire_after_days,
+                    days_remaining,
+                    views_remaining: views_remaining.or(push.views_remaining),
+                    created_at,
+                    expired_on,
+                    deleted,
+                    ..push
+                },
+            )
+    }
+
+    /// Parse the body as every response, and show what was parsed in every
+    /// way the commands do, which must not panic.
+    fn parse_and_show(body: &str) {
+        use chrono::Utc;
+
+        use crate::dates::{DateFormat, DateFormatter};
+        use crate::models::{AuditLog, Preview, ServerVersion};
+        use crate::{audit, info, localized, redact};
+
+        let mut pushes = parse_json::<Vec<Push>>(body, "push list").unwrap_or_default();
+        pushes.extend(parse_json::<Push>(body, "push"));
+        let log = parse_json::<AuditLog>(body, "audit log").unwrap_or_default();
+        let _ = parse_json::<Preview>(body, "preview");
+        let _ = parse_json::<ServerVersion>(body, "version");
+        let _ = (redact::sanitize_json(body), localized::classify_body(body));
+
+        for format in [DateFormat::Iso, DateFormat::Relative, DateFormat::Local] {
+            let dates = DateFormatter::with(format, Utc::now());
+            for push in &pushes {
+                let _ = info::format_info(push, &dates);
+                let _ = info::expired_error(push, &dates);
+                let _ = push.limits();
+            }
+            for view in &log.views {
+                let _ = audit::format_view(view, &dates);
+                let _ = (view.created(), view.ip_addr());
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn malformed_responses_never_panic(response in response()) {
+            parse_and_show(&response.to_string());
+        }
+
+        #[test]
+        fn extreme_values_never_panic(push in extreme_push()) {
+            parse_and_show(&serde_json::to_string(&push).unwrap());
+            parse_and_show(&format!(r#"{{"views":[{{"created_at":{:?},"kind":{}}}]}}"#,
+                push.created_at.unwrap_or_default(), push.views_remaining.unwrap_or_default()));
+        }
+    }
+
+    /// Responses that broke parsers of other clients, or could: deeply nested
+    /// JSON, huge strings, wrong types, and pages of proxies instead of JSON.
+    /// Add a file for every response that ever made the CLI panic.
+    #[test]
+    fn corpus_of_malformed_responses() {
+        let corpus =
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/responses");
+        let mut count = 0;
+        for entry in std::fs::read_dir(corpus).unwrap() {
+            let body = std::fs::read(entry.unwrap().path()).unwrap();
+            parse_and_show(&String::from_utf8_lossy(&body));
+            count += 1;
+        }
+        assert!(count > 0);
+
+        // too large to keep as files.
+        parse_and_show(&format!("{}{}", "[".repeat(100_000), "]".repeat(100_000)));
+        parse_and_show(&format!(
+            r#"{{"url_token":"{}","note":"{}"}}"#,
+            "a".repeat(1 << 20),
+            "\\u0000".repeat(1 << 16)
+        ));
+        let nested_files = (0..12).fold(String::from(r#"{"x":"/y"}"#), |files, _| {
+            serde_json::to_string(&files).unwrap()
+        });
+        parse_and_show(&format!(
+            r#"{{"url_token":"abc","files":{}}}"#,
+            nested_files
+        ));
+    }
+
+    #[test]
+    fn parse_errors_name_the_response() {
+        let err = parse_json::<Push>("<html>", "push").unwrap_err();
+        assert!(err.to_string().starts_with("unexpected push format: "));
+    }
+}
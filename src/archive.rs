@@ -0,0 +1,378 @@
+//! Archives of directories for `push file --dir`.
+//!
+//! Sharing a directory, e.g. a set of certificates or a build output, would
+//! otherwise take three steps: archive it, encrypt it, and push the archive.
+//! With `--dir`, the directory is packed into a `.tar.gz` (or, with
+//! `--format zip`, a `.zip`) in the temporary directory, which is pushed as a
+//! single file and removed afterwards. The entries are stored below a folder
+//! named like the directory, so unpacking them does not litter the current
+//! directory of the recipient.
+//!
+//! Which files are archived can be narrowed with globs, matched against the
+//! path of each file within the directory, with `/` as separator:
+//!   - with `--include`, only files matching one of the globs are archived
+//!   - files and directories matching one of the `--exclude` globs are left
+//!     out, directories with everything below them
+//!
+//! `*` also matches `/`, so `--exclude '*.log'` leaves out log files at any
+//! depth. Symbolic links are not followed, and left out with a warning, as
+//! they could point anywhere on the machine.
+//!
+//! With `--encrypt-for`, the archive is encrypted with age or gpg before it is
+//! pushed, see `crypto`, and gets the extension of the tool, e.g.
+//! `certs.tar.gz.age`. The instance then only ever sees ciphertext.
+
+use std::env;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use chrono::{DateTime, Datelike, Local, Timelike};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use tracing::{info, warn};
+use walkdir::WalkDir;
+use zip::write::SimpleFileOptions;
+use zip::CompressionMethod;
+
+use crate::args::{ArchiveFormat, PPCFile};
+use crate::crypto::{self, Tool};
+use crate::errors::PPCError;
+use crate::fail_on;
+use crate::i18n;
+use crate::signals::TempFile;
+
+impl ArchiveFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ArchiveFormat::TarGz => "tar.gz",
+            ArchiveFormat::Zip => "zip",
+        }
+    }
+}
+
+/// An archive in the temporary directory, which is removed when it is
+/// dropped.
+pub struct Archive {
+    file: TempFile,
+    name: String,
+}
+
+impl Archive {
+    pub fn path(&self) -> &Path {
+        self.file.path()
+    }
+
+    /// File name of the archive as the recipient sees it.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Archive the directory of `--dir`, and encrypt the archive if requested.
+pub fn create(ppc_file: &PPCFile) -> Result<Archive, PPCError> {
+    let dir = ppc_file
+        .dir
+        .as_deref()
+        .ok_or_else(|| PPCError::from("no directory to archive"))?;
+    if !dir.is_dir() {
+        return Err(PPCError::from(format!(
+            "{} is not a directory",
+            dir.display()
+        )));
+    }
+
+    let root = root_name(dir);
+    let filter = Filter::new(&ppc_file.include, &ppc_file.exclude)?;
+    let entries = entries(dir, &filter)?;
+    if entries.is_empty() {
+        return Err(PPCError::from(format!(
+            "no files to archive in {}",
+            dir.display()
+        )));
+    }
+
+    let format = ppc_file.format;
+    let file = private_temp_file(format.extension())?;
+    match format {
+        ArchiveFormat::TarGz => write_tar_gz(file.path(), &root, &entries),
+        ArchiveFormat::Zip => write_zip(file.path(), &root, &entries),
+    }
+    .map_err(|e| PPCError::from(format!("could not archive {}: {}", dir.display(), e)))?;
+    info!("archived {} files of {}", entries.len(), dir.display());
+
+    let name = format!("{}.{}", root, format.extension());
+    let Some(recipient) = &ppc_file.encrypt_for else {
+        return Ok(Archive { file, name });
+    };
+
+    let extension = Tool::for_recipient(recipient).extension();
+    let encrypted = private_temp_file(&format!("{}.{}", format.extension(), extension))?;
+    crypto::encrypt_file(file.path(), encrypted.path(), recipient)?;
+    Ok(Archive {
+        file: encrypted,
+        name: format!("{}.{}", name, extension),
+    })
+}
+
+/// Name of the folder in the archive, which is the name of the directory.
+/// `.` and the like are resolved first.
+fn root_name(dir: &Path) -> String {
+    fs::canonicalize(dir)
+        .ok()
+        .and_then(|dir| {
+            dir.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| String::from("archive"))
+}
+
+/// The globs of `--include` and `--exclude`.
+struct Filter {
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+}
+
+impl Filter {
+    fn new(include: &[String], exclude: &[String]) -> Result<Self, PPCError> {
+        let include = match include {
+            [] => None,
+            globs => Some(glob_set(globs)?),
+        };
+        Ok(Self {
+            include,
+            exclude: glob_set(exclude)?,
+        })
+    }
+
+    fn excludes(&self, path: &str) -> bool {
+        self.exclude.is_match(path)
+    }
+
+    fn includes_file(&self, path: &str) -> bool {
+        let included = self.include.as_ref().is_none_or(|set| set.is_match(path));
+        included && !self.excludes(path)
+    }
+}
+
+fn glob_set(globs: &[String]) -> Result<GlobSet, PPCError> {
+    let mut builder = GlobSetBuilder::new();
+    for glob in globs {
+        let glob =
+            Glob::new(glob).map_err(|e| PPCError::from(format!("invalid glob {}: {}", glob, e)))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|e| PPCError::from(format!("invalid globs: {}", e)))
+}
+
+/// A file to archive.
+struct Entry {
+    path: PathBuf,
+    /// Path within the directory, with `/` as separator
+    name: String,
+}
+
+/// The files of the directory that pass the filter, sorted by path, so the
+/// archive is the same on every run.
+fn entries(dir: &Path, filter: &Filter) -> Result<Vec<Entry>, PPCError> {
+    let relative = |path: &Path| {
+        let components = path
+            .strip_prefix(dir)
+            .unwrap_or(path)
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        components.join("/")
+    };
+
+    let walker = WalkDir::new(dir)
+        .follow_links(false)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || !filter.excludes(&relative(entry.path())));
+
+    let mut entries = Vec::new();
+    for entry in walker {
+        let entry = entry
+            .map_err(|e| PPCError::from(format!("could not read {}: {}", dir.display(), e)))?;
+        let name = relative(entry.path());
+        let file_type = entry.file_type();
+        if file_type.is_symlink() {
+            warn!(
+                "{}",
+                i18n::format(
+                    "push-symlink-skipped",
+                    &[("path", entry.path().display().to_string().into())]
+                )
+            );
+            fail_on::soft_failure();
+        } else if file_type.is_file() && filter.includes_file(&name) {
+            entries.push(Entry {
+                path: entry.into_path(),
+                name,
+            });
+        }
+    }
+    Ok(entries)
+}
+
+/// A new, empty file in the temporary directory that only the user can read,
+/// as archives contain the secrets to push.
+fn private_temp_file(extension: &str) -> Result<TempFile, PPCError> {
+    let random = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(16)
+        .map(char::from)
+        .collect::<String>();
+    let path = env::temp_dir().join(format!("pwpush-cli-{}.{}", random, extension));
+
+    let mut options = File::options();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(&path)
+        .map_err(|e| PPCError::from(format!("could not create {}: {}", path.display(), e)))?;
+    Ok(TempFile::new(path))
+}
+
+fn write_tar_gz(path: &Path, root: &str, entries: &[Entry]) -> io::Result<()> {
+    let encoder = GzEncoder::new(File::create(path)?, Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+    builder.follow_symlinks(false);
+    for entry in entries {
+        builder.append_path_with_name(&entry.path, format!("{}/{}", root, entry.name))?;
+    }
+    builder.into_inner()?.finish()?.sync_all()
+}
+
+fn write_zip(path: &Path, root: &str, entries: &[Entry]) -> io::Result<()> {
+    let mut writer = zip::ZipWriter::new(File::create(path)?);
+    for entry in entries {
+        let metadata = fs::metadata(&entry.path)?;
+        let mut options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .large_file(metadata.len() >= u64::from(u32::MAX));
+        if let Some(modified) = metadata.modified().ok().and_then(zip_time) {
+            options = options.last_modified_time(modified);
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            options = options.unix_permissions(metadata.permissions().mode());
+        }
+
+        writer.start_file(format!("{}/{}", root, entry.name), options)?;
+        io::copy(&mut File::open(&entry.path)?, &mut writer)?;
+    }
+    writer.finish()?.sync_all()
+}
+
+/// zip archives store the local time, without time zone, and only years from
+/// 1980 on.
+fn zip_time(time: SystemTime) -> Option<zip::DateTime> {
+    let time = DateTime::<Local>::from(time);
+    zip::DateTime::from_date_and_time(
+        u16::try_from(time.year()).ok()?,
+        time.month() as u8,
+        time.day() as u8,
+        time.hour() as u8,
+        time.minute() as u8,
+        time.second() as u8,
+    )
+    .ok()
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::{self, File};
+    use std::path::Path;
+
+    use flate2::read::GzDecoder;
+
+    use super::{entries, write_tar_gz, write_zip, Filter};
+
+    fn tree() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for (path, content) in [
+            ("certs/server.pem", "cert"),
+            ("certs/server.key", "key"),
+            ("logs/debug.log", "log"),
+            (".git/config", "git"),
+            ("README", "readme"),
+        ] {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    fn names(dir: &Path, include: &[&str], exclude: &[&str]) -> Vec<String> {
+        let strings = |globs: &[&str]| globs.iter().map(|g| String::from(*g)).collect::<Vec<_>>();
+        let filter = Filter::new(&strings(include), &strings(exclude)).unwrap();
+        entries(dir, &filter)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect()
+    }
+
+    #[test]
+    fn globs_select_the_files() {
+        let dir = tree();
+
+        assert_eq!(
+            names(dir.path(), &[], &[".git", "*.log"]),
+            vec!["README", "certs/server.key", "certs/server.pem"]
+        );
+        assert_eq!(
+            names(dir.path(), &["certs/*"], &["*.key"]),
+            vec!["certs/server.pem"]
+        );
+        assert!(names(dir.path(), &["*.txt"], &[]).is_empty());
+        assert!(Filter::new(&[String::from("[")], &[]).is_err());
+    }
+
+    #[test]
+    fn archives_contain_the_files_below_the_root() {
+        let dir = tree();
+        let filter = Filter::new(&[], &[String::from(".git")]).unwrap();
+        let entries = entries(dir.path(), &filter).unwrap();
+        let out = tempfile::tempdir().unwrap();
+
+        let tar_gz = out.path().join("secrets.tar.gz");
+        write_tar_gz(&tar_gz, "secrets", &entries).unwrap();
+        let mut archive = tar::Archive::new(GzDecoder::new(File::open(&tar_gz).unwrap()));
+        let tar_names = archive
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap().path().unwrap().display().to_string())
+            .collect::<Vec<_>>();
+
+        let zip = out.path().join("secrets.zip");
+        write_zip(&zip, "secrets", &entries).unwrap();
+        let archive = zip::ZipArchive::new(File::open(&zip).unwrap()).unwrap();
+        let zip_names = archive.file_names().map(String::from).collect::<Vec<_>>();
+
+        let expected = vec![
+            "secrets/README",
+            "secrets/certs/server.key",
+            "secrets/certs/server.pem",
+            "secrets/logs/debug.log",
+        ];
+        assert_eq!(tar_names, expected);
+        let mut zip_names = zip_names;
+        zip_names.sort();
+        assert_eq!(zip_names, expected);
+    }
+}
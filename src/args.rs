@@ -1,41 +1,273 @@
-use std::fmt::Display;
+use std::fmt::{self, Display};
+use std::path::PathBuf;
+use std::time::Duration;
 
-use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
+use chrono::{DateTime, Utc};
+use clap::{ArgAction, ArgGroup, Args, Parser, Subcommand, ValueEnum};
+use ipnet::IpNet;
+use schemars::JsonSchema;
+use secrecy::SecretString;
+use serde::{Deserialize, Serialize};
+
+use crate::dates::{self, DateFormat};
+use crate::extra_headers::{self, ExtraHeader};
+use crate::i18n::{self, Language};
+use crate::localized;
+#[cfg(feature = "serve")]
+use crate::serve;
+use crate::vault::{self, SecretRef};
+use crate::{audit, bulk, download, duration, expire_at, history, redact};
 
 /// Interact with Password Pusher from the command line
-#[derive(Debug, Parser)]
+#[derive(Parser)]
+#[command(version)]
 pub struct PPCArgs {
-    /// Password Pusher instance URL. Default is pwpush.com
-    #[arg(id = "url", long, short, default_value = "pwpush.com")]
-    pub instance_url: String,
+    /// Password Pusher instance URL, optionally with a port and a path prefix
+    /// for instances behind a reverse proxy, e.g. intranet.example.com/pwpush
+    /// or `[fd00::10]:5100`. Default is pwpush.com
+    #[arg(id = "url", long, short)]
+    pub instance_url: Option<String>,
 
-    /// Password Pusher instance protocol
-    #[arg(id = "protocol", long, short, default_value = "https")]
+    /// Password Pusher instance protocol. Default is https
+    #[arg(id = "protocol", long, short)]
     #[clap(value_enum)]
-    pub instance_protocol: InstanceProtocol,
+    pub instance_protocol: Option<InstanceProtocol>,
+
+    /// Use instance and credentials of this profile from the configuration
+    /// file. Options given on the command line take precedence
+    #[arg(id = "profile", long)]
+    pub profile: Option<String>,
 
     /// Email for authenticated requests (goes into X-User-Email header)
     #[arg(id = "email", long, short, requires = "token")]
     pub email: Option<String>,
 
-    /// Token for authenticated requests (goes into X-User-Token header)
-    #[arg(id = "token", long, short, requires = "email")]
-    pub token: Option<String>,
+    /// Token for authenticated requests (goes into X-User-Token header, or
+    /// into the Authorization header with the bearer scheme)
+    #[arg(id = "token", long, short, value_parser = secret)]
+    pub token: Option<SecretString>,
+
+    /// How the token is sent. Default is bearer for a token without email,
+    /// otherwise detected from the server version
+    #[arg(id = "auth-scheme", long, value_name = "SCHEME")]
+    #[clap(value_enum)]
+    pub auth_scheme: Option<AuthScheme>,
 
     /// Command output in json. If omitted, human-readable output is produced
-    #[arg(id = "json", long, short, action = ArgAction::SetTrue)]
+    #[arg(
+        id = "json",
+        long,
+        short,
+        action = ArgAction::SetTrue,
+        default_value_if("output", "json", "true")
+    )]
     pub json_output: bool,
 
+    /// Format of the command output. Quiet and csv are supported by push,
+    /// expire, account, summary, version and paths
+    #[arg(
+        id = "output",
+        long,
+        value_name = "FORMAT",
+        default_value = "human",
+        default_value_if("plain", "true", "plain"),
+        conflicts_with = "json"
+    )]
+    #[clap(value_enum)]
+    pub output_format: OutputFormat,
+
+    /// The same as --output plain: no colors, no alignment and no QR codes
+    /// in the terminal, e.g. for screen readers
+    #[arg(
+        id = "plain",
+        long,
+        action = ArgAction::SetTrue,
+        conflicts_with_all = ["json", "output"]
+    )]
+    pub plain: bool,
+
     /// Verbosity of log level. Logs always go to stderr.
     #[arg(id = "log", long, short, default_value = "warn")]
     #[clap(value_enum)]
     pub log_verbosity: LogVerbosity,
 
+    /// Format of log lines. JSON logs contain one object per line
+    #[arg(id = "log-format", long, default_value = "text")]
+    #[clap(value_enum)]
+    pub log_format: LogFormat,
+
+    /// Append logs to this file instead of stderr. Errors are still shown on
+    /// stderr. The file is rotated once it grows beyond 1 MiB
+    #[arg(id = "log-file", long, value_name = "FILE")]
+    pub log_file: Option<PathBuf>,
+
+    /// Configuration file. Defaults to config.toml in the configuration
+    /// directory of the platform, see `paths`
+    #[arg(id = "config", long, value_name = "FILE")]
+    pub config_file: Option<PathBuf>,
+
+    /// Read further arguments from this file, one or more per line, quoted
+    /// like in a shell. Files with secrets must only be readable by the user
+    #[arg(id = "options-from", long, value_name = "FILE")]
+    pub options_from: Option<PathBuf>,
+
+    /// Save sanitized request/response pairs to this directory, e.g. to attach
+    /// them to a bug report. Secrets are redacted before writing.
+    #[arg(id = "record", long, value_name = "DIR", conflicts_with = "replay")]
+    pub record_dir: Option<PathBuf>,
+
+    /// Do not contact the instance, but answer requests from a directory that
+    /// was created with --record
+    #[arg(id = "replay", long, value_name = "DIR")]
+    pub replay_dir: Option<PathBuf>,
+
+    /// Print the response body of the instance exactly as received, without
+    /// interpreting it. Supported by get, audit and version
+    #[arg(id = "raw", long, action = ArgAction::SetTrue, conflicts_with = "json")]
+    pub raw: bool,
+
+    /// Send all requests to the HTTP server on this Unix domain socket, e.g.
+    /// the reverse proxy of a local instance. --url is still used for the
+    /// request paths
+    #[arg(
+        id = "unix-socket",
+        long,
+        value_name = "PATH",
+        conflicts_with = "replay"
+    )]
+    pub unix_socket: Option<PathBuf>,
+
+    /// Add this header to every request to the instance, given as
+    /// "Name: value", e.g. for authenticating proxies in front of self-hosted
+    /// instances. Can be given multiple times
+    #[arg(id = "header", long, short = 'H', value_name = "HEADER", value_parser = extra_headers::parse)]
+    pub headers: Vec<ExtraHeader>,
+
+    /// Send this User-Agent header instead of `pwpush-cli/<version>`, e.g. for
+    /// instances that filter requests by client
+    #[arg(id = "user-agent", long, value_name = "USER_AGENT")]
+    pub user_agent: Option<String>,
+
+    /// Send an X-Request-Id header with each request to the instance, made of
+    /// the trace ID of this run and a running number, and show the trace ID
+    /// with errors, to find the requests in the logs of the instance
+    #[arg(id = "request-id", long, action = ArgAction::SetTrue)]
+    pub request_id: bool,
+
+    /// Send at most this many requests per second, e.g. to keep large imports
+    /// from getting the account throttled
+    #[arg(id = "max-rate", long, value_name = "REQUESTS", value_parser = positive_rate)]
+    pub max_rate: Option<f64>,
+
+    /// How long to wait in total for an instance that rate limits requests,
+    /// before giving up
+    #[arg(id = "retry-budget", long, value_name = "DURATION", default_value = "60s", value_parser = duration::parse)]
+    pub retry_budget: Duration,
+
+    /// How dates are shown in human-readable output: iso, relative, local or a
+    /// strftime pattern like "%d.%m.%Y %H:%M". Default is relative on a
+    /// terminal and iso otherwise
+    #[arg(id = "date-format", long, value_name = "FORMAT", value_parser = dates::parse)]
+    pub date_format: Option<DateFormat>,
+
+    /// Language of human-readable output and error messages, e.g. de.
+    /// Default is the language of LANG, or English. JSON output is always
+    /// English
+    #[arg(id = "lang", long, value_name = "LANGUAGE", value_parser = i18n::parse)]
+    pub lang: Option<Language>,
+
+    /// Ask the instance for messages in these languages, sent as
+    /// Accept-Language header, e.g. de or "fr-CH, fr;q=0.9"
+    #[arg(id = "accept-language", long, value_name = "LANGUAGES", value_parser = localized::parse)]
+    pub accept_language: Option<String>,
+
+    /// Read the command as JSON document from stdin and write the result as
+    /// JSON to stdout, for use from other programs. The command line must not
+    /// contain a command then
+    #[arg(id = "stdin-json", long, action = ArgAction::SetTrue)]
+    pub stdin_json: bool,
+
+    /// Print long output of list and audit directly, instead of through
+    /// $PAGER
+    #[arg(id = "no-pager", long, action = ArgAction::SetTrue)]
+    pub no_pager: bool,
+
+    /// Print the tables of list, audit and summary at full width, instead of
+    /// cutting and wrapping columns to fit the terminal
+    #[arg(id = "no-truncate", long, action = ArgAction::SetTrue)]
+    pub no_truncate: bool,
+
+    /// Keep ETags between runs, so polling scripts get "304 Not Modified" from
+    /// the instance. info and list then print "unchanged" if nothing changed
+    /// since the last call with --if-changed
+    #[arg(id = "if-changed", long, action = ArgAction::SetTrue)]
+    pub if_changed: bool,
+
+    /// What fails the command: error (default) for errors and failed items of
+    /// bulk operations, warning also for soft failures like a webhook that
+    /// could not be notified, none only for errors that stop the command
+    #[arg(id = "fail-on", long, value_name = "LEVEL", default_value = "error")]
+    #[clap(value_enum)]
+    pub fail_on: FailOn,
+
+    /// Write a JSON summary of a bulk operation to this file: the number of
+    /// items that succeeded, failed or were skipped, and the error of each
+    /// failed item. Supported by expire bulk, push text --recipients and
+    /// bulk resume
+    #[arg(id = "report", long, value_name = "FILE")]
+    pub report_file: Option<PathBuf>,
+
+    /// Answer yes to all confirmation prompts. Required for destructive
+    /// operations in non-interactive mode
+    #[arg(id = "yes", long, short, action = ArgAction::SetTrue)]
+    pub assume_yes: bool,
+
     /// Top-level action to perform
     #[clap(subcommand)]
     pub action: PPCAction,
 }
 
+/// The token is redacted, so the arguments can be logged safely.
+impl fmt::Debug for PPCArgs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PPCArgs")
+            .field("instance_url", &self.instance_url)
+            .field("instance_protocol", &self.instance_protocol)
+            .field("profile", &self.profile)
+            .field("email", &self.email)
+            .field("token", &redact::option(&self.token))
+            .field("auth_scheme", &self.auth_scheme)
+            .field("json_output", &self.json_output)
+            .field("output_format", &self.output_format)
+            .field("plain", &self.plain)
+            .field("log_verbosity", &self.log_verbosity)
+            .field("log_format", &self.log_format)
+            .field("log_file", &self.log_file)
+            .field("config_file", &self.config_file)
+            .field("options_from", &self.options_from)
+            .field("record_dir", &self.record_dir)
+            .field("replay_dir", &self.replay_dir)
+            .field("raw", &self.raw)
+            .field("unix_socket", &self.unix_socket)
+            .field("headers", &self.headers)
+            .field("request_id", &self.request_id)
+            .field("max_rate", &self.max_rate)
+            .field("retry_budget", &self.retry_budget)
+            .field("date_format", &self.date_format)
+            .field("lang", &self.lang)
+            .field("accept_language", &self.accept_language)
+            .field("stdin_json", &self.stdin_json)
+            .field("no_pager", &self.no_pager)
+            .field("no_truncate", &self.no_truncate)
+            .field("if_changed", &self.if_changed)
+            .field("fail_on", &self.fail_on)
+            .field("report_file", &self.report_file)
+            .field("assume_yes", &self.assume_yes)
+            .field("action", &self.action)
+            .finish()
+    }
+}
+
 /// For each PPC object type there is a common set of actions. These subcommands
 /// allow a very human-friendly interface for the CLI.
 #[derive(Debug, Subcommand)]
@@ -44,9 +276,133 @@ pub enum PPCAction {
     #[clap(subcommand)]
     Push(PPCObject),
 
+    /// Retrieve a secret including its payload. This counts as a view.
+    Get(PPCGet),
+
     /// Expire an existing secret.
     #[clap(subcommand)]
-    Expire(PPCObject),
+    Expire(PPCExpire),
+
+    /// Show the metadata of a push, without retrieving the payload. Requires
+    /// authentication, as only pushes of the account can be found.
+    #[clap(subcommand)]
+    Info(PPCTokenObject),
+
+    /// List the active or expired pushes of the account. Requires
+    /// authentication.
+    List(PPCList),
+
+    /// Browse the pushes of the account in an interactive table. Requires
+    /// authentication.
+    #[cfg(feature = "tui")]
+    Browse(PPCBrowse),
+
+    /// Show who accessed a push. Requires authentication.
+    Audit(PPCAudit),
+
+    /// Dump the metadata of all pushes of the account. Requires
+    /// authentication.
+    Export(PPCExport),
+
+    /// Show the account of the credentials and check that the instance
+    /// accepts them. Exits with code 4 if it does not.
+    Account,
+
+    /// Show counts, upcoming expirations and the most viewed pushes of the
+    /// account. Requires authentication.
+    Summary,
+
+    /// List active pushes that expire soon. Exits with code 2 if there are
+    /// any, for use in cron jobs. Requires authentication.
+    Remind(PPCRemind),
+
+    /// Re-create active text pushes from an export on the selected instance.
+    Import(PPCImport),
+
+    /// Copy a text push to the instance of another profile. Retrieving the
+    /// payload counts as a view of the original push.
+    Copy(PPCCopy),
+
+    /// Replace a text push by a new push with the same payload and settings,
+    /// and expire the original. Retrieving the payload counts as a view.
+    Rotate(PPCRotate),
+
+    /// Create a new text push with the settings and, unless it is expired, the
+    /// payload of an existing one. Retrieving the payload counts as a view.
+    Clone(PPCClone),
+
+    /// Compare the metadata of two pushes field by field, e.g. to check a
+    /// rotated push before expiring the old one. Requires authentication,
+    /// unless --payload is given.
+    Diff(PPCDiff),
+
+    /// Change the note or the viewer settings of a push of the account, on
+    /// instances that support editing pushes. Requires authentication.
+    Update(PPCUpdate),
+
+    /// Work with pushes that were queued with --queue-on-failure.
+    #[clap(subcommand)]
+    Queue(QueueCommand),
+
+    /// Resume or discard bulk jobs that were interrupted or partially failed.
+    #[clap(subcommand)]
+    Bulk(BulkCommand),
+
+    /// Show or clear the local history of pushes made on this machine. Needs
+    /// `enabled = true` in the [history] section of the configuration file.
+    #[clap(subcommand)]
+    History(HistoryCommand),
+
+    /// Store or delete the password of the SMTP server for --email-to in the
+    /// keyring of the system.
+    #[cfg(feature = "email")]
+    #[clap(subcommand)]
+    Smtp(SmtpCommand),
+
+    /// Show version and build information of the CLI and the server version
+    /// of the instance.
+    Version,
+
+    /// Manage named push presets in the configuration file.
+    #[clap(subcommand)]
+    Preset(PresetCommand),
+
+    /// Store tokens of profiles encrypted in the configuration file, and move
+    /// profiles with their tokens between machines.
+    #[clap(subcommand)]
+    Auth(AuthCommand),
+
+    /// Inspect the settings from the command line, the environment, profiles,
+    /// presets, the configuration file and the policy.
+    #[clap(subcommand)]
+    Config(ConfigCommand),
+
+    /// Show where configuration, policy, queue, history, cache and keys are
+    /// kept. The configuration and key directories can be moved with
+    /// PWPUSH_CONFIG_DIR and PWPUSH_KEY_DIR.
+    Paths,
+
+    /// Check the configuration, file permissions, the instances of all
+    /// profiles and their credentials, and the keyring, and print a report.
+    /// Fails if any check fails.
+    Doctor,
+
+    /// Print the JSON Schema of the output of --json, generated from the
+    /// models of the CLI, including the current schema_version.
+    Schema,
+
+    /// Create a text push step by step, with questions for all options.
+    Wizard,
+
+    /// Answer a single request in a line-based key=value protocol on stdin,
+    /// for embedding into other programs. See the README for the attributes.
+    Helper(PPCHelper),
+
+    /// Serve a local HTTP/JSON API that creates, lists and expires text
+    /// pushes with the credentials of the selected instance, for desktop
+    /// tools and editors.
+    #[cfg(feature = "serve")]
+    Serve(PPCServe),
 }
 
 /// Sepcify the object to operate on. These options resemble the three very
@@ -54,26 +410,581 @@ pub enum PPCAction {
 #[derive(Debug, Subcommand)]
 pub enum PPCObject {
     /// Work on text-based secrets (typically passwords)
-    Text(PPCText),
+    #[command(visible_alias = "t")]
+    Text(Box<PPCText>),
+
+    /// Work on files
+    #[command(visible_alias = "f")]
+    File(Box<PPCFile>),
+
+    /// Work on URLs
+    #[command(visible_alias = "u")]
+    Url(PPCUrl),
+
+    /// Share the credentials of a WiFi network, in the format phones join
+    /// from when scanning it as QR code
+    #[command(visible_alias = "w")]
+    Wifi(Box<PPCWifi>),
+
+    /// Share the seed of a TOTP second factor as `otpauth://` URI, which
+    /// authenticator apps import
+    Totp(Box<PPCTotp>),
+}
+
+/// Expire a single push, or many pushes at once.
+#[derive(Debug, Subcommand)]
+pub enum PPCExpire {
+    #[command(flatten)]
+    Push(PPCTokenObject),
+
+    /// Expire all pushes listed in a file, or all pushes with a tag in the
+    /// history, several at the same time
+    Bulk(PPCExpireBulk),
+}
+
+#[derive(Debug, Args)]
+pub struct PPCExpireBulk {
+    /// File with one URL token per line. Empty lines and lines starting with
+    /// `#` are ignored
+    #[arg(
+        id = "from-file",
+        long,
+        value_name = "FILE",
+        required_unless_present = "tag"
+    )]
+    pub from_file: Option<PathBuf>,
+
+    /// Expire the pushes of the instance in the history that have this tag.
+    /// If given multiple times, pushes need all of the tags
+    #[arg(
+        id = "tag",
+        long = "tag",
+        value_name = "TAG",
+        value_parser = history::parse_tag,
+        conflicts_with = "from-file"
+    )]
+    pub tags: Vec<String>,
+
+    /// Kind of the pushes in the file or history
+    #[arg(id = "kind", long, default_value = "text")]
+    pub kind: PushKind,
+
+    /// Number of pushes to expire at the same time
+    #[arg(id = "jobs", long, default_value_t = bulk::DEFAULT_JOBS)]
+    pub jobs: usize,
+}
+
+/// Specify the type of an existing push, which is then addressed by its URL
+/// token. Used by all actions that operate on pushes that were already
+/// created.
+#[derive(Debug, Subcommand)]
+pub enum PPCTokenObject {
+    /// Work on text-based secrets (typically passwords)
+    #[command(visible_alias = "t")]
+    Text(PPCToken),
 
     /// Work on files
-    File(PPCFile),
+    #[command(visible_alias = "f")]
+    File(PPCToken),
 
     /// Work on URLs
-    URL(PPCURL),
+    #[command(visible_alias = "u")]
+    Url(PPCToken),
+}
+
+impl PPCTokenObject {
+    pub fn kind(&self) -> PushKind {
+        match self {
+            PPCTokenObject::Text(_) => PushKind::Text,
+            PPCTokenObject::File(_) => PushKind::File,
+            PPCTokenObject::Url(_) => PushKind::Url,
+        }
+    }
+
+    pub fn token(&self) -> &PPCToken {
+        match self {
+            PPCTokenObject::Text(token)
+            | PPCTokenObject::File(token)
+            | PPCTokenObject::Url(token) => token,
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct PPCGet {
+    /// Passphrase of the push. If the push requires one and it is not given,
+    /// it is asked for interactively
+    #[arg(id = "passphrase", long, value_parser = secret)]
+    pub passphrase: Option<SecretString>,
+
+    /// Decode a payload that was pushed with --base64 and write the raw bytes
+    /// to stdout. JSON output keeps the encoded payload
+    #[arg(id = "decode-base64", long, action = ArgAction::SetTrue)]
+    pub decode_base64: bool,
+
+    /// Decrypt a payload that was pushed with --encrypt-for
+    #[arg(id = "decrypt", long, action = ArgAction::SetTrue)]
+    pub decrypt: bool,
+
+    /// age identity file for --decrypt. gpg uses the keyring instead
+    #[arg(id = "identity", long, value_name = "FILE", requires = "decrypt")]
+    pub identity: Option<PathBuf>,
+
+    /// Store the payload in HashiCorp Vault under `<path>#<key>` instead of
+    /// printing it, keeping the other keys of the secret. Uses VAULT_ADDR and
+    /// VAULT_TOKEN, which needs to be allowed to read the secret as well
+    #[arg(id = "to-vault", long, value_name = "REF", value_parser = vault::parse_ref)]
+    pub to_vault: Option<SecretRef>,
+
+    /// Directory to download the files of a file push to
+    #[arg(id = "output-dir", long, value_name = "DIR", default_value = ".")]
+    pub output_dir: PathBuf,
+
+    /// Only keep the downloaded files if each has one of these SHA-256
+    /// checksums, fail otherwise
+    #[arg(id = "verify", long, value_name = "SHA256", value_parser = download::parse_checksum)]
+    pub verify: Vec<String>,
+
+    #[clap(subcommand)]
+    pub object: PPCTokenObject,
+}
+
+impl fmt::Debug for PPCGet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PPCGet")
+            .field("passphrase", &redact::option(&self.passphrase))
+            .field("decode_base64", &self.decode_base64)
+            .field("decrypt", &self.decrypt)
+            .field("identity", &self.identity)
+            .field("to_vault", &self.to_vault)
+            .field("output_dir", &self.output_dir)
+            .field("verify", &self.verify)
+            .field("object", &self.object)
+            .finish()
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct PPCAudit {
+    /// Keep polling the audit log and print new events as they arrive
+    #[arg(id = "watch", long, action = ArgAction::SetTrue)]
+    pub watch: bool,
+
+    /// Seconds between two polls in watch mode
+    #[arg(id = "interval", long, default_value = "30", value_name = "SECONDS")]
+    pub interval: u64,
+
+    /// In watch mode, run this shell command for each new view. Details of the
+    /// view are passed in PWPUSH_* environment variables
+    #[arg(id = "on-view-exec", long, value_name = "COMMAND", requires = "watch")]
+    pub on_view_exec: Option<String>,
+
+    /// In watch mode, POST each new view as JSON to this URL
+    #[arg(id = "on-view-webhook", long, value_name = "URL", requires = "watch")]
+    pub on_view_webhook: Option<String>,
+
+    /// Only show events of the last time span, e.g. 30m, 24h or 7d
+    #[arg(id = "since", long, value_name = "DURATION", value_parser = duration::parse)]
+    pub since: Option<Duration>,
+
+    /// Only show events from this address or network, e.g. 10.0.0.0/8
+    #[arg(id = "ip", long, value_name = "NETWORK", value_parser = audit::parse_network)]
+    pub ip: Option<IpNet>,
+
+    /// Only show events of these kinds, e.g. failed-view,expire
+    #[arg(id = "kind", long, value_enum, value_delimiter = ',')]
+    pub kind: Vec<AuditKind>,
+
+    /// Order of the events by time, instead of the order of the instance
+    #[arg(id = "sort", long, value_enum, conflicts_with = "watch")]
+    pub sort: Option<SortOrder>,
+
+    /// Print one line per event in this format for log management systems
+    /// like Splunk or Elastic, instead of the usual output
+    #[arg(id = "format", long, value_enum)]
+    pub format: Option<AuditFormat>,
+
+    /// Report on the audit logs of all active pushes of the account instead
+    /// of a single push: views, failed views, last access and addresses with
+    /// repeated failed views
+    #[arg(id = "all", long, action = ArgAction::SetTrue, conflicts_with_all = ["watch", "sort", "format"])]
+    pub all: bool,
+
+    /// Number of audit logs to fetch at the same time with --all
+    #[arg(id = "jobs", long, default_value_t = bulk::DEFAULT_JOBS, requires = "all")]
+    pub jobs: usize,
+
+    /// The push to audit, unless --all is given
+    #[clap(subcommand)]
+    pub object: Option<PPCTokenObject>,
+}
+
+#[derive(Debug, Args)]
+pub struct PPCExport {
+    /// Format of the export
+    #[arg(id = "format", long, default_value = "json")]
+    #[clap(value_enum)]
+    pub format: InventoryFormat,
+
+    /// Also retrieve the payloads of active text pushes, so they can be
+    /// imported. Careful: every retrieval counts as a view
+    #[arg(id = "include-payloads", long, action = ArgAction::SetTrue)]
+    pub include_payloads: bool,
+
+    /// Write the export to this file instead of stdout
+    #[arg(id = "output", long, short, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct PPCRemind {
+    /// Time window, e.g. 30m, 24h, 3d or 2w
+    #[arg(id = "within", long, default_value = "24h", value_parser = duration::parse)]
+    pub within: Duration,
+}
+
+#[derive(Debug, Args)]
+pub struct PPCImport {
+    /// File created with the export command
+    #[arg(id = "file")]
+    pub file: PathBuf,
+
+    /// Format of the file
+    #[arg(id = "format", long, default_value = "json")]
+    #[clap(value_enum)]
+    pub format: InventoryFormat,
+}
+
+#[derive(Debug, Args)]
+pub struct PPCList {
+    /// Which list to show
+    #[arg(id = "state")]
+    pub state: PushState,
+
+    /// Kind of the pushes to list
+    #[arg(id = "kind", long, default_value = "text")]
+    pub kind: PushKind,
+
+    /// Keep polling the list and print pushes that were added, changed or
+    /// removed
+    #[arg(id = "watch", long, action = ArgAction::SetTrue)]
+    pub watch: bool,
+
+    /// Seconds between two polls in watch mode
+    #[arg(id = "interval", long, default_value = "30", value_name = "SECONDS")]
+    pub interval: u64,
+
+    /// Only show active pushes with at most --days-left days or --views-left
+    /// views remaining, or that run out of views within --days-left days at
+    /// the rate they were viewed so far
+    #[arg(id = "expiring-soon", long, action = ArgAction::SetTrue, conflicts_with = "watch")]
+    pub expiring_soon: bool,
+
+    /// Remaining days that count as soon for --expiring-soon
+    #[arg(
+        id = "days-left",
+        long,
+        default_value = "1",
+        value_name = "DAYS",
+        requires = "expiring-soon"
+    )]
+    pub days_left: i64,
+
+    /// Remaining views that count as soon for --expiring-soon
+    #[arg(
+        id = "views-left",
+        long,
+        default_value = "1",
+        value_name = "VIEWS",
+        requires = "expiring-soon"
+    )]
+    pub views_left: i64,
+}
+
+#[cfg(feature = "tui")]
+#[derive(Debug, Args)]
+pub struct PPCBrowse {
+    /// Kind of the pushes to browse
+    #[arg(id = "kind", long, default_value = "text")]
+    pub kind: PushKind,
+}
+
+#[derive(Debug, Args)]
+pub struct PPCCopy {
+    /// URL token of the text push to copy
+    #[arg(id = "url_token")]
+    pub url_token: String,
+
+    /// Profile from the configuration file to copy the push to
+    #[arg(id = "to-profile", long, value_name = "PROFILE")]
+    pub to_profile: String,
+}
+
+#[derive(Args)]
+pub struct PPCRotate {
+    /// URL token of the text push to replace
+    #[arg(id = "url_token")]
+    pub url_token: String,
+
+    /// Passphrase of the push. The new push is protected with the same
+    /// passphrase
+    #[arg(id = "passphrase", long, value_parser = secret)]
+    pub passphrase: Option<SecretString>,
+}
+
+impl fmt::Debug for PPCRotate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PPCRotate")
+            .field("url_token", &self.url_token)
+            .field("passphrase", &redact::option(&self.passphrase))
+            .finish()
+    }
+}
+
+#[derive(Args)]
+pub struct PPCClone {
+    /// URL token of the text push to clone
+    #[arg(id = "url_token")]
+    pub url_token: String,
+
+    /// Passphrase of the push. The new push is protected with the same
+    /// passphrase
+    #[arg(id = "passphrase", long, value_parser = secret)]
+    pub passphrase: Option<SecretString>,
+
+    /// Read the payload from stdin instead of taking it from the original,
+    /// e.g. if the original is expired
+    #[arg(id = "stdin", long, action = ArgAction::SetTrue, conflicts_with = "prompt")]
+    pub stdin: bool,
+
+    /// Type in the payload with hidden input instead of taking it from the
+    /// original
+    #[arg(id = "prompt", long, action = ArgAction::SetTrue)]
+    pub prompt: bool,
+
+    /// Note of the new push, instead of the note of the original
+    #[arg(id = "note", long)]
+    pub note: Option<String>,
+
+    /// Expire the new push after this many days, instead of the days of the
+    /// original
+    #[arg(id = "expire-after-days", long)]
+    pub expire_after_days: Option<usize>,
+
+    /// Expire the new push after this many views, instead of the views of the
+    /// original
+    #[arg(id = "expire-after-views", long)]
+    pub expire_after_views: Option<usize>,
+
+    /// Allow users to delete passwords once retrieved
+    #[arg(id = "deletable-by-viewer", long)]
+    pub deletable_by_viewer: Option<bool>,
+
+    /// Helps to avoid chat systems and URL scanners from eating up views
+    #[arg(id = "retrieval-step", long)]
+    pub retrieval_step: Option<bool>,
+}
+
+impl fmt::Debug for PPCClone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PPCClone")
+            .field("url_token", &self.url_token)
+            .field("passphrase", &redact::option(&self.passphrase))
+            .field("stdin", &self.stdin)
+            .field("prompt", &self.prompt)
+            .field("note", &self.note)
+            .field("expire_after_days", &self.expire_after_days)
+            .field("expire_after_views", &self.expire_after_views)
+            .field("deletable_by_viewer", &self.deletable_by_viewer)
+            .field("retrieval_step", &self.retrieval_step)
+            .finish()
+    }
+}
+
+#[derive(Args)]
+pub struct PPCDiff {
+    /// URL token of the first push
+    #[arg(id = "url_token_a")]
+    pub url_token_a: String,
+
+    /// URL token of the second push
+    #[arg(id = "url_token_b")]
+    pub url_token_b: String,
+
+    /// Kind of the pushes to compare
+    #[arg(id = "kind", long, default_value = "text")]
+    pub kind: PushKind,
+
+    /// Also tell whether the payloads are the same, without showing them.
+    /// Retrieving the pushes counts as a view of each
+    #[arg(id = "payload", long, action = ArgAction::SetTrue)]
+    pub payload: bool,
+
+    /// Passphrase of the pushes with --payload. A rotated push keeps the
+    /// passphrase of the original, so one is used for both
+    #[arg(id = "passphrase", long, value_parser = secret, requires = "payload")]
+    pub passphrase: Option<SecretString>,
+}
+
+impl fmt::Debug for PPCDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PPCDiff")
+            .field("url_token_a", &self.url_token_a)
+            .field("url_token_b", &self.url_token_b)
+            .field("kind", &self.kind)
+            .field("payload", &self.payload)
+            .field("passphrase", &redact::option(&self.passphrase))
+            .finish()
+    }
+}
+
+#[derive(Debug, Args)]
+#[command(group(
+    ArgGroup::new("changes")
+        .args(["note", "deletable-by-viewer", "retrieval-step"])
+        .required(true)
+        .multiple(true)
+))]
+pub struct PPCUpdate {
+    /// URL token of the push to change
+    #[arg(id = "url_token")]
+    pub url_token: String,
+
+    /// Kind of the push
+    #[arg(id = "kind", long, default_value = "text")]
+    pub kind: PushKind,
+
+    /// New note of the push, an empty note removes it
+    #[arg(id = "note", long)]
+    pub note: Option<String>,
+
+    /// Allow or forbid viewers to delete the push once retrieved
+    #[arg(id = "deletable-by-viewer", long)]
+    pub deletable_by_viewer: Option<bool>,
+
+    /// Turn the retrieval step on or off
+    #[arg(id = "retrieval-step", long)]
+    pub retrieval_step: Option<bool>,
 }
 
 #[derive(Debug, Args)]
+pub struct PPCToken {
+    /// URL token of the push, i.e. the last part of the secret link
+    #[arg(id = "url_token")]
+    pub url_token: String,
+}
+
+#[derive(Clone, Args)]
 pub struct PPCText {
-    /// The URL encoded password or secret text to share
-    #[arg(id = "password")]
-    pub password_payload: String,
+    /// The URL encoded password or secret text to share. Visible to other
+    /// users in the process list, prefer --stdin or --prompt
+    #[arg(
+        id = "password",
+        required_unless_present_any = ["payload-file", "stdin", "prompt", "from-op", "from-bw", "from-vault", "from-env"],
+        value_parser = secret
+    )]
+    pub password_payload: Option<SecretString>,
+
+    /// Read the secret from this file instead of the command line. The file
+    /// must contain UTF-8 text
+    #[arg(
+        id = "payload-file",
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["password", "stdin", "prompt"]
+    )]
+    pub payload_file: Option<PathBuf>,
+
+    /// Read the secret from stdin instead of the command line
+    #[arg(id = "stdin", long, action = ArgAction::SetTrue, conflicts_with_all = ["password", "prompt"])]
+    pub stdin: bool,
+
+    /// Type in the secret with hidden input instead of giving it on the
+    /// command line
+    #[arg(id = "prompt", long, action = ArgAction::SetTrue, conflicts_with = "password")]
+    pub prompt: bool,
+
+    /// Read the secret with the 1Password CLI, from a secret reference like
+    /// op://vault/item/field or the password of an item
+    #[arg(
+        id = "from-op",
+        long,
+        value_name = "ITEM",
+        conflicts_with_all = ["password", "payload-file", "stdin", "prompt", "from-bw", "from-vault"]
+    )]
+    pub from_op: Option<String>,
+
+    /// Read the password of this item with the Bitwarden CLI
+    #[arg(
+        id = "from-bw",
+        long,
+        value_name = "ITEM",
+        conflicts_with_all = ["password", "payload-file", "stdin", "prompt", "from-vault"]
+    )]
+    pub from_bw: Option<String>,
+
+    /// Read the secret from HashiCorp Vault, given as `<path>#<key>`, e.g.
+    /// secret/data/db#password. Uses VAULT_ADDR and VAULT_TOKEN
+    #[arg(
+        id = "from-vault",
+        long,
+        value_name = "REF",
+        value_parser = vault::parse_ref,
+        conflicts_with_all = ["password", "payload-file", "stdin", "prompt"]
+    )]
+    pub from_vault: Option<SecretRef>,
+
+    /// Push these environment variables as KEY=value lines, e.g. credentials
+    /// generated by a CI job. Values are masked in GitHub Actions logs
+    #[arg(
+        id = "from-env",
+        long,
+        value_name = "VAR",
+        value_delimiter = ',',
+        conflicts_with_all = ["password", "payload-file", "stdin", "prompt", "from-op", "from-bw", "from-vault"]
+    )]
+    pub from_env: Vec<String>,
+
+    /// Encode the secret as base64, e.g. to push binary data like keys. Read
+    /// it with get --decode-base64
+    #[arg(id = "base64", long, action = ArgAction::SetTrue)]
+    pub base64: bool,
+
+    /// Convert Windows line breaks (CRLF) in a secret read with --stdin or
+    /// --payload-file to LF, e.g. for keys piped from PowerShell
+    #[arg(id = "normalize-newlines", long, action = ArgAction::SetTrue, conflicts_with = "base64")]
+    pub normalize_newlines: bool,
+
+    /// Encrypt the secret with age or gpg for this recipient before pushing.
+    /// age recipients and SSH keys use age, everything else gpg
+    #[arg(id = "encrypt-for", long, value_name = "RECIPIENT")]
+    pub encrypt_for: Option<String>,
+
+    /// Do not warn about secrets given on the command line
+    #[arg(id = "no-warn", long, action = ArgAction::SetTrue)]
+    pub no_warn: bool,
 
     /// Require recipients to enter this passphrase to view the created push
-    #[arg(id = "passphrase", long)]
-    pub passphrase: Option<String>,
+    #[arg(id = "passphrase", long, value_parser = secret)]
+    pub passphrase: Option<SecretString>,
 
-    /// If authenticated, the URL encoded note for this push. Visible only to the push creator
+    /// Show the secret to recipients as QR code instead of text, e.g. to scan
+    /// it with a phone. Needs an instance with QR code pushes enabled
+    #[arg(id = "qr", long, action = ArgAction::SetTrue)]
+    pub qr: bool,
+
+    /// Also print the link as QR code, so recipients next to you can open it
+    /// by scanning the terminal with their phone
+    #[arg(
+        id = "qr-link",
+        long,
+        action = ArgAction::SetTrue,
+        conflicts_with_all = ["queue-on-failure", "to-profiles", "recipients"]
+    )]
+    pub qr_link: bool,
+
+    /// If authenticated, the URL encoded note for this push. Visible only to the push creator.
+    /// Placeholders {date}, {time}, {datetime}, {hostname} and {user} are expanded
     #[arg(id = "note", long)]
     pub note: Option<String>,
 
@@ -81,6 +992,39 @@ pub struct PPCText {
     #[arg(id = "expire-after-days", long)]
     pub expire_after_days: Option<usize>,
 
+    /// Expire at this time instead, e.g. 2024-12-01T18:00 in local time. As
+    /// instances count whole days, it is rounded to the nearest day
+    #[arg(
+        id = "expire-at",
+        long,
+        value_name = "TIMESTAMP",
+        value_parser = expire_at::parse,
+        conflicts_with = "expire-after-days"
+    )]
+    pub expire_at: Option<DateTime<Utc>>,
+
+    /// Expire after this time instead, e.g. 3d, 2w or 12h. As instances
+    /// count whole days, partial days are rounded up
+    #[arg(
+        id = "expire-after",
+        long,
+        value_name = "DURATION",
+        value_parser = expire_at::parse_after,
+        conflicts_with_all = ["expire-after-days", "expire-at"]
+    )]
+    pub expire_after: Option<Duration>,
+
+    /// Expire exactly at the time given with --expire-at, by holding the push
+    /// like --ephemeral --hold until then
+    #[arg(
+        id = "exact",
+        long,
+        action = ArgAction::SetTrue,
+        requires = "expire-at",
+        conflicts_with_all = ["hold", "queue-on-failure"]
+    )]
+    pub exact: bool,
+
     /// Expire secret link and delete after this many views
     #[arg(id = "expire-after-views", long)]
     pub expire_after_views: Option<usize>,
@@ -92,16 +1036,867 @@ pub struct PPCText {
     /// Helps to avoid chat systems and URL scanners from eating up views
     #[arg(id = "retrieval-step", long)]
     pub retrieval_step: Option<bool>,
+
+    /// Ignore the push defaults from the configuration file. Its passphrase
+    /// policy still applies
+    #[arg(id = "no-defaults", long, action = ArgAction::SetTrue)]
+    pub no_defaults: bool,
+
+    /// Apply the options of this preset from the configuration file
+    #[arg(id = "preset", long)]
+    pub preset: Option<String>,
+
+    /// If the instance cannot be reached, store the push encrypted in the
+    /// local queue instead of failing. Send it later with `queue flush`
+    #[arg(id = "queue-on-failure", long, action = ArgAction::SetTrue)]
+    pub queue_on_failure: bool,
+
+    /// Apply hardened options: 1 day, 1 view, retrieval step on, deletable by
+    /// viewer off and a generated passphrase, unless one is given. Presets and
+    /// push defaults cannot weaken them
+    #[arg(
+        id = "paranoid",
+        long,
+        action = ArgAction::SetTrue,
+        conflicts_with_all = ["expire-after-days", "expire-at", "expire-after", "expire-after-views", "retrieval-step", "deletable-by-viewer"]
+    )]
+    pub paranoid: bool,
+
+    /// Refuse passphrases that are easy to guess, instead of only warning
+    /// about them. Meant for automated use
+    #[arg(id = "enforce-strong-passphrase", long, action = ArgAction::SetTrue)]
+    pub enforce_strong_passphrase: bool,
+
+    /// Push the secret to the instances of these profiles from the
+    /// configuration file at the same time, instead of the selected instance,
+    /// e.g. work,dr-site. Prints a link per profile
+    #[arg(
+        id = "to-profiles",
+        long,
+        value_name = "PROFILES",
+        value_delimiter = ',',
+        conflicts_with_all = ["queue-on-failure", "hold", "exact"]
+    )]
+    pub to_profiles: Vec<String>,
+
+    /// Create a separate push for each of these recipients, e.g.
+    /// alice,bob,carol, with one view each unless --expire-after-views is
+    /// given. The note names the recipient, or replaces {recipient} in it
+    #[arg(
+        id = "recipients",
+        long,
+        value_name = "NAMES",
+        value_delimiter = ',',
+        conflicts_with_all = ["queue-on-failure", "hold", "exact", "to-profiles"]
+    )]
+    pub recipients: Vec<String>,
+
+    /// POST a JSON summary of the new push to this URL, e.g. an incoming
+    /// webhook of Slack, Teams or Matrix. It contains the link, the limits
+    /// and the note, but never the payload
+    #[arg(id = "notify-webhook", long, value_name = "URL")]
+    pub notify_webhook: Option<String>,
+
+    /// Email the link to this address through the SMTP server of the
+    /// configuration file. The passphrase is not part of the email
+    #[arg(
+        id = "email-to",
+        long,
+        value_name = "ADDRESS",
+        conflicts_with_all = ["queue-on-failure", "to-profiles", "recipients"]
+    )]
+    pub email_to: Option<String>,
+
+    /// Also print a short link from the shortener of the configuration file,
+    /// e.g. for links that are read out over the phone
+    #[arg(
+        id = "shorten",
+        long,
+        action = ArgAction::SetTrue,
+        conflicts_with_all = ["queue-on-failure", "to-profiles", "recipients"]
+    )]
+    pub shorten: bool,
+
+    /// Check that the link opens on the instance before printing it, e.g. to
+    /// catch reverse proxies that forward the API but not the public URL
+    #[arg(
+        id = "validate",
+        long,
+        action = ArgAction::SetTrue,
+        conflicts_with_all = ["queue-on-failure", "to-profiles", "recipients"]
+    )]
+    pub validate: bool,
+
+    /// Tag the push in the local history, e.g. team:infra, to find it with
+    /// `history list --tag` or expire it with `expire bulk --tag`. Tags are
+    /// not sent to the instance. Can be given multiple times
+    #[arg(
+        id = "tag",
+        long = "tag",
+        value_name = "TAG",
+        value_parser = history::parse_tag,
+        conflicts_with = "queue-on-failure"
+    )]
+    pub tags: Vec<String>,
+
+    /// Keep running and expire the push as soon as its audit log shows the
+    /// first successful view. Unlike a single view, this is not used up by
+    /// link scanners that do not get past the retrieval step. Requires
+    /// authentication
+    #[arg(
+        id = "expire-after-first-view-confirmed",
+        long,
+        action = ArgAction::SetTrue,
+        conflicts_with_all = ["hold", "exact", "queue-on-failure", "to-profiles", "recipients"]
+    )]
+    pub expire_after_first_view_confirmed: bool,
+
+    /// Refuse to push a secret that was already pushed within --dedupe-window,
+    /// or only warn about it with `warn`. Pushed secrets are remembered as
+    /// keyed hashes in a local state file
+    #[arg(
+        id = "dedupe",
+        long,
+        value_name = "MODE",
+        num_args = 0..=1,
+        default_missing_value = "abort"
+    )]
+    pub dedupe: Option<DedupeMode>,
+
+    /// How long pushed secrets are remembered for --dedupe, e.g. 12h or 3d
+    #[arg(
+        id = "dedupe-window",
+        long,
+        value_name = "DURATION",
+        default_value = "24h",
+        value_parser = duration::parse,
+        requires = "dedupe"
+    )]
+    pub dedupe_window: Duration,
+
+    /// Print the link of the push that was created with the same key within
+    /// the last 24 hours instead of pushing again, so retries do not create
+    /// duplicates. Keys are recorded in the local history
+    #[arg(
+        id = "idempotency-key",
+        long,
+        value_name = "KEY",
+        conflicts_with_all = ["queue-on-failure", "to-profiles", "recipients"]
+    )]
+    pub idempotency_key: Option<String>,
+
+    /// Keep running after the push was created, until Ctrl-C is pressed or
+    /// the time given with --hold-for has elapsed
+    #[arg(id = "hold", long, action = ArgAction::SetTrue, conflicts_with = "queue-on-failure")]
+    pub hold: bool,
+
+    /// End the hold after this long, e.g. 10m or 2h
+    #[arg(id = "hold-for", long, value_name = "DURATION", value_parser = duration::parse, requires = "hold")]
+    pub hold_for: Option<Duration>,
+
+    /// Expire the push when the hold ends, so the link dies when you walk
+    /// away. Requires --hold
+    #[arg(id = "ephemeral", long, action = ArgAction::SetTrue, requires = "hold")]
+    pub ephemeral: bool,
+}
+
+/// Payload and passphrase are redacted, so push options can be logged safely.
+impl fmt::Debug for PPCText {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PPCText")
+            .field("password_payload", &redact::option(&self.password_payload))
+            .field("payload_file", &self.payload_file)
+            .field("stdin", &self.stdin)
+            .field("prompt", &self.prompt)
+            .field("from_op", &self.from_op)
+            .field("from_bw", &self.from_bw)
+            .field("from_vault", &self.from_vault)
+            .field("from_env", &self.from_env)
+            .field("base64", &self.base64)
+            .field("normalize_newlines", &self.normalize_newlines)
+            .field("encrypt_for", &self.encrypt_for)
+            .field("no_warn", &self.no_warn)
+            .field("passphrase", &redact::option(&self.passphrase))
+            .field("qr", &self.qr)
+            .field("qr_link", &self.qr_link)
+            .field("note", &self.note)
+            .field("expire_after_days", &self.expire_after_days)
+            .field("expire_after_views", &self.expire_after_views)
+            .field("expire_at", &self.expire_at)
+            .field("expire_after", &self.expire_after)
+            .field("exact", &self.exact)
+            .field("deletable_by_viewer", &self.deletable_by_viewer)
+            .field("retrieval_step", &self.retrieval_step)
+            .field("no_defaults", &self.no_defaults)
+            .field("preset", &self.preset)
+            .field("queue_on_failure", &self.queue_on_failure)
+            .field("paranoid", &self.paranoid)
+            .field("enforce_strong_passphrase", &self.enforce_strong_passphrase)
+            .field("to_profiles", &self.to_profiles)
+            .field("recipients", &self.recipients)
+            .field("notify_webhook", &redact::option(&self.notify_webhook))
+            .field("email_to", &self.email_to)
+            .field("shorten", &self.shorten)
+            .field("validate", &self.validate)
+            .field("tags", &self.tags)
+            .field(
+                "expire_after_first_view_confirmed",
+                &self.expire_after_first_view_confirmed,
+            )
+            .field("dedupe", &self.dedupe)
+            .field("dedupe_window", &self.dedupe_window)
+            .field("idempotency_key", &self.idempotency_key)
+            .field("hold", &self.hold)
+            .field("hold_for", &self.hold_for)
+            .field("ephemeral", &self.ephemeral)
+            .finish()
+    }
+}
+
+/// What `--dedupe` does about a secret that was pushed before.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum DedupeMode {
+    /// Push it anyway, with a warning
+    Warn,
+
+    /// Do not push it
+    Abort,
+}
+
+/// Formats of the archive of `push file --dir`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum ArchiveFormat {
+    /// gzip compressed tar archive, which keeps file permissions
+    #[value(name = "tar.gz")]
+    TarGz,
+
+    /// zip archive, which opens on Windows without extra tools
+    Zip,
+}
+
+#[derive(Debug, Args)]
+pub struct PPCHelper {
+    /// What to do with the attributes on stdin
+    pub operation: HelperOperation,
+}
+
+/// Operations of the `helper` protocol.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum HelperOperation {
+    /// Create a text push, answers with url and url_token
+    Push,
+
+    /// Expire the text push given as url_token
+    Expire,
+}
+
+#[cfg(feature = "serve")]
+#[derive(Debug, Args)]
+pub struct PPCServe {
+    /// Address to listen on. Only loopback addresses are allowed, as the API
+    /// needs no credentials
+    #[arg(id = "listen", long, value_name = "ADDRESS", default_value = serve::DEFAULT_LISTEN)]
+    pub listen: std::net::SocketAddr,
+
+    /// Report tracked pushes that were viewed for the last time, expired or
+    /// were deleted to this webhook. Pushes created through the API are
+    /// tracked, others can be added with `POST /watch`. Needs credentials.
+    /// Repeat for more webhooks
+    #[arg(id = "event-webhook", long, value_name = "URL")]
+    pub event_webhooks: Vec<String>,
+
+    /// How often to look for tracked pushes that ended
+    #[arg(id = "watch-interval", long, value_name = "DURATION", default_value = "1m", value_parser = duration::parse, requires = "event-webhook")]
+    pub watch_interval: Duration,
+}
+
+/// Options of a file push. The options are a subset of those of text pushes,
+/// and mean the same.
+#[derive(Debug, Clone, Args)]
+pub struct PPCFile {
+    /// Files to push. They are streamed from disk, so their size is only
+    /// limited by the instance
+    #[arg(id = "files", value_name = "FILE", required_unless_present = "dir")]
+    pub files: Vec<PathBuf>,
+
+    /// Push an archive of this directory instead of single files
+    #[arg(id = "dir", long, value_name = "DIR", conflicts_with = "files")]
+    pub dir: Option<PathBuf>,
+
+    /// Format of the archive of --dir
+    #[arg(
+        id = "format",
+        long,
+        value_enum,
+        default_value = "tar.gz",
+        conflicts_with = "files"
+    )]
+    pub format: ArchiveFormat,
+
+    /// Only archive files whose path within --dir matches this glob, e.g.
+    /// '*.pem'. Can be given several times
+    #[arg(
+        id = "include",
+        long,
+        value_name = "GLOB",
+        requires = "dir",
+        conflicts_with = "files"
+    )]
+    pub include: Vec<String>,
+
+    /// Leave out files and directories whose path within --dir matches this
+    /// glob, e.g. '**/.git'. Can be given several times
+    #[arg(
+        id = "exclude",
+        long,
+        value_name = "GLOB",
+        requires = "dir",
+        conflicts_with = "files"
+    )]
+    pub exclude: Vec<String>,
+
+    /// Encrypt the archive of --dir with age or gpg for this recipient before
+    /// pushing. age recipients and SSH keys use age, everything else gpg
+    #[arg(
+        id = "encrypt-for",
+        long,
+        value_name = "RECIPIENT",
+        requires = "dir",
+        conflicts_with = "files"
+    )]
+    pub encrypt_for: Option<String>,
+
+    /// Require recipients to enter this passphrase to view the created push
+    #[arg(id = "passphrase", long, value_parser = secret)]
+    pub passphrase: Option<SecretString>,
+
+    /// If authenticated, the note for this push. Visible only to the push
+    /// creator. Placeholders like {date} and {user} are expanded
+    #[arg(id = "note", long)]
+    pub note: Option<String>,
+
+    /// Expire secret link and delete after this many days
+    #[arg(id = "expire-after-days", long)]
+    pub expire_after_days: Option<usize>,
+
+    /// Expire secret link and delete after this many views
+    #[arg(id = "expire-after-views", long)]
+    pub expire_after_views: Option<usize>,
+
+    /// Allow users to delete the files once retrieved
+    #[arg(id = "deletable-by-viewer", long)]
+    pub deletable_by_viewer: Option<bool>,
+
+    /// Helps to avoid chat systems and URL scanners from eating up views
+    #[arg(id = "retrieval-step", long)]
+    pub retrieval_step: Option<bool>,
+
+    /// Ignore the push defaults from the configuration file. Its passphrase
+    /// policy still applies
+    #[arg(id = "no-defaults", long, action = ArgAction::SetTrue)]
+    pub no_defaults: bool,
+
+    /// Also print a short link from the shortener of the configuration file
+    #[arg(id = "shorten", long, action = ArgAction::SetTrue)]
+    pub shorten: bool,
+
+    /// Check that the link opens on the instance before printing it
+    #[arg(id = "validate", long, action = ArgAction::SetTrue)]
+    pub validate: bool,
+
+    /// Tag the push in the local history. Can be given multiple times
+    #[arg(id = "tag", long = "tag", value_name = "TAG", value_parser = history::parse_tag)]
+    pub tags: Vec<String>,
 }
 
 #[derive(Debug, Args)]
-pub struct PPCFile {}
+pub struct PPCUrl {}
+
+/// Options of a WiFi push, see `wifi`.
+#[derive(Clone, Args)]
+pub struct PPCWifi {
+    /// Name of the network
+    #[arg(id = "ssid", long)]
+    pub ssid: String,
+
+    /// Password of the network. Visible to other users in the process list,
+    /// leave it out to be asked for it, or use --stdin
+    #[arg(id = "psk", long, value_parser = secret, conflicts_with = "stdin")]
+    pub psk: Option<SecretString>,
+
+    /// Read the password of the network from the first line of stdin
+    #[arg(id = "stdin", long, action = ArgAction::SetTrue)]
+    pub stdin: bool,
+
+    /// Security of the network
+    #[arg(id = "security", long, value_enum, default_value = "wpa")]
+    pub security: WifiSecurity,
+
+    /// The network does not broadcast its name
+    #[arg(id = "hidden", long, action = ArgAction::SetTrue)]
+    pub hidden: bool,
+
+    #[command(flatten)]
+    pub push: PPCStructuredPush,
+}
+
+/// The password is redacted, so push options can be logged safely.
+impl fmt::Debug for PPCWifi {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PPCWifi")
+            .field("ssid", &self.ssid)
+            .field("psk", &redact::option(&self.psk))
+            .field("stdin", &self.stdin)
+            .field("security", &self.security)
+            .field("hidden", &self.hidden)
+            .field("push", &self.push)
+            .finish()
+    }
+}
+
+/// Options of a TOTP push, see `totp`.
+#[derive(Clone, Args)]
+pub struct PPCTotp {
+    /// Service the account belongs to, shown by authenticator apps, e.g.
+    /// "Example Corp"
+    #[arg(id = "issuer", long)]
+    pub issuer: String,
+
+    /// Name of the account, e.g. alice@example.com
+    #[arg(id = "account", long)]
+    pub account: String,
+
+    /// The shared secret in base32, as shown by the service. Visible to other
+    /// users in the process list, leave it out to be asked for it, or use
+    /// --stdin
+    #[arg(id = "secret", long, value_parser = secret, conflicts_with = "stdin")]
+    pub secret: Option<SecretString>,
+
+    /// Read the shared secret from the first line of stdin
+    #[arg(id = "stdin", long, action = ArgAction::SetTrue)]
+    pub stdin: bool,
+
+    /// Hash algorithm of the codes, if the service does not use the default
+    #[arg(id = "algorithm", long, value_enum, default_value = "sha1")]
+    pub algorithm: TotpAlgorithm,
+
+    /// Number of digits of the codes
+    #[arg(id = "digits", long, default_value_t = 6, value_parser = clap::value_parser!(u8).range(6..=8))]
+    pub digits: u8,
+
+    /// Seconds each code is valid
+    #[arg(id = "period", long, default_value_t = 30, value_parser = clap::value_parser!(u32).range(1..))]
+    pub period: u32,
+
+    #[command(flatten)]
+    pub push: PPCStructuredPush,
+}
+
+/// The secret is redacted, so push options can be logged safely.
+impl fmt::Debug for PPCTotp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PPCTotp")
+            .field("issuer", &self.issuer)
+            .field("account", &self.account)
+            .field("secret", &redact::option(&self.secret))
+            .field("stdin", &self.stdin)
+            .field("algorithm", &self.algorithm)
+            .field("digits", &self.digits)
+            .field("period", &self.period)
+            .field("push", &self.push)
+            .finish()
+    }
+}
+
+/// Push options of payloads that are built from other options, like `push
+/// wifi`, see `structured`. They are a subset of those of text pushes, and
+/// mean the same.
+#[derive(Clone, Args)]
+pub struct PPCStructuredPush {
+    /// Show the payload to recipients as QR code, e.g. to scan it with a
+    /// phone. Needs an instance with QR code pushes enabled
+    #[arg(id = "qr", long, action = ArgAction::SetTrue)]
+    pub qr: bool,
+
+    /// Also print the link as QR code, so recipients next to you can open it
+    /// by scanning the terminal with their phone
+    #[arg(id = "qr-link", long, action = ArgAction::SetTrue)]
+    pub qr_link: bool,
+
+    /// Require recipients to enter this passphrase to view the created push
+    #[arg(id = "passphrase", long, value_parser = secret)]
+    pub passphrase: Option<SecretString>,
+
+    /// If authenticated, the note for this push. Visible only to the push
+    /// creator. Placeholders like {date} and {user} are expanded
+    #[arg(id = "note", long)]
+    pub note: Option<String>,
+
+    /// Expire secret link and delete after this many days
+    #[arg(id = "expire-after-days", long)]
+    pub expire_after_days: Option<usize>,
+
+    /// Expire secret link and delete after this many views
+    #[arg(id = "expire-after-views", long)]
+    pub expire_after_views: Option<usize>,
+
+    /// Ignore the push defaults from the configuration file. Its passphrase
+    /// policy still applies
+    #[arg(id = "no-defaults", long, action = ArgAction::SetTrue)]
+    pub no_defaults: bool,
+
+    /// Apply the options of this preset from the configuration file
+    #[arg(id = "preset", long)]
+    pub preset: Option<String>,
+
+    /// Do not warn about secrets given on the command line
+    #[arg(id = "no-warn", long, action = ArgAction::SetTrue)]
+    pub no_warn: bool,
+}
+
+/// The passphrase is redacted, so push options can be logged safely.
+impl fmt::Debug for PPCStructuredPush {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PPCStructuredPush")
+            .field("qr", &self.qr)
+            .field("qr_link", &self.qr_link)
+            .field("passphrase", &redact::option(&self.passphrase))
+            .field("note", &self.note)
+            .field("expire_after_days", &self.expire_after_days)
+            .field("expire_after_views", &self.expire_after_views)
+            .field("no_defaults", &self.no_defaults)
+            .field("preset", &self.preset)
+            .field("no_warn", &self.no_warn)
+            .finish()
+    }
+}
+
+/// Security types of WiFi networks, as named in the `WIFI:` format.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum WifiSecurity {
+    /// WPA, WPA2 or WPA3 with a password
+    Wpa,
+
+    /// WEP with a password, only for old networks
+    Wep,
+
+    /// An open network without password
+    Nopass,
+}
+
+/// Hash algorithms of TOTP codes, as named in `otpauth://` URIs.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum TotpAlgorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+/// Subcommands to inspect and modify the presets in the configuration file.
+#[derive(Debug, Subcommand)]
+pub enum PresetCommand {
+    /// Show all configured presets
+    List,
+
+    /// Add a preset, or replace an existing preset with the same name
+    Add(PresetAdd),
+
+    /// Remove a preset
+    Remove {
+        /// Name of the preset to remove
+        name: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Show the settings a command would use, and where each of them comes
+    /// from. Nothing is sent to the instance
+    Resolve {
+        /// The command with its options, e.g. `push text --stdin --preset
+        /// handoff`. The payload is never read. Default is `push text --stdin`
+        #[arg(
+            value_name = "COMMAND",
+            trailing_var_arg = true,
+            allow_hyphen_values = true
+        )]
+        command: Vec<String>,
+    },
+}
+
+#[cfg(feature = "email")]
+#[derive(Debug, Subcommand)]
+pub enum SmtpCommand {
+    /// Type in the password of the SMTP server and store it in the keyring
+    SetPassword,
+
+    /// Delete the password of the SMTP server from the keyring
+    DeletePassword,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AuthCommand {
+    /// Type in the token of a profile and store it encrypted in the
+    /// configuration file. The profile is created if it does not exist
+    SetToken {
+        /// Name of the profile
+        profile: String,
+
+        /// Encrypt with a passphrase instead of the key of this machine. It
+        /// is asked for on use, or taken from PWPUSH_CREDENTIALS_PASSPHRASE
+        #[clap(long)]
+        with_passphrase: bool,
+
+        /// Read the token from stdin instead of asking for it
+        #[clap(long)]
+        stdin: bool,
+    },
+
+    /// Encrypt all tokens that are stored in plain text in the configuration
+    /// file
+    Encrypt {
+        /// Encrypt with a passphrase instead of the key of this machine
+        #[clap(long)]
+        with_passphrase: bool,
+    },
+
+    /// Write profiles with their tokens as text that is encrypted with a
+    /// passphrase, to move them to another machine
+    Export {
+        /// Names of the profiles, all profiles if none are given
+        profiles: Vec<String>,
+
+        /// File to write to, only readable by the user, instead of stdout
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Add the profiles of `auth export` to the configuration file, with
+    /// their tokens encrypted for this machine
+    Import {
+        /// File of the export, read from stdin if not given
+        file: Option<PathBuf>,
+
+        /// Encrypt the tokens with a passphrase instead of the key of this
+        /// machine
+        #[clap(long)]
+        with_passphrase: bool,
+
+        /// Replace profiles that exist already
+        #[clap(long)]
+        force: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum QueueCommand {
+    /// Show all queued pushes
+    List,
+
+    /// Send all queued pushes. Pushes rejected by the instance are dropped,
+    /// the rest stays queued if the instance is still unreachable
+    Flush,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum BulkCommand {
+    /// Show the jobs of expire bulk and push text --recipients that did not
+    /// finish
+    List,
+
+    /// Continue a job with the items that are not done yet
+    Resume {
+        /// ID of the job, as shown by `bulk list`
+        job_id: String,
+    },
+
+    /// Delete a job without finishing it
+    Discard {
+        /// ID of the job, as shown by `bulk list`
+        job_id: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum HistoryCommand {
+    /// Show all recorded pushes, oldest first
+    List {
+        /// Only show pushes with this tag. If given multiple times, pushes
+        /// need all of the tags
+        #[arg(id = "tag", long = "tag", value_name = "TAG", value_parser = history::parse_tag)]
+        tags: Vec<String>,
+    },
+
+    /// Show recorded pushes whose URL token, instance, note or URL contain
+    /// the text, ignoring case
+    Search {
+        /// Text to look for
+        query: String,
+    },
+
+    /// Delete the history
+    Clear,
+}
 
 #[derive(Debug, Args)]
-pub struct PPCURL {}
+pub struct PresetAdd {
+    /// Name of the preset, used with `push text --preset <name>`
+    #[arg(id = "name")]
+    pub name: String,
+
+    /// Expire secret link and delete after this many days
+    #[arg(id = "expire-after-days", long)]
+    pub expire_after_days: Option<usize>,
+
+    /// Expire secret link and delete after this many views
+    #[arg(id = "expire-after-views", long)]
+    pub expire_after_views: Option<usize>,
+
+    /// Allow users to delete passwords once retrieved
+    #[arg(id = "deletable-by-viewer", long)]
+    pub deletable_by_viewer: Option<bool>,
+
+    /// Helps to avoid chat systems and URL scanners from eating up views
+    #[arg(id = "retrieval-step", long)]
+    pub retrieval_step: Option<bool>,
+
+    /// Text that is prepended to the note of each push
+    #[arg(id = "note-prefix", long)]
+    pub note_prefix: Option<String>,
+
+    /// Generate a random passphrase of this length for each push
+    #[arg(id = "passphrase-length", long, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub passphrase_length: Option<usize>,
+}
+
+/// The three kinds of pushes the API knows about.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, ValueEnum, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PushKind {
+    Text,
+    File,
+    Url,
+}
+
+impl Display for PushKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PushKind::Text => write!(f, "text"),
+            PushKind::File => write!(f, "file"),
+            PushKind::Url => write!(f, "url"),
+        }
+    }
+}
+
+/// Formats of `--output`, see `render`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Text for people
+    Human,
+
+    /// The same as --json
+    Json,
+
+    /// Only the essential value, like the link of a new push
+    Quiet,
+
+    /// A header line and one line per result
+    Csv,
+
+    /// Label: value lines without colors or alignment, e.g. for screen
+    /// readers
+    Plain,
+}
+
+impl Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputFormat::Human => write!(f, "human"),
+            OutputFormat::Json => write!(f, "json"),
+            OutputFormat::Quiet => write!(f, "quiet"),
+            OutputFormat::Csv => write!(f, "csv"),
+            OutputFormat::Plain => write!(f, "plain"),
+        }
+    }
+}
+
+/// The API keeps separate lists for active and expired pushes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum PushState {
+    Active,
+    Expired,
+}
+
+impl Display for PushState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PushState::Active => write!(f, "active"),
+            PushState::Expired => write!(f, "expired"),
+        }
+    }
+}
+
+/// Kinds of audit events for `audit --kind`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum AuditKind {
+    /// Successful views of the payload
+    View,
+
+    /// Views that failed, e.g. because of a wrong passphrase
+    FailedView,
+
+    /// Expirations and deletions of the push
+    Expire,
+}
+
+/// Order of events by time.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum SortOrder {
+    OldestFirst,
+    NewestFirst,
+}
+
+/// Formats of `audit --format` for log management systems, see `siem`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum AuditFormat {
+    /// ArcSight Common Event Format
+    Cef,
+
+    /// RFC 5424 syslog messages with key=value pairs
+    Syslog,
+
+    /// One JSON object per line, with Elastic Common Schema fields
+    Ndjson,
+}
+
+/// File formats for exports of the push inventory.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum InventoryFormat {
+    /// A JSON array with one object per push
+    Json,
+
+    /// One line per push, with a header line
+    Csv,
+}
+
+/// Value parser for secrets, so they are wrapped right away and never exist as
+/// plain string in the parsed arguments.
+fn secret(value: &str) -> Result<SecretString, String> {
+    Ok(SecretString::from(value))
+}
+
+/// Parse a request rate, which must be a positive number.
+fn positive_rate(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(rate) if rate > 0.0 && rate.is_finite() => Ok(rate),
+        _ => Err(format!("`{}` is not a positive number", value)),
+    }
+}
 
 /// Limit available instance protocols to a valid protocol.
-#[derive(Debug, Copy, Clone, ValueEnum)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum InstanceProtocol {
     /// API calls use http://
     Http,
@@ -119,6 +1914,27 @@ impl Display for InstanceProtocol {
     }
 }
 
+/// How credentials are sent to the instance.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuthScheme {
+    /// Email and token in the X-User-Email and X-User-Token headers, as
+    /// understood by all servers
+    HeaderPair,
+
+    /// Only the token, as `Authorization: Bearer <token>`, for newer servers
+    Bearer,
+}
+
+impl Display for AuthScheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuthScheme::HeaderPair => write!(f, "header-pair"),
+            AuthScheme::Bearer => write!(f, "bearer"),
+        }
+    }
+}
+
 /// Define values that allow the user to specify the preferred log level.
 /// These values correspond to the available options in the log crate, see
 /// <https://docs.rs/log/latest/log/> for details.
@@ -139,3 +1955,71 @@ pub enum LogVerbosity {
     /// structures
     Debug = 3,
 }
+
+/// Available formats for log output on stderr.
+#[derive(Debug, Copy, Clone, ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines with timestamp and level
+    Text,
+
+    /// One JSON object per line, for log aggregation
+    Json,
+}
+
+/// What fails a command, see `fail_on`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum FailOn {
+    /// Only errors that stop the command
+    None,
+
+    /// Errors and failed items of bulk operations
+    Error,
+
+    /// Errors, failed items and soft failures
+    Warning,
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+
+    use super::PPCArgs;
+
+    #[test]
+    fn debug_redacts_secrets() {
+        let args = PPCArgs::parse_from([
+            "pwpush-cli",
+            "--email",
+            "user@example.com",
+            "--token",
+            "t0k3n",
+            "push",
+            "text",
+            "secret",
+            "--passphrase",
+            "open",
+            "--note",
+            "visible",
+            "--notify-webhook",
+            "https://hooks.example.com/services/T0/B0/hook-s3cret",
+        ]);
+
+        let debug = format!("{:?}", args);
+
+        assert!(!debug.contains("t0k3n"));
+        assert!(!debug.contains("secret"));
+        assert!(!debug.contains("open"));
+        assert!(!debug.contains("hook-s3cret"));
+        assert!(debug.contains("user@example.com"));
+        assert!(debug.contains("visible"));
+        assert!(debug.contains("password_payload: Some(REDACTED)"));
+    }
+
+    #[test]
+    fn debug_redacts_get_passphrase() {
+        let args =
+            PPCArgs::parse_from(["pwpush-cli", "get", "--passphrase", "open", "text", "abc"]);
+
+        assert!(!format!("{:?}", args).contains("open"));
+    }
+}
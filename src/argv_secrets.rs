@@ -0,0 +1,169 @@
+//! Refusing secrets on the command line, for security-sensitive environments.
+//!
+//! Arguments are visible to other users of the machine in the process list,
+//! and end up in shell histories and the logs of CI systems. A warning points
+//! to the alternatives, see `payload`, but warnings are easily missed. With
+//!
+//! ```toml
+//! [push]
+//! forbid_argv_secrets = true
+//! ```
+//!
+//! in the configuration file, or `forbid_argv_secrets = true` in the policy
+//! file, where users cannot turn it off, see `policy`, commands fail before
+//! anything is sent if a payload, the secret part of a payload like the
+//! password of `push wifi`, or a passphrase was given as argument. They have
+//! to come from `--stdin`, `--prompt`, `--payload-file` or a password manager
+//! instead, and passwords and passphrases are asked for where the command
+//! supports it.
+//!
+//! The secrets are looked up in the arguments the process was started with,
+//! so secrets from `--options-from` files and from `--stdin-json` are fine,
+//! as neither shows up in the process list.
+
+use std::env;
+use std::ffi::OsString;
+
+use secrecy::{ExposeSecret, SecretString};
+
+use crate::args::{PPCAction, PPCArgs, PPCObject};
+use crate::config::PPCConfig;
+use crate::errors::PPCError;
+
+/// Fail if the configuration or the policy forbids secrets on the command
+/// line, and one was given there.
+pub fn check(args: &PPCArgs, config: &PPCConfig) -> Result<(), PPCError> {
+    if !config.push.forbid_argv_secrets && !config.policy.forbid_argv_secrets {
+        return Ok(());
+    }
+    let argv = env::args_os().collect::<Vec<_>>();
+    let given = given_as_argument(&args.action, &argv);
+    if given.is_empty() {
+        return Ok(());
+    }
+
+    let reason = format!(
+        "{} must not be given as command line argument, use --stdin, --prompt or \
+         --payload-file for payloads, and leave out --passphrase to be asked for it",
+        given.join(" and ")
+    );
+    if config.policy.forbid_argv_secrets {
+        Err(config.policy.violation(reason))
+    } else {
+        Err(PPCError::from(format!(
+            "refused by forbid_argv_secrets in the configuration file: {}",
+            reason
+        )))
+    }
+}
+
+/// Names of the secrets of the command that appear in `argv`.
+fn given_as_argument(action: &PPCAction, argv: &[OsString]) -> Vec<&'static str> {
+    secrets(action)
+        .into_iter()
+        .filter(|(_, secret)| appears_in(argv, secret.expose_secret()))
+        .map(|(name, _)| name)
+        .collect()
+}
+
+/// The secrets a command was given, with names for the error.
+fn secrets(action: &PPCAction) -> Vec<(&'static str, &SecretString)> {
+    let (payload, passphrase) = match action {
+        PPCAction::Push(PPCObject::Text(ppc_text)) => (
+            ppc_text
+                .password_payload
+                .as_ref()
+                .map(|payload| ("the payload", payload)),
+            ppc_text.passphrase.as_ref(),
+        ),
+        PPCAction::Push(PPCObject::Wifi(ppc_wifi)) => (
+            ppc_wifi.psk.as_ref().map(|psk| ("--psk", psk)),
+            ppc_wifi.push.passphrase.as_ref(),
+        ),
+        PPCAction::Push(PPCObject::Totp(ppc_totp)) => (
+            ppc_totp.secret.as_ref().map(|secret| ("--secret", secret)),
+            ppc_totp.push.passphrase.as_ref(),
+        ),
+        PPCAction::Push(PPCObject::File(ppc_file)) => (None, ppc_file.passphrase.as_ref()),
+        PPCAction::Get(ppc_get) => (None, ppc_get.passphrase.as_ref()),
+        PPCAction::Rotate(ppc_rotate) => (None, ppc_rotate.passphrase.as_ref()),
+        PPCAction::Clone(ppc_clone) => (None, ppc_clone.passphrase.as_ref()),
+        PPCAction::Diff(ppc_diff) => (None, ppc_diff.passphrase.as_ref()),
+        _ => (None, None),
+    };
+    payload
+        .into_iter()
+        .chain(passphrase.map(|passphrase| ("--passphrase", passphrase)))
+        .collect()
+}
+
+/// Whether the secret is an argument of its own, or the value of an option
+/// like `--passphrase=secret`.
+fn appears_in(argv: &[OsString], secret: &str) -> bool {
+    if secret.is_empty() {
+        return false;
+    }
+    argv.iter().skip(1).any(|arg| {
+        let arg = arg.to_string_lossy();
+        arg == secret
+            || (arg.starts_with("--")
+                && arg
+                    .split_once('=')
+                    .is_some_and(|(_, value)| value == secret))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::ffi::OsString;
+
+    use clap::Parser;
+
+    use super::given_as_argument;
+    use crate::args::PPCArgs;
+
+    fn argv(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn secrets_in_the_arguments_are_found() {
+        let line = [
+            "pwpush-cli",
+            "push",
+            "text",
+            "--passphrase=open-sesame",
+            "s3cret",
+        ];
+        let args = PPCArgs::parse_from(line);
+
+        assert_eq!(
+            given_as_argument(&args.action, &argv(&line)),
+            vec!["the payload", "--passphrase"]
+        );
+        // expanded from an options file, which the process list does not show.
+        assert!(given_as_argument(
+            &args.action,
+            &argv(&["pwpush-cli", "--options-from", "push.args"])
+        )
+        .is_empty());
+
+        let line = [
+            "pwpush-cli",
+            "get",
+            "--passphrase",
+            "open-sesame",
+            "text",
+            "abc",
+        ];
+        let args = PPCArgs::parse_from(line);
+        assert_eq!(
+            given_as_argument(&args.action, &argv(&line)),
+            vec!["--passphrase"]
+        );
+
+        let line = ["pwpush-cli", "push", "text", "--stdin"];
+        let args = PPCArgs::parse_from(line);
+        assert!(given_as_argument(&args.action, &argv(&line)).is_empty());
+    }
+}
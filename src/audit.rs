@@ -0,0 +1,429 @@
+//! The `audit` command, including the `--watch` mode.
+//!
+//! Without `--watch`, the audit log is fetched once and printed. With
+//! `--watch`, the audit endpoint is polled in the given interval, and only
+//! events that were not printed before are emitted, so a sender can see the
+//! moment a recipient opens the link. In JSON mode, watching produces one JSON
+//! object per line (jsonl), which is easy to consume from scripts while the
+//! command is still running.
+//!
+//! Hooks given with `--on-view-exec` and `--on-view-webhook` are triggered for
+//! every new event in watch mode. Events that already existed when watching
+//! started are printed, but do not trigger hooks.
+//!
+//! `--since`, `--ip` and `--kind` filter the events on this side, as the API
+//! has no filters. In watch mode, they also decide which events trigger hooks.
+//!
+//! With `--format`, events are printed in the formats of log management
+//! systems instead, see `siem`. With `--all`, the logs of all active pushes
+//! are summarized in a report instead, see `audit_report`.
+//!
+//! The API does not assign ids to audit events, so events are identified by
+//! their full content.
+
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::ops::ControlFlow;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use ipnet::IpNet;
+use tracing::debug;
+
+use crate::args::{AuditFormat, AuditKind, PPCArgs, PPCAudit, SortOrder};
+use crate::dates::DateFormatter;
+use crate::errors::PPCError;
+use crate::hooks::ViewHooks;
+use crate::http::Transport;
+use crate::instance::Instance;
+use crate::models::{AuditLog, AuditView};
+use crate::scheduler::Scheduler;
+use crate::siem::{self, Source};
+use crate::table::{self, Column, Table};
+use crate::{audit_report, i18n, output, pager, platform, pwpush_api, render};
+
+pub fn run(
+    transport: &dyn Transport,
+    instance: &Instance,
+    args: &PPCArgs,
+    ppc_audit: &PPCAudit,
+) -> Result<(), PPCError> {
+    let object =
+        match (&ppc_audit.object, ppc_audit.all) {
+            (None, true) => {
+                let report = audit_report::run(transport, instance, args, ppc_audit, Utc::now())?;
+                render::from_args(args).render(&report)?;
+                return report.result();
+            }
+            (Some(object), false) => object,
+            (Some(_), true) => {
+                return Err(PPCError::from(
+                    "--all audits all active pushes, leave out the push",
+                ))
+            }
+            (None, false) => return Err(PPCError::from(
+                "no push given, use e.g. `audit text <url_token>`, or --all for all active pushes",
+            )),
+        };
+    let kind = object.kind();
+    let url_token = &object.token().url_token;
+
+    let hostname = platform::hostname();
+    let output = Output {
+        args,
+        format: ppc_audit.format,
+        source: Source {
+            instance: &instance.url,
+            kind,
+            url_token,
+            hostname: &hostname,
+        },
+    };
+
+    let filter = Filter::new(ppc_audit, Utc::now());
+    let mut log = pwpush_api::audit(transport, instance, kind, url_token)?;
+    if !ppc_audit.watch {
+        log.views.retain(|view| filter.matches(view));
+        if let Some(order) = ppc_audit.sort {
+            sort(&mut log, order);
+        }
+        return output.print_log(&log);
+    }
+
+    let mut seen = HashSet::new();
+    for view in new_views(&mut seen, &log) {
+        if filter.matches(view) {
+            output.print_view(view);
+        }
+    }
+
+    let hooks = ViewHooks {
+        exec: ppc_audit.on_view_exec.as_deref(),
+        webhook: ppc_audit.on_view_webhook.as_deref(),
+    };
+    let webhooks = platform::transport()?;
+
+    // the push was already found in the first request, so errors at this
+    // point are most likely temporary network issues. Watching continues, as
+    // the user would otherwise miss events.
+    let interval = Duration::from_secs(ppc_audit.interval);
+    Scheduler::new("poll-audit-log", interval).run(|| -> Result<ControlFlow<()>, PPCError> {
+        debug!("polling audit log of {} push {}", kind, url_token);
+        let log = pwpush_api::audit(transport, instance, kind, url_token)?;
+        for view in new_views(&mut seen, &log) {
+            if !filter.matches(view) {
+                continue;
+            }
+            output.print_view(view);
+            hooks.fire(webhooks, kind, url_token, view);
+        }
+        Ok(ControlFlow::Continue(()))
+    });
+    Ok(())
+}
+
+/// Value parser for `--ip`. A single address is a network of its own.
+pub fn parse_network(value: &str) -> Result<IpNet, String> {
+    if let Ok(address) = value.parse::<IpAddr>() {
+        return Ok(IpNet::from(address));
+    }
+    value.parse().map_err(|_| {
+        format!(
+            "`{}` is neither an address nor a network like 10.0.0.0/8",
+            value
+        )
+    })
+}
+
+/// The filters given on the command line. Without any, all events match.
+pub struct Filter {
+    since: Option<DateTime<Utc>>,
+    network: Option<IpNet>,
+    kinds: Vec<AuditKind>,
+}
+
+impl Filter {
+    pub fn new(ppc_audit: &PPCAudit, now: DateTime<Utc>) -> Self {
+        Self {
+            // spans beyond the range of chrono reach back before any event,
+            // so they filter nothing.
+            since: ppc_audit
+                .since
+                .and_then(|since| chrono::Duration::from_std(since).ok())
+                .and_then(|since| now.checked_sub_signed(since)),
+            network: ppc_audit.ip,
+            kinds: ppc_audit.kind.clone(),
+        }
+    }
+
+    /// Events without a valid time or address never match filters on them.
+    pub fn matches(&self, view: &AuditView) -> bool {
+        let since = self
+            .since
+            .is_none_or(|since| view.created().is_some_and(|created| created >= since));
+        let network = self
+            .network
+            .is_none_or(|network| view.ip_addr().is_some_and(|ip| network.contains(&ip)));
+        let kind = self.kinds.is_empty() || self.kinds.contains(&kind_of(view));
+        since && network && kind
+    }
+}
+
+/// The kind of an event. Servers report kinds as numbers or names, and older
+/// ones mark failed views only with `successful`.
+pub fn kind_of(view: &AuditView) -> AuditKind {
+    let name = view.kind_name();
+    if view.successful == Some(false) || name.starts_with("failed") {
+        AuditKind::FailedView
+    } else if name == "manual deletion" || name.starts_with("expire") {
+        AuditKind::Expire
+    } else {
+        AuditKind::View
+    }
+}
+
+/// Sort by time. Events without a valid time come first, the sort is stable
+/// so they keep their order.
+fn sort(log: &mut AuditLog, order: SortOrder) {
+    log.views.sort_by_key(|view| view.created());
+    if order == SortOrder::NewestFirst {
+        log.views.reverse();
+    }
+}
+
+/// Events of the log that are not in `seen` yet. These are added to `seen`, so
+/// every event is only returned once.
+fn new_views<'a>(seen: &mut HashSet<String>, log: &'a AuditLog) -> Vec<&'a AuditView> {
+    log.views
+        .iter()
+        .filter(|view| {
+            // serialization cannot fail, the view consists only of strings,
+            // bools and json values.
+            seen.insert(serde_json::to_string(view).unwrap())
+        })
+        .collect()
+}
+
+/// How events are printed.
+struct Output<'a> {
+    args: &'a PPCArgs,
+    format: Option<AuditFormat>,
+    source: Source<'a>,
+}
+
+impl Output<'_> {
+    fn print_log(&self, log: &AuditLog) -> Result<(), PPCError> {
+        // log management systems read the output, not people, so there is no
+        // pager and no message for an empty log.
+        if self.format.is_some() {
+            for view in &log.views {
+                self.print_view(view);
+            }
+            return Ok(());
+        }
+        if self.args.json_output {
+            output::print_json(log);
+            return Ok(());
+        }
+
+        if log.views.is_empty() {
+            println!("{}", i18n::text("audit-no-views"));
+            return Ok(());
+        }
+        let dates = DateFormatter::new(self.args);
+        let mut table = Table::new(vec![
+            Column::shrink_to(10),
+            Column::fixed(),
+            Column::fixed(),
+            Column::fixed(),
+            Column::shrink_to(12),
+        ]);
+        for view in &log.views {
+            table.row(cells(view, &dates).into());
+        }
+        pager::print(self.args, &table.render(table::width(self.args)))
+    }
+
+    fn print_view(&self, view: &AuditView) {
+        if let Some(format) = self.format {
+            println!("{}", siem::format(format, &self.source, view));
+        } else if self.args.json_output {
+            output::print_json_line(view);
+        } else {
+            // relative dates are computed anew for every event, as watching
+            // can run for a long time.
+            let line = format_view(view, &DateFormatter::new(self.args));
+            println!("{}", table::fit(&line, table::width(self.args)));
+        }
+    }
+}
+
+/// A single line per event, with the most relevant information first.
+pub fn format_view(view: &AuditView, dates: &DateFormatter) -> String {
+    cells(view, dates).join("  ")
+}
+
+/// The columns of an event in the log, see `table`.
+fn cells(view: &AuditView, dates: &DateFormatter) -> [String; 5] {
+    let outcome = match view.successful {
+        Some(false) => i18n::text("audit-failed"),
+        _ => i18n::text("audit-successful"),
+    };
+
+    [
+        dates.format(&view.created_at),
+        view.kind_name(),
+        outcome,
+        String::from(view.ip.as_deref().unwrap_or("-")),
+        String::from(view.user_agent.as_deref().unwrap_or("-")),
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use chrono::Utc;
+
+    use chrono::TimeZone;
+    use clap::Parser;
+
+    use super::{format_view, kind_of, new_views, parse_network, sort, Filter};
+    use crate::args::{AuditKind, PPCAction, PPCArgs, SortOrder};
+    use crate::dates::{DateFormat, DateFormatter};
+    use crate::models::AuditLog;
+
+    fn log(json: &str) -> AuditLog {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn new_views_only_once() {
+        let mut seen = HashSet::new();
+        let first = log(r#"{"views":[{"ip":"10.0.0.1","created_at":"2023-12-01T10:00:00Z"}]}"#);
+        let second = log(
+            r#"{"views":[{"ip":"10.0.0.1","created_at":"2023-12-01T10:00:00Z"},
+                         {"ip":"10.0.0.2","created_at":"2023-12-01T11:00:00Z"}]}"#,
+        );
+
+        assert_eq!(new_views(&mut seen, &first).len(), 1);
+
+        let views = new_views(&mut seen, &second);
+        assert_eq!(views.len(), 1);
+        assert_eq!(views[0].ip.as_deref(), Some("10.0.0.2"));
+
+        assert!(new_views(&mut seen, &second).is_empty());
+    }
+
+    fn filter(argv: &[&str]) -> Filter {
+        let argv = ["pwpush-cli", "audit"]
+            .iter()
+            .chain(argv)
+            .chain(&["text", "abc"])
+            .copied();
+        let PPCAction::Audit(ppc_audit) = PPCArgs::parse_from(argv).action else {
+            panic!("not an audit command");
+        };
+        Filter::new(
+            &ppc_audit,
+            Utc.with_ymd_and_hms(2023, 12, 2, 12, 0, 0).unwrap(),
+        )
+    }
+
+    fn events() -> AuditLog {
+        log(r#"{"views":[
+            {"ip":"10.1.2.3","created_at":"2023-12-02T10:00:00Z","kind":0},
+            {"ip":"192.168.0.7","created_at":"2023-11-20T10:00:00Z","successful":false},
+            {"ip":"10.9.9.9","created_at":"2023-12-02T11:00:00Z","kind":1},
+            {"created_at":"not a date","kind":"failed_passphrase"}
+        ]}"#)
+    }
+
+    fn matching(filter: &Filter) -> Vec<usize> {
+        let log = events();
+        (0..log.views.len())
+            .filter(|&i| filter.matches(&log.views[i]))
+            .collect()
+    }
+
+    #[test]
+    fn filters_by_time_network_and_kind() {
+        assert_eq!(matching(&filter(&[])), vec![0, 1, 2, 3]);
+        assert_eq!(matching(&filter(&["--since", "24h"])), vec![0, 2]);
+        assert_eq!(matching(&filter(&["--ip", "10.0.0.0/8"])), vec![0, 2]);
+        assert_eq!(matching(&filter(&["--ip", "192.168.0.7"])), vec![1]);
+        assert_eq!(
+            matching(&filter(&["--kind", "failed-view,expire"])),
+            vec![1, 2, 3]
+        );
+        assert_eq!(
+            matching(&filter(&["--since", "24h", "--kind", "view"])),
+            vec![0]
+        );
+    }
+
+    #[test]
+    fn huge_since_filters_nothing() {
+        assert_eq!(
+            matching(&filter(&["--since", "100000000w"])),
+            vec![0, 1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn kinds_of_old_and_new_servers() {
+        let log = events();
+        let kinds: Vec<_> = log.views.iter().map(kind_of).collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                AuditKind::View,
+                AuditKind::FailedView,
+                AuditKind::Expire,
+                AuditKind::FailedView
+            ]
+        );
+    }
+
+    #[test]
+    fn sorts_by_time() {
+        let mut log = events();
+        sort(&mut log, SortOrder::NewestFirst);
+
+        let ips: Vec<_> = log.views.iter().map(|view| view.ip.as_deref()).collect();
+        assert_eq!(
+            ips,
+            vec![
+                Some("10.9.9.9"),
+                Some("10.1.2.3"),
+                Some("192.168.0.7"),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn networks_and_addresses_are_accepted() {
+        assert!(parse_network("10.0.0.0/8").is_ok());
+        assert!(parse_network("2001:db8::1").is_ok());
+        assert!(parse_network("10.0.0.0/33").is_err());
+        assert!(parse_network("intranet").is_err());
+    }
+
+    #[test]
+    fn format_view_human() {
+        let log = log(
+            r#"{"views":[{"ip":"10.0.0.1","user_agent":"curl/8.0","successful":false,
+                          "created_at":"2023-12-01T10:00:00Z","kind":0}]}"#,
+        );
+
+        assert_eq!(
+            format_view(
+                &log.views[0],
+                &DateFormatter::with(DateFormat::Iso, Utc::now())
+            ),
+            "2023-12-01T10:00:00Z  view  failed  10.0.0.1  curl/8.0"
+        );
+    }
+}
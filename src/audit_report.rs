@@ -0,0 +1,328 @@
+//! Report over the audit logs of all active pushes, with `audit --all`.
+//!
+//! The API has no endpoint for this, so the active pushes of all three kinds
+//! are listed, and their audit logs fetched with `--jobs` requests at the same
+//! time, see `bulk::run_parallel`. Each push becomes one row of the report,
+//! with its views, failed views and last access. `--since`, `--ip` and
+//! `--kind` filter the events before they are counted, like for a single
+//! push.
+//!
+//! Addresses with several failed views of the same push are flagged as
+//! suspicious, as this is what guessing a passphrase looks like.
+//!
+//! A log that cannot be fetched does not stop the report. Its row carries the
+//! error instead, and the command fails at the end, see `fail_on`.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::args::{AuditKind, PPCArgs, PPCAudit, PushKind, PushState};
+use crate::audit::{self, Filter};
+use crate::dates::DateFormatter;
+use crate::errors::PPCError;
+use crate::http::Transport;
+use crate::i18n;
+use crate::instance::Instance;
+use crate::models::{AuditLog, Push};
+use crate::render::CommandOutput;
+use crate::table::{Column, Table};
+use crate::{bulk, pwpush_api};
+
+/// Failed views of a push from one address from which on it is suspicious.
+const SUSPICIOUS_FAILED_VIEWS: usize = 3;
+
+/// The audit log of one push, summarized.
+#[derive(Debug, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct AuditReportEntry {
+    pub kind: PushKind,
+    pub url_token: String,
+    pub note: Option<String>,
+    pub views: usize,
+    pub failed_views: usize,
+
+    /// Time of the latest view, successful or not
+    pub last_access: Option<String>,
+
+    /// Addresses with repeated failed views
+    pub suspicious_addresses: Vec<String>,
+
+    /// Why the audit log could not be fetched
+    pub error: Option<String>,
+}
+
+/// Result of `audit --all`.
+pub struct AuditReport {
+    pub entries: Vec<AuditReportEntry>,
+    dates: DateFormatter,
+}
+
+/// Entry point for `audit --all`.
+pub fn run(
+    transport: &dyn Transport,
+    instance: &Instance,
+    args: &PPCArgs,
+    ppc_audit: &PPCAudit,
+    now: DateTime<Utc>,
+) -> Result<AuditReport, PPCError> {
+    let mut pushes = Vec::new();
+    for kind in [PushKind::Text, PushKind::File, PushKind::Url] {
+        for push in pwpush_api::list(transport, instance, kind, PushState::Active)? {
+            pushes.push((kind, push));
+        }
+    }
+
+    let logs = bulk::run_parallel(&pushes, ppc_audit.jobs, |(kind, push)| {
+        pwpush_api::audit(transport, instance, *kind, &push.url_token)
+    });
+
+    let filter = Filter::new(ppc_audit, now);
+    let entries = pushes
+        .iter()
+        .zip(logs)
+        .map(|((kind, push), log)| entry(*kind, push, log, &filter))
+        .collect();
+    Ok(AuditReport {
+        entries,
+        dates: DateFormatter::at(args, now),
+    })
+}
+
+impl AuditReport {
+    /// Fails if any audit log could not be fetched, after the report was
+    /// printed.
+    pub fn result(&self) -> Result<(), PPCError> {
+        let failed = self.entries.iter().filter(|e| e.error.is_some()).count();
+        if failed == 0 {
+            return Ok(());
+        }
+        Err(PPCError::partial(format!(
+            "the audit logs of {} of {} pushes could not be fetched",
+            failed,
+            self.entries.len()
+        )))
+    }
+}
+
+impl CommandOutput for AuditReport {
+    fn json(&self) -> Value {
+        // the entries only consist of strings, numbers and lists of strings.
+        serde_json::to_value(&self.entries).unwrap()
+    }
+
+    fn human(&self, width: Option<usize>) -> String {
+        format_report(self, width)
+    }
+}
+
+fn entry(
+    kind: PushKind,
+    push: &Push,
+    log: Result<AuditLog, PPCError>,
+    filter: &Filter,
+) -> AuditReportEntry {
+    let mut entry = AuditReportEntry {
+        kind,
+        url_token: push.url_token.clone(),
+        note: push.note.clone(),
+        views: 0,
+        failed_views: 0,
+        last_access: None,
+        suspicious_addresses: Vec::new(),
+        error: None,
+    };
+    let log = match log {
+        Ok(log) => log,
+        Err(e) => {
+            entry.error = Some(e.to_string());
+            return entry;
+        }
+    };
+
+    let mut failures = BTreeMap::<&str, usize>::new();
+    let mut last_access = None;
+    for view in log.views.iter().filter(|view| filter.matches(view)) {
+        match audit::kind_of(view) {
+            AuditKind::View => entry.views += 1,
+            AuditKind::FailedView => {
+                entry.failed_views += 1;
+                if let Some(ip) = &view.ip {
+                    *failures.entry(ip).or_default() += 1;
+                }
+            }
+            AuditKind::Expire => continue,
+        }
+        // events without a valid time cannot be the last access.
+        if let Some(created) = view.created() {
+            if last_access.is_none_or(|(last, _)| created > last) {
+                last_access = Some((created, &view.created_at));
+            }
+        }
+    }
+
+    entry.last_access = last_access.map(|(_, created_at)| created_at.clone());
+    entry.suspicious_addresses = failures
+        .into_iter()
+        .filter(|(_, count)| *count >= SUSPICIOUS_FAILED_VIEWS)
+        .map(|(ip, _)| String::from(ip))
+        .collect();
+    entry
+}
+
+fn format_report(report: &AuditReport, width: Option<usize>) -> String {
+    if report.entries.is_empty() {
+        return format!("{}\n", i18n::text("audit-report-no-pushes"));
+    }
+
+    let mut table = Table::new(vec![
+        Column::fixed(),
+        Column::fixed(),
+        Column::fixed(),
+        Column::fixed(),
+        Column::shrink_to(12),
+    ]);
+    for entry in &report.entries {
+        let views = i18n::format(
+            "audit-report-views",
+            &[
+                ("views", entry.views.into()),
+                ("failed", entry.failed_views.into()),
+            ],
+        );
+        let last_access = match &entry.last_access {
+            Some(date) => i18n::format(
+                "audit-report-last-access",
+                &[("date", report.dates.format(date).into())],
+            ),
+            None => i18n::text("audit-report-never"),
+        };
+        let remark = match &entry.error {
+            Some(error) => error.clone(),
+            None if !entry.suspicious_addresses.is_empty() => i18n::format(
+                "audit-report-suspicious",
+                &[("addresses", entry.suspicious_addresses.join(", ").into())],
+            ),
+            None => String::new(),
+        };
+        table.row(vec![
+            entry.kind.to_string(),
+            entry.url_token.clone(),
+            views,
+            last_access,
+            remark,
+        ]);
+    }
+    table.render(width)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{DateTime, Utc};
+    use clap::Parser;
+
+    use super::{format_report, run, AuditReport};
+    use crate::args::{PPCAction, PPCArgs};
+    use crate::config::PPCConfig;
+    use crate::http::mock::MockTransport;
+    use crate::instance::Instance;
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-12-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn report(transport: &MockTransport, options: &[&str]) -> AuditReport {
+        let cmdline = [
+            "pwpush-cli",
+            "--email",
+            "user@example.com",
+            "--token",
+            "t0k3n",
+            "--date-format",
+            "iso",
+            "audit",
+            "--all",
+        ];
+        let args = PPCArgs::parse_from(cmdline.iter().chain(options));
+        let instance = Instance::resolve(&args, &PPCConfig::default()).unwrap();
+        let PPCAction::Audit(ppc_audit) = &args.action else {
+            panic!("not an audit");
+        };
+        run(transport, &instance, &args, ppc_audit, now()).unwrap()
+    }
+
+    const FAILED: &str =
+        r#"{"ip":"203.0.113.7","successful":false,"created_at":"2024-11-30T10:00:00Z"}"#;
+
+    #[test]
+    fn summarizes_the_logs_of_all_active_pushes() {
+        let log = format!(
+            r#"{{"views":[{{"ip":"198.51.100.1","created_at":"2024-11-29T10:00:00Z"}},{},{},{},{{"kind":1,"created_at":"2024-11-30T11:00:00Z"}}]}}"#,
+            FAILED, FAILED, FAILED
+        );
+        let transport = MockTransport::new()
+            .respond(200, r#"[{"url_token":"abc","note":"db"}]"#)
+            .respond(200, "[]")
+            .respond(200, "[]")
+            .respond(200, &log);
+
+        let report = report(&transport, &["--jobs", "1"]);
+
+        let entry = &report.entries[0];
+        assert_eq!(report.entries.len(), 1);
+        assert_eq!((entry.views, entry.failed_views), (1, 3));
+        assert_eq!(entry.last_access.as_deref(), Some("2024-11-30T10:00:00Z"));
+        assert_eq!(entry.suspicious_addresses, vec!["203.0.113.7"]);
+        assert!(report.result().is_ok());
+        assert_eq!(
+            format_report(&report, None),
+            "text  abc  1 views, 3 failed  last access 2024-11-30T10:00:00Z  \
+             repeated failed views from 203.0.113.7\n"
+        );
+    }
+
+    #[test]
+    fn filters_events_before_counting() {
+        let transport = MockTransport::new()
+            .respond(200, r#"[{"url_token":"abc"}]"#)
+            .respond(200, "[]")
+            .respond(200, "[]")
+            .respond(
+                200,
+                &format!(r#"{{"views":[{},{},{}]}}"#, FAILED, FAILED, FAILED),
+            );
+
+        let report = report(&transport, &["--ip", "10.0.0.0/8"]);
+
+        assert_eq!(report.entries[0].failed_views, 0);
+        assert!(report.entries[0].suspicious_addresses.is_empty());
+        assert_eq!(report.entries[0].last_access, None);
+    }
+
+    #[test]
+    fn failed_logs_are_reported_and_fail_at_the_end() {
+        let transport = MockTransport::new()
+            .respond(200, r#"[{"url_token":"abc"}]"#)
+            .respond(200, "[]")
+            .respond(200, "[]")
+            .respond(500, "");
+
+        let report = report(&transport, &[]);
+
+        assert!(report.entries[0].error.is_some());
+        let err = report.result().unwrap_err();
+        assert!(err.is_partial());
+        assert!(err
+            .to_string()
+            .contains("the audit logs of 1 of 1 pushes could not be fetched"));
+    }
+
+    #[test]
+    fn all_conflicts_with_watch() {
+        assert!(PPCArgs::try_parse_from(["pwpush-cli", "audit", "--all", "--watch"]).is_err());
+    }
+}
@@ -0,0 +1,170 @@
+//! Pushing the same secret to the instances of several profiles.
+//!
+//! Teams that mirror secrets across a primary and a disaster-recovery
+//! instance would otherwise have to push every secret twice, with the risk of
+//! the copies getting out of sync. `push text --to-profiles work,dr-site`
+//! creates the push on the instance of every given profile at the same time,
+//! with the same payload and options, and prints a link per profile.
+//!
+//! All profiles are resolved before anything is pushed, so a typo in a
+//! profile name does not leave the secret on only some of the instances. A
+//! failure on one instance does not stop the others, the command fails at the
+//! end if any push failed.
+
+use serde_json::json;
+
+use crate::args::{PPCArgs, PPCText, PushKind};
+use crate::bulk;
+use crate::config::PPCConfig;
+use crate::errors::PPCError;
+use crate::http::Transport;
+use crate::instance::Instance;
+use crate::models::Push;
+use crate::{history, hooks, output, platform, pwpush_api, share};
+
+/// Entry point for `push text --to-profiles`. The options must already be
+/// merged, including the payload.
+pub fn run(
+    transport: &dyn Transport,
+    args: &PPCArgs,
+    config: &PPCConfig,
+    ppc_text: &PPCText,
+    profiles: &[String],
+) -> Result<(), PPCError> {
+    let mut targets = Vec::new();
+    for name in profiles {
+        let mut instance = Instance::from_profile(config, name)?;
+        instance.detect_auth_scheme(transport);
+        targets.push((name.as_str(), instance));
+    }
+
+    let webhooks = platform::transport()?;
+    let results = push_to(transport, &targets, ppc_text);
+
+    let mut report = Vec::new();
+    for ((name, instance), result) in targets.iter().zip(&results) {
+        let (line, json) = match result {
+            Ok(push) => {
+                let url = share::url(transport, instance, PushKind::Text, push);
+                history::record(
+                    config,
+                    instance,
+                    push,
+                    ppc_text.note.as_deref(),
+                    &ppc_text.tags,
+                    &url,
+                );
+                hooks::notify_push(webhooks, ppc_text.notify_webhook.as_deref(), push, &url);
+                hooks::post_push(&config.hooks, PushKind::Text, push, &url);
+                (
+                    format!("{}: {}", name, url),
+                    json!({
+                        "profile": name,
+                        "instance": instance.base_url(),
+                        "url_token": push.url_token,
+                        "url": url,
+                    }),
+                )
+            }
+            Err(e) => (
+                format!("{}: failed: {}", name, e),
+                json!({
+                    "profile": name,
+                    "instance": instance.base_url(),
+                    "error": e.to_string(),
+                }),
+            ),
+        };
+        if args.json_output {
+            report.push(json);
+        } else {
+            println!("{}", line);
+        }
+    }
+    if args.json_output {
+        output::print_json_line(&report);
+    }
+
+    match results.iter().filter(|result| result.is_err()).count() {
+        0 => Ok(()),
+        failed => Err(PPCError::partial(format!(
+            "{} of {} profiles could not be pushed to",
+            failed,
+            targets.len()
+        ))),
+    }
+}
+
+/// Create the push on all instances at the same time.
+fn push_to(
+    transport: &dyn Transport,
+    targets: &[(&str, Instance)],
+    ppc_text: &PPCText,
+) -> Vec<Result<Push, PPCError>> {
+    bulk::run_parallel(targets, targets.len(), |(_, instance)| {
+        pwpush_api::create_text_push(transport, instance, ppc_text)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+
+    use super::push_to;
+    use crate::args::{InstanceProtocol, PPCAction, PPCArgs, PPCObject};
+    use crate::http::mock::MockTransport;
+    use crate::instance::Instance;
+
+    fn instance(url: &str) -> Instance {
+        Instance {
+            protocol: InstanceProtocol::Https,
+            url: String::from(url),
+            email: None,
+            token: None,
+            auth_scheme: None,
+        }
+    }
+
+    #[test]
+    fn pushes_to_every_instance() {
+        let args = PPCArgs::parse_from([
+            "pwpush-cli",
+            "push",
+            "text",
+            "secret",
+            "--to-profiles",
+            "work,dr-site",
+        ]);
+        let PPCAction::Push(PPCObject::Text(ppc_text)) = args.action else {
+            panic!("not a text push");
+        };
+        assert_eq!(ppc_text.to_profiles, ["work", "dr-site"]);
+
+        let transport = MockTransport::new()
+            .respond(201, r#"{"url_token":"abc"}"#)
+            .fail("connection refused");
+        let targets = [
+            ("work", instance("pwpush.example.com")),
+            ("dr-site", instance("pwpush-dr.example.com")),
+        ];
+
+        let results = push_to(&transport, &targets, &ppc_text);
+
+        // the order of the responses depends on the threads, but both
+        // instances were asked.
+        assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 1);
+        let mut urls = transport
+            .requests()
+            .iter()
+            .map(|request| request.url.clone())
+            .collect::<Vec<_>>();
+        urls.sort();
+        assert_eq!(
+            urls,
+            [
+                "https://pwpush-dr.example.com/p.json",
+                "https://pwpush.example.com/p.json"
+            ]
+        );
+    }
+}
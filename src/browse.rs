@@ -0,0 +1,404 @@
+//! Interactive browser for the pushes of the account.
+//!
+//! `browse` shows the active or expired pushes of one kind in a table, and
+//! offers keybindings for the common follow-up actions:
+//!   - `tab`: switch between active and expired pushes
+//!   - `up`/`down` or `k`/`j`: select a push
+//!   - `p`: preview the push, with its link and expiration
+//!   - `a`: show the audit log of the push
+//!   - `c`: copy the link of the push to the clipboard
+//!   - `e`: expire the push, after confirming with `y`
+//!   - `r`: reload the list
+//!   - `q` or `esc`: quit
+//!
+//! The link is copied with the OSC 52 escape sequence, which is understood by
+//! most terminal emulators, also over SSH, or `clip.exe` on Windows, see
+//! `terminal`. No clipboard library is needed.
+//!
+//! The browser is only available with the `tui` cargo feature, which pulls in
+//! ratatui and crossterm.
+
+use std::io;
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Paragraph, Row, Table, TableState, Tabs};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::args::{PPCArgs, PPCBrowse, PushKind, PushState};
+use crate::audit;
+use crate::dates::DateFormatter;
+use crate::errors::PPCError;
+use crate::http::Transport;
+use crate::info;
+use crate::instance::Instance;
+use crate::models::Push;
+use crate::pwpush_api;
+use crate::share;
+use crate::terminal;
+
+pub fn run(
+    transport: &dyn Transport,
+    instance: &Instance,
+    args: &PPCArgs,
+    ppc_browse: &PPCBrowse,
+) -> Result<(), PPCError> {
+    let mut app = App::new(ppc_browse.kind, DateFormatter::new(args));
+    app.reload(transport, instance);
+
+    let mut terminal = ratatui::try_init().map_err(io_err)?;
+    let result = event_loop(&mut terminal, &mut app, transport, instance);
+    ratatui::restore();
+    result
+}
+
+fn event_loop(
+    terminal: &mut DefaultTerminal,
+    app: &mut App,
+    transport: &dyn Transport,
+    instance: &Instance,
+) -> Result<(), PPCError> {
+    loop {
+        terminal.draw(|frame| draw(frame, app)).map_err(io_err)?;
+
+        let Event::Key(key) = event::read().map_err(io_err)? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        if app.handle_key(key.code, transport, instance) == Flow::Quit {
+            return Ok(());
+        }
+
+        if let Some(link) = app.clipboard.take() {
+            terminal::copy_to_clipboard(&link).map_err(io_err)?;
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Flow {
+    Continue,
+    Quit,
+}
+
+/// State of the browser. Keys are handled here, independent of the terminal,
+/// so the behavior can be tested.
+struct App {
+    kind: PushKind,
+    state: PushState,
+    pushes: Vec<Push>,
+    table: TableState,
+    dates: DateFormatter,
+    /// Preview or audit log of the selected push
+    detail: Vec<String>,
+    status: String,
+    confirm_expire: bool,
+    /// Link to copy to the clipboard after the key was handled
+    clipboard: Option<String>,
+}
+
+impl App {
+    fn new(kind: PushKind, dates: DateFormatter) -> Self {
+        Self {
+            kind,
+            state: PushState::Active,
+            pushes: Vec::new(),
+            table: TableState::default(),
+            dates,
+            detail: Vec::new(),
+            status: String::new(),
+            confirm_expire: false,
+            clipboard: None,
+        }
+    }
+
+    fn selected(&self) -> Option<&Push> {
+        self.table.selected().and_then(|i| self.pushes.get(i))
+    }
+
+    fn handle_key(&mut self, key: KeyCode, transport: &dyn Transport, instance: &Instance) -> Flow {
+        if self.confirm_expire {
+            self.confirm_expire = false;
+            if key == KeyCode::Char('y') {
+                self.expire(transport, instance);
+            } else {
+                self.status = String::from("not expired");
+            }
+            return Flow::Continue;
+        }
+
+        match key {
+            KeyCode::Char('q') | KeyCode::Esc => return Flow::Quit,
+            KeyCode::Tab => {
+                self.state = match self.state {
+                    PushState::Active => PushState::Expired,
+                    PushState::Expired => PushState::Active,
+                };
+                self.reload(transport, instance);
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.table.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.table.select_previous(),
+            KeyCode::Char('r') => self.reload(transport, instance),
+            KeyCode::Char('p') => self.preview(transport, instance),
+            KeyCode::Char('a') => self.audit(transport, instance),
+            KeyCode::Char('c') => {
+                if let Some(push) = self.selected() {
+                    self.clipboard = Some(share::url(transport, instance, self.kind, push));
+                    self.status = String::from("link copied to the clipboard");
+                }
+            }
+            KeyCode::Char('e') => match self.selected() {
+                Some(_) if self.state == PushState::Expired => {
+                    self.status = String::from("the push is already expired");
+                }
+                Some(push) => {
+                    self.status = format!("expire {}? [y/N]", push.url_token);
+                    self.confirm_expire = true;
+                }
+                None => {}
+            },
+            _ => {}
+        }
+        Flow::Continue
+    }
+
+    fn reload(&mut self, transport: &dyn Transport, instance: &Instance) {
+        self.detail.clear();
+        match pwpush_api::list(transport, instance, self.kind, self.state) {
+            Ok(pushes) => {
+                self.status = format!("{} {} pushes", pushes.len(), self.state);
+                self.pushes = pushes;
+            }
+            Err(e) => {
+                self.status = e.to_string();
+                self.pushes.clear();
+            }
+        }
+        self.table.select(if self.pushes.is_empty() {
+            None
+        } else {
+            Some(0)
+        });
+    }
+
+    fn preview(&mut self, transport: &dyn Transport, instance: &Instance) {
+        let Some(push) = self.selected() else {
+            return;
+        };
+        let mut detail = vec![format!(
+            "link:                 {}",
+            share::url(transport, instance, self.kind, push)
+        )];
+        detail.extend(
+            info::format_info(push, &self.dates)
+                .lines()
+                .map(String::from),
+        );
+        self.detail = detail;
+    }
+
+    fn audit(&mut self, transport: &dyn Transport, instance: &Instance) {
+        let Some(push) = self.selected() else {
+            return;
+        };
+        match pwpush_api::audit(transport, instance, self.kind, &push.url_token) {
+            Ok(log) if log.views.is_empty() => {
+                self.detail = vec![String::from("no views recorded")];
+            }
+            Ok(log) => {
+                self.detail = log
+                    .views
+                    .iter()
+                    .map(|view| audit::format_view(view, &self.dates))
+                    .collect();
+            }
+            Err(e) => self.status = e.to_string(),
+        }
+    }
+
+    fn expire(&mut self, transport: &dyn Transport, instance: &Instance) {
+        let Some(url_token) = self.selected().map(|push| push.url_token.clone()) else {
+            return;
+        };
+        match pwpush_api::expire_push(transport, instance, self.kind, &url_token) {
+            Ok(()) => {
+                self.reload(transport, instance);
+                self.status = format!("expired {}", url_token);
+            }
+            Err(e) => self.status = e.to_string(),
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, app: &mut App) {
+    let [tabs_area, table_area, detail_area, status_area] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(5),
+        Constraint::Length(10),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let selected_tab = match app.state {
+        PushState::Active => 0,
+        PushState::Expired => 1,
+    };
+    frame.render_widget(
+        Tabs::new(["active", "expired"]).select(selected_tab),
+        tabs_area,
+    );
+
+    let rows = app.pushes.iter().map(|push| {
+        Row::new([
+            push.url_token.clone(),
+            app.dates.format(push.created_at.as_deref().unwrap_or("-")),
+            info::countdown(push, &app.dates),
+            push.note.clone().unwrap_or_default(),
+        ])
+    });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(24),
+            Constraint::Length(20),
+            Constraint::Length(36),
+            Constraint::Fill(1),
+        ],
+    )
+    .header(
+        Row::new(["token", "created", "expiration", "note"])
+            .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!("{} pushes", app.kind)),
+    )
+    .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(table, table_area, &mut app.table);
+
+    let detail = app.detail.iter().map(|line| Line::from(line.as_str()));
+    frame.render_widget(
+        Paragraph::new(detail.collect::<Vec<_>>()).block(Block::default().borders(Borders::ALL)),
+        detail_area,
+    );
+
+    let help =
+        "tab: active/expired  p: preview  a: audit  c: copy link  e: expire  r: reload  q: quit";
+    let status = if app.status.is_empty() {
+        String::from(help)
+    } else {
+        format!("{}  |  {}", app.status, help)
+    };
+    frame.render_widget(Paragraph::new(status), status_area);
+}
+
+fn io_err(e: io::Error) -> PPCError {
+    PPCError::from(format!("terminal error: {}", e))
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Utc;
+    use clap::Parser;
+    use ratatui::backend::TestBackend;
+    use ratatui::crossterm::event::KeyCode;
+    use ratatui::Terminal;
+
+    use super::{draw, App, Flow};
+    use crate::args::{PPCArgs, PushKind, PushState};
+    use crate::config::PPCConfig;
+    use crate::dates::{DateFormat, DateFormatter};
+    use crate::http::mock::MockTransport;
+    use crate::http::Method;
+    use crate::instance::Instance;
+
+    fn setup() -> (App, Instance) {
+        let args = PPCArgs::parse_from(["pwpush-cli", "browse"]);
+        let instance = Instance::resolve(&args, &PPCConfig::default()).unwrap();
+        let dates = DateFormatter::with(DateFormat::Iso, Utc::now());
+        (App::new(PushKind::Text, dates), instance)
+    }
+
+    #[test]
+    fn switch_lists() {
+        let (mut app, instance) = setup();
+        let transport = MockTransport::new()
+            .respond(200, r#"[{"url_token":"a"},{"url_token":"b"}]"#)
+            .respond(200, r#"[{"url_token":"c","expired":true}]"#);
+
+        app.reload(&transport, &instance);
+        app.handle_key(KeyCode::Down, &transport, &instance);
+        assert_eq!(app.selected().unwrap().url_token, "b");
+
+        app.handle_key(KeyCode::Tab, &transport, &instance);
+        assert_eq!(app.state, PushState::Expired);
+        assert_eq!(app.selected().unwrap().url_token, "c");
+        assert_eq!(
+            transport.requests()[1].url,
+            "https://pwpush.com/p/expired.json"
+        );
+
+        assert_eq!(
+            app.handle_key(KeyCode::Char('q'), &transport, &instance),
+            Flow::Quit
+        );
+    }
+
+    #[test]
+    fn expire_needs_confirmation() {
+        let (mut app, instance) = setup();
+        let transport = MockTransport::new()
+            .respond(200, r#"[{"url_token":"a"}]"#)
+            .respond(200, r#"{"expired":true}"#)
+            .respond(200, "[]");
+
+        app.reload(&transport, &instance);
+        app.handle_key(KeyCode::Char('e'), &transport, &instance);
+        app.handle_key(KeyCode::Char('n'), &transport, &instance);
+        assert_eq!(transport.requests().len(), 1);
+        assert_eq!(app.status, "not expired");
+
+        app.handle_key(KeyCode::Char('e'), &transport, &instance);
+        app.handle_key(KeyCode::Char('y'), &transport, &instance);
+        let requests = transport.requests();
+        assert_eq!(requests[1].method, Method::Delete);
+        assert_eq!(requests[1].url, "https://pwpush.com/p/a.json");
+        assert!(app.pushes.is_empty());
+        assert_eq!(app.status, "expired a");
+    }
+
+    #[test]
+    fn copy_and_audit() {
+        let (mut app, instance) = setup();
+        let transport = MockTransport::new()
+            .respond(200, r#"[{"url_token":"a"}]"#)
+            .respond(200, r#"{"url":"https://pwpush.com/p/a"}"#)
+            .respond(200, r#"{"views":[]}"#);
+
+        app.reload(&transport, &instance);
+        app.handle_key(KeyCode::Char('c'), &transport, &instance);
+        assert_eq!(app.clipboard.as_deref(), Some("https://pwpush.com/p/a"));
+
+        app.handle_key(KeyCode::Char('a'), &transport, &instance);
+        assert_eq!(app.detail, ["no views recorded"]);
+    }
+
+    #[test]
+    fn renders_table() {
+        let (mut app, instance) = setup();
+        let transport = MockTransport::new().respond(200, r#"[{"url_token":"tok3n","note":"db"}]"#);
+        app.reload(&transport, &instance);
+
+        let mut terminal = Terminal::new(TestBackend::new(120, 20)).unwrap();
+        terminal.draw(|frame| draw(frame, &mut app)).unwrap();
+
+        let screen = format!("{:?}", terminal.backend().buffer());
+        assert!(screen.contains("tok3n"));
+        assert!(screen.contains("1 active pushes"));
+    }
+}
@@ -0,0 +1,196 @@
+//! Operations on many pushes at once.
+//!
+//! Bulk operations read their URL tokens from a file and process them with a
+//! bounded number of worker threads. Every token is reported individually, and
+//! a failure of one token does not stop the others. The command only fails at
+//! the end, if any token failed, so scripts can rely on the exit code.
+//!
+//! Instead of a file, `expire bulk --tag` takes the tokens of the pushes with
+//! the tags from the local history, see `history`.
+//!
+//! If tokens fail or the run is interrupted, the tokens that were not expired
+//! are kept as a job, which `bulk resume` continues, see `jobs`.
+
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::args::{PPCArgs, PPCExpireBulk};
+use crate::config::PPCConfig;
+use crate::errors::PPCError;
+use crate::http::Transport;
+use crate::instance::Instance;
+use crate::{history, i18n, jobs, paths, prompt};
+
+/// Number of requests that are sent at the same time, unless given with
+/// `--jobs`.
+pub const DEFAULT_JOBS: usize = 4;
+
+/// Entry point for `expire bulk`.
+pub fn expire(
+    transport: &dyn Transport,
+    instance: &Instance,
+    args: &PPCArgs,
+    config: &PPCConfig,
+    ppc_bulk: &PPCExpireBulk,
+) -> Result<(), PPCError> {
+    let (tokens, none) = match &ppc_bulk.from_file {
+        Some(path) => (
+            read_tokens(path)?,
+            i18n::format(
+                "bulk-none-in-file",
+                &[("path", path.display().to_string().into())],
+            ),
+        ),
+        None => (
+            history::tagged_tokens(instance, ppc_bulk.kind, &ppc_bulk.tags)?,
+            i18n::format(
+                "bulk-none-tagged",
+                &[("tags", ppc_bulk.tags.join(", ").into())],
+            ),
+        ),
+    };
+    if tokens.is_empty() {
+        eprintln!("{}", none);
+        return Ok(());
+    }
+
+    let question = i18n::format(
+        "bulk-question",
+        &[
+            ("count", tokens.len().into()),
+            ("kind", ppc_bulk.kind.to_string().into()),
+            ("url", instance.url.as_str().into()),
+        ],
+    );
+    if !prompt::confirm(&question, args.assume_yes)? {
+        eprintln!("{}", i18n::text("expire-aborted"));
+        return Ok(());
+    }
+
+    let items = tokens.into_iter().map(jobs::Item::new).collect();
+    let mut job = jobs::Job::new(
+        instance,
+        jobs::Operation::Expire {
+            kind: ppc_bulk.kind,
+        },
+        items,
+    );
+    let store = jobs::Jobs::open(&jobs::default_dir()?, &paths::key_dir()?)?;
+    jobs::execute(
+        transport,
+        instance,
+        args,
+        config,
+        &store,
+        &mut job,
+        ppc_bulk.jobs,
+    )
+}
+
+/// URL tokens from a file with one token per line.
+fn read_tokens(path: &Path) -> Result<Vec<String>, PPCError> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| PPCError::from(format!("could not read {}: {}", path.display(), e)))?;
+
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect())
+}
+
+/// Apply `f` to all items with at most `jobs` threads, and return the results
+/// in the order of the items.
+pub fn run_parallel<T, R, F>(items: &[T], jobs: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new((0..items.len()).map(|_| None).collect::<Vec<_>>());
+
+    thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, items.len().max(1)) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(index) else {
+                    break;
+                };
+                let result = f(item);
+                results.lock().unwrap()[index] = Some(result);
+            });
+        }
+    });
+
+    // every index below the number of items was claimed by exactly one
+    // worker, so all results are present.
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(Option::unwrap)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use clap::Parser;
+
+    use super::{read_tokens, run_parallel};
+    use crate::args::{PPCArgs, PushKind};
+    use crate::config::PPCConfig;
+    use crate::http::mock::MockTransport;
+    use crate::instance::Instance;
+    use crate::pwpush_api;
+
+    #[test]
+    fn read_tokens_skips_comments() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tokens.txt");
+        fs::write(&path, "# handoff\nabc\n\n  def  \n").unwrap();
+
+        assert_eq!(read_tokens(&path).unwrap(), vec!["abc", "def"]);
+    }
+
+    #[test]
+    fn run_parallel_keeps_order_and_bounds_threads() {
+        let items = (0..20).collect::<Vec<_>>();
+        let running = AtomicUsize::new(0);
+        let most = AtomicUsize::new(0);
+
+        let results = run_parallel(&items, 3, |item| {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            most.fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(std::time::Duration::from_millis(2));
+            running.fetch_sub(1, Ordering::SeqCst);
+            item * 2
+        });
+
+        assert_eq!(results, (0..20).map(|i| i * 2).collect::<Vec<_>>());
+        assert!(most.load(Ordering::SeqCst) <= 3);
+    }
+
+    #[test]
+    fn failures_are_reported_per_token() {
+        let args = PPCArgs::parse_from(["pwpush-cli", "summary"]);
+        let instance = Instance::resolve(&args, &PPCConfig::default()).unwrap();
+        let transport = MockTransport::new()
+            .respond(200, r#"{"expired":true}"#)
+            .respond(404, "{}");
+
+        let results = run_parallel(&["abc", "def"], 1, |url_token| {
+            pwpush_api::expire_push(&transport, &instance, PushKind::Text, url_token)
+        });
+
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+    }
+}
@@ -0,0 +1,283 @@
+//! Conditional requests for endpoints that are polled.
+//!
+//! `audit --watch` and `list --watch` fetch the same URL again and again, and
+//! most of the time nothing changed in between. Responses that come with an
+//! `ETag` or `Last-Modified` header are kept in memory, and the next request
+//! to the same URL asks the instance with `If-None-Match` or
+//! `If-Modified-Since` whether it changed. If the instance answers with
+//! `304 Not Modified`, the kept response is used, and the body does not have
+//! to be downloaded and rendered again by the instance.
+//!
+//! Only requests marked as cacheable take part, so responses with payloads are
+//! never kept. Usually, the cache only lives as long as the process. With
+//! `--if-changed`, it is kept in the cache directory of the platform, e.g.
+//! `~/.cache/pwpush-cli` on Linux (see `paths`), between runs, so that scripts which call `info` or `list` repeatedly get the same
+//! benefit, and `unchanged` is printed instead of the same result again. The
+//! kept responses contain notes, so the file is encrypted, see `state`.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chacha20poly1305::XChaCha20Poly1305;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+use zeroize::Zeroizing;
+
+use crate::errors::PPCError;
+use crate::http::{Method, PPCRequest, PPCResponse, Transport};
+use crate::redact::sanitize_url;
+use crate::{i18n, paths, state};
+
+const NOT_MODIFIED: u16 = 304;
+const KEY_FILE: &str = "cache.key";
+const CACHE_FILE: &str = "responses";
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    response: PPCResponse,
+}
+
+/// The encrypted copy of the cache on disk.
+struct Store {
+    path: PathBuf,
+    cipher: XChaCha20Poly1305,
+}
+
+impl Store {
+    fn open(dir: &Path, keys: &Path) -> Result<Self, PPCError> {
+        state::create_private_dir(dir)?;
+
+        Ok(Self {
+            path: dir.join(CACHE_FILE),
+            cipher: state::cipher(&state::key_file(keys, dir, KEY_FILE)?)?,
+        })
+    }
+
+    fn load(&self) -> Result<HashMap<String, Entry>, PPCError> {
+        if !self.path.exists() {
+            return Ok(HashMap::new());
+        }
+        let content = fs::read(&self.path).map_err(|e| {
+            PPCError::from(format!("could not read {}: {}", self.path.display(), e))
+        })?;
+        let plaintext = state::unseal(&self.cipher, &content)
+            .map_err(|e| PPCError::from(format!("{}: {}", self.path.display(), e)))?;
+
+        serde_json::from_slice(&plaintext)
+            .map_err(|e| PPCError::from(format!("could not parse {}: {}", self.path.display(), e)))
+    }
+
+    fn save(&self, entries: &HashMap<String, Entry>) -> Result<(), PPCError> {
+        // the entries only consist of strings and numbers, so serialization
+        // cannot fail.
+        let plaintext = Zeroizing::new(serde_json::to_vec(entries).unwrap());
+        state::replace_private(&self.path, &state::seal(&self.cipher, &plaintext)?)
+    }
+}
+
+/// Default location of the cache that is kept between runs.
+pub fn default_dir() -> Result<PathBuf, PPCError> {
+    paths::cache_dir()
+}
+
+/// Wraps another transport and turns repeated requests to cacheable
+/// endpoints into conditional requests.
+pub struct CachingTransport<T: Transport> {
+    inner: T,
+    entries: Mutex<HashMap<String, Entry>>,
+    store: Option<Store>,
+}
+
+impl<T: Transport> CachingTransport<T> {
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            entries: Mutex::new(HashMap::new()),
+            store: None,
+        }
+    }
+
+    /// A cache that is kept in the given directory between runs, with its key
+    /// in the key directory `keys`. A cache that cannot be read is started
+    /// over, it only saves requests.
+    pub fn persistent(inner: T, dir: &Path, keys: &Path) -> Result<Self, PPCError> {
+        let store = Store::open(dir, keys)?;
+        let entries = store.load().unwrap_or_else(|e| {
+            warn!(
+                "{}",
+                i18n::format("cache-reset", &[("error", e.to_string().into())])
+            );
+            HashMap::new()
+        });
+
+        Ok(Self {
+            inner,
+            entries: Mutex::new(entries),
+            store: Some(store),
+        })
+    }
+
+    fn save(&self, entries: &HashMap<String, Entry>) {
+        if let Some(store) = &self.store {
+            if let Err(e) = store.save(entries) {
+                warn!(
+                    "{}",
+                    i18n::format("cache-not-saved", &[("error", e.to_string().into())])
+                );
+            }
+        }
+    }
+}
+
+impl<T: Transport> Transport for CachingTransport<T> {
+    fn send(&self, request: &PPCRequest) -> Result<PPCResponse, PPCError> {
+        if !request.cacheable || request.method != Method::Get {
+            return self.inner.send(request);
+        }
+
+        let mut conditional = request.clone();
+        if let Some(entry) = self.entries.lock().unwrap().get(&request.url) {
+            if let Some(etag) = &entry.etag {
+                conditional = conditional.header("If-None-Match", etag);
+            }
+            if let Some(last_modified) = &entry.last_modified {
+                conditional = conditional.header("If-Modified-Since", last_modified);
+            }
+        }
+
+        let response = self.inner.send(&conditional)?;
+
+        let mut entries = self.entries.lock().unwrap();
+        if response.status == NOT_MODIFIED {
+            if let Some(entry) = entries.get(&request.url) {
+                debug!(
+                    "{} not modified, using cached response",
+                    sanitize_url(&request.url)
+                );
+                let mut response = entry.response.clone();
+                response.not_modified = true;
+                return Ok(response);
+            }
+        }
+
+        let etag = response.header("ETag").map(String::from);
+        let last_modified = response.header("Last-Modified").map(String::from);
+        if response.is_success() && (etag.is_some() || last_modified.is_some()) {
+            entries.insert(
+                request.url.clone(),
+                Entry {
+                    etag,
+                    last_modified,
+                    response: response.clone(),
+                },
+            );
+        } else if entries.remove(&request.url).is_none() {
+            return Ok(response);
+        }
+        self.save(&entries);
+
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CachingTransport;
+    use crate::http::mock::MockTransport;
+    use crate::http::{Method, PPCRequest, Transport};
+
+    fn request() -> PPCRequest {
+        PPCRequest::new(
+            Method::Get,
+            String::from("https://pwpush.com/p/abc/audit.json"),
+        )
+        .cacheable()
+    }
+
+    #[test]
+    fn not_modified_uses_cached_response() {
+        let mock = MockTransport::new()
+            .respond_with_headers(200, &[("ETag", "\"v1\"")], r#"{"views":[]}"#)
+            .respond(304, "");
+        let cache = CachingTransport::new(mock);
+
+        cache.send(&request()).unwrap();
+        let response = cache.send(&request()).unwrap();
+
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, r#"{"views":[]}"#);
+        let requests = cache.inner.requests();
+        assert!(requests[0].headers.is_empty());
+        assert_eq!(
+            requests[1].headers,
+            [(String::from("If-None-Match"), String::from("\"v1\""))]
+        );
+    }
+
+    #[test]
+    fn changed_response_replaces_cache() {
+        let mock = MockTransport::new()
+            .respond_with_headers(
+                200,
+                &[("Last-Modified", "Fri, 01 Dec 2023 10:00:00 GMT")],
+                "1",
+            )
+            .respond_with_headers(
+                200,
+                &[("Last-Modified", "Fri, 01 Dec 2023 11:00:00 GMT")],
+                "2",
+            )
+            .respond(304, "");
+        let cache = CachingTransport::new(mock);
+
+        cache.send(&request()).unwrap();
+        cache.send(&request()).unwrap();
+        let response = cache.send(&request()).unwrap();
+
+        assert_eq!(response.body, "2");
+        assert_eq!(
+            cache.inner.requests()[2].headers[0].1,
+            "Fri, 01 Dec 2023 11:00:00 GMT"
+        );
+    }
+
+    #[test]
+    fn other_requests_are_not_cached() {
+        let mock = MockTransport::new()
+            .respond_with_headers(200, &[("ETag", "\"v1\"")], "secret")
+            .respond_with_headers(200, &[("ETag", "\"v1\"")], "secret");
+        let cache = CachingTransport::new(mock);
+        let retrieve = PPCRequest::new(Method::Get, String::from("https://pwpush.com/p/abc.json"));
+
+        cache.send(&retrieve).unwrap();
+        cache.send(&retrieve).unwrap();
+
+        assert!(cache.inner.requests()[1].headers.is_empty());
+        assert!(cache.entries.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn persistent_cache_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let keys = tempfile::tempdir().unwrap();
+        let mock = MockTransport::new().respond_with_headers(200, &[("ETag", "\"v1\"")], "1");
+        let cache = CachingTransport::persistent(mock, dir.path(), keys.path()).unwrap();
+        let response = cache.send(&request()).unwrap();
+        assert!(!response.not_modified);
+
+        let mock = MockTransport::new().respond(304, "");
+        let cache = CachingTransport::persistent(mock, dir.path(), keys.path()).unwrap();
+        let response = cache.send(&request()).unwrap();
+
+        assert!(response.not_modified);
+        assert_eq!(response.body, "1");
+        assert_eq!(
+            cache.inner.requests()[0].headers,
+            [(String::from("If-None-Match"), String::from("\"v1\""))]
+        );
+    }
+}
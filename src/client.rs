@@ -0,0 +1,477 @@
+//! Pushes for Rust programs that use this crate as library.
+//!
+//! The CLI assembles its pushes from the command line, see `args`. Other
+//! programs create them with a builder instead, and send them with a client
+//! for an instance:
+//!
+//! ```no_run
+//! use pwpush_cli::client::{Client, TextPush};
+//!
+//! let client = Client::new("pwpush.com")?;
+//! let push = TextPush::builder()
+//!     .payload("s3cret")
+//!     .expire_days(3)
+//!     .passphrase("correct horse")
+//!     .send(&client)?;
+//! println!("{}", client.url(&push));
+//! # Ok::<(), pwpush_cli::errors::PPCError>(())
+//! ```
+//!
+//! The payload is required at compile time, `send` only exists once it was
+//! given:
+//!
+//! ```compile_fail
+//! use pwpush_cli::client::{Client, TextPush};
+//!
+//! let client = Client::new("pwpush.com")?;
+//! TextPush::builder().expire_days(3).send(&client)?;
+//! # Ok::<(), pwpush_cli::errors::PPCError>(())
+//! ```
+//!
+//! The options are checked before anything is sent, so limits the instance
+//! would reject fail with an error that names them.
+//!
+//! `Client` and `send` block until the instance answered, and are behind the
+//! `blocking` feature. With the `async` feature, `AsyncClient` and
+//! `send_async` are the same for programs with a tokio runtime:
+//!
+//! ```ignore
+//! let client = AsyncClient::new("pwpush.com")?;
+//! let push = TextPush::builder().payload("s3cret").send_async(&client).await?;
+//! println!("{}", client.url(&push).await);
+//! ```
+//!
+//! Both build their requests and parse the answers with the same functions of
+//! `pwpush_api` as the CLI, only the transport differs. Programs with a
+//! transport of their own can do the same with `TextPushBuilder::request` and
+//! `pwpush_api::parse_push`.
+//!
+//! Without default features, the library also builds for
+//! `wasm32-unknown-unknown`. Web and Tauri frontends then send the request of
+//! `TextPushBuilder::request` with `fetch` and parse the answer with
+//! `pwpush_api::parse_push`, as the clients need a native target.
+
+use secrecy::SecretString;
+#[cfg(any(feature = "blocking", feature = "async"))]
+use zeroize::Zeroize;
+
+use crate::args::PPCText;
+#[cfg(any(feature = "blocking", feature = "async"))]
+use crate::args::PushKind;
+use crate::errors::PPCError;
+use crate::expire_at::MAX_DAYS;
+use crate::http::PPCRequest;
+#[cfg(feature = "async")]
+use crate::http::{AsyncTransport, ReqwestAsyncTransport};
+#[cfg(feature = "blocking")]
+use crate::http::{ReqwestTransport, Transport};
+use crate::instance::Instance;
+#[cfg(any(feature = "blocking", feature = "async"))]
+use crate::models::Push;
+#[cfg(feature = "blocking")]
+use crate::redirect::RedirectTransport;
+#[cfg(any(feature = "blocking", feature = "async"))]
+use crate::share;
+#[cfg(feature = "blocking")]
+use crate::user_agent::UserAgentTransport;
+use crate::{dedupe, pwpush_api};
+
+#[cfg(all(target_arch = "wasm32", any(feature = "blocking", feature = "async")))]
+compile_error!("the clients need a native target, build for WebAssembly without default features");
+
+/// Most views instances accept, as Password Pusher limits them by default.
+pub const MAX_VIEWS: u32 = 100;
+
+/// A Password Pusher instance together with the transport that reaches it.
+/// The transport can be replaced, e.g. to record requests in tests.
+#[cfg(feature = "blocking")]
+pub struct Client<T: Transport = Box<dyn Transport>> {
+    transport: T,
+    instance: Instance,
+}
+
+#[cfg(feature = "blocking")]
+impl Client {
+    /// An anonymous client for the instance at `url`, e.g. `pwpush.com` or
+    /// `https://pwpush.example.com`.
+    pub fn new(url: &str) -> Result<Self, PPCError> {
+        Ok(Self::with_transport(
+            Instance::new(url, None, None)?,
+            default_transport(),
+        ))
+    }
+
+    /// A client that authenticates with the API token of an account, and its
+    /// email on servers that need both.
+    pub fn authenticated(
+        url: &str,
+        email: Option<String>,
+        token: SecretString,
+    ) -> Result<Self, PPCError> {
+        Ok(Self::with_transport(
+            Instance::new(url, email, Some(token))?,
+            default_transport(),
+        ))
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<T: Transport> Client<T> {
+    pub fn with_transport(instance: Instance, transport: T) -> Self {
+        Self {
+            transport,
+            instance,
+        }
+    }
+
+    pub fn instance(&self) -> &Instance {
+        &self.instance
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// The link to share for a text push, see `share::url`.
+    pub fn url(&self, push: &Push) -> String {
+        share::url(&self.transport, &self.instance, PushKind::Text, push)
+    }
+}
+
+/// The transport of the CLI without its options: redirects are only
+/// followed within the instance, and the User-Agent names this crate.
+#[cfg(feature = "blocking")]
+fn default_transport() -> Box<dyn Transport> {
+    Box::new(UserAgentTransport::new(
+        RedirectTransport::new(ReqwestTransport::without_redirects()),
+        None,
+    ))
+}
+
+/// Like `Client`, for programs with a tokio runtime.
+#[cfg(feature = "async")]
+pub struct AsyncClient<T: AsyncTransport = ReqwestAsyncTransport> {
+    transport: T,
+    instance: Instance,
+}
+
+#[cfg(feature = "async")]
+impl AsyncClient {
+    /// An anonymous client for the instance at `url`, see `Client::new`.
+    pub fn new(url: &str) -> Result<Self, PPCError> {
+        Ok(Self::with_transport(
+            Instance::new(url, None, None)?,
+            ReqwestAsyncTransport::new(),
+        ))
+    }
+
+    /// A client with credentials, see `Client::authenticated`.
+    pub fn authenticated(
+        url: &str,
+        email: Option<String>,
+        token: SecretString,
+    ) -> Result<Self, PPCError> {
+        Ok(Self::with_transport(
+            Instance::new(url, email, Some(token))?,
+            ReqwestAsyncTransport::new(),
+        ))
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T: AsyncTransport> AsyncClient<T> {
+    pub fn with_transport(instance: Instance, transport: T) -> Self {
+        Self {
+            transport,
+            instance,
+        }
+    }
+
+    pub fn instance(&self) -> &Instance {
+        &self.instance
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// The link to share for a text push, see `share::url`.
+    pub async fn url(&self, push: &Push) -> String {
+        let request = pwpush_api::preview_request(&self.instance, PushKind::Text, &push.url_token);
+        let preview = self
+            .transport
+            .send(&request)
+            .await
+            .and_then(pwpush_api::parse_preview);
+        share::from_preview(&self.instance, PushKind::Text, push, preview)
+    }
+}
+
+/// A text push, created with `TextPush::builder`.
+pub struct TextPush;
+
+impl TextPush {
+    pub fn builder() -> TextPushBuilder<NoPayload> {
+        TextPushBuilder {
+            payload: NoPayload,
+            passphrase: None,
+            note: None,
+            expire_days: None,
+            expire_views: None,
+            deletable_by_viewer: None,
+            retrieval_step: None,
+        }
+    }
+}
+
+/// State of a builder before the payload is given.
+#[derive(Debug)]
+pub struct NoPayload;
+
+/// State of a builder with its payload, which can be sent.
+#[derive(Debug)]
+pub struct WithPayload(SecretString);
+
+/// Options of a text push. Options that are not set are left to the
+/// instance, like the CLI does without them.
+#[derive(Debug)]
+pub struct TextPushBuilder<P> {
+    payload: P,
+    passphrase: Option<SecretString>,
+    note: Option<String>,
+    expire_days: Option<u32>,
+    expire_views: Option<u32>,
+    deletable_by_viewer: Option<bool>,
+    retrieval_step: Option<bool>,
+}
+
+impl TextPushBuilder<NoPayload> {
+    pub fn payload(self, payload: impl Into<SecretString>) -> TextPushBuilder<WithPayload> {
+        TextPushBuilder {
+            payload: WithPayload(payload.into()),
+            passphrase: self.passphrase,
+            note: self.note,
+            expire_days: self.expire_days,
+            expire_views: self.expire_views,
+            deletable_by_viewer: self.deletable_by_viewer,
+            retrieval_step: self.retrieval_step,
+        }
+    }
+}
+
+impl<P> TextPushBuilder<P> {
+    /// Passphrase the recipient has to enter to see the payload.
+    pub fn passphrase(mut self, passphrase: impl Into<SecretString>) -> Self {
+        self.passphrase = Some(passphrase.into());
+        self
+    }
+
+    /// Note for the creator, only stored for authenticated clients.
+    pub fn note(mut self, note: impl Into<String>) -> Self {
+        self.note = Some(note.into());
+        self
+    }
+
+    /// Expire after this many days, from 1 to 90.
+    pub fn expire_days(mut self, days: u32) -> Self {
+        self.expire_days = Some(days);
+        self
+    }
+
+    /// Expire after this many views, from 1 to `MAX_VIEWS`.
+    pub fn expire_views(mut self, views: u32) -> Self {
+        self.expire_views = Some(views);
+        self
+    }
+
+    pub fn deletable_by_viewer(mut self, deletable: bool) -> Self {
+        self.deletable_by_viewer = Some(deletable);
+        self
+    }
+
+    /// Show an extra page before the payload, so link scanners do not use up
+    /// views.
+    pub fn retrieval_step(mut self, retrieval_step: bool) -> Self {
+        self.retrieval_step = Some(retrieval_step);
+        self
+    }
+}
+
+impl TextPushBuilder<WithPayload> {
+    /// Create the push on the instance of the client.
+    #[cfg(feature = "blocking")]
+    pub fn send<T: Transport>(self, client: &Client<T>) -> Result<Push, PPCError> {
+        let mut request = self.request(&client.instance)?;
+        let response = client.transport.send(&request);
+        // the body contains the payload, see `pwpush_api::send_text_push`.
+        request.body.zeroize();
+        pwpush_api::parse_push(response?)
+    }
+
+    /// Create the push on the instance of the client, see `send`.
+    #[cfg(feature = "async")]
+    pub async fn send_async<T: AsyncTransport>(
+        self,
+        client: &AsyncClient<T>,
+    ) -> Result<Push, PPCError> {
+        let mut request = self.request(&client.instance)?;
+        let response = client.transport.send(&request).await;
+        request.body.zeroize();
+        pwpush_api::parse_push(response?)
+    }
+
+    /// The request that creates the push on the instance, if the options are
+    /// valid. It contains the payload, so its body should be wiped after use.
+    pub fn request(self, instance: &Instance) -> Result<PPCRequest, PPCError> {
+        Ok(pwpush_api::text_push_request(instance, &self.text()?))
+    }
+
+    /// The push as the CLI would have parsed it, if the options are valid.
+    fn text(self) -> Result<PPCText, PPCError> {
+        check_limit("days", self.expire_days, MAX_DAYS as u32)?;
+        check_limit("views", self.expire_views, MAX_VIEWS)?;
+
+        Ok(PPCText {
+            password_payload: Some(self.payload.0),
+            payload_file: None,
+            stdin: false,
+            prompt: false,
+            from_op: None,
+            from_bw: None,
+            from_vault: None,
+            from_env: Vec::new(),
+            base64: false,
+            normalize_newlines: false,
+            encrypt_for: None,
+            no_warn: true,
+            passphrase: self.passphrase,
+            qr: false,
+            qr_link: false,
+            note: self.note,
+            expire_after_days: self.expire_days.map(|days| days as usize),
+            expire_after_views: self.expire_views.map(|views| views as usize),
+            deletable_by_viewer: self.deletable_by_viewer,
+            retrieval_step: self.retrieval_step,
+            no_defaults: true,
+            preset: None,
+            queue_on_failure: false,
+            paranoid: false,
+            enforce_strong_passphrase: false,
+            hold: false,
+            hold_for: None,
+            ephemeral: false,
+            expire_at: None,
+            expire_after: None,
+            exact: false,
+            to_profiles: Vec::new(),
+            recipients: Vec::new(),
+            notify_webhook: None,
+            email_to: None,
+            shorten: false,
+            validate: false,
+            tags: Vec::new(),
+            expire_after_first_view_confirmed: false,
+            dedupe: None,
+            dedupe_window: dedupe::DEFAULT_WINDOW,
+            idempotency_key: None,
+        })
+    }
+}
+
+fn check_limit(unit: &str, value: Option<u32>, max: u32) -> Result<(), PPCError> {
+    match value {
+        Some(value) if value == 0 || value > max => Err(PPCError::from(format!(
+            "pushes expire after 1 to {} {}, not {}",
+            max, unit, value
+        ))),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TextPush;
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    use crate::http::mock::MockTransport;
+    use crate::instance::Instance;
+
+    fn instance() -> Instance {
+        Instance::new("pwpush.com", None, None).unwrap()
+    }
+
+    #[test]
+    fn builder_checks_limits() {
+        for builder in [
+            TextPush::builder().payload("s3cret").expire_days(0),
+            TextPush::builder().payload("s3cret").expire_days(91),
+            TextPush::builder().payload("s3cret").expire_views(101),
+        ] {
+            assert!(builder.request(&instance()).is_err());
+        }
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn send_creates_the_push_with_the_options_given() {
+        use super::Client;
+
+        let client = Client::with_transport(
+            instance(),
+            MockTransport::new().respond(201, r#"{"url_token":"abc"}"#),
+        );
+
+        let push = TextPush::builder()
+            .expire_days(3)
+            .payload("s3cret")
+            .passphrase("correct horse")
+            .retrieval_step(true)
+            .send(&client)
+            .unwrap();
+
+        assert_eq!(push.url_token, "abc");
+        let requests = client.transport().requests();
+        assert_eq!(requests[0].url, "https://pwpush.com/p.json");
+        assert_eq!(
+            requests[0].body.as_deref(),
+            Some(
+                "password[payload]=s3cret&password[passphrase]=correct%20horse&\
+                 password[expire_after_days]=3&password[retrieval_step]=true"
+            )
+        );
+    }
+
+    /// The mock answers right away, so the futures are ready when they are
+    /// first polled and need no runtime.
+    #[cfg(feature = "async")]
+    #[test]
+    fn send_async_sends_the_same_request() {
+        use std::future::Future;
+        use std::pin::pin;
+        use std::task::{Context, Poll, Waker};
+
+        use super::AsyncClient;
+
+        fn ready<F: Future>(future: F) -> F::Output {
+            match pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+                Poll::Ready(output) => output,
+                Poll::Pending => panic!("the mock transport does not wait"),
+            }
+        }
+
+        let client = AsyncClient::with_transport(
+            instance(),
+            MockTransport::new()
+                .respond(201, r#"{"url_token":"abc","retrieval_step":true}"#)
+                .respond(404, ""),
+        );
+
+        let builder = TextPush::builder().payload("s3cret").retrieval_step(true);
+        let expected = builder.request(client.instance()).unwrap();
+        let builder = TextPush::builder().payload("s3cret").retrieval_step(true);
+        let push = ready(builder.send_async(&client)).unwrap();
+
+        assert_eq!(push.url_token, "abc");
+        assert_eq!(client.transport().requests()[0].body, expected.body);
+        assert_eq!(ready(client.url(&push)), "https://pwpush.com/p/abc/r");
+    }
+}
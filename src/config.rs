@@ -0,0 +1,672 @@
+//! Configuration file handling.
+//!
+//! The configuration is a TOML file that is read on every start. By default it
+//! lives at `config.toml` in the configuration directory of the platform,
+//! e.g. `~/.config/pwpush-cli/config.toml` on Linux (see `paths`), a
+//! different file can be given with `--config`. A missing file is not an
+//! error, the application then simply runs with built-in defaults.
+//!
+//! Example:
+//!
+//! ```toml
+//! [push]
+//! expire_after_days = 3
+//! expire_after_views = 5
+//! retrieval_step = true
+//! deletable_by_viewer = false
+//! passphrase_policy = "required"
+//! warn_positional_secret = false
+//! forbid_argv_secrets = true
+//!
+//! [presets.customer-handoff]
+//! expire_after_days = 2
+//! expire_after_views = 1
+//! retrieval_step = true
+//! note_prefix = "customer handoff: "
+//! passphrase_length = 12
+//!
+//! [profiles.internal]
+//! url = "pwpush.example.com"
+//! email = "user@example.com"
+//! token = "..."
+//!
+//! [history]
+//! enabled = true
+//!
+//! [smtp]
+//! host = "smtp.example.com"
+//! username = "user@example.com"
+//! from = "Ops <ops@example.com>"
+//!
+//! [shortener]
+//! url = "https://s.example.com/api/shorten?link={url}"
+//! response_field = "short_url"
+//!
+//! [aliases]
+//! once = "push text --expire-after-views 1 --retrieval-step"
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use toml_edit::DocumentMut;
+use tracing::{debug, info, warn};
+
+use crate::args::{AuthScheme, InstanceProtocol, PPCFile, PPCText};
+use crate::errors::PPCError;
+use crate::policy::{self, Policy};
+use crate::{credentials, i18n, paranoid, passphrase, paths, state};
+
+/// Root of the configuration file. Every section is optional, so an empty file
+/// is a valid configuration.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PPCConfig {
+    pub push: PushDefaults,
+    pub presets: BTreeMap<String, Preset>,
+    pub profiles: BTreeMap<String, Profile>,
+    pub history: HistorySettings,
+    pub smtp: SmtpSettings,
+    pub shortener: ShortenerSettings,
+    pub hooks: HookSettings,
+    pub aliases: BTreeMap<String, Alias>,
+
+    /// Rules of the organization, from the policy file instead of the
+    /// configuration file, see `policy`
+    #[serde(skip)]
+    pub policy: Policy,
+}
+
+/// Default options for new pushes. These are merged into each push, unless the
+/// option was given explicitly on the command line or `--no-defaults` is used.
+/// The passphrase policy applies to every push.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PushDefaults {
+    pub expire_after_days: Option<usize>,
+    pub expire_after_views: Option<usize>,
+    pub deletable_by_viewer: Option<bool>,
+    pub retrieval_step: Option<bool>,
+    pub passphrase_policy: PassphrasePolicy,
+
+    /// Warn about secrets given as command line argument, enabled if not set
+    pub warn_positional_secret: Option<bool>,
+
+    /// Warn about passphrases that are easy to guess, enabled if not set
+    pub warn_weak_passphrase: Option<bool>,
+
+    /// Refuse payloads and passphrases given as arguments, see `argv_secrets`
+    pub forbid_argv_secrets: bool,
+
+    /// Largest payload in bytes the instance accepts, 1 MiB if not set
+    pub max_payload_size: Option<usize>,
+}
+
+/// Settings of the local push history, see `history`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HistorySettings {
+    /// Record pushes made on this machine, disabled if not set
+    pub enabled: bool,
+}
+
+/// SMTP server for `--email-to`, see `email`. The password is kept in the
+/// keyring of the system, not in the file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SmtpSettings {
+    /// Host name of the server, required to send emails
+    pub host: Option<String>,
+    /// Port of the server. Defaults to the port of the security mode
+    pub port: Option<u16>,
+    pub security: SmtpSecurity,
+    /// User to log in as, emails are sent without login if not set
+    pub username: Option<String>,
+    /// Sender of the emails, required to send emails
+    pub from: Option<String>,
+    /// Subject of the emails, with the same placeholders as the body
+    pub subject: Option<String>,
+    /// Text of the emails. `{url}` and `{expiration}` are replaced by the
+    /// link and its limits, and the placeholders of notes are supported
+    pub body: Option<String>,
+}
+
+/// Link shortener for `--shorten`, see `shortener`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShortenerSettings {
+    /// Endpoint of the shortener, required to shorten links. `{url}` is
+    /// replaced by the percent-encoded link
+    pub url: Option<String>,
+    pub method: ShortenerMethod,
+    /// Body of POST requests. `{url}` is replaced by the link
+    pub body: Option<String>,
+    /// Content type of the body, `application/json` if not set
+    pub content_type: Option<String>,
+    /// Additional headers, e.g. with an API key
+    pub headers: BTreeMap<String, String>,
+    /// Dotted path of the short link in a JSON response, e.g. `data.link`.
+    /// The whole response is the short link if not set
+    pub response_field: Option<String>,
+}
+
+/// Shell commands that are run around operations, see `hooks`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HookSettings {
+    /// Run before each push is created. The push is not created if it fails
+    pub pre_push: Option<String>,
+    /// Run after each push was created
+    pub post_push: Option<String>,
+    /// Run after each push was expired
+    pub post_expire: Option<String>,
+}
+
+/// A command defined by the user, see `aliases`. Either a command line, which
+/// is split at whitespace, or a list of arguments, for arguments with spaces.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum Alias {
+    Line(String),
+    Args(Vec<String>),
+}
+
+impl Alias {
+    pub fn args(&self) -> Vec<String> {
+        match self {
+            Alias::Line(line) => line.split_whitespace().map(String::from).collect(),
+            Alias::Args(args) => args.clone(),
+        }
+    }
+}
+
+/// HTTP method of the requests to the shortener.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShortenerMethod {
+    #[default]
+    Get,
+    Post,
+}
+
+/// How the connection to the SMTP server is protected.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// Upgrade the connection with STARTTLS, on port 587 by default
+    #[default]
+    Starttls,
+
+    /// TLS from the start, on port 465 by default
+    Tls,
+
+    /// No encryption, on port 25 by default. Only for relays on the local
+    /// machine or network
+    None,
+}
+
+/// Whether pushes need to be protected with a passphrase.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PassphrasePolicy {
+    /// A passphrase is used only if given on the command line
+    #[default]
+    Optional,
+
+    /// Pushes without a passphrase are refused
+    Required,
+}
+
+/// A named bundle of push options, selected with `--preset <name>`. Options
+/// of a preset take precedence over the push defaults, but not over options
+/// given on the command line.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Preset {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expire_after_days: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expire_after_views: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deletable_by_viewer: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retrieval_step: Option<bool>,
+
+    /// Prepended to the note of each push
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note_prefix: Option<String>,
+
+    /// Generate a random passphrase of this length, unless one was given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub passphrase_length: Option<usize>,
+}
+
+/// A named instance with optional credentials, selected with `--profile`. See
+/// `instance` for how profiles are combined with command line options.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    pub url: Option<String>,
+    pub protocol: Option<InstanceProtocol>,
+    pub email: Option<String>,
+    pub token: Option<SecretString>,
+    pub auth_scheme: Option<AuthScheme>,
+}
+
+impl PPCConfig {
+    /// Look up a profile by name.
+    pub fn profile(&self, name: &str) -> Result<&Profile, PPCError> {
+        self.profiles
+            .get(name)
+            .ok_or_else(|| PPCError::from(format!("profile `{}` does not exist", name)))
+    }
+
+    /// Create the effective push options by filling in all options the user did
+    /// not give explicitly, first from `--paranoid`, then from the selected
+    /// preset and then from the push defaults. Fails if the preset does not exist, or if the result
+    /// violates the passphrase policy or the policy file.
+    pub fn apply(&self, ppc_text: &PPCText) -> Result<PPCText, PPCError> {
+        let mut merged = ppc_text.clone();
+
+        // clap ensures that none of these options were given, and presets and
+        // defaults must not weaken them.
+        if ppc_text.paranoid {
+            debug!("applying hardened options of --paranoid");
+            paranoid::apply(&mut merged);
+        }
+
+        if let Some(name) = &ppc_text.preset {
+            let preset = self
+                .presets
+                .get(name)
+                .ok_or_else(|| PPCError::from(format!("preset `{}` does not exist", name)))?;
+            debug!("applying preset `{}`", name);
+
+            merged.expire_after_days = merged.expire_after_days.or(preset.expire_after_days);
+            merged.expire_after_views = merged.expire_after_views.or(preset.expire_after_views);
+            merged.deletable_by_viewer = merged.deletable_by_viewer.or(preset.deletable_by_viewer);
+            merged.retrieval_step = merged.retrieval_step.or(preset.retrieval_step);
+
+            if let Some(prefix) = &preset.note_prefix {
+                merged.note = Some(format!(
+                    "{}{}",
+                    prefix,
+                    merged.note.as_deref().unwrap_or_default()
+                ));
+            }
+
+            if let (None, Some(length)) = (&merged.passphrase, preset.passphrase_length) {
+                merged.passphrase = Some(SecretString::from(passphrase::generate(length)));
+            }
+        }
+
+        if !ppc_text.no_defaults {
+            debug!("applying push defaults from configuration");
+            let defaults = &self.push;
+            merged.expire_after_days = merged.expire_after_days.or(defaults.expire_after_days);
+            merged.expire_after_views = merged.expire_after_views.or(defaults.expire_after_views);
+            merged.deletable_by_viewer =
+                merged.deletable_by_viewer.or(defaults.deletable_by_viewer);
+            merged.retrieval_step = merged.retrieval_step.or(defaults.retrieval_step);
+        }
+
+        // --no-defaults only skips the default values, the passphrase policy
+        // and the policy file still apply.
+        self.check_passphrase_policy(merged.passphrase.as_ref())?;
+        self.policy.enforce(&mut merged)?;
+        Ok(merged)
+    }
+
+    /// Like `apply`, for file pushes, which have no presets.
+    pub fn apply_file(&self, ppc_file: &PPCFile) -> Result<PPCFile, PPCError> {
+        let mut merged = ppc_file.clone();
+
+        if !ppc_file.no_defaults {
+            debug!("applying push defaults from configuration");
+            let defaults = &self.push;
+            merged.expire_after_days = merged.expire_after_days.or(defaults.expire_after_days);
+            merged.expire_after_views = merged.expire_after_views.or(defaults.expire_after_views);
+            merged.deletable_by_viewer =
+                merged.deletable_by_viewer.or(defaults.deletable_by_viewer);
+            merged.retrieval_step = merged.retrieval_step.or(defaults.retrieval_step);
+        }
+
+        self.check_passphrase_policy(merged.passphrase.as_ref())?;
+        self.policy.enforce_file(&mut merged)?;
+        Ok(merged)
+    }
+
+    /// Fail if the passphrase policy requires a passphrase, but there is none.
+    /// An empty passphrase does not count.
+    fn check_passphrase_policy(&self, passphrase: Option<&SecretString>) -> Result<(), PPCError> {
+        let missing = passphrase.is_none_or(|passphrase| passphrase.expose_secret().is_empty());
+        if self.push.passphrase_policy == PassphrasePolicy::Required && missing {
+            return Err(PPCError::from(
+                "the configured passphrase policy requires a passphrase, use --passphrase",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Fail on settings that parse, but make no sense.
+    fn validate(self) -> Result<Self, PPCError> {
+        for (name, preset) in &self.presets {
+            if preset.passphrase_length == Some(0) {
+                return Err(PPCError::from(format!(
+                    "passphrase_length of preset `{}` is 0, which generates an empty passphrase",
+                    name
+                )));
+            }
+        }
+        Ok(self)
+    }
+}
+
+/// The path of the configuration file that is in effect: the given path, or
+/// the default location if no path was given. Used by commands that write to
+/// the configuration file.
+pub fn path(path: Option<&Path>) -> Result<PathBuf, PPCError> {
+    match path {
+        Some(p) => Ok(p.to_path_buf()),
+        None => default_path().ok_or_else(|| {
+            PPCError::from("could not determine the configuration directory, use --config")
+        }),
+    }
+}
+
+/// Load the configuration from the given path, or from the default location if
+/// no path was given, together with the policy file. A missing file results in
+/// the built-in defaults, so that commands writing to the configuration can
+/// create it. If the file was given explicitly, a warning is logged, as it
+/// might be a typo.
+pub fn load(path: Option<&Path>) -> Result<PPCConfig, PPCError> {
+    let mut config = load_file(path)?;
+    config.policy = policy::load()?;
+    Ok(config)
+}
+
+fn load_file(path: Option<&Path>) -> Result<PPCConfig, PPCError> {
+    let explicit = path.is_some();
+    let path = match path {
+        Some(p) => p.to_path_buf(),
+        None => match default_path() {
+            Some(p) => p,
+            None => return Ok(PPCConfig::default()),
+        },
+    };
+
+    if !path.exists() {
+        if explicit {
+            warn!(
+                "{}",
+                i18n::format(
+                    "config-missing",
+                    &[("path", path.display().to_string().into())]
+                )
+            );
+        }
+        debug!("no configuration file found, using built-in defaults");
+        return Ok(PPCConfig::default());
+    }
+
+    info!("loading configuration from {}", path.display());
+    let content = fs::read_to_string(&path).map_err(|e| {
+        PPCError::from(format!(
+            "could not read configuration file {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+
+    let config = parse(&content).map_err(|e| {
+        PPCError::from(format!(
+            "invalid configuration file {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+    credentials::check_permissions(&path, &config);
+    Ok(config)
+}
+
+fn parse(content: &str) -> Result<PPCConfig, PPCError> {
+    toml::from_str::<PPCConfig>(content)
+        .map_err(|e| PPCError::from(format!("{}", e)))?
+        .validate()
+}
+
+/// Read the configuration file for modification, with `toml_edit`, so
+/// comments and formatting survive. A missing file is treated
+/// like an empty one, as it will be created when writing.
+pub fn read_document(path: &Path) -> Result<DocumentMut, PPCError> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => {
+            return Err(PPCError::from(format!(
+                "could not read configuration file {}: {}",
+                path.display(),
+                e
+            )))
+        }
+    };
+
+    content.parse::<DocumentMut>().map_err(|e| {
+        PPCError::from(format!(
+            "invalid configuration file {}: {}",
+            path.display(),
+            e
+        ))
+    })
+}
+
+/// Write the configuration file after modification. If it contains tokens,
+/// it is made accessible by the user only, before the tokens are written.
+pub fn write_document(path: &Path, document: &DocumentMut) -> Result<(), PPCError> {
+    let write_err = |e: std::io::Error| {
+        PPCError::from(format!(
+            "could not write configuration file {}: {}",
+            path.display(),
+            e
+        ))
+    };
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(write_err)?;
+    }
+    if credentials::has_tokens(document) {
+        if !path.exists() {
+            return state::write_private(path, document.to_string().as_bytes());
+        }
+        credentials::restrict_permissions(path)?;
+    }
+    // written in place, so a configuration file that is a symlink, e.g. into
+    // a dotfiles repository, stays one.
+    fs::write(path, document.to_string()).map_err(write_err)
+}
+
+/// Default location of the configuration file.
+fn default_path() -> Option<PathBuf> {
+    Some(paths::config_dir()?.join("config.toml"))
+}
+
+#[cfg(test)]
+mod test {
+    use secrecy::{ExposeSecret, SecretString};
+
+    use super::{parse, PassphrasePolicy};
+    use crate::args::PPCText;
+    use crate::dedupe;
+
+    fn text() -> PPCText {
+        PPCText {
+            password_payload: Some(SecretString::from("password")),
+            payload_file: None,
+            stdin: false,
+            prompt: false,
+            from_op: None,
+            from_bw: None,
+            from_vault: None,
+            from_env: Vec::new(),
+            base64: false,
+            normalize_newlines: false,
+            encrypt_for: None,
+            no_warn: false,
+            passphrase: None,
+            qr: false,
+            qr_link: false,
+            note: None,
+            expire_after_days: None,
+            expire_after_views: None,
+            deletable_by_viewer: None,
+            retrieval_step: None,
+            no_defaults: false,
+            preset: None,
+            queue_on_failure: false,
+            paranoid: false,
+            enforce_strong_passphrase: false,
+            hold: false,
+            hold_for: None,
+            ephemeral: false,
+            expire_at: None,
+            expire_after: None,
+            exact: false,
+            to_profiles: Vec::new(),
+            recipients: Vec::new(),
+            notify_webhook: None,
+            email_to: None,
+            shorten: false,
+            validate: false,
+            tags: Vec::new(),
+            expire_after_first_view_confirmed: false,
+            dedupe: None,
+            dedupe_window: dedupe::DEFAULT_WINDOW,
+            idempotency_key: None,
+        }
+    }
+
+    #[test]
+    fn parse_empty_config() {
+        let config = parse("").unwrap();
+
+        assert_eq!(config.push.expire_after_days, None);
+        assert_eq!(config.push.passphrase_policy, PassphrasePolicy::Optional);
+    }
+
+    #[test]
+    fn parse_rejects_unknown_keys() {
+        assert!(parse("[push]\nexpire_after_weeks = 1").is_err());
+    }
+
+    #[test]
+    fn defaults_fill_missing_options() {
+        let config = parse("[push]\nexpire_after_days = 3\nretrieval_step = true").unwrap();
+
+        let mut ppc_text = text();
+        ppc_text.expire_after_days = Some(1);
+        let merged = config.apply(&ppc_text).unwrap();
+
+        assert_eq!(merged.expire_after_days, Some(1));
+        assert_eq!(merged.retrieval_step, Some(true));
+        assert_eq!(merged.expire_after_views, None);
+    }
+
+    #[test]
+    fn no_defaults_keeps_the_passphrase_policy() {
+        let config =
+            parse("[push]\nexpire_after_days = 3\npassphrase_policy = \"required\"").unwrap();
+
+        let mut ppc_text = text();
+        ppc_text.no_defaults = true;
+        assert!(config.apply(&ppc_text).is_err());
+
+        ppc_text.passphrase = Some(SecretString::from("passphrase"));
+        let merged = config.apply(&ppc_text).unwrap();
+        assert_eq!(merged.expire_after_days, None);
+
+        ppc_text.passphrase = Some(SecretString::from(""));
+        assert!(config.apply(&ppc_text).is_err());
+    }
+
+    #[test]
+    fn empty_generated_passphrase() {
+        assert!(parse("[presets.handoff]\npassphrase_length = 0").is_err());
+    }
+
+    #[test]
+    fn preset_overrides_defaults() {
+        let config = parse(
+            "[push]\nexpire_after_days = 3\nexpire_after_views = 5\n\
+             [presets.handoff]\nexpire_after_days = 1\nnote_prefix = \"handoff: \"\n\
+             passphrase_length = 8",
+        )
+        .unwrap();
+
+        let mut ppc_text = text();
+        ppc_text.preset = Some(String::from("handoff"));
+        ppc_text.note = Some(String::from("ticket 42"));
+        let merged = config.apply(&ppc_text).unwrap();
+
+        assert_eq!(merged.expire_after_days, Some(1));
+        assert_eq!(merged.expire_after_views, Some(5));
+        assert_eq!(merged.note.as_deref(), Some("handoff: ticket 42"));
+        assert_eq!(merged.passphrase.map(|p| p.expose_secret().len()), Some(8));
+    }
+
+    #[test]
+    fn preset_keeps_explicit_passphrase() {
+        let config = parse("[presets.handoff]\npassphrase_length = 8").unwrap();
+
+        let mut ppc_text = text();
+        ppc_text.preset = Some(String::from("handoff"));
+        ppc_text.passphrase = Some(SecretString::from("passphrase"));
+        let merged = config.apply(&ppc_text).unwrap();
+
+        assert_eq!(
+            merged.passphrase.as_ref().map(|p| p.expose_secret()),
+            Some("passphrase")
+        );
+    }
+
+    #[test]
+    fn paranoid_overrides_preset() {
+        let config = parse(
+            "[push]\nretrieval_step = false\n\
+             [presets.handoff]\nexpire_after_days = 7\npassphrase_length = 8",
+        )
+        .unwrap();
+
+        let mut ppc_text = text();
+        ppc_text.preset = Some(String::from("handoff"));
+        ppc_text.paranoid = true;
+        let merged = config.apply(&ppc_text).unwrap();
+
+        assert_eq!(merged.expire_after_days, Some(1));
+        assert_eq!(merged.expire_after_views, Some(1));
+        assert_eq!(merged.retrieval_step, Some(true));
+        assert_eq!(merged.deletable_by_viewer, Some(false));
+        assert_eq!(merged.passphrase.map(|p| p.expose_secret().len()), Some(16));
+    }
+
+    #[test]
+    fn unknown_preset() {
+        let mut ppc_text = text();
+        ppc_text.preset = Some(String::from("missing"));
+
+        assert!(parse("").unwrap().apply(&ppc_text).is_err());
+    }
+
+    #[test]
+    fn required_passphrase_policy() {
+        let config = parse("[push]\npassphrase_policy = \"required\"").unwrap();
+
+        assert!(config.apply(&text()).is_err());
+
+        let mut ppc_text = text();
+        ppc_text.passphrase = Some(SecretString::from("passphrase"));
+        assert!(config.apply(&ppc_text).is_ok());
+    }
+}
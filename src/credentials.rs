@@ -0,0 +1,931 @@
+//! Encrypted tokens in the configuration file.
+//!
+//! Tokens of profiles can be written into the configuration file in plain
+//! text, but then they leak through backups, synced dotfiles or a shared
+//! screen. `auth set-token` and `auth encrypt` store them encrypted instead,
+//! in one of two ways:
+//!   - `token = "pwpush:local:..."`: with a random key in the data directory
+//!     of this machine, see `state`. Nothing needs to be typed in, but the
+//!     token can only be used by this user on this machine.
+//!   - `token = "pwpush:passphrase:..."`: with a key derived from a passphrase
+//!     with Argon2id. The passphrase is asked for when the token is used, or
+//!     taken from `PWPUSH_CREDENTIALS_PASSPHRASE`.
+//!
+//! Tokens are encrypted with XChaCha20-Poly1305, with the name of the profile
+//! as associated data, so an encrypted token that is copied to another
+//! profile is refused. The cipher compares authentication tags in constant
+//! time, so wrong keys and passphrases cannot be guessed byte by byte, and
+//! decrypted tokens and derived keys are zeroized when they are dropped.
+//!
+//! A configuration file with tokens is written with permissions for the user
+//! only, and a file with tokens in plain text that others can read is warned
+//! about.
+//!
+//! `auth export` and `auth import` move profiles with their tokens to another
+//! machine, as armored text that is encrypted with a passphrase of its own.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal, Read};
+use std::path::{Path, PathBuf};
+
+use argon2::Argon2;
+use base64::prelude::{Engine, BASE64_STANDARD};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use toml_edit::{value, DocumentMut, Item, Table};
+use tracing::{debug, info, warn};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::args::{AuthCommand, AuthScheme, InstanceProtocol, PPCArgs};
+use crate::config::{self, PPCConfig, Profile};
+use crate::errors::PPCError;
+use crate::{i18n, prompt, state};
+
+/// Passphrase of tokens that are protected with one, instead of a prompt.
+pub const PASSPHRASE_VAR: &str = "PWPUSH_CREDENTIALS_PASSPHRASE";
+
+const LOCAL_PREFIX: &str = "pwpush:local:";
+const PASSPHRASE_PREFIX: &str = "pwpush:passphrase:";
+
+const KEY_FILE: &str = "credentials.key";
+
+const EXPORT_BEGIN: &str = "-----BEGIN PWPUSH-CLI PROFILES-----";
+const EXPORT_END: &str = "-----END PWPUSH-CLI PROFILES-----";
+const EXPORT_VERSION: u32 = 1;
+/// Associated data of exports, so they cannot be mistaken for tokens.
+const EXPORT_CONTEXT: &[u8] = b"pwpush-cli profiles export";
+
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 24;
+const KEY_LENGTH: usize = 32;
+
+/// How a token is encrypted.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Protection {
+    /// With the key of this machine
+    Local,
+    /// With a key derived from a passphrase
+    Passphrase,
+}
+
+impl Protection {
+    fn of(token: &str) -> Option<Self> {
+        [Protection::Local, Protection::Passphrase]
+            .into_iter()
+            .find(|protection| token.starts_with(protection.prefix()))
+    }
+
+    /// Prefix of tokens that are encrypted this way.
+    fn prefix(self) -> &'static str {
+        match self {
+            Protection::Local => LOCAL_PREFIX,
+            Protection::Passphrase => PASSPHRASE_PREFIX,
+        }
+    }
+}
+
+/// The keys to encrypt and decrypt tokens with.
+pub struct Keys {
+    /// Directory of the key of this machine, which is created on first use
+    dir: PathBuf,
+    /// Passphrase for tokens that are protected with one. Asked for when it
+    /// is needed first, unless it was given.
+    passphrase: Option<SecretString>,
+}
+
+impl Keys {
+    /// The keys of this user, with the passphrase from the environment, if set.
+    pub fn open() -> Result<Self, PPCError> {
+        let passphrase = env::var(PASSPHRASE_VAR)
+            .ok()
+            .filter(|passphrase| !passphrase.is_empty())
+            .map(SecretString::from);
+        Ok(Self::new(state::dir("credentials")?, passphrase))
+    }
+
+    fn new(dir: PathBuf, passphrase: Option<SecretString>) -> Self {
+        Self { dir, passphrase }
+    }
+
+    /// The passphrase, which is asked for if it was not given. New
+    /// passphrases are asked for twice, so typos do not lock the user out.
+    fn passphrase(&mut self, profile: &str, new: bool) -> Result<&SecretString, PPCError> {
+        if self.passphrase.is_none() {
+            let question = format!("Passphrase for the token of profile `{}`: ", profile);
+            let passphrase = if new {
+                new_passphrase(&question)?
+            } else {
+                prompt::secret(&question)
+                    .map_err(|e| PPCError::from(format!("{}, or set {}", e, PASSPHRASE_VAR)))?
+            };
+            self.passphrase = Some(passphrase);
+        }
+        Ok(self.passphrase.as_ref().expect("passphrase was just set"))
+    }
+
+    fn local_key(&self, create: bool) -> Result<Zeroizing<Vec<u8>>, PPCError> {
+        let path = self.dir.join(KEY_FILE);
+        if !create && !path.exists() {
+            return Err(PPCError::from(
+                "the token was encrypted on another machine or by another user, move \
+                 profiles with `auth export` and `auth import` instead of copying them",
+            ));
+        }
+        state::create_private_dir(&self.dir)?;
+        state::key(&path)
+    }
+
+    /// Encrypt the token of the profile, for the configuration file.
+    pub fn seal(
+        &mut self,
+        profile: &str,
+        token: &SecretString,
+        protection: Protection,
+    ) -> Result<String, PPCError> {
+        let context = context(profile);
+        let plaintext = token.expose_secret().as_bytes();
+        let sealed = match protection {
+            Protection::Local => encrypt(&self.local_key(true)?, &context, plaintext)?,
+            Protection::Passphrase => {
+                seal_with_passphrase(self.passphrase(profile, true)?, &context, plaintext)?
+            }
+        };
+        Ok(format!(
+            "{}{}",
+            protection.prefix(),
+            BASE64_STANDARD.encode(sealed)
+        ))
+    }
+
+    /// The token of the profile as it is sent to the instance. Tokens in
+    /// plain text are returned as they are.
+    pub fn reveal(
+        &mut self,
+        profile: &str,
+        token: &SecretString,
+    ) -> Result<SecretString, PPCError> {
+        let stored = token.expose_secret();
+        let Some(protection) = Protection::of(stored) else {
+            return Ok(token.clone());
+        };
+
+        let error = |e: PPCError| {
+            PPCError::from(format!(
+                "could not decrypt the token of profile `{}`: {}",
+                profile, e
+            ))
+        };
+        let encoded = &stored[protection.prefix().len()..];
+        let sealed = BASE64_STANDARD
+            .decode(encoded)
+            .map_err(|_| error(PPCError::from("it is not valid base64")))?;
+
+        let context = context(profile);
+        let plaintext = match protection {
+            Protection::Local => decrypt(&self.local_key(false).map_err(error)?, &context, &sealed),
+            Protection::Passphrase => {
+                let passphrase = self.passphrase(profile, false).map_err(error)?;
+                open_with_passphrase(passphrase, &context, &sealed)
+            }
+        }
+        .map_err(error)?;
+
+        debug!("decrypted the token of profile `{}`", profile);
+        String::from_utf8(plaintext.to_vec())
+            .map(SecretString::from)
+            .map_err(|_| error(PPCError::from("it is not valid UTF-8")))
+    }
+}
+
+/// The token of a profile as it is sent to the instance, see `Keys::reveal`.
+/// The keys are only opened for encrypted tokens.
+pub fn reveal(profile: &str, token: &SecretString) -> Result<SecretString, PPCError> {
+    if Protection::of(token.expose_secret()).is_none() {
+        return Ok(token.clone());
+    }
+    Keys::open()?.reveal(profile, token)
+}
+
+/// Associated data of the token of a profile.
+fn context(profile: &str) -> Vec<u8> {
+    format!("pwpush-cli token of profile {}", profile).into_bytes()
+}
+
+/// Encrypt with a random nonce, which is prepended to the ciphertext.
+fn encrypt(key: &[u8], context: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, PPCError> {
+    let cipher =
+        XChaCha20Poly1305::new_from_slice(key).map_err(|_| PPCError::from("invalid key"))?;
+    let mut nonce = [0u8; NONCE_LENGTH];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let ciphertext = cipher
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad: context,
+            },
+        )
+        .map_err(|_| PPCError::from("could not encrypt"))?;
+
+    let mut sealed = nonce.to_vec();
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+/// Decrypt what was encrypted with `encrypt`, with the same context.
+fn decrypt(key: &[u8], context: &[u8], sealed: &[u8]) -> Result<Zeroizing<Vec<u8>>, PPCError> {
+    if sealed.len() < NONCE_LENGTH {
+        return Err(PPCError::from("it is truncated"));
+    }
+    let cipher =
+        XChaCha20Poly1305::new_from_slice(key).map_err(|_| PPCError::from("invalid key"))?;
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LENGTH);
+    cipher
+        .decrypt(
+            XNonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: context,
+            },
+        )
+        .map(Zeroizing::new)
+        .map_err(|_| PPCError::from("wrong key or passphrase, or it was changed"))
+}
+
+/// Key of the passphrase, with the recommended Argon2id parameters.
+fn derive(passphrase: &SecretString, salt: &[u8]) -> Result<Zeroizing<[u8; KEY_LENGTH]>, PPCError> {
+    let mut key = Zeroizing::new([0u8; KEY_LENGTH]);
+    Argon2::default()
+        .hash_password_into(passphrase.expose_secret().as_bytes(), salt, key.as_mut())
+        .map_err(|e| PPCError::from(format!("could not derive key: {}", e)))?;
+    Ok(key)
+}
+
+/// Encrypt with a key of the passphrase and a random salt, which is
+/// prepended to the nonce and ciphertext.
+fn seal_with_passphrase(
+    passphrase: &SecretString,
+    context: &[u8],
+    plaintext: &[u8],
+) -> Result<Vec<u8>, PPCError> {
+    let mut salt = [0u8; SALT_LENGTH];
+    rand::thread_rng().fill_bytes(&mut salt);
+
+    let mut sealed = salt.to_vec();
+    sealed.extend(encrypt(
+        derive(passphrase, &salt)?.as_ref(),
+        context,
+        plaintext,
+    )?);
+    Ok(sealed)
+}
+
+fn open_with_passphrase(
+    passphrase: &SecretString,
+    context: &[u8],
+    sealed: &[u8],
+) -> Result<Zeroizing<Vec<u8>>, PPCError> {
+    if sealed.len() < SALT_LENGTH {
+        return Err(PPCError::from("it is truncated"));
+    }
+    let (salt, sealed) = sealed.split_at(SALT_LENGTH);
+    decrypt(derive(passphrase, salt)?.as_ref(), context, sealed)
+}
+
+/// Ask for a new passphrase twice.
+fn new_passphrase(question: &str) -> Result<SecretString, PPCError> {
+    let passphrase = prompt::secret(question)?;
+    if passphrase.expose_secret().is_empty() {
+        return Err(PPCError::from("the passphrase must not be empty"));
+    }
+    let repeated = prompt::secret("Repeat the passphrase: ")?;
+    if passphrase.expose_secret() != repeated.expose_secret() {
+        return Err(PPCError::from("the passphrases do not match"));
+    }
+    Ok(passphrase)
+}
+
+/// Whether any profile of the configuration file has a token.
+pub fn has_tokens(document: &DocumentMut) -> bool {
+    document
+        .get("profiles")
+        .and_then(Item::as_table_like)
+        .is_some_and(|profiles| {
+            profiles
+                .iter()
+                .filter_map(|(_, profile)| profile.as_table_like())
+                .any(|profile| profile.contains_key("token"))
+        })
+}
+
+/// Make the file accessible by the user only.
+pub fn restrict_permissions(path: &Path) -> Result<(), PPCError> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600)).map_err(|e| {
+            PPCError::from(format!(
+                "could not restrict permissions of {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// Whether any profile has a token in plain text.
+pub fn has_plain_tokens(config: &PPCConfig) -> bool {
+    config
+        .profiles
+        .values()
+        .filter_map(|profile| profile.token.as_ref())
+        .any(|token| Protection::of(token.expose_secret()).is_none())
+}
+
+/// Warn if tokens in plain text can be read by other users.
+pub fn check_permissions(path: &Path, config: &PPCConfig) {
+    if has_plain_tokens(config) && state::readable_by_others(path) {
+        warn!(
+            "{}",
+            i18n::format(
+                "auth-readable",
+                &[("path", path.display().to_string().into())]
+            )
+        );
+    }
+}
+
+/// A profile in an export, with its token in plain text.
+#[derive(Default, Serialize, Deserialize)]
+struct ExportedProfile {
+    url: Option<String>,
+    protocol: Option<InstanceProtocol>,
+    email: Option<String>,
+    token: Option<String>,
+    auth_scheme: Option<AuthScheme>,
+}
+
+impl Drop for ExportedProfile {
+    fn drop(&mut self) {
+        self.token.zeroize();
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Export {
+    version: u32,
+    profiles: BTreeMap<String, ExportedProfile>,
+}
+
+/// The profiles with their tokens as armored text, encrypted with the
+/// passphrase.
+fn export(
+    keys: &mut Keys,
+    profiles: &BTreeMap<String, Profile>,
+    passphrase: &SecretString,
+) -> Result<String, PPCError> {
+    let mut export = Export {
+        version: EXPORT_VERSION,
+        profiles: BTreeMap::new(),
+    };
+    for (name, profile) in profiles {
+        let token = match &profile.token {
+            Some(token) => Some(keys.reveal(name, token)?.expose_secret().to_string()),
+            None => None,
+        };
+        export.profiles.insert(
+            name.clone(),
+            ExportedProfile {
+                url: profile.url.clone(),
+                protocol: profile.protocol,
+                email: profile.email.clone(),
+                token,
+                auth_scheme: profile.auth_scheme,
+            },
+        );
+    }
+
+    // the serialized profiles contain the tokens.
+    let plaintext = Zeroizing::new(
+        serde_json::to_vec(&export)
+            .map_err(|e| PPCError::from(format!("could not export: {}", e)))?,
+    );
+    let sealed = seal_with_passphrase(passphrase, EXPORT_CONTEXT, &plaintext)?;
+
+    let encoded = BASE64_STANDARD.encode(sealed);
+    let mut armored = format!("{}\n", EXPORT_BEGIN);
+    for line in encoded.as_bytes().chunks(64) {
+        // base64 is plain ASCII.
+        armored.push_str(std::str::from_utf8(line).unwrap());
+        armored.push('\n');
+    }
+    armored.push_str(EXPORT_END);
+    armored.push('\n');
+    Ok(armored)
+}
+
+/// The profiles of an export made by `export`.
+fn import(
+    armored: &str,
+    passphrase: &SecretString,
+) -> Result<BTreeMap<String, ExportedProfile>, PPCError> {
+    let body = armored
+        .trim()
+        .strip_prefix(EXPORT_BEGIN)
+        .and_then(|rest| rest.strip_suffix(EXPORT_END))
+        .ok_or_else(|| PPCError::from("not an export of `auth export`"))?;
+    let encoded = body.split_whitespace().collect::<String>();
+    let sealed = BASE64_STANDARD
+        .decode(encoded)
+        .map_err(|_| PPCError::from("the export is damaged, it is not valid base64"))?;
+
+    let plaintext = open_with_passphrase(passphrase, EXPORT_CONTEXT, &sealed)
+        .map_err(|e| PPCError::from(format!("could not decrypt the export: {}", e)))?;
+    let export = serde_json::from_slice::<Export>(&plaintext)
+        .map_err(|e| PPCError::from(format!("invalid export: {}", e)))?;
+    if export.version != EXPORT_VERSION {
+        return Err(PPCError::from(format!(
+            "the export has version {}, update pwpush-cli to import it",
+            export.version
+        )));
+    }
+    Ok(export.profiles)
+}
+
+/// The table of a profile in the configuration file, created if needed.
+fn profile_table<'a>(document: &'a mut DocumentMut, name: &str) -> Result<&'a mut Table, PPCError> {
+    if !document.contains_key("profiles") {
+        let mut profiles = Table::new();
+        profiles.set_implicit(true);
+        document.insert("profiles", Item::Table(profiles));
+    }
+    let profiles = document["profiles"]
+        .as_table_mut()
+        .ok_or_else(|| PPCError::from("`profiles` in the configuration file is not a table"))?;
+    if !profiles.contains_key(name) {
+        profiles.insert(name, Item::Table(Table::new()));
+    }
+    profiles[name]
+        .as_table_mut()
+        .ok_or_else(|| PPCError::from(format!("profile `{}` is not a table", name)))
+}
+
+fn set_token(
+    path: &Path,
+    keys: &mut Keys,
+    profile: &str,
+    token: &SecretString,
+    protection: Protection,
+) -> Result<(), PPCError> {
+    let sealed = keys.seal(profile, token, protection)?;
+    let mut document = config::read_document(path)?;
+    profile_table(&mut document, profile)?.insert("token", value(sealed));
+    config::write_document(path, &document)?;
+    info!(
+        "saved the token of profile `{}` to {}",
+        profile,
+        path.display()
+    );
+    Ok(())
+}
+
+/// Encrypt all tokens in plain text, and return the names of their profiles.
+fn encrypt_all(
+    path: &Path,
+    keys: &mut Keys,
+    config: &PPCConfig,
+    protection: Protection,
+) -> Result<Vec<String>, PPCError> {
+    let mut document = config::read_document(path)?;
+    let mut encrypted = Vec::new();
+    for (name, profile) in &config.profiles {
+        let Some(token) = &profile.token else {
+            continue;
+        };
+        if Protection::of(token.expose_secret()).is_some() {
+            continue;
+        }
+        let sealed = keys.seal(name, token, protection)?;
+        profile_table(&mut document, name)?.insert("token", value(sealed));
+        encrypted.push(name.clone());
+    }
+
+    if !encrypted.is_empty() {
+        config::write_document(path, &document)?;
+    }
+    Ok(encrypted)
+}
+
+fn add_profiles(
+    path: &Path,
+    keys: &mut Keys,
+    config: &PPCConfig,
+    profiles: &BTreeMap<String, ExportedProfile>,
+    protection: Protection,
+    force: bool,
+) -> Result<(), PPCError> {
+    if !force {
+        if let Some(name) = profiles
+            .keys()
+            .find(|name| config.profiles.contains_key(*name))
+        {
+            return Err(PPCError::from(format!(
+                "profile `{}` exists already, use --force to replace it",
+                name
+            )));
+        }
+    }
+
+    let mut document = config::read_document(path)?;
+    for (name, profile) in profiles {
+        let token = match &profile.token {
+            Some(token) => {
+                Some(keys.seal(name, &SecretString::from(token.as_str()), protection)?)
+            }
+            None => None,
+        };
+
+        let table = profile_table(&mut document, name)?;
+        table.clear();
+        if let Some(url) = &profile.url {
+            table.insert("url", value(url.as_str()));
+        }
+        if let Some(protocol) = profile.protocol {
+            table.insert("protocol", value(protocol.to_string()));
+        }
+        if let Some(email) = &profile.email {
+            table.insert("email", value(email.as_str()));
+        }
+        if let Some(token) = token {
+            table.insert("token", value(token));
+        }
+        if let Some(auth_scheme) = profile.auth_scheme {
+            // the names are the same as in the configuration file.
+            let name = serde_json::to_value(auth_scheme).unwrap();
+            table.insert("auth_scheme", value(name.as_str().unwrap_or_default()));
+        }
+    }
+    config::write_document(path, &document)
+}
+
+/// Read a secret from stdin, up to the end of the first line.
+fn read_stdin(what: &str) -> Result<SecretString, PPCError> {
+    let mut input = Zeroizing::new(String::new());
+    io::stdin()
+        .lock()
+        .read_to_string(&mut input)
+        .map_err(|e| PPCError::from(format!("could not read the {} from stdin: {}", what, e)))?;
+    let line = input.lines().next().unwrap_or_default();
+    if line.is_empty() {
+        return Err(PPCError::from(format!("no {} on stdin", what)));
+    }
+    Ok(SecretString::from(line))
+}
+
+/// Entry point for the `auth` subcommands.
+pub fn run(args: &PPCArgs, config: &PPCConfig, command: &AuthCommand) -> Result<(), PPCError> {
+    let path = config::path(args.config_file.as_deref())?;
+    let mut keys = Keys::open()?;
+    let protection = |with_passphrase: bool| {
+        if with_passphrase {
+            Protection::Passphrase
+        } else {
+            Protection::Local
+        }
+    };
+
+    match command {
+        AuthCommand::SetToken {
+            profile,
+            with_passphrase,
+            stdin,
+        } => {
+            let token = if *stdin {
+                read_stdin("token")?
+            } else {
+                prompt::secret(&format!("Token of profile `{}`: ", profile))
+                    .map_err(|e| PPCError::from(format!("{}, or use --stdin", e)))?
+            };
+            set_token(
+                &path,
+                &mut keys,
+                profile,
+                &token,
+                protection(*with_passphrase),
+            )?;
+            let protection = match protection(*with_passphrase) {
+                Protection::Local => "local",
+                Protection::Passphrase => "passphrase",
+            };
+            eprintln!(
+                "{}",
+                i18n::format(
+                    "auth-stored",
+                    &[
+                        ("profile", profile.as_str().into()),
+                        ("protection", protection.into())
+                    ]
+                )
+            );
+            Ok(())
+        }
+        AuthCommand::Encrypt { with_passphrase } => {
+            let encrypted = encrypt_all(&path, &mut keys, config, protection(*with_passphrase))?;
+            if encrypted.is_empty() {
+                eprintln!(
+                    "{}",
+                    i18n::format(
+                        "auth-nothing-to-encrypt",
+                        &[("path", path.display().to_string().into())]
+                    )
+                );
+            } else {
+                eprintln!(
+                    "{}",
+                    i18n::format(
+                        "auth-encrypted",
+                        &[("profiles", encrypted.join(", ").into())]
+                    )
+                );
+            }
+            Ok(())
+        }
+        AuthCommand::Export { profiles, output } => {
+            let selected = if profiles.is_empty() {
+                config.profiles.clone()
+            } else {
+                profiles
+                    .iter()
+                    .map(|name| Ok((name.clone(), config.profile(name)?.clone())))
+                    .collect::<Result<_, PPCError>>()?
+            };
+            if selected.is_empty() {
+                return Err(PPCError::from("there are no profiles to export"));
+            }
+
+            let passphrase = new_passphrase("Passphrase for the export: ")?;
+            let armored = export(&mut keys, &selected, &passphrase)?;
+            match output {
+                Some(output) => state::write_private(output, armored.as_bytes())?,
+                None => print!("{}", armored),
+            }
+            eprintln!(
+                "{}",
+                i18n::format("auth-exported", &[("count", selected.len().into())])
+            );
+            Ok(())
+        }
+        AuthCommand::Import {
+            file,
+            with_passphrase,
+            force,
+        } => {
+            let armored = match file {
+                Some(file) => fs::read_to_string(file).map_err(|e| {
+                    PPCError::from(format!("could not read {}: {}", file.display(), e))
+                })?,
+                None if io::stdin().is_terminal() => {
+                    return Err(PPCError::from(
+                        "give the file of the export, or pipe it to stdin",
+                    ))
+                }
+                None => {
+                    let mut armored = String::new();
+                    io::stdin()
+                        .lock()
+                        .read_to_string(&mut armored)
+                        .map_err(|e| PPCError::from(format!("could not read stdin: {}", e)))?;
+                    armored
+                }
+            };
+
+            // the passphrase of the export is only asked for in a terminal,
+            // as stdin may carry the export.
+            let passphrase = prompt::secret("Passphrase of the export: ")?;
+            let profiles = import(&armored, &passphrase)?;
+            add_profiles(
+                &path,
+                &mut keys,
+                config,
+                &profiles,
+                protection(*with_passphrase),
+                *force,
+            )?;
+            let imported = profiles.keys().cloned().collect::<Vec<_>>().join(", ");
+            eprintln!(
+                "{}",
+                i18n::format("auth-imported", &[("profiles", imported.into())])
+            );
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+    use std::fs;
+
+    use secrecy::{ExposeSecret, SecretString};
+
+    use super::{add_profiles, encrypt_all, export, import, set_token, Keys, Protection};
+    use crate::config;
+
+    fn keys_in(dir: &tempfile::TempDir) -> Keys {
+        Keys::new(
+            dir.path().join("data"),
+            Some(SecretString::from("correct horse battery staple")),
+        )
+    }
+
+    #[test]
+    fn tokens_are_bound_to_their_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut keys = keys_in(&dir);
+        let token = SecretString::from("t0k3n");
+
+        for protection in [Protection::Local, Protection::Passphrase] {
+            let sealed = keys.seal("work", &token, protection).unwrap();
+            assert!(!sealed.contains("t0k3n"));
+
+            let sealed = SecretString::from(sealed);
+            assert_eq!(
+                keys.reveal("work", &sealed).unwrap().expose_secret(),
+                "t0k3n"
+            );
+            assert!(keys.reveal("private", &sealed).is_err());
+        }
+
+        let plain = SecretString::from("plain");
+        assert_eq!(
+            keys.reveal("work", &plain).unwrap().expose_secret(),
+            "plain"
+        );
+    }
+
+    #[test]
+    fn local_tokens_need_the_key_of_the_machine() {
+        let dir = tempfile::tempdir().unwrap();
+        let sealed = SecretString::from(
+            keys_in(&dir)
+                .seal("work", &SecretString::from("t0k3n"), Protection::Local)
+                .unwrap(),
+        );
+
+        let other = tempfile::tempdir().unwrap();
+        let err = keys_in(&other).reveal("work", &sealed).unwrap_err();
+        assert!(err.to_string().contains("auth export"));
+
+        let mut wrong = Keys::new(dir.path().join("data"), Some(SecretString::from("wrong")));
+        let sealed = SecretString::from(
+            keys_in(&dir)
+                .seal("work", &SecretString::from("t0k3n"), Protection::Passphrase)
+                .unwrap(),
+        );
+        assert!(wrong.reveal("work", &sealed).is_err());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn config_with_tokens_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(
+            &path,
+            "# mine\n[profiles.work]\nurl = \"pwpush.example.com\"\n",
+        )
+        .unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        let mut keys = keys_in(&dir);
+        set_token(
+            &path,
+            &mut keys,
+            "work",
+            &SecretString::from("t0k3n"),
+            Protection::Local,
+        )
+        .unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("# mine\n"));
+        assert!(content.contains("token = \"pwpush:local:"));
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        let config = config::load(Some(&path)).unwrap();
+        let token = config.profile("work").unwrap().token.clone().unwrap();
+        assert_eq!(
+            keys.reveal("work", &token).unwrap().expose_secret(),
+            "t0k3n"
+        );
+    }
+
+    #[test]
+    fn encrypt_all_skips_encrypted_tokens() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(
+            &path,
+            "[profiles.work]\ntoken = \"t0k3n\"\n[profiles.anonymous]\nurl = \"pwpush.com\"\n",
+        )
+        .unwrap();
+        let mut keys = keys_in(&dir);
+
+        let config = config::load(Some(&path)).unwrap();
+        let encrypted = encrypt_all(&path, &mut keys, &config, Protection::Local).unwrap();
+        assert_eq!(encrypted, vec![String::from("work")]);
+
+        let config = config::load(Some(&path)).unwrap();
+        assert!(encrypt_all(&path, &mut keys, &config, Protection::Local)
+            .unwrap()
+            .is_empty());
+        assert!(config.profile("anonymous").unwrap().token.is_none());
+    }
+
+    #[test]
+    fn export_and_import_move_profiles() {
+        let source = tempfile::tempdir().unwrap();
+        let path = source.path().join("config.toml");
+        fs::write(
+            &path,
+            "[profiles.work]\nurl = \"pwpush.example.com\"\nemail = \"user@example.com\"\n\
+             auth_scheme = \"header-pair\"\n",
+        )
+        .unwrap();
+        let mut keys = keys_in(&source);
+        set_token(
+            &path,
+            &mut keys,
+            "work",
+            &SecretString::from("t0k3n"),
+            Protection::Local,
+        )
+        .unwrap();
+
+        let config = config::load(Some(&path)).unwrap();
+        let transfer = SecretString::from("transfer passphrase");
+        let armored = export(&mut keys, &config.profiles, &transfer).unwrap();
+        assert!(armored.starts_with("-----BEGIN PWPUSH-CLI PROFILES-----\n"));
+        assert!(!armored.contains("t0k3n"));
+        assert!(import(&armored, &SecretString::from("wrong")).is_err());
+
+        // on the other machine.
+        let target = tempfile::tempdir().unwrap();
+        let path = target.path().join("config.toml");
+        fs::write(&path, "[profiles.work]\nurl = \"old.example.com\"\n").unwrap();
+        let mut keys = keys_in(&target);
+        let profiles = import(&armored, &transfer).unwrap();
+
+        let config = config::load(Some(&path)).unwrap();
+        assert!(add_profiles(
+            &path,
+            &mut keys,
+            &config,
+            &profiles,
+            Protection::Local,
+            false
+        )
+        .is_err());
+        add_profiles(
+            &path,
+            &mut keys,
+            &config,
+            &profiles,
+            Protection::Local,
+            true,
+        )
+        .unwrap();
+
+        let config = config::load(Some(&path)).unwrap();
+        let work = config.profile("work").unwrap();
+        assert_eq!(work.url.as_deref(), Some("pwpush.example.com"));
+        assert_eq!(work.email.as_deref(), Some("user@example.com"));
+        let token = work.token.clone().unwrap();
+        assert_eq!(
+            keys.reveal("work", &token).unwrap().expose_secret(),
+            "t0k3n"
+        );
+
+        assert!(import("garbage", &transfer).is_err());
+        assert!(add_profiles(
+            &path,
+            &mut keys,
+            &config,
+            &BTreeMap::new(),
+            Protection::Local,
+            false
+        )
+        .is_ok());
+    }
+}
@@ -0,0 +1,266 @@
+//! End-to-end encryption of payloads with existing key infrastructure.
+//!
+//! The payload is piped through an external encryption tool before pushing,
+//! so the instance only ever sees ciphertext. Which tool is used depends on
+//! the recipient:
+//!   - age recipients (`age1...`) and SSH public keys (`ssh-ed25519 ...`,
+//!     `ssh-rsa ...`) are encrypted with `age`
+//!   - everything else is taken as GnuPG key ID, fingerprint or email and
+//!     encrypted with `gpg`
+//!
+//! Both tools produce ASCII-armored output, which fits into a text push. For
+//! decryption, the tool is derived from the armor header. `age` needs an
+//! identity file, `gpg` uses the keyring and agent of the user.
+//!
+//! Archives of file pushes are encrypted to binary files instead, which the
+//! recipients decrypt with the tool itself, see `archive`.
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+
+use secrecy::SecretString;
+use tracing::debug;
+use zeroize::Zeroizing;
+
+use crate::errors::PPCError;
+
+/// External encryption tools.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Tool {
+    Age,
+    Gpg,
+}
+
+impl Tool {
+    fn program(self) -> &'static str {
+        match self {
+            Tool::Age => "age",
+            Tool::Gpg => "gpg",
+        }
+    }
+
+    /// Extension of files encrypted by the tool.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Tool::Age => "age",
+            Tool::Gpg => "gpg",
+        }
+    }
+
+    /// The tool that can encrypt for this recipient.
+    pub fn for_recipient(recipient: &str) -> Self {
+        if recipient.starts_with("age1") || recipient.starts_with("ssh-") {
+            Tool::Age
+        } else {
+            Tool::Gpg
+        }
+    }
+
+    /// The tool that produced this armored ciphertext, if any.
+    pub fn for_ciphertext(ciphertext: &str) -> Option<Self> {
+        let header = ciphertext.trim_start();
+        if header.starts_with("-----BEGIN AGE ENCRYPTED FILE-----") {
+            Some(Tool::Age)
+        } else if header.starts_with("-----BEGIN PGP MESSAGE-----") {
+            Some(Tool::Gpg)
+        } else {
+            None
+        }
+    }
+}
+
+/// Encrypt the payload for the recipient, resulting in armored text.
+pub fn encrypt(plaintext: &[u8], recipient: &str) -> Result<SecretString, PPCError> {
+    let tool = Tool::for_recipient(recipient);
+    let args = match tool {
+        Tool::Age => vec!["--encrypt", "--armor", "--recipient", recipient],
+        Tool::Gpg => vec![
+            "--batch",
+            "--yes",
+            "--armor",
+            "--encrypt",
+            "--recipient",
+            recipient,
+        ],
+    };
+
+    let output = run_filter(tool.program(), &args, plaintext)?;
+    // armored output is plain ASCII.
+    let text = String::from_utf8(output.to_vec())
+        .map_err(|_| PPCError::from(format!("{} produced unexpected output", tool.program())))?;
+    Ok(SecretString::from(text))
+}
+
+/// Encrypt the file `input` for the recipient into the binary file `output`,
+/// which must exist. Both are streamed, so files of any size can be
+/// encrypted.
+pub fn encrypt_file(input: &Path, output: &Path, recipient: &str) -> Result<(), PPCError> {
+    let tool = Tool::for_recipient(recipient);
+    let args = match tool {
+        Tool::Age => vec!["--encrypt", "--recipient", recipient],
+        Tool::Gpg => vec!["--batch", "--yes", "--encrypt", "--recipient", recipient],
+    };
+    run_file_filter(tool.program(), &args, input, output)
+}
+
+/// Decrypt an armored payload. `identity` is the identity file for age.
+pub fn decrypt(ciphertext: &str, identity: Option<&Path>) -> Result<Zeroizing<Vec<u8>>, PPCError> {
+    let tool = Tool::for_ciphertext(ciphertext).ok_or_else(|| {
+        PPCError::from("the payload is not an armored age or PGP message, it cannot be decrypted")
+    })?;
+
+    let identity = identity.map(|path| path.to_string_lossy().into_owned());
+    let args = match (tool, &identity) {
+        (Tool::Age, Some(identity)) => vec!["--decrypt", "--identity", identity.as_str()],
+        (Tool::Age, None) => {
+            return Err(PPCError::from(
+                "decrypting an age message needs an identity file, use --identity",
+            ))
+        }
+        (Tool::Gpg, _) => vec!["--quiet", "--decrypt"],
+    };
+
+    run_filter(tool.program(), &args, ciphertext.as_bytes())
+}
+
+/// Run `program` with `input` on stdin and return its stdout. stderr is passed
+/// through, so the user sees prompts and error messages of the tool.
+fn run_filter(program: &str, args: &[&str], input: &[u8]) -> Result<Zeroizing<Vec<u8>>, PPCError> {
+    debug!("running {} {}", program, args.join(" "));
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(|e| PPCError::from(format!("could not run {}: {}", program, e)))?;
+
+    // stdin is written from a separate thread, as the tool might fill the
+    // stdout pipe before it has read all input, which would block both sides.
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = Zeroizing::new(input.to_vec());
+    let writer = thread::spawn(move || stdin.write_all(&input));
+
+    let mut output = Zeroizing::new(Vec::new());
+    let read = child
+        .stdout
+        .take()
+        .expect("stdout is piped")
+        .read_to_end(&mut output);
+
+    let status = child
+        .wait()
+        .map_err(|e| PPCError::from(format!("could not run {}: {}", program, e)))?;
+    // a failed write means that the tool exited early, which is reported by
+    // the exit status below.
+    let _ = writer.join();
+    read.map_err(|e| PPCError::from(format!("could not read output of {}: {}", program, e)))?;
+
+    if !status.success() {
+        return Err(PPCError::from(format!(
+            "{} failed with {}",
+            program, status
+        )));
+    }
+    Ok(output)
+}
+
+/// Run `program` with the file `input` on stdin and its stdout written to the
+/// file `output`.
+fn run_file_filter(
+    program: &str,
+    args: &[&str],
+    input: &Path,
+    output: &Path,
+) -> Result<(), PPCError> {
+    debug!("running {} {}", program, args.join(" "));
+    let open = |path: &Path, write: bool| {
+        File::options()
+            .read(!write)
+            .write(write)
+            .truncate(write)
+            .open(path)
+            .map_err(|e| PPCError::from(format!("could not open {}: {}", path.display(), e)))
+    };
+
+    let status = Command::new(program)
+        .args(args)
+        .stdin(open(input, false)?)
+        .stdout(open(output, true)?)
+        .stderr(Stdio::inherit())
+        .status()
+        .map_err(|e| PPCError::from(format!("could not run {}: {}", program, e)))?;
+    if !status.success() {
+        return Err(PPCError::from(format!(
+            "{} failed with {}",
+            program, status
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decrypt, run_file_filter, run_filter, Tool};
+
+    #[test]
+    fn tool_for_recipient() {
+        assert_eq!(
+            Tool::for_recipient("age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p"),
+            Tool::Age
+        );
+        assert_eq!(
+            Tool::for_recipient("ssh-ed25519 AAAAC3Nza user@host"),
+            Tool::Age
+        );
+        assert_eq!(Tool::for_recipient("user@example.com"), Tool::Gpg);
+        assert_eq!(Tool::for_recipient("0xDEADBEEF"), Tool::Gpg);
+    }
+
+    #[test]
+    fn tool_for_ciphertext() {
+        assert_eq!(
+            Tool::for_ciphertext("-----BEGIN AGE ENCRYPTED FILE-----\nYWdl\n"),
+            Some(Tool::Age)
+        );
+        assert_eq!(
+            Tool::for_ciphertext("\n-----BEGIN PGP MESSAGE-----\n\nhQ\n"),
+            Some(Tool::Gpg)
+        );
+        assert_eq!(Tool::for_ciphertext("plain secret"), None);
+    }
+
+    #[test]
+    fn decrypt_needs_identity_for_age() {
+        assert!(decrypt("-----BEGIN AGE ENCRYPTED FILE-----\n", None).is_err());
+        assert!(decrypt("plain secret", None).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_filter_pipes_through_program() {
+        let output = run_filter("cat", &[], b"secret").unwrap();
+        assert_eq!(&output[..], b"secret");
+
+        assert!(run_filter("false", &[], b"secret").is_err());
+        assert!(run_filter("pwpush-cli-does-not-exist", &[], b"").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_file_filter_streams_between_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("archive.tar.gz");
+        let output = dir.path().join("archive.tar.gz.age");
+        std::fs::write(&input, b"archive").unwrap();
+        std::fs::write(&output, b"left over from before").unwrap();
+
+        run_file_filter("cat", &[], &input, &output).unwrap();
+        assert_eq!(std::fs::read(&output).unwrap(), b"archive");
+
+        assert!(run_file_filter("false", &[], &input, &output).is_err());
+    }
+}
@@ -0,0 +1,181 @@
+//! Rendering of the timestamps reported by the API.
+//!
+//! The API reports times in ISO-8601, which is what scripts want, but not what
+//! a person at the terminal wants to read. `--date-format` selects one of
+//!   - `iso`: ISO-8601 in UTC, e.g. `2023-12-01T10:15:23Z`
+//!   - `relative`: distance to now, e.g. `3 hours ago` or `in 2 days`
+//!   - `local`: date and time in the local time zone
+//!   - any other value is taken as strftime pattern, e.g. `%d.%m.%Y %H:%M`
+//!
+//! Without `--date-format`, relative dates are used if stdout is a terminal,
+//! and ISO-8601 otherwise. Values that cannot be parsed as date are shown as
+//! the API sent them.
+
+use std::io::{self, IsTerminal};
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local, NaiveDate, SecondsFormat, Utc};
+
+use crate::args::PPCArgs;
+use crate::i18n;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DateFormat {
+    Iso,
+    Relative,
+    Local,
+    Strftime(String),
+}
+
+/// Parse a date format. Used as value parser for clap, hence the plain string
+/// as error.
+pub fn parse(value: &str) -> Result<DateFormat, String> {
+    match value {
+        "iso" => Ok(DateFormat::Iso),
+        "relative" => Ok(DateFormat::Relative),
+        "local" => Ok(DateFormat::Local),
+        pattern => {
+            if StrftimeItems::new(pattern).any(|item| item == Item::Error) {
+                Err(format!(
+                    "`{}` is neither iso, relative, local nor a valid strftime pattern",
+                    pattern
+                ))
+            } else {
+                Ok(DateFormat::Strftime(String::from(pattern)))
+            }
+        }
+    }
+}
+
+/// Renders dates in the selected format. Relative dates are computed against
+/// the time the formatter was created, so all dates of one output agree.
+pub struct DateFormatter {
+    format: DateFormat,
+    now: DateTime<Utc>,
+}
+
+impl DateFormatter {
+    pub fn new(args: &PPCArgs) -> Self {
+        Self::at(args, Utc::now())
+    }
+
+    /// Like `new`, with relative dates computed against `now`, e.g. the time
+    /// of the instance, see `skew`.
+    pub fn at(args: &PPCArgs, now: DateTime<Utc>) -> Self {
+        let format = args.date_format.clone().unwrap_or_else(|| {
+            if io::stdout().is_terminal() {
+                DateFormat::Relative
+            } else {
+                DateFormat::Iso
+            }
+        });
+        Self::with(format, now)
+    }
+
+    pub fn with(format: DateFormat, now: DateTime<Utc>) -> Self {
+        Self { format, now }
+    }
+
+    pub fn is_relative(&self) -> bool {
+        self.format == DateFormat::Relative
+    }
+
+    pub fn format(&self, value: &str) -> String {
+        let Some(date) = parse_date(value) else {
+            return String::from(value);
+        };
+
+        match &self.format {
+            DateFormat::Iso => date.to_rfc3339_opts(SecondsFormat::Secs, true),
+            DateFormat::Relative => relative(date, self.now),
+            DateFormat::Local => date
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S %Z")
+                .to_string(),
+            DateFormat::Strftime(pattern) => date.with_timezone(&Local).format(pattern).to_string(),
+        }
+    }
+}
+
+/// Timestamps come with time and offset, but some fields only hold a date.
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Some(date.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|date| date.and_utc())
+}
+
+fn relative(date: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let seconds = (date - now).num_seconds();
+    let distance = seconds.unsigned_abs();
+
+    let (amount, unit) = match distance {
+        0..=59 => return i18n::text("date-just-now"),
+        60..=3599 => (distance / 60, "minutes"),
+        3600..=86399 => (distance / 3600, "hours"),
+        _ => (distance / 86400, "days"),
+    };
+
+    let id = if seconds < 0 {
+        format!("date-{}-ago", unit)
+    } else {
+        format!("date-in-{}", unit)
+    };
+    i18n::format(&id, &[("amount", amount.into())])
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{DateTime, Utc};
+
+    use super::{parse, DateFormat, DateFormatter};
+
+    fn formatter(format: DateFormat) -> DateFormatter {
+        let now = DateTime::parse_from_rfc3339("2023-12-03T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        DateFormatter::with(format, now)
+    }
+
+    #[test]
+    fn parse_formats() {
+        assert_eq!(parse("iso"), Ok(DateFormat::Iso));
+        assert_eq!(parse("relative"), Ok(DateFormat::Relative));
+        assert_eq!(
+            parse("%d.%m.%Y"),
+            Ok(DateFormat::Strftime(String::from("%d.%m.%Y")))
+        );
+        assert!(parse("%Q").is_err());
+    }
+
+    #[test]
+    fn iso_is_normalized_to_utc() {
+        let dates = formatter(DateFormat::Iso);
+
+        assert_eq!(
+            dates.format("2023-12-01T11:15:23.121+01:00"),
+            "2023-12-01T10:15:23Z"
+        );
+        assert_eq!(dates.format("2023-12-02"), "2023-12-02T00:00:00Z");
+    }
+
+    #[test]
+    fn relative_dates() {
+        let dates = formatter(DateFormat::Relative);
+
+        assert_eq!(dates.format("2023-12-03T11:59:30Z"), "just now");
+        assert_eq!(dates.format("2023-12-03T11:00:00Z"), "1 hour ago");
+        assert_eq!(dates.format("2023-12-01T10:00:00Z"), "2 days ago");
+        assert_eq!(dates.format("2023-12-03T12:05:00Z"), "in 5 minutes");
+    }
+
+    #[test]
+    fn unparseable_dates_are_kept() {
+        let dates = formatter(DateFormat::Iso);
+
+        assert_eq!(dates.format("yesterday"), "yesterday");
+    }
+}
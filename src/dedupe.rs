@@ -0,0 +1,244 @@
+//! Detection of secrets that are pushed twice by accident.
+//!
+//! With `--dedupe`, the payload of a text push is compared with the payloads
+//! pushed within `--dedupe-window` (24 hours by default). If the same secret
+//! was pushed before, the push is aborted, or with `--dedupe warn` only warned
+//! about, so that nobody ends up with two active links to the same secret.
+//!
+//! Payloads are not stored, only their HMAC-SHA256 with a random key that is
+//! generated on first use. A plain hash would let anybody who can read the
+//! state file try out guesses of weak secrets, the key keeps the hashes useless
+//! without it. The hashes live in `dedupe` in the data directory, e.g.
+//! `~/.local/share/pwpush-cli/dedupe` on Linux, the key in the key directory
+//! (see `paths` and `state`), and both are only readable by the user. Entries older than the window are dropped
+//! whenever a push is recorded.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use base64::prelude::{Engine, BASE64_STANDARD};
+use chrono::{DateTime, SecondsFormat, Utc};
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tracing::{debug, warn};
+use zeroize::Zeroizing;
+
+use crate::args::{DedupeMode, PPCText};
+use crate::errors::PPCError;
+use crate::{fail_on, i18n, paths, state};
+
+const KEY_FILE: &str = "dedupe.key";
+const RECENT_FILE: &str = "recent.json";
+
+/// Default of `--dedupe-window`, for pushes that are not created from the
+/// command line.
+pub const DEFAULT_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A payload that was pushed, identified by its keyed hash.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Entry {
+    hash: String,
+    pushed_at: DateTime<Utc>,
+}
+
+/// The recently pushed payloads.
+pub struct Recent {
+    path: PathBuf,
+    key: Zeroizing<Vec<u8>>,
+    entries: Vec<Entry>,
+}
+
+impl Recent {
+    /// Open the state in the directory, with its key in the key directory
+    /// `keys`, creating the directories and the key if they do not exist yet.
+    pub fn open(dir: &Path, keys: &Path) -> Result<Self, PPCError> {
+        state::create_private_dir(dir)?;
+        let key = state::key(&state::key_file(keys, dir, KEY_FILE)?)?;
+
+        let path = dir.join(RECENT_FILE);
+        let entries = if path.exists() {
+            let content = fs::read(&path)
+                .map_err(|e| PPCError::from(format!("could not read {}: {}", path.display(), e)))?;
+            serde_json::from_slice(&content).map_err(|e| {
+                PPCError::from(format!(
+                    "could not parse {}: {}. Remove it to start over",
+                    path.display(),
+                    e
+                ))
+            })?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Recent { path, key, entries })
+    }
+
+    fn hash(&self, payload: &SecretString) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts any key");
+        mac.update(payload.expose_secret().as_bytes());
+        BASE64_STANDARD.encode(mac.finalize().into_bytes())
+    }
+
+    /// When the payload was last pushed within the window, if it was.
+    pub fn last_pushed(
+        &self,
+        payload: &SecretString,
+        window: Duration,
+        now: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        let hash = self.hash(payload);
+        self.entries
+            .iter()
+            .filter(|entry| entry.hash == hash && within(entry, window, now))
+            .map(|entry| entry.pushed_at)
+            .max()
+    }
+
+    /// Remember that the payload was pushed now, and forget everything that
+    /// is older than the window.
+    pub fn record(
+        &mut self,
+        payload: &SecretString,
+        window: Duration,
+        now: DateTime<Utc>,
+    ) -> Result<(), PPCError> {
+        let hash = self.hash(payload);
+        self.entries.retain(|entry| within(entry, window, now));
+        self.entries.push(Entry {
+            hash,
+            pushed_at: now,
+        });
+
+        debug!("recording push in {}", self.path.display());
+        state::replace_private(&self.path, &serde_json::to_vec(&self.entries).unwrap())
+    }
+}
+
+fn within(entry: &Entry, window: Duration, now: DateTime<Utc>) -> bool {
+    chrono::Duration::from_std(window)
+        .map(|window| entry.pushed_at > now - window)
+        .unwrap_or(true)
+}
+
+/// Default location of the state.
+pub fn default_dir() -> Result<PathBuf, PPCError> {
+    state::dir("dedupe")
+}
+
+/// Check the payload against the recent pushes if `--dedupe` was given. The
+/// returned state is used to record the push once it was created.
+pub fn check(
+    ppc_text: &PPCText,
+    payload: &SecretString,
+    now: DateTime<Utc>,
+) -> Result<Option<Recent>, PPCError> {
+    let Some(mode) = ppc_text.dedupe else {
+        return Ok(None);
+    };
+
+    let recent = Recent::open(&default_dir()?, &paths::key_dir()?)?;
+    if let Some(pushed_at) = recent.last_pushed(payload, ppc_text.dedupe_window, now) {
+        let date = [(
+            "date",
+            pushed_at.to_rfc3339_opts(SecondsFormat::Secs, true).into(),
+        )];
+        match mode {
+            DedupeMode::Abort => {
+                return Err(PPCError::from(i18n::format(
+                    "push-duplicate-refused",
+                    &date,
+                )))
+            }
+            DedupeMode::Warn => warn!("{}", i18n::format("push-duplicate", &date)),
+        }
+    }
+    Ok(Some(recent))
+}
+
+/// Record a push that was created. The push exists already, so failing to
+/// record it is only warned about.
+pub fn record(
+    recent: Option<Recent>,
+    ppc_text: &PPCText,
+    payload: &SecretString,
+    now: DateTime<Utc>,
+) {
+    if let Some(mut recent) = recent {
+        if let Err(e) = recent.record(payload, ppc_text.dedupe_window, now) {
+            warn!(
+                "{}",
+                i18n::format("push-not-deduplicated", &[("error", e.to_string().into())])
+            );
+            fail_on::soft_failure();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use base64::prelude::{Engine, BASE64_STANDARD};
+    use chrono::{TimeZone, Utc};
+    use secrecy::SecretString;
+    use sha2::{Digest, Sha256};
+
+    use super::Recent;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    #[test]
+    fn finds_recent_pushes() {
+        let dir = tempfile::tempdir().unwrap();
+        let keys = tempfile::tempdir().unwrap();
+        let now = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let payload = SecretString::from("s3cret");
+
+        let mut recent = Recent::open(dir.path(), keys.path()).unwrap();
+        assert_eq!(recent.last_pushed(&payload, DAY, now), None);
+        recent.record(&payload, DAY, now).unwrap();
+
+        let later = now + chrono::Duration::hours(3);
+        let recent = Recent::open(dir.path(), keys.path()).unwrap();
+        assert_eq!(recent.last_pushed(&payload, DAY, later), Some(now));
+        assert_eq!(
+            recent.last_pushed(&SecretString::from("other"), DAY, later),
+            None
+        );
+        assert_eq!(
+            recent.last_pushed(&payload, DAY, now + chrono::Duration::days(2)),
+            None
+        );
+    }
+
+    #[test]
+    fn stores_keyed_hashes_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let keys = tempfile::tempdir().unwrap();
+        let now = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+
+        let mut recent = Recent::open(dir.path(), keys.path()).unwrap();
+        recent
+            .record(&SecretString::from("s3cret"), DAY, now)
+            .unwrap();
+        recent
+            .record(
+                &SecretString::from("other"),
+                DAY,
+                now + chrono::Duration::days(2),
+            )
+            .unwrap();
+
+        let content = std::fs::read_to_string(dir.path().join("recent.json")).unwrap();
+        assert!(!content.contains("s3cret"));
+        assert!(!content.contains(&BASE64_STANDARD.encode(Sha256::digest("s3cret"))));
+        // the first push is outside of the window of the second one.
+        assert_eq!(
+            Recent::open(dir.path(), keys.path()).unwrap().entries.len(),
+            1
+        );
+    }
+}
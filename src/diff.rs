@@ -0,0 +1,248 @@
+//! Field-level comparison of two pushes.
+//!
+//! After `rotate` or `clone`, the old link should only be expired once the new
+//! push is known to be right. `diff <url_token_a> <url_token_b>` shows the
+//! metadata fields that differ between two pushes, like the note, the limits
+//! or the viewer settings.
+//!
+//! The metadata is looked up in the pushes of the account like `info` does,
+//! so no view is used up. With `--payload`, both pushes are retrieved instead,
+//! which counts as a view of each, and the output tells whether the payloads
+//! are the same, e.g. to verify that a rotated secret actually changed. The
+//! payloads themselves are never printed.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::args::{PPCArgs, PPCDiff};
+use crate::errors::PPCError;
+use crate::http::Transport;
+use crate::instance::Instance;
+use crate::models::Push;
+use crate::{info, output, repush};
+
+/// A metadata field with different values in the two pushes.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Difference {
+    pub field: String,
+    pub a: Value,
+    pub b: Value,
+}
+
+/// How the payloads of the two pushes compare.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PayloadComparison {
+    Same,
+    Different,
+    /// At least one of the pushes has no payload anymore, e.g. because it is
+    /// expired.
+    Unavailable,
+}
+
+impl PayloadComparison {
+    fn name(self) -> &'static str {
+        match self {
+            PayloadComparison::Same => "same",
+            PayloadComparison::Different => "different",
+            PayloadComparison::Unavailable => "unavailable",
+        }
+    }
+}
+
+/// Entry point for the `diff` command.
+pub fn run(
+    transport: &dyn Transport,
+    instance: &Instance,
+    args: &PPCArgs,
+    ppc_diff: &PPCDiff,
+) -> Result<(), PPCError> {
+    let a = fetch(transport, instance, ppc_diff, &ppc_diff.url_token_a)?;
+    let b = fetch(transport, instance, ppc_diff, &ppc_diff.url_token_b)?;
+
+    let differences = differences(&a, &b);
+    let payload = ppc_diff.payload.then(|| compare_payloads(&a, &b));
+
+    if args.json_output {
+        let output = json!({
+            "a": a.url_token,
+            "b": b.url_token,
+            "differences": differences,
+            "payload": payload.map(PayloadComparison::name),
+        });
+        output::print_json(&output);
+    } else {
+        print!("{}", format_diff(&differences, payload));
+    }
+    Ok(())
+}
+
+/// The push with its metadata, and with `--payload` also its payload.
+fn fetch(
+    transport: &dyn Transport,
+    instance: &Instance,
+    ppc_diff: &PPCDiff,
+    url_token: &str,
+) -> Result<Push, PPCError> {
+    if ppc_diff.payload {
+        repush::retrieve(
+            transport,
+            instance,
+            ppc_diff.kind,
+            url_token,
+            ppc_diff.passphrase.as_ref(),
+        )
+    } else {
+        info::lookup(transport, instance, ppc_diff.kind, url_token).map(|(push, _)| push)
+    }
+}
+
+/// The metadata fields that differ, in the order the instance reports them.
+/// The URL token always differs and the payload is never shown, so both are
+/// left out.
+pub fn differences(a: &Push, b: &Push) -> Vec<Difference> {
+    let (a, b) = (metadata(a), metadata(b));
+
+    a.iter()
+        .filter(|(field, value)| b.get(*field).unwrap_or(&Value::Null) != *value)
+        .map(|(field, value)| Difference {
+            field: field.clone(),
+            a: value.clone(),
+            b: b.get(field).cloned().unwrap_or(Value::Null),
+        })
+        .collect()
+}
+
+fn metadata(push: &Push) -> serde_json::Map<String, Value> {
+    let push = Push {
+        payload: None,
+        ..push.clone()
+    };
+    // the push only consists of strings, numbers and bools, so serialization
+    // cannot fail.
+    let Value::Object(mut fields) = serde_json::to_value(&push).unwrap() else {
+        unreachable!("a push is serialized as object");
+    };
+    fields.shift_remove("url_token");
+    fields
+}
+
+pub fn compare_payloads(a: &Push, b: &Push) -> PayloadComparison {
+    match (&a.payload, &b.payload) {
+        (Some(a), Some(b)) if a == b => PayloadComparison::Same,
+        (Some(_), Some(_)) => PayloadComparison::Different,
+        _ => PayloadComparison::Unavailable,
+    }
+}
+
+pub fn format_diff(differences: &[Difference], payload: Option<PayloadComparison>) -> String {
+    let show = |value: &Value| match value {
+        Value::Null => String::from("-"),
+        value => value.to_string(),
+    };
+
+    let mut out = String::new();
+    if differences.is_empty() {
+        out += "metadata is the same\n";
+    }
+    for difference in differences {
+        out += &format!(
+            "{}: {} -> {}\n",
+            difference.field.replace('_', " "),
+            show(&difference.a),
+            show(&difference.b)
+        );
+    }
+    match payload {
+        Some(PayloadComparison::Same) => out += "payload: same\n",
+        Some(PayloadComparison::Different) => out += "payload: different\n",
+        Some(PayloadComparison::Unavailable) => {
+            out += "payload: unavailable, at least one of the pushes is expired\n"
+        }
+        None => {}
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+    use serde_json::json;
+
+    use super::{compare_payloads, differences, format_diff, run, PayloadComparison};
+    use crate::args::{PPCAction, PPCArgs};
+    use crate::config::PPCConfig;
+    use crate::http::mock::MockTransport;
+    use crate::instance::Instance;
+    use crate::models::Push;
+
+    fn push(json: serde_json::Value) -> Push {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn only_differing_fields_are_reported() {
+        let a = push(json!({
+            "url_token": "abc",
+            "note": "db",
+            "expire_after_views": 5,
+            "retrieval_step": true,
+            "payload": "old",
+        }));
+        let b = push(json!({
+            "url_token": "def",
+            "note": "db (rotated)",
+            "expire_after_views": 5,
+            "payload": "new",
+        }));
+
+        let differences = differences(&a, &b);
+
+        let fields: Vec<_> = differences.iter().map(|d| d.field.as_str()).collect();
+        assert_eq!(fields, vec!["retrieval_step", "note"]);
+        assert_eq!(differences[1].a, json!("db"));
+        assert_eq!(differences[1].b, json!("db (rotated)"));
+    }
+
+    #[test]
+    fn payloads_are_compared_without_showing_them() {
+        let a = push(json!({"url_token": "abc", "payload": "old"}));
+        let b = push(json!({"url_token": "def", "payload": "new"}));
+        let expired = push(json!({"url_token": "ghi", "expired": true}));
+
+        assert_eq!(compare_payloads(&a, &a), PayloadComparison::Same);
+        assert_eq!(compare_payloads(&a, &b), PayloadComparison::Different);
+        assert_eq!(
+            compare_payloads(&a, &expired),
+            PayloadComparison::Unavailable
+        );
+
+        let out = format_diff(&differences(&a, &b), Some(compare_payloads(&a, &b)));
+        assert_eq!(out, "metadata is the same\npayload: different\n");
+        assert!(!out.contains("old"));
+    }
+
+    #[test]
+    fn metadata_comes_from_the_account_lists() {
+        let args = PPCArgs::parse_from(["pwpush-cli", "diff", "abc", "def"]);
+        let instance = Instance::resolve(&args, &PPCConfig::default()).unwrap();
+        let transport = MockTransport::new()
+            .respond(200, r#"[{"url_token":"abc"}]"#)
+            .respond(200, r#"[]"#)
+            .respond(200, r#"[{"url_token":"def"}]"#);
+        let PPCAction::Diff(ppc_diff) = &args.action else {
+            panic!("not a diff command");
+        };
+
+        run(&transport, &instance, &args, ppc_diff).unwrap();
+
+        let urls: Vec<_> = transport.requests().into_iter().map(|r| r.url).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://pwpush.com/p/active.json",
+                "https://pwpush.com/p/active.json",
+                "https://pwpush.com/p/expired.json",
+            ]
+        );
+    }
+}
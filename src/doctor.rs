@@ -0,0 +1,473 @@
+//! The `doctor` command, which checks the setup of the CLI.
+//!
+//! Problems with the setup show up as errors of unrelated commands, often
+//! only for one of several profiles. `doctor` checks everything at once and
+//! prints a report:
+//!   - the configuration file and the policy file can be read and parsed
+//!   - files with secrets, i.e. the configuration file with tokens and the
+//!     data and key directories, see `state`, are not readable by other users
+//!   - each instance, the one selected by the command line and those of all
+//!     profiles, can be reached, with TLS unless it is a loopback address,
+//!     and is allowed by the policy
+//!   - the credentials are accepted, with an authenticated request for the
+//!     list of active text pushes, which only reads metadata
+//!   - the keyring can be used and has the SMTP password, if an SMTP server
+//!     is configured
+//!
+//! The configuration is not loaded before the command runs, so broken files
+//! are reported like any other failed check. Failed checks fail the command,
+//! warnings are soft failures, see `fail_on`.
+
+use std::fs;
+use std::path::Path;
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use tracing::debug;
+
+use crate::args::{InstanceProtocol, PPCArgs, PushKind, PushState};
+use crate::config::{self, PPCConfig};
+use crate::errors::PPCError;
+use crate::http::{Transport, TLS_BACKEND};
+use crate::instance::Instance;
+use crate::{credentials, fail_on, output, paths, pwpush_api, state};
+
+/// Outcome of a check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Pass,
+    Warn,
+    Fail,
+    /// The check does not apply, e.g. credentials of a profile without any
+    Skip,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct Check {
+    pub name: String,
+    pub status: Status,
+    pub detail: String,
+}
+
+/// The checks so far, in the order they ran.
+#[derive(Default)]
+struct Report {
+    checks: Vec<Check>,
+}
+
+impl Report {
+    fn add(&mut self, name: &str, status: Status, detail: impl Into<String>) {
+        let detail = detail.into();
+        debug!("check {}: {:?}, {}", name, status, detail);
+        self.checks.push(Check {
+            name: String::from(name),
+            status,
+            detail,
+        });
+    }
+
+    fn count(&self, status: Status) -> usize {
+        self.checks
+            .iter()
+            .filter(|check| check.status == status)
+            .count()
+    }
+}
+
+pub fn run(transport: &dyn Transport, args: &PPCArgs) -> Result<(), PPCError> {
+    let mut report = Report::default();
+
+    let config = check_config(&mut report, args);
+    check_permissions(&mut report, args, &config);
+
+    let selected = Instance::resolve(args, &config).and_then(|instance| {
+        config.policy.check_instance(&instance)?;
+        Ok(instance)
+    });
+    check_instance(&mut report, transport, "instance", selected);
+    for name in config.profiles.keys() {
+        let label = format!("profile {}", name);
+        check_instance(
+            &mut report,
+            transport,
+            &label,
+            Instance::from_profile(&config, name),
+        );
+    }
+
+    check_keyring(&mut report, &config);
+
+    if args.json_output {
+        output::print_json(&report.checks);
+    } else {
+        print!("{}", format_report(&report));
+    }
+
+    for _ in 0..report.count(Status::Warn) {
+        fail_on::soft_failure();
+    }
+    match report.count(Status::Fail) {
+        0 => Ok(()),
+        failed => Err(PPCError::from(format!(
+            "{} of {} checks failed",
+            failed,
+            report.checks.len()
+        ))),
+    }
+}
+
+/// The configuration, or the defaults if it cannot be loaded, so the other
+/// checks still run.
+fn check_config(report: &mut Report, args: &PPCArgs) -> PPCConfig {
+    let path = match config::path(args.config_file.as_deref()) {
+        Ok(path) => path,
+        Err(e) => {
+            report.add("configuration", Status::Fail, e.to_string());
+            return PPCConfig::default();
+        }
+    };
+
+    match config::load(Some(&path)) {
+        Ok(config) if !path.exists() => {
+            report.add(
+                "configuration",
+                Status::Skip,
+                format!("{} does not exist, the defaults apply", path.display()),
+            );
+            config
+        }
+        Ok(config) => {
+            report.add(
+                "configuration",
+                Status::Pass,
+                format!(
+                    "{} is valid, with {} profiles",
+                    path.display(),
+                    config.profiles.len()
+                ),
+            );
+            config
+        }
+        Err(e) => {
+            report.add("configuration", Status::Fail, e.to_string());
+            PPCConfig::default()
+        }
+    }
+}
+
+fn check_permissions(report: &mut Report, args: &PPCArgs, config: &PPCConfig) {
+    if let Ok(path) = config::path(args.config_file.as_deref()) {
+        let has_tokens = config
+            .profiles
+            .values()
+            .any(|profile| profile.token.is_some());
+        if path.exists() && has_tokens && state::readable_by_others(&path) {
+            let (status, what) = if credentials::has_plain_tokens(config) {
+                (Status::Fail, "tokens in plain text")
+            } else {
+                (Status::Warn, "encrypted tokens")
+            };
+            report.add(
+                "permissions",
+                status,
+                format!(
+                    "{} has {} and can be read by other users, restrict it with `chmod 600`",
+                    path.display(),
+                    what
+                ),
+            );
+        } else if path.exists() {
+            report.add(
+                "permissions",
+                Status::Pass,
+                format!("{} is safe", path.display()),
+            );
+        }
+    }
+
+    for dir in [paths::data_dir(), paths::key_dir()] {
+        let Ok(dir) = dir else {
+            continue;
+        };
+        if !dir.exists() {
+            continue;
+        }
+        let exposed = exposed_files(&dir);
+        if exposed.is_empty() {
+            report.add(
+                "permissions",
+                Status::Pass,
+                format!("{} is only accessible by the user", dir.display()),
+            );
+        } else {
+            report.add(
+                "permissions",
+                Status::Fail,
+                format!(
+                    "{} can be read by other users, restrict them with `chmod -R go-rwx {}`",
+                    exposed.join(", "),
+                    dir.display()
+                ),
+            );
+        }
+    }
+}
+
+/// Files and directories below `dir`, and `dir` itself, that others can
+/// access. The data directory is flat, so one level of subdirectories is
+/// enough.
+fn exposed_files(dir: &Path) -> Vec<String> {
+    let mut paths = vec![dir.to_path_buf()];
+    let mut pending = vec![(dir.to_path_buf(), 0)];
+    while let Some((dir, depth)) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if depth < 1 && path.is_dir() {
+                pending.push((path.clone(), depth + 1));
+            }
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    paths
+        .iter()
+        .filter(|path| state::readable_by_others(path))
+        .map(|path| path.display().to_string())
+        .collect()
+}
+
+/// Check that the instance can be reached and accepts the credentials.
+fn check_instance(
+    report: &mut Report,
+    transport: &dyn Transport,
+    label: &str,
+    instance: Result<Instance, PPCError>,
+) {
+    let mut instance = match instance {
+        Ok(instance) => instance,
+        Err(e) => {
+            report.add(label, Status::Fail, e.to_string());
+            return;
+        }
+    };
+
+    let connection = format!("{}: connection", label);
+    match pwpush_api::server_version(transport, &instance) {
+        Ok(version) => {
+            let version = version
+                .application_version
+                .map(|version| format!(", version {}", version))
+                .unwrap_or_default();
+            report.add(
+                &connection,
+                Status::Pass,
+                format!("{} answered{}", instance.base_url(), version),
+            );
+        }
+        Err(e) => {
+            report.add(&connection, Status::Fail, e.to_string());
+            return;
+        }
+    }
+
+    let tls = format!("{}: TLS", label);
+    match instance.protocol {
+        InstanceProtocol::Https => report.add(
+            &tls,
+            Status::Pass,
+            format!("certificate accepted by {}", TLS_BACKEND),
+        ),
+        InstanceProtocol::Http if is_loopback(&instance.url) => {
+            report.add(&tls, Status::Skip, "plain HTTP to a loopback address")
+        }
+        InstanceProtocol::Http => report.add(
+            &tls,
+            Status::Warn,
+            "plain HTTP, tokens and payloads are sent unencrypted",
+        ),
+    }
+
+    let credentials = format!("{}: credentials", label);
+    if instance.token.is_none() {
+        report.add(&credentials, Status::Skip, "no credentials configured");
+        return;
+    }
+    instance.detect_auth_scheme(transport);
+    match pwpush_api::list(transport, &instance, PushKind::Text, PushState::Active) {
+        Ok(_) => report.add(
+            &credentials,
+            Status::Pass,
+            match &instance.email {
+                Some(email) => format!("accepted for {}", email),
+                None => String::from("accepted"),
+            },
+        ),
+        Err(e) => report.add(&credentials, Status::Fail, e.to_string()),
+    }
+}
+
+fn is_loopback(url: &str) -> bool {
+    let host = url.split(['/', ':']).next().unwrap_or_default();
+    host == "localhost"
+        || host
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback())
+}
+
+#[cfg(feature = "email")]
+fn check_keyring(report: &mut Report, config: &PPCConfig) {
+    if config.smtp.host.is_none() || config.smtp.username.is_none() {
+        report.add(
+            "keyring",
+            Status::Skip,
+            "no SMTP server with login configured",
+        );
+        return;
+    }
+    match crate::email::has_password(&config.smtp) {
+        Ok(true) => report.add("keyring", Status::Pass, "has the SMTP password"),
+        Ok(false) => report.add(
+            "keyring",
+            Status::Warn,
+            "has no SMTP password, store it with `smtp set-password`",
+        ),
+        Err(e) => report.add("keyring", Status::Fail, e.to_string()),
+    }
+}
+
+#[cfg(not(feature = "email"))]
+fn check_keyring(report: &mut Report, _config: &PPCConfig) {
+    report.add(
+        "keyring",
+        Status::Skip,
+        "only used for email, which this build does not support",
+    );
+}
+
+fn format_report(report: &Report) -> String {
+    let width = report
+        .checks
+        .iter()
+        .map(|check| check.name.len())
+        .max()
+        .unwrap_or_default();
+
+    let mut out = String::new();
+    for check in &report.checks {
+        let status = match check.status {
+            Status::Pass => "ok",
+            Status::Warn => "warn",
+            Status::Fail => "FAIL",
+            Status::Skip => "skip",
+        };
+        out += &format!(
+            "{:<4}  {:<width$}  {}\n",
+            status,
+            check.name,
+            check.detail,
+            width = width
+        );
+    }
+    out += &format!(
+        "\n{} passed, {} warned, {} failed\n",
+        report.count(Status::Pass),
+        report.count(Status::Warn),
+        report.count(Status::Fail)
+    );
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::{check_instance, exposed_files, format_report, is_loopback, Report, Status};
+    use crate::args::{AuthScheme, InstanceProtocol};
+    use crate::errors::PPCError;
+    use crate::http::mock::MockTransport;
+    use crate::instance::Instance;
+
+    fn instance(protocol: InstanceProtocol, token: Option<&str>) -> Instance {
+        Instance {
+            protocol,
+            url: String::from("pwpush.example.com"),
+            email: None,
+            token: token.map(Into::into),
+            auth_scheme: Some(AuthScheme::Bearer),
+        }
+    }
+
+    fn statuses(report: &Report) -> Vec<(&str, Status)> {
+        report
+            .checks
+            .iter()
+            .map(|check| (check.name.as_str(), check.status))
+            .collect()
+    }
+
+    #[test]
+    fn instances_are_checked_step_by_step() {
+        let transport = MockTransport::new()
+            .respond(200, r#"{"application_version":"1.50.0"}"#)
+            .respond(401, r#"{"error":"unauthorized"}"#)
+            .respond(200, "{}")
+            .fail("connection refused");
+        let mut report = Report::default();
+
+        let https = instance(InstanceProtocol::Https, Some("t0k3n"));
+        check_instance(&mut report, &transport, "instance", Ok(https));
+        let http = instance(InstanceProtocol::Http, None);
+        check_instance(&mut report, &transport, "profile plain", Ok(http.clone()));
+        check_instance(&mut report, &transport, "profile down", Ok(http));
+        let error = PPCError::from("profile `x` does not exist");
+        check_instance(&mut report, &transport, "profile x", Err(error));
+
+        assert_eq!(
+            statuses(&report),
+            vec![
+                ("instance: connection", Status::Pass),
+                ("instance: TLS", Status::Pass),
+                ("instance: credentials", Status::Fail),
+                ("profile plain: connection", Status::Pass),
+                ("profile plain: TLS", Status::Warn),
+                ("profile plain: credentials", Status::Skip),
+                ("profile down: connection", Status::Fail),
+                ("profile x", Status::Fail),
+            ]
+        );
+        assert!(report.checks[0].detail.contains("version 1.50.0"));
+
+        let text = format_report(&report);
+        assert!(text.contains("FAIL  instance: credentials"));
+        assert!(text.ends_with("\n3 passed, 1 warned, 3 failed\n"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn exposed_files_are_found() {
+        use std::fs;
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::set_permissions(dir.path(), fs::Permissions::from_mode(0o700)).unwrap();
+        let queue = dir.path().join("queue");
+        fs::create_dir(&queue).unwrap();
+        fs::set_permissions(&queue, fs::Permissions::from_mode(0o700)).unwrap();
+        let key = queue.join("queue.key");
+        fs::write(&key, "k3y").unwrap();
+        fs::set_permissions(&key, fs::Permissions::from_mode(0o644)).unwrap();
+
+        assert_eq!(exposed_files(dir.path()), vec![key.display().to_string()]);
+        fs::set_permissions(&key, fs::Permissions::from_mode(0o600)).unwrap();
+        assert!(exposed_files(dir.path()).is_empty());
+    }
+
+    #[test]
+    fn loopback_addresses() {
+        assert!(is_loopback("localhost:5100"));
+        assert!(is_loopback("127.0.0.1/pwpush"));
+        assert!(!is_loopback("pwpush.example.com"));
+    }
+}
@@ -0,0 +1,305 @@
+//! Downloads of the files of a file push, with checksums.
+//!
+//! `get file` downloads the files of the push into the current directory, or
+//! into `--output-dir`, and prints the SHA-256 checksum of each file in the
+//! format of `sha256sum`. The output can be kept along with the files and
+//! checked later with `sha256sum -c`.
+//!
+//! With `--verify <SUM>`, the files are only kept if each of them has one of
+//! the given checksums, e.g. the one the sender published next to the link.
+//! Otherwise the command fails and nothing is written, so a replaced or
+//! damaged artifact is never picked up by the next step of a pipeline.
+//!
+//! Each file is downloaded to a hidden file next to its final name, which is
+//! renamed once all files are complete and verified. An interrupted or
+//! rejected download thus never leaves a file with the final name. The names
+//! of the files come from the instance, so only their last component is used,
+//! and existing files are only overwritten with `--yes`.
+
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tracing::info;
+
+use crate::args::{PPCArgs, PPCGet};
+use crate::errors::PPCError;
+use crate::http::Transport;
+use crate::instance::Instance;
+use crate::models::{Push, PushFile};
+use crate::output;
+use crate::pwpush_api;
+use crate::signals::TempFile;
+
+/// A downloaded file, as listed in the JSON output of `get file`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct Download {
+    pub filename: String,
+    /// Where the file was written to
+    pub path: PathBuf,
+    pub size: u64,
+    /// Hex encoded SHA-256 checksum of the content
+    pub sha256: String,
+}
+
+/// Value parser for `--verify`. Accepts the hex checksum with an optional
+/// `sha256:` prefix, as some tools print it.
+pub fn parse_checksum(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    let hex = value.strip_prefix("sha256:").unwrap_or(value);
+    if hex.len() != 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(String::from("expected a SHA-256 checksum of 64 hex digits"));
+    }
+    Ok(hex.to_ascii_lowercase())
+}
+
+pub fn run(
+    transport: &dyn Transport,
+    instance: &Instance,
+    args: &PPCArgs,
+    ppc_get: &PPCGet,
+    push: &Push,
+) -> Result<(), PPCError> {
+    let files = push.files.as_deref().unwrap_or_default();
+    if files.is_empty() {
+        return Err(PPCError::from(format!(
+            "push {} has no files",
+            push.url_token
+        )));
+    }
+
+    let downloads = download_all(
+        transport,
+        instance,
+        files,
+        &ppc_get.output_dir,
+        &ppc_get.verify,
+        args.assume_yes,
+    )?;
+
+    if args.json_output {
+        // pushes are always serializable, see `output`.
+        let mut output = serde_json::to_value(push).unwrap();
+        output["downloads"] = serde_json::to_value(&downloads).unwrap();
+        output::print_json(&output);
+        return Ok(());
+    }
+
+    // the payload goes to stderr, to keep stdout in the format of sha256sum.
+    if let Some(payload) = push.payload.as_deref().filter(|p| !p.is_empty()) {
+        eprintln!("{}", payload);
+    }
+    for download in &downloads {
+        println!("{}  {}", download.sha256, download.path.display());
+    }
+    Ok(())
+}
+
+/// Download the files into `dir`, see the module docs. Fails without writing
+/// any file if one of them does not match the checksums in `verify`.
+fn download_all(
+    transport: &dyn Transport,
+    instance: &Instance,
+    files: &[PushFile],
+    dir: &Path,
+    verify: &[String],
+    overwrite: bool,
+) -> Result<Vec<Download>, PPCError> {
+    let targets = files
+        .iter()
+        .map(|file| Ok(dir.join(file_name(&file.filename)?)))
+        .collect::<Result<Vec<_>, PPCError>>()?;
+    if let Some(existing) = targets.iter().find(|target| target.exists() && !overwrite) {
+        return Err(PPCError::from(format!(
+            "{} already exists, remove it or overwrite it with --yes",
+            existing.display()
+        )));
+    }
+    fs::create_dir_all(dir)
+        .map_err(|e| PPCError::from(format!("could not create {}: {}", dir.display(), e)))?;
+
+    let mut downloaded = Vec::new();
+    for (file, target) in files.iter().zip(targets) {
+        let temporary = TempFile::new(part_path(&target));
+        // left over from a download that could not be cleaned up.
+        let _ = fs::remove_file(temporary.path());
+
+        pwpush_api::download_file(transport, instance, file, temporary.path())?;
+        let (size, sha256) = checksum(temporary.path())?;
+        info!("downloaded {} ({} bytes)", file.filename, size);
+
+        let download = Download {
+            filename: file.filename.clone(),
+            path: target,
+            size,
+            sha256,
+        };
+        downloaded.push((temporary, download));
+    }
+
+    let mismatches = downloaded
+        .iter()
+        .map(|(_, download)| download)
+        .filter(|download| !verify.is_empty() && !verify.contains(&download.sha256))
+        .map(|download| format!("{} has checksum {}", download.filename, download.sha256))
+        .collect::<Vec<_>>();
+    if !mismatches.is_empty() {
+        return Err(PPCError::from(format!(
+            "checksum verification failed, no files were written: {}",
+            mismatches.join(", ")
+        )));
+    }
+
+    downloaded
+        .into_iter()
+        .map(|(temporary, download)| {
+            fs::rename(temporary.path(), &download.path).map_err(|e| {
+                PPCError::from(format!(
+                    "could not write {}: {}",
+                    download.path.display(),
+                    e
+                ))
+            })?;
+            Ok(download)
+        })
+        .collect()
+}
+
+/// The last component of a file name from the instance, so that it cannot
+/// point outside of the output directory.
+fn file_name(filename: &str) -> Result<&str, PPCError> {
+    Path::new(filename)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| PPCError::from(format!("invalid file name {:?}", filename)))
+}
+
+/// The hidden file that a file is downloaded to before it gets its name.
+fn part_path(target: &Path) -> PathBuf {
+    let name = target.file_name().unwrap_or_default().to_string_lossy();
+    target.with_file_name(format!(".{}.part", name))
+}
+
+/// Size and hex encoded SHA-256 checksum of the file, read in chunks.
+fn checksum(path: &Path) -> Result<(u64, String), PPCError> {
+    let mut hasher = Sha256::new();
+    let size = File::open(path)
+        .and_then(|mut file| io::copy(&mut file, &mut hasher))
+        .map_err(|e| PPCError::from(format!("could not read {}: {}", path.display(), e)))?;
+    Ok((size, format!("{:x}", hasher.finalize())))
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use secrecy::SecretString;
+
+    use super::{download_all, file_name, parse_checksum};
+    use crate::args::InstanceProtocol;
+    use crate::http::mock::MockTransport;
+    use crate::instance::Instance;
+    use crate::models::PushFile;
+
+    // sha256 of "hello\n"
+    const HELLO: &str = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+
+    fn instance() -> Instance {
+        Instance {
+            protocol: InstanceProtocol::Https,
+            url: String::from("pwpush.example.com"),
+            email: None,
+            token: Some(SecretString::from("t0k3n")),
+            auth_scheme: None,
+        }
+    }
+
+    fn files() -> Vec<PushFile> {
+        vec![PushFile {
+            filename: String::from("../hello.txt"),
+            url: String::from("/rails/active_storage/blobs/redirect/x/hello.txt"),
+        }]
+    }
+
+    #[test]
+    fn files_are_downloaded_with_checksums() {
+        let dir = tempfile::tempdir().unwrap();
+        let transport = MockTransport::new().respond(200, "hello\n");
+
+        let downloads = download_all(
+            &transport,
+            &instance(),
+            &files(),
+            dir.path(),
+            &[String::from(HELLO)],
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(downloads[0].sha256, HELLO);
+        assert_eq!(downloads[0].size, 6);
+        assert_eq!(downloads[0].path, dir.path().join("hello.txt"));
+        assert_eq!(fs::read_to_string(&downloads[0].path).unwrap(), "hello\n");
+
+        let request = &transport.requests()[0];
+        assert_eq!(
+            request.url,
+            "https://pwpush.example.com/rails/active_storage/blobs/redirect/x/hello.txt"
+        );
+        assert!(request.headers.is_empty(), "credentials were sent");
+    }
+
+    #[test]
+    fn mismatching_files_are_not_written() {
+        let dir = tempfile::tempdir().unwrap();
+        let transport = MockTransport::new().respond(200, "tampered\n");
+
+        let error = download_all(
+            &transport,
+            &instance(),
+            &files(),
+            dir.path(),
+            &[String::from(HELLO)],
+            false,
+        )
+        .unwrap_err();
+
+        assert!(error.to_string().contains("checksum verification failed"));
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn existing_files_are_kept_without_overwrite() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("hello.txt"), "old").unwrap();
+
+        let result = download_all(
+            &MockTransport::new(),
+            &instance(),
+            &files(),
+            dir.path(),
+            &[],
+            false,
+        );
+
+        assert!(result.is_err());
+        assert_eq!(
+            fs::read_to_string(dir.path().join("hello.txt")).unwrap(),
+            "old"
+        );
+    }
+
+    #[test]
+    fn checksums_and_names_are_validated() {
+        assert_eq!(
+            parse_checksum(&format!("sha256:{}", HELLO.to_uppercase())),
+            Ok(String::from(HELLO))
+        );
+        assert!(parse_checksum("abc").is_err());
+        assert_eq!(file_name("a/b/report.pdf").unwrap(), "report.pdf");
+        assert!(file_name("..").is_err());
+    }
+}
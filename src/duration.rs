@@ -0,0 +1,88 @@
+//! Parsing of human-friendly durations like `30m`, `24h`, `3d` or `2w`.
+//!
+//! A duration is a positive whole number followed by a single unit. Without a
+//! unit, the number is taken as seconds, like in `sleep`.
+
+use std::time::Duration;
+
+const MINUTE: u64 = 60;
+const HOUR: u64 = 60 * MINUTE;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+
+/// Parse a duration. Used as value parser for clap, hence the plain string as
+/// error, which clap shows next to the offending argument.
+pub fn parse(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let number = number
+        .parse::<u64>()
+        .map_err(|_| format!("`{}` does not start with a number", value))?;
+    let factor = match unit {
+        "" | "s" => 1,
+        "m" => MINUTE,
+        "h" => HOUR,
+        "d" => DAY,
+        "w" => WEEK,
+        other => {
+            return Err(format!(
+                "unknown unit `{}`, use one of s, m, h, d or w",
+                other
+            ))
+        }
+    };
+
+    number
+        .checked_mul(factor)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("`{}` is too long", value))
+}
+
+/// Format a duration the way it is parsed, with the largest unit that fits
+/// exactly, e.g. `10m` instead of `600s`.
+pub fn format(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (factor, unit) = [(WEEK, "w"), (DAY, "d"), (HOUR, "h"), (MINUTE, "m")]
+        .into_iter()
+        .find(|(factor, _)| seconds > 0 && seconds.is_multiple_of(*factor))
+        .unwrap_or((1, "s"));
+    format!("{}{}", seconds / factor, unit)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{format, parse};
+
+    #[test]
+    fn parse_units() {
+        assert_eq!(parse("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse("30m"), Ok(Duration::from_secs(30 * 60)));
+        assert_eq!(parse("24h"), Ok(Duration::from_secs(24 * 3600)));
+        assert_eq!(parse("3d"), Ok(Duration::from_secs(3 * 86400)));
+        assert_eq!(parse("2w"), Ok(Duration::from_secs(14 * 86400)));
+    }
+
+    #[test]
+    fn parse_rejects_invalid_values() {
+        assert!(parse("").is_err());
+        assert!(parse("h").is_err());
+        assert!(parse("3 days").is_err());
+        assert!(parse("-1d").is_err());
+        assert!(parse("99999999999999999999w").is_err());
+    }
+
+    #[test]
+    fn format_uses_largest_unit() {
+        assert_eq!(format(Duration::from_secs(0)), "0s");
+        assert_eq!(format(Duration::from_secs(90)), "90s");
+        assert_eq!(format(Duration::from_secs(10 * 60)), "10m");
+        assert_eq!(format(Duration::from_secs(36 * 3600)), "36h");
+        assert_eq!(format(Duration::from_secs(14 * 86400)), "2w");
+    }
+}
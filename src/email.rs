@@ -0,0 +1,269 @@
+//! Sending the link of a new push by email.
+//!
+//! With `push text --email-to <address>`, the link is sent to the recipient
+//! right after the push was created, through the SMTP server in the `[smtp]`
+//! section of the configuration file. The passphrase and the payload are never
+//! part of the email, a passphrase should be shared on a different channel.
+//!
+//! The SMTP password does not belong into a configuration file, it is kept in
+//! the keyring of the system (Keychain on macOS, the Credential Manager on
+//! Windows, the Secret Service on Linux), and stored there with
+//! `smtp set-password`.
+//!
+//! Subject and text of the emails can be changed in the configuration file.
+//! `{url}` and `{expiration}` are replaced by the link and its limits, and
+//! the placeholders of notes like `{user}` are supported as well, see
+//! `template`.
+//!
+//! Sending emails needs the `email` feature, which is on by default.
+
+use tracing::debug;
+
+use crate::config::{PPCConfig, SmtpSettings};
+use crate::errors::PPCError;
+use crate::models::Push;
+use crate::template;
+
+#[cfg(feature = "email")]
+use crate::args::{PPCArgs, SmtpCommand};
+#[cfg(feature = "email")]
+use crate::i18n;
+
+/// Service name of the SMTP password in the keyring.
+#[cfg(feature = "email")]
+const KEYRING_SERVICE: &str = "pwpush-cli-smtp";
+
+const DEFAULT_SUBJECT: &str = "A secret was shared with you";
+
+const DEFAULT_BODY: &str = "Hello,
+
+a secret was shared with you through Password Pusher:
+
+{url}
+
+The link expires after {expiration}. If the secret is protected by a
+passphrase, you get it separately.
+";
+
+/// Fail early if the email cannot be sent, before the push is created.
+pub fn check(config: &PPCConfig, to: &str) -> Result<(), PPCError> {
+    let smtp = &config.smtp;
+    if smtp.host.is_none() || smtp.from.is_none() {
+        return Err(PPCError::from(
+            "--email-to needs host and from in the [smtp] section of the configuration file",
+        ));
+    }
+    check_addresses(smtp, to)
+}
+
+#[cfg(feature = "email")]
+fn check_addresses(smtp: &SmtpSettings, to: &str) -> Result<(), PPCError> {
+    mailbox(to)?;
+    mailbox(smtp.from.as_deref().unwrap_or_default())?;
+    Ok(())
+}
+
+#[cfg(not(feature = "email"))]
+fn check_addresses(_smtp: &SmtpSettings, _to: &str) -> Result<(), PPCError> {
+    Err(PPCError::from(
+        "--email-to is not available, as pwpush-cli was built without the email feature",
+    ))
+}
+
+/// Subject and text of the email for a push.
+pub fn compose(smtp: &SmtpSettings, push: &Push, url: &str) -> (String, String) {
+    let expiration = push
+        .limits()
+        .unwrap_or_else(|| String::from("the default limits of the instance"));
+    let fill = |text: &str| {
+        template::expand(
+            &text
+                .replace("{url}", url)
+                .replace("{expiration}", &expiration),
+        )
+    };
+
+    (
+        fill(smtp.subject.as_deref().unwrap_or(DEFAULT_SUBJECT)),
+        fill(smtp.body.as_deref().unwrap_or(DEFAULT_BODY)),
+    )
+}
+
+/// Send the link of a push. The push exists already, so the error tells that
+/// only the email is missing.
+pub fn send(config: &PPCConfig, to: &str, push: &Push, url: &str) -> Result<(), PPCError> {
+    let (subject, body) = compose(&config.smtp, push, url);
+    debug!("emailing link of push {} to {}", push.url_token, to);
+
+    deliver(&config.smtp, to, subject, body).map_err(|e| {
+        PPCError::partial(format!(
+            "push {} was created, but the link could not be emailed to {}: {}",
+            push.url_token, to, e
+        ))
+    })
+}
+
+#[cfg(feature = "email")]
+fn deliver(smtp: &SmtpSettings, to: &str, subject: String, body: String) -> Result<(), PPCError> {
+    use lettre::transport::smtp::authentication::Credentials;
+    use lettre::{Message, SmtpTransport, Transport};
+
+    use crate::config::SmtpSecurity;
+
+    let host = smtp.host.as_deref().unwrap_or_default();
+    let message = Message::builder()
+        .from(mailbox(smtp.from.as_deref().unwrap_or_default())?)
+        .to(mailbox(to)?)
+        .subject(subject)
+        .body(body)
+        .map_err(|e| PPCError::from(format!("could not compose email: {}", e)))?;
+
+    let mut builder = match smtp.security {
+        SmtpSecurity::Starttls => SmtpTransport::starttls_relay(host),
+        SmtpSecurity::Tls => SmtpTransport::relay(host),
+        SmtpSecurity::None => Ok(SmtpTransport::builder_dangerous(host)),
+    }
+    .map_err(|e| PPCError::from(format!("invalid SMTP server {}: {}", host, e)))?;
+    if let Some(port) = smtp.port {
+        builder = builder.port(port);
+    }
+    if let Some(username) = &smtp.username {
+        let password = keyring_entry(smtp)?.get_password().map_err(|e| {
+            PPCError::from(format!(
+                "could not read the SMTP password from the keyring, store it with `smtp set-password`: {}",
+                e
+            ))
+        })?;
+        builder = builder.credentials(Credentials::new(username.clone(), password));
+    }
+
+    builder
+        .build()
+        .send(&message)
+        .map(|_| ())
+        .map_err(|e| PPCError::from(e.to_string()))
+}
+
+#[cfg(not(feature = "email"))]
+fn deliver(
+    _smtp: &SmtpSettings,
+    _to: &str,
+    _subject: String,
+    _body: String,
+) -> Result<(), PPCError> {
+    unreachable!("--email-to is refused by check without the email feature")
+}
+
+#[cfg(feature = "email")]
+fn mailbox(address: &str) -> Result<lettre::message::Mailbox, PPCError> {
+    address
+        .parse()
+        .map_err(|e| PPCError::from(format!("invalid email address {}: {}", address, e)))
+}
+
+/// The keyring entry of the SMTP password, one per user and server.
+#[cfg(feature = "email")]
+fn keyring_entry(smtp: &SmtpSettings) -> Result<keyring::Entry, PPCError> {
+    let (Some(host), Some(username)) = (&smtp.host, &smtp.username) else {
+        return Err(PPCError::from(
+            "the SMTP password needs host and username in the [smtp] section of the configuration file",
+        ));
+    };
+    keyring::Entry::new(KEYRING_SERVICE, &format!("{}@{}", username, host))
+        .map_err(|e| PPCError::from(format!("could not open the keyring: {}", e)))
+}
+
+/// Whether the SMTP password is stored in the keyring. Fails if the keyring
+/// cannot be used, e.g. without a secret service on Linux.
+#[cfg(feature = "email")]
+pub fn has_password(smtp: &SmtpSettings) -> Result<bool, PPCError> {
+    match keyring_entry(smtp)?.get_password() {
+        Ok(password) => {
+            // only its existence matters, it is wiped right away.
+            drop(zeroize::Zeroizing::new(password));
+            Ok(true)
+        }
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(PPCError::from(format!("could not use the keyring: {}", e))),
+    }
+}
+
+/// Entry point for the `smtp` subcommands.
+#[cfg(feature = "email")]
+pub fn run(args: &PPCArgs, config: &PPCConfig, command: &SmtpCommand) -> Result<(), PPCError> {
+    use secrecy::ExposeSecret;
+
+    use crate::prompt;
+
+    let entry = keyring_entry(&config.smtp)?;
+    match command {
+        SmtpCommand::SetPassword => {
+            let password = prompt::secret("SMTP password: ")?;
+            entry
+                .set_password(password.expose_secret())
+                .map_err(|e| PPCError::from(format!("could not store the password: {}", e)))
+        }
+        SmtpCommand::DeletePassword => {
+            if !prompt::confirm(&i18n::text("smtp-delete-question"), args.assume_yes)? {
+                eprintln!("{}", i18n::text("smtp-delete-aborted"));
+                return Ok(());
+            }
+            entry
+                .delete_credential()
+                .map_err(|e| PPCError::from(format!("could not delete the password: {}", e)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{check, compose};
+    use crate::config::{PPCConfig, SmtpSettings};
+    use crate::models::Push;
+
+    fn push() -> Push {
+        serde_json::from_str(r#"{"url_token":"abc","expire_after_days":2,"payload":"s3cret"}"#)
+            .unwrap()
+    }
+
+    #[test]
+    fn compose_fills_in_link_and_limits() {
+        let smtp = SmtpSettings {
+            subject: Some(String::from("Access for {expiration}")),
+            body: Some(String::from("Open {url} soon")),
+            ..SmtpSettings::default()
+        };
+
+        let (subject, body) = compose(&smtp, &push(), "https://pwpush.com/en/p/abc");
+
+        assert_eq!(subject, "Access for 2 days");
+        assert_eq!(body, "Open https://pwpush.com/en/p/abc soon");
+    }
+
+    #[test]
+    fn default_text_leaves_out_the_payload() {
+        let (_, body) = compose(
+            &SmtpSettings::default(),
+            &push(),
+            "https://pwpush.com/en/p/abc",
+        );
+
+        assert!(body.contains("https://pwpush.com/en/p/abc"));
+        assert!(body.contains("expires after 2 days"));
+        assert!(!body.contains("s3cret"));
+    }
+
+    #[test]
+    fn check_requires_server_and_sender() {
+        let mut config = PPCConfig::default();
+        assert!(check(&config, "alice@example.com").is_err());
+
+        config.smtp.host = Some(String::from("smtp.example.com"));
+        config.smtp.from = Some(String::from("Ops <ops@example.com>"));
+        assert_eq!(
+            check(&config, "alice@example.com").is_ok(),
+            cfg!(feature = "email")
+        );
+        assert!(check(&config, "not an address").is_err());
+    }
+}
@@ -1,18 +1,93 @@
 use std::fmt::Display;
+use std::process::ExitCode;
 
+/// Exit code for pushes that expired or were deleted, so scripts can tell
+/// them apart from other failures. Reminders use 2, see `remind`.
+pub const EXIT_EXPIRED: u8 = 3;
+
+/// Exit code for credentials the instance rejected, as opposed to network
+/// problems, which fail with 1.
+pub const EXIT_UNAUTHORIZED: u8 = 4;
+
+/// Exit code with `--fail-on warning` for commands that did their work, but
+/// had soft failures, see `fail_on`.
+pub const EXIT_WARNINGS: u8 = 5;
+
+/// Exit code after Ctrl-C or SIGTERM, as shells use for SIGINT.
+pub const EXIT_INTERRUPTED: u8 = 130;
+
+#[derive(Debug)]
 pub struct PPCError {
     message: String,
+
+    /// Most errors exit with 1, some have a code of their own.
+    exit_code: u8,
+
+    /// A suggestion how to fix the cause, shown below the message, see
+    /// `report`.
+    hint: Option<String>,
+
+    /// Whether part of the work was done, e.g. some items of a bulk
+    /// operation, which `--fail-on none` accepts.
+    partial: bool,
+}
+
+impl PPCError {
+    pub fn with_exit_code(message: String, exit_code: u8) -> Self {
+        Self {
+            message,
+            exit_code,
+            hint: None,
+            partial: false,
+        }
+    }
+
+    /// An error of work that was partly done, see `fail_on`.
+    pub fn partial(message: String) -> Self {
+        Self {
+            partial: true,
+            ..Self::from(message)
+        }
+    }
+
+    pub fn is_partial(&self) -> bool {
+        self.partial
+    }
+
+    /// Add a suggestion how to fix the cause.
+    pub fn with_hint(mut self, hint: String) -> Self {
+        self.hint = Some(hint);
+        self
+    }
+
+    pub fn hint(&self) -> Option<&str> {
+        self.hint.as_deref()
+    }
+
+    pub fn exit_code(&self) -> ExitCode {
+        ExitCode::from(self.exit_code)
+    }
+
+    /// The exit code as number, e.g. to report it in JSON.
+    pub fn code(&self) -> u8 {
+        self.exit_code
+    }
 }
 
 impl From<String> for PPCError {
     fn from(value: String) -> Self {
-        Self { message: value }
+        Self {
+            message: value,
+            exit_code: 1,
+            hint: None,
+            partial: false,
+        }
     }
 }
 
 impl From<&str> for PPCError {
     fn from(value: &str) -> Self {
-        Self { message: String::from(value) }
+        Self::from(String::from(value))
     }
 }
 
@@ -20,4 +95,4 @@ impl Display for PPCError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.message)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,127 @@
+//! Expiring a single push with `expire <kind> <url_token>`.
+//!
+//! Destroys the payload of the push on the instance after a confirmation,
+//! and prints the result through the renderer of `--output`, in the same
+//! shape as the items of `expire bulk`, see `bulk`.
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::args::{PPCArgs, PPCTokenObject, PushKind};
+use crate::config::PPCConfig;
+use crate::errors::PPCError;
+use crate::http::Transport;
+use crate::instance::Instance;
+use crate::render::{self, CommandOutput};
+use crate::{hooks, i18n, prompt, pwpush_api};
+
+#[derive(Debug, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct Expiration {
+    pub kind: PushKind,
+    pub url_token: String,
+    pub expired: bool,
+}
+
+impl CommandOutput for Expiration {
+    fn json(&self) -> Value {
+        // the expiration only consists of strings and bools.
+        serde_json::to_value(self).unwrap()
+    }
+
+    fn human(&self, _width: Option<usize>) -> String {
+        format!(
+            "{}\n",
+            i18n::format(
+                "expire-done",
+                &[
+                    ("kind", self.kind.to_string().into()),
+                    ("url_token", self.url_token.as_str().into()),
+                ],
+            )
+        )
+    }
+
+    fn quiet(&self) -> String {
+        format!("{}\n", self.url_token)
+    }
+}
+
+/// Entry point for `expire <kind> <url_token>`.
+pub fn run(
+    transport: &dyn Transport,
+    instance: &Instance,
+    args: &PPCArgs,
+    config: &PPCConfig,
+    object: &PPCTokenObject,
+) -> Result<(), PPCError> {
+    let kind = object.kind();
+    let url_token = &object.token().url_token;
+
+    let question = i18n::format(
+        "expire-question",
+        &[
+            ("kind", kind.to_string().into()),
+            ("url_token", url_token.as_str().into()),
+            ("url", instance.url.as_str().into()),
+        ],
+    );
+    if !prompt::confirm(&question, args.assume_yes)? {
+        eprintln!("{}", i18n::text("expire-aborted"));
+        return Ok(());
+    }
+
+    let expiration = expire(transport, instance, kind, url_token)?;
+    hooks::post_expire(&config.hooks, kind, url_token);
+    render::from_args(args).render(&expiration)
+}
+
+/// Expire the push, without asking or printing.
+pub fn expire(
+    transport: &dyn Transport,
+    instance: &Instance,
+    kind: PushKind,
+    url_token: &str,
+) -> Result<Expiration, PPCError> {
+    pwpush_api::expire_push(transport, instance, kind, url_token)?;
+    Ok(Expiration {
+        kind,
+        url_token: String::from(url_token),
+        expired: true,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::expire;
+    use crate::args::{InstanceProtocol, PushKind};
+    use crate::http::mock::MockTransport;
+    use crate::instance::Instance;
+    use crate::render::CommandOutput;
+
+    fn instance() -> Instance {
+        Instance {
+            protocol: InstanceProtocol::Https,
+            url: String::from("pwpush.example.com"),
+            email: None,
+            token: None,
+            auth_scheme: None,
+        }
+    }
+
+    #[test]
+    fn expiration_is_rendered_like_bulk_items() {
+        let transport = MockTransport::new().respond(200, r#"{"url_token":"abc","expired":true}"#);
+
+        let expiration = expire(&transport, &instance(), PushKind::Text, "abc").unwrap();
+
+        assert_eq!(
+            expiration.json(),
+            json!({ "kind": "text", "url_token": "abc", "expired": true })
+        );
+        assert_eq!(expiration.human(None), "expired text push abc\n");
+        assert_eq!(expiration.quiet(), "abc\n");
+    }
+}
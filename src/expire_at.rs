@@ -0,0 +1,341 @@
+//! Expiration at an absolute point in time, with `--expire-at`.
+//!
+//! Instances only expire pushes after a whole number of days, counted from
+//! the creation of the push. A timestamp is therefore converted to the
+//! nearest day count on the client, with a warning if this is not exact.
+//!
+//! With `--exact`, the day count is rounded up instead, so the push is still
+//! active at the given time, and the CLI holds the push like with
+//! `--ephemeral --hold` until then, to expire it right on time. See `hold`.
+//!
+//! `--expire-after` takes a duration like `3d`, `2w` or `12h` instead, see
+//! `duration`, and saves computing the day count by hand. Partial days are
+//! rounded up, so the push lasts at least as long as asked for.
+
+use std::time::Duration;
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, SecondsFormat, TimeDelta, Utc};
+use tracing::warn;
+
+use crate::args::PPCText;
+use crate::duration;
+use crate::errors::PPCError;
+use crate::i18n;
+
+/// Longest expiration instances accept, in days.
+pub const MAX_DAYS: i64 = 90;
+
+/// Formats accepted for timestamps without time zone, which are taken as
+/// local time.
+const LOCAL_FORMATS: [&str; 3] = ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"];
+
+/// Parse a timestamp, either RFC 3339 with time zone, or date and time in the
+/// local time zone, e.g. `2024-12-01T18:00`. A date alone means midnight at
+/// the start of that day. Used as value parser for clap, hence the plain
+/// string as error.
+pub fn parse(value: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+        return Ok(timestamp.with_timezone(&Utc));
+    }
+
+    let naive = LOCAL_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+        .ok_or_else(|| {
+            format!(
+                "`{}` is not a timestamp like 2024-12-01T18:00 or 2024-12-01T18:00:00+01:00",
+                value
+            )
+        })?;
+    naive
+        .and_local_timezone(Local)
+        // in the hour that is repeated when clocks are turned back, the
+        // earlier of both points in time is taken.
+        .earliest()
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+        .ok_or_else(|| format!("`{}` does not exist in the local time zone", value))
+}
+
+/// Parse the duration of `--expire-after`. Unlike other durations, a unit is
+/// required, as a plain number would be seconds, but is easily meant as days.
+pub fn parse_after(value: &str) -> Result<Duration, String> {
+    if value.trim().chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!(
+            "`{}` has no unit, use e.g. {}d for days or --expire-after-days",
+            value,
+            value.trim()
+        ));
+    }
+    duration::parse(value)
+}
+
+/// Day count for a push that should expire after `duration`, and whether it
+/// expires exactly then.
+fn days_after(duration: Duration) -> Result<(i64, bool), PPCError> {
+    const DAY: u64 = 24 * 60 * 60;
+
+    let seconds = duration.as_secs();
+    if seconds == 0 {
+        return Err(PPCError::from("--expire-after must be longer than 0"));
+    }
+    let days = seconds.div_ceil(DAY);
+    if days > MAX_DAYS as u64 {
+        return Err(PPCError::from(format!(
+            "{} is more than {} days, which instances do not accept",
+            duration::format(duration),
+            MAX_DAYS
+        )));
+    }
+    Ok((days as i64, seconds.is_multiple_of(DAY)))
+}
+
+/// Day count for a push created at `now` that should expire at `at`, and
+/// whether it expires exactly then.
+fn days(at: DateTime<Utc>, now: DateTime<Utc>, round_up: bool) -> Result<(i64, bool), PPCError> {
+    let remaining = at - now;
+    if remaining <= TimeDelta::zero() {
+        return Err(PPCError::from(format!(
+            "{} is in the past",
+            at.to_rfc3339_opts(SecondsFormat::Secs, true)
+        )));
+    }
+
+    let day = TimeDelta::days(1);
+    let whole = remaining.num_days();
+    let rest = remaining - TimeDelta::days(whole);
+    let days = if rest.is_zero() {
+        whole
+    } else if round_up || rest * 2 >= day {
+        whole + 1
+    } else {
+        whole
+    };
+    let days = days.max(1);
+
+    if days > MAX_DAYS {
+        return Err(PPCError::from(format!(
+            "{} is more than {} days ahead, which instances do not accept",
+            at.to_rfc3339_opts(SecondsFormat::Secs, true),
+            MAX_DAYS
+        )));
+    }
+    Ok((days, rest.is_zero()))
+}
+
+/// Convert `--expire-at` and `--expire-after` into the options the instance
+/// and the hold understand. Options without either are returned unchanged.
+pub fn apply(ppc_text: &PPCText, now: DateTime<Utc>) -> Result<PPCText, PPCError> {
+    let mut converted = ppc_text.clone();
+    if let Some(after) = ppc_text.expire_after {
+        let (days, exact) = days_after(after)?;
+        converted.expire_after_days = Some(days as usize);
+        if !exact {
+            warn!(
+                "{}",
+                i18n::format(
+                    "push-whole-days",
+                    &[
+                        ("days", days.into()),
+                        ("requested", duration::format(after).into())
+                    ]
+                )
+            );
+        }
+    }
+    let Some(at) = ppc_text.expire_at else {
+        return Ok(converted);
+    };
+
+    let (days, exact) = days(at, now, ppc_text.exact)?;
+    converted.expire_after_days = Some(days as usize);
+    if ppc_text.exact {
+        // `days` only fails for times in the past.
+        converted.hold = true;
+        converted.hold_for = Some((at - now).to_std().unwrap_or(Duration::ZERO));
+        converted.ephemeral = true;
+    } else if !exact {
+        warn!(
+            "{}",
+            i18n::format(
+                "push-whole-days-on",
+                &[
+                    ("days", days.into()),
+                    (
+                        "date",
+                        (now + TimeDelta::days(days))
+                            .to_rfc3339_opts(SecondsFormat::Secs, true)
+                            .into()
+                    ),
+                    (
+                        "requested",
+                        at.to_rfc3339_opts(SecondsFormat::Secs, true).into()
+                    )
+                ]
+            )
+        );
+    }
+    Ok(converted)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use chrono::{DateTime, Utc};
+    use clap::Parser;
+
+    use super::{apply, days, days_after, parse, parse_after};
+    use crate::args::{PPCAction, PPCArgs, PPCObject, PPCText};
+
+    fn utc(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn text(cmdline: &[&str]) -> PPCText {
+        let args = PPCArgs::try_parse_from(cmdline).unwrap();
+        match args.action {
+            PPCAction::Push(PPCObject::Text(text)) => *text,
+            _ => panic!("not a text push"),
+        }
+    }
+
+    #[test]
+    fn parse_timestamps() {
+        assert_eq!(
+            parse("2024-12-01T18:00:00+01:00"),
+            Ok(utc("2024-12-01T17:00:00Z"))
+        );
+        assert!(parse("2024-12-01T18:00").is_ok());
+        assert!(parse("2024-12-01 18:00").is_ok());
+        assert!(parse("2024-12-01").is_ok());
+        assert!(parse("tomorrow").is_err());
+        assert!(parse("2024-13-01").is_err());
+    }
+
+    #[test]
+    fn days_rounds_to_nearest() {
+        let now = utc("2024-12-01T12:00:00Z");
+
+        assert_eq!(
+            days(utc("2024-12-04T12:00:00Z"), now, false).ok(),
+            Some((3, true))
+        );
+        assert_eq!(
+            days(utc("2024-12-05T00:00:00Z"), now, false).ok(),
+            Some((4, false))
+        );
+        assert_eq!(
+            days(utc("2024-12-04T23:59:00Z"), now, false).ok(),
+            Some((3, false))
+        );
+        assert_eq!(
+            days(utc("2024-12-04T13:00:00Z"), now, true).ok(),
+            Some((4, false))
+        );
+        assert_eq!(
+            days(utc("2024-12-01T13:00:00Z"), now, false).ok(),
+            Some((1, false))
+        );
+    }
+
+    #[test]
+    fn days_rejects_past_and_far_future() {
+        let now = utc("2024-12-01T12:00:00Z");
+
+        assert!(days(utc("2024-12-01T11:00:00Z"), now, false).is_err());
+        assert!(days(utc("2025-06-01T12:00:00Z"), now, false).is_err());
+    }
+
+    #[test]
+    fn apply_converts_to_days() {
+        let ppc_text = text(&[
+            "pwpush-cli",
+            "push",
+            "text",
+            "--expire-at",
+            "2024-12-04T12:00:00Z",
+            "secret",
+        ]);
+
+        let converted = apply(&ppc_text, utc("2024-12-01T12:00:00Z")).unwrap();
+
+        assert_eq!(converted.expire_after_days, Some(3));
+        assert!(!converted.hold);
+    }
+
+    #[test]
+    fn apply_holds_for_exact_expiry() {
+        let ppc_text = text(&[
+            "pwpush-cli",
+            "push",
+            "text",
+            "--expire-at",
+            "2024-12-01T12:30:00Z",
+            "--exact",
+            "secret",
+        ]);
+
+        let converted = apply(&ppc_text, utc("2024-12-01T12:00:00Z")).unwrap();
+
+        assert_eq!(converted.expire_after_days, Some(1));
+        assert!(converted.hold && converted.ephemeral);
+        assert_eq!(converted.hold_for, Some(Duration::from_secs(30 * 60)));
+    }
+
+    #[test]
+    fn parse_after_requires_unit() {
+        assert_eq!(parse_after("12h"), Ok(Duration::from_secs(12 * 3600)));
+        assert_eq!(parse_after("2w"), Ok(Duration::from_secs(14 * 86400)));
+        assert!(parse_after("3").unwrap_err().contains("use e.g. 3d"));
+        assert!(parse_after("3 days").is_err());
+    }
+
+    #[test]
+    fn days_after_rounds_up() {
+        let hours = |h: u64| Duration::from_secs(h * 3600);
+
+        assert_eq!(days_after(hours(72)).ok(), Some((3, true)));
+        assert_eq!(days_after(hours(12)).ok(), Some((1, false)));
+        assert_eq!(days_after(hours(25)).ok(), Some((2, false)));
+        assert!(days_after(Duration::ZERO).is_err());
+        assert!(days_after(hours(91 * 24)).is_err());
+    }
+
+    #[test]
+    fn apply_converts_duration_to_days() {
+        let ppc_text = text(&[
+            "pwpush-cli",
+            "push",
+            "text",
+            "--expire-after",
+            "2w",
+            "secret",
+        ]);
+
+        let converted = apply(&ppc_text, utc("2024-12-01T12:00:00Z")).unwrap();
+
+        assert_eq!(converted.expire_after_days, Some(14));
+    }
+
+    #[test]
+    fn expire_at_conflicts_with_days() {
+        assert!(PPCArgs::try_parse_from([
+            "pwpush-cli",
+            "push",
+            "text",
+            "--expire-at",
+            "2024-12-04T12:00:00Z",
+            "--expire-after-days",
+            "3",
+            "secret",
+        ])
+        .is_err());
+    }
+}
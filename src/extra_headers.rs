@@ -0,0 +1,225 @@
+//! Additional headers for the requests to the instance.
+//!
+//! Self-hosted instances sometimes sit behind authenticating proxies, e.g.
+//! Cloudflare Access or an OAuth2 proxy, which need headers of their own:
+//!
+//! ```text
+//! pwpush-cli --header 'CF-Access-Client-Id: ...' --header 'CF-Access-Client-Secret: ...' list active
+//! ```
+//!
+//! The headers are added right before a request is sent, below recording, so
+//! they never end up in recordings. Their values are treated as secrets, as
+//! they usually are credentials. A header given this way replaces a header of
+//! the same name that the CLI would send otherwise.
+//!
+//! Only requests to the origin of the selected instance, i.e. the same scheme,
+//! host and port, get the headers. Requests to other addresses, e.g. to
+//! webhooks or to the other instance of `copy`, are sent without them.
+
+use std::fmt;
+
+use secrecy::{ExposeSecret, SecretString};
+use url::{Origin, Url};
+use zeroize::Zeroize;
+
+use crate::errors::PPCError;
+use crate::http::{PPCRequest, PPCResponse, Transport};
+use crate::redact;
+
+/// A header given with `--header`.
+#[derive(Clone)]
+pub struct ExtraHeader {
+    pub name: String,
+    pub value: SecretString,
+}
+
+/// The value is redacted, as it is usually a credential.
+impl fmt::Debug for ExtraHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExtraHeader")
+            .field("name", &self.name)
+            .field("value", &redact::Redacted)
+            .finish()
+    }
+}
+
+/// Parse a header given as `Name: value`. Used as value parser for clap, hence
+/// the plain string as error.
+pub fn parse(value: &str) -> Result<ExtraHeader, String> {
+    let (name, header_value) = value
+        .split_once(':')
+        .ok_or_else(|| String::from("headers must be given as `Name: value`"))?;
+    let name = name.trim();
+
+    // header names are tokens as defined in RFC 9110.
+    let is_token = |c: char| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c);
+    if name.is_empty() || !name.chars().all(is_token) {
+        return Err(format!("`{}` is not a valid header name", name));
+    }
+    if header_value.contains(['\r', '\n']) {
+        return Err(format!(
+            "the value of header `{}` contains a line break",
+            name
+        ));
+    }
+
+    Ok(ExtraHeader {
+        name: String::from(name),
+        value: SecretString::from(header_value.trim()),
+    })
+}
+
+/// Wraps another transport and adds the extra headers to the requests to the
+/// origin of `base_url`, the URL of the instance.
+pub struct ExtraHeadersTransport<T: Transport> {
+    inner: T,
+    headers: Vec<ExtraHeader>,
+
+    /// `None` if the instance is not known, then no request gets the headers.
+    origin: Option<Origin>,
+}
+
+impl<T: Transport> ExtraHeadersTransport<T> {
+    pub fn new(inner: T, headers: Vec<ExtraHeader>, base_url: Option<&str>) -> Self {
+        let origin = base_url
+            .and_then(|url| Url::parse(url).ok())
+            .map(|url| url.origin());
+        Self {
+            inner,
+            headers,
+            origin,
+        }
+    }
+
+    /// Whether the request goes to the instance. The origin includes the
+    /// default port of the scheme, so `https://host` and `https://host:443`
+    /// are the same.
+    fn is_instance(&self, request: &PPCRequest) -> bool {
+        let origin = Url::parse(&request.url).map(|url| url.origin());
+        matches!((&self.origin, origin), (Some(instance), Ok(origin)) if *instance == origin)
+    }
+}
+
+impl<T: Transport> Transport for ExtraHeadersTransport<T> {
+    fn send(&self, request: &PPCRequest) -> Result<PPCResponse, PPCError> {
+        if self.headers.is_empty() || !self.is_instance(request) {
+            return self.inner.send(request);
+        }
+
+        let mut request = request.clone();
+        request.headers.retain(|(name, _)| {
+            !self
+                .headers
+                .iter()
+                .any(|extra| extra.name.eq_ignore_ascii_case(name))
+        });
+        for extra in &self.headers {
+            request.headers.push((
+                extra.name.clone(),
+                String::from(extra.value.expose_secret()),
+            ));
+        }
+
+        let response = self.inner.send(&request);
+        // the copy may contain the payload, see `pwpush_api::send_text_push`.
+        request.body.zeroize();
+        response
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use secrecy::ExposeSecret;
+
+    use super::{parse, ExtraHeadersTransport};
+    use crate::http::mock::MockTransport;
+    use crate::http::{Method, PPCRequest, Transport};
+
+    #[test]
+    fn parse_headers() {
+        let header = parse("CF-Access-Client-Id: abc:def ").unwrap();
+        assert_eq!(header.name, "CF-Access-Client-Id");
+        assert_eq!(header.value.expose_secret(), "abc:def");
+
+        assert!(parse("no colon").is_err());
+        assert!(parse(": value").is_err());
+        assert!(parse("Bad Name: value").is_err());
+    }
+
+    #[test]
+    fn debug_redacts_value() {
+        let debug = format!("{:?}", parse("X-Proxy-Token: s3cret").unwrap());
+
+        assert!(!debug.contains("s3cret"));
+        assert!(debug.contains("X-Proxy-Token"));
+    }
+
+    #[test]
+    fn adds_and_replaces_headers() {
+        let headers = vec![
+            parse("X-Proxy-Token: s3cret").unwrap(),
+            parse("accept: application/json").unwrap(),
+        ];
+        let transport = ExtraHeadersTransport::new(
+            MockTransport::new().respond(200, "{}"),
+            headers,
+            Some("https://pwpush.com"),
+        );
+
+        let request = PPCRequest::new(Method::Get, String::from("https://pwpush.com/p.json"))
+            .header("Accept", "*/*")
+            .header("X-User-Email", "user@example.com");
+        transport.send(&request).unwrap();
+
+        let sent = &transport.inner.requests()[0];
+        assert_eq!(
+            sent.headers,
+            vec![
+                (
+                    String::from("X-User-Email"),
+                    String::from("user@example.com")
+                ),
+                (String::from("X-Proxy-Token"), String::from("s3cret")),
+                (String::from("accept"), String::from("application/json")),
+            ]
+        );
+    }
+
+    #[test]
+    fn only_instance_gets_headers() {
+        let headers = vec![parse("X-Proxy-Token: s3cret").unwrap()];
+        let transport = ExtraHeadersTransport::new(
+            MockTransport::new()
+                .respond(200, "{}")
+                .respond(200, "{}")
+                .respond(200, "{}")
+                .respond(200, "{}"),
+            headers,
+            Some("https://pwpush.example.com/pwpush"),
+        );
+
+        for url in [
+            "https://pwpush.example.com:443/pwpush/p.json",
+            "http://pwpush.example.com/pwpush/p.json",
+            "https://pwpush.example.com:8443/p.json",
+            "https://hooks.example.com/hook",
+        ] {
+            transport
+                .send(&PPCRequest::new(Method::Post, String::from(url)))
+                .unwrap();
+        }
+
+        let has_token: Vec<bool> = transport
+            .inner
+            .requests()
+            .iter()
+            .map(|request| {
+                request
+                    .headers
+                    .iter()
+                    .any(|(name, _)| name == "X-Proxy-Token")
+            })
+            .collect();
+        assert_eq!(has_token, vec![true, false, false, false]);
+    }
+}
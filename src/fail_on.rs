@@ -0,0 +1,96 @@
+//! Soft failures, and how strictly they are treated, see `--fail-on`.
+//!
+//! Some failures do not stop a command, as its actual work is done: the push
+//! exists, but the webhook could not be notified, the link not shortened or
+//! emailed, or the push not recorded in the history. Likewise, bulk
+//! operations and pushes to several profiles go on when single items fail.
+//! Pipelines differ in how much of that they accept, so `--fail-on` selects
+//! what flips the exit code:
+//!   - `error` (default): failed items and links that could not be emailed
+//!     fail the command, other soft failures are only warned about
+//!   - `warning`: soft failures fail the command as well, with exit code 5
+//!     unless it failed anyway
+//!   - `none`: only failures that stop the command fail it, failed items and
+//!     unsent emails are only warned about
+//!
+//! Code that continues after a soft failure logs a warning and calls
+//! `soft_failure`, errors of partly done work are created with
+//! `PPCError::partial`.
+
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use tracing::warn;
+
+use crate::args::FailOn;
+use crate::errors::{PPCError, EXIT_WARNINGS};
+
+static SOFT_FAILURES: AtomicUsize = AtomicUsize::new(0);
+
+/// Count a soft failure, which was already logged as warning.
+pub fn soft_failure() {
+    SOFT_FAILURES.fetch_add(1, Ordering::Relaxed);
+}
+
+/// The result of the command, with soft failures and partial errors treated
+/// as `--fail-on` says.
+pub fn apply(fail_on: FailOn, result: Result<ExitCode, PPCError>) -> Result<ExitCode, PPCError> {
+    decide(fail_on, result, SOFT_FAILURES.load(Ordering::Relaxed))
+}
+
+fn decide(
+    fail_on: FailOn,
+    result: Result<ExitCode, PPCError>,
+    soft_failures: usize,
+) -> Result<ExitCode, PPCError> {
+    match (fail_on, result) {
+        (FailOn::None, Err(e)) if e.is_partial() => {
+            warn!("{}", e);
+            Ok(ExitCode::SUCCESS)
+        }
+        (FailOn::Warning, Ok(code)) if code == ExitCode::SUCCESS && soft_failures > 0 => {
+            Err(PPCError::with_exit_code(
+                format!(
+                    "{} soft {}, see the warnings above (--fail-on warning)",
+                    soft_failures,
+                    if soft_failures == 1 {
+                        "failure"
+                    } else {
+                        "failures"
+                    }
+                ),
+                EXIT_WARNINGS,
+            ))
+        }
+        (_, result) => result,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::process::ExitCode;
+
+    use super::decide;
+    use crate::args::FailOn;
+    use crate::errors::{PPCError, EXIT_WARNINGS};
+
+    fn partial() -> Result<ExitCode, PPCError> {
+        Err(PPCError::partial(String::from("1 of 3 pushes failed")))
+    }
+
+    #[test]
+    fn strictness_follows_fail_on() {
+        let ok = || Ok(ExitCode::SUCCESS);
+
+        assert!(decide(FailOn::Error, ok(), 2).is_ok());
+        assert!(decide(FailOn::Error, partial(), 0).is_err());
+
+        let error = decide(FailOn::Warning, ok(), 2).unwrap_err();
+        assert_eq!(error.code(), EXIT_WARNINGS);
+        assert!(decide(FailOn::Warning, ok(), 0).is_ok());
+        assert_eq!(decide(FailOn::Warning, partial(), 2).unwrap_err().code(), 1);
+
+        assert!(decide(FailOn::None, partial(), 2).is_ok());
+        assert!(decide(FailOn::None, Err(PPCError::from("unauthorized")), 0).is_err());
+    }
+}
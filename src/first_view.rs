@@ -0,0 +1,195 @@
+//! Expiring a push as soon as the audit log confirms the first view.
+//!
+//! A push limited to a single view is used up by whoever opens the link
+//! first, which may be a link scanner of a chat system or mail server rather
+//! than the recipient. With `push text --expire-after-first-view-confirmed`,
+//! the CLI keeps running after the link was printed and polls the audit log of
+//! the push instead. Once a successful view shows up, the push is expired, so
+//! the secret can still be opened by exactly one real visitor, as the push
+//! may have more views left, but by nobody after that.
+//!
+//! The audit log is only available to the owner of a push, so this needs
+//! authentication. Watching ends once the push would have expired on its own
+//! after the days it was created with. If the CLI is stopped before, the push
+//! stays active until it expires on its own.
+
+use std::ops::ControlFlow;
+use std::time::{Duration, Instant};
+
+use tracing::debug;
+
+use crate::args::PushKind;
+use crate::errors::{PPCError, EXIT_INTERRUPTED};
+use crate::http::Transport;
+use crate::i18n;
+use crate::instance::Instance;
+use crate::models::{AuditLog, AuditView, Push};
+use crate::pwpush_api;
+use crate::scheduler::Scheduler;
+
+/// How often the audit log is polled.
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Fail early if the audit log will not be available, before the push is
+/// created.
+pub fn check(instance: &Instance) -> Result<(), PPCError> {
+    if instance.token.is_none() {
+        return Err(PPCError::from(
+            "--expire-after-first-view-confirmed requires authentication, as only the owner of a push can see its audit log",
+        ));
+    }
+    Ok(())
+}
+
+/// Poll the audit log until the first successful view, then expire the push.
+pub fn run(transport: &dyn Transport, instance: &Instance, push: &Push) -> Result<(), PPCError> {
+    let url_token = &push.url_token;
+    eprintln!(
+        "{}",
+        i18n::format("first-view-waiting", &[("url_token", url_token.into())])
+    );
+
+    let deadline = deadline(push, Instant::now());
+    // errors are most likely temporary network issues, and giving up would
+    // leave the push active after it was viewed, so they are retried.
+    let viewed = Scheduler::new("poll-audit-log", POLL_INTERVAL).run(|| {
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Ok(ControlFlow::Break(None));
+        }
+
+        debug!("polling audit log of text push {}", url_token);
+        let log = pwpush_api::audit(transport, instance, PushKind::Text, url_token)?;
+        Ok(match first_successful(&log) {
+            Some(view) => ControlFlow::Break(Some(view.clone())),
+            None => ControlFlow::Continue(()),
+        })
+    });
+
+    match viewed {
+        Some(Some(view)) => {
+            debug!("first confirmed view at {}", view.created_at);
+            expire(transport, instance, url_token, &view)
+        }
+        Some(None) => {
+            eprintln!(
+                "{}",
+                i18n::format("first-view-missed", &[("url_token", url_token.into())])
+            );
+            Ok(())
+        }
+        None => Err(PPCError::with_exit_code(
+            format!(
+                "stopped watching push {}, it stays active until it expires on its own",
+                url_token
+            ),
+            EXIT_INTERRUPTED,
+        )),
+    }
+}
+
+/// When the push expires on its own. The days come from the instance, so a
+/// limit too far out to represent counts as none.
+fn deadline(push: &Push, now: Instant) -> Option<Instant> {
+    let seconds = push.expire_after_days?.checked_mul(24 * 60 * 60)?;
+    now.checked_add(Duration::from_secs(seconds))
+}
+
+/// The first view that actually revealed the secret. Failed attempts, e.g.
+/// with a wrong passphrase, and other events like deletions do not count.
+fn first_successful(log: &AuditLog) -> Option<&AuditView> {
+    log.views
+        .iter()
+        .find(|view| view.successful != Some(false) && view.kind_name() == "view")
+}
+
+fn expire(
+    transport: &dyn Transport,
+    instance: &Instance,
+    url_token: &str,
+    view: &AuditView,
+) -> Result<(), PPCError> {
+    pwpush_api::expire_push(transport, instance, PushKind::Text, url_token).map_err(|e| {
+        PPCError::from(format!(
+            "push {} was viewed, but could not be expired, expire it with `expire text {}`: {}",
+            url_token, url_token, e
+        ))
+    })?;
+    eprintln!(
+        "{}",
+        i18n::format(
+            "first-view-expired",
+            &[
+                ("url_token", url_token.into()),
+                ("date", view.created_at.clone().into())
+            ]
+        )
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use super::{check, deadline, first_successful};
+    use crate::api_body;
+    use crate::args::InstanceProtocol;
+    use crate::instance::Instance;
+    use crate::models::{AuditLog, Push};
+
+    #[test]
+    fn only_successful_views_count() {
+        let log: AuditLog = serde_json::from_str(
+            r#"{"views":[
+                {"successful":false,"created_at":"2024-05-01T12:00:00Z","kind":0},
+                {"successful":true,"created_at":"2024-05-01T12:01:00Z","kind":1},
+                {"successful":true,"created_at":"2024-05-01T12:02:00Z","kind":0}
+            ]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            first_successful(&log).map(|view| view.created_at.as_str()),
+            Some("2024-05-01T12:02:00Z")
+        );
+        assert!(first_successful(&AuditLog::default()).is_none());
+    }
+
+    #[test]
+    fn check_requires_authentication() {
+        let instance = Instance {
+            protocol: InstanceProtocol::Https,
+            url: String::from("pwpush.example.com"),
+            email: None,
+            token: None,
+            auth_scheme: None,
+        };
+
+        assert!(check(&instance).is_err());
+    }
+
+    #[test]
+    fn huge_limits_of_the_instance_mean_no_deadline() {
+        let now = Instant::now();
+        let push = |body: &str| api_body::parse_json::<Push>(body, "push").unwrap();
+
+        assert_eq!(
+            deadline(&push(r#"{"url_token":"abc","expire_after_days":2}"#), now),
+            Some(now + Duration::from_secs(2 * 24 * 60 * 60))
+        );
+        assert_eq!(
+            deadline(
+                &push(r#"{"url_token":"abc","expire_after_days":18446744073709551615}"#),
+                now
+            ),
+            None
+        );
+        assert_eq!(
+            deadline(
+                &push(r#"{"url_token":"abc","expire_after_days":213503982334601}"#),
+                now
+            ),
+            None
+        );
+    }
+}
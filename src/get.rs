@@ -0,0 +1,306 @@
+//! Retrieval of a single push, including its payload.
+//!
+//! Pushes can be protected by a passphrase. If none was given with
+//! `--passphrase`, the user is asked for it interactively (with hidden input)
+//! as soon as the instance reports that one is required, and may try again a
+//! few times. In non-interactive mode, retrieval of a protected push fails
+//! instead.
+//!
+//! With `--to-vault`, the payload is stored in HashiCorp Vault instead of
+//! being printed, see `vault`. The files of file pushes are downloaded, see
+//! `download`.
+//!
+//! Expired and deleted pushes fail with `EXIT_EXPIRED` and a message saying
+//! when the push expired, so scripts can tell them apart from other errors.
+
+use std::io::{self, Write};
+
+use secrecy::{ExposeSecret, SecretString};
+use serde_json::json;
+use tracing::warn;
+use zeroize::Zeroizing;
+
+use crate::args::{PPCArgs, PPCGet, PushKind};
+use crate::crypto;
+use crate::dates::DateFormatter;
+use crate::download;
+use crate::errors::PPCError;
+use crate::http::Transport;
+use crate::i18n;
+use crate::info;
+use crate::instance::Instance;
+use crate::models::Push;
+use crate::output;
+use crate::payload;
+use crate::platform;
+use crate::prompt;
+use crate::pwpush_api::{self, Retrieval};
+use crate::vault::Vault;
+
+/// Number of passphrases the user may enter before giving up.
+const MAX_PASSPHRASE_ATTEMPTS: usize = 3;
+
+pub fn run(
+    transport: &dyn Transport,
+    instance: &Instance,
+    args: &PPCArgs,
+    ppc_get: &PPCGet,
+) -> Result<(), PPCError> {
+    let kind = ppc_get.object.kind();
+    let url_token = &ppc_get.object.token().url_token;
+
+    let mut ask = || {
+        prompt::secret(&format!("Passphrase for {} push {}: ", kind, url_token))
+            .map_err(|e| PPCError::from(format!("{}, use --passphrase", e)))
+    };
+    let push = fetch(
+        transport,
+        instance,
+        kind,
+        url_token,
+        ppc_get.passphrase.as_ref(),
+        &mut ask,
+    )?;
+
+    if push.expired == Some(true) {
+        return Err(info::expired_error(&push, &DateFormatter::new(args)));
+    }
+
+    if kind == PushKind::File {
+        return download::run(transport, instance, args, ppc_get, &push);
+    }
+
+    if let Some(secret) = &ppc_get.to_vault {
+        let bytes = decoded(payload_of(&push)?, ppc_get)?;
+        let text = std::str::from_utf8(&bytes)
+            .map_err(|_| PPCError::from("only text payloads can be stored in Vault"))?;
+        Vault::from_env()?.write(platform::transport()?, secret, text)?;
+
+        if args.json_output {
+            let output = json!({ "url_token": url_token, "vault": secret.to_string() });
+            output::print_json(&output);
+        } else {
+            println!(
+                "{}",
+                i18n::format(
+                    "get-stored-in-vault",
+                    &[
+                        ("url_token", url_token.into()),
+                        ("secret", secret.to_string().into())
+                    ]
+                )
+            );
+        }
+        return Ok(());
+    }
+
+    if args.json_output {
+        output::print_json(&push);
+        return Ok(());
+    }
+
+    let payload = payload_of(&push)?;
+    if !ppc_get.decrypt && !ppc_get.decode_base64 {
+        println!("{}", payload);
+        return Ok(());
+    }
+
+    let mut bytes = decoded(payload, ppc_get)?;
+
+    // decoded bytes are written as they are, text gets a line break like
+    // any other output.
+    if !ppc_get.decode_base64 {
+        bytes.push(b'\n');
+    }
+
+    let mut stdout = io::stdout().lock();
+    stdout
+        .write_all(&bytes)
+        .and_then(|()| stdout.flush())
+        .map_err(|e| PPCError::from(format!("could not write payload: {}", e)))
+}
+
+fn payload_of(push: &Push) -> Result<&str, PPCError> {
+    push.payload
+        .as_deref()
+        .ok_or_else(|| PPCError::from(format!("push {} has no payload", push.url_token)))
+}
+
+/// The payload with --decrypt and --decode-base64 applied.
+fn decoded(payload: &str, ppc_get: &PPCGet) -> Result<Zeroizing<Vec<u8>>, PPCError> {
+    // decryption comes first, as --base64 encodes before encrypting.
+    let mut bytes = Zeroizing::new(payload.as_bytes().to_vec());
+    if ppc_get.decrypt {
+        bytes = crypto::decrypt(payload, ppc_get.identity.as_deref())?;
+    }
+    if ppc_get.decode_base64 {
+        let text = std::str::from_utf8(&bytes)
+            .map_err(|_| PPCError::from("the decrypted payload is not base64"))?;
+        bytes = payload::decode(text)?;
+    }
+    Ok(bytes)
+}
+
+/// Retrieve the push, asking for the passphrase with `ask` if needed. An
+/// explicitly given passphrase is not asked for again if it is incorrect.
+fn fetch(
+    transport: &dyn Transport,
+    instance: &Instance,
+    kind: PushKind,
+    url_token: &str,
+    passphrase: Option<&SecretString>,
+    ask: &mut dyn FnMut() -> Result<SecretString, PPCError>,
+) -> Result<Push, PPCError> {
+    let exposed = passphrase.map(|p| p.expose_secret());
+    match pwpush_api::retrieve_with_passphrase(transport, instance, kind, url_token, exposed)? {
+        Retrieval::Push(push) => return Ok(*push),
+        Retrieval::PassphraseRequired if passphrase.is_some() => {
+            return Err(PPCError::from(format!(
+                "incorrect passphrase for push {}",
+                url_token
+            )))
+        }
+        Retrieval::PassphraseRequired => {}
+    }
+
+    for attempt in 1..=MAX_PASSPHRASE_ATTEMPTS {
+        let passphrase = ask()?;
+        match pwpush_api::retrieve_with_passphrase(
+            transport,
+            instance,
+            kind,
+            url_token,
+            Some(passphrase.expose_secret()),
+        )? {
+            Retrieval::Push(push) => return Ok(*push),
+            Retrieval::PassphraseRequired => warn!(
+                "{}",
+                i18n::format(
+                    "get-wrong-passphrase",
+                    &[
+                        ("attempt", attempt.into()),
+                        ("attempts", MAX_PASSPHRASE_ATTEMPTS.into())
+                    ]
+                )
+            ),
+        }
+    }
+
+    Err(PPCError::from(format!(
+        "giving up on push {} after {} incorrect passphrases",
+        url_token, MAX_PASSPHRASE_ATTEMPTS
+    )))
+}
+
+#[cfg(test)]
+mod test {
+    use secrecy::SecretString;
+
+    use super::{fetch, MAX_PASSPHRASE_ATTEMPTS};
+    use crate::args::{InstanceProtocol, PushKind};
+    use crate::errors::PPCError;
+    use crate::http::mock::MockTransport;
+    use crate::instance::Instance;
+
+    const PASSPHRASE_ERROR: &str =
+        r#"{"error":"This push has a passphrase that was incorrect or not provided."}"#;
+
+    fn instance() -> Instance {
+        Instance {
+            protocol: InstanceProtocol::Https,
+            url: String::from("pwpush.com"),
+            email: None,
+            token: None,
+            auth_scheme: None,
+        }
+    }
+
+    #[test]
+    fn fetch_prompts_and_retries() {
+        let transport = MockTransport::new()
+            .respond(200, PASSPHRASE_ERROR)
+            .respond(200, PASSPHRASE_ERROR)
+            .respond(200, r#"{"url_token":"abc","payload":"secret"}"#);
+        let mut answers = vec!["right", "wrong"];
+        let mut ask = || Ok(SecretString::from(answers.pop().unwrap()));
+
+        let push = fetch(
+            &transport,
+            &instance(),
+            PushKind::Text,
+            "abc",
+            None,
+            &mut ask,
+        )
+        .unwrap();
+
+        assert_eq!(push.payload.as_deref(), Some("secret"));
+        let urls = transport
+            .requests()
+            .iter()
+            .map(|r| r.url.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            urls,
+            vec![
+                "https://pwpush.com/p/abc.json",
+                "https://pwpush.com/p/abc.json?passphrase=wrong",
+                "https://pwpush.com/p/abc.json?passphrase=right",
+            ]
+        );
+    }
+
+    #[test]
+    fn fetch_gives_up_after_max_attempts() {
+        let mut transport = MockTransport::new();
+        for _ in 0..=MAX_PASSPHRASE_ATTEMPTS {
+            transport = transport.respond(200, PASSPHRASE_ERROR);
+        }
+        let mut ask = || Ok(SecretString::from("wrong"));
+
+        assert!(fetch(
+            &transport,
+            &instance(),
+            PushKind::Text,
+            "abc",
+            None,
+            &mut ask
+        )
+        .is_err());
+        assert_eq!(transport.requests().len(), MAX_PASSPHRASE_ATTEMPTS + 1);
+    }
+
+    #[test]
+    fn fetch_does_not_prompt_for_explicit_passphrase() {
+        let transport = MockTransport::new().respond(200, PASSPHRASE_ERROR);
+        let mut ask = || -> Result<SecretString, PPCError> { panic!("must not prompt") };
+
+        let result = fetch(
+            &transport,
+            &instance(),
+            PushKind::Text,
+            "abc",
+            Some(&SecretString::from("wrong")),
+            &mut ask,
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn fetch_fails_when_prompt_fails() {
+        let transport = MockTransport::new().respond(200, PASSPHRASE_ERROR);
+        let mut ask = || Err(PPCError::from("cannot prompt in non-interactive mode"));
+
+        assert!(fetch(
+            &transport,
+            &instance(),
+            PushKind::Text,
+            "abc",
+            None,
+            &mut ask
+        )
+        .is_err());
+        assert_eq!(transport.requests().len(), 1);
+    }
+}
@@ -0,0 +1,380 @@
+//! Line-based protocol for embedding the CLI into other programs.
+//!
+//! Like git credential helpers, `helper <operation>` reads attributes as
+//! `key=value` lines from stdin, until an empty line or the end of input, and
+//! answers with `key=value` lines on stdout. Callers need neither JSON nor
+//! argv quoting, and secrets do not show up in the process list.
+//!
+//! `helper push` creates a text push. It takes the attributes `payload`
+//! (required), `passphrase`, `note`, `expire_after_days`, `expire_after_views`,
+//! `deletable_by_viewer`, `retrieval_step` and `preset`, and answers with
+//! `url` and `url_token`, plus `passphrase` if one was generated from a
+//! preset:
+//!
+//! ```text
+//! $ printf 'payload=s3cret\nexpire_after_views=1\n' | pwpush-cli helper push
+//! url=https://pwpush.com/en/p/fkwjfvhall92
+//! url_token=fkwjfvhall92
+//! ```
+//!
+//! `helper expire` expires the text push given as `url_token`, and answers
+//! with `expired=true`.
+//!
+//! Values extend to the end of the line, so they cannot contain line breaks.
+//! Unknown attributes are refused, so that a typo cannot go unnoticed and
+//! e.g. leave a push active for longer than intended. On failure, the answer
+//! is a single `error` line, and the exit code is not zero. This protocol is
+//! stable, new attributes may be added but existing ones keep their meaning.
+
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, Write};
+
+use secrecy::{ExposeSecret, SecretString};
+use tracing::debug;
+use zeroize::Zeroizing;
+
+use crate::args::{HelperOperation, PPCHelper, PPCText, PushKind};
+use crate::config::PPCConfig;
+use crate::errors::PPCError;
+use crate::http::Transport;
+use crate::instance::Instance;
+use crate::{dedupe, history, passphrase, payload, pwpush_api, share, template};
+
+/// Attributes of `helper push`.
+const PUSH_ATTRIBUTES: [&str; 8] = [
+    "payload",
+    "passphrase",
+    "note",
+    "expire_after_days",
+    "expire_after_views",
+    "deletable_by_viewer",
+    "retrieval_step",
+    "preset",
+];
+
+/// Attributes of `helper expire`.
+const EXPIRE_ATTRIBUTES: [&str; 1] = ["url_token"];
+
+/// Attributes as given on stdin. They may contain the payload, so all values
+/// are wiped when dropped.
+type Attributes = BTreeMap<String, Zeroizing<String>>;
+
+/// Answer a single request on stdin.
+pub fn run(
+    transport: &dyn Transport,
+    instance: &Instance,
+    config: &PPCConfig,
+    ppc_helper: &PPCHelper,
+) -> Result<(), PPCError> {
+    respond(
+        transport,
+        instance,
+        config,
+        ppc_helper.operation,
+        &mut io::stdin().lock(),
+        &mut io::stdout().lock(),
+    )
+}
+
+fn respond(
+    transport: &dyn Transport,
+    instance: &Instance,
+    config: &PPCConfig,
+    operation: HelperOperation,
+    input: &mut dyn BufRead,
+    output: &mut dyn Write,
+) -> Result<(), PPCError> {
+    let answer = read_attributes(input).and_then(|attributes| match operation {
+        HelperOperation::Push => push(transport, instance, config, &attributes),
+        HelperOperation::Expire => expire(transport, instance, &attributes),
+    });
+
+    let lines = match &answer {
+        Ok(lines) => lines.clone(),
+        // the message must stay on a single line.
+        Err(e) => vec![(String::from("error"), e.to_string().replace('\n', " "))],
+    };
+    let mut text = Zeroizing::new(String::new());
+    for (key, value) in &lines {
+        text.push_str(&format!("{}={}\n", key, value));
+    }
+    output
+        .write_all(text.as_bytes())
+        .and_then(|()| output.flush())
+        .map_err(|e| PPCError::from(format!("could not write answer: {}", e)))?;
+
+    answer.map(|_| ())
+}
+
+/// Read `key=value` lines until an empty line or the end of input.
+fn read_attributes(input: &mut dyn BufRead) -> Result<Attributes, PPCError> {
+    let mut attributes = Attributes::new();
+    loop {
+        let mut line = Zeroizing::new(String::new());
+        let read = input
+            .read_line(&mut line)
+            .map_err(|e| PPCError::from(format!("could not read attributes: {}", e)))?;
+        let line = line.trim_end_matches(['\r', '\n']);
+        if read == 0 || line.is_empty() {
+            break;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| PPCError::from("attributes must be given as key=value"))?;
+        debug!("read attribute {}", key);
+        attributes.insert(String::from(key), Zeroizing::new(String::from(value)));
+    }
+    Ok(attributes)
+}
+
+/// Fail on attributes the operation does not know.
+fn check_known(attributes: &Attributes, known: &[&str]) -> Result<(), PPCError> {
+    match attributes.keys().find(|key| !known.contains(&key.as_str())) {
+        Some(key) => Err(PPCError::from(format!("unknown attribute `{}`", key))),
+        None => Ok(()),
+    }
+}
+
+/// Parse the value of an attribute, if it was given.
+fn parse_value<T: std::str::FromStr>(
+    attributes: &Attributes,
+    key: &str,
+) -> Result<Option<T>, PPCError> {
+    attributes
+        .get(key)
+        .map(|value| {
+            value
+                .parse::<T>()
+                .map_err(|_| PPCError::from(format!("invalid value for `{}`", key)))
+        })
+        .transpose()
+}
+
+fn push(
+    transport: &dyn Transport,
+    instance: &Instance,
+    config: &PPCConfig,
+    attributes: &Attributes,
+) -> Result<Vec<(String, String)>, PPCError> {
+    check_known(attributes, &PUSH_ATTRIBUTES)?;
+    let secret = |key: &str| {
+        attributes
+            .get(key)
+            .map(|value| SecretString::from(value.as_str()))
+    };
+
+    let given_passphrase = secret("passphrase");
+    if let Some(given) = &given_passphrase {
+        passphrase::check(
+            given.expose_secret(),
+            config.push.warn_weak_passphrase.unwrap_or(true),
+            false,
+        )?;
+    }
+
+    let ppc_text = PPCText {
+        password_payload: Some(
+            secret("payload").ok_or_else(|| PPCError::from("attribute `payload` is missing"))?,
+        ),
+        payload_file: None,
+        stdin: false,
+        prompt: false,
+        from_op: None,
+        from_bw: None,
+        from_vault: None,
+        from_env: Vec::new(),
+        base64: false,
+        normalize_newlines: false,
+        encrypt_for: None,
+        // the payload was read from stdin, not from the command line.
+        no_warn: true,
+        passphrase: given_passphrase,
+        qr: false,
+        qr_link: false,
+        note: attributes.get("note").map(|note| note.to_string()),
+        expire_after_days: parse_value(attributes, "expire_after_days")?,
+        expire_after_views: parse_value(attributes, "expire_after_views")?,
+        deletable_by_viewer: parse_value(attributes, "deletable_by_viewer")?,
+        retrieval_step: parse_value(attributes, "retrieval_step")?,
+        no_defaults: false,
+        preset: attributes.get("preset").map(|preset| preset.to_string()),
+        queue_on_failure: false,
+        paranoid: false,
+        enforce_strong_passphrase: false,
+        hold: false,
+        hold_for: None,
+        ephemeral: false,
+        expire_at: None,
+        expire_after: None,
+        exact: false,
+        to_profiles: Vec::new(),
+        recipients: Vec::new(),
+        notify_webhook: None,
+        email_to: None,
+        shorten: false,
+        validate: false,
+        tags: Vec::new(),
+        expire_after_first_view_confirmed: false,
+        dedupe: None,
+        dedupe_window: dedupe::DEFAULT_WINDOW,
+        idempotency_key: None,
+    };
+    let mut merged = config.apply(&ppc_text)?;
+    merged.password_payload = Some(payload::resolve(&ppc_text, config)?);
+    merged.note = merged.note.as_deref().map(template::expand);
+
+    let push = pwpush_api::create_text_push(transport, instance, &merged)?;
+    let url = share::url(transport, instance, PushKind::Text, &push);
+    history::record(
+        config,
+        instance,
+        &push,
+        merged.note.as_deref(),
+        &merged.tags,
+        &url,
+    );
+    let mut answer = vec![
+        (String::from("url"), url),
+        (String::from("url_token"), push.url_token),
+    ];
+    // a passphrase that was generated from a preset must be passed on,
+    // otherwise nobody would be able to open the push.
+    if ppc_text.passphrase.is_none() {
+        if let Some(passphrase) = &merged.passphrase {
+            answer.push((
+                String::from("passphrase"),
+                String::from(passphrase.expose_secret()),
+            ));
+        }
+    }
+    Ok(answer)
+}
+
+fn expire(
+    transport: &dyn Transport,
+    instance: &Instance,
+    attributes: &Attributes,
+) -> Result<Vec<(String, String)>, PPCError> {
+    check_known(attributes, &EXPIRE_ATTRIBUTES)?;
+    let url_token = attributes
+        .get("url_token")
+        .ok_or_else(|| PPCError::from("attribute `url_token` is missing"))?;
+
+    pwpush_api::expire_push(transport, instance, PushKind::Text, url_token)?;
+    Ok(vec![(String::from("expired"), String::from("true"))])
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use super::respond;
+    use crate::args::{HelperOperation, InstanceProtocol};
+    use crate::config::PPCConfig;
+    use crate::http::mock::MockTransport;
+    use crate::http::Method;
+    use crate::instance::Instance;
+
+    fn instance() -> Instance {
+        Instance {
+            protocol: InstanceProtocol::Https,
+            url: String::from("pwpush.example.com"),
+            email: None,
+            token: None,
+            auth_scheme: None,
+        }
+    }
+
+    fn answer(
+        transport: &MockTransport,
+        operation: HelperOperation,
+        input: &str,
+    ) -> (bool, String) {
+        let mut output = Vec::new();
+        let result = respond(
+            transport,
+            &instance(),
+            &PPCConfig::default(),
+            operation,
+            &mut Cursor::new(input),
+            &mut output,
+        );
+        (result.is_ok(), String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn push_answers_with_url() {
+        let transport = MockTransport::new()
+            .respond(201, r#"{"url_token":"abc"}"#)
+            .respond(200, r#"{"url":"https://pwpush.example.com/en/p/abc"}"#);
+
+        let (ok, output) = answer(
+            &transport,
+            HelperOperation::Push,
+            "payload=s3cret=with=equals\nexpire_after_views=1\nretrieval_step=false\n\nignored\n",
+        );
+
+        assert!(ok);
+        assert_eq!(
+            output,
+            "url=https://pwpush.example.com/en/p/abc\nurl_token=abc\n"
+        );
+        let body = transport.requests()[0].body.clone().unwrap();
+        assert!(body.contains("password[payload]=s3cret%3Dwith%3Dequals"));
+        assert!(body.contains("password[expire_after_views]=1"));
+        assert!(body.contains("password[retrieval_step]=false"));
+    }
+
+    #[test]
+    fn push_refuses_invalid_attributes() {
+        let transport = MockTransport::new();
+
+        let (ok, output) = answer(&transport, HelperOperation::Push, "note=no payload\n");
+        assert!(!ok);
+        assert_eq!(output, "error=attribute `payload` is missing\n");
+
+        let (_, output) = answer(
+            &transport,
+            HelperOperation::Push,
+            "payload=x\nexpire_after_view=1\n",
+        );
+        assert_eq!(output, "error=unknown attribute `expire_after_view`\n");
+
+        let (_, output) = answer(
+            &transport,
+            HelperOperation::Push,
+            "payload=x\nretrieval_step=maybe\n",
+        );
+        assert_eq!(output, "error=invalid value for `retrieval_step`\n");
+
+        let (_, output) = answer(&transport, HelperOperation::Push, "payload\n");
+        assert_eq!(output, "error=attributes must be given as key=value\n");
+
+        assert!(transport.requests().is_empty());
+    }
+
+    #[test]
+    fn expire_push() {
+        let transport = MockTransport::new().respond(200, r#"{"expired":true}"#);
+
+        let (ok, output) = answer(&transport, HelperOperation::Expire, "url_token=abc\n");
+
+        assert!(ok);
+        assert_eq!(output, "expired=true\n");
+        let request = &transport.requests()[0];
+        assert_eq!(request.method, Method::Delete);
+        assert!(request.url.contains("/p/abc"));
+    }
+
+    #[test]
+    fn reports_instance_errors() {
+        let transport = MockTransport::new().fail("connection refused");
+
+        let (ok, output) = answer(&transport, HelperOperation::Expire, "url_token=abc\n");
+
+        assert!(!ok);
+        assert!(output.starts_with("error="));
+        assert!(output.contains("connection refused"));
+    }
+}
@@ -0,0 +1,399 @@
+//! Local history of pushes made on this machine.
+//!
+//! The instance only lists the pushes of an account, so pushes made without
+//! authentication cannot be found again once the link is lost. With
+//! `enabled = true` in the `[history]` section of the configuration file, the
+//! URL token, instance, note, creation time and link of every push made on
+//! this machine are recorded, and can be shown with `history list` and
+//! `history search`, and deleted with `history clear`.
+//!
+//! The links give access to the secrets, so each entry is encrypted, see
+//! `state`. The history lives in `history` in the data directory, e.g.
+//! `~/.local/share/pwpush-cli/history` on Linux (see `paths`), with one line
+//! per push, so recording a push only appends to the file.
+//!
+//! Pushes with an idempotency key are recorded even if the history is
+//! disabled, as the key could not be looked up otherwise, see `idempotency`.
+//!
+//! Pushes can be tagged with `--tag`, e.g. `--tag team:infra --tag rotation`,
+//! as a lightweight organization on top of the flat lists of the instance.
+//! Tags only exist in the history, the instance never sees them, so tagged
+//! pushes are recorded even if the history is disabled. `history list --tag`
+//! shows the pushes with a tag, and `expire bulk --tag` expires them, e.g. all
+//! secrets of the last rotation. The kind of a push is taken from its link.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use base64::prelude::{Engine, BASE64_STANDARD};
+use chacha20poly1305::XChaCha20Poly1305;
+use chrono::{DateTime, SecondsFormat, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+use zeroize::Zeroizing;
+
+use crate::args::{HistoryCommand, PPCArgs, PushKind};
+use crate::config::PPCConfig;
+use crate::dates::DateFormatter;
+use crate::errors::PPCError;
+use crate::instance::Instance;
+use crate::models::Push;
+use crate::{fail_on, i18n, output, paths, prompt, state};
+
+const KEY_FILE: &str = "history.key";
+const HISTORY_FILE: &str = "history";
+
+/// A push that was made on this machine.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[schemars(rename = "HistoryEntry")]
+pub struct Entry {
+    pub url_token: String,
+    pub instance: String,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub url: String,
+    /// Given with `--idempotency-key`, see `idempotency`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<String>,
+    /// Given with `--tag`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl Entry {
+    /// Whether the entry has all of the tags.
+    fn has_tags(&self, tags: &[String]) -> bool {
+        tags.iter().all(|tag| self.tags.contains(tag))
+    }
+
+    /// Kind of the push, from the path of its link, e.g. `/en/p/<token>/r`.
+    fn kind(&self) -> Option<PushKind> {
+        let url = url::Url::parse(&self.url).ok()?;
+        let segments = url.path_segments()?.collect::<Vec<_>>();
+        let position = segments.iter().position(|s| *s == self.url_token)?;
+        match *segments.get(position.checked_sub(1)?)? {
+            "p" => Some(PushKind::Text),
+            "f" => Some(PushKind::File),
+            "r" => Some(PushKind::Url),
+            _ => None,
+        }
+    }
+
+    fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        [
+            Some(&self.url_token),
+            Some(&self.instance),
+            self.note.as_ref(),
+            Some(&self.url),
+        ]
+        .into_iter()
+        .flatten()
+        .any(|field| field.to_lowercase().contains(&query))
+    }
+}
+
+/// The encrypted history file.
+pub struct History {
+    path: PathBuf,
+    cipher: XChaCha20Poly1305,
+}
+
+impl History {
+    /// Open the history in the given directory, with its key in the key
+    /// directory `keys`, creating the directories and the key if needed.
+    pub fn open(dir: &Path, keys: &Path) -> Result<Self, PPCError> {
+        state::create_private_dir(dir)?;
+
+        Ok(Self {
+            path: dir.join(HISTORY_FILE),
+            cipher: state::cipher(&state::key_file(keys, dir, KEY_FILE)?)?,
+        })
+    }
+
+    pub fn add(&self, entry: &Entry) -> Result<(), PPCError> {
+        // the entry only consists of strings and a date, so serialization
+        // cannot fail.
+        let plaintext = Zeroizing::new(serde_json::to_vec(entry).unwrap());
+        let mut line = BASE64_STANDARD.encode(state::seal(&self.cipher, &plaintext)?);
+        line.push('\n');
+
+        debug!("recording push {} in history", entry.url_token);
+        state::append_private(&self.path, line.as_bytes())
+    }
+
+    /// All recorded pushes, oldest first.
+    pub fn entries(&self) -> Result<Vec<Entry>, PPCError> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(&self.path).map_err(|e| {
+            PPCError::from(format!("could not read {}: {}", self.path.display(), e))
+        })?;
+
+        content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.is_empty())
+            .map(|(number, line)| {
+                let invalid = |reason: String| {
+                    PPCError::from(format!(
+                        "{}, line {}: {}",
+                        self.path.display(),
+                        number + 1,
+                        reason
+                    ))
+                };
+                let sealed = BASE64_STANDARD
+                    .decode(line)
+                    .map_err(|e| invalid(e.to_string()))?;
+                let plaintext =
+                    state::unseal(&self.cipher, &sealed).map_err(|e| invalid(e.to_string()))?;
+                serde_json::from_slice(&plaintext).map_err(|e| invalid(e.to_string()))
+            })
+            .collect()
+    }
+
+    /// Delete all recorded pushes. The key is kept, it protects nothing on its
+    /// own.
+    pub fn clear(&self) -> Result<(), PPCError> {
+        if !self.path.exists() {
+            return Ok(());
+        }
+        fs::remove_file(&self.path)
+            .map_err(|e| PPCError::from(format!("could not remove {}: {}", self.path.display(), e)))
+    }
+}
+
+/// Default location of the history.
+pub fn default_dir() -> Result<PathBuf, PPCError> {
+    state::dir("history")
+}
+
+/// Value parser for `--tag`. Tags are shown separated by spaces, so they
+/// cannot contain any.
+pub fn parse_tag(value: &str) -> Result<String, String> {
+    if value.is_empty() || value.chars().any(char::is_whitespace) {
+        return Err(String::from("tags must not be empty or contain spaces"));
+    }
+    Ok(String::from(value))
+}
+
+/// Record a push that was made, if the history is enabled or the push has
+/// tags. The push exists already, so failing to record it is only warned
+/// about.
+pub fn record(
+    config: &PPCConfig,
+    instance: &Instance,
+    push: &Push,
+    note: Option<&str>,
+    tags: &[String],
+    url: &str,
+) {
+    if config.history.enabled || !tags.is_empty() {
+        record_with_key(instance, push, note, tags, url, None);
+    }
+}
+
+/// Record a push along with its idempotency key, whether the history is
+/// enabled or not.
+pub fn record_with_key(
+    instance: &Instance,
+    push: &Push,
+    note: Option<&str>,
+    tags: &[String],
+    url: &str,
+    idempotency_key: Option<&str>,
+) {
+    let entry = Entry {
+        url_token: push.url_token.clone(),
+        instance: instance.base_url(),
+        note: note.map(String::from),
+        created_at: Utc::now(),
+        url: String::from(url),
+        idempotency_key: idempotency_key.map(String::from),
+        tags: tags.to_vec(),
+    };
+    if let Err(e) =
+        default_dir().and_then(|dir| History::open(&dir, &paths::key_dir()?)?.add(&entry))
+    {
+        warn!(
+            "{}",
+            i18n::format(
+                "push-not-in-history",
+                &[
+                    ("url_token", push.url_token.as_str().into()),
+                    ("error", e.to_string().into())
+                ]
+            )
+        );
+        fail_on::soft_failure();
+    }
+}
+
+/// Entry point for the `history` subcommands.
+pub fn run(args: &PPCArgs, config: &PPCConfig, command: &HistoryCommand) -> Result<(), PPCError> {
+    if !config.history.enabled {
+        warn!("{}", i18n::text("history-disabled"));
+    }
+    let history = History::open(&default_dir()?, &paths::key_dir()?)?;
+
+    match command {
+        HistoryCommand::List { tags } => {
+            let entries = history
+                .entries()?
+                .into_iter()
+                .filter(|entry| entry.has_tags(tags))
+                .collect::<Vec<_>>();
+            print(args, &entries)
+        }
+        HistoryCommand::Search { query } => {
+            let entries = history
+                .entries()?
+                .into_iter()
+                .filter(|entry| entry.matches(query))
+                .collect::<Vec<_>>();
+            print(args, &entries)
+        }
+        HistoryCommand::Clear => {
+            if !prompt::confirm(&i18n::text("history-clear-question"), args.assume_yes)? {
+                eprintln!("{}", i18n::text("history-clear-aborted"));
+                return Ok(());
+            }
+            history.clear()
+        }
+    }
+}
+
+/// URL tokens of the pushes of the kind on the instance that have all of the
+/// tags, oldest first, for `expire bulk --tag`.
+pub fn tagged_tokens(
+    instance: &Instance,
+    kind: PushKind,
+    tags: &[String],
+) -> Result<Vec<String>, PPCError> {
+    let base_url = instance.base_url();
+    let tokens = History::open(&default_dir()?, &paths::key_dir()?)?
+        .entries()?
+        .into_iter()
+        .filter(|entry| {
+            entry.has_tags(tags) && entry.instance == base_url && entry.kind() == Some(kind)
+        })
+        .map(|entry| entry.url_token)
+        .collect::<Vec<_>>();
+    Ok(tokens)
+}
+
+fn print(args: &PPCArgs, entries: &[Entry]) -> Result<(), PPCError> {
+    if args.json_output {
+        output::print_json(entries);
+        return Ok(());
+    }
+
+    let dates = DateFormatter::new(args);
+    for entry in entries {
+        let created_at = entry.created_at.to_rfc3339_opts(SecondsFormat::Secs, true);
+        let tags = entry
+            .tags
+            .iter()
+            .map(|tag| format!("#{}", tag))
+            .collect::<Vec<_>>();
+        let line = format!(
+            "{}  {}  {}  {}  {}",
+            dates.format(&created_at),
+            entry.url_token,
+            entry.url,
+            entry.note.as_deref().unwrap_or_default(),
+            tags.join(" ")
+        );
+        println!("{}", line.trim_end());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use chrono::{TimeZone, Utc};
+
+    use super::{parse_tag, Entry, History};
+    use crate::args::PushKind;
+
+    fn entry(url_token: &str, note: Option<&str>) -> Entry {
+        Entry {
+            url_token: String::from(url_token),
+            instance: String::from("https://pwpush.example.com"),
+            note: note.map(String::from),
+            created_at: Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap(),
+            url: format!("https://pwpush.example.com/en/p/{}", url_token),
+            idempotency_key: None,
+            tags: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn entries_are_encrypted() {
+        let dir = tempfile::tempdir().unwrap();
+        let keys = tempfile::tempdir().unwrap();
+        let history = History::open(dir.path(), keys.path()).unwrap();
+
+        history.add(&entry("abc", Some("db password"))).unwrap();
+        history.add(&entry("def", None)).unwrap();
+
+        let content = fs::read_to_string(dir.path().join("history")).unwrap();
+        assert_eq!(content.lines().count(), 2);
+        assert!(!content.contains("abc"));
+        assert!(!content.contains("db password"));
+
+        let reopened = History::open(dir.path(), keys.path()).unwrap();
+        assert_eq!(
+            reopened.entries().unwrap(),
+            vec![entry("abc", Some("db password")), entry("def", None)]
+        );
+    }
+
+    #[test]
+    fn search_ignores_case() {
+        let entry = entry("abc", Some("DB password"));
+
+        assert!(entry.matches("db pass"));
+        assert!(entry.matches("ABC"));
+        assert!(entry.matches("example.com"));
+        assert!(!entry.matches("ssh"));
+    }
+
+    #[test]
+    fn entries_are_selected_by_tags_and_kind() {
+        let mut tagged = entry("abc", None);
+        tagged.tags = vec![String::from("team:infra"), String::from("rotation")];
+
+        assert!(tagged.has_tags(&[String::from("rotation")]));
+        assert!(tagged.has_tags(&[]));
+        assert!(!tagged.has_tags(&[String::from("rotation"), String::from("db")]));
+
+        assert_eq!(tagged.kind(), Some(PushKind::Text));
+        tagged.url = String::from("https://pwpush.example.com/pwpush/f/abc/r");
+        assert_eq!(tagged.kind(), Some(PushKind::File));
+        tagged.url = String::from("https://pwpush.example.com/abc");
+        assert_eq!(tagged.kind(), None);
+
+        assert!(parse_tag("team:infra").is_ok());
+        assert!(parse_tag("two words").is_err());
+        assert!(parse_tag("").is_err());
+    }
+
+    #[test]
+    fn clear_removes_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let keys = tempfile::tempdir().unwrap();
+        let history = History::open(dir.path(), keys.path()).unwrap();
+
+        history.clear().unwrap();
+        history.add(&entry("abc", None)).unwrap();
+        history.clear().unwrap();
+
+        assert!(history.entries().unwrap().is_empty());
+    }
+}
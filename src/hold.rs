@@ -0,0 +1,175 @@
+//! Holding pushes open only while the CLI keeps running.
+//!
+//! With `push text --hold`, the CLI does not exit after the link was printed,
+//! but waits until Ctrl-C is pressed or the time given with `--hold-for` has
+//! elapsed. With `--ephemeral`, the push is expired at that point, so a link
+//! that was shared e.g. in a pair-debugging session dies when the session
+//! ends, and not only after the configured days or views.
+//!
+//! SIGTERM ends the hold like Ctrl-C, see `signals`. If the CLI is killed in
+//! a way that cannot be caught, e.g. with `kill -9` or
+//! by closing the laptop lid for good, the push stays active until it expires
+//! on its own. Ephemeral pushes should therefore still be given short limits.
+
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+use tracing::{debug, warn};
+
+use crate::args::{PPCText, PushKind};
+use crate::errors::PPCError;
+use crate::http::Transport;
+use crate::instance::Instance;
+use crate::models::Push;
+use crate::{duration, i18n, pwpush_api, signals};
+
+/// Why the hold ended.
+#[derive(Debug, PartialEq, Eq)]
+enum Ending {
+    Interrupted,
+    TimedOut,
+}
+
+/// Wait until the hold ends, then expire the push if it is ephemeral.
+pub fn run(
+    transport: &dyn Transport,
+    instance: &Instance,
+    ppc_text: &PPCText,
+    push: &Push,
+) -> Result<(), PPCError> {
+    let listener = signals::listen();
+
+    let until = match ppc_text.hold_for {
+        Some(hold_for) => i18n::format(
+            "hold-until-timeout",
+            &[("duration", duration::format(hold_for).into())],
+        ),
+        None => i18n::text("hold-until"),
+    };
+    if ppc_text.ephemeral {
+        eprintln!(
+            "{}",
+            i18n::format(
+                "hold-ephemeral",
+                &[
+                    ("url_token", push.url_token.as_str().into()),
+                    ("until", until.into())
+                ]
+            )
+        );
+    } else {
+        eprintln!(
+            "{}",
+            i18n::format("hold-waiting", &[("until", until.into())])
+        );
+    }
+
+    let ending = wait(listener.receiver(), ppc_text.hold_for);
+    debug!("hold ended: {:?}", ending);
+    finish(transport, instance, ppc_text.ephemeral, &push.url_token)
+}
+
+fn wait(receiver: &Receiver<()>, hold_for: Option<Duration>) -> Ending {
+    let received = match hold_for {
+        Some(hold_for) => receiver.recv_timeout(hold_for),
+        None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+    };
+    match received {
+        Ok(()) => Ending::Interrupted,
+        Err(RecvTimeoutError::Timeout) => Ending::TimedOut,
+        // without a sender, Ctrl-C can no longer be noticed, so the hold is
+        // over as well.
+        Err(RecvTimeoutError::Disconnected) => {
+            warn!("{}", i18n::text("hold-no-interrupt"));
+            Ending::Interrupted
+        }
+    }
+}
+
+fn finish(
+    transport: &dyn Transport,
+    instance: &Instance,
+    ephemeral: bool,
+    url_token: &str,
+) -> Result<(), PPCError> {
+    if !ephemeral {
+        return Ok(());
+    }
+
+    pwpush_api::expire_push(transport, instance, PushKind::Text, url_token).map_err(|e| {
+        PPCError::from(format!(
+            "could not expire push {}, expire it with `expire text {}`: {}",
+            url_token, url_token, e
+        ))
+    })?;
+    eprintln!(
+        "{}",
+        i18n::format("hold-expired", &[("url_token", url_token.into())])
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use super::{finish, wait, Ending};
+    use crate::args::InstanceProtocol;
+    use crate::http::mock::MockTransport;
+    use crate::http::Method;
+    use crate::instance::Instance;
+
+    fn instance() -> Instance {
+        Instance {
+            protocol: InstanceProtocol::Https,
+            url: String::from("pwpush.example.com"),
+            email: None,
+            token: None,
+            auth_scheme: None,
+        }
+    }
+
+    #[test]
+    fn wait_ends_on_interrupt_or_timeout() {
+        let (sender, receiver) = mpsc::channel();
+
+        assert_eq!(
+            wait(&receiver, Some(Duration::from_millis(10))),
+            Ending::TimedOut
+        );
+
+        sender.send(()).unwrap();
+        assert_eq!(wait(&receiver, None), Ending::Interrupted);
+    }
+
+    #[test]
+    fn finish_expires_ephemeral_pushes() {
+        let transport = MockTransport::new().respond(200, r#"{"expired":true}"#);
+
+        finish(&transport, &instance(), true, "abc").unwrap();
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, Method::Delete);
+        assert!(requests[0].url.contains("/p/abc"));
+    }
+
+    #[test]
+    fn finish_keeps_other_pushes() {
+        let transport = MockTransport::new();
+
+        finish(&transport, &instance(), false, "abc").unwrap();
+
+        assert!(transport.requests().is_empty());
+    }
+
+    #[test]
+    fn finish_explains_failures() {
+        let transport = MockTransport::new().fail("connection refused");
+
+        let err = finish(&transport, &instance(), true, "abc").unwrap_err();
+
+        assert!(err.to_string().contains("expire text abc"));
+    }
+}
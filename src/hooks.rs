@@ -0,0 +1,468 @@
+//! User-defined actions that are triggered by events, e.g. a new view of a push
+//! while watching its audit log, or a new push.
+//!
+//! Two kinds of actions are supported:
+//!   - a shell command, which gets the details of the event in environment
+//!     variables prefixed with `PWPUSH_`
+//!   - a webhook, which receives the details of the event as JSON body of a
+//!     POST request
+//!
+//! `push text --notify-webhook` announces new pushes with their link, limits
+//! and note, never with the payload. The body has a `text` field with a
+//! readable summary, which is all the incoming webhooks of Slack, Teams and
+//! Matrix bridges like hookshot need to post a message.
+//!
+//! `serve --event-webhook` reports tracked pushes that were viewed for the
+//! last time, expired or were deleted, see `watchlist`. These events have a
+//! `text` field as well.
+//!
+//! The `[hooks]` section of the configuration file runs commands around
+//! operations of the CLI itself, to chain automation like ticket updates
+//! without wrapping the CLI in a script:
+//!   - `pre_push` before each push is created, with `PWPUSH_PUSH_KIND`
+//!   - `post_push` after each push was created, also with `PWPUSH_URL`,
+//!     `PWPUSH_URL_TOKEN` and `PWPUSH_NOTE`
+//!   - `post_expire` after each push was expired, with `PWPUSH_PUSH_KIND` and
+//!     `PWPUSH_URL_TOKEN`
+//!
+//! `PWPUSH_EVENT` names the hook, so one script can serve all of them. The
+//! payload and the passphrase are never passed to hooks. Their output goes
+//! to stderr, so it does not mix with the output of the CLI in pipes.
+//!
+//! Webhooks are third parties, so their requests go through a plain transport
+//! of their own, see `platform::transport`, never through the one of the
+//! instance with its credentials, `--header` values and recordings.
+//!
+//! Failing actions never abort the operation that triggered them, they are
+//! only reported as warnings. The exception is `pre_push`, which runs before
+//! anything happened, so a failing command prevents the push, e.g. to enforce
+//! a rule of the team.
+
+use std::io;
+use std::process::Command;
+
+use serde_json::json;
+use tracing::{debug, warn};
+
+use crate::args::PushKind;
+use crate::config::HookSettings;
+use crate::errors::PPCError;
+use crate::fail_on;
+use crate::http::{Method, PPCRequest, Transport};
+use crate::i18n;
+use crate::models::{AuditView, Push};
+
+/// Actions to run for each new view of a watched push.
+pub struct ViewHooks<'a> {
+    pub exec: Option<&'a str>,
+    pub webhook: Option<&'a str>,
+}
+
+impl ViewHooks<'_> {
+    /// Run the actions for a view. The webhook is posted with `webhooks`, not
+    /// with the transport of the instance.
+    pub fn fire(
+        &self,
+        webhooks: &dyn Transport,
+        kind: PushKind,
+        url_token: &str,
+        view: &AuditView,
+    ) {
+        if let Some(command) = self.exec {
+            if let Err(e) = run_command(command, &view_env(kind, url_token, view)) {
+                warn!(
+                    "{}",
+                    i18n::format(
+                        "hook-on-view-command-failed",
+                        &[("error", e.to_string().into())]
+                    )
+                );
+                fail_on::soft_failure();
+            }
+        }
+
+        if let Some(url) = self.webhook {
+            if let Err(e) = post_webhook(webhooks, url, kind, url_token, view) {
+                warn!(
+                    "{}",
+                    i18n::format(
+                        "hook-on-view-webhook-failed",
+                        &[("error", e.to_string().into())]
+                    )
+                );
+                fail_on::soft_failure();
+            }
+        }
+    }
+}
+
+/// Environment variables describing a view event.
+fn view_env(kind: PushKind, url_token: &str, view: &AuditView) -> Vec<(String, String)> {
+    let successful = view.successful.unwrap_or(true);
+
+    vec![
+        (String::from("PWPUSH_PUSH_KIND"), kind.to_string()),
+        (String::from("PWPUSH_URL_TOKEN"), String::from(url_token)),
+        (String::from("PWPUSH_VIEW_KIND"), view.kind_name()),
+        (
+            String::from("PWPUSH_VIEW_CREATED_AT"),
+            view.created_at.clone(),
+        ),
+        (
+            String::from("PWPUSH_VIEW_SUCCESSFUL"),
+            successful.to_string(),
+        ),
+        (
+            String::from("PWPUSH_VIEW_IP"),
+            view.ip.clone().unwrap_or_default(),
+        ),
+        (
+            String::from("PWPUSH_VIEW_USER_AGENT"),
+            view.user_agent.clone().unwrap_or_default(),
+        ),
+    ]
+}
+
+/// Run the `pre_push` hook, if any. The push must not be created if it
+/// fails.
+pub fn pre_push(settings: &HookSettings, kind: PushKind) -> Result<(), PPCError> {
+    let Some(command) = &settings.pre_push else {
+        return Ok(());
+    };
+    let env = [
+        (String::from("PWPUSH_EVENT"), String::from("pre_push")),
+        (String::from("PWPUSH_PUSH_KIND"), kind.to_string()),
+    ];
+    run_hook(command, &env).map_err(|e| PPCError::from(format!("pre-push hook failed: {}", e)))
+}
+
+/// Run the `post_push` hook for a new push, if any.
+pub fn post_push(settings: &HookSettings, kind: PushKind, push: &Push, push_url: &str) {
+    let Some(command) = &settings.post_push else {
+        return;
+    };
+    let env = [
+        (String::from("PWPUSH_EVENT"), String::from("post_push")),
+        (String::from("PWPUSH_PUSH_KIND"), kind.to_string()),
+        (String::from("PWPUSH_URL"), String::from(push_url)),
+        (String::from("PWPUSH_URL_TOKEN"), push.url_token.clone()),
+        (
+            String::from("PWPUSH_NOTE"),
+            push.note.clone().unwrap_or_default(),
+        ),
+    ];
+    if let Err(e) = run_hook(command, &env) {
+        warn!(
+            "{}",
+            i18n::format("hook-post-push-failed", &[("error", e.to_string().into())])
+        );
+        fail_on::soft_failure();
+    }
+}
+
+/// Run the `post_expire` hook for an expired push, if any.
+pub fn post_expire(settings: &HookSettings, kind: PushKind, url_token: &str) {
+    let Some(command) = &settings.post_expire else {
+        return;
+    };
+    let env = [
+        (String::from("PWPUSH_EVENT"), String::from("post_expire")),
+        (String::from("PWPUSH_PUSH_KIND"), kind.to_string()),
+        (String::from("PWPUSH_URL_TOKEN"), String::from(url_token)),
+    ];
+    if let Err(e) = run_hook(command, &env) {
+        warn!(
+            "{}",
+            i18n::format(
+                "hook-post-expire-failed",
+                &[("error", e.to_string().into())]
+            )
+        );
+        fail_on::soft_failure();
+    }
+}
+
+/// Run a command through the system shell with additional environment
+/// variables. Output of the command is passed through, so it shows up in the
+/// terminal just like the output of the CLI itself.
+pub fn run_command(command: &str, env: &[(String, String)]) -> Result<(), PPCError> {
+    wait(
+        command,
+        shell(command).envs(env.iter().map(|(k, v)| (k, v))),
+    )
+}
+
+/// Like `run_command`, but with the output of the command on stderr, for
+/// hooks of commands whose output is piped.
+fn run_hook(command: &str, env: &[(String, String)]) -> Result<(), PPCError> {
+    wait(
+        command,
+        shell(command)
+            .envs(env.iter().map(|(k, v)| (k, v)))
+            .stdout(io::stderr()),
+    )
+}
+
+fn shell(command: &str) -> Command {
+    debug!("running hook command `{}`", command);
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+
+        // cmd does not follow the quoting rules of other programs, so quotes
+        // in the command would end up escaped. It is passed on as is instead.
+        let mut c = Command::new("cmd");
+        c.arg("/C").raw_arg(command);
+        c
+    }
+    #[cfg(not(windows))]
+    {
+        let mut c = Command::new("sh");
+        c.arg("-c").arg(command);
+        c
+    }
+}
+
+fn wait(command: &str, shell: &mut Command) -> Result<(), PPCError> {
+    let status = shell
+        .status()
+        .map_err(|e| PPCError::from(format!("could not start `{}`: {}", command, e)))?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(PPCError::from(format!(
+            "`{}` exited with {}",
+            command, status
+        )))
+    }
+}
+
+fn post_webhook(
+    transport: &dyn Transport,
+    url: &str,
+    kind: PushKind,
+    url_token: &str,
+    view: &AuditView,
+) -> Result<(), PPCError> {
+    debug!("posting view event to webhook {}", url);
+
+    let body = json!({
+        "push_kind": kind.to_string(),
+        "url_token": url_token,
+        "view": view,
+    });
+    send_json(transport, url, &body)
+}
+
+/// Announce a new push to the webhook given with `--notify-webhook`, if any,
+/// with the transport for webhooks. The push exists already, so a failing
+/// webhook is only warned about.
+pub fn notify_push(webhooks: &dyn Transport, webhook: Option<&str>, push: &Push, push_url: &str) {
+    let Some(webhook) = webhook else {
+        return;
+    };
+    debug!("announcing push {} to webhook {}", push.url_token, webhook);
+    if let Err(e) = send_json(webhooks, webhook, &push_summary(push, push_url)) {
+        warn!(
+            "{}",
+            i18n::format("hook-notify-failed", &[("error", e.to_string().into())])
+        );
+        fail_on::soft_failure();
+    }
+}
+
+/// The announcement of a new push. Only the metadata is included, the payload
+/// never leaves the push.
+fn push_summary(push: &Push, push_url: &str) -> serde_json::Value {
+    let mut text = match &push.note {
+        Some(note) => format!("New secret link for {}: {}", note, push_url),
+        None => format!("New secret link: {}", push_url),
+    };
+    if let Some(limits) = push.limits() {
+        text += &format!(" (expires after {})", limits);
+    }
+
+    json!({
+        "text": text,
+        "url": push_url,
+        "url_token": push.url_token,
+        "created_at": push.created_at,
+        "expire_after_days": push.expire_after_days,
+        "expire_after_views": push.expire_after_views,
+        "note": push.note,
+    })
+}
+
+/// Report the end of a tracked push to the webhooks of `serve`. Failing
+/// webhooks are only warned about, the others are notified anyway.
+#[cfg(feature = "serve")]
+pub fn notify_expiry(transport: &dyn Transport, webhooks: &[String], event: &str, push: &Push) {
+    let body = expiry_summary(event, push);
+    for webhook in webhooks {
+        debug!(
+            "reporting {} of push {} to webhook {}",
+            event, push.url_token, webhook
+        );
+        if let Err(e) = send_json(transport, webhook, &body) {
+            warn!(
+                "{}",
+                i18n::format("hook-event-failed", &[("error", e.to_string().into())])
+            );
+            fail_on::soft_failure();
+        }
+    }
+}
+
+/// The event of a push that ended, with the metadata of the push only.
+#[cfg(feature = "serve")]
+fn expiry_summary(event: &str, push: &Push) -> serde_json::Value {
+    let what = match &push.note {
+        Some(note) => format!("Secret link for {}", note),
+        None => format!("Secret link {}", push.url_token),
+    };
+    let text = match event {
+        "fully_viewed" => format!("{} was viewed for the last time", what),
+        "deleted" => format!("{} was deleted", what),
+        _ => format!("{} expired", what),
+    };
+
+    json!({
+        "event": event,
+        "text": text,
+        "url_token": push.url_token,
+        "created_at": push.created_at,
+        "expired_on": push.expired_on,
+        "expire_after_days": push.expire_after_days,
+        "expire_after_views": push.expire_after_views,
+        "note": push.note,
+    })
+}
+
+fn send_json(
+    transport: &dyn Transport,
+    url: &str,
+    body: &serde_json::Value,
+) -> Result<(), PPCError> {
+    let request = PPCRequest::new(Method::Post, String::from(url))
+        .header("Content-Type", "application/json")
+        .body(body.to_string());
+
+    let response = transport.send(&request)?;
+    if response.is_success() {
+        Ok(())
+    } else {
+        Err(PPCError::from(format!(
+            "webhook responded with status {}",
+            response.status
+        )))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{notify_push, pre_push, run_command, ViewHooks};
+    use crate::args::PushKind;
+    use crate::config::HookSettings;
+    use crate::http::mock::MockTransport;
+    use crate::http::Method;
+    use crate::models::{AuditView, Push};
+
+    fn view() -> AuditView {
+        serde_json::from_str(r#"{"ip":"10.0.0.1","created_at":"2023-12-01T10:00:00Z"}"#).unwrap()
+    }
+
+    #[test]
+    fn webhook_receives_event() {
+        let transport = MockTransport::new().respond(200, "ok");
+        let hooks = ViewHooks {
+            exec: None,
+            webhook: Some("https://hooks.example.com/view"),
+        };
+
+        hooks.fire(&transport, PushKind::Text, "abc", &view());
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, Method::Post);
+        assert_eq!(requests[0].url, "https://hooks.example.com/view");
+
+        let body: serde_json::Value =
+            serde_json::from_str(requests[0].body.as_deref().unwrap()).unwrap();
+        assert_eq!(body["url_token"], "abc");
+        assert_eq!(body["view"]["ip"], "10.0.0.1");
+    }
+
+    #[test]
+    fn push_announcement_leaves_out_the_payload() {
+        let transport = MockTransport::new().respond(200, "ok");
+        let push: Push = serde_json::from_str(
+            r#"{"url_token":"abc","expire_after_days":1,"expire_after_views":3,"note":"db","payload":"s3cret"}"#,
+        )
+        .unwrap();
+
+        notify_push(
+            &transport,
+            Some("https://hooks.example.com/push"),
+            &push,
+            "https://pwpush.com/en/p/abc",
+        );
+        notify_push(&transport, None, &push, "https://pwpush.com/en/p/abc");
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 1);
+        let body = requests[0].body.as_deref().unwrap();
+        assert!(!body.contains("s3cret"));
+
+        let body: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(
+            body["text"],
+            "New secret link for db: https://pwpush.com/en/p/abc (expires after 1 day or 3 views)"
+        );
+        assert_eq!(body["expire_after_views"], 3);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_command_passes_env() {
+        let env = vec![(String::from("PWPUSH_URL_TOKEN"), String::from("abc"))];
+
+        assert!(run_command("test \"$PWPUSH_URL_TOKEN\" = abc", &env).is_ok());
+        assert!(run_command("test \"$PWPUSH_URL_TOKEN\" = xyz", &env).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn failing_pre_push_hook_prevents_the_push() {
+        let hooks = |command: &str| HookSettings {
+            pre_push: Some(String::from(command)),
+            ..HookSettings::default()
+        };
+
+        assert!(pre_push(&HookSettings::default(), PushKind::Text).is_ok());
+        assert!(pre_push(
+            &hooks("test \"$PWPUSH_EVENT-$PWPUSH_PUSH_KIND\" = pre_push-file"),
+            PushKind::File
+        )
+        .is_ok());
+        assert!(pre_push(&hooks("exit 1"), PushKind::File).is_err());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn run_command_passes_env() {
+        let env = vec![(String::from("PWPUSH_URL_TOKEN"), String::from("abc"))];
+
+        assert!(run_command(
+            "if \"%PWPUSH_URL_TOKEN%\"==\"abc\" (exit /b 0) else (exit /b 1)",
+            &env
+        )
+        .is_ok());
+        assert!(run_command(
+            "if \"%PWPUSH_URL_TOKEN%\"==\"xyz\" (exit /b 0) else (exit /b 1)",
+            &env
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,772 @@
+//! Small abstraction over the HTTP client used to talk to Password Pusher.
+//!
+//! The API functions do not use reqwest directly, but describe each request as
+//! a plain `PPCRequest` and hand it to a `Transport`. In production this is
+//! the `ReqwestTransport`, in tests a `MockTransport` can be used that records
+//! all requests and answers with canned responses. This allows to test the API
+//! functions end to end without touching pwpush.com or any other instance.
+
+use std::fmt::{self, Display};
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs::File;
+use std::path::{Path, PathBuf};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
+
+#[cfg(not(target_arch = "wasm32"))]
+use reqwest::blocking::Body;
+#[cfg(not(target_arch = "wasm32"))]
+use reqwest::header::CONTENT_TYPE;
+#[cfg(not(target_arch = "wasm32"))]
+use reqwest::redirect::Policy;
+use serde::{Deserialize, Serialize};
+#[cfg(not(target_arch = "wasm32"))]
+use tracing::{debug, debug_span};
+
+use crate::errors::PPCError;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::i18n;
+use crate::multipart::Multipart;
+use crate::redact::{sanitize_body, sanitize_headers, sanitize_json, sanitize_url};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::user_agent;
+
+/// HTTP methods used by the Password Pusher API. Only the methods for the
+/// endpoints that are already implemented are listed here.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Method {
+    Get,
+    Post,
+    Patch,
+    Delete,
+}
+
+impl Display for Method {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Method::Get => write!(f, "GET"),
+            Method::Post => write!(f, "POST"),
+            Method::Patch => write!(f, "PATCH"),
+            Method::Delete => write!(f, "DELETE"),
+        }
+    }
+}
+
+/// Everything needed to perform a single API call. Headers are kept in a
+/// simple vec, as there are only very few of them and the order in which they
+/// were added is preserved, which makes assertions in tests straightforward.
+#[derive(Clone, PartialEq, Eq)]
+pub struct PPCRequest {
+    pub method: Method,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+
+    /// Body of file uploads, used instead of `body`. The files are streamed
+    /// from disk when the request is sent, see `multipart`.
+    pub multipart: Option<Multipart>,
+
+    /// The body of a successful response is written to this file instead of
+    /// `body`, for downloads that may not fit into memory.
+    pub download: Option<PathBuf>,
+
+    /// The response does not contain secrets and may be kept for conditional
+    /// requests, see `cache`.
+    pub cacheable: bool,
+}
+
+impl PPCRequest {
+    pub fn new(method: Method, url: String) -> Self {
+        Self {
+            method,
+            url,
+            headers: Vec::new(),
+            body: None,
+            multipart: None,
+            download: None,
+            cacheable: false,
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((String::from(name), String::from(value)));
+        self
+    }
+
+    pub fn body(mut self, body: String) -> Self {
+        self.body = Some(body);
+        self
+    }
+
+    pub fn multipart(mut self, multipart: Multipart) -> Self {
+        self.multipart = Some(multipart);
+        self
+    }
+
+    pub fn download_to(mut self, path: &Path) -> Self {
+        self.download = Some(path.to_path_buf());
+        self
+    }
+
+    pub fn cacheable(mut self) -> Self {
+        self.cacheable = true;
+        self
+    }
+}
+
+/// Credentials, payload and passphrase are redacted, see `redact`.
+impl fmt::Debug for PPCRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PPCRequest")
+            .field("method", &self.method)
+            .field("url", &sanitize_url(&self.url))
+            .field("headers", &sanitize_headers(&self.headers))
+            .field("body", &self.body.as_deref().map(sanitize_body))
+            .field("multipart", &self.multipart)
+            .field("download", &self.download)
+            .field("cacheable", &self.cacheable)
+            .finish()
+    }
+}
+
+/// The parts of an HTTP response the application cares about.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PPCResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+
+    /// Set if the instance answered `304 Not Modified`, and this is the
+    /// response it gave before, see `cache`
+    #[serde(skip)]
+    pub not_modified: bool,
+}
+
+/// Retrieved pushes contain the payload, which is redacted.
+impl fmt::Debug for PPCResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PPCResponse")
+            .field("status", &self.status)
+            .field("headers", &sanitize_headers(&self.headers))
+            .field("body", &sanitize_json(&self.body))
+            .finish()
+    }
+}
+
+impl PPCResponse {
+    /// True for all 2xx status codes.
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Value of the first header with this name, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Anything that is able to perform a `PPCRequest`. Errors are only returned
+/// if no response could be obtained at all (DNS failures, refused connections,
+/// ...). Responses with error status codes are still valid responses and it
+/// is up to the caller to interpret them.
+///
+/// Transports are shared between the worker threads of bulk operations, so
+/// they need to be `Sync`.
+pub trait Transport: Sync {
+    fn send(&self, request: &PPCRequest) -> Result<PPCResponse, PPCError>;
+}
+
+/// Transports are stacked, e.g. throttling around recording around the
+/// network. Boxed transports can be wrapped as well.
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    fn send(&self, request: &PPCRequest) -> Result<PPCResponse, PPCError> {
+        (**self).send(request)
+    }
+}
+
+/// Name of the TLS implementation the binary was built with, selected with
+/// the `native-tls` and `rustls` cargo features.
+#[cfg(feature = "rustls")]
+pub const TLS_BACKEND: &str = "rustls";
+#[cfg(all(feature = "native-tls", not(feature = "rustls")))]
+pub const TLS_BACKEND: &str = "native-tls";
+// in WebAssembly, the browser does TLS.
+#[cfg(all(
+    target_arch = "wasm32",
+    not(any(feature = "native-tls", feature = "rustls"))
+))]
+pub const TLS_BACKEND: &str = "browser";
+#[cfg(not(any(feature = "native-tls", feature = "rustls", target_arch = "wasm32")))]
+compile_error!("pwpush-cli needs a TLS backend, enable the native-tls or the rustls feature");
+
+/// Time that a request with a file upload or download may take in total.
+#[cfg(not(target_arch = "wasm32"))]
+const TRANSFER_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// Time that idle connections are kept open for the next request.
+#[cfg(not(target_arch = "wasm32"))]
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// Interval of TCP keep-alive probes, so that connections survive pauses of
+/// long runs, e.g. of `watch` or `--max-rate`, behind NAT gateways.
+#[cfg(not(target_arch = "wasm32"))]
+const TCP_KEEPALIVE: Duration = Duration::from_secs(30);
+
+/// The production transport, backed by a blocking reqwest client.
+///
+/// One transport is built per run and shared by all its requests to the
+/// instance, also by the worker threads of bulk operations, the pages that are
+/// looked through to verify expirations and both instances of `copy`, and one
+/// more for all other requests, see `platform::transport`. The client keeps
+/// connections alive in its pool, so each of them only pays for the TCP and
+/// TLS handshakes once, not once per request.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct ReqwestTransport {
+    client: reqwest::blocking::Client,
+
+    /// Running number of requests sent by this transport, a field of the span
+    /// of each request so its log lines can be correlated, see `logging`.
+    request_count: AtomicUsize,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ReqwestTransport {
+    pub fn new() -> Self {
+        Self::with_redirects(Policy::default())
+    }
+
+    /// A transport that returns redirects instead of following them, for
+    /// requests to the instance, see `redirect`.
+    pub fn without_redirects() -> Self {
+        Self::with_redirects(Policy::none())
+    }
+
+    fn with_redirects(policy: Policy) -> Self {
+        // requests to the instance set the User-Agent themselves, see
+        // `user_agent`. This is for all others, e.g. to Vault.
+        let builder = reqwest::blocking::Client::builder()
+            .user_agent(user_agent::default())
+            .redirect(policy)
+            .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+            .tcp_keepalive(TCP_KEEPALIVE);
+        // with both features, reqwest would pick native-tls.
+        #[cfg(feature = "rustls")]
+        let builder = builder.use_rustls_tls();
+
+        Self {
+            // like `Client::new`, this only fails if the TLS backend cannot be
+            // initialized, which leaves nothing to do.
+            client: builder
+                .build()
+                .expect("could not initialize the TLS backend"),
+            request_count: AtomicUsize::new(0),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for ReqwestTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Transport for ReqwestTransport {
+    fn send(&self, request: &PPCRequest) -> Result<PPCResponse, PPCError> {
+        let request_id = self.request_count.fetch_add(1, Ordering::Relaxed) + 1;
+        let _span = debug_span!(
+            "request",
+            request_id,
+            method = %request.method,
+            url = %sanitize_url(&request.url),
+        )
+        .entered();
+        debug!(
+            "sending {} request to {}",
+            request.method,
+            sanitize_url(&request.url)
+        );
+
+        let mut builder = match request.method {
+            Method::Get => self.client.get(&request.url),
+            Method::Post => self.client.post(&request.url),
+            Method::Patch => self.client.patch(&request.url),
+            Method::Delete => self.client.delete(&request.url),
+        };
+
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+
+        if let Some(body) = &request.body {
+            builder = builder.body(body.clone());
+        }
+        if let Some(multipart) = &request.multipart {
+            // the files are read while they are sent, so large uploads take
+            // longer than the default timeout allows.
+            builder = builder
+                .header(CONTENT_TYPE, multipart.content_type())
+                .body(Body::sized(multipart.reader(), multipart.content_length()))
+                .timeout(TRANSFER_TIMEOUT);
+        }
+        if request.download.is_some() {
+            builder = builder.timeout(TRANSFER_TIMEOUT);
+        }
+
+        let response = builder.send().map_err(|e| transport_error(&e))?;
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| {
+                value
+                    .to_str()
+                    .ok()
+                    .map(|value| (name.to_string(), String::from(value)))
+            })
+            .collect();
+        let body = match &request.download {
+            Some(path) if response.status().is_success() => {
+                let mut response = response;
+                File::create(path)
+                    .and_then(|mut file| {
+                        response.copy_to(&mut file).map_err(std::io::Error::other)?;
+                        file.sync_all()
+                    })
+                    .map_err(|e| {
+                        PPCError::from(format!("could not download to {}: {}", path.display(), e))
+                    })?;
+                String::new()
+            }
+            _ => response
+                .text()
+                .map_err(|e| PPCError::from(format!("could not read response body: {}", e)))?,
+        };
+
+        debug!(status, "received response with status {}", status);
+        Ok(PPCResponse {
+            status,
+            headers,
+            body,
+            not_modified: false,
+        })
+    }
+}
+
+/// Transport of the async API of the library, see `client::AsyncClient`.
+/// Like `Transport`, but the response is awaited in the runtime of the
+/// calling program.
+#[cfg(feature = "async")]
+pub trait AsyncTransport: Sync {
+    fn send(
+        &self,
+        request: &PPCRequest,
+    ) -> impl std::future::Future<Output = Result<PPCResponse, PPCError>> + Send;
+}
+
+/// The async transport, backed by an async reqwest client, which needs a
+/// tokio runtime. Only requests with a form body are supported, i.e. no file
+/// uploads and downloads. Redirects are returned instead of followed, as the
+/// credentials must not go to another host, see `redirect`.
+#[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+pub struct ReqwestAsyncTransport {
+    client: reqwest::Client,
+}
+
+#[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+impl ReqwestAsyncTransport {
+    pub fn new() -> Self {
+        let builder = reqwest::Client::builder()
+            .user_agent(user_agent::default())
+            .redirect(Policy::none())
+            .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+            .tcp_keepalive(TCP_KEEPALIVE);
+        #[cfg(feature = "rustls")]
+        let builder = builder.use_rustls_tls();
+
+        Self {
+            // see `ReqwestTransport::with_redirects`.
+            client: builder
+                .build()
+                .expect("could not initialize the TLS backend"),
+        }
+    }
+}
+
+#[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+impl Default for ReqwestAsyncTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+impl AsyncTransport for ReqwestAsyncTransport {
+    async fn send(&self, request: &PPCRequest) -> Result<PPCResponse, PPCError> {
+        if request.multipart.is_some() || request.download.is_some() {
+            return Err(PPCError::from(
+                "file uploads and downloads are not supported by the async API",
+            ));
+        }
+        debug!(
+            "sending {} request to {}",
+            request.method,
+            sanitize_url(&request.url)
+        );
+
+        let mut builder = match request.method {
+            Method::Get => self.client.get(&request.url),
+            Method::Post => self.client.post(&request.url),
+            Method::Patch => self.client.patch(&request.url),
+            Method::Delete => self.client.delete(&request.url),
+        };
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        if let Some(body) = &request.body {
+            builder = builder.body(body.clone());
+        }
+
+        let response = builder.send().await.map_err(|e| transport_error(&e))?;
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| {
+                value
+                    .to_str()
+                    .ok()
+                    .map(|value| (name.to_string(), String::from(value)))
+            })
+            .collect();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| PPCError::from(format!("could not read response body: {}", e)))?;
+
+        debug!(status, "received response with status {}", status);
+        Ok(PPCResponse {
+            status,
+            headers,
+            body,
+            not_modified: false,
+        })
+    }
+}
+
+/// A failed request as error for people. reqwest names only the URL in its
+/// message, the actual cause is at the end of the chain of sources.
+#[cfg(not(target_arch = "wasm32"))]
+fn transport_error(error: &reqwest::Error) -> PPCError {
+    let mut cause: &dyn std::error::Error = error;
+    while let Some(source) = cause.source() {
+        cause = source;
+    }
+    let host = error
+        .url()
+        .and_then(|url| url.host_str())
+        .unwrap_or_default();
+    classify(
+        host,
+        error.is_timeout(),
+        error.is_connect(),
+        &cause.to_string(),
+    )
+    .unwrap_or_else(|| PPCError::from(format!("{}: {}", error, cause)))
+}
+
+/// The error for a failure with `cause`, if it is a common one.
+#[cfg(not(target_arch = "wasm32"))]
+fn classify(host: &str, timeout: bool, connect: bool, cause: &str) -> Option<PPCError> {
+    let error = |id: &str| {
+        let message = i18n::format(
+            &format!("error-{}", id),
+            &[("host", host.into()), ("cause", cause.into())],
+        );
+        Some(PPCError::from(message).with_hint(i18n::text(&format!("hint-{}", id))))
+    };
+
+    let lower = cause.to_lowercase();
+    if timeout {
+        error("timeout")
+    } else if lower.contains("certificate") {
+        error("certificate")
+    } else if !connect {
+        None
+    } else if [
+        "dns error",
+        "failed to lookup address",
+        "not known",
+        "no such host",
+    ]
+    .iter()
+    .any(|text| lower.contains(text))
+    {
+        error("dns")
+    } else if lower.contains("connection refused") || lower.contains("os error 10061") {
+        error("connection-refused")
+    } else {
+        error("connection")
+    }
+}
+
+/// Test double for the `Transport` trait. Responses are handed out in the order
+/// they were queued, and every request is recorded so tests can assert on the
+/// exact method, URL, headers and body that would have been sent.
+#[cfg(test)]
+pub mod mock {
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    use super::{PPCRequest, PPCResponse, Transport};
+    use crate::errors::PPCError;
+
+    #[derive(Default)]
+    pub struct MockTransport {
+        responses: Mutex<VecDeque<Result<PPCResponse, PPCError>>>,
+        requests: Mutex<Vec<PPCRequest>>,
+    }
+
+    impl MockTransport {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Queue a response with the given status code and body.
+        pub fn respond(self, status: u16, body: &str) -> Self {
+            self.respond_with_headers(status, &[], body)
+        }
+
+        /// Queue a response that also carries the given headers.
+        pub fn respond_with_headers(
+            self,
+            status: u16,
+            headers: &[(&str, &str)],
+            body: &str,
+        ) -> Self {
+            self.responses.lock().unwrap().push_back(Ok(PPCResponse {
+                status,
+                headers: headers
+                    .iter()
+                    .map(|(name, value)| (String::from(*name), String::from(*value)))
+                    .collect(),
+                body: String::from(body),
+                not_modified: false,
+            }));
+            self
+        }
+
+        /// Queue a transport level failure, e.g. an unreachable host.
+        pub fn fail(self, message: &str) -> Self {
+            self.responses
+                .lock()
+                .unwrap()
+                .push_back(Err(PPCError::from(message)));
+            self
+        }
+
+        /// All requests sent so far, in order.
+        pub fn requests(&self) -> Vec<PPCRequest> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    impl Transport for MockTransport {
+        fn send(&self, request: &PPCRequest) -> Result<PPCResponse, PPCError> {
+            self.requests.lock().unwrap().push(request.clone());
+            let response = self
+                .responses
+                .lock()
+                .unwrap()
+                .pop_front()
+                .expect("MockTransport received more requests than responses were queued")?;
+            match &request.download {
+                Some(path) if response.is_success() => {
+                    std::fs::write(path, &response.body).unwrap();
+                    Ok(PPCResponse {
+                        body: String::new(),
+                        ..response
+                    })
+                }
+                _ => Ok(response),
+            }
+        }
+    }
+
+    /// The same answers for the async API, see `client`.
+    #[cfg(feature = "async")]
+    impl super::AsyncTransport for MockTransport {
+        async fn send(&self, request: &PPCRequest) -> Result<PPCResponse, PPCError> {
+            Transport::send(self, request)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Instant;
+
+    use super::{classify, Method, PPCRequest, PPCResponse, ReqwestTransport, Transport};
+    use crate::bulk;
+
+    /// A local HTTP/1.1 server that answers every request with `{}` and keeps
+    /// connections open. Returns its URL and the number of connections made.
+    fn serve() -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/p.json", listener.local_addr().unwrap());
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&connections);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                counter.fetch_add(1, Ordering::Relaxed);
+                thread::spawn(move || {
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    let mut stream = stream;
+                    let mut line = String::new();
+                    loop {
+                        line.clear();
+                        match reader.read_line(&mut line) {
+                            Ok(0) | Err(_) => return,
+                            Ok(_) if line == "\r\n" => {
+                                let response = "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}";
+                                if stream.write_all(response.as_bytes()).is_err() {
+                                    return;
+                                }
+                            }
+                            Ok(_) => {}
+                        }
+                    }
+                });
+            }
+        });
+        (url, connections)
+    }
+
+    fn get(transport: &ReqwestTransport, url: &str) {
+        let request = PPCRequest::new(Method::Get, String::from(url));
+        assert_eq!(transport.send(&request).unwrap().status, 200);
+    }
+
+    #[test]
+    fn connections_are_reused() {
+        let (url, connections) = serve();
+        let transport = ReqwestTransport::without_redirects();
+
+        bulk::run_parallel(&[(); 100], 4, |_| get(&transport, &url));
+
+        let connections = connections.load(Ordering::Relaxed);
+        assert!(
+            (1..=4).contains(&connections),
+            "{} connections",
+            connections
+        );
+    }
+
+    /// Latency of 100 sequential requests to a local server, with one
+    /// transport for all of them and with a new one for each. The new ones
+    /// are built before the clock starts, so only the connections are
+    /// compared, not building the clients. Run with
+    /// `cargo test --release keep_alive_latency -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn keep_alive_latency() {
+        const REQUESTS: usize = 100;
+        let (url, connections) = serve();
+        let shared = ReqwestTransport::without_redirects();
+        get(&shared, &url);
+        let fresh: Vec<_> = (0..REQUESTS)
+            .map(|_| ReqwestTransport::without_redirects())
+            .collect();
+
+        let start = Instant::now();
+        for _ in 0..REQUESTS {
+            get(&shared, &url);
+        }
+        let reused = start.elapsed();
+        let start = Instant::now();
+        for transport in &fresh {
+            get(transport, &url);
+        }
+        let new = start.elapsed();
+
+        println!(
+            "{} requests: {:?} reusing connections, {:?} with new clients",
+            REQUESTS, reused, new
+        );
+        assert_eq!(connections.load(Ordering::Relaxed), 1 + REQUESTS);
+        assert!(reused < new);
+    }
+
+    #[test]
+    fn debug_redacts_request_secrets() {
+        let request = PPCRequest::new(
+            Method::Get,
+            String::from("https://pwpush.com/p/abc.json?passphrase=open"),
+        )
+        .header("X-User-Token", "t0k3n")
+        .body(String::from("password[payload]=secret&password[note]=n"));
+
+        let debug = format!("{:?}", request);
+
+        assert!(!debug.contains("open"));
+        assert!(!debug.contains("t0k3n"));
+        assert!(!debug.contains("secret"));
+        assert!(debug.contains("password[note]=n"));
+    }
+
+    #[test]
+    fn common_failures_get_hints() {
+        let dns = classify(
+            "pwpush.exmaple.com",
+            false,
+            true,
+            "failed to lookup address information: Name or service not known",
+        )
+        .unwrap();
+        assert_eq!(
+            dns.to_string(),
+            "could not find the host pwpush.exmaple.com"
+        );
+        assert!(dns.hint().unwrap().contains("--url"));
+
+        let refused = classify(
+            "localhost",
+            false,
+            true,
+            "Connection refused (os error 111)",
+        );
+        assert!(refused
+            .unwrap()
+            .to_string()
+            .contains("refused the connection"));
+        let timeout = classify("pwpush.com", true, false, "operation timed out");
+        assert!(timeout.unwrap().to_string().contains("timed out"));
+        assert!(classify("pwpush.com", false, false, "unexpected EOF").is_none());
+    }
+
+    #[test]
+    fn debug_redacts_response_payload() {
+        let response = PPCResponse {
+            status: 200,
+            headers: Vec::new(),
+            body: String::from(r#"{"url_token":"abc","payload":"secret"}"#),
+            not_modified: false,
+        };
+
+        let debug = format!("{:?}", response);
+
+        assert!(!debug.contains("secret"));
+        assert!(debug.contains("abc"));
+    }
+}
@@ -0,0 +1,349 @@
+//! Translations of human-readable output and error messages.
+//!
+//! Messages are kept in Fluent files in `locales/`, one per language, which
+//! are compiled into the binary. Code refers to a message by its ID and passes
+//! the values of its placeholders, e.g. `i18n::format("countdown-days",
+//! &[("days", 2.into())])`, and Fluent takes care of plurals and word order.
+//! Messages that are missing in a language are shown in English.
+//!
+//! The language is taken from `--lang`, or else from the locale of the
+//! environment (`LC_ALL`, `LC_MESSAGES` and `LANG`, in that order), e.g.
+//! `LANG=de_DE.UTF-8`. Unsupported locales fall back to English.
+//!
+//! Output that programs read stays English, whatever the language: `--json`,
+//! `--stdin-json`, and the protocols of `helper` and `serve`. Messages of the
+//! instance are never translated. Warnings are shown to the user and are
+//! translated, while debug and info logs are for diagnosis and stay English.
+//!
+//! To add a language, add a file to `locales/` with the messages of
+//! `locales/en.ftl`, and a variant to `Language`.
+
+use std::env;
+use std::sync::OnceLock;
+
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource, FluentValue};
+use tracing::{debug, warn};
+use unic_langid::LanguageIdentifier;
+
+use crate::args::{PPCAction, PPCArgs};
+
+static LOCALIZER: OnceLock<Localizer> = OnceLock::new();
+
+/// A language that messages are translated to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    English,
+    German,
+}
+
+impl Language {
+    const ALL: [Language; 2] = [Language::English, Language::German];
+
+    /// The language code, as in `--lang`.
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::German => "de",
+        }
+    }
+
+    fn messages(self) -> &'static str {
+        match self {
+            Language::English => include_str!("../locales/en.ftl"),
+            Language::German => include_str!("../locales/de.ftl"),
+        }
+    }
+
+    /// The language of a locale like `de`, `de-AT` or `de_DE.UTF-8`.
+    fn of_locale(locale: &str) -> Option<Self> {
+        let code = locale
+            .split(['_', '-', '.', '@'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|language| language.code() == code)
+    }
+}
+
+/// Value parser for `--lang`.
+pub fn parse(value: &str) -> Result<Language, String> {
+    Language::of_locale(value).ok_or_else(|| {
+        let codes = Language::ALL.map(Language::code);
+        format!(
+            "`{}` is not supported, use one of {}",
+            value,
+            codes.join(", ")
+        )
+    })
+}
+
+/// The messages of a language, with English as fallback.
+struct Localizer {
+    bundles: Vec<FluentBundle<FluentResource>>,
+}
+
+impl Localizer {
+    fn new(language: Language) -> Self {
+        let mut bundles = vec![bundle(language)];
+        if language != Language::English {
+            bundles.push(bundle(Language::English));
+        }
+        Self { bundles }
+    }
+
+    fn format(&self, id: &str, args: &[(&str, FluentValue)]) -> String {
+        let mut fluent_args = FluentArgs::new();
+        for (name, value) in args {
+            fluent_args.set(*name, value.clone());
+        }
+
+        for bundle in &self.bundles {
+            let Some(pattern) = bundle.get_message(id).and_then(|message| message.value()) else {
+                continue;
+            };
+            let mut errors = Vec::new();
+            let text = bundle.format_pattern(pattern, Some(&fluent_args), &mut errors);
+            if !errors.is_empty() {
+                debug!("could not format message {}: {:?}", id, errors);
+            }
+            return text.into_owned();
+        }
+
+        // all messages are defined in English, see the tests.
+        warn!("message {} is not defined", id);
+        String::from(id)
+    }
+}
+
+fn bundle(language: Language) -> FluentBundle<FluentResource> {
+    // the messages are part of the binary and checked by the tests, so they
+    // always parse.
+    let resource = FluentResource::try_new(String::from(language.messages()))
+        .unwrap_or_else(|(resource, _)| resource);
+    let id = language
+        .code()
+        .parse::<LanguageIdentifier>()
+        .expect("language codes are valid");
+
+    let mut bundle = FluentBundle::new_concurrent(vec![id]);
+    // Unicode isolation marks around placeholders show up as garbage in
+    // many terminals.
+    bundle.set_use_isolating(false);
+    if let Err(errors) = bundle.add_resource(resource) {
+        debug!(
+            "messages of {} are defined twice: {:?}",
+            language.code(),
+            errors
+        );
+    }
+    bundle
+}
+
+/// Select the language of messages, see the module docs. Call once at start,
+/// before anything is printed. Without it, messages are English.
+pub fn init(args: &PPCArgs) {
+    let (language, source) = selected(args, |name| env::var(name).ok());
+
+    debug!(
+        "messages are shown in {:?}, selected by {}",
+        language, source
+    );
+    let _ = LOCALIZER.set(Localizer::new(language));
+}
+
+/// The language of messages for the arguments, and what selected it.
+pub fn selected(args: &PPCArgs, var: impl Fn(&str) -> Option<String>) -> (Language, String) {
+    if is_machine_readable(args) {
+        return (Language::English, String::from("machine-readable output"));
+    }
+    if let Some(language) = args.lang {
+        return (language, String::from("--lang"));
+    }
+    match locale_var(var) {
+        Some((name, locale)) => (
+            Language::of_locale(&locale).unwrap_or(Language::English),
+            String::from(name),
+        ),
+        None => (Language::English, String::from("default")),
+    }
+}
+
+fn is_machine_readable(args: &PPCArgs) -> bool {
+    if args.json_output || args.stdin_json {
+        return true;
+    }
+    match args.action {
+        PPCAction::Helper(_) => true,
+        #[cfg(feature = "serve")]
+        PPCAction::Serve(_) => true,
+        _ => false,
+    }
+}
+
+/// The first locale variable that is set, with its value, like the C library
+/// selects it.
+fn locale_var(var: impl Fn(&str) -> Option<String>) -> Option<(&'static str, String)> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .find_map(|name| {
+            var(name)
+                .filter(|locale| !locale.is_empty())
+                .map(|locale| (name, locale))
+        })
+}
+
+fn localizer() -> &'static Localizer {
+    LOCALIZER.get_or_init(|| Localizer::new(Language::English))
+}
+
+/// The message in the selected language.
+pub fn text(id: &str) -> String {
+    localizer().format(id, &[])
+}
+
+/// The message in the selected language, with its placeholders replaced.
+pub fn format(id: &str, args: &[(&str, FluentValue)]) -> String {
+    localizer().format(id, args)
+}
+
+/// Whether the answer to a prompt means yes. The English answers are always
+/// understood.
+pub fn is_yes(answer: &str) -> bool {
+    let answer = answer.trim().to_lowercase();
+    let answers = text("prompt-yes") + ", y, yes";
+    answers.split(',').any(|yes| yes.trim() == answer)
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::Path;
+
+    use clap::Parser;
+    use fluent_bundle::FluentResource;
+    use fluent_syntax::ast::Entry;
+
+    use super::{parse, selected, Language, Localizer};
+    use crate::args::PPCArgs;
+
+    fn ids(language: Language) -> Vec<String> {
+        let resource = FluentResource::try_new(String::from(language.messages()))
+            .unwrap_or_else(|(_, errors)| panic!("{:?} does not parse: {:?}", language, errors));
+        resource
+            .entries()
+            .filter_map(|entry| match entry {
+                Entry::Message(message) => Some(String::from(message.id.name)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn languages_define_the_english_messages() {
+        let english = ids(Language::English);
+        for language in Language::ALL {
+            let translated = ids(language);
+            for id in &english {
+                assert!(translated.contains(id), "{:?} misses {}", language, id);
+            }
+            for id in &translated {
+                assert!(
+                    english.contains(id),
+                    "{:?} defines unknown {}",
+                    language,
+                    id
+                );
+            }
+        }
+    }
+
+    /// The IDs of the messages that code passes literally to `text` and
+    /// `format`.
+    fn used_ids(source: &str) -> Vec<&str> {
+        ["i18n::text(", "i18n::format("]
+            .iter()
+            .flat_map(|call| source.split(call).skip(1))
+            .filter_map(|rest| rest.trim_start().strip_prefix('"')?.split('"').next())
+            .filter(|id| {
+                !id.is_empty()
+                    && id
+                        .chars()
+                        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+            })
+            .collect()
+    }
+
+    #[test]
+    fn used_messages_are_defined() {
+        let english = ids(Language::English);
+        let src = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        for entry in fs::read_dir(src).unwrap() {
+            let path = entry.unwrap().path();
+            let source = fs::read_to_string(&path).unwrap();
+            for id in used_ids(&source) {
+                assert!(
+                    english.iter().any(|defined| defined == id),
+                    "{} uses undefined message {}",
+                    path.display(),
+                    id
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn plurals_follow_the_language() {
+        let english = Localizer::new(Language::English);
+        assert_eq!(
+            english.format("countdown-days", &[("days", 1.into())]),
+            "in 1 day"
+        );
+        assert_eq!(
+            english.format("countdown-days", &[("days", 3.into())]),
+            "in 3 days"
+        );
+
+        let german = Localizer::new(Language::German);
+        assert_eq!(
+            german.format("date-days-ago", &[("amount", 2.into())]),
+            "vor 2 Tagen"
+        );
+        assert_eq!(german.format("no-such-message", &[]), "no-such-message");
+    }
+
+    #[test]
+    fn language_of_the_locale() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| String::from(*value))
+            }
+        };
+
+        let args = PPCArgs::parse_from(["pwpush-cli", "summary"]);
+        let language = |vars| selected(&args, env(vars));
+
+        assert_eq!(language(&[]), (Language::English, String::from("default")));
+        assert_eq!(
+            language(&[("LANG", "de_DE.UTF-8")]),
+            (Language::German, String::from("LANG"))
+        );
+        assert_eq!(
+            language(&[("LC_ALL", ""), ("LANG", "de_AT")]),
+            (Language::German, String::from("LANG"))
+        );
+        assert_eq!(
+            language(&[("LC_MESSAGES", "C"), ("LANG", "de_DE")]),
+            (Language::English, String::from("LC_MESSAGES"))
+        );
+
+        let json = PPCArgs::parse_from(["pwpush-cli", "--json", "--lang", "de", "summary"]);
+        assert_eq!(selected(&json, env(&[])).0, Language::English);
+        assert_eq!(parse("de-CH"), Ok(Language::German));
+        assert!(parse("fr").is_err());
+    }
+}
@@ -0,0 +1,135 @@
+//! Idempotency keys for push creation.
+//!
+//! Automation that retries a failed step, e.g. after a timeout, cannot tell
+//! whether the push was created before the step failed, and a retry would
+//! leave a second active link to the same secret. With
+//! `push text --idempotency-key <KEY>`, the push is recorded in the local
+//! history along with the key. When a push with the same key was created on
+//! the same instance within the last 24 hours, its link is printed again and
+//! nothing is pushed, so the step can be retried safely.
+//!
+//! The key is only known to this machine, the instance never sees it. It is
+//! compared as given, so scripts should derive it from something stable, like
+//! the ID of the pipeline run. Pushes with a key are recorded even if the
+//! history is disabled, see `history`.
+
+use std::time::Duration;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::json;
+use tracing::info;
+
+use crate::args::PPCArgs;
+use crate::errors::PPCError;
+use crate::history::{self, Entry, History};
+use crate::instance::Instance;
+use crate::{output, paths};
+
+/// How long a key is looked up for. Older pushes are likely expired, so a
+/// new push is made instead.
+pub const WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The latest push of the instance with the key, if it was made within the
+/// window.
+fn find<'a>(
+    entries: &'a [Entry],
+    instance: &str,
+    key: &str,
+    now: DateTime<Utc>,
+) -> Option<&'a Entry> {
+    entries
+        .iter()
+        .filter(|entry| entry.instance == instance && entry.idempotency_key.as_deref() == Some(key))
+        .filter(|entry| {
+            now.signed_duration_since(entry.created_at)
+                .to_std()
+                .map_or(true, |age| age <= WINDOW)
+        })
+        .max_by_key(|entry| entry.created_at)
+}
+
+/// The push that was already made with the key, if any.
+pub fn check(
+    instance: &Instance,
+    key: &str,
+    now: DateTime<Utc>,
+) -> Result<Option<Entry>, PPCError> {
+    let history = History::open(&history::default_dir()?, &paths::key_dir()?)?;
+    let entries = history.entries()?;
+    Ok(find(&entries, &instance.base_url(), key, now).cloned())
+}
+
+/// Print the link of a push that is reused instead of pushing again, like the
+/// link of a new push.
+pub fn print(args: &PPCArgs, entry: &Entry) {
+    let created_at = entry.created_at.to_rfc3339_opts(SecondsFormat::Secs, true);
+    info!(
+        "push {} with this idempotency key was created at {}, not pushing again",
+        entry.url_token, created_at
+    );
+
+    if args.json_output {
+        output::print_json(&json!({
+            "url_token": entry.url_token,
+            "url": entry.url,
+            "created_at": created_at,
+            "reused": true,
+        }));
+    } else {
+        println!("{}", entry.url);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use super::find;
+    use crate::history::Entry;
+
+    const INSTANCE: &str = "https://pwpush.example.com";
+
+    fn entry(url_token: &str, instance: &str, key: Option<&str>, hours_ago: i64) -> Entry {
+        Entry {
+            url_token: String::from(url_token),
+            instance: String::from(instance),
+            note: None,
+            created_at: Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap()
+                - Duration::hours(hours_ago),
+            url: format!("{}/en/p/{}", instance, url_token),
+            tags: Vec::new(),
+            idempotency_key: key.map(String::from),
+        }
+    }
+
+    #[test]
+    fn recent_push_with_key_is_found() {
+        let now = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let entries = vec![
+            entry("old", INSTANCE, Some("deploy-42"), 3),
+            entry("unkeyed", INSTANCE, None, 2),
+            entry("new", INSTANCE, Some("deploy-42"), 1),
+            entry("other", INSTANCE, Some("deploy-43"), 0),
+        ];
+
+        let found = find(&entries, INSTANCE, "deploy-42", now).unwrap();
+        assert_eq!(found.url_token, "new");
+        assert!(find(&entries, INSTANCE, "deploy-44", now).is_none());
+    }
+
+    #[test]
+    fn keys_are_scoped_to_instance_and_window() {
+        let now = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let entries = vec![
+            entry(
+                "elsewhere",
+                "https://other.example.com",
+                Some("deploy-42"),
+                1,
+            ),
+            entry("expired", INSTANCE, Some("deploy-42"), 25),
+        ];
+
+        assert!(find(&entries, INSTANCE, "deploy-42", now).is_none());
+    }
+}
@@ -0,0 +1,598 @@
+//! The `info` and `list` commands.
+//!
+//! Retrieving a push counts as a view, so `info` does not use the push
+//! endpoint. Instead, the push is looked up in the active and expired lists of
+//! the account, which contain all metadata but no payload.
+//!
+//! Human output shows the remaining days and views as a countdown, e.g.
+//! "expires in 2 days or after 3 more views". JSON output keeps the fields exactly
+//! as the API reports them. `list` adds fields derived from them, see
+//! `ListEntry`: the number of views so far, and the days until the push is
+//! predicted to expire, by its days or, at the rate it was viewed so far, by
+//! its views. A push with 10 views left that was viewed 5 times a day is gone
+//! in 2 days, even if it could last for a week. `list active --expiring-soon`
+//! only shows the pushes that expire soon by any of these, so their links can
+//! be replaced in time, e.g. with `rotate`.
+//!
+//! `list --watch` polls the list and only prints what changed since the last
+//! poll: added, changed (e.g. viewed) and removed pushes. Scripts that poll on
+//! their own can use `--if-changed` instead, which prints `unchanged` if the
+//! instance reports that nothing changed since the last run, see `cache`.
+
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::json;
+use tracing::debug;
+
+use crate::args::{PPCArgs, PPCList, PPCTokenObject, PushKind, PushState};
+use crate::dates::DateFormatter;
+use crate::errors::{PPCError, EXIT_EXPIRED};
+use crate::http::Transport;
+use crate::instance::Instance;
+use crate::models::Push;
+use crate::scheduler::Scheduler;
+use crate::table::{self, Column, Table};
+use crate::{i18n, output, pager, pwpush_api, skew};
+
+pub fn info(
+    transport: &dyn Transport,
+    instance: &Instance,
+    args: &PPCArgs,
+    object: &PPCTokenObject,
+) -> Result<(), PPCError> {
+    let (push, unchanged) = lookup(
+        transport,
+        instance,
+        object.kind(),
+        &object.token().url_token,
+    )?;
+
+    let dates = DateFormatter::new(args);
+    if args.if_changed && unchanged {
+        print_unchanged(args);
+    } else if args.json_output {
+        output::print_json(&push);
+    } else {
+        print!("{}", format_info(&push, &dates));
+    }
+
+    // the metadata is still shown, but scripts can tell from the exit code
+    // that the push cannot be opened anymore.
+    if push.expired == Some(true) {
+        return Err(expired_error(&push, &dates));
+    }
+    Ok(())
+}
+
+/// Find a push in the active and expired pushes of the account. The flag
+/// tells whether both lists were unchanged since the last call with
+/// `--if-changed`.
+pub fn lookup(
+    transport: &dyn Transport,
+    instance: &Instance,
+    kind: PushKind,
+    url_token: &str,
+) -> Result<(Push, bool), PPCError> {
+    let mut unchanged = true;
+    for state in [PushState::Active, PushState::Expired] {
+        let listing = pwpush_api::listing(transport, instance, kind, state)?;
+        unchanged &= listing.unchanged;
+        if let Some(push) = listing
+            .pushes
+            .into_iter()
+            .find(|push| push.url_token == url_token)
+        {
+            return Ok((push, unchanged));
+        }
+    }
+    Err(PPCError::from(format!(
+        "{} push {} was not found in the pushes of the account",
+        kind, url_token
+    )))
+}
+
+/// Tell a polling script that the result is the same as last time.
+fn print_unchanged(args: &PPCArgs) {
+    if args.json_output {
+        output::print_json_line(&json!({ "unchanged": true }));
+    } else {
+        println!("{}", i18n::text("info-unchanged"));
+    }
+}
+
+/// Error for a push that cannot be opened anymore, with as much detail as
+/// the instance reported, e.g. "push abc has expired (expired on 2023-12-02
+/// after 3 views)".
+pub fn expired_error(push: &Push, dates: &DateFormatter) -> PPCError {
+    let (state, event) = if push.deleted == Some(true) {
+        ("was deleted", "deleted")
+    } else {
+        ("has expired", "expired")
+    };
+
+    let mut details = Vec::new();
+    if let Some(date) = &push.expired_on {
+        details.push(format!("{} on {}", event, dates.format(date)));
+    }
+    if let Some(views) = push.views() {
+        let plural = if views == 1 { "" } else { "s" };
+        details.push(format!("after {} view{}", views, plural));
+    }
+
+    let message = if details.is_empty() {
+        format!("push {} {}", push.url_token, state)
+    } else {
+        format!("push {} {} ({})", push.url_token, state, details.join(" "))
+    };
+    PPCError::with_exit_code(message, EXIT_EXPIRED)
+}
+
+pub fn list(
+    transport: &dyn Transport,
+    instance: &Instance,
+    args: &PPCArgs,
+    ppc_list: &PPCList,
+) -> Result<(), PPCError> {
+    let (kind, state) = (ppc_list.kind, ppc_list.state);
+    if ppc_list.expiring_soon && state == PushState::Expired {
+        return Err(PPCError::from(
+            "--expiring-soon only applies to the list of active pushes",
+        ));
+    }
+    let listing = pwpush_api::listing(transport, instance, kind, state)?;
+    let now = skew::now(listing.skew);
+    let mut pushes = listing.pushes;
+    if ppc_list.expiring_soon {
+        pushes.retain(|push| expires_soon(push, now, ppc_list.days_left, ppc_list.views_left));
+    }
+
+    if !ppc_list.watch {
+        if args.if_changed && listing.unchanged {
+            print_unchanged(args);
+        } else if args.json_output {
+            let entries = pushes
+                .into_iter()
+                .map(|push| ListEntry::new(push, now))
+                .collect::<Vec<_>>();
+            output::print_json(&entries);
+        } else {
+            let dates = DateFormatter::at(args, now);
+            let mut table = Table::new(vec![
+                Column::fixed(),
+                Column::shrink_to(10),
+                Column::shrink_to(12),
+                Column::shrink_to(12),
+            ]);
+            for push in &pushes {
+                table.row(cells(push, &dates).into());
+            }
+            pager::print(args, &table.render(table::width(args)))?;
+        }
+        return Ok(());
+    }
+
+    // in watch mode, all pushes of the first poll are reported as added, and
+    // later polls only report the differences. Unchanged lists are answered
+    // from the cache, see `cache`.
+    let mut known = HashMap::new();
+    print_changes(args, &changes(&mut known, pushes));
+
+    let interval = Duration::from_secs(ppc_list.interval);
+    Scheduler::new("poll-push-list", interval).run(|| -> Result<ControlFlow<()>, PPCError> {
+        debug!("polling {} {} pushes", state, kind);
+        let pushes = pwpush_api::list(transport, instance, kind, state)?;
+        print_changes(args, &changes(&mut known, pushes));
+        Ok(ControlFlow::Continue(()))
+    });
+    Ok(())
+}
+
+/// A push of `list`, with the fields that are derived from the ones of the
+/// API.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ListEntry {
+    #[serde(flatten)]
+    pub push: Push,
+
+    /// Views so far
+    pub views: Option<i64>,
+
+    /// Days until the push expires by its days, or by its views at the rate
+    /// it was viewed so far, whichever comes first
+    pub predicted_days_remaining: Option<i64>,
+}
+
+impl ListEntry {
+    fn new(push: Push, now: DateTime<Utc>) -> Self {
+        Self {
+            views: push.views(),
+            predicted_days_remaining: predicted_days_remaining(&push, now),
+            push,
+        }
+    }
+}
+
+/// See `ListEntry`. Pushes younger than a day count as one day old, so a
+/// few views right after creation do not predict a rate that lasts.
+fn predicted_days_remaining(push: &Push, now: DateTime<Utc>) -> Option<i64> {
+    let created_at = push
+        .created_at
+        .as_deref()
+        .and_then(|created_at| DateTime::parse_from_rfc3339(created_at).ok());
+    let by_views = match (push.views(), push.views_remaining, created_at) {
+        (Some(views), Some(remaining), Some(created_at)) if views > 0 => {
+            let age = (now - created_at.with_timezone(&Utc)).num_minutes() as f64 / (24.0 * 60.0);
+            let per_day = views as f64 / age.max(1.0);
+            Some((remaining.max(0) as f64 / per_day).floor() as i64)
+        }
+        _ => None,
+    };
+
+    match (push.days_remaining, by_views) {
+        (Some(days), Some(by_views)) => Some(days.min(by_views)),
+        (days, by_views) => days.or(by_views),
+    }
+}
+
+/// Whether the push expires within `days` or `views`, see `--expiring-soon`.
+fn expires_soon(push: &Push, now: DateTime<Utc>, days: i64, views: i64) -> bool {
+    push.days_remaining
+        .is_some_and(|remaining| remaining <= days)
+        || push
+            .views_remaining
+            .is_some_and(|remaining| remaining <= views)
+        || predicted_days_remaining(push, now).is_some_and(|remaining| remaining <= days)
+}
+
+/// Difference between two polls of a list. In JSON, each change is a line of
+/// its own, e.g. `{"change":"removed","url_token":"..."}`.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "change", rename_all = "lowercase")]
+enum Change {
+    Added { push: Push },
+    Changed { push: Push },
+    Removed { url_token: String },
+}
+
+/// Changes of `pushes` compared to `known`, which is updated to `pushes`.
+fn changes(known: &mut HashMap<String, Push>, pushes: Vec<Push>) -> Vec<Change> {
+    let mut current = HashMap::with_capacity(pushes.len());
+    let mut changes = Vec::new();
+
+    for push in pushes {
+        match known.get(&push.url_token) {
+            None => changes.push(Change::Added { push: push.clone() }),
+            Some(previous) if previous != &push => {
+                changes.push(Change::Changed { push: push.clone() })
+            }
+            Some(_) => {}
+        }
+        current.insert(push.url_token.clone(), push);
+    }
+
+    let mut removed = known
+        .keys()
+        .filter(|url_token| !current.contains_key(*url_token))
+        .cloned()
+        .collect::<Vec<_>>();
+    removed.sort();
+    changes.extend(
+        removed
+            .into_iter()
+            .map(|url_token| Change::Removed { url_token }),
+    );
+
+    *known = current;
+    changes
+}
+
+fn print_changes(args: &PPCArgs, changes: &[Change]) {
+    let dates = DateFormatter::new(args);
+    let width = table::width(args);
+    for change in changes {
+        if args.json_output {
+            output::print_json_line(change);
+            continue;
+        }
+        match change {
+            Change::Added { push } => {
+                println!(
+                    "{}",
+                    table::fit(&format!("+ {}", format_line(push, &dates)), width)
+                )
+            }
+            Change::Changed { push } => {
+                println!(
+                    "{}",
+                    table::fit(&format!("~ {}", format_line(push, &dates)), width)
+                )
+            }
+            Change::Removed { url_token } => println!("- {}", url_token),
+        }
+    }
+}
+
+/// A single line per push in the list.
+fn format_line(push: &Push, dates: &DateFormatter) -> String {
+    let [url_token, created_at, countdown, note] = cells(push, dates);
+    format!(
+        "{:<24}  {:<20}  {:<36}  {}",
+        url_token, created_at, countdown, note
+    )
+}
+
+/// The columns of a push in the list, see `table`.
+fn cells(push: &Push, dates: &DateFormatter) -> [String; 4] {
+    let created_at = push.created_at.as_deref().unwrap_or("-");
+    [
+        push.url_token.clone(),
+        dates.format(created_at),
+        countdown(push, dates),
+        push.note.clone().unwrap_or_default(),
+    ]
+}
+
+/// Remaining lifetime of the push in words.
+pub fn countdown(push: &Push, dates: &DateFormatter) -> String {
+    if push.expired == Some(true) {
+        return match &push.expired_on {
+            Some(date) if dates.is_relative() => i18n::format(
+                "countdown-expired-relative",
+                &[("date", dates.format(date).into())],
+            ),
+            Some(date) => i18n::format(
+                "countdown-expired-on",
+                &[("date", dates.format(date).into())],
+            ),
+            None => i18n::text("countdown-expired"),
+        };
+    }
+
+    let days = push.days_remaining.map(|days| match days {
+        i64::MIN..=0 => i18n::text("countdown-today"),
+        days => i18n::format("countdown-days", &[("days", days.into())]),
+    });
+    let views = push
+        .views_remaining
+        .map(|views| i18n::format("countdown-views", &[("views", views.max(1).into())]));
+
+    match (days, views) {
+        (Some(days), Some(views)) => i18n::format(
+            "countdown-days-or-views",
+            &[("days", days.into()), ("views", views.into())],
+        ),
+        (Some(days), None) => i18n::format("countdown-days-only", &[("days", days.into())]),
+        (None, Some(views)) => i18n::format("countdown-views-only", &[("views", views.into())]),
+        (None, None) => i18n::text("countdown-unknown"),
+    }
+}
+
+pub fn format_info(push: &Push, dates: &DateFormatter) -> String {
+    let yes_no = |value: Option<bool>| match value {
+        Some(true) => "yes",
+        Some(false) => "no",
+        None => "unknown",
+    };
+
+    let mut out = format!("url token:            {}\n", push.url_token);
+    if let Some(created_at) = &push.created_at {
+        out += &format!("created:              {}\n", dates.format(created_at));
+    }
+    out += &format!("expiration:           {}\n", countdown(push, dates));
+    if let Some(views) = push.views() {
+        out += &format!("views:                {}\n", views);
+    }
+    out += &format!(
+        "deletable by viewer:  {}\n",
+        yes_no(push.deletable_by_viewer)
+    );
+    out += &format!("retrieval step:       {}\n", yes_no(push.retrieval_step));
+    if let Some(note) = &push.note {
+        out += &format!("note:                 {}\n", note);
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{DateTime, Utc};
+    use clap::Parser;
+
+    use std::collections::HashMap;
+    use std::process::ExitCode;
+
+    use super::{
+        changes, countdown, expired_error, expires_soon, format_info, info,
+        predicted_days_remaining, Change,
+    };
+    use crate::args::{PPCAction, PPCArgs};
+    use crate::config::PPCConfig;
+    use crate::dates::{DateFormat, DateFormatter};
+    use crate::errors::EXIT_EXPIRED;
+    use crate::http::mock::MockTransport;
+    use crate::instance::Instance;
+    use crate::models::Push;
+
+    fn push(days: Option<i64>, views: Option<i64>) -> Push {
+        Push {
+            url_token: String::from("abc"),
+            days_remaining: days,
+            views_remaining: views,
+            ..Push::default()
+        }
+    }
+
+    #[test]
+    fn views_run_out_at_the_rate_so_far() {
+        let now = "2024-03-11T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        // 4 of 20 views in 2 days, so 16 views last another 8 days.
+        let viewed = Push {
+            created_at: Some(String::from("2024-03-09T12:00:00Z")),
+            expire_after_views: Some(20),
+            ..push(Some(5), Some(16))
+        };
+        let unviewed = Push {
+            expire_after_views: Some(20),
+            ..push(Some(5), Some(20))
+        };
+
+        assert_eq!(predicted_days_remaining(&viewed, now), Some(5));
+        assert_eq!(
+            predicted_days_remaining(
+                &Push {
+                    days_remaining: Some(30),
+                    ..viewed.clone()
+                },
+                now
+            ),
+            Some(8)
+        );
+        assert_eq!(predicted_days_remaining(&unviewed, now), Some(5));
+        assert_eq!(predicted_days_remaining(&push(None, Some(3)), now), None);
+
+        assert!(!expires_soon(&viewed, now, 1, 1));
+        assert!(expires_soon(&viewed, now, 5, 1));
+        assert!(expires_soon(&push(Some(9), Some(1)), now, 1, 1));
+    }
+
+    fn iso() -> DateFormatter {
+        DateFormatter::with(DateFormat::Iso, Utc::now())
+    }
+
+    #[test]
+    fn countdowns() {
+        assert_eq!(
+            countdown(&push(Some(2), Some(3)), &iso()),
+            "expires in 2 days or after 3 more views"
+        );
+        assert_eq!(
+            countdown(&push(Some(1), Some(1)), &iso()),
+            "expires in 1 day or after 1 more view"
+        );
+        assert_eq!(countdown(&push(Some(0), None), &iso()), "expires today");
+        assert_eq!(
+            countdown(&push(None, Some(4)), &iso()),
+            "expires after 4 more views"
+        );
+        assert_eq!(countdown(&push(None, None), &iso()), "expiration unknown");
+
+        let expired = Push {
+            expired: Some(true),
+            expired_on: Some(String::from("2023-12-02")),
+            ..push(Some(0), Some(0))
+        };
+        assert_eq!(
+            countdown(&expired, &iso()),
+            "expired on 2023-12-02T00:00:00Z"
+        );
+    }
+
+    #[test]
+    fn expired_errors() {
+        let expired = Push {
+            expired: Some(true),
+            expired_on: Some(String::from("2023-12-02")),
+            expire_after_views: Some(5),
+            ..push(Some(0), Some(2))
+        };
+        let error = expired_error(&expired, &iso());
+        assert_eq!(
+            error.to_string(),
+            "push abc has expired (expired on 2023-12-02T00:00:00Z after 3 views)"
+        );
+        assert_eq!(error.exit_code(), ExitCode::from(EXIT_EXPIRED));
+
+        let deleted = Push {
+            expired: Some(true),
+            deleted: Some(true),
+            ..push(None, None)
+        };
+        assert_eq!(
+            expired_error(&deleted, &iso()).to_string(),
+            "push abc was deleted"
+        );
+    }
+
+    #[test]
+    fn info_format() {
+        let push = Push {
+            expire_after_views: Some(5),
+            retrieval_step: Some(true),
+            note: Some(String::from("db")),
+            ..push(Some(2), Some(3))
+        };
+
+        let out = format_info(&push, &iso());
+
+        assert!(out.contains("expiration:           expires in 2 days or after 3 more views\n"));
+        assert!(out.contains("views:                2\n"));
+        assert!(out.contains("deletable by viewer:  unknown\n"));
+        assert!(out.contains("retrieval step:       yes\n"));
+    }
+
+    #[test]
+    fn list_changes() {
+        let mut known = HashMap::new();
+        let first = vec![
+            push(Some(2), Some(3)),
+            Push {
+                url_token: String::from("old"),
+                ..Push::default()
+            },
+        ];
+
+        assert_eq!(changes(&mut known, first.clone()).len(), 2);
+        assert!(changes(&mut known, first).is_empty());
+
+        let viewed = push(Some(2), Some(2));
+        assert_eq!(
+            changes(&mut known, vec![viewed.clone()]),
+            [
+                Change::Changed { push: viewed },
+                Change::Removed {
+                    url_token: String::from("old")
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn list_change_json() {
+        let change = Change::Removed {
+            url_token: String::from("abc"),
+        };
+
+        assert_eq!(
+            serde_json::to_string(&change).unwrap(),
+            r#"{"change":"removed","url_token":"abc"}"#
+        );
+    }
+
+    #[test]
+    fn info_searches_both_lists() {
+        let args = PPCArgs::parse_from(["pwpush-cli", "info", "file", "abc"]);
+        let instance = Instance::resolve(&args, &PPCConfig::default()).unwrap();
+        let transport = MockTransport::new()
+            .respond(200, r#"[{"url_token":"other"}]"#)
+            .respond(200, r#"[{"url_token":"abc","expired":true}]"#);
+        let PPCAction::Info(object) = &args.action else {
+            panic!("not an info command");
+        };
+
+        let result = info(&transport, &instance, &args, object);
+
+        assert_eq!(
+            result.unwrap_err().exit_code(),
+            ExitCode::from(EXIT_EXPIRED)
+        );
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].url, "https://pwpush.com/f/active.json");
+        assert_eq!(requests[1].url, "https://pwpush.com/f/expired.json");
+    }
+}
@@ -0,0 +1,345 @@
+//! Resolution of the Password Pusher instance that API calls go to.
+//!
+//! An instance is defined by its protocol, its URL (host and optional port)
+//! and the credentials for authenticated requests. These can be given on the
+//! command line, or stored in named profiles in the configuration file:
+//!
+//! ```toml
+//! [profiles.work]
+//! url = "pwpush.example.com"
+//! protocol = "https"
+//! email = "user@example.com"
+//! token = "..."
+//! # "header-pair" or "bearer", detected from the server version if omitted
+//! auth_scheme = "bearer"
+//! ```
+//!
+//! Options given on the command line take precedence over the profile selected
+//! with `--profile`, which in turn takes precedence over the built-in defaults
+//! (`https://pwpush.com`, anonymous). Tokens of profiles can be encrypted, see
+//! `credentials`, and are only decrypted when the profile is used.
+//!
+//! Older servers only accept email and token as a pair of `X-User-*` headers,
+//! newer ones also a bearer token without email. A token without email is
+//! always sent as bearer token. With both, the scheme is taken from
+//! `--auth-scheme` or the profile, or detected from the server version before
+//! the first request.
+
+use std::fmt;
+
+use secrecy::SecretString;
+use tracing::debug;
+
+use crate::args::{AuthScheme, InstanceProtocol, PPCArgs};
+use crate::config::{PPCConfig, Profile};
+use crate::errors::PPCError;
+use crate::http::Transport;
+use crate::{api_url, credentials, pwpush_api, redact, version};
+
+/// Used if neither the command line nor a profile specify an instance URL.
+pub const DEFAULT_URL: &str = "pwpush.com";
+
+/// Everything needed to address a Password Pusher instance.
+#[derive(Clone)]
+pub struct Instance {
+    pub protocol: InstanceProtocol,
+    pub url: String,
+    pub email: Option<String>,
+    pub token: Option<SecretString>,
+
+    /// How the token is sent, `None` until it is known. See `auth_scheme`.
+    pub auth_scheme: Option<AuthScheme>,
+}
+
+/// The token is redacted, so the instance can be logged safely.
+impl fmt::Debug for Instance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Instance")
+            .field("protocol", &self.protocol)
+            .field("url", &self.url)
+            .field("email", &self.email)
+            .field("token", &redact::option(&self.token))
+            .field("auth_scheme", &self.auth_scheme)
+            .finish()
+    }
+}
+
+impl Instance {
+    /// The instance selected by the global command line options.
+    pub fn resolve(args: &PPCArgs, config: &PPCConfig) -> Result<Self, PPCError> {
+        let profile = match &args.profile {
+            Some(name) => config.profile(name)?.clone(),
+            None => Profile::default(),
+        };
+
+        // clap ensures that an email on the command line comes with a token,
+        // so credentials are taken over as a whole.
+        let (email, token) = match (&args.token, &args.profile) {
+            (Some(token), _) => (args.email.clone(), Some(token.clone())),
+            (None, Some(name)) => (
+                profile.email,
+                profile
+                    .token
+                    .map(|token| credentials::reveal(name, &token))
+                    .transpose()?,
+            ),
+            (None, None) => (profile.email, None),
+        };
+
+        Self::build(
+            args.instance_protocol.or(profile.protocol),
+            args.instance_url.clone().or(profile.url),
+            email,
+            token,
+            args.auth_scheme.or(profile.auth_scheme),
+        )
+    }
+
+    /// Protocol and URL of the instance selected by the global command line
+    /// options, like the `base_url` of `resolve`, but without revealing the
+    /// token of the profile.
+    pub fn resolve_base_url(args: &PPCArgs, config: &PPCConfig) -> Result<String, PPCError> {
+        let profile = match &args.profile {
+            Some(name) => Some(config.profile(name)?),
+            None => None,
+        };
+        let (protocol, url) = api_url::normalize(
+            args.instance_protocol
+                .or(profile.and_then(|profile| profile.protocol)),
+            args.instance_url
+                .as_deref()
+                .or(profile.and_then(|profile| profile.url.as_deref()))
+                .unwrap_or(DEFAULT_URL),
+        )?;
+        Ok(format!("{}://{}", protocol, url))
+    }
+
+    /// The instance of a profile, without any influence of the command line.
+    /// Fails if the policy file does not allow the instance.
+    pub fn from_profile(config: &PPCConfig, name: &str) -> Result<Self, PPCError> {
+        let profile = config.profile(name)?.clone();
+        let instance = Self::build(
+            profile.protocol,
+            profile.url,
+            profile.email,
+            profile
+                .token
+                .map(|token| credentials::reveal(name, &token))
+                .transpose()?,
+            profile.auth_scheme,
+        )?;
+        config.policy.check_instance(&instance)?;
+        Ok(instance)
+    }
+
+    /// An instance by URL, e.g. `pwpush.com` or `https://pwpush.example.com`,
+    /// with optional credentials, for use as library. The header pair is
+    /// used for a token with email, see `detect_auth_scheme` for newer
+    /// servers.
+    pub fn new(
+        url: &str,
+        email: Option<String>,
+        token: Option<SecretString>,
+    ) -> Result<Self, PPCError> {
+        Self::build(None, Some(String::from(url)), email, token, None)
+    }
+
+    fn build(
+        protocol: Option<InstanceProtocol>,
+        url: Option<String>,
+        email: Option<String>,
+        token: Option<SecretString>,
+        auth_scheme: Option<AuthScheme>,
+    ) -> Result<Self, PPCError> {
+        if email.is_some() && token.is_none() {
+            return Err(PPCError::from(
+                "an email was given without a token for authenticated requests",
+            ));
+        }
+        // without an email, there is nothing to choose from.
+        let auth_scheme = match (&email, auth_scheme) {
+            (None, Some(AuthScheme::HeaderPair)) if token.is_some() => {
+                return Err(PPCError::from(
+                    "the header-pair auth scheme needs an email together with the token",
+                ))
+            }
+            (None, _) => Some(AuthScheme::Bearer),
+            (Some(_), scheme) => scheme,
+        };
+
+        let (protocol, url) = api_url::normalize(protocol, url.as_deref().unwrap_or(DEFAULT_URL))?;
+        let instance = Self {
+            protocol,
+            url,
+            email,
+            token,
+            auth_scheme,
+        };
+        debug!(
+            "using instance {} (authenticated: {})",
+            instance.base_url(),
+            instance.token.is_some()
+        );
+
+        Ok(instance)
+    }
+
+    /// Decide how the token is sent, if neither the command line nor the
+    /// profile did. Servers that are too old to report their version only
+    /// know the header pair.
+    pub fn detect_auth_scheme(&mut self, transport: &dyn Transport) {
+        if self.auth_scheme.is_some() || self.token.is_none() {
+            return;
+        }
+
+        let scheme = match pwpush_api::server_version(transport, self) {
+            Ok(server)
+                if server
+                    .application_version
+                    .as_deref()
+                    .is_some_and(|v| version::supports(v, version::BEARER_AUTH_VERSION)) =>
+            {
+                AuthScheme::Bearer
+            }
+            Ok(_) => AuthScheme::HeaderPair,
+            Err(e) => {
+                debug!(
+                    "could not detect the auth scheme, using the header pair: {}",
+                    e
+                );
+                AuthScheme::HeaderPair
+            }
+        };
+        debug!("detected auth scheme {:?}", scheme);
+        self.auth_scheme = Some(scheme);
+    }
+
+    /// Protocol and URL, without trailing slash, e.g. `https://pwpush.com`.
+    /// See `api_url` for building URLs below it.
+    pub fn base_url(&self) -> String {
+        format!("{}://{}", self.protocol, self.url)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+    use secrecy::ExposeSecret;
+
+    use super::Instance;
+    use crate::args::{AuthScheme, InstanceProtocol, PPCArgs};
+    use crate::config::PPCConfig;
+    use crate::http::mock::MockTransport;
+
+    fn config() -> PPCConfig {
+        toml::from_str(
+            "[profiles.work]\nurl = \"pwpush.example.com\"\nprotocol = \"http\"\n\
+             email = \"user@example.com\"\ntoken = \"t0k3n\"\n\
+             [profiles.broken]\nemail = \"user@example.com\"",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn resolve_defaults() {
+        let args = PPCArgs::parse_from(["pwpush-cli", "audit", "text", "abc"]);
+        let instance = Instance::resolve(&args, &config()).unwrap();
+
+        assert_eq!(instance.base_url(), "https://pwpush.com");
+        assert_eq!(instance.email, None);
+    }
+
+    #[test]
+    fn resolve_profile() {
+        let args = PPCArgs::parse_from(["pwpush-cli", "--profile", "work", "audit", "text", "abc"]);
+        let instance = Instance::resolve(&args, &config()).unwrap();
+
+        assert_eq!(instance.base_url(), "http://pwpush.example.com");
+        assert_eq!(
+            instance.token.as_ref().map(|t| t.expose_secret()),
+            Some("t0k3n")
+        );
+    }
+
+    #[test]
+    fn resolve_command_line_over_profile() {
+        let args = PPCArgs::parse_from([
+            "pwpush-cli",
+            "--profile",
+            "work",
+            "--protocol",
+            "https",
+            "--email",
+            "other@example.com",
+            "--token",
+            "other",
+            "audit",
+            "text",
+            "abc",
+        ]);
+        let instance = Instance::resolve(&args, &config()).unwrap();
+
+        assert_eq!(instance.protocol, InstanceProtocol::Https);
+        assert_eq!(instance.url, "pwpush.example.com");
+        assert_eq!(instance.email.as_deref(), Some("other@example.com"));
+        assert_eq!(
+            instance.token.as_ref().map(|t| t.expose_secret()),
+            Some("other")
+        );
+    }
+
+    #[test]
+    fn debug_redacts_token() {
+        let instance = Instance::from_profile(&config(), "work").unwrap();
+
+        let debug = format!("{:?}", instance);
+
+        assert!(!debug.contains("t0k3n"));
+        assert!(debug.contains("token: Some(REDACTED)"));
+    }
+
+    #[test]
+    fn resolve_token_without_email_as_bearer() {
+        let args = PPCArgs::parse_from(["pwpush-cli", "--token", "t0k3n", "audit", "text", "abc"]);
+        let instance = Instance::resolve(&args, &config()).unwrap();
+
+        assert_eq!(instance.email, None);
+        assert_eq!(instance.auth_scheme, Some(AuthScheme::Bearer));
+
+        let args = PPCArgs::parse_from([
+            "pwpush-cli",
+            "--token",
+            "t0k3n",
+            "--auth-scheme",
+            "header-pair",
+            "audit",
+            "text",
+            "abc",
+        ]);
+        assert!(Instance::resolve(&args, &config()).is_err());
+    }
+
+    #[test]
+    fn detect_auth_scheme_from_server_version() {
+        let mut instance = Instance::from_profile(&config(), "work").unwrap();
+        let transport = MockTransport::new().respond(200, r#"{"application_version":"1.49.2"}"#);
+        instance.detect_auth_scheme(&transport);
+        assert_eq!(instance.auth_scheme, Some(AuthScheme::Bearer));
+
+        let mut instance = Instance::from_profile(&config(), "work").unwrap();
+        let transport = MockTransport::new().respond(404, "not found");
+        instance.detect_auth_scheme(&transport);
+        assert_eq!(instance.auth_scheme, Some(AuthScheme::HeaderPair));
+
+        // an explicit scheme is never overridden, so nothing is requested.
+        let transport = MockTransport::new();
+        instance.detect_auth_scheme(&transport);
+        assert!(transport.requests().is_empty());
+    }
+
+    #[test]
+    fn resolve_errors() {
+        assert!(Instance::from_profile(&config(), "missing").is_err());
+        assert!(Instance::from_profile(&config(), "broken").is_err());
+    }
+}
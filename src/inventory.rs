@@ -0,0 +1,390 @@
+//! Export and import of the pushes of an account.
+//!
+//! `export` walks the active and expired lists of all three push kinds and
+//! writes their metadata as JSON or CSV. Payloads are not part of the lists,
+//! and retrieving a payload counts as a view. So payloads are only included
+//! for active text pushes, and only if explicitly requested with
+//! `--include-payloads`.
+//!
+//! `import` reads such a dump and re-creates all active text pushes that
+//! contain a payload on the selected instance, keeping note, expiry
+//! and viewer settings. The remaining days and views of the original push are
+//! used as new limits, so the new push does not live longer than the old one.
+//! Everything else is skipped with a warning, as there is nothing that could be
+//! re-created from metadata alone.
+
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tracing::{info, warn};
+
+use crate::args::{InventoryFormat, PPCArgs, PPCExport, PPCImport, PPCText, PushKind, PushState};
+use crate::config::PPCConfig;
+use crate::errors::PPCError;
+use crate::http::Transport;
+use crate::i18n;
+use crate::instance::Instance;
+use crate::models::Push;
+use crate::output;
+use crate::pwpush_api;
+use crate::repush;
+
+/// A single push in an export.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InventoryRecord {
+    pub kind: PushKind,
+    pub state: PushState,
+
+    #[serde(flatten)]
+    pub push: Push,
+}
+
+/// How values of a CSV column are converted back to JSON when importing.
+#[derive(Copy, Clone)]
+enum Column {
+    Text,
+    Number,
+    Bool,
+}
+
+/// Columns of the CSV format, in order.
+const CSV_COLUMNS: [(&str, Column); 14] = [
+    ("kind", Column::Text),
+    ("state", Column::Text),
+    ("url_token", Column::Text),
+    ("created_at", Column::Text),
+    ("expire_after_days", Column::Number),
+    ("expire_after_views", Column::Number),
+    ("days_remaining", Column::Number),
+    ("views_remaining", Column::Number),
+    ("expired", Column::Bool),
+    ("expired_on", Column::Text),
+    ("deletable_by_viewer", Column::Bool),
+    ("retrieval_step", Column::Bool),
+    ("note", Column::Text),
+    ("payload", Column::Text),
+];
+
+pub fn export(
+    transport: &dyn Transport,
+    instance: &Instance,
+    ppc_export: &PPCExport,
+) -> Result<(), PPCError> {
+    let records = collect(transport, instance, ppc_export.include_payloads)?;
+    info!("exporting {} pushes", records.len());
+
+    let content = match ppc_export.format {
+        InventoryFormat::Json => to_json(&records),
+        InventoryFormat::Csv => to_csv(&records)?,
+    };
+
+    match &ppc_export.output {
+        Some(path) => fs::write(path, content)
+            .map_err(|e| PPCError::from(format!("could not write {}: {}", path.display(), e))),
+        None => std::io::stdout()
+            .write_all(content.as_bytes())
+            .map_err(|e| PPCError::from(format!("could not write export: {}", e))),
+    }
+}
+
+pub fn import(
+    transport: &dyn Transport,
+    instance: &Instance,
+    args: &PPCArgs,
+    config: &PPCConfig,
+    ppc_import: &PPCImport,
+) -> Result<(), PPCError> {
+    let records = read(&ppc_import.file, ppc_import.format)?;
+    let mut created = Vec::new();
+
+    for record in &records {
+        let mut ppc_text = match importable(record) {
+            Ok(ppc_text) => ppc_text,
+            Err(reason) => {
+                warn!(
+                    "{}",
+                    i18n::format(
+                        "import-skipped",
+                        &[
+                            ("url_token", record.push.url_token.as_str().into()),
+                            ("reason", i18n::text(reason).into())
+                        ]
+                    )
+                );
+                continue;
+            }
+        };
+        config.policy.enforce(&mut ppc_text)?;
+
+        let push = pwpush_api::create_text_push(transport, instance, &ppc_text)?;
+        created.push((record.push.url_token.clone(), push.url_token));
+    }
+
+    if args.json_output {
+        let mapping = created
+            .iter()
+            .map(|(old, new)| serde_json::json!({ "source": old, "url_token": new }))
+            .collect::<Vec<_>>();
+        output::print_json_line(&mapping);
+    } else {
+        for (old, new) in &created {
+            println!("{} -> {}", old, new);
+        }
+        println!(
+            "{}",
+            i18n::format(
+                "import-done",
+                &[
+                    ("imported", created.len().into()),
+                    ("total", records.len().into())
+                ]
+            )
+        );
+    }
+
+    Ok(())
+}
+
+/// Fetch all lists of the account, in a stable order.
+pub fn collect(
+    transport: &dyn Transport,
+    instance: &Instance,
+    include_payloads: bool,
+) -> Result<Vec<InventoryRecord>, PPCError> {
+    let mut records = Vec::new();
+
+    for kind in [PushKind::Text, PushKind::File, PushKind::Url] {
+        for state in [PushState::Active, PushState::Expired] {
+            for push in pwpush_api::list(transport, instance, kind, state)? {
+                let push =
+                    if include_payloads && kind == PushKind::Text && state == PushState::Active {
+                        pwpush_api::retrieve(transport, instance, kind, &push.url_token)?
+                    } else {
+                        push
+                    };
+
+                records.push(InventoryRecord { kind, state, push });
+            }
+        }
+    }
+
+    Ok(records)
+}
+
+/// The push options to re-create a record, or the ID of the message with the
+/// reason why it cannot be re-created.
+fn importable(record: &InventoryRecord) -> Result<PPCText, &'static str> {
+    if record.kind != PushKind::Text {
+        return Err("import-not-text");
+    }
+    if record.state != PushState::Active {
+        return Err("import-expired");
+    }
+    if record.push.payload.is_none() {
+        return Err("import-no-payload");
+    }
+
+    repush::options_from(&record.push)
+}
+
+fn to_json(records: &[InventoryRecord]) -> String {
+    // records only consist of strings, numbers and bools, so serialization
+    // cannot fail.
+    serde_json::to_string_pretty(records).unwrap() + "\n"
+}
+
+fn to_csv(records: &[InventoryRecord]) -> Result<String, PPCError> {
+    let csv_err = |e: csv::Error| PPCError::from(format!("could not write csv: {}", e));
+    let mut writer = csv::Writer::from_writer(Vec::new());
+
+    writer
+        .write_record(CSV_COLUMNS.iter().map(|(name, _)| *name))
+        .map_err(csv_err)?;
+
+    for record in records {
+        // see to_json for why this cannot fail.
+        let value = serde_json::to_value(record).unwrap();
+        let row = CSV_COLUMNS.iter().map(|(name, _)| match &value[*name] {
+            Value::Null => String::new(),
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        });
+        writer.write_record(row).map_err(csv_err)?;
+    }
+
+    let bytes = writer
+        .into_inner()
+        .map_err(|e| PPCError::from(format!("could not write csv: {}", e)))?;
+    // all fields are valid UTF-8 strings, and so is the csv made of them.
+    Ok(String::from_utf8(bytes).unwrap())
+}
+
+fn read(path: &Path, format: InventoryFormat) -> Result<Vec<InventoryRecord>, PPCError> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| PPCError::from(format!("could not read {}: {}", path.display(), e)))?;
+
+    match format {
+        InventoryFormat::Json => from_json(&content),
+        InventoryFormat::Csv => from_csv(&content),
+    }
+    .map_err(|e| PPCError::from(format!("invalid export {}: {}", path.display(), e)))
+}
+
+fn from_json(content: &str) -> Result<Vec<InventoryRecord>, PPCError> {
+    serde_json::from_str(content).map_err(|e| PPCError::from(format!("{}", e)))
+}
+
+fn from_csv(content: &str) -> Result<Vec<InventoryRecord>, PPCError> {
+    let mut reader = csv::Reader::from_reader(content.as_bytes());
+    let headers = reader
+        .headers()
+        .map_err(|e| PPCError::from(format!("{}", e)))?
+        .clone();
+
+    let mut records = Vec::new();
+    for row in reader.records() {
+        let row = row.map_err(|e| PPCError::from(format!("{}", e)))?;
+
+        let mut object = Map::new();
+        for (name, field) in headers.iter().zip(row.iter()) {
+            let column = CSV_COLUMNS
+                .iter()
+                .find(|(column, _)| *column == name)
+                .map(|(_, column)| *column)
+                .ok_or_else(|| PPCError::from(format!("unknown column `{}`", name)))?;
+
+            object.insert(String::from(name), csv_value(field, column)?);
+        }
+
+        let record = serde_json::from_value(Value::Object(object))
+            .map_err(|e| PPCError::from(format!("line {}: {}", records.len() + 2, e)))?;
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+fn csv_value(field: &str, column: Column) -> Result<Value, PPCError> {
+    if field.is_empty() {
+        return Ok(Value::Null);
+    }
+
+    match column {
+        Column::Text => Ok(Value::from(field)),
+        Column::Number => field
+            .parse::<i64>()
+            .map(Value::from)
+            .map_err(|_| PPCError::from(format!("`{}` is not a number", field))),
+        Column::Bool => field
+            .parse::<bool>()
+            .map(Value::from)
+            .map_err(|_| PPCError::from(format!("`{}` is not true or false", field))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+    use secrecy::ExposeSecret;
+
+    use super::{collect, from_csv, from_json, importable, to_csv, to_json, InventoryRecord};
+    use crate::args::{PPCArgs, PushKind, PushState};
+    use crate::config::PPCConfig;
+    use crate::http::mock::MockTransport;
+    use crate::instance::Instance;
+    use crate::models::Push;
+
+    fn record(kind: PushKind, state: PushState, payload: Option<&str>) -> InventoryRecord {
+        InventoryRecord {
+            kind,
+            state,
+            push: Push {
+                url_token: String::from("abc"),
+                created_at: Some(String::from("2023-12-01T10:00:00Z")),
+                days_remaining: Some(2),
+                views_remaining: Some(0),
+                deletable_by_viewer: Some(true),
+                note: Some(String::from("note, with \"quotes\"")),
+                payload: payload.map(String::from),
+                ..Push::default()
+            },
+        }
+    }
+
+    #[test]
+    fn json_roundtrip() {
+        let records = vec![record(PushKind::Text, PushState::Active, Some("secret"))];
+
+        assert_eq!(from_json(&to_json(&records)).unwrap(), records);
+    }
+
+    #[test]
+    fn csv_roundtrip() {
+        let records = vec![
+            record(PushKind::Text, PushState::Active, Some("secret")),
+            record(PushKind::Url, PushState::Expired, None),
+        ];
+
+        let csv = to_csv(&records).unwrap();
+        assert!(csv.starts_with("kind,state,url_token,"));
+        assert_eq!(from_csv(&csv).unwrap(), records);
+    }
+
+    #[test]
+    fn csv_rejects_unknown_columns() {
+        assert!(from_csv("kind,colour\ntext,blue\n").is_err());
+    }
+
+    #[test]
+    fn importable_uses_remaining_limits() {
+        let ppc_text =
+            importable(&record(PushKind::Text, PushState::Active, Some("secret"))).unwrap();
+
+        assert_eq!(
+            ppc_text
+                .password_payload
+                .as_ref()
+                .map(|p| p.expose_secret()),
+            Some("secret")
+        );
+        assert_eq!(ppc_text.expire_after_days, Some(2));
+        assert_eq!(ppc_text.expire_after_views, None);
+        assert_eq!(ppc_text.deletable_by_viewer, Some(true));
+    }
+
+    #[test]
+    fn importable_skips_other_records() {
+        assert!(importable(&record(PushKind::Text, PushState::Active, None)).is_err());
+        assert!(importable(&record(PushKind::Text, PushState::Expired, Some("s"))).is_err());
+        assert!(importable(&record(PushKind::File, PushState::Active, Some("s"))).is_err());
+    }
+
+    #[test]
+    fn collect_walks_all_lists() {
+        let args = PPCArgs::parse_from(["pwpush-cli", "export"]);
+        let instance = Instance::resolve(&args, &PPCConfig::default()).unwrap();
+        let transport = MockTransport::new()
+            .respond(200, r#"[{"url_token":"t1"}]"#)
+            .respond(200, "[]")
+            .respond(200, "[]")
+            .respond(200, r#"[{"url_token":"f1"}]"#)
+            .respond(200, "[]")
+            .respond(200, "[]");
+
+        let records = collect(&transport, &instance, false).unwrap();
+
+        let urls = transport
+            .requests()
+            .iter()
+            .map(|r| r.url.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(urls[0], "https://pwpush.com/p/active.json");
+        assert_eq!(urls[5], "https://pwpush.com/r/expired.json");
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].kind, PushKind::File);
+        assert_eq!(records[1].state, PushState::Expired);
+    }
+}
@@ -0,0 +1,768 @@
+//! Resumable bulk jobs.
+//!
+//! `expire bulk` and `push text --recipients` work through a list of items.
+//! If some of them fail, e.g. because the instance went away in between, or
+//! the run is interrupted with Ctrl-C, simply running the command again would
+//! expire everything again, or worse, push the secret a second time to the
+//! recipients who already got their link. Instead, the state of the job is
+//! written to a file, and `bulk resume <job-id>` continues with the items
+//! that are not done yet. `bulk list` shows the unfinished jobs, and `bulk
+//! discard <job-id>` deletes one. Finished jobs are deleted automatically.
+//!
+//! Items whose request was sent, but whose response was lost, count as
+//! failed, so they are tried again on resume. The instance cannot tell
+//! whether the push was created in that case.
+//!
+//! Jobs of pushes contain the payload, and jobs of expirations contain URL
+//! tokens, which give access to the pushes as well. So job files are
+//! encrypted like the queue, see `state`, and live in `jobs` in the data
+//! directory. A job can only be resumed on the instance it was started on,
+//! with the credentials that are configured at that point.
+//!
+//! Every run ends with a `BulkSummary`: the items that succeeded, failed or
+//! were skipped, with the error of each failed item, and whether they fail
+//! the command under `--fail-on`. `--json` prints it next to the items, in
+//! one object, and `--report` writes it to a file, so orchestration tools can
+//! act on the failed items without parsing messages.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chacha20poly1305::XChaCha20Poly1305;
+use chrono::Utc;
+use rand::RngCore;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::debug;
+use zeroize::Zeroizing;
+
+use crate::args::{BulkCommand, FailOn, PPCAction, PPCArgs, PPCExpire, PPCObject, PushKind};
+use crate::config::PPCConfig;
+use crate::errors::{PPCError, EXIT_INTERRUPTED};
+use crate::http::Transport;
+use crate::i18n;
+use crate::instance::Instance;
+use crate::models::Push;
+use crate::{
+    bulk, history, hooks, output, paths, platform, prompt, pwpush_api, share, signals, state,
+};
+
+const KEY_FILE: &str = "jobs.key";
+const EXTENSION: &str = "job";
+
+/// What a job does with each of its items.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "operation", rename_all = "snake_case")]
+pub enum Operation {
+    /// Expire pushes, the items are URL tokens
+    Expire { kind: PushKind },
+
+    /// Create text pushes, the items are recipients
+    Push { notify_webhook: Option<String> },
+}
+
+impl Operation {
+    /// Name of the operation for people, like the command that started it.
+    fn name(&self) -> String {
+        match self {
+            Operation::Expire { kind } => format!("expire {}", kind),
+            Operation::Push { .. } => String::from("push text"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Status {
+    Pending,
+    Done,
+    Failed { error: String },
+}
+
+// no Debug, as the body contains the payload.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Item {
+    /// URL token or recipient
+    pub name: String,
+
+    /// Form body of the push, see `api_body::push_form`. Dropped once
+    /// the push was created, so finished items no longer contain the payload
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+
+    /// Note of the push, for the history
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+
+    #[serde(flatten)]
+    pub status: Status,
+}
+
+impl Item {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            body: None,
+            note: None,
+            status: Status::Pending,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Job {
+    #[serde(skip)]
+    pub id: String,
+
+    pub created_at: String,
+
+    /// Base URL of the instance the job runs on
+    pub instance: String,
+
+    #[serde(flatten)]
+    pub operation: Operation,
+
+    pub items: Vec<Item>,
+}
+
+impl Job {
+    pub fn new(instance: &Instance, operation: Operation, items: Vec<Item>) -> Self {
+        let now = Utc::now();
+        Self {
+            id: format!(
+                "{}-{:04x}",
+                now.format("%Y%m%dT%H%M%S"),
+                rand::thread_rng().next_u32() as u16
+            ),
+            created_at: now.to_rfc3339(),
+            instance: instance.base_url(),
+            operation,
+            items,
+        }
+    }
+
+    fn open_items(&self) -> usize {
+        self.items
+            .iter()
+            .filter(|item| item.status != Status::Done)
+            .count()
+    }
+
+    /// Verb for the items in messages, e.g. "pushes could not be expired".
+    fn describe(&self) -> &'static str {
+        match self.operation {
+            Operation::Expire { .. } => "pushes could not be expired",
+            Operation::Push { .. } => "recipients could not be pushed to",
+        }
+    }
+}
+
+/// A directory of encrypted job files.
+pub struct Jobs {
+    dir: PathBuf,
+    cipher: XChaCha20Poly1305,
+}
+
+impl Jobs {
+    /// Open the jobs in the given directory, with their key in the key
+    /// directory `keys`, creating the directories and the key if needed.
+    pub fn open(dir: &Path, keys: &Path) -> Result<Self, PPCError> {
+        state::create_private_dir(dir)?;
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            cipher: state::cipher(&state::key_file(keys, dir, KEY_FILE)?)?,
+        })
+    }
+
+    pub fn save(&self, job: &Job) -> Result<(), PPCError> {
+        // the job only consists of strings, so serialization cannot fail.
+        let plaintext = Zeroizing::new(serde_json::to_vec(job).unwrap());
+        let content = state::seal(&self.cipher, &plaintext)?;
+        state::replace_private(&self.path(&job.id), &content)
+    }
+
+    pub fn load(&self, id: &str) -> Result<Job, PPCError> {
+        let path = self.path(id);
+        if !path.exists() {
+            return Err(PPCError::from(format!(
+                "there is no unfinished job {}, see `bulk list`",
+                id
+            )));
+        }
+        let content = fs::read(&path)
+            .map_err(|e| PPCError::from(format!("could not read {}: {}", path.display(), e)))?;
+        let plaintext = state::unseal(&self.cipher, &content)
+            .map_err(|e| PPCError::from(format!("{}: {}", path.display(), e)))?;
+
+        let mut job: Job = serde_json::from_slice(&plaintext)
+            .map_err(|e| PPCError::from(format!("invalid job {}: {}", id, e)))?;
+        job.id = String::from(id);
+        Ok(job)
+    }
+
+    pub fn remove(&self, id: &str) -> Result<(), PPCError> {
+        let path = self.path(id);
+        match fs::remove_file(&path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(PPCError::from(format!(
+                "could not remove {}: {}",
+                path.display(),
+                e
+            ))),
+        }
+    }
+
+    /// IDs of all unfinished jobs, oldest first.
+    pub fn ids(&self) -> Result<Vec<String>, PPCError> {
+        let read_err = |e: std::io::Error| PPCError::from(format!("could not read jobs: {}", e));
+
+        let mut ids = Vec::new();
+        for entry in fs::read_dir(&self.dir).map_err(read_err)? {
+            let path = entry.map_err(read_err)?.path();
+            if path.extension().and_then(|e| e.to_str()) == Some(EXTENSION) {
+                if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+                    ids.push(String::from(stem));
+                }
+            }
+        }
+
+        ids.sort();
+        Ok(ids)
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", id, EXTENSION))
+    }
+}
+
+/// Default location of the jobs.
+pub fn default_dir() -> Result<PathBuf, PPCError> {
+    state::dir("jobs")
+}
+
+/// Result of a run of a job, for scripts, see the module docs.
+#[derive(Debug, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct BulkSummary {
+    pub job_id: String,
+
+    /// Operation of the job, e.g. "expire text" or "push text"
+    pub operation: String,
+
+    /// All items of the job, including the ones done in earlier runs
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+
+    /// Items that were not started because the run was interrupted
+    pub skipped: usize,
+
+    /// Whether the failed and skipped items fail the command, see --fail-on
+    pub fails_command: bool,
+
+    /// Command that continues with the items that are not done, if any
+    pub resume: Option<String>,
+
+    pub errors: Vec<BulkError>,
+}
+
+/// A failed item of a bulk operation.
+#[derive(Debug, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct BulkError {
+    /// URL token or recipient
+    pub item: String,
+    pub error: String,
+}
+
+/// The result of an item in this run.
+enum Outcome {
+    Expired,
+    Pushed(Push),
+    Failed(PPCError),
+    /// Not started because of an interruption
+    Skipped,
+}
+
+/// Work on all items of the job that are not done yet, report them, and keep
+/// the job for `bulk resume` if any of them remain.
+pub fn execute(
+    transport: &dyn Transport,
+    instance: &Instance,
+    args: &PPCArgs,
+    config: &PPCConfig,
+    jobs: &Jobs,
+    job: &mut Job,
+    parallel: usize,
+) -> Result<(), PPCError> {
+    let open = job
+        .items
+        .iter()
+        .enumerate()
+        .filter(|(_, item)| item.status != Status::Done)
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+
+    let webhooks = platform::transport()?;
+    let listener = signals::listen();
+    let outcomes = bulk::run_parallel(&open, parallel, |index| {
+        if signals::interrupted() {
+            return Outcome::Skipped;
+        }
+        process(transport, instance, &job.operation, &job.items[*index])
+    });
+    drop(listener);
+
+    let mut report = Report::new(args, job);
+    for (index, outcome) in open.into_iter().zip(outcomes) {
+        let item = &mut job.items[index];
+        match &outcome {
+            Outcome::Expired => {
+                if let Operation::Expire { kind } = &job.operation {
+                    hooks::post_expire(&config.hooks, *kind, &item.name);
+                }
+                item.status = Status::Done;
+            }
+            Outcome::Pushed(push) => {
+                let url = share::url(transport, instance, PushKind::Text, push);
+                history::record(config, instance, push, item.note.as_deref(), &[], &url);
+                if let Operation::Push { notify_webhook } = &job.operation {
+                    hooks::notify_push(webhooks, notify_webhook.as_deref(), push, &url);
+                }
+                hooks::post_push(&config.hooks, PushKind::Text, push, &url);
+                item.status = Status::Done;
+                item.body = None;
+                report.add(&item.name, &outcome, Some(&url));
+                continue;
+            }
+            Outcome::Failed(e) => {
+                item.status = Status::Failed {
+                    error: e.to_string(),
+                }
+            }
+            Outcome::Skipped => {}
+        }
+        report.add(&item.name, &outcome, None);
+    }
+
+    let remaining = job.open_items();
+    let summary = report.finish(job, args.fail_on);
+    if remaining == 0 {
+        debug!("job {} is done", job.id);
+        jobs.remove(&job.id)?;
+    } else {
+        jobs.save(job)?;
+    }
+    if let Some(path) = &args.report_file {
+        write_report(path, &summary)?;
+    }
+    if remaining == 0 {
+        return Ok(());
+    }
+
+    if signals::interrupted() {
+        return Err(PPCError::with_exit_code(
+            format!(
+                "interrupted, {} of {} items are not done, continue with `bulk resume {}`",
+                remaining,
+                job.items.len(),
+                job.id
+            ),
+            EXIT_INTERRUPTED,
+        ));
+    }
+    Err(PPCError::partial(format!(
+        "{} of {} {}, retry them with `bulk resume {}`",
+        remaining,
+        job.items.len(),
+        job.describe(),
+        job.id
+    )))
+}
+
+fn process(
+    transport: &dyn Transport,
+    instance: &Instance,
+    operation: &Operation,
+    item: &Item,
+) -> Outcome {
+    let result = match operation {
+        Operation::Expire { kind } => {
+            pwpush_api::expire_push(transport, instance, *kind, &item.name)
+                .map(|()| Outcome::Expired)
+        }
+        Operation::Push { .. } => match &item.body {
+            Some(body) => pwpush_api::create_text_push_from_body(transport, instance, body)
+                .map(Outcome::Pushed),
+            None => Err(PPCError::from("the job contains no push for this item")),
+        },
+    };
+    result.unwrap_or_else(Outcome::Failed)
+}
+
+/// Output of a run, in the format of the command that started the job.
+struct Report {
+    json_output: bool,
+    expire: bool,
+    width: usize,
+    json: Vec<serde_json::Value>,
+    succeeded: usize,
+    skipped: usize,
+    errors: Vec<BulkError>,
+}
+
+impl Report {
+    fn new(args: &PPCArgs, job: &Job) -> Self {
+        Self {
+            json_output: args.json_output,
+            expire: matches!(job.operation, Operation::Expire { .. }),
+            width: job
+                .items
+                .iter()
+                .map(|item| item.name.len())
+                .max()
+                .unwrap_or(0),
+            json: Vec::new(),
+            succeeded: 0,
+            skipped: 0,
+            errors: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, name: &str, outcome: &Outcome, url: Option<&str>) {
+        match outcome {
+            Outcome::Expired | Outcome::Pushed(_) => self.succeeded += 1,
+            Outcome::Failed(e) => self.errors.push(BulkError {
+                item: String::from(name),
+                error: e.to_string(),
+            }),
+            Outcome::Skipped => self.skipped += 1,
+        }
+
+        let width = self.width;
+        let (line, value) = match (self.expire, outcome) {
+            (true, Outcome::Failed(e)) => (
+                format!("{}: failed: {}", name, e),
+                json!({ "url_token": name, "expired": false, "error": e.to_string() }),
+            ),
+            (true, Outcome::Skipped) => (
+                format!("{}: skipped", name),
+                json!({ "url_token": name, "expired": false, "skipped": true }),
+            ),
+            (true, _) => (
+                format!("{}: expired", name),
+                json!({ "url_token": name, "expired": true }),
+            ),
+            (false, Outcome::Pushed(push)) => (
+                format!("{:width$}  {}", name, url.unwrap_or_default()),
+                json!({ "recipient": name, "url_token": push.url_token, "url": url }),
+            ),
+            (false, Outcome::Failed(e)) => (
+                format!("{:width$}  failed: {}", name, e),
+                json!({ "recipient": name, "error": e.to_string() }),
+            ),
+            (false, _) => (
+                format!("{:width$}  skipped", name),
+                json!({ "recipient": name, "skipped": true }),
+            ),
+        };
+
+        if self.json_output {
+            self.json.push(value);
+        } else {
+            println!("{}", line);
+        }
+    }
+
+    /// Print the JSON output, the items with the summary.
+    fn finish(self, job: &Job, fail_on: FailOn) -> BulkSummary {
+        let failed = self.errors.len();
+        let summary = BulkSummary {
+            job_id: job.id.clone(),
+            operation: job.operation.name(),
+            total: job.items.len(),
+            succeeded: self.succeeded,
+            failed,
+            skipped: self.skipped,
+            fails_command: self.skipped > 0 || (failed > 0 && fail_on != FailOn::None),
+            resume: (job.open_items() > 0).then(|| format!("pwpush-cli bulk resume {}", job.id)),
+            errors: self.errors,
+        };
+
+        if self.json_output {
+            output::print_json_line(&json!({ "items": self.json, "summary": summary }));
+        }
+        summary
+    }
+}
+
+/// Write the summary as JSON document. It names the URL tokens of the job,
+/// so only the user can read it.
+fn write_report(path: &Path, summary: &BulkSummary) -> Result<(), PPCError> {
+    // the summary only consists of strings, numbers and bools.
+    let value = output::versioned(serde_json::to_value(summary).unwrap());
+    let mut content = serde_json::to_string_pretty(&value).unwrap();
+    content.push('\n');
+    state::replace_private(path, content.as_bytes())
+}
+
+/// Fail if the command is no bulk operation but --report is given, before it
+/// changes anything.
+pub fn check(args: &PPCArgs) -> Result<(), PPCError> {
+    if args.report_file.is_none() {
+        return Ok(());
+    }
+    match &args.action {
+        PPCAction::Expire(PPCExpire::Bulk(_)) | PPCAction::Bulk(BulkCommand::Resume { .. }) => {
+            Ok(())
+        }
+        PPCAction::Push(PPCObject::Text(ppc_text)) if !ppc_text.recipients.is_empty() => Ok(()),
+        _ => Err(PPCError::from(
+            "--report is only supported by expire bulk, push text --recipients and bulk resume",
+        )),
+    }
+}
+
+/// Entry point for the `bulk` subcommands.
+pub fn run(
+    transport: &dyn Transport,
+    instance: &Instance,
+    args: &PPCArgs,
+    config: &PPCConfig,
+    command: &BulkCommand,
+) -> Result<(), PPCError> {
+    let jobs = Jobs::open(&default_dir()?, &paths::key_dir()?)?;
+
+    match command {
+        BulkCommand::List => list(&jobs, args),
+        BulkCommand::Resume { job_id } => {
+            let mut job = jobs.load(job_id)?;
+            if job.instance != instance.base_url() {
+                return Err(PPCError::from(format!(
+                    "job {} was started on {}, resume it with the same instance",
+                    job.id, job.instance
+                )));
+            }
+            execute(
+                transport,
+                instance,
+                args,
+                config,
+                &jobs,
+                &mut job,
+                bulk::DEFAULT_JOBS,
+            )
+        }
+        BulkCommand::Discard { job_id } => {
+            let job = jobs.load(job_id)?;
+            let question = i18n::format(
+                "job-discard-question",
+                &[
+                    ("id", job.id.as_str().into()),
+                    ("open", job.open_items().into()),
+                ],
+            );
+            if !prompt::confirm(&question, args.assume_yes)? {
+                eprintln!("{}", i18n::text("job-discard-aborted"));
+                return Ok(());
+            }
+            jobs.remove(&job.id)
+        }
+    }
+}
+
+fn list(jobs: &Jobs, args: &PPCArgs) -> Result<(), PPCError> {
+    let mut listed = Vec::new();
+    for id in jobs.ids()? {
+        let job = jobs.load(&id)?;
+        let operation = job.operation.name();
+
+        if args.json_output {
+            listed.push(json!({
+                "id": job.id,
+                "operation": operation,
+                "instance": job.instance,
+                "created_at": job.created_at,
+                "items": job.items.len(),
+                "open": job.open_items(),
+            }));
+        } else {
+            println!(
+                "{}  {:12} {} of {} open  {}",
+                job.id,
+                operation,
+                job.open_items(),
+                job.items.len(),
+                job.instance
+            );
+        }
+    }
+
+    if args.json_output {
+        output::print_json_line(&listed);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+
+    use super::{check, execute, Item, Job, Jobs, Operation, Status};
+    use crate::args::{InstanceProtocol, PPCArgs, PushKind};
+    use crate::config::PPCConfig;
+    use crate::http::mock::MockTransport;
+    use crate::instance::Instance;
+    use crate::output::SCHEMA_VERSION;
+
+    fn instance() -> Instance {
+        Instance {
+            protocol: InstanceProtocol::Https,
+            url: String::from("pwpush.example.com"),
+            email: None,
+            token: None,
+            auth_scheme: None,
+        }
+    }
+
+    fn expire_job() -> Job {
+        let items = ["a", "b", "c"].map(|name| Item::new(String::from(name)));
+        Job::new(
+            &instance(),
+            Operation::Expire {
+                kind: PushKind::Text,
+            },
+            items.to_vec(),
+        )
+    }
+
+    #[test]
+    fn jobs_are_stored_encrypted() {
+        let dir = tempfile::tempdir().unwrap();
+        let keys = tempfile::tempdir().unwrap();
+        let jobs = Jobs::open(dir.path(), keys.path()).unwrap();
+        let mut job = expire_job();
+        job.items[0].body = Some(String::from("password[payload]=s3cret"));
+
+        jobs.save(&job).unwrap();
+
+        assert_eq!(jobs.ids().unwrap(), vec![job.id.clone()]);
+        let loaded = jobs.load(&job.id).unwrap();
+        assert_eq!(loaded.operation, job.operation);
+        assert_eq!(loaded.items[0].body, job.items[0].body);
+        assert_eq!(loaded.items[2].status, Status::Pending);
+        let content = std::fs::read(dir.path().join(format!("{}.job", job.id))).unwrap();
+        assert!(!String::from_utf8_lossy(&content).contains("s3cret"));
+    }
+
+    #[test]
+    fn resume_only_sends_open_items() {
+        let dir = tempfile::tempdir().unwrap();
+        let keys = tempfile::tempdir().unwrap();
+        let jobs = Jobs::open(dir.path(), keys.path()).unwrap();
+        let args = PPCArgs::parse_from(["pwpush-cli", "--json", "bulk", "list"]);
+        let mut job = expire_job();
+
+        // run_parallel sends the requests in any order, so every item fails
+        // on the first run, but a single one is enough to keep the job.
+        let transport = MockTransport::new()
+            .respond(500, "error")
+            .respond(500, "error")
+            .respond(500, "error");
+        assert!(execute(
+            &transport,
+            &instance(),
+            &args,
+            &PPCConfig::default(),
+            &jobs,
+            &mut job,
+            2
+        )
+        .is_err());
+        assert!(job
+            .items
+            .iter()
+            .all(|item| matches!(item.status, Status::Failed { .. })));
+        assert_eq!(jobs.ids().unwrap().len(), 1);
+
+        job.items[1].status = Status::Done;
+        let transport = MockTransport::new()
+            .respond(200, r#"{"expired":true}"#)
+            .respond(200, r#"{"expired":true}"#);
+        execute(
+            &transport,
+            &instance(),
+            &args,
+            &PPCConfig::default(),
+            &jobs,
+            &mut job,
+            2,
+        )
+        .unwrap();
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests.iter().all(|request| !request.url.contains("/p/b")));
+        assert!(jobs.ids().unwrap().is_empty());
+    }
+
+    #[test]
+    fn report_lists_failed_items() {
+        let dir = tempfile::tempdir().unwrap();
+        let keys = tempfile::tempdir().unwrap();
+        let jobs = Jobs::open(dir.path(), keys.path()).unwrap();
+        let report = dir.path().join("report.json");
+        let args = PPCArgs::parse_from([
+            "pwpush-cli",
+            "--report",
+            report.to_str().unwrap(),
+            "bulk",
+            "resume",
+            "x",
+        ]);
+        let mut job = expire_job();
+        job.items[0].status = Status::Done;
+        let transport = MockTransport::new()
+            .respond(200, r#"{"expired":true}"#)
+            .respond(500, "error");
+
+        assert!(execute(
+            &transport,
+            &instance(),
+            &args,
+            &PPCConfig::default(),
+            &jobs,
+            &mut job,
+            1
+        )
+        .is_err());
+
+        let summary: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&report).unwrap()).unwrap();
+        assert_eq!(summary["schema_version"], SCHEMA_VERSION);
+        assert_eq!(summary["operation"], "expire text");
+        assert_eq!(
+            (&summary["total"], &summary["succeeded"], &summary["failed"]),
+            (&3.into(), &1.into(), &1.into())
+        );
+        assert_eq!(summary["fails_command"], true);
+        assert_eq!(
+            summary["resume"],
+            format!("pwpush-cli bulk resume {}", job.id)
+        );
+        assert_eq!(summary["errors"][0]["item"], "c");
+    }
+
+    #[test]
+    fn report_is_refused_for_other_commands() {
+        let args = |argv: &[&str]| {
+            PPCArgs::parse_from(["pwpush-cli", "--report", "report.json"].iter().chain(argv))
+        };
+
+        assert!(check(&args(&["bulk", "resume", "x"])).is_ok());
+        assert!(check(&args(&["push", "text", "secret", "--recipients", "alice"])).is_ok());
+        assert!(check(&args(&["push", "text", "secret"])).is_err());
+        assert!(check(&args(&["summary"])).is_err());
+    }
+}
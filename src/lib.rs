@@ -0,0 +1,183 @@
+//! **Yet another CLI for Password Pusher**
+//!
+//! Public instance available at <https://pwpush.com>
+//!
+//! ## CLI interface design
+//!
+//! The CLI interface needs to provide an ergonomic interface for interactive
+//! use as well as in non-interactive environments. This holds true both for
+//! input, control and configuration, and output.
+//!
+//! The interface is structured into subcommands that give a verbose interface
+//! with easy-to-remember syntax. The `--help` switch must always be availble.
+//! The current subcommands resemble the API quite closely, but more abstract
+//! commands are planned in order adjust to actual use cases like searching
+//! or bulk publishing. These advanced commands will rely on the functionality
+//! of the more basic subcommands, and exporting the basic ones does not hurt
+//! the user experience.
+//!
+//! Due to the design of the API we need to specify the object under operation
+//! explicitly with each api call. This results in all of the subcommands being
+//! divided into tree sub-subcommands, one for passwords (text), files, and urls
+//! respectively.
+//!
+//! Basic subcommands:
+//!   - push -> used to publish a new secret, calls the POST endpoint
+//!   - expire
+//!   - info (looked up in the lists, as retrieving would count as a view)
+//!   - preview
+//!   - audit (with `--watch` to follow new events)
+//!   - list
+//!     - active
+//!     - expired
+//!
+//! The application honors a strict separation of error messages to stderr and
+//! normal output to stdout.
+//!
+//! For output in a machine-readable format, all commands print JSON with
+//! `--json`, in a versioned schema, see `output`. Programs can also pass the
+//! command as JSON document on stdin with `--stdin-json`, see `stdin_json`.
+//!
+//! ## API description
+//!
+//! See <https://pwpush.com/api> for publicly available api documentation.
+//!
+//! In essence, the API can be divided into three almost identical sections.
+//! These are management for file pushes, text pushes and URL pushes. At the
+//! moment, following endpoints are defined for each of these:
+//!   - GET :url_token
+//!   - POST
+//!   - GET :url_token/preview
+//!   - GET :url_token/audit
+//!   - DELETE :url_token
+//!   - GET active
+//!   - GET expired
+//!
+//! Each of the endpoints (except the POST endpoint) is prefixed with a single
+//! letter that resembles the type of object the endpoint should operate on:
+//!   - file pushes -> 'f'
+//!   - text pushes -> 'p'
+//!   - url pushes -> 'r'
+//!
+//! Instead of HTTP headers, the API currently relies on a '.json' suffix in
+//! order to deliver a JSON response instead of a web page. There is no general
+//! api prefix, no api versioning, and the api endpoints reside outside of the
+//! localization of 'Password Pusher'. So a final API URL to retrieve all active
+//! file pushes in the public pwpush.com instance looks like this:
+//! `https://pwpush.com/f/active.json`
+//!
+//! The API supports authentication in the form of authentication tokens that
+//! are bound to a specific user with a login. Authenticated API requests need
+//! two special HTTP headers:
+//!   - `X-User-Email`: email of the account that the token was generated from
+//!   - `X-User-Token`: token out of the accounts token view
+//!
+//! Newer servers also accept the token alone, as `Authorization: Bearer`.
+//!
+//! The modules are the library the `pwpush-cli` binary is built on, so other
+//! Rust programs can talk to Password Pusher instances the same way, e.g.
+//! with the builder of `client`.
+
+pub mod account;
+pub mod aliases;
+pub mod api_body;
+pub mod api_url;
+pub mod archive;
+pub mod args;
+pub mod argv_secrets;
+pub mod audit;
+pub mod audit_report;
+pub mod broadcast;
+#[cfg(feature = "tui")]
+pub mod browse;
+pub mod bulk;
+pub mod cache;
+pub mod client;
+pub mod config;
+pub mod credentials;
+pub mod crypto;
+pub mod dates;
+pub mod dedupe;
+pub mod diff;
+pub mod doctor;
+pub mod download;
+pub mod duration;
+pub mod email;
+pub mod errors;
+pub mod expire;
+pub mod expire_at;
+pub mod extra_headers;
+pub mod fail_on;
+pub mod first_view;
+pub mod get;
+pub mod helper;
+pub mod history;
+pub mod hold;
+pub mod hooks;
+pub mod http;
+pub mod i18n;
+pub mod idempotency;
+pub mod info;
+pub mod instance;
+pub mod inventory;
+pub mod jobs;
+pub mod localized;
+pub mod logging;
+pub mod logins;
+#[cfg(feature = "serve")]
+pub mod metrics;
+pub mod models;
+pub mod multipart;
+pub mod options_file;
+pub mod output;
+pub mod pager;
+pub mod paranoid;
+pub mod passphrase;
+pub mod password_managers;
+pub mod paths;
+pub mod payload;
+pub mod platform;
+pub mod policy;
+pub mod presets;
+pub mod prompt;
+pub mod pwpush_api;
+pub mod qr;
+pub mod queue;
+pub mod raw;
+pub mod recipients;
+pub mod recording;
+pub mod redact;
+pub mod redirect;
+pub mod remind;
+pub mod render;
+pub mod report;
+pub mod repush;
+pub mod scheduler;
+#[cfg(feature = "serve")]
+pub mod serve;
+pub mod settings;
+pub mod share;
+pub mod shortener;
+pub mod siem;
+pub mod signals;
+pub mod skew;
+pub mod state;
+pub mod stdin_json;
+pub mod structured;
+pub mod summary;
+pub mod table;
+pub mod template;
+pub mod terminal;
+pub mod throttle;
+pub mod totp;
+pub mod trace;
+#[cfg(all(unix, feature = "unix-socket"))]
+pub mod unix_socket;
+pub mod update;
+pub mod user_agent;
+pub mod vault;
+pub mod version;
+#[cfg(feature = "serve")]
+pub mod watchlist;
+pub mod wifi;
+pub mod wizard;
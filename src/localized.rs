@@ -0,0 +1,171 @@
+//! Instances that answer in other languages than English.
+//!
+//! Password Pusher is translated to many languages, and self-hosted instances
+//! often default to the language of their users. With `--accept-language`,
+//! e.g. `--accept-language de` or `--accept-language "fr-CH, fr;q=0.9"`, every
+//! request to the instance carries an `Accept-Language` header, so messages of
+//! the instance are in the language of the user, like the messages of the CLI
+//! with `--lang`, see `i18n`. Without it, the instance picks its default.
+//!
+//! Most answers of the API are data, but a few errors only exist as message,
+//! like a missing passphrase. The CLI reacts to these, so they are recognized
+//! by `classify_body` in the languages Password Pusher ships, and turned into an
+//! `InstanceError`, instead of relying on English wording. Messages that are
+//! not recognized are shown as the instance sent them.
+
+use zeroize::Zeroize;
+
+use crate::errors::PPCError;
+use crate::http::{PPCRequest, PPCResponse, Transport};
+
+const HEADER: &str = "Accept-Language";
+
+/// Errors of the instance that the CLI reacts to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum InstanceError {
+    /// The push has a passphrase, which was missing or wrong
+    PassphraseRequired,
+
+    /// The request needs a signed in user
+    Unauthenticated,
+}
+
+/// Parts of the messages of each error, in lower case, in English, German,
+/// French, Spanish, Italian, Dutch and Portuguese.
+const MESSAGES: [(InstanceError, &[&str]); 2] = [
+    (
+        InstanceError::PassphraseRequired,
+        &[
+            "passphrase",
+            "phrase secrète",
+            "frase de contraseña",
+            "wachtwoordzin",
+            "frase secreta",
+            "frase-senha",
+        ],
+    ),
+    (
+        InstanceError::Unauthenticated,
+        &[
+            "sign in or sign up",
+            "anmelden oder registrieren",
+            "vous connecter ou vous inscrire",
+            "iniciar sesión o registrarte",
+            "accedere o registrarti",
+            "inloggen of registreren",
+            "entrar ou se registrar",
+        ],
+    ),
+];
+
+/// The error of a message of the instance, if it is a known one.
+fn classify(message: &str) -> Option<InstanceError> {
+    let message = message.to_lowercase();
+    MESSAGES
+        .iter()
+        .find(|(_, parts)| parts.iter().any(|part| message.contains(part)))
+        .map(|(error, _)| *error)
+}
+
+/// The error of a JSON body like `{"error":"..."}`, if it is a known one.
+pub fn classify_body(body: &str) -> Option<InstanceError> {
+    let value = serde_json::from_str::<serde_json::Value>(body).ok()?;
+    classify(value.get("error")?.as_str()?)
+}
+
+/// Value parser for `--accept-language`, which takes the value of the header,
+/// e.g. `de` or `fr-CH, fr;q=0.9`.
+pub fn parse(value: &str) -> Result<String, String> {
+    let is_valid = |c: char| c.is_ascii_alphanumeric() || "-*;=., ".contains(c);
+    if value.trim().is_empty() || !value.chars().all(is_valid) {
+        return Err(format!(
+            "`{}` is not a list of languages like `de` or `fr-CH, fr;q=0.9`",
+            value
+        ));
+    }
+    Ok(String::from(value.trim()))
+}
+
+/// Wraps another transport and sets the Accept-Language of every request,
+/// like `user_agent::UserAgentTransport`. Requests are passed on unchanged if
+/// no languages were given.
+pub struct AcceptLanguageTransport<T: Transport> {
+    inner: T,
+    languages: Option<String>,
+}
+
+impl<T: Transport> AcceptLanguageTransport<T> {
+    pub fn new(inner: T, languages: Option<String>) -> Self {
+        Self { inner, languages }
+    }
+}
+
+impl<T: Transport> Transport for AcceptLanguageTransport<T> {
+    fn send(&self, request: &PPCRequest) -> Result<PPCResponse, PPCError> {
+        let Some(languages) = &self.languages else {
+            return self.inner.send(request);
+        };
+        let mut request = request.clone();
+        request
+            .headers
+            .retain(|(name, _)| !name.eq_ignore_ascii_case(HEADER));
+        request
+            .headers
+            .push((String::from(HEADER), languages.clone()));
+
+        let response = self.inner.send(&request);
+        // the copy may contain the payload, see `pwpush_api::send_text_push`.
+        request.body.zeroize();
+        response
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{classify, classify_body, parse, AcceptLanguageTransport, InstanceError};
+    use crate::http::mock::MockTransport;
+    use crate::http::{Method, PPCRequest, Transport};
+
+    #[test]
+    fn messages_are_recognized_in_other_languages() {
+        assert_eq!(
+            classify("This push has a passphrase that was incorrect or not provided."),
+            Some(InstanceError::PassphraseRequired)
+        );
+        assert_eq!(
+            classify("Ce push a une phrase secrète incorrecte ou non fournie."),
+            Some(InstanceError::PassphraseRequired)
+        );
+        assert_eq!(
+            classify_body(
+                r#"{"error":"Sie müssen sich anmelden oder registrieren, bevor Sie fortfahren können."}"#
+            ),
+            Some(InstanceError::Unauthenticated)
+        );
+        assert_eq!(classify_body(r#"{"error":"not found"}"#), None);
+        assert_eq!(classify_body("<html>passphrase</html>"), None);
+    }
+
+    #[test]
+    fn sets_accept_language() {
+        let transport = AcceptLanguageTransport::new(
+            MockTransport::new().respond(200, "{}"),
+            Some(parse(" fr-CH, fr;q=0.9").unwrap()),
+        );
+        transport
+            .send(&PPCRequest::new(
+                Method::Get,
+                String::from("https://pwpush.com/"),
+            ))
+            .unwrap();
+
+        assert_eq!(
+            transport.inner.requests()[0].headers,
+            vec![(
+                String::from("Accept-Language"),
+                String::from("fr-CH, fr;q=0.9")
+            )]
+        );
+        assert!(parse("de\r\nX-Evil: 1").is_err());
+    }
+}
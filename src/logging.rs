@@ -0,0 +1,502 @@
+//! Setup of the logging backend.
+//!
+//! Diagnostics are produced with the macros and spans of the tracing crate
+//! and directed to stderr, so they never mix with the actual command output
+//! on stdout. With `--log-file`, logs are appended to a file instead, and only
+//! errors are additionally shown on stderr. This keeps stderr clean when the
+//! CLI runs under cron or systemd timers. The log file is rotated on startup
+//! once it exceeds `MAX_LOG_FILE_SIZE`, keeping `ROTATED_LOG_FILES` old files
+//! with the suffixes `.1`, `.2`, ...
+//!
+//! Two formats are available:
+//!   - `text` (default): human-readable lines with timestamp, level and the
+//!     trace ID of the invocation, see `trace`
+//!   - `json`: one JSON object per line, for log aggregation in automated
+//!     environments
+//!
+//! Each command runs in a `command` span, and each request to an instance in
+//! a `request` span within it, see `http`. JSON lines contain a timestamp, the
+//! level, the module that logged the message, the trace ID of the invocation,
+//! see `trace`, the message itself, the names of the enclosing spans, and the
+//! fields of these spans and of the log call, e.g. the `request_id` of API
+//! requests:
+//!
+//! ```json
+//! {"timestamp":"2023-12-01T10:15:23.121Z","level":"DEBUG","target":"pwpush_cli::http","trace_id":"3f9a0c1e","message":"received response","spans":["command","request"],"command":"push","request_id":1,"method":"POST","url":"https://pwpush.com/p.json","status":201}
+//! ```
+//!
+//! Fields are redacted by their name, see `redact::log_field`, so secrets
+//! cannot end up in logs through a span.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::{Local, SecondsFormat, Utc};
+use serde_json::{Map, Value as JsonValue};
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::span::{Attributes, Id, Record};
+use tracing::{debug, Event, Level, Metadata, Subscriber};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{Layer, Registry};
+
+use crate::args::{LogFormat, LogVerbosity, PPCArgs};
+use crate::{redact, trace};
+
+/// Only log messages from this crate are emitted. Dependencies like reqwest
+/// are rather chatty on the debug level, and their output would not help users
+/// of the CLI.
+const CRATE_TARGET: &str = env!("CARGO_CRATE_NAME");
+
+/// Size in bytes after which the log file is rotated.
+const MAX_LOG_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Number of rotated log files that are kept next to the current one.
+const ROTATED_LOG_FILES: usize = 3;
+
+pub fn initialize(args: &PPCArgs) {
+    let level = level_filter(args.log_verbosity);
+    let result = match &args.log_file {
+        None => install(PPCLayer {
+            level,
+            format: args.log_format,
+            file: None,
+        }),
+        Some(path) => open_log_file(path).and_then(|file| {
+            install(PPCLayer {
+                level,
+                format: args.log_format,
+                file: Some(Mutex::new(file)),
+            })
+        }),
+    };
+
+    if let Err(e) = result {
+        eprintln!(
+            "error initializing logging backend: {}\nOperation will \
+             continue normally, but logging output may not be \
+             available.",
+            e
+        );
+        return;
+    }
+
+    debug!("logging framework set up");
+}
+
+fn install(layer: PPCLayer) -> Result<(), String> {
+    tracing::subscriber::set_global_default(Registry::default().with(layer))
+        .map_err(|e| format!("{:?}", e))
+}
+
+/// Rotate the log file if needed, and open it for appending.
+fn open_log_file(path: &Path) -> Result<File, String> {
+    rotate(path, MAX_LOG_FILE_SIZE, ROTATED_LOG_FILES)
+        .map_err(|e| format!("could not rotate log file {}: {}", path.display(), e))?;
+
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("could not open log file {}: {}", path.display(), e))
+}
+
+/// Move `path` to `path.1` if it is larger than `max_size`, shifting already
+/// rotated files by one and dropping the oldest one.
+fn rotate(path: &Path, max_size: u64, keep: usize) -> std::io::Result<()> {
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.len() > max_size => {}
+        Ok(_) => return Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    }
+
+    let rotated = |n: usize| -> PathBuf {
+        let mut name = path.as_os_str().to_os_string();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    };
+
+    for n in (1..keep).rev() {
+        if rotated(n).exists() {
+            fs::rename(rotated(n), rotated(n + 1))?;
+        }
+    }
+
+    if keep > 0 {
+        fs::rename(path, rotated(1))
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+fn level_filter(verbosity: LogVerbosity) -> LevelFilter {
+    match verbosity {
+        LogVerbosity::Error => LevelFilter::ERROR,
+        LogVerbosity::Warn => LevelFilter::WARN,
+        LogVerbosity::Info => LevelFilter::INFO,
+        LogVerbosity::Debug => LevelFilter::DEBUG,
+    }
+}
+
+/// A log event before it is formatted, with the fields of its spans.
+struct Line {
+    level: Level,
+    target: String,
+    message: String,
+
+    /// Names of the enclosing spans, outermost first
+    spans: Vec<&'static str>,
+
+    /// Fields of the spans and the event, inner ones replace outer ones of
+    /// the same name
+    fields: Map<String, JsonValue>,
+}
+
+/// Fields of a span, kept in its extensions until it is closed.
+struct SpanFields(Map<String, JsonValue>);
+
+/// Writes the events of this crate to stderr or the log file. Spans are
+/// always recorded, so that the events of any level have their context.
+struct PPCLayer {
+    level: LevelFilter,
+    format: LogFormat,
+    file: Option<Mutex<File>>,
+}
+
+impl<S> Layer<S> for PPCLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn enabled(&self, metadata: &Metadata<'_>, _: Context<'_, S>) -> bool {
+        metadata.target().starts_with(CRATE_TARGET)
+            && (metadata.is_span() || *metadata.level() <= self.level)
+    }
+
+    fn on_new_span(&self, attributes: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut fields = Map::new();
+        attributes.record(&mut FieldCollector(&mut fields));
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanFields(fields));
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(SpanFields(fields)) = span.extensions_mut().get_mut::<SpanFields>() {
+                values.record(&mut FieldCollector(fields));
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut spans = Vec::new();
+        let mut fields = Map::new();
+        for span in ctx
+            .event_scope(event)
+            .into_iter()
+            .flat_map(|scope| scope.from_root())
+        {
+            spans.push(span.name());
+            if let Some(SpanFields(span_fields)) = span.extensions().get::<SpanFields>() {
+                fields.extend(span_fields.clone());
+            }
+        }
+        event.record(&mut FieldCollector(&mut fields));
+        let message = match fields.remove("message") {
+            Some(JsonValue::String(message)) => message,
+            Some(other) => other.to_string(),
+            None => String::new(),
+        };
+        let line = Line {
+            level: *event.metadata().level(),
+            target: String::from(event.metadata().target()),
+            message,
+            spans,
+            fields,
+        };
+        self.write(&line);
+    }
+}
+
+impl PPCLayer {
+    fn write(&self, line: &Line) {
+        let line_text = match self.format {
+            LogFormat::Text => format_text(
+                line,
+                &Local::now().to_rfc3339_opts(SecondsFormat::Secs, false),
+                trace::id(),
+            ),
+            LogFormat::Json => format_json(
+                line,
+                &Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+                trace::id(),
+            ),
+        };
+
+        // there is nothing sensible to do if the log output is gone, so write
+        // errors are ignored.
+        match &self.file {
+            Some(file) => {
+                if let Ok(mut file) = file.lock() {
+                    let _ = writeln!(file, "{}", line_text);
+                }
+                if line.level == Level::ERROR {
+                    let _ = writeln!(std::io::stderr().lock(), "{}", line_text);
+                }
+            }
+            None => {
+                let _ = writeln!(std::io::stderr().lock(), "{}", line_text);
+            }
+        }
+    }
+}
+
+/// Render a log event as a human-readable line. Fields are left out, they
+/// are for the JSON format, but the trace ID is kept to find the requests in
+/// the logs of the instance.
+fn format_text(line: &Line, timestamp: &str, trace_id: &str) -> String {
+    format!(
+        "{} - {} - {} - {}",
+        timestamp, line.level, trace_id, line.message
+    )
+}
+
+/// Render a log event as a single line of JSON.
+fn format_json(line: &Line, timestamp: &str, trace_id: &str) -> String {
+    let mut fields = Map::new();
+    fields.insert(String::from("timestamp"), JsonValue::from(timestamp));
+    fields.insert(String::from("level"), JsonValue::from(line.level.as_str()));
+    fields.insert(
+        String::from("target"),
+        JsonValue::from(line.target.as_str()),
+    );
+    fields.insert(String::from("trace_id"), JsonValue::from(trace_id));
+    fields.insert(
+        String::from("message"),
+        JsonValue::from(line.message.as_str()),
+    );
+    if !line.spans.is_empty() {
+        fields.insert(String::from("spans"), JsonValue::from(line.spans.clone()));
+    }
+    for (name, value) in &line.fields {
+        fields.entry(name.clone()).or_insert_with(|| value.clone());
+    }
+
+    JsonValue::Object(fields).to_string()
+}
+
+/// Copies the fields of events and spans into a JSON object, redacted by
+/// their name. Numbers and bools keep their type, everything else is
+/// rendered as string.
+struct FieldCollector<'a>(&'a mut Map<String, JsonValue>);
+
+impl FieldCollector<'_> {
+    fn insert(&mut self, field: &Field, value: JsonValue) {
+        let value = if redact::is_secret_log_field(field.name()) {
+            JsonValue::from(redact::REDACTED)
+        } else {
+            value
+        };
+        self.0.insert(String::from(field.name()), value);
+    }
+}
+
+impl Visit for FieldCollector<'_> {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, JsonValue::from(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, JsonValue::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, JsonValue::from(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(
+            field,
+            JsonValue::from(redact::log_field(field.name(), value)),
+        );
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        let value = format!("{:?}", value);
+        self.insert(
+            field,
+            JsonValue::from(redact::log_field(field.name(), &value)),
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::sync::Mutex;
+
+    use clap::Parser;
+    use serde_json::{json, Map, Value};
+    use tracing::{debug, debug_span, Level};
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::Registry;
+
+    use super::{format_json, format_text, rotate, Line, PPCLayer};
+    use crate::api_body;
+    use crate::args::{LogFormat, PPCAction, PPCArgs, PPCObject};
+
+    fn line(level: Level, message: &str, fields: Map<String, Value>) -> Line {
+        Line {
+            level,
+            target: String::from("pwpush_cli::http"),
+            message: String::from(message),
+            spans: Vec::new(),
+            fields,
+        }
+    }
+
+    #[test]
+    fn format_line_as_json() {
+        let mut line = line(
+            Level::DEBUG,
+            "sending POST request",
+            Map::from_iter([(String::from("request_id"), json!(3))]),
+        );
+        line.spans = vec!["command", "request"];
+
+        let actual = format_json(&line, "2023-12-01T10:15:23.121Z", "3f9a0c1e");
+        let expected = r#"{"timestamp":"2023-12-01T10:15:23.121Z","level":"DEBUG","target":"pwpush_cli::http","trace_id":"3f9a0c1e","message":"sending POST request","spans":["command","request"],"request_id":3}"#;
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn format_line_escapes_message() {
+        let line = line(Level::WARN, "quote \" and\nnewline", Map::new());
+
+        let actual = format_json(&line, "t", "0");
+        let expected = r#"{"timestamp":"t","level":"WARN","target":"pwpush_cli::http","trace_id":"0","message":"quote \" and\nnewline"}"#;
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn format_line_as_text() {
+        let line = line(Level::INFO, "starting application", Map::new());
+
+        let actual = format_text(&line, "2023-12-01T10:15:23+00:00", "3f9a0c1e");
+        let expected = "2023-12-01T10:15:23+00:00 - INFO - 3f9a0c1e - starting application";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn events_carry_redacted_span_fields() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pwpush.log");
+        let layer = PPCLayer {
+            level: Level::DEBUG.into(),
+            format: LogFormat::Json,
+            file: Some(Mutex::new(fs::File::create(&path).unwrap())),
+        };
+
+        tracing::subscriber::with_default(Registry::default().with(layer), || {
+            let _command = debug_span!("command", command = "push").entered();
+            let _request = debug_span!(
+                "request",
+                request_id = 1,
+                url = "https://pwpush.com/p/abc.json?passphrase=s3cret",
+                passphrase = "s3cret",
+            )
+            .entered();
+            debug!(status = 200, "received response");
+        });
+
+        let content = fs::read_to_string(&path).unwrap();
+        let logged = serde_json::from_str::<Value>(content.trim()).unwrap();
+        assert_eq!(logged["message"], "received response");
+        assert_eq!(logged["spans"], json!(["command", "request"]));
+        assert_eq!(logged["command"], "push");
+        assert_eq!(logged["request_id"], 1);
+        assert_eq!(logged["status"], 200);
+        assert_eq!(
+            logged["url"],
+            "https://pwpush.com/p/abc.json?passphrase=REDACTED"
+        );
+        assert_eq!(logged["passphrase"], "REDACTED");
+        assert!(!content.contains("s3cret"));
+    }
+
+    #[test]
+    fn push_bodies_are_logged_redacted() {
+        let args = PPCArgs::parse_from([
+            "pwpush-cli",
+            "push",
+            "text",
+            "s3cretpayload",
+            "--passphrase",
+            "hunter2xyzQ",
+        ]);
+        let PPCAction::Push(PPCObject::Text(ppc_text)) = &args.action else {
+            panic!("not a text push");
+        };
+
+        for format in [LogFormat::Text, LogFormat::Json] {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("pwpush.log");
+            let layer = PPCLayer {
+                level: Level::DEBUG.into(),
+                format,
+                file: Some(Mutex::new(fs::File::create(&path).unwrap())),
+            };
+
+            tracing::subscriber::with_default(Registry::default().with(layer), || {
+                api_body::push_form(ppc_text);
+            });
+
+            let content = fs::read_to_string(&path).unwrap();
+            assert!(content.contains("final body string"));
+            assert!(!content.contains("s3cretpayload"));
+            assert!(!content.contains("hunter2xyzQ"));
+        }
+    }
+
+    #[test]
+    fn rotate_keeps_small_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pwpush.log");
+        fs::write(&path, "small").unwrap();
+
+        rotate(&path, 10, 2).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "small");
+    }
+
+    #[test]
+    fn rotate_shifts_old_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pwpush.log");
+        let rotated = |n: usize| dir.path().join(format!("pwpush.log.{}", n));
+        fs::write(&path, "current content").unwrap();
+        fs::write(rotated(1), "first").unwrap();
+        fs::write(rotated(2), "second").unwrap();
+
+        rotate(&path, 10, 2).unwrap();
+
+        assert!(!path.exists());
+        assert_eq!(fs::read_to_string(rotated(1)).unwrap(), "current content");
+        assert_eq!(fs::read_to_string(rotated(2)).unwrap(), "first");
+        assert!(!rotated(3).exists());
+    }
+
+    #[test]
+    fn rotate_ignores_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+
+        assert!(rotate(&dir.path().join("pwpush.log"), 10, 2).is_ok());
+    }
+}
@@ -0,0 +1,151 @@
+//! Instances with logins disabled.
+//!
+//! Self-hosted instances can disable logins, so they only offer anonymous
+//! pushes. Everything that belongs to an account is missing then: the lists
+//! of pushes, audit logs, and notes, which only the account that created a
+//! push can see. The API does not tell about it, and the failing requests
+//! answer with errors that point in the wrong direction, like a missing page
+//! or invalid credentials.
+//!
+//! So the sign in page is probed, see `pwpush_api::logins_enabled`, but only
+//! where it matters, to not cost a request on every run:
+//!   - when a command that needs an account fails, `explain` replaces the
+//!     error by one that names the reason
+//!   - when an anonymous push has a note, `check_note` leaves out the note with
+//!     a warning, as the instance would not keep it anyway
+//!
+//! If the probe fails itself, the original error or the note are kept.
+
+use tracing::{debug, warn};
+
+use crate::args::PPCAction;
+use crate::errors::{PPCError, EXIT_INTERRUPTED};
+use crate::fail_on;
+use crate::http::Transport;
+use crate::i18n;
+use crate::instance::Instance;
+use crate::pwpush_api;
+
+/// Whether the command works on pushes of an account.
+fn needs_account(action: &PPCAction) -> bool {
+    match action {
+        PPCAction::Info(_)
+        | PPCAction::List(_)
+        | PPCAction::Audit(_)
+        | PPCAction::Export(_)
+        | PPCAction::Account
+        | PPCAction::Summary
+        | PPCAction::Remind(_) => true,
+        #[cfg(feature = "tui")]
+        PPCAction::Browse(_) => true,
+        _ => false,
+    }
+}
+
+/// The error of a failed command, or, if the command needs an account and
+/// the instance has logins disabled, an error that says so.
+pub fn explain(
+    transport: &dyn Transport,
+    instance: &Instance,
+    action: &PPCAction,
+    error: PPCError,
+) -> PPCError {
+    if !needs_account(action) || error.code() == EXIT_INTERRUPTED {
+        return error;
+    }
+    match pwpush_api::logins_enabled(transport, instance) {
+        Ok(false) => {
+            debug!("the command failed with: {}", error);
+            PPCError::from(format!(
+                "{} has logins disabled, and this command needs an account",
+                instance.base_url()
+            ))
+            .with_hint(String::from(
+                "only anonymous pushes are possible on this instance, use a profile of an instance with accounts for the rest",
+            ))
+        }
+        Ok(true) => error,
+        Err(e) => {
+            debug!("could not check whether logins are enabled: {}", e);
+            error
+        }
+    }
+}
+
+/// Leave out the note of an anonymous push if the instance has logins
+/// disabled, with a warning.
+pub fn check_note(transport: &dyn Transport, instance: &Instance, note: &mut Option<String>) {
+    if note.is_none() || instance.token.is_some() {
+        return;
+    }
+    match pwpush_api::logins_enabled(transport, instance) {
+        Ok(false) => {
+            warn!(
+                "{}",
+                i18n::format(
+                    "push-note-left-out",
+                    &[("url", instance.base_url().to_string().into())]
+                )
+            );
+            fail_on::soft_failure();
+            *note = None;
+        }
+        Ok(true) => {}
+        Err(e) => debug!("could not check whether logins are enabled: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+
+    use super::{check_note, explain};
+    use crate::args::PPCArgs;
+    use crate::config::PPCConfig;
+    use crate::errors::PPCError;
+    use crate::http::mock::MockTransport;
+    use crate::instance::Instance;
+
+    fn instance() -> Instance {
+        let args = PPCArgs::parse_from(["pwpush-cli", "summary"]);
+        Instance::resolve(&args, &PPCConfig::default()).unwrap()
+    }
+
+    #[test]
+    fn commands_with_accounts_explain_disabled_logins() {
+        let summary = PPCArgs::parse_from(["pwpush-cli", "summary"]).action;
+        let transport = MockTransport::new().respond(404, "").respond(200, "<html>");
+
+        let error = explain(
+            &transport,
+            &instance(),
+            &summary,
+            PPCError::from("not found"),
+        );
+        assert!(error.to_string().contains("has logins disabled"));
+        let error = explain(
+            &transport,
+            &instance(),
+            &summary,
+            PPCError::from("not found"),
+        );
+        assert_eq!(error.to_string(), "not found");
+        assert!(transport.requests()[0].url.ends_with("/users/sign_in"));
+
+        let paths = PPCArgs::parse_from(["pwpush-cli", "version"]).action;
+        explain(&transport, &instance(), &paths, PPCError::from("not found"));
+        assert_eq!(transport.requests().len(), 2);
+    }
+
+    #[test]
+    fn notes_are_left_out_without_logins() {
+        let transport = MockTransport::new().respond(404, "");
+        let mut note = Some(String::from("db"));
+        check_note(&transport, &instance(), &mut note);
+        assert_eq!(note, None);
+
+        // nothing to check without a note.
+        check_note(&transport, &instance(), &mut note);
+        assert_eq!(transport.requests().len(), 1);
+    }
+}
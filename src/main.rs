@@ -1,129 +1,509 @@
-//! **Yet another CLI for Password Pusher**
-//!
-//! Public instance available at <https://pwpush.com>
-//!
-//! ## CLI interface design
-//!
-//! The CLI interface needs to provide an ergonomic interface for interactive
-//! use as well as in non-interactive environments. This holds true both for
-//! input, control and configuration, and output.
-//!
-//! The interface is structured into subcommands that give a verbose interface
-//! with easy-to-remember syntax. The `--help` switch must always be availble.
-//! The current subcommands resemble the API quite closely, but more abstract
-//! commands are planned in order adjust to actual use cases like searching
-//! or bulk publishing. These advanced commands will rely on the functionality
-//! of the more basic subcommands, and exporting the basic ones does not hurt
-//! the user experience.
-//!
-//! Due to the design of the API we need to specify the object under operation
-//! explicitly with each api call. This results in all of the subcommands being
-//! divided into tree sub-subcommands, one for passwords (text), files, and urls
-//! respectively.
-//!
-//! Basic subcommands:
-//!   - push -> used to publish a new secretcalls the POST endpoint
-//!   - expire
-//!   - info
-//!   - preview
-//!   - audit
-//!   - list
-//!     - active
-//!     - expired
-//!
-//! The application honors a strict separation of error messages to stderr and
-//! normal output to stdout.
-//!
-//! For output in a machine-readable format, JSON output is planned but not
-//! available at the moment.
-//!
-//! ## API description
-//!
-//! See <https://pwpush.com/api> for publicly available api documentation.
-//!
-//! In essence, the API can be divided into three almost identical sections.
-//! These are management for file pushes, text pushes and URL pushes. At the
-//! moment, following endpoints are defined for each of these:
-//!   - GET :url_token
-//!   - POST
-//!   - GET :url_token/preview
-//!   - GET :url_token/audit
-//!   - DELETE :url_token
-//!   - GET active
-//!   - GET expired
-//!
-//! Each of the endpoints (except the POST endpoint) is prefixed with a single
-//! letter that resembles the type of object the endpoint should operate on:
-//!   - file pushes -> 'f'
-//!   - text pushes -> 'p'
-//!   - url pushes -> 'r'
-//!
-//! Instead of HTTP headers, the API currently relies on a '.json' suffix in
-//! order to deliver a JSON response instead of a web page. There is no general
-//! api prefix, no api versioning, and the api endpoints reside outside of the
-//! localization of 'Password Pusher'. So a final API URL to retrieve all active
-//! file pushes in the public pwpush.com instance looks like this:
-//! `https://pwpush.com/f/active.json`
-//!
-//! The API supports authentication in the form of authentication tokens that
-//! are bound to a specific user with a login. Authenticated API requests need
-//! two special HTTP headers:
-//!   - `X-User-Email`: email of the account that the token was generated from
-//!   - `X-User-Token`: token out of the accounts token view
-
-mod args;
-mod errors;
-mod pwpush_api;
+//! The `pwpush-cli` binary. It only parses the command line and runs the
+//! commands, see the library for everything else.
 
-use clap::Parser;
-use errors::PPCError;
-use log::{debug, error, info};
+use std::process::ExitCode;
 
-use crate::args::*;
+use clap::Parser;
+use pwpush_cli::args::*;
+use pwpush_cli::errors::PPCError;
+use pwpush_cli::instance::Instance;
+use pwpush_cli::*;
+use secrecy::ExposeSecret;
+use tracing::{info, info_span, warn};
 
-fn main() {
-    let args = PPCArgs::parse();
+fn main() -> ExitCode {
+    let cmdline = options_file::expand(std::env::args_os().collect()).and_then(aliases::expand);
+    let cmdline = match cmdline {
+        Ok(cmdline) => cmdline,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::from(2);
+        }
+    };
+    let args = match stdin_json::args(&cmdline) {
+        None => PPCArgs::parse_from(&cmdline),
+        Some(Ok(args)) => args,
+        Some(Err(e)) => {
+            stdin_json::print_error(&e, 2, None);
+            return ExitCode::from(2);
+        }
+    };
 
-    initialize_logging(&args);
+    logging::initialize(&args);
+    i18n::init(&args);
+    terminal::enable_ansi();
+    signals::install();
 
+    // all diagnostics of the run are correlated by this span, see `logging`.
+    let _command = info_span!("command", command = command_name(&args.action)).entered();
     info!("starting application");
 
-    let res = run(&args);
+    // paths and doctor help to find a broken configuration file, so they must
+    // not load it, and the schema does not depend on it.
+    let res = render::check(&args)
+        .and_then(|()| jobs::check(&args))
+        .and_then(|()| match args.action {
+            PPCAction::Paths => render::from_args(&args)
+                .render(&paths::run(&args))
+                .map(|()| ExitCode::SUCCESS),
+            // a broken configuration is reported by doctor, the extra headers
+            // then only go to the instance of the command line.
+            PPCAction::Doctor => build_transport(
+                &args,
+                &config::load(args.config_file.as_deref()).unwrap_or_default(),
+            )
+            .and_then(|transport| doctor::run(transport.as_ref(), &args))
+            .map(|()| ExitCode::SUCCESS),
+            PPCAction::Schema => output::run().map(|()| ExitCode::SUCCESS),
+            _ => config::load(args.config_file.as_deref()).and_then(|config| {
+                build_transport(&args, &config)
+                    .and_then(|transport| run(transport.as_ref(), &args, &config))
+            }),
+        });
 
-    if let Err(e) = res {
-        error!("task could not complete sucessfully: {}", e);
-    } else {
-        info!("application terminated normally");
+    match fail_on::apply(args.fail_on, res) {
+        Ok(code) => {
+            info!("application terminated normally");
+            code
+        }
+        Err(e) => {
+            report::print(&args, &e);
+            if args.stdin_json {
+                stdin_json::print_error(&e, e.code(), args.request_id.then(trace::id));
+            }
+            e.exit_code()
+        }
     }
 }
 
-fn run(args: &PPCArgs) -> Result<(), PPCError> {
+/// Run the requested action and determine the exit code. Most actions only
+/// fail or succeed, but some report their result in the exit code, e.g. for
+/// use in cron jobs.
+fn run(
+    transport: &dyn http::Transport,
+    args: &PPCArgs,
+    config: &config::PPCConfig,
+) -> Result<ExitCode, PPCError> {
+    argv_secrets::check(args, config)?;
+    let mut instance = Instance::resolve(args, config)?;
+    // presets and the SMTP password only touch local files, and must keep
+    // working without network.
+    if !is_local(&args.action) {
+        config.policy.check_instance(&instance)?;
+        instance.detect_auth_scheme(transport);
+    }
+
+    if args.raw {
+        return raw::run(transport, &instance, &args.action).map(|()| ExitCode::SUCCESS);
+    }
+
     match &args.action {
+        PPCAction::Remind(ppc_remind) => remind::run(transport, &instance, args, ppc_remind),
+        action => {
+            run_action(transport, &instance, args, config, action).map(|()| ExitCode::SUCCESS)
+        }
+    }
+    .map_err(|e| logins::explain(transport, &instance, &args.action, e))
+}
+
+fn is_local(action: &PPCAction) -> bool {
+    match action {
+        PPCAction::Preset(_) | PPCAction::Auth(_) | PPCAction::Config(_) => true,
+        PPCAction::Bulk(BulkCommand::List | BulkCommand::Discard { .. }) => true,
+        #[cfg(feature = "email")]
+        PPCAction::Smtp(_) => true,
+        _ => false,
+    }
+}
+
+/// Name of the command for logs, as given on the command line.
+fn command_name(action: &PPCAction) -> &'static str {
+    match action {
+        PPCAction::Push(_) => "push",
+        PPCAction::Get(_) => "get",
+        PPCAction::Expire(_) => "expire",
+        PPCAction::Info(_) => "info",
+        PPCAction::List(_) => "list",
+        #[cfg(feature = "tui")]
+        PPCAction::Browse(_) => "browse",
+        PPCAction::Audit(_) => "audit",
+        PPCAction::Export(_) => "export",
+        PPCAction::Account => "account",
+        PPCAction::Summary => "summary",
+        PPCAction::Remind(_) => "remind",
+        PPCAction::Import(_) => "import",
+        PPCAction::Copy(_) => "copy",
+        PPCAction::Rotate(_) => "rotate",
+        PPCAction::Clone(_) => "clone",
+        PPCAction::Diff(_) => "diff",
+        PPCAction::Update(_) => "update",
+        PPCAction::Queue(_) => "queue",
+        PPCAction::Bulk(_) => "bulk",
+        PPCAction::History(_) => "history",
+        #[cfg(feature = "email")]
+        PPCAction::Smtp(_) => "smtp",
+        PPCAction::Version => "version",
+        PPCAction::Preset(_) => "preset",
+        PPCAction::Auth(_) => "auth",
+        PPCAction::Config(_) => "config",
+        PPCAction::Paths => "paths",
+        PPCAction::Doctor => "doctor",
+        PPCAction::Schema => "schema",
+        PPCAction::Wizard => "wizard",
+        PPCAction::Helper(_) => "helper",
+        #[cfg(feature = "serve")]
+        PPCAction::Serve(_) => "serve",
+    }
+}
+
+fn run_action(
+    transport: &dyn http::Transport,
+    instance: &Instance,
+    args: &PPCArgs,
+    config: &config::PPCConfig,
+    action: &PPCAction,
+) -> Result<(), PPCError> {
+    match action {
         PPCAction::Push(push_command) => match push_command {
-            PPCObject::Text(ppc_text) => pwpush_api::push_text(args, ppc_text),
-            PPCObject::File(_) => todo!(),
-            PPCObject::URL(_) => todo!(),
+            PPCObject::Text(ppc_text) => push_text(transport, instance, args, config, ppc_text),
+            PPCObject::File(ppc_file) => push_file(transport, instance, args, config, ppc_file),
+            PPCObject::Url(_) => todo!(),
+            PPCObject::Wifi(ppc_wifi) => {
+                let ppc_text = wifi::text(ppc_wifi, config)?;
+                push_text(transport, instance, args, config, &ppc_text)
+            }
+            PPCObject::Totp(ppc_totp) => {
+                let ppc_text = totp::text(ppc_totp, config)?;
+                push_text(transport, instance, args, config, &ppc_text)
+            }
+        },
+        PPCAction::Get(ppc_get) => get::run(transport, instance, args, ppc_get),
+        PPCAction::Expire(PPCExpire::Bulk(ppc_bulk)) => {
+            bulk::expire(transport, instance, args, config, ppc_bulk)
+        }
+        PPCAction::Expire(PPCExpire::Push(object)) => {
+            expire::run(transport, instance, args, config, object)
+        }
+        PPCAction::Info(object) => info::info(transport, instance, args, object),
+        PPCAction::List(ppc_list) => info::list(transport, instance, args, ppc_list),
+        #[cfg(feature = "tui")]
+        PPCAction::Browse(ppc_browse) => browse::run(transport, instance, args, ppc_browse),
+        PPCAction::Audit(ppc_audit) => audit::run(transport, instance, args, ppc_audit),
+        PPCAction::Export(ppc_export) => inventory::export(transport, instance, ppc_export),
+        PPCAction::Account => render::from_args(args).render(&account::check(transport, instance)?),
+        PPCAction::Summary => render::from_args(args).render(&summary::run(transport, instance)?),
+        PPCAction::Import(ppc_import) => {
+            inventory::import(transport, instance, args, config, ppc_import)
+        }
+        PPCAction::Copy(ppc_copy) => repush::copy(transport, instance, args, config, ppc_copy),
+        PPCAction::Rotate(ppc_rotate) => {
+            repush::rotate(transport, instance, args, config, ppc_rotate)
+        }
+        PPCAction::Clone(ppc_clone) => repush::clone(transport, instance, args, config, ppc_clone),
+        PPCAction::Diff(ppc_diff) => diff::run(transport, instance, args, ppc_diff),
+        PPCAction::Update(ppc_update) => update::run(transport, instance, args, ppc_update),
+        PPCAction::Queue(queue_command) => queue::run(transport, args, queue_command),
+        PPCAction::Bulk(bulk_command) => jobs::run(transport, instance, args, config, bulk_command),
+        PPCAction::History(history_command) => history::run(args, config, history_command),
+        PPCAction::Version => {
+            render::from_args(args).render(&version::run(transport, instance, args))
+        }
+        PPCAction::Preset(preset_command) => presets::run(args, config, preset_command),
+        PPCAction::Auth(auth_command) => credentials::run(args, config, auth_command),
+        PPCAction::Config(config_command) => {
+            render::from_args(args).render(&settings::run(args, config, config_command)?)
+        }
+        PPCAction::Paths | PPCAction::Doctor | PPCAction::Schema => {
+            unreachable!("runs before the configuration is loaded")
+        }
+        #[cfg(feature = "email")]
+        PPCAction::Smtp(smtp_command) => email::run(args, config, smtp_command),
+        PPCAction::Wizard => match wizard::run(config)? {
+            Some(ppc_text) => push_text(transport, instance, args, config, &ppc_text),
+            None => {
+                eprintln!("{}", i18n::text("push-aborted"));
+                Ok(())
+            }
         },
-        args::PPCAction::Expire(_) => todo!(),
+        PPCAction::Helper(ppc_helper) => helper::run(transport, instance, config, ppc_helper),
+        #[cfg(feature = "serve")]
+        PPCAction::Serve(ppc_serve) => serve::run(transport, instance, config, ppc_serve),
+        PPCAction::Remind(_) => unreachable!("remind is handled in run"),
     }
 }
 
-fn initialize_logging(args: &PPCArgs) {
-    // log output with macros from the log crate are directed to stderr.
-    if let Err(e) = stderrlog::new()
-        .module(module_path!())
-        .timestamp(stderrlog::Timestamp::Second)
-        .verbosity(args.log_verbosity as usize)
-        .init()
-    {
-        eprintln!(
-            "error initializing logging backend: {:?}\nOperation will \
-             continue normally, but logging output may not be \
-             available.",
-            e
-        );
-        return;
-    }
-
-    debug!("logging framework set up");
+fn push_text(
+    transport: &dyn http::Transport,
+    instance: &Instance,
+    args: &PPCArgs,
+    config: &config::PPCConfig,
+    ppc_text: &PPCText,
+) -> Result<(), PPCError> {
+    let ppc_text = &expire_at::apply(ppc_text, chrono::Utc::now())?;
+    if let Some(key) = &ppc_text.idempotency_key {
+        if let Some(entry) = idempotency::check(instance, key, chrono::Utc::now())? {
+            idempotency::print(args, &entry);
+            return Ok(());
+        }
+    }
+    recipients::check(&ppc_text.recipients)?;
+    if let Some(to) = &ppc_text.email_to {
+        email::check(config, to)?;
+    }
+    let third_party = platform::transport()?;
+    let shortener = ppc_text
+        .shorten
+        .then(|| shortener::Shortener::new(&config.shortener, third_party))
+        .transpose()?;
+    if ppc_text.expire_after_first_view_confirmed {
+        first_view::check(instance)?;
+    }
+    if ppc_text.qr_link {
+        qr::check()?;
+    }
+
+    // payload and passphrase are wrapped in secrets, which wipe their memory
+    // when `merged` goes out of scope.
+    let mut merged = config.apply(ppc_text)?;
+    // generated passphrases are checked as well, as presets may generate
+    // short ones.
+    if let Some(passphrase) = &merged.passphrase {
+        passphrase::check(
+            passphrase.expose_secret(),
+            config.push.warn_weak_passphrase.unwrap_or(true),
+            ppc_text.enforce_strong_passphrase,
+        )?;
+    }
+    let plain = payload::read(ppc_text, config)?;
+    let recent = dedupe::check(ppc_text, &plain, chrono::Utc::now())?;
+    merged.password_payload = Some(payload::prepare(plain.clone(), ppc_text, config)?);
+    merged.note = merged.note.as_deref().map(template::expand);
+    hooks::pre_push(&config.hooks, PushKind::Text)?;
+    if !ppc_text.to_profiles.is_empty() {
+        let pushed = broadcast::run(transport, args, config, &merged, &ppc_text.to_profiles);
+        if pushed.is_ok() {
+            dedupe::record(recent, ppc_text, &plain, chrono::Utc::now());
+        }
+        print_generated_passphrase(ppc_text, &merged);
+        return pushed;
+    }
+    if !ppc_text.recipients.is_empty() {
+        let pushed = recipients::run(transport, instance, args, config, ppc_text, &merged);
+        if pushed.is_ok() {
+            dedupe::record(recent, ppc_text, &plain, chrono::Utc::now());
+        }
+        print_generated_passphrase(ppc_text, &merged);
+        return pushed;
+    }
+    logins::check_note(transport, instance, &mut merged.note);
+    let queue = if ppc_text.queue_on_failure {
+        Some(queue::Queue::open(
+            &queue::default_dir()?,
+            &paths::key_dir()?,
+        )?)
+    } else {
+        None
+    };
+    let pushed = pwpush_api::push_text(transport, instance, &merged, queue.as_ref())?;
+    let mut emailed = Ok(());
+    if let Some(push) = &pushed {
+        dedupe::record(recent, ppc_text, &plain, chrono::Utc::now());
+        let url = share::print(
+            transport,
+            instance,
+            args,
+            PushKind::Text,
+            push,
+            shortener.as_ref(),
+            ppc_text.validate,
+        )?;
+        match &ppc_text.idempotency_key {
+            Some(key) => history::record_with_key(
+                instance,
+                push,
+                merged.note.as_deref(),
+                &merged.tags,
+                &url,
+                Some(key),
+            ),
+            None => history::record(
+                config,
+                instance,
+                push,
+                merged.note.as_deref(),
+                &merged.tags,
+                &url,
+            ),
+        }
+        if ppc_text.qr_link && render::is_plain(args) {
+            warn!("{}", i18n::text("push-qr-link-plain"));
+        } else if ppc_text.qr_link && !args.json_output {
+            if let Err(e) = qr::print(&url) {
+                warn!("{}", e);
+                fail_on::soft_failure();
+            }
+        }
+        hooks::notify_push(third_party, merged.notify_webhook.as_deref(), push, &url);
+        hooks::post_push(&config.hooks, PushKind::Text, push, &url);
+        if let Some(to) = &ppc_text.email_to {
+            emailed = email::send(config, to, push, &url);
+        }
+    }
+
+    print_generated_passphrase(ppc_text, &merged);
+
+    // the link and passphrase are printed first, so they can be shared while
+    // the push is held.
+    if let (true, Some(push)) = (ppc_text.hold, &pushed) {
+        hold::run(transport, instance, ppc_text, push)?;
+    }
+    if let (true, Some(push)) = (ppc_text.expire_after_first_view_confirmed, &pushed) {
+        first_view::run(transport, instance, push)?;
+    }
+
+    // the push is held or watched even if the email failed, as it was
+    // created anyway.
+    emailed
+}
+
+fn push_file(
+    transport: &dyn http::Transport,
+    instance: &Instance,
+    args: &PPCArgs,
+    config: &config::PPCConfig,
+    ppc_file: &PPCFile,
+) -> Result<(), PPCError> {
+    let mut merged = config.apply_file(ppc_file)?;
+    merged.note = merged.note.as_deref().map(template::expand);
+    logins::check_note(transport, instance, &mut merged.note);
+
+    let third_party = platform::transport()?;
+    let shortener = ppc_file
+        .shorten
+        .then(|| shortener::Shortener::new(&config.shortener, third_party))
+        .transpose()?;
+    let archive = merged
+        .dir
+        .as_ref()
+        .map(|_| archive::create(&merged))
+        .transpose()?;
+    hooks::pre_push(&config.hooks, PushKind::File)?;
+    let push = pwpush_api::push_file(transport, instance, &merged, archive.as_ref())?;
+    let url = share::print(
+        transport,
+        instance,
+        args,
+        PushKind::File,
+        &push,
+        shortener.as_ref(),
+        ppc_file.validate,
+    )?;
+    history::record(
+        config,
+        instance,
+        &push,
+        merged.note.as_deref(),
+        &merged.tags,
+        &url,
+    );
+    hooks::post_push(&config.hooks, PushKind::File, &push, &url);
+    Ok(())
+}
+
+/// A passphrase that was generated from a preset or by --paranoid must be
+/// shown, otherwise nobody would be able to open the push. This also applies
+/// to queued pushes.
+fn print_generated_passphrase(ppc_text: &PPCText, merged: &PPCText) {
+    if ppc_text.passphrase.is_none() {
+        if let Some(passphrase) = &merged.passphrase {
+            println!(
+                "{}",
+                i18n::format(
+                    "push-passphrase",
+                    &[("passphrase", passphrase.expose_secret().into())]
+                )
+            );
+        }
+    }
+    if ppc_text.paranoid {
+        eprintln!("{}", paranoid::summary(ppc_text.passphrase.is_none()));
+    }
+}
+
+/// Select the transport for API calls. Usually this is the network, but for
+/// debugging, exchanges can be recorded to or replayed from a directory.
+fn build_transport(
+    args: &PPCArgs,
+    config: &config::PPCConfig,
+) -> Result<Box<dyn http::Transport>, PPCError> {
+    if let Some(dir) = &args.replay_dir {
+        info!("replaying API responses from {}", dir.display());
+        return Ok(Box::new(recording::ReplayTransport::new(dir)?));
+    }
+
+    match &args.unix_socket {
+        #[cfg(all(unix, feature = "unix-socket"))]
+        Some(path) => {
+            info!("sending requests to unix socket {}", path.display());
+            wrap_transport(unix_socket::UnixSocketTransport::new(path), args, config)
+        }
+        #[cfg(not(unix))]
+        Some(_) => Err(PPCError::from(
+            "--unix-socket is only supported on unix systems",
+        )),
+        #[cfg(all(unix, not(feature = "unix-socket")))]
+        Some(_) => Err(PPCError::from(
+            "--unix-socket is not available, as pwpush-cli was built without the unix-socket feature",
+        )),
+        None => wrap_transport(http::ReqwestTransport::without_redirects(), args, config),
+    }
+}
+
+/// Add recording, throttling and caching to a transport that talks to an
+/// instance.
+fn wrap_transport<T: http::Transport + 'static>(
+    transport: T,
+    args: &PPCArgs,
+    config: &config::PPCConfig,
+) -> Result<Box<dyn http::Transport>, PPCError> {
+    // recordings are taken below the throttling, so that they also contain
+    // the responses of rate-limited requests. Extra headers are added below
+    // the recording, as they usually are credentials, and replace the
+    // User-Agent and Accept-Language if they contain them. Redirects are
+    // handled right above the network, so all headers are checked before they
+    // are sent to another address.
+    let transport = redirect::RedirectTransport::new(transport);
+    // errors in the address of the instance are reported when it is resolved.
+    let base_url = Instance::resolve_base_url(args, config).ok();
+    let transport = extra_headers::ExtraHeadersTransport::new(
+        transport,
+        args.headers.clone(),
+        base_url.as_deref(),
+    );
+    let transport = user_agent::UserAgentTransport::new(transport, args.user_agent.as_deref());
+    let transport =
+        localized::AcceptLanguageTransport::new(transport, args.accept_language.clone());
+    let transport = trace::RequestIdTransport::new(transport, args.request_id);
+    let (max_rate, budget) = (args.max_rate, args.retry_budget);
+    let throttled: Box<dyn http::Transport> = match &args.record_dir {
+        Some(dir) => {
+            info!("recording API exchanges to {}", dir.display());
+            let transport = recording::RecordingTransport::new(transport, dir)?;
+            Box::new(throttle::ThrottledTransport::new(
+                transport, max_rate, budget,
+            ))
+        }
+        None => Box::new(throttle::ThrottledTransport::new(
+            transport, max_rate, budget,
+        )),
+    };
+    if args.if_changed {
+        let dir = cache::default_dir()?;
+        return Ok(Box::new(cache::CachingTransport::persistent(
+            throttled,
+            &dir,
+            &paths::key_dir()?,
+        )?));
+    }
+    Ok(Box::new(cache::CachingTransport::new(throttled)))
 }
@@ -0,0 +1,218 @@
+//! Prometheus metrics of the local API, see `serve`.
+//!
+//! `GET /metrics` shows how the API is used in the text format of Prometheus,
+//! so teams that run `serve` next to their tools can scrape and alert on it:
+//!
+//! - `pwpush_serve_requests_total{route,status}` counts the requests by route
+//!   and status code of the answer
+//! - `pwpush_serve_request_duration_seconds{route}` is a histogram of the
+//!   time it took to answer, including the requests to the instance
+//! - `pwpush_serve_pushes_created_total` and `pwpush_serve_expirations_total`
+//!   count the pushes that were created and expired
+//! - `pwpush_serve_failures_total{route}` counts requests that failed on the
+//!   side of the instance, i.e. that were answered with 502
+//! - `pwpush_serve_start_time_seconds` is the Unix time the API started at
+//!
+//! Routes are the paths with URL tokens replaced by `:url_token`, so the
+//! number of series stays small and tokens do not end up in the monitoring
+//! system. Metrics are kept in memory and start from zero on each start.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::Duration;
+
+use chrono::Utc;
+
+/// Value of the `Content-Type` header of `/metrics`.
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Upper bounds of the buckets of the duration histogram, in seconds.
+const BUCKETS: [f64; 9] = [0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Counts of durations per bucket, with the sum for the average.
+#[derive(Debug, Default)]
+struct Histogram {
+    buckets: [u64; BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+}
+
+/// The metrics of a running API.
+#[derive(Debug)]
+pub struct Metrics {
+    started_at: i64,
+    requests: BTreeMap<(&'static str, u16), u64>,
+    durations: BTreeMap<&'static str, Histogram>,
+    failures: BTreeMap<&'static str, u64>,
+    pushes_created: u64,
+    expirations: u64,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self {
+            started_at: Utc::now().timestamp(),
+            requests: BTreeMap::new(),
+            durations: BTreeMap::new(),
+            failures: BTreeMap::new(),
+            pushes_created: 0,
+            expirations: 0,
+        }
+    }
+
+    /// Record an answered request.
+    pub fn observe(&mut self, method: &str, path: &str, status: u16, duration: Duration) {
+        let route = route(path);
+        *self.requests.entry((route, status)).or_default() += 1;
+        self.durations
+            .entry(route)
+            .or_default()
+            .observe(duration.as_secs_f64());
+
+        match (method, route, status) {
+            ("POST", "/push", 201) => self.pushes_created += 1,
+            ("DELETE", "/pushes/:url_token", 200) => self.expirations += 1,
+            (_, _, 502) => *self.failures.entry(route).or_default() += 1,
+            _ => {}
+        }
+    }
+
+    /// The metrics in the text format of Prometheus.
+    pub fn render(&self) -> String {
+        // writing to a String cannot fail, so the results are ignored.
+        let mut out = String::new();
+
+        let name = "pwpush_serve_requests_total";
+        header(&mut out, name, "counter", "Requests to the local API.");
+        for ((route, status), count) in &self.requests {
+            let _ = writeln!(
+                out,
+                "{}{{route=\"{}\",status=\"{}\"}} {}",
+                name, route, status, count
+            );
+        }
+
+        let name = "pwpush_serve_request_duration_seconds";
+        header(&mut out, name, "histogram", "Time to answer requests.");
+        for (route, histogram) in &self.durations {
+            let bounds = BUCKETS.iter().map(|bound| bound.to_string());
+            let counts = histogram.buckets.iter().copied();
+            for (bound, count) in bounds
+                .chain([String::from("+Inf")])
+                .zip(counts.chain([histogram.count]))
+            {
+                let _ = writeln!(
+                    out,
+                    "{}_bucket{{route=\"{}\",le=\"{}\"}} {}",
+                    name, route, bound, count
+                );
+            }
+            let _ = writeln!(out, "{}_sum{{route=\"{}\"}} {}", name, route, histogram.sum);
+            let _ = writeln!(
+                out,
+                "{}_count{{route=\"{}\"}} {}",
+                name, route, histogram.count
+            );
+        }
+
+        let name = "pwpush_serve_failures_total";
+        header(&mut out, name, "counter", "Requests the instance failed.");
+        for (route, count) in &self.failures {
+            let _ = writeln!(out, "{}{{route=\"{}\"}} {}", name, route, count);
+        }
+
+        let name = "pwpush_serve_pushes_created_total";
+        header(&mut out, name, "counter", "Pushes created.");
+        let _ = writeln!(out, "{} {}", name, self.pushes_created);
+
+        let name = "pwpush_serve_expirations_total";
+        header(&mut out, name, "counter", "Pushes expired.");
+        let _ = writeln!(out, "{} {}", name, self.expirations);
+
+        let name = "pwpush_serve_start_time_seconds";
+        header(&mut out, name, "gauge", "Unix time the API was started at.");
+        let _ = writeln!(out, "{} {}", name, self.started_at);
+        out
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}\n# TYPE {} {}", name, help, name, kind);
+}
+
+/// The route of a path, without the query and with URL tokens replaced, see
+/// the module docs.
+fn route(path: &str) -> &'static str {
+    let path = path.split('?').next().unwrap_or_default();
+    let segments = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+    match segments.as_slice() {
+        ["health"] => "/health",
+        ["metrics"] => "/metrics",
+        ["push"] => "/push",
+        ["pushes", "active"] => "/pushes/active",
+        ["pushes", "expired"] => "/pushes/expired",
+        ["pushes", _] => "/pushes/:url_token",
+        _ => "other",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{route, Metrics};
+
+    #[test]
+    fn routes_hide_tokens() {
+        assert_eq!(route("/pushes/abc123"), "/pushes/:url_token");
+        assert_eq!(route("/pushes/active?page=2"), "/pushes/active");
+        assert_eq!(route("/push/"), "/push");
+        assert_eq!(route("/../etc/passwd"), "other");
+    }
+
+    #[test]
+    fn renders_prometheus_text() {
+        let mut metrics = Metrics::new();
+        metrics.observe("POST", "/push", 201, Duration::from_millis(30));
+        metrics.observe("POST", "/push", 502, Duration::from_millis(3));
+        metrics.observe("DELETE", "/pushes/abc", 200, Duration::from_millis(20));
+
+        let text = metrics.render();
+
+        assert!(text.contains("pwpush_serve_requests_total{route=\"/push\",status=\"201\"} 1\n"));
+        assert!(text.contains("pwpush_serve_pushes_created_total 1\n"));
+        assert!(text.contains("pwpush_serve_expirations_total 1\n"));
+        assert!(text.contains("pwpush_serve_failures_total{route=\"/push\"} 1\n"));
+        assert!(text.contains(
+            "pwpush_serve_request_duration_seconds_bucket{route=\"/push\",le=\"0.01\"} 1\n"
+        ));
+        assert!(text.contains(
+            "pwpush_serve_request_duration_seconds_bucket{route=\"/push\",le=\"+Inf\"} 2\n"
+        ));
+        assert!(text.contains(
+            "pwpush_serve_request_duration_seconds_count{route=\"/pushes/:url_token\"} 1\n"
+        ));
+        assert!(!text.contains("abc"));
+    }
+}
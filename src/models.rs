@@ -0,0 +1,283 @@
+//! Typed representations of the JSON responses of the API.
+//!
+//! The API is not formally specified and differs slightly between server
+//! versions, so all fields that are not strictly needed are optional, and
+//! unknown fields are ignored.
+
+use std::fmt;
+use std::net::IpAddr;
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::redact;
+
+/// Metadata of a push as returned by the API, e.g. after creating a push or in
+/// the lists of active and expired pushes. The payload is only part of the
+/// response when a push is retrieved.
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Push {
+    pub url_token: String,
+
+    #[serde(default)]
+    pub created_at: Option<String>,
+
+    #[serde(default)]
+    pub expire_after_days: Option<u64>,
+
+    #[serde(default)]
+    pub expire_after_views: Option<u64>,
+
+    #[serde(default)]
+    pub days_remaining: Option<i64>,
+
+    #[serde(default)]
+    pub views_remaining: Option<i64>,
+
+    #[serde(default)]
+    pub expired: Option<bool>,
+
+    #[serde(default)]
+    pub expired_on: Option<String>,
+
+    /// Set if the push was deleted by the creator or a viewer before it expired
+    #[serde(default)]
+    pub deleted: Option<bool>,
+
+    #[serde(default)]
+    pub deletable_by_viewer: Option<bool>,
+
+    #[serde(default)]
+    pub retrieval_step: Option<bool>,
+
+    #[serde(default)]
+    pub note: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload: Option<String>,
+
+    /// Files of a file push, only part of the response when it is retrieved
+    #[serde(
+        default,
+        deserialize_with = "deserialize_files",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub files: Option<Vec<PushFile>>,
+}
+
+/// The payload is redacted, so pushes can be logged safely.
+impl fmt::Debug for Push {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Push")
+            .field("url_token", &self.url_token)
+            .field("created_at", &self.created_at)
+            .field("expire_after_days", &self.expire_after_days)
+            .field("expire_after_views", &self.expire_after_views)
+            .field("days_remaining", &self.days_remaining)
+            .field("views_remaining", &self.views_remaining)
+            .field("expired", &self.expired)
+            .field("expired_on", &self.expired_on)
+            .field("deleted", &self.deleted)
+            .field("deletable_by_viewer", &self.deletable_by_viewer)
+            .field("retrieval_step", &self.retrieval_step)
+            .field("note", &self.note)
+            .field("payload", &redact::option(&self.payload))
+            .field("files", &self.files)
+            .finish()
+    }
+}
+
+impl Push {
+    /// Number of views so far. The API only reports the limit and the
+    /// remaining views, so this is derived from both. Nonsensical numbers of a
+    /// broken instance saturate rather than overflow.
+    pub fn views(&self) -> Option<i64> {
+        match (self.expire_after_views, self.views_remaining) {
+            (Some(limit), Some(remaining)) => {
+                let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+                Some(limit.saturating_sub(remaining).max(0))
+            }
+            _ => None,
+        }
+    }
+
+    /// The limits the push was created with, e.g. "1 day or 3 views", for
+    /// messages to people.
+    pub fn limits(&self) -> Option<String> {
+        let plural = |count: u64| if count == 1 { "" } else { "s" };
+
+        let mut limits = Vec::new();
+        if let Some(days) = self.expire_after_days {
+            limits.push(format!("{} day{}", days, plural(days)));
+        }
+        if let Some(views) = self.expire_after_views {
+            limits.push(format!("{} view{}", views, plural(views)));
+        }
+        (!limits.is_empty()).then(|| limits.join(" or "))
+    }
+}
+
+/// A file of a file push.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct PushFile {
+    pub filename: String,
+
+    /// Where the file is downloaded from, absolute or relative to the
+    /// instance
+    pub url: String,
+}
+
+/// Instances list the files of a push as an object of file names and URLs,
+/// which older versions encode as a JSON string. Lists of `PushFile`, as in
+/// the JSON output, are accepted as well.
+fn deserialize_files<'de, D>(deserializer: D) -> Result<Option<Vec<PushFile>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::Error;
+    use serde_json::Value;
+
+    fn files(value: Value) -> Result<Option<Vec<PushFile>>, String> {
+        match value {
+            Value::Null => Ok(None),
+            Value::String(text) => {
+                let value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+                files(value)
+            }
+            Value::Object(map) => map
+                .into_iter()
+                .map(|(filename, url)| match url {
+                    Value::String(url) => Ok(PushFile { filename, url }),
+                    _ => Err(format!("URL of file {} is not a string", filename)),
+                })
+                .collect::<Result<_, _>>()
+                .map(Some),
+            value @ Value::Array(_) => serde_json::from_value(value)
+                .map(Some)
+                .map_err(|e| e.to_string()),
+            _ => Err(String::from("files are neither an object nor a list")),
+        }
+    }
+
+    files(Value::deserialize(deserializer)?).map_err(D::Error::custom)
+}
+
+/// Response of the preview endpoint of a push.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Preview {
+    /// The URL to share, as the instance presents it to its users
+    pub url: String,
+}
+
+/// Response of the version endpoint. Only available on newer servers.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ServerVersion {
+    #[serde(default)]
+    pub application_version: Option<String>,
+
+    #[serde(default)]
+    pub api_version: Option<String>,
+
+    #[serde(default)]
+    pub edition: Option<String>,
+}
+
+/// Response of the `audit` endpoint.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct AuditLog {
+    #[serde(default)]
+    pub views: Vec<AuditView>,
+}
+
+/// A single access to a push, as recorded by the instance.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct AuditView {
+    #[serde(default)]
+    pub ip: Option<String>,
+
+    #[serde(default)]
+    pub user_agent: Option<String>,
+
+    #[serde(default)]
+    pub referrer: Option<String>,
+
+    /// False for failed attempts, e.g. with a wrong passphrase
+    #[serde(default)]
+    pub successful: Option<bool>,
+
+    pub created_at: String,
+
+    /// Server versions differ in whether this is reported as number or as
+    /// name, so it is kept as it was received.
+    #[serde(default)]
+    pub kind: Option<serde_json::Value>,
+}
+
+impl AuditView {
+    /// Human-readable name of the kind of access.
+    pub fn kind_name(&self) -> String {
+        match &self.kind {
+            None => String::from("view"),
+            Some(serde_json::Value::Number(n)) if n.as_u64() == Some(0) => String::from("view"),
+            Some(serde_json::Value::Number(n)) if n.as_u64() == Some(1) => {
+                String::from("manual deletion")
+            }
+            Some(serde_json::Value::String(name)) => name.replace('_', " "),
+            Some(other) => other.to_string(),
+        }
+    }
+
+    /// The time of the access, if the instance reported a valid timestamp.
+    pub fn created(&self) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(&self.created_at)
+            .ok()
+            .map(|date| date.with_timezone(&Utc))
+    }
+
+    /// The address of the visitor, if the instance reported a valid one.
+    pub fn ip_addr(&self) -> Option<IpAddr> {
+        self.ip.as_deref()?.parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Push, PushFile};
+
+    #[test]
+    fn debug_redacts_payload() {
+        let push = Push {
+            url_token: String::from("abc"),
+            payload: Some(String::from("secret")),
+            ..Push::default()
+        };
+
+        let debug = format!("{:?}", push);
+
+        assert!(!debug.contains("secret"));
+        assert!(debug.contains("payload: Some(REDACTED)"));
+    }
+
+    #[test]
+    fn files_are_read_in_all_formats() {
+        let expected = Some(vec![PushFile {
+            filename: String::from("report.pdf"),
+            url: String::from("/rails/active_storage/blobs/redirect/x/report.pdf"),
+        }]);
+
+        for files in [
+            r#"{"report.pdf":"/rails/active_storage/blobs/redirect/x/report.pdf"}"#,
+            r#""{\"report.pdf\":\"/rails/active_storage/blobs/redirect/x/report.pdf\"}""#,
+            r#"[{"filename":"report.pdf","url":"/rails/active_storage/blobs/redirect/x/report.pdf"}]"#,
+        ] {
+            let json = format!(r#"{{"url_token":"abc","files":{}}}"#, files);
+            let push = serde_json::from_str::<Push>(&json).unwrap();
+            assert_eq!(push.files, expected, "{}", files);
+        }
+
+        let push = serde_json::from_str::<Push>(r#"{"url_token":"abc"}"#).unwrap();
+        assert_eq!(push.files, None);
+        assert!(serde_json::from_str::<Push>(r#"{"url_token":"abc","files":1}"#).is_err());
+    }
+}
@@ -0,0 +1,331 @@
+//! Multipart bodies for file uploads, streamed from disk.
+//!
+//! File pushes are sent as `multipart/form-data`, with the options as text
+//! fields and each file as a part of its own. Files can be hundreds of
+//! megabytes, so they are never read into memory: `Multipart` only knows the
+//! paths and sizes, and `reader` opens one file after the other while the
+//! transport sends the body, reading it in small chunks. Only the headers of
+//! the parts are kept in memory.
+//!
+//! The sizes are taken when the body is built, so the exact length of the body
+//! is known in advance and sent as `Content-Length`, which instances behind
+//! proxies that refuse chunked uploads need. A file that changes its size in
+//! the meantime fails the upload instead of sending something else.
+//!
+//! Each send opens the files again, so retried requests upload them from the
+//! start.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Cursor, Read};
+use std::path::{Path, PathBuf};
+
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+
+use crate::errors::PPCError;
+use crate::redact;
+
+/// A file that is uploaded as part of the body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilePart {
+    /// Name of the form field, e.g. `file_push[files][]`
+    pub name: String,
+    pub path: PathBuf,
+    /// Name of the file as the instance shows it
+    pub filename: String,
+    pub size: u64,
+}
+
+/// A `multipart/form-data` body of text fields and files.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Multipart {
+    boundary: String,
+    fields: Vec<(String, String)>,
+    files: Vec<FilePart>,
+}
+
+impl Multipart {
+    pub fn new() -> Self {
+        let boundary = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect::<String>();
+        Self {
+            boundary: format!("pwpush-cli-{}", boundary),
+            fields: Vec::new(),
+            files: Vec::new(),
+        }
+    }
+
+    /// Add a text field.
+    pub fn text(mut self, name: &str, value: &str) -> Self {
+        self.fields.push((String::from(name), String::from(value)));
+        self
+    }
+
+    /// Add a file, which is read when the body is sent. `filename` is the name
+    /// the instance shows, which defaults to the name of the file on disk.
+    pub fn file(
+        mut self,
+        name: &str,
+        path: &Path,
+        filename: Option<&str>,
+    ) -> Result<Self, PPCError> {
+        let metadata = path
+            .metadata()
+            .map_err(|e| PPCError::from(format!("could not read {}: {}", path.display(), e)))?;
+        if !metadata.is_file() {
+            return Err(PPCError::from(format!("{} is not a file", path.display())));
+        }
+        let filename = match filename {
+            Some(filename) => String::from(filename),
+            None => path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .ok_or_else(|| PPCError::from(format!("{} has no file name", path.display())))?,
+        };
+
+        self.files.push(FilePart {
+            name: String::from(name),
+            path: path.to_path_buf(),
+            filename,
+            size: metadata.len(),
+        });
+        Ok(self)
+    }
+
+    /// Value of the `Content-Type` header.
+    pub fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    /// Length of the whole body in bytes.
+    pub fn content_length(&self) -> u64 {
+        let fields = self
+            .fields
+            .iter()
+            .map(|(name, value)| (self.field_head(name).len() + value.len() + 2) as u64);
+        let files = self
+            .files
+            .iter()
+            .map(|file| self.file_head(file).len() as u64 + file.size + 2);
+        fields.chain(files).sum::<u64>() + self.tail().len() as u64
+    }
+
+    /// The body, with the files opened as they are reached.
+    pub fn reader(&self) -> Reader {
+        let mut sections = VecDeque::new();
+        for (name, value) in &self.fields {
+            let text = format!("{}{}\r\n", self.field_head(name), value);
+            sections.push_back(Section::Bytes(text.into_bytes()));
+        }
+        for file in &self.files {
+            sections.push_back(Section::Bytes(self.file_head(file).into_bytes()));
+            sections.push_back(Section::File(file.clone()));
+            sections.push_back(Section::Bytes(b"\r\n".to_vec()));
+        }
+        sections.push_back(Section::Bytes(self.tail().into_bytes()));
+
+        Reader {
+            sections,
+            current: None,
+        }
+    }
+
+    /// The fields and file names, with secrets redacted, for logs and
+    /// recordings.
+    pub fn summary(&self) -> String {
+        let fields = self
+            .fields
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("&");
+        let files = self
+            .files
+            .iter()
+            .map(|file| format!("{}=@{} ({} bytes)", file.name, file.filename, file.size));
+        std::iter::once(redact::sanitize_body(&fields))
+            .filter(|fields| !fields.is_empty())
+            .chain(files)
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+
+    fn field_head(&self, name: &str) -> String {
+        format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n",
+            self.boundary,
+            escape(name)
+        )
+    }
+
+    fn file_head(&self, file: &FilePart) -> String {
+        format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\n",
+            self.boundary,
+            escape(&file.name),
+            escape(&file.filename)
+        )
+    }
+
+    fn tail(&self) -> String {
+        format!("--{}--\r\n", self.boundary)
+    }
+}
+
+impl Default for Multipart {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Secrets of the fields are redacted, see `summary`.
+impl fmt::Debug for Multipart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Multipart").field(&self.summary()).finish()
+    }
+}
+
+/// Quotes and line breaks would end the header value early.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace(['\r', '\n'], " ")
+}
+
+enum Section {
+    Bytes(Vec<u8>),
+    File(FilePart),
+}
+
+/// Reads the body of a `Multipart`, see `Multipart::reader`.
+pub struct Reader {
+    sections: VecDeque<Section>,
+    current: Option<Current>,
+}
+
+enum Current {
+    Bytes(Cursor<Vec<u8>>),
+    File {
+        file: io::Take<File>,
+        part: FilePart,
+    },
+}
+
+impl Read for Reader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let read = match &mut self.current {
+                Some(Current::Bytes(cursor)) => cursor.read(buf)?,
+                Some(Current::File { file, part }) => {
+                    let read = file.read(buf)?;
+                    // a file that shrank would make the body shorter than
+                    // announced.
+                    if read == 0 && !buf.is_empty() && file.limit() > 0 {
+                        return Err(io::Error::other(format!(
+                            "{} changed while it was uploaded",
+                            part.path.display()
+                        )));
+                    }
+                    read
+                }
+                None => 0,
+            };
+            if read > 0 || buf.is_empty() {
+                return Ok(read);
+            }
+
+            self.current = match self.sections.pop_front() {
+                None => return Ok(0),
+                Some(Section::Bytes(bytes)) => Some(Current::Bytes(Cursor::new(bytes))),
+                Some(Section::File(part)) => {
+                    let file = File::open(&part.path).map_err(|e| {
+                        io::Error::new(
+                            e.kind(),
+                            format!("could not read {}: {}", part.path.display(), e),
+                        )
+                    })?;
+                    Some(Current::File {
+                        file: file.take(part.size),
+                        part,
+                    })
+                }
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::io::Read;
+
+    use super::Multipart;
+
+    #[test]
+    fn body_has_the_announced_length() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.pdf");
+        fs::write(&path, vec![7u8; 100_000]).unwrap();
+
+        let multipart = Multipart::new()
+            .text("file_push[note]", "quarterly")
+            .file("file_push[files][]", &path, None)
+            .unwrap();
+
+        let mut body = Vec::new();
+        multipart.reader().read_to_end(&mut body).unwrap();
+        assert_eq!(body.len() as u64, multipart.content_length());
+
+        let text = String::from_utf8_lossy(&body);
+        assert!(text.starts_with(&format!("--{}\r\n", multipart.boundary)));
+        assert!(text.contains("name=\"file_push[note]\"\r\n\r\nquarterly\r\n"));
+        assert!(text.contains("name=\"file_push[files][]\"; filename=\"report.pdf\""));
+        assert!(text.ends_with(&format!("\r\n--{}--\r\n", multipart.boundary)));
+    }
+
+    #[test]
+    fn changed_files_fail_the_upload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("growing.log");
+        fs::write(&path, "0123456789").unwrap();
+        let multipart = Multipart::new()
+            .file("file_push[files][]", &path, Some("app.log"))
+            .unwrap();
+
+        // only the announced length is sent of a file that grew.
+        fs::write(&path, "0123456789 and more").unwrap();
+        let mut body = Vec::new();
+        multipart.reader().read_to_end(&mut body).unwrap();
+        assert_eq!(body.len() as u64, multipart.content_length());
+
+        fs::write(&path, "01234").unwrap();
+        let error = multipart.reader().read_to_end(&mut Vec::new()).unwrap_err();
+        assert!(error.to_string().contains("changed while it was uploaded"));
+    }
+
+    #[test]
+    fn summary_redacts_secrets() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("id_rsa");
+        fs::write(&path, "key").unwrap();
+
+        let multipart = Multipart::new()
+            .text("file_push[passphrase]", "open sesame")
+            .text("file_push[note]", "deploy key")
+            .file("file_push[files][]", &path, None)
+            .unwrap();
+
+        let summary = multipart.summary();
+        assert!(!summary.contains("open sesame"));
+        assert!(summary.contains("file_push[note]=deploy key"));
+        assert!(summary.contains("file_push[files][]=@id_rsa (3 bytes)"));
+        assert!(Multipart::new().file("x", dir.path(), None).is_err());
+    }
+}
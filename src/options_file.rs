@@ -0,0 +1,280 @@
+//! Options files, for long command lines that are used again and again.
+//!
+//! `--options-from <FILE>` is replaced by the arguments in the file, before
+//! the command line is parsed, so the file can hold global options, the
+//! command, its options, or all of it:
+//!
+//! ```text
+//! # handoff to the customer
+//! push text
+//! --expire-after-days 2 --expire-after-views 1
+//! --note "Credentials for the staging system.
+//! Call 555-0100 if the link has expired."
+//! ```
+//!
+//! Arguments are separated by whitespace, and quoted like in a POSIX shell:
+//! text in single quotes is taken as is, in double quotes `\"` and `\\` are
+//! escaped, and both can span lines, e.g. for notes with line breaks. `#`
+//! starts a comment that runs to the end of the line. Options after
+//! `--options-from` on the command line take precedence, as clap keeps the
+//! last value of an option.
+//!
+//! Files with `--passphrase`, `--token`, `--psk` or `--secret` are refused
+//! if other users can read them, like `ssh` refuses private keys. Payloads
+//! belong into `--payload-file` or `--stdin` anyway.
+//!
+//! The more common `@file` syntax is not supported, as a payload or
+//! passphrase starting with `@` would be taken for a file name.
+
+use std::ffi::OsString;
+use std::fs;
+use std::path::Path;
+
+use zeroize::Zeroizing;
+
+use crate::errors::PPCError;
+use crate::state;
+
+pub const FLAG: &str = "--options-from";
+
+/// Options that take a secret, which must not be readable by other users.
+const SECRET_OPTIONS: [&str; 5] = ["--passphrase", "--token", "-t", "--psk", "--secret"];
+
+/// The command line with each `--options-from` replaced by the arguments in
+/// the file. Arguments after `--` are left alone, as they are positional.
+pub fn expand(cmdline: Vec<OsString>) -> Result<Vec<OsString>, PPCError> {
+    let mut expanded = Vec::with_capacity(cmdline.len());
+    let mut args = cmdline.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            expanded.push(arg);
+            expanded.extend(args);
+            break;
+        }
+
+        let path = match arg.to_str() {
+            Some(FLAG) => args
+                .next()
+                .ok_or_else(|| PPCError::from(format!("{} needs a file", FLAG)))?,
+            Some(other) => match other.strip_prefix(FLAG).and_then(|o| o.strip_prefix('=')) {
+                Some(path) => OsString::from(path),
+                None => {
+                    expanded.push(arg);
+                    continue;
+                }
+            },
+            None => {
+                expanded.push(arg);
+                continue;
+            }
+        };
+        expanded.extend(read(Path::new(&path))?.into_iter().map(OsString::from));
+    }
+    Ok(expanded)
+}
+
+fn read(path: &Path) -> Result<Vec<String>, PPCError> {
+    let content = Zeroizing::new(fs::read_to_string(path).map_err(|e| {
+        PPCError::from(format!(
+            "could not read options file {}: {}",
+            path.display(),
+            e
+        ))
+    })?);
+    let args = split(&content)
+        .map_err(|e| PPCError::from(format!("invalid options file {}: {}", path.display(), e)))?;
+
+    if args
+        .iter()
+        .any(|arg| arg == FLAG || arg.starts_with("--options-from="))
+    {
+        return Err(PPCError::from(format!(
+            "options file {} must not contain {}",
+            path.display(),
+            FLAG
+        )));
+    }
+    if args.iter().any(|arg| is_secret_option(arg)) && state::readable_by_others(path) {
+        return Err(PPCError::from(format!(
+            "options file {} contains secrets and can be read by other users, \
+             restrict it with `chmod 600`",
+            path.display()
+        )));
+    }
+    Ok(args)
+}
+
+fn is_secret_option(arg: &str) -> bool {
+    SECRET_OPTIONS.iter().any(|option| {
+        arg == *option
+            || arg.starts_with(&format!("{}=", option))
+            // `-tTOKEN`
+            || (option.len() == 2 && arg.starts_with(option))
+    })
+}
+
+/// Split the content into arguments, see the module docs for the quoting.
+fn split(content: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut current: Option<String> = None;
+    let mut chars = content.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => args.extend(current.take()),
+            '#' if current.is_none() => {
+                chars.by_ref().find(|&c| c == '\n');
+            }
+            '\'' => {
+                let arg = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => arg.push(c),
+                        None => return Err(String::from("missing closing '")),
+                    }
+                }
+            }
+            '"' => {
+                let arg = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\')) => arg.push(c),
+                            Some(c) => {
+                                arg.push('\\');
+                                arg.push(c);
+                            }
+                            None => return Err(String::from("missing closing \"")),
+                        },
+                        Some(c) => arg.push(c),
+                        None => return Err(String::from("missing closing \"")),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                // a line continuation, as in a shell.
+                Some('\n') => {}
+                Some(c) => current.get_or_insert_with(String::new).push(c),
+                None => current.get_or_insert_with(String::new).push('\\'),
+            },
+            c => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+    args.extend(current);
+    Ok(args)
+}
+
+#[cfg(test)]
+mod test {
+    use std::ffi::OsString;
+    use std::fs;
+
+    use super::{expand, split};
+
+    #[test]
+    fn arguments_are_split_like_in_a_shell() {
+        let args = split(
+            "# handoff\npush text  --note \"two\nlines \\\"quoted\\\"\" \\\n--tag 'a b'#1 # comment\n\n''",
+        )
+        .unwrap();
+        assert_eq!(
+            args,
+            vec![
+                "push",
+                "text",
+                "--note",
+                "two\nlines \"quoted\"",
+                "--tag",
+                "a b#1",
+                ""
+            ]
+        );
+        assert!(split("--note 'open").is_err());
+        assert!(split("--note \"open").is_err());
+    }
+
+    #[test]
+    fn files_are_expanded_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("push.args");
+        fs::write(&path, "push text --expire-after-views 1\n").unwrap();
+        let cmdline = |args: &[&str]| args.iter().map(OsString::from).collect::<Vec<_>>();
+
+        let expanded = expand(cmdline(&[
+            "pwpush-cli",
+            "--json",
+            "--options-from",
+            path.to_str().unwrap(),
+            "s3cret",
+            "--",
+            "--options-from",
+        ]))
+        .unwrap();
+        assert_eq!(
+            expanded,
+            cmdline(&[
+                "pwpush-cli",
+                "--json",
+                "push",
+                "text",
+                "--expire-after-views",
+                "1",
+                "s3cret",
+                "--",
+                "--options-from"
+            ])
+        );
+
+        fs::write(&path, "--options-from other.args").unwrap();
+        let option = format!("--options-from={}", path.display());
+        assert!(expand(cmdline(&["pwpush-cli", &option])).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn secrets_in_readable_files_are_refused() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("push.args");
+        fs::write(&path, "push text --passphrase=open-sesame").unwrap();
+        let cmdline = vec![
+            OsString::from("pwpush-cli"),
+            OsString::from("--options-from"),
+            OsString::from(&path),
+        ];
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        let error = expand(cmdline.clone()).unwrap_err();
+        assert!(error.to_string().contains("can be read by other users"));
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        assert_eq!(expand(cmdline).unwrap().len(), 4);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn wifi_and_totp_secrets_in_readable_files_are_refused() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        for content in [
+            "push wifi --ssid Office --psk hunter2hunter2",
+            "push totp --issuer Example --account ops --secret JBSWY3DPEHPK3PXP",
+        ] {
+            let path = dir.path().join("push.args");
+            fs::write(&path, content).unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+            let cmdline = vec![
+                OsString::from("pwpush-cli"),
+                OsString::from("--options-from"),
+                OsString::from(&path),
+            ];
+
+            let error = expand(cmdline).unwrap_err();
+
+            assert!(error.to_string().contains("can be read by other users"));
+        }
+    }
+}
@@ -0,0 +1,175 @@
+//! JSON output of all commands, with a versioned schema.
+//!
+//! Scripts parse the output of `--json`, so it is treated as an interface:
+//!   - every top-level object has a `schema_version` as its first key. Where
+//!     the output is a list, or one object per line, each object has it.
+//!   - keys keep the order in which the fields are declared, and lists keep
+//!     the order of the instance or are sorted, so the same state always
+//!     gives the same output.
+//!   - new fields can be added at any time, so parsers must ignore unknown
+//!     keys. Removing or renaming a field, or changing its type, increases
+//!     `SCHEMA_VERSION`.
+//!
+//! Changes of `SCHEMA_VERSION`:
+//!   - 2: the bulk operations `expire bulk`, `push text --recipients` and
+//!     `bulk resume` print one object with the `items` and a `summary`,
+//!     instead of a list of the items.
+//!
+//! The field types are documented by `schema`, which prints a JSON Schema
+//! generated from the same serde models that produce the output.
+//!
+//! Formats of other systems, like the Elastic Common Schema of `audit
+//! --format ndjson` or exports that are read back by `import`, have a schema
+//! of their own and are not versioned here.
+
+use schemars::SchemaGenerator;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::errors::PPCError;
+use crate::models::{AuditLog, Push, ServerVersion};
+use crate::{
+    account, audit_report, doctor, download, expire, history, info, jobs, remind, settings, summary,
+};
+
+/// Version of the JSON output, see the module docs for when it changes.
+pub const SCHEMA_VERSION: u64 = 2;
+
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+/// The value with `schema_version` added as first key of the top-level
+/// object, or of each object of a top-level list. Other values are returned
+/// as they are.
+pub fn versioned(value: Value) -> Value {
+    match value {
+        Value::Object(object) => {
+            let mut versioned = Map::with_capacity(object.len() + 1);
+            versioned.insert(String::from(SCHEMA_VERSION_KEY), json!(SCHEMA_VERSION));
+            for (key, value) in object {
+                if key != SCHEMA_VERSION_KEY {
+                    versioned.insert(key, value);
+                }
+            }
+            Value::Object(versioned)
+        }
+        Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .map(|item| match item {
+                    Value::Object(_) => versioned(item),
+                    other => other,
+                })
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+fn to_value<T: Serialize + ?Sized>(value: &T) -> Value {
+    // all outputs consist of strings, numbers, bools and maps with string
+    // keys, so serialization cannot fail.
+    versioned(serde_json::to_value(value).unwrap())
+}
+
+/// Print the value as pretty JSON, for output that is printed once.
+pub fn print_json<T: Serialize + ?Sized>(value: &T) {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&to_value(value)).unwrap()
+    );
+}
+
+/// Print the value as JSON on a single line, for output that is printed
+/// continuously, e.g. in watch mode, one object per line.
+pub fn print_json_line<T: Serialize + ?Sized>(value: &T) {
+    println!("{}", to_value(value));
+}
+
+/// JSON Schema of the models that are printed, with the name of each model
+/// as definition, e.g. `#/$defs/Push`.
+pub fn schema() -> Value {
+    let mut generator = SchemaGenerator::default();
+    generator.subschema_for::<account::Account>();
+    generator.subschema_for::<AuditLog>();
+    generator.subschema_for::<audit_report::AuditReportEntry>();
+    generator.subschema_for::<doctor::Check>();
+    generator.subschema_for::<download::Download>();
+    generator.subschema_for::<expire::Expiration>();
+    generator.subschema_for::<history::Entry>();
+    generator.subschema_for::<info::ListEntry>();
+    generator.subschema_for::<jobs::BulkSummary>();
+    generator.subschema_for::<Push>();
+    generator.subschema_for::<remind::Reminder>();
+    generator.subschema_for::<ServerVersion>();
+    generator.subschema_for::<settings::Setting>();
+    generator.subschema_for::<summary::Summary>();
+
+    // models that are part of others, like AuditView or PushKind, are
+    // defined as well.
+    let definitions = generator.take_definitions(true);
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "pwpush-cli JSON output",
+        "description": "Each top-level object of the output also has a schema_version.",
+        SCHEMA_VERSION_KEY: SCHEMA_VERSION,
+        "$defs": definitions,
+    })
+}
+
+/// Entry point for the `schema` command. The schema is always JSON.
+pub fn run() -> Result<(), PPCError> {
+    println!("{}", serde_json::to_string_pretty(&schema()).unwrap());
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::{schema, versioned, SCHEMA_VERSION};
+    use crate::models::Push;
+
+    #[test]
+    fn version_is_first_key() {
+        let push = Push {
+            url_token: String::from("abc"),
+            ..Push::default()
+        };
+
+        let value = versioned(serde_json::to_value(&push).unwrap());
+        let keys = value.as_object().unwrap().keys().collect::<Vec<_>>();
+        assert_eq!(keys[0], "schema_version");
+        assert_eq!(keys[1], "url_token");
+        assert_eq!(value["schema_version"], SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn lists_version_each_object() {
+        let value = versioned(json!([{ "url_token": "abc" }, "other"]));
+        assert_eq!(
+            value,
+            json!([{ "schema_version": SCHEMA_VERSION, "url_token": "abc" }, "other"])
+        );
+
+        assert_eq!(versioned(json!("plain")), json!("plain"));
+    }
+
+    #[test]
+    fn schema_documents_all_fields() {
+        let schema = schema();
+        assert_eq!(schema["schema_version"], SCHEMA_VERSION);
+
+        // every field of the output must be described.
+        let push = serde_json::to_value(Push {
+            payload: Some(String::from("secret")),
+            ..Push::default()
+        })
+        .unwrap();
+        let properties = schema["$defs"]["Push"]["properties"].as_object().unwrap();
+        for key in push.as_object().unwrap().keys() {
+            assert!(properties.contains_key(key), "{} is not documented", key);
+        }
+
+        assert!(schema["$defs"]["PushKind"]["enum"].is_array());
+    }
+}
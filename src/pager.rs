@@ -0,0 +1,135 @@
+//! Paging of long human-readable output, like git does it.
+//!
+//! If the output of `list` or `audit` does not fit on the terminal, it is
+//! piped through `$PAGER`, or `less -R` if that is not set. `--no-pager`, an
+//! empty `$PAGER` or `PAGER=cat` print it directly instead. Output that does
+//! not go to a terminal, JSON output and watch mode are never paged.
+//!
+//! Like git, `LESS=FRX` is set unless `$LESS` is already set, so less exits
+//! right away for output that fits after all and leaves it on the screen.
+//!
+//! A pager that cannot be started is skipped, and the text printed directly.
+//! Once it was started, it may have shown part of the text already, so later
+//! failures are returned instead of printing the text a second time.
+
+use std::env;
+use std::io::{self, IsTerminal, Write};
+use std::process::{Child, Command, Stdio};
+
+use tracing::debug;
+
+use crate::args::PPCArgs;
+use crate::errors::PPCError;
+use crate::platform;
+
+/// Used if `$PAGER` is not set.
+const DEFAULT_PAGER: &str = "less -R";
+
+/// Print the text, through the pager if it is too long for the terminal.
+pub fn print(args: &PPCArgs, text: &str) -> Result<(), PPCError> {
+    let height = platform::terminal_size().map(|(_, height)| height);
+    let pager = env::var("PAGER").ok();
+
+    let command = if io::stdout().is_terminal() && !args.no_pager && !args.json_output {
+        pager_command(pager.as_deref()).filter(|_| needs_paging(text, height))
+    } else {
+        None
+    };
+
+    let Some(command) = command else {
+        print!("{}", text);
+        return Ok(());
+    };
+    let child = match spawn_pager(&command) {
+        Ok(child) => child,
+        Err(e) => {
+            debug!("could not run pager `{}`: {}", command.join(" "), e);
+            print!("{}", text);
+            return Ok(());
+        }
+    };
+    page(child, text).map_err(|e| {
+        PPCError::from(format!(
+            "could not page the output through `{}`: {}",
+            command.join(" "),
+            e
+        ))
+    })
+}
+
+/// The pager to run, if any, split into program and arguments.
+fn pager_command(pager: Option<&str>) -> Option<Vec<String>> {
+    let pager = pager.unwrap_or(DEFAULT_PAGER).trim();
+    if pager.is_empty() || pager == "cat" {
+        return None;
+    }
+    Some(pager.split_whitespace().map(String::from).collect())
+}
+
+/// True if the text has more lines than fit on the terminal, leaving a line
+/// for the prompt. Without a known height, nothing is paged.
+fn needs_paging(text: &str, height: Option<usize>) -> bool {
+    height.is_some_and(|height| text.lines().count() >= height.max(1))
+}
+
+fn spawn_pager(command: &[String]) -> io::Result<Child> {
+    let mut pager = Command::new(&command[0]);
+    pager.args(&command[1..]).stdin(Stdio::piped());
+    if env::var_os("LESS").is_none() {
+        pager.env("LESS", "FRX");
+    }
+    pager.spawn()
+}
+
+/// Hand the text to the started pager, and wait until the user quits it.
+fn page(mut child: Child, text: &str) -> io::Result<()> {
+    if let Some(mut stdin) = child.stdin.take() {
+        // the user may quit the pager before all output was read.
+        match stdin.write_all(text.as_bytes()) {
+            Err(e) if e.kind() != io::ErrorKind::BrokenPipe => return Err(e),
+            _ => {}
+        }
+    }
+    child.wait().map(|_| ())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{needs_paging, page, pager_command, spawn_pager};
+
+    #[test]
+    fn pager_from_environment() {
+        assert_eq!(
+            pager_command(None),
+            Some(vec![String::from("less"), String::from("-R")])
+        );
+        assert_eq!(
+            pager_command(Some("more")),
+            Some(vec![String::from("more")])
+        );
+        assert_eq!(pager_command(Some("")), None);
+        assert_eq!(pager_command(Some("cat")), None);
+    }
+
+    #[test]
+    fn pages_only_long_output() {
+        let text = "line\n".repeat(30);
+
+        assert!(needs_paging(&text, Some(24)));
+        assert!(!needs_paging(&text, Some(50)));
+        assert!(!needs_paging(&text, None));
+    }
+
+    #[test]
+    fn missing_pagers_are_not_started() {
+        assert!(spawn_pager(&[String::from("pwpush-cli-no-such-pager")]).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn pagers_may_quit_before_reading_everything() {
+        let child = spawn_pager(&[String::from("true")]).unwrap();
+
+        assert!(page(child, &"line\n".repeat(100_000)).is_ok());
+    }
+}
@@ -0,0 +1,43 @@
+//! Hardened push options in a single switch.
+//!
+//! `push text --paranoid` gives pushes the settings that leave the least room
+//! for mistakes: a single view within a single day, the retrieval step so
+//! link scanners do not use up that view, no deletion by viewers so the
+//! sender can see in the audit log whether the recipient got the secret, and
+//! a generated passphrase that should be shared on a different channel than
+//! the link. These take precedence over presets and push defaults, and
+//! cannot be combined with the options they set.
+
+use secrecy::SecretString;
+
+use crate::args::PPCText;
+use crate::passphrase;
+
+pub const EXPIRE_AFTER_DAYS: usize = 1;
+pub const EXPIRE_AFTER_VIEWS: usize = 1;
+pub const PASSPHRASE_LENGTH: usize = 16;
+
+/// Set the hardened options. A passphrase that was given explicitly is kept.
+pub fn apply(ppc_text: &mut PPCText) {
+    ppc_text.expire_after_days = Some(EXPIRE_AFTER_DAYS);
+    ppc_text.expire_after_views = Some(EXPIRE_AFTER_VIEWS);
+    ppc_text.retrieval_step = Some(true);
+    ppc_text.deletable_by_viewer = Some(false);
+    if ppc_text.passphrase.is_none() {
+        ppc_text.passphrase = Some(SecretString::from(passphrase::generate(PASSPHRASE_LENGTH)));
+    }
+}
+
+/// The applied options, to show them next to the link.
+pub fn summary(generated_passphrase: bool) -> String {
+    format!(
+        "--paranoid applied: expire after {} day or {} view, retrieval step on, deletable by viewer off, {}",
+        EXPIRE_AFTER_DAYS,
+        EXPIRE_AFTER_VIEWS,
+        if generated_passphrase {
+            format!("generated passphrase of {} characters", PASSPHRASE_LENGTH)
+        } else {
+            String::from("given passphrase")
+        }
+    )
+}
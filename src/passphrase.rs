@@ -0,0 +1,315 @@
+//! Generation of random passphrases for pushes, and a check of passphrases
+//! given by the user.
+//!
+//! The strength of a passphrase is estimated locally, nothing is sent
+//! anywhere. The estimate is deliberately simple: common passwords count as
+//! weak, also when they are only part of the passphrase, with letters swapped
+//! for look-alike digits and symbols, as in `P@ssw0rd`, or with digits and
+//! symbols added, as in `Password1!`. A common password counts as a single
+//! guess out of the list, and only the rest of the passphrase adds entropy.
+//! Keyboard and alphabet sequences and repetitions count as weak as well,
+//! otherwise the entropy is derived from the length and the kinds of
+//! characters used. Weak passphrases are warned about, which can be turned off
+//! with `warn_weak_passphrase = false` in the `[push]` section of the
+//! configuration file, and refused with `--enforce-strong-passphrase`.
+
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use tracing::warn;
+
+use crate::errors::PPCError;
+use crate::i18n;
+
+/// Passphrases below this estimated entropy count as weak. 12 random letters
+/// and digits, as generated by presets, are well above.
+const MIN_STRONG_BITS: f64 = 60.0;
+
+/// Frequently used passwords and the words they are made of, which are
+/// guessed first by any attacker, also with digits or symbols around them.
+/// Look-alike digits and symbols are undone before comparing, see
+/// `letter`.
+const COMMON: [&str; 64] = [
+    "123456",
+    "password",
+    "12345678",
+    "qwerty",
+    "123456789",
+    "12345",
+    "1234567",
+    "111111",
+    "123123",
+    "abc123",
+    "iloveyou",
+    "admin",
+    "welcome",
+    "monkey",
+    "dragon",
+    "letmein",
+    "secret",
+    "changeme",
+    "qwertz",
+    "azerty",
+    "qazwsx",
+    "1q2w3e4r",
+    "zaq12wsx",
+    "trustno1",
+    "sunshine",
+    "princess",
+    "football",
+    "baseball",
+    "soccer",
+    "hockey",
+    "master",
+    "shadow",
+    "superman",
+    "batman",
+    "starwars",
+    "pokemon",
+    "freedom",
+    "whatever",
+    "hello",
+    "login",
+    "access",
+    "passwort",
+    "hallo",
+    "schatz",
+    "summer",
+    "winter",
+    "spring",
+    "autumn",
+    "sommer",
+    "michael",
+    "jennifer",
+    "charlie",
+    "mustang",
+    "computer",
+    "internet",
+    "killer",
+    "ninja",
+    "cookie",
+    "flower",
+    "purple",
+    "orange",
+    "banana",
+    "chocolate",
+    "default",
+];
+
+/// Guesses that a common password is worth: its place in the list, and
+/// whether it is capitalized.
+const COMMON_BITS: f64 = 7.0;
+
+/// Runs of characters that are as easy to guess as a single one.
+const SEQUENCES: [&str; 3] = [
+    "abcdefghijklmnopqrstuvwxyz",
+    "0123456789",
+    "qwertyuiopasdfghjklzxcvbnm",
+];
+
+/// Generate a random passphrase of the given length. Only ASCII letters and
+/// digits are used, so the passphrase can be read out over the phone and
+/// survives every chat system without escaping issues.
+pub fn generate(length: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect()
+}
+
+/// Estimate of how hard a passphrase is to guess.
+#[derive(Debug, PartialEq)]
+pub struct Strength {
+    pub bits: f64,
+
+    /// ID of the message why the passphrase is weak, if it is
+    pub weakness: Option<&'static str>,
+}
+
+pub fn strength(passphrase: &str) -> Strength {
+    let chars = passphrase.chars().collect::<Vec<_>>();
+    let Some((start, len)) = common_part(&chars) else {
+        let (bits, guessable) = entropy(&chars);
+        let weakness = if bits >= MIN_STRONG_BITS {
+            None
+        } else if guessable * 2 >= chars.len() {
+            Some("passphrase-sequences")
+        } else {
+            Some("passphrase-short")
+        };
+        return Strength { bits, weakness };
+    };
+
+    if len == chars.len() {
+        return Strength {
+            bits: 0.0,
+            weakness: Some("passphrase-common"),
+        };
+    }
+    let rest = [&chars[..start], &chars[start + len..]].concat();
+    let bits = COMMON_BITS + entropy(&rest).0;
+    Strength {
+        bits,
+        weakness: (bits < MIN_STRONG_BITS).then_some("passphrase-common-variant"),
+    }
+}
+
+/// The longest common password in the passphrase, as start and length.
+fn common_part(chars: &[char]) -> Option<(usize, usize)> {
+    let letters = chars.iter().map(|c| letter(*c)).collect::<Vec<_>>();
+    COMMON
+        .iter()
+        .map(|common| common.chars().map(letter).collect::<Vec<_>>())
+        .filter_map(|common| {
+            letters
+                .windows(common.len())
+                .position(|window| window == common)
+                .map(|start| (start, common.len()))
+        })
+        .max_by_key(|(_, len)| *len)
+}
+
+/// The letter that a character stands for in passwords, e.g. `a` for `@`.
+fn letter(c: char) -> char {
+    match c.to_ascii_lowercase() {
+        '0' => 'o',
+        '1' | '!' | '|' | 'l' => 'i',
+        '3' => 'e',
+        '4' | '@' => 'a',
+        '5' | '$' => 's',
+        '7' | '+' => 't',
+        '8' => 'b',
+        '9' => 'g',
+        c => c,
+    }
+}
+
+/// Estimated entropy of the characters in bits, and how many of them only
+/// continue a repetition or a sequence.
+fn entropy(chars: &[char]) -> (f64, usize) {
+    let lower = chars
+        .iter()
+        .flat_map(|c| c.to_lowercase())
+        .collect::<Vec<_>>();
+    // characters that continue a repetition or a sequence do not add
+    // anything an attacker would have to guess.
+    let guessable = lower
+        .windows(2)
+        .filter(|pair| pair[0] == pair[1] || follows(pair[0], pair[1]))
+        .count();
+    let effective = lower.len().saturating_sub(guessable);
+
+    let mut pool = 0;
+    if chars.iter().any(|c| c.is_ascii_lowercase()) {
+        pool += 26;
+    }
+    if chars.iter().any(|c| c.is_ascii_uppercase()) {
+        pool += 26;
+    }
+    if chars.iter().any(|c| c.is_ascii_digit()) {
+        pool += 10;
+    }
+    if chars.iter().any(|c| c.is_ascii_punctuation() || *c == ' ') {
+        pool += 33;
+    }
+    if !chars.iter().all(char::is_ascii) {
+        pool += 100;
+    }
+    (effective as f64 * f64::from(pool.max(1)).log2(), guessable)
+}
+
+/// True if `next` comes right after `previous` in one of the well-known
+/// sequences.
+fn follows(previous: char, next: char) -> bool {
+    SEQUENCES.iter().any(|sequence| {
+        sequence
+            .find(previous)
+            .and_then(|i| sequence[i + 1..].chars().next())
+            == Some(next)
+    })
+}
+
+/// Warn about a weak passphrase, or refuse it if `enforce` is set.
+pub fn check(passphrase: &str, warn_weak: bool, enforce: bool) -> Result<(), PPCError> {
+    let Some(weakness) = strength(passphrase).weakness else {
+        return Ok(());
+    };
+
+    if enforce {
+        return Err(PPCError::from(format!(
+            "the passphrase is too weak, as {}",
+            i18n::text(weakness)
+        )));
+    }
+    if warn_weak {
+        warn!(
+            "{}",
+            i18n::format(
+                "push-weak-passphrase",
+                &[("weakness", i18n::text(weakness).into())]
+            )
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{check, generate, strength};
+
+    #[test]
+    fn generate_has_requested_length() {
+        assert_eq!(generate(0).len(), 0);
+        assert_eq!(generate(16).len(), 16);
+    }
+
+    #[test]
+    fn generate_is_alphanumeric() {
+        assert!(generate(64).chars().all(|c| c.is_ascii_alphanumeric()));
+    }
+
+    #[test]
+    fn weak_passphrases() {
+        assert!(strength("Password").weakness.is_some());
+        assert!(strength("aaaaaaaaaaaaaaaaaaaa").weakness.is_some());
+        assert!(strength("abcdefghijklmnop1234").weakness.is_some());
+        assert!(strength("Tr0ub4").weakness.is_some());
+    }
+
+    #[test]
+    fn variations_of_common_passwords() {
+        for passphrase in [
+            "Password1!",
+            "P@ssw0rd2024",
+            "Summer2024!",
+            "iloveyou123",
+            "l3tm31n",
+        ] {
+            assert!(
+                strength(passphrase).weakness.is_some(),
+                "{} counts as strong",
+                passphrase
+            );
+        }
+        assert_eq!(
+            strength("Password1!").weakness,
+            Some("passphrase-common-variant")
+        );
+        assert_eq!(strength("P4ssw0rd").weakness, Some("passphrase-common"));
+        // enough around a common password makes up for it.
+        assert!(strength("password nK4vQ9zLw2Rx").weakness.is_none());
+    }
+
+    #[test]
+    fn strong_passphrases() {
+        assert!(strength("correct horse battery staple").weakness.is_none());
+        assert!(strength("nK4vQ9zLw2Rx").weakness.is_none());
+    }
+
+    #[test]
+    fn check_enforces() {
+        assert!(check("secret", true, false).is_ok());
+        assert!(check("secret", false, true).is_err());
+        assert!(check("correct horse battery staple", true, true).is_ok());
+    }
+}
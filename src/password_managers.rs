@@ -0,0 +1,120 @@
+//! Payloads from the command line tools of password managers.
+//!
+//! Handing a secret from a password manager to someone else usually means
+//! copying it into a push manually. With `--from-op` and `--from-bw`, the
+//! secret is read with the CLI of 1Password (`op`) or Bitwarden (`bw`)
+//! directly, so it never appears on the command line, in the shell history or
+//! in the clipboard.
+//!
+//! The tools have to be installed and signed in, e.g. with `op signin` or by
+//! exporting `BW_SESSION`. Their stdin and stderr are passed through, so they
+//! can ask for a master password if needed.
+//!
+//! For 1Password, secret references like `op://vault/item/field` are read as
+//! they are, anything else is taken as item name or ID and its password field
+//! is used. For Bitwarden, the password of the item with the given name or ID
+//! is used.
+
+use std::process::{Command, Stdio};
+
+use secrecy::SecretString;
+use tracing::debug;
+use zeroize::Zeroizing;
+
+use crate::errors::PPCError;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Source<'a> {
+    OnePassword(&'a str),
+    Bitwarden(&'a str),
+}
+
+/// Read the secret with the CLI of the password manager.
+pub fn read(source: Source) -> Result<SecretString, PPCError> {
+    let (program, args) = command(source);
+    debug!("reading secret with {} {}", program, args.join(" "));
+
+    let output = Command::new(program)
+        .args(&args)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| PPCError::from(format!("could not run {}: {}", program, e)))?;
+    let stdout = Zeroizing::new(output.stdout);
+
+    if !output.status.success() {
+        return Err(PPCError::from(format!(
+            "{} failed with {}, is it installed and signed in?",
+            program, output.status
+        )));
+    }
+
+    let text = std::str::from_utf8(&stdout)
+        .map_err(|_| PPCError::from(format!("{} returned a secret that is not UTF-8", program)))?;
+    // the tools end their output with a line break, which is not part of
+    // the secret.
+    let secret = text.strip_suffix('\n').unwrap_or(text);
+    let secret = secret.strip_suffix('\r').unwrap_or(secret);
+    if secret.is_empty() {
+        return Err(PPCError::from(format!(
+            "{} returned an empty secret",
+            program
+        )));
+    }
+    Ok(SecretString::from(secret))
+}
+
+fn command(source: Source<'_>) -> (&'static str, Vec<&str>) {
+    match source {
+        Source::OnePassword(reference) if reference.starts_with("op://") => {
+            ("op", vec!["read", reference])
+        }
+        Source::OnePassword(item) => (
+            "op",
+            vec![
+                "item",
+                "get",
+                item,
+                "--fields",
+                "label=password",
+                "--reveal",
+            ],
+        ),
+        Source::Bitwarden(item) => ("bw", vec!["get", "password", item]),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{command, Source};
+
+    #[test]
+    fn one_password_commands() {
+        assert_eq!(
+            command(Source::OnePassword("op://ops/db/password")),
+            ("op", vec!["read", "op://ops/db/password"])
+        );
+        assert_eq!(
+            command(Source::OnePassword("db")),
+            (
+                "op",
+                vec![
+                    "item",
+                    "get",
+                    "db",
+                    "--fields",
+                    "label=password",
+                    "--reveal"
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn bitwarden_command() {
+        assert_eq!(
+            command(Source::Bitwarden("db")),
+            ("bw", vec!["get", "password", "db"])
+        );
+    }
+}
@@ -0,0 +1,328 @@
+//! Locations of the files of the CLI, following the conventions of each
+//! platform.
+//!
+//! | Directory | Linux and other unix systems          | macOS                                     | Windows                    |
+//! |-----------|---------------------------------------|-------------------------------------------|----------------------------|
+//! | config    | `$XDG_CONFIG_HOME` or `~/.config`     | `~/Library/Application Support`           | `%APPDATA%`                |
+//! | data      | `$XDG_DATA_HOME` or `~/.local/share`  | `~/Library/Application Support`           | `%LOCALAPPDATA%`           |
+//! | cache     | `$XDG_CACHE_HOME` or `~/.cache`       | `~/Library/Caches`                        | `%LOCALAPPDATA%`, `cache`  |
+//! | keys      | `$XDG_STATE_HOME` or `~/.local/state` | `~/Library/Application Support`           | `%LOCALAPPDATA%`           |
+//!
+//! Each with a `pwpush-cli` directory below, except for the keys on macOS
+//! and Windows, which get a `pwpush-cli-keys` directory next to it. This way,
+//! the keys are never inside the data directory they protect. The XDG
+//! variables are respected on macOS as well, if they are set.
+//! `PWPUSH_CONFIG_DIR` replaces the configuration directory on all platforms,
+//! e.g. for portable setups, and `PWPUSH_KEY_DIR` the key directory, e.g. to
+//! keep the keys on a separate volume.
+//!
+//! Earlier versions used the Linux locations on macOS too. If only the old
+//! directory exists there, it is still used, so configuration, queue and
+//! history are not lost.
+//!
+//! The data directory holds the state of the queue, `--dedupe` and the
+//! history, the key directory the keys it is encrypted with, see `state`.
+//! `paths` shows all locations in effect.
+
+use std::env;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use serde_json::{json, Value};
+use tracing::debug;
+
+use crate::args::PPCArgs;
+use crate::errors::PPCError;
+use crate::render::CommandOutput;
+use crate::{cache, config, dedupe, history, policy, queue};
+
+/// Replaces the configuration directory.
+pub const CONFIG_DIR_VAR: &str = "PWPUSH_CONFIG_DIR";
+
+/// Replaces the key directory.
+pub const KEY_DIR_VAR: &str = "PWPUSH_KEY_DIR";
+
+const APP: &str = "pwpush-cli";
+
+/// The key directory where it is next to the data directory.
+const KEYS: &str = "pwpush-cli-keys";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Os {
+    Unix,
+    Mac,
+    Windows,
+}
+
+impl Os {
+    fn current() -> Self {
+        if cfg!(windows) {
+            Os::Windows
+        } else if cfg!(target_os = "macos") {
+            Os::Mac
+        } else {
+            Os::Unix
+        }
+    }
+}
+
+/// The kinds of directories.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Kind {
+    Config,
+    Data,
+    Cache,
+    Keys,
+}
+
+/// The directory of the given kind, with `var` looking up environment
+/// variables, so tests do not depend on the machine they run on. Empty
+/// variables count as not set.
+fn resolve(os: Os, kind: Kind, var: &dyn Fn(&str) -> Option<OsString>) -> Option<PathBuf> {
+    let var = |name: &str| {
+        var(name)
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+    };
+
+    let replacement = match kind {
+        Kind::Config => var(CONFIG_DIR_VAR),
+        Kind::Keys => var(KEY_DIR_VAR),
+        _ => None,
+    };
+    if replacement.is_some() {
+        return replacement;
+    }
+
+    let base = match (os, kind) {
+        (Os::Windows, Kind::Config) => var("APPDATA")?,
+        (Os::Windows, Kind::Data) => var("LOCALAPPDATA")?,
+        (Os::Windows, Kind::Cache) => return Some(var("LOCALAPPDATA")?.join(APP).join("cache")),
+        (Os::Windows, Kind::Keys) => return Some(var("LOCALAPPDATA")?.join(KEYS)),
+        (_, Kind::Config) => var("XDG_CONFIG_HOME").or_else(|| match os {
+            Os::Mac => Some(var("HOME")?.join("Library").join("Application Support")),
+            _ => Some(var("HOME")?.join(".config")),
+        })?,
+        (_, Kind::Data) => var("XDG_DATA_HOME").or_else(|| match os {
+            Os::Mac => Some(var("HOME")?.join("Library").join("Application Support")),
+            _ => Some(var("HOME")?.join(".local").join("share")),
+        })?,
+        (_, Kind::Cache) => var("XDG_CACHE_HOME").or_else(|| match os {
+            Os::Mac => Some(var("HOME")?.join("Library").join("Caches")),
+            _ => Some(var("HOME")?.join(".cache")),
+        })?,
+        (_, Kind::Keys) => match (var("XDG_STATE_HOME"), os) {
+            (Some(dir), _) => dir,
+            (None, Os::Mac) => {
+                return Some(
+                    var("HOME")?
+                        .join("Library")
+                        .join("Application Support")
+                        .join(KEYS),
+                )
+            }
+            (None, _) => var("HOME")?.join(".local").join("state"),
+        },
+    };
+    Some(base.join(APP))
+}
+
+/// The directory of the given kind on this machine, or the directory of
+/// earlier versions if only that one exists.
+fn current(kind: Kind) -> Option<PathBuf> {
+    let var = |name: &str| env::var_os(name);
+    let dir = resolve(Os::current(), kind, &var)?;
+    if Os::current() != Os::Mac || dir.exists() {
+        return Some(dir);
+    }
+
+    match resolve(Os::Unix, kind, &var) {
+        Some(legacy) if legacy.exists() => {
+            debug!("using {} of an earlier version", legacy.display());
+            Some(legacy)
+        }
+        _ => Some(dir),
+    }
+}
+
+/// Directory of the configuration file.
+pub fn config_dir() -> Option<PathBuf> {
+    current(Kind::Config)
+}
+
+/// Directory of the local state, like the queue and the history.
+pub fn data_dir() -> Result<PathBuf, PPCError> {
+    current(Kind::Data).ok_or_else(|| PPCError::from("could not determine the data directory"))
+}
+
+/// Directory of data that can be deleted at any time.
+pub fn cache_dir() -> Result<PathBuf, PPCError> {
+    current(Kind::Cache).ok_or_else(|| PPCError::from("could not determine the cache directory"))
+}
+
+/// Directory of the keys of the encrypted state, outside of the data and
+/// cache directories, see `state`.
+pub fn key_dir() -> Result<PathBuf, PPCError> {
+    current(Kind::Keys).ok_or_else(|| PPCError::from("could not determine the key directory"))
+}
+
+/// Result of the `paths` command, the name and location of each directory.
+pub struct Paths(Vec<(&'static str, String)>);
+
+impl CommandOutput for Paths {
+    fn json(&self) -> Value {
+        Value::Object(
+            self.0
+                .iter()
+                .map(|(name, path)| (name.to_string(), json!(path)))
+                .collect(),
+        )
+    }
+
+    fn human(&self, _width: Option<usize>) -> String {
+        self.0
+            .iter()
+            .map(|(name, path)| format!("{:8} {}\n", format!("{}:", name), path))
+            .collect()
+    }
+}
+
+/// Entry point for the `paths` command.
+pub fn run(args: &PPCArgs) -> Paths {
+    let show = |path: Result<PathBuf, PPCError>| match path {
+        Ok(path) => path.display().to_string(),
+        Err(e) => format!("unknown ({})", e),
+    };
+
+    Paths(vec![
+        ("config", show(config::path(args.config_file.as_deref()))),
+        ("policy", show(policy::path())),
+        ("queue", show(queue::default_dir())),
+        ("dedupe", show(dedupe::default_dir())),
+        ("history", show(history::default_dir())),
+        ("cache", show(cache::default_dir())),
+        ("keys", show(key_dir())),
+    ])
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::ffi::OsString;
+    use std::path::PathBuf;
+
+    use super::{resolve, Kind, Os, CONFIG_DIR_VAR, KEY_DIR_VAR};
+
+    fn lookup(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<OsString> {
+        let vars: HashMap<String, OsString> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), OsString::from(value)))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn unix_follows_xdg() {
+        let home = lookup(&[("HOME", "/home/u")]);
+        assert_eq!(
+            resolve(Os::Unix, Kind::Config, &home),
+            Some(PathBuf::from("/home/u/.config/pwpush-cli"))
+        );
+        assert_eq!(
+            resolve(Os::Unix, Kind::Data, &home),
+            Some(PathBuf::from("/home/u/.local/share/pwpush-cli"))
+        );
+
+        assert_eq!(
+            resolve(Os::Unix, Kind::Keys, &home),
+            Some(PathBuf::from("/home/u/.local/state/pwpush-cli"))
+        );
+
+        let xdg = lookup(&[("HOME", "/home/u"), ("XDG_CACHE_HOME", "/tmp/cache")]);
+        assert_eq!(
+            resolve(Os::Unix, Kind::Cache, &xdg),
+            Some(PathBuf::from("/tmp/cache/pwpush-cli"))
+        );
+    }
+
+    #[test]
+    fn macos_uses_library() {
+        let home = lookup(&[("HOME", "/Users/u"), ("XDG_DATA_HOME", "")]);
+        assert_eq!(
+            resolve(Os::Mac, Kind::Data, &home),
+            Some(PathBuf::from(
+                "/Users/u/Library/Application Support/pwpush-cli"
+            ))
+        );
+        assert_eq!(
+            resolve(Os::Mac, Kind::Cache, &home),
+            Some(PathBuf::from("/Users/u/Library/Caches/pwpush-cli"))
+        );
+        assert_eq!(
+            resolve(Os::Mac, Kind::Keys, &home),
+            Some(PathBuf::from(
+                "/Users/u/Library/Application Support/pwpush-cli-keys"
+            ))
+        );
+    }
+
+    #[test]
+    fn windows_uses_appdata() {
+        let vars = lookup(&[
+            ("APPDATA", r"C:\Users\u\AppData\Roaming"),
+            ("LOCALAPPDATA", r"C:\Users\u\AppData\Local"),
+        ]);
+        assert_eq!(
+            resolve(Os::Windows, Kind::Config, &vars),
+            Some(PathBuf::from(r"C:\Users\u\AppData\Roaming").join("pwpush-cli"))
+        );
+        assert_eq!(
+            resolve(Os::Windows, Kind::Cache, &vars),
+            Some(
+                PathBuf::from(r"C:\Users\u\AppData\Local")
+                    .join("pwpush-cli")
+                    .join("cache")
+            )
+        );
+        assert_eq!(resolve(Os::Windows, Kind::Config, &lookup(&[])), None);
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn current_dirs_follow_the_platform() {
+        use std::env;
+
+        let appdata = PathBuf::from(env::var_os("APPDATA").unwrap());
+        if env::var_os(CONFIG_DIR_VAR).is_none() {
+            assert_eq!(super::config_dir(), Some(appdata.join("pwpush-cli")));
+        }
+        let local = PathBuf::from(env::var_os("LOCALAPPDATA").unwrap());
+        assert_eq!(
+            super::cache_dir().unwrap(),
+            local.join("pwpush-cli").join("cache")
+        );
+    }
+
+    #[test]
+    fn override_replaces_config_dir_only() {
+        let vars = lookup(&[("HOME", "/home/u"), (CONFIG_DIR_VAR, "/opt/pwpush")]);
+        assert_eq!(
+            resolve(Os::Unix, Kind::Config, &vars),
+            Some(PathBuf::from("/opt/pwpush"))
+        );
+        assert_eq!(
+            resolve(Os::Windows, Kind::Config, &vars),
+            Some(PathBuf::from("/opt/pwpush"))
+        );
+        assert_eq!(
+            resolve(Os::Unix, Kind::Data, &vars),
+            Some(PathBuf::from("/home/u/.local/share/pwpush-cli"))
+        );
+
+        let vars = lookup(&[("HOME", "/home/u"), (KEY_DIR_VAR, "/media/keys")]);
+        assert_eq!(
+            resolve(Os::Mac, Kind::Keys, &vars),
+            Some(PathBuf::from("/media/keys"))
+        );
+    }
+}
@@ -0,0 +1,474 @@
+//! Sources of the payload of a text push.
+//!
+//! The payload can be given as positional argument, read from a file with
+//! `--payload-file`, read from stdin with `--stdin`, typed in with hidden
+//! input with `--prompt`, or read from a password manager with `--from-op`,
+//! `--from-bw` or `--from-vault`, see `password_managers` and `vault`.
+//! `--from-env` assembles the payload from environment variables, one
+//! `KEY=value` line each, for CI jobs that hand over credentials they just
+//! generated. GitHub Actions prints such values in the job log unless they are
+//! masked, so they are registered with `::add-mask::` there, see `mask`.
+//! Arguments are visible to other users of the machine
+//! in the process list and usually end up in the shell history. So if a secret
+//! is given as argument in an interactive shell, a warning points to the
+//! alternatives. It can be turned off with `--no-warn`, or with
+//! `warn_positional_secret = false` in the `[push]` section of the
+//! configuration file.
+//!
+//! Text pushes can only hold text, so input from files and stdin must be valid
+//! UTF-8. Binary data like keys or certificates can be pushed with `--base64`,
+//! which encodes the input exactly as it was read, and retrieved again with
+//! `get --decode-base64`. Windows tools like PowerShell end lines with CRLF,
+//! which becomes part of multi-line secrets, `--normalize-newlines` converts
+//! them to LF. With `--encrypt-for`, the payload is encrypted last,
+//! see `crypto`. Payloads larger than the instance accepts are refused before sending
+//! them. Instances limit payloads to 1 MiB by default, a different limit can be
+//! configured with `max_payload_size` in the `[push]` section.
+
+use std::env;
+use std::fs::File;
+use std::io::{self, IsTerminal, Read};
+use std::path::Path;
+
+use base64::prelude::{Engine, BASE64_STANDARD};
+use secrecy::{ExposeSecret, SecretString};
+use tracing::warn;
+use zeroize::Zeroizing;
+
+use crate::args::PPCText;
+use crate::config::PPCConfig;
+use crate::crypto;
+use crate::errors::PPCError;
+use crate::i18n;
+use crate::password_managers::{self, Source};
+use crate::platform;
+use crate::prompt;
+use crate::vault::Vault;
+
+/// Payload size limit of Password Pusher instances in the default
+/// configuration, in bytes.
+pub const DEFAULT_MAX_PAYLOAD_SIZE: usize = 1024 * 1024;
+
+/// The payload of the push, from whichever source the user selected.
+pub fn resolve(ppc_text: &PPCText, config: &PPCConfig) -> Result<SecretString, PPCError> {
+    prepare(read(ppc_text, config)?, ppc_text, config)
+}
+
+/// Encrypt the payload as it was read, if requested, and check its size.
+/// Split from `resolve` for callers that need the payload before encryption,
+/// which is random for each push.
+pub fn prepare(
+    mut payload: SecretString,
+    ppc_text: &PPCText,
+    config: &PPCConfig,
+) -> Result<SecretString, PPCError> {
+    if let Some(recipient) = &ppc_text.encrypt_for {
+        payload = crypto::encrypt(payload.expose_secret().as_bytes(), recipient)?;
+    }
+
+    let limit = config
+        .push
+        .max_payload_size
+        .unwrap_or(DEFAULT_MAX_PAYLOAD_SIZE);
+    check_size(payload.expose_secret().len(), limit)?;
+
+    Ok(payload)
+}
+
+/// The payload as given by the user, before encryption.
+pub fn read(ppc_text: &PPCText, config: &PPCConfig) -> Result<SecretString, PPCError> {
+    let encoding = Encoding::of(ppc_text);
+    if let Some(path) = &ppc_text.payload_file {
+        return read_file(path, encoding);
+    }
+    if ppc_text.stdin {
+        return read_payload(&mut io::stdin().lock(), "stdin", encoding);
+    }
+    if ppc_text.prompt {
+        return prompt::secret("Secret: ").map(|secret| maybe_encode(secret, ppc_text.base64));
+    }
+    if let Some(item) = &ppc_text.from_op {
+        return password_managers::read(Source::OnePassword(item))
+            .map(|secret| maybe_encode(secret, ppc_text.base64));
+    }
+    if let Some(item) = &ppc_text.from_bw {
+        return password_managers::read(Source::Bitwarden(item))
+            .map(|secret| maybe_encode(secret, ppc_text.base64));
+    }
+    if let Some(secret) = &ppc_text.from_vault {
+        return Vault::from_env()?
+            .read(platform::transport()?, secret)
+            .map(|secret| maybe_encode(secret, ppc_text.base64));
+    }
+    if !ppc_text.from_env.is_empty() {
+        let lines = env_lines(&ppc_text.from_env, |name| env::var(name).ok())?;
+        if env::var("GITHUB_ACTIONS").as_deref() == Ok("true") {
+            mask(&ppc_text.from_env, |name| env::var(name).ok());
+        }
+        return Ok(maybe_encode(
+            SecretString::from(lines.as_str()),
+            ppc_text.base64,
+        ));
+    }
+
+    if should_warn(ppc_text, config, io::stdin().is_terminal()) {
+        warn!("{}", i18n::text("push-secret-argument"));
+    }
+
+    // clap ensures that the payload is given if no other source is.
+    ppc_text
+        .password_payload
+        .clone()
+        .map(|secret| maybe_encode(secret, ppc_text.base64))
+        .ok_or_else(|| PPCError::from("no secret given"))
+}
+
+/// The variables as `KEY=value` lines, in the order they were given.
+/// Variables that are not set, or not valid unicode, are an error, as a
+/// payload with a missing credential is of no use to the recipient.
+fn env_lines(
+    names: &[String],
+    var: impl Fn(&str) -> Option<String>,
+) -> Result<Zeroizing<String>, PPCError> {
+    let mut lines = Zeroizing::new(String::new());
+    for name in names {
+        let value = Zeroizing::new(var(name).ok_or_else(|| {
+            PPCError::from(format!(
+                "environment variable {} for --from-env is not set",
+                name
+            ))
+        })?);
+        if !lines.is_empty() {
+            lines.push('\n');
+        }
+        lines.push_str(&format!("{}={}", name, value.as_str()));
+    }
+    Ok(lines)
+}
+
+/// Tell GitHub Actions to mask the values in the job log, by printing one
+/// `::add-mask::` command per line of each value. The commands themselves are
+/// not shown in the log.
+fn mask(names: &[String], var: impl Fn(&str) -> Option<String>) {
+    for name in names {
+        let value = Zeroizing::new(var(name).unwrap_or_default());
+        for line in value.lines().filter(|line| !line.trim().is_empty()) {
+            println!("::add-mask::{}", line.replace('%', "%25"));
+        }
+    }
+}
+
+fn maybe_encode(secret: SecretString, base64: bool) -> SecretString {
+    if base64 {
+        encode(secret.expose_secret().as_bytes())
+    } else {
+        secret
+    }
+}
+
+fn encode(bytes: &[u8]) -> SecretString {
+    SecretString::from(BASE64_STANDARD.encode(bytes))
+}
+
+/// Decode a payload that was pushed with `--base64`.
+pub fn decode(payload: &str) -> Result<Zeroizing<Vec<u8>>, PPCError> {
+    // line breaks are tolerated, as some tools wrap base64 at 76 characters.
+    let compact = Zeroizing::new(payload.split_whitespace().collect::<String>());
+    BASE64_STANDARD
+        .decode(compact.as_bytes())
+        .map(Zeroizing::new)
+        .map_err(|e| PPCError::from(format!("the payload is not valid base64: {}", e)))
+}
+
+fn should_warn(ppc_text: &PPCText, config: &PPCConfig, interactive: bool) -> bool {
+    interactive
+        && ppc_text.password_payload.is_some()
+        && !ppc_text.no_warn
+        && config.push.warn_positional_secret.unwrap_or(true)
+}
+
+/// How input from files and stdin is turned into the payload.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Encoding {
+    /// UTF-8 text as is
+    Text,
+    /// UTF-8 text with CRLF line breaks converted to LF
+    NormalizedText,
+    /// Any bytes, encoded as base64
+    Base64,
+}
+
+impl Encoding {
+    fn of(ppc_text: &PPCText) -> Self {
+        if ppc_text.base64 {
+            Encoding::Base64
+        } else if ppc_text.normalize_newlines {
+            Encoding::NormalizedText
+        } else {
+            Encoding::Text
+        }
+    }
+}
+
+fn read_file(path: &Path, encoding: Encoding) -> Result<SecretString, PPCError> {
+    let source = path.display().to_string();
+    let mut file = File::open(path)
+        .map_err(|e| PPCError::from(format!("could not open {}: {}", source, e)))?;
+
+    read_payload(&mut file, &source, encoding)
+}
+
+/// Read the whole input as payload. Text input must be valid UTF-8, and a
+/// single trailing line break is removed, as `echo` and most editors add one
+/// that is not part of the secret. With base64, the input is encoded as is.
+/// `source` is only used in error messages.
+fn read_payload(
+    input: &mut dyn Read,
+    source: &str,
+    encoding: Encoding,
+) -> Result<SecretString, PPCError> {
+    let mut bytes = Zeroizing::new(Vec::new());
+    input
+        .read_to_end(&mut bytes)
+        .map_err(|e| PPCError::from(format!("could not read secret from {}: {}", source, e)))?;
+
+    if bytes.is_empty() {
+        return Err(PPCError::from(format!(
+            "the secret read from {} is empty",
+            source
+        )));
+    }
+    if encoding == Encoding::Base64 {
+        return Ok(encode(&bytes));
+    }
+
+    if encoding == Encoding::NormalizedText {
+        let mut normalized = Zeroizing::new(Vec::with_capacity(bytes.len()));
+        for (index, byte) in bytes.iter().enumerate() {
+            if *byte != b'\r' || bytes.get(index + 1) != Some(&b'\n') {
+                normalized.push(*byte);
+            }
+        }
+        bytes = normalized;
+    }
+
+    if bytes.ends_with(b"\n") {
+        bytes.pop();
+        if bytes.ends_with(b"\r") {
+            bytes.pop();
+        }
+    }
+
+    let text = std::str::from_utf8(&bytes).map_err(|e| {
+        PPCError::from(format!(
+            "the secret from {} is not valid UTF-8 text (invalid byte at offset {}), \
+             use --base64 to push binary data",
+            source,
+            e.valid_up_to()
+        ))
+    })?;
+
+    if text.is_empty() {
+        return Err(PPCError::from(format!(
+            "the secret read from {} is empty",
+            source
+        )));
+    }
+    Ok(SecretString::from(text))
+}
+
+pub fn check_size(size: usize, limit: usize) -> Result<(), PPCError> {
+    if size > limit {
+        return Err(PPCError::from(format!(
+            "the secret is {} bytes long, but the instance only accepts up to {} bytes",
+            size, limit
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+    use secrecy::ExposeSecret;
+
+    use super::{check_size, decode, env_lines, read_payload, should_warn, Encoding};
+    use crate::args::{PPCAction, PPCArgs, PPCObject, PPCText};
+    use crate::config::PPCConfig;
+
+    fn text(cmdline: &[&str]) -> PPCText {
+        match PPCArgs::parse_from(cmdline).action {
+            PPCAction::Push(PPCObject::Text(text)) => *text,
+            other => panic!("not a text push: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn password_manager_sources() {
+        let text = text(&[
+            "pwpush-cli",
+            "push",
+            "text",
+            "--from-op",
+            "op://ops/db/password",
+        ]);
+        assert_eq!(text.from_op.as_deref(), Some("op://ops/db/password"));
+        assert!(text.password_payload.is_none());
+
+        for conflicting in [
+            &["pwpush-cli", "push", "text", "--from-bw", "db", "secret"][..],
+            &[
+                "pwpush-cli",
+                "push",
+                "text",
+                "--from-bw",
+                "db",
+                "--from-op",
+                "db",
+            ][..],
+            &["pwpush-cli", "push", "text", "--from-op", "db", "--stdin"][..],
+        ] {
+            assert!(PPCArgs::try_parse_from(conflicting).is_err());
+        }
+    }
+
+    #[test]
+    fn payload_from_environment() {
+        let text = text(&[
+            "pwpush-cli",
+            "push",
+            "text",
+            "--from-env",
+            "DB_USER,DB_PASSWORD",
+        ]);
+        assert_eq!(text.from_env, ["DB_USER", "DB_PASSWORD"]);
+
+        let var = |name: &str| match name {
+            "DB_USER" => Some(String::from("app")),
+            "DB_PASSWORD" => Some(String::from("s3cr=t")),
+            _ => None,
+        };
+        let lines = env_lines(&text.from_env, var).unwrap();
+        assert_eq!(lines.as_str(), "DB_USER=app\nDB_PASSWORD=s3cr=t");
+
+        let missing = env_lines(&[String::from("DB_HOST")], var).unwrap_err();
+        assert!(missing.to_string().contains("DB_HOST"));
+    }
+
+    #[test]
+    fn read_payload_strips_one_line_break() {
+        let read = |input: &str| {
+            read_payload(&mut input.as_bytes(), "stdin", Encoding::Text)
+                .unwrap()
+                .expose_secret()
+                .to_string()
+        };
+
+        assert_eq!(read("secret\n"), "secret");
+        assert_eq!(read("secret\r\n"), "secret");
+        assert_eq!(read("two\nlines\n\n"), "two\nlines\n");
+        assert!(read_payload(&mut "\n".as_bytes(), "stdin", Encoding::Text).is_err());
+    }
+
+    #[test]
+    fn read_payload_normalizes_crlf() {
+        let read = |input: &str, encoding| {
+            read_payload(&mut input.as_bytes(), "stdin", encoding)
+                .unwrap()
+                .expose_secret()
+                .to_string()
+        };
+        let key = "-----BEGIN KEY-----\r\nabc\r\n-----END KEY-----\r\n";
+
+        assert_eq!(
+            read(key, Encoding::NormalizedText),
+            "-----BEGIN KEY-----\nabc\n-----END KEY-----"
+        );
+        assert_eq!(
+            read(key, Encoding::Text),
+            "-----BEGIN KEY-----\r\nabc\r\n-----END KEY-----"
+        );
+        // a lone carriage return is not a Windows line break.
+        assert_eq!(read("a\rb\r\n", Encoding::NormalizedText), "a\rb");
+    }
+
+    #[test]
+    fn read_payload_rejects_invalid_utf8() {
+        let err = read_payload(&mut &b"ok\xff\xfe"[..], "key.bin", Encoding::Text).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "the secret from key.bin is not valid UTF-8 text (invalid byte at offset 2), \
+             use --base64 to push binary data"
+        );
+    }
+
+    #[test]
+    fn read_payload_keeps_unicode() {
+        let payload = read_payload(&mut "pässwörd 🔑".as_bytes(), "stdin", Encoding::Text).unwrap();
+
+        assert_eq!(payload.expose_secret(), "pässwörd 🔑");
+    }
+
+    #[test]
+    fn base64_roundtrip_keeps_bytes() {
+        let binary = b"\x00\xff\xfe binary\n";
+        let payload = read_payload(&mut &binary[..], "key.bin", Encoding::Base64).unwrap();
+
+        assert_eq!(payload.expose_secret(), "AP/+IGJpbmFyeQo=");
+        assert_eq!(&decode(payload.expose_secret()).unwrap()[..], &binary[..]);
+    }
+
+    #[test]
+    fn decode_tolerates_line_breaks() {
+        assert_eq!(&decode("aGVs\nbG8=\n").unwrap()[..], b"hello");
+        assert!(decode("not base64!").is_err());
+    }
+
+    #[test]
+    fn check_size_limit() {
+        assert!(check_size(1024, 1024).is_ok());
+        assert!(check_size(1025, 1024).is_err());
+    }
+
+    #[test]
+    fn warn_only_for_interactive_arguments() {
+        let config = PPCConfig::default();
+        let positional = text(&["pwpush-cli", "push", "text", "secret"]);
+
+        assert!(should_warn(&positional, &config, true));
+        assert!(!should_warn(&positional, &config, false));
+        assert!(!should_warn(
+            &text(&["pwpush-cli", "push", "text", "--stdin"]),
+            &config,
+            true
+        ));
+    }
+
+    #[test]
+    fn warn_can_be_disabled() {
+        let config: PPCConfig = toml::from_str("[push]\nwarn_positional_secret = false").unwrap();
+
+        assert!(!should_warn(
+            &text(&["pwpush-cli", "push", "text", "secret"]),
+            &config,
+            true
+        ));
+        assert!(!should_warn(
+            &text(&["pwpush-cli", "push", "text", "secret", "--no-warn"]),
+            &PPCConfig::default(),
+            true
+        ));
+    }
+
+    #[test]
+    fn payload_sources_are_exclusive() {
+        assert!(PPCArgs::try_parse_from(["pwpush-cli", "push", "text"]).is_err());
+        assert!(
+            PPCArgs::try_parse_from(["pwpush-cli", "push", "text", "secret", "--stdin"]).is_err()
+        );
+        assert!(
+            PPCArgs::try_parse_from(["pwpush-cli", "push", "text", "--stdin", "--prompt"]).is_err()
+        );
+    }
+}
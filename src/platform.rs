@@ -0,0 +1,70 @@
+//! Services of the operating system, with fallbacks for WebAssembly.
+//!
+//! The library also builds for `wasm32-unknown-unknown`, so that web and
+//! Tauri frontends can reuse the request building and response parsing, see
+//! `client`. There is no operating system in the browser: no host name, no
+//! terminal, no hidden input, no signals and no blocking HTTP. Everything
+//! that needs one goes through this module, so the rest of the code does not
+//! have to care.
+
+use std::io;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::OnceLock;
+
+use crate::errors::PPCError;
+use crate::http::Transport;
+
+/// Name of this host, empty if it is not known.
+pub fn hostname() -> String {
+    #[cfg(not(target_arch = "wasm32"))]
+    return gethostname::gethostname().to_string_lossy().into_owned();
+    #[cfg(target_arch = "wasm32")]
+    return String::new();
+}
+
+/// Width and height of the terminal in columns and rows, if there is one.
+pub fn terminal_size() -> Option<(usize, usize)> {
+    #[cfg(not(target_arch = "wasm32"))]
+    return terminal_size::terminal_size()
+        .map(|(terminal_size::Width(w), terminal_size::Height(h))| (w as usize, h as usize));
+    #[cfg(target_arch = "wasm32")]
+    return None;
+}
+
+/// Print the question and read a line from the terminal without echoing it.
+pub fn read_password(question: &str) -> io::Result<String> {
+    #[cfg(not(target_arch = "wasm32"))]
+    return rpassword::prompt_password(question);
+    #[cfg(target_arch = "wasm32")]
+    return Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("no terminal to read from ({})", question.trim_end()),
+    ));
+}
+
+/// Call the handler on SIGINT and SIGTERM, or Ctrl+C on Windows.
+pub fn on_interrupt(handler: fn()) -> Result<(), String> {
+    #[cfg(not(target_arch = "wasm32"))]
+    return ctrlc::set_handler(handler).map_err(|e| e.to_string());
+    #[cfg(target_arch = "wasm32")]
+    {
+        // nothing to interrupt in the browser.
+        let _ = handler;
+        Ok(())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+static TRANSPORT: OnceLock<crate::http::ReqwestTransport> = OnceLock::new();
+
+/// A transport for requests that do not go to the instance, e.g. to Vault or
+/// to webhooks. It is built on first use and shared by all of them, so they
+/// reuse its connections like the requests to the instance do.
+pub fn transport() -> Result<&'static dyn Transport, PPCError> {
+    #[cfg(not(target_arch = "wasm32"))]
+    return Ok(TRANSPORT.get_or_init(crate::http::ReqwestTransport::new));
+    #[cfg(target_arch = "wasm32")]
+    return Err(PPCError::from(
+        "blocking requests are not available in WebAssembly",
+    ));
+}
@@ -0,0 +1,321 @@
+//! Rules of an organization that apply to every push, set by an administrator.
+//!
+//! The configuration file belongs to the user, so it cannot enforce anything.
+//! A policy file at `/etc/pwpush-cli/policy.toml` (or
+//! `%ProgramData%\pwpush-cli\policy.toml` on Windows) is read in addition, and
+//! its rules cannot be changed from the command line or the configuration:
+//!
+//! ```toml
+//! # instances that may be used, as given with --url. Any instance if not set
+//! allowed_instances = ["pwpush.example.com", "intranet.example.com/pwpush"]
+//! max_expire_after_days = 7
+//! max_expire_after_views = 10
+//! require_passphrase = true
+//! # "refuse" (default) fails pushes with higher limits, "adjust" lowers them
+//! # to the maximum with a warning
+//! enforcement = "adjust"
+//! # payloads and passphrases must not be given as arguments
+//! forbid_argv_secrets = true
+//! ```
+//!
+//! Limits that are not given at all are set to the maximum, as the defaults of
+//! the instance are unknown. A missing passphrase is always refused, as a
+//! generated one might not reach the recipient. An invalid policy file is an
+//! error, so a typo never turns the policy off.
+//!
+//! This keeps honest mistakes from leaking secrets for longer than intended,
+//! it does not stop users who talk to the instance with other tools.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use tracing::{debug, info, warn};
+
+use crate::args::{PPCFile, PPCText};
+use crate::errors::PPCError;
+use crate::i18n;
+use crate::instance::Instance;
+
+/// What happens to pushes with higher limits than allowed.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Enforcement {
+    /// Fail the push
+    #[default]
+    Refuse,
+
+    /// Lower the limits to the maximum, with a warning
+    Adjust,
+}
+
+/// The rules of the policy file. The default policy allows everything.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Policy {
+    /// Instances that may be used, any instance if not set
+    pub allowed_instances: Option<Vec<String>>,
+    pub max_expire_after_days: Option<usize>,
+    pub max_expire_after_views: Option<usize>,
+    pub require_passphrase: bool,
+    pub enforcement: Enforcement,
+
+    /// Refuse payloads and passphrases given as arguments, see `argv_secrets`
+    pub forbid_argv_secrets: bool,
+
+    /// The file the policy was read from, to point users to it
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Policy {
+    /// Fail if the instance is not one of the allowed instances.
+    pub fn check_instance(&self, instance: &Instance) -> Result<(), PPCError> {
+        let Some(allowed) = &self.allowed_instances else {
+            return Ok(());
+        };
+
+        let normalize = |url: &str| url.trim_end_matches('/').to_lowercase();
+        let url = normalize(&instance.url);
+        if allowed.iter().any(|allowed| normalize(allowed) == url) {
+            return Ok(());
+        }
+        Err(self.violation(format!(
+            "instance {} is not allowed, use one of {}",
+            instance.url,
+            allowed.join(", ")
+        )))
+    }
+
+    /// Bring the options of a push in line with the policy, or fail if they
+    /// cannot be.
+    pub fn enforce(&self, ppc_text: &mut PPCText) -> Result<(), PPCError> {
+        self.enforce_options(
+            ppc_text.passphrase.is_some(),
+            &mut ppc_text.expire_after_days,
+            &mut ppc_text.expire_after_views,
+        )
+    }
+
+    /// Like `enforce`, for file pushes.
+    pub fn enforce_file(&self, ppc_file: &mut PPCFile) -> Result<(), PPCError> {
+        self.enforce_options(
+            ppc_file.passphrase.is_some(),
+            &mut ppc_file.expire_after_days,
+            &mut ppc_file.expire_after_views,
+        )
+    }
+
+    fn enforce_options(
+        &self,
+        has_passphrase: bool,
+        expire_after_days: &mut Option<usize>,
+        expire_after_views: &mut Option<usize>,
+    ) -> Result<(), PPCError> {
+        if self.require_passphrase && !has_passphrase {
+            return Err(self.violation(String::from("a passphrase is required, use --passphrase")));
+        }
+
+        *expire_after_days = self.limit(
+            "expire_after_days",
+            *expire_after_days,
+            self.max_expire_after_days,
+        )?;
+        *expire_after_views = self.limit(
+            "expire_after_views",
+            *expire_after_views,
+            self.max_expire_after_views,
+        )?;
+        Ok(())
+    }
+
+    fn limit(
+        &self,
+        name: &str,
+        value: Option<usize>,
+        max: Option<usize>,
+    ) -> Result<Option<usize>, PPCError> {
+        let Some(max) = max else {
+            return Ok(value);
+        };
+
+        match value {
+            None => {
+                debug!("setting {} to the policy maximum of {}", name, max);
+                Ok(Some(max))
+            }
+            Some(value) if value <= max => Ok(Some(value)),
+            Some(value) => match self.enforcement {
+                Enforcement::Refuse => Err(self.violation(format!(
+                    "{} is {}, but at most {} is allowed",
+                    name, value, max
+                ))),
+                Enforcement::Adjust => {
+                    warn!(
+                        "{}",
+                        i18n::format(
+                            "policy-lowered",
+                            &[
+                                ("name", name.into()),
+                                ("value", value.to_string().into()),
+                                ("max", max.to_string().into()),
+                                ("path", self.path.display().to_string().into())
+                            ]
+                        )
+                    );
+                    Ok(Some(max))
+                }
+            },
+        }
+    }
+
+    /// An error for something the policy does not allow.
+    pub fn violation(&self, reason: String) -> PPCError {
+        PPCError::from(format!(
+            "refused by the policy in {}: {}",
+            self.path.display(),
+            reason
+        ))
+    }
+}
+
+/// Load the policy from its system-wide location. Without a policy file,
+/// everything is allowed.
+pub fn load() -> Result<Policy, PPCError> {
+    match path() {
+        Ok(path) => load_from(&path),
+        Err(_) => Ok(Policy::default()),
+    }
+}
+
+pub fn load_from(path: &Path) -> Result<Policy, PPCError> {
+    if !path.exists() {
+        debug!("no policy file at {}", path.display());
+        return Ok(Policy::default());
+    }
+
+    info!("loading policy from {}", path.display());
+    let invalid = |reason: String| {
+        PPCError::from(format!(
+            "invalid policy file {}: {}",
+            path.display(),
+            reason
+        ))
+    };
+    let content = fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+    let mut policy: Policy = toml::from_str(&content).map_err(|e| invalid(e.to_string()))?;
+    policy.path = path.to_path_buf();
+    Ok(policy)
+}
+
+/// The system-wide location of the policy file.
+pub fn path() -> Result<PathBuf, PPCError> {
+    if cfg!(windows) {
+        let base = std::env::var_os("ProgramData")
+            .ok_or_else(|| PPCError::from("%ProgramData% is not set"))?;
+        Ok(PathBuf::from(base).join("pwpush-cli").join("policy.toml"))
+    } else {
+        Ok(PathBuf::from("/etc/pwpush-cli/policy.toml"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use clap::Parser;
+    use secrecy::SecretString;
+
+    use super::{load_from, Policy};
+    use crate::args::{InstanceProtocol, PPCAction, PPCArgs, PPCObject, PPCText};
+    use crate::instance::Instance;
+
+    fn policy(content: &str) -> Policy {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("policy.toml");
+        fs::write(&path, content).unwrap();
+        load_from(&path).unwrap()
+    }
+
+    fn text(argv: &[&str]) -> PPCText {
+        let argv = ["pwpush-cli", "push", "text", "secret"]
+            .iter()
+            .chain(argv)
+            .copied();
+        let PPCAction::Push(PPCObject::Text(ppc_text)) = PPCArgs::parse_from(argv).action else {
+            panic!("not a text push");
+        };
+        *ppc_text
+    }
+
+    fn instance(url: &str) -> Instance {
+        Instance {
+            protocol: InstanceProtocol::Https,
+            url: String::from(url),
+            email: None,
+            token: None,
+            auth_scheme: None,
+        }
+    }
+
+    #[test]
+    fn refuses_higher_limits() {
+        let policy = policy("max_expire_after_days = 7\n");
+
+        let mut ppc_text = text(&["--expire-after-days", "30"]);
+        let err = policy.enforce(&mut ppc_text).unwrap_err();
+        assert!(err.to_string().contains("at most 7"));
+
+        let mut ppc_text = text(&["--expire-after-days", "3"]);
+        policy.enforce(&mut ppc_text).unwrap();
+        assert_eq!(ppc_text.expire_after_days, Some(3));
+    }
+
+    #[test]
+    fn adjusts_limits() {
+        let policy = policy(
+            "max_expire_after_days = 7\nmax_expire_after_views = 2\nenforcement = \"adjust\"\n",
+        );
+
+        let mut ppc_text = text(&["--expire-after-days", "30"]);
+        policy.enforce(&mut ppc_text).unwrap();
+
+        assert_eq!(ppc_text.expire_after_days, Some(7));
+        // the default of the instance is unknown, so the maximum is used.
+        assert_eq!(ppc_text.expire_after_views, Some(2));
+    }
+
+    #[test]
+    fn requires_passphrase() {
+        let policy = policy("require_passphrase = true\n");
+
+        let mut ppc_text = text(&[]);
+        assert!(policy.enforce(&mut ppc_text).is_err());
+
+        ppc_text.passphrase = Some(SecretString::from("correct horse"));
+        assert!(policy.enforce(&mut ppc_text).is_ok());
+    }
+
+    #[test]
+    fn pins_instances() {
+        let policy = policy("allowed_instances = [\"pwpush.example.com/\"]\n");
+
+        assert!(policy
+            .check_instance(&instance("PWPush.example.com"))
+            .is_ok());
+        assert!(policy.check_instance(&instance("pwpush.com")).is_err());
+        assert!(Policy::default()
+            .check_instance(&instance("pwpush.com"))
+            .is_ok());
+    }
+
+    #[test]
+    fn invalid_files_are_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("policy.toml");
+        fs::write(&path, "max_expire_after_day = 7\n").unwrap();
+
+        assert!(load_from(&path).is_err());
+        assert!(load_from(&dir.path().join("missing.toml")).is_ok());
+    }
+}
@@ -0,0 +1,167 @@
+//! Management of push presets in the configuration file.
+//!
+//! Presets are read as part of the regular configuration (see `config`), but
+//! adding and removing them needs to write to the configuration file. This is
+//! done with `toml_edit`, so comments and formatting of the rest of the file
+//! survive the modification.
+
+use std::path::Path;
+
+use serde_json::json;
+use toml_edit::{value, Item, Table};
+use tracing::info;
+
+use crate::args::{PPCArgs, PresetCommand};
+use crate::config::{self, PPCConfig, Preset};
+use crate::errors::PPCError;
+use crate::i18n;
+use crate::output;
+
+/// Entry point for the `preset` subcommands.
+pub fn run(args: &PPCArgs, config: &PPCConfig, command: &PresetCommand) -> Result<(), PPCError> {
+    match command {
+        PresetCommand::List => list(args, config),
+        PresetCommand::Add(preset_add) => {
+            let path = config::path(args.config_file.as_deref())?;
+            let preset = Preset {
+                expire_after_days: preset_add.expire_after_days,
+                expire_after_views: preset_add.expire_after_views,
+                deletable_by_viewer: preset_add.deletable_by_viewer,
+                retrieval_step: preset_add.retrieval_step,
+                note_prefix: preset_add.note_prefix.clone(),
+                passphrase_length: preset_add.passphrase_length,
+            };
+            add(&path, &preset_add.name, &preset)
+        }
+        PresetCommand::Remove { name } => {
+            let path = config::path(args.config_file.as_deref())?;
+            remove(&path, name)
+        }
+    }
+}
+
+fn list(args: &PPCArgs, config: &PPCConfig) -> Result<(), PPCError> {
+    if args.json_output {
+        // preset names are keys, so the presets are wrapped to keep them
+        // apart from the schema_version.
+        output::print_json(&json!({ "presets": config.presets }));
+        return Ok(());
+    }
+
+    if config.presets.is_empty() {
+        println!("{}", i18n::text("presets-none"));
+    }
+
+    for (name, preset) in &config.presets {
+        println!("{}", name);
+        // same reasoning as for json above.
+        for line in toml::to_string(preset).unwrap().lines() {
+            println!("    {}", line);
+        }
+    }
+
+    Ok(())
+}
+
+fn add(path: &Path, name: &str, preset: &Preset) -> Result<(), PPCError> {
+    let mut document = config::read_document(path)?;
+
+    if !document.contains_key("presets") {
+        let mut presets = Table::new();
+        presets.set_implicit(true);
+        document.insert("presets", Item::Table(presets));
+    }
+    let presets = document["presets"]
+        .as_table_mut()
+        .ok_or_else(|| PPCError::from("`presets` in the configuration file is not a table"))?;
+
+    presets.insert(name, Item::Table(preset_table(preset)));
+
+    config::write_document(path, &document)?;
+    info!("saved preset `{}` to {}", name, path.display());
+    Ok(())
+}
+
+fn remove(path: &Path, name: &str) -> Result<(), PPCError> {
+    let mut document = config::read_document(path)?;
+
+    let removed = document
+        .get_mut("presets")
+        .and_then(Item::as_table_mut)
+        .and_then(|presets| presets.remove(name));
+    if removed.is_none() {
+        return Err(PPCError::from(format!("preset `{}` does not exist", name)));
+    }
+
+    config::write_document(path, &document)?;
+    info!("removed preset `{}` from {}", name, path.display());
+    Ok(())
+}
+
+fn preset_table(preset: &Preset) -> Table {
+    let mut table = Table::new();
+
+    if let Some(days) = preset.expire_after_days {
+        table.insert("expire_after_days", value(days as i64));
+    }
+    if let Some(views) = preset.expire_after_views {
+        table.insert("expire_after_views", value(views as i64));
+    }
+    if let Some(deletable) = preset.deletable_by_viewer {
+        table.insert("deletable_by_viewer", value(deletable));
+    }
+    if let Some(retrieval_step) = preset.retrieval_step {
+        table.insert("retrieval_step", value(retrieval_step));
+    }
+    if let Some(prefix) = &preset.note_prefix {
+        table.insert("note_prefix", value(prefix.as_str()));
+    }
+    if let Some(length) = preset.passphrase_length {
+        table.insert("passphrase_length", value(length as i64));
+    }
+
+    table
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::{add, remove};
+    use crate::config::{self, Preset};
+
+    #[test]
+    fn add_creates_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sub").join("config.toml");
+        let preset = Preset {
+            expire_after_days: Some(2),
+            retrieval_step: Some(true),
+            ..Preset::default()
+        };
+
+        add(&path, "handoff", &preset).unwrap();
+
+        let config = config::load(Some(&path)).unwrap();
+        assert_eq!(config.presets.get("handoff"), Some(&preset));
+    }
+
+    #[test]
+    fn add_and_remove_keep_comments() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, "# my settings\n[push]\nexpire_after_days = 3\n").unwrap();
+
+        add(&path, "handoff", &Preset::default()).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("# my settings\n"));
+        assert!(content.contains("[presets.handoff]"));
+
+        remove(&path, "handoff").unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("# my settings\n"));
+        assert!(!content.contains("handoff"));
+
+        assert!(remove(&path, "handoff").is_err());
+    }
+}
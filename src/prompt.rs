@@ -0,0 +1,114 @@
+//! Interactive confirmation for destructive operations, and hidden input of
+//! secrets.
+//!
+//! Prompts go to stderr (or the terminal itself for hidden input), so they
+//! never end up in output that is piped into other programs. If stdin is not a
+//! terminal, nobody can answer the prompt, so the operation is refused unless
+//! `--yes` was given. Silently assuming either answer would be surprising in
+//! scripts.
+
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use secrecy::SecretString;
+
+use crate::errors::PPCError;
+use crate::{i18n, platform, terminal};
+
+/// Ask the user to confirm `question`. Returns `true` without asking if
+/// `assume_yes` is set.
+pub fn confirm(question: &str, assume_yes: bool) -> Result<bool, PPCError> {
+    if assume_yes {
+        return Ok(true);
+    }
+
+    confirm_with(
+        question,
+        io::stdin().is_terminal(),
+        &mut io::stdin().lock(),
+        &mut io::stderr(),
+    )
+}
+
+/// Ask the user for a secret, without echoing the input. Fails in
+/// non-interactive mode, callers should point to the option that provides the
+/// value instead.
+pub fn secret(question: &str) -> Result<SecretString, PPCError> {
+    if !io::stdin().is_terminal() {
+        return Err(PPCError::from(format!(
+            "cannot prompt in non-interactive mode ({})",
+            question.trim_end_matches([':', ' '])
+        )));
+    }
+    if !terminal::supports_hidden_input() {
+        return Err(PPCError::from(format!(
+            "cannot read hidden input in this terminal ({}), run through winpty or use --stdin",
+            question.trim_end_matches([':', ' '])
+        )));
+    }
+
+    platform::read_password(question)
+        .map(SecretString::from)
+        .map_err(|e| PPCError::from(format!("could not read input: {}", e)))
+}
+
+fn confirm_with(
+    question: &str,
+    interactive: bool,
+    input: &mut dyn BufRead,
+    output: &mut dyn Write,
+) -> Result<bool, PPCError> {
+    if !interactive {
+        return Err(PPCError::from(i18n::format(
+            "error-confirm-non-interactive",
+            &[("question", question.into())],
+        )));
+    }
+
+    let io_err = |e: io::Error| PPCError::from(format!("could not read confirmation: {}", e));
+    write!(output, "{} {} ", question, i18n::text("prompt-yes-no")).map_err(io_err)?;
+    output.flush().map_err(io_err)?;
+
+    let mut answer = String::new();
+    input.read_line(&mut answer).map_err(io_err)?;
+
+    Ok(i18n::is_yes(&answer))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{confirm, confirm_with};
+
+    fn ask(answer: &str) -> bool {
+        let mut output = Vec::new();
+        let result = confirm_with("expire?", true, &mut answer.as_bytes(), &mut output).unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), "expire? [y/N] ");
+        result
+    }
+
+    #[test]
+    fn confirm_accepts_yes() {
+        assert!(ask("y\n"));
+        assert!(ask("YES\n"));
+    }
+
+    #[test]
+    fn confirm_defaults_to_no() {
+        assert!(!ask("\n"));
+        assert!(!ask("nope\n"));
+        assert!(!ask(""));
+    }
+
+    #[test]
+    fn confirm_refuses_non_interactive() {
+        let mut output = Vec::new();
+
+        assert!(confirm_with("expire?", false, &mut "y\n".as_bytes(), &mut output).is_err());
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn confirm_assume_yes() {
+        assert!(confirm("expire?", true).unwrap());
+    }
+}
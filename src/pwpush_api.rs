@@ -1,10 +1,92 @@
-use std::fmt;
+use std::collections::HashSet;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
 
-use log::debug;
-use reqwest;
-use urlencoding;
+use secrecy::ExposeSecret;
+use tracing::{debug, warn};
+use url::Url;
+use zeroize::Zeroize;
 
-use crate::{args, errors::PPCError};
+use crate::{
+    api_body, api_url,
+    archive::Archive,
+    args,
+    errors::{PPCError, EXIT_EXPIRED, EXIT_UNAUTHORIZED},
+    http::{Method, PPCRequest, PPCResponse, Transport},
+    i18n,
+    instance::Instance,
+    localized::{self, InstanceError},
+    models,
+    queue::Queue,
+    skew,
+};
+
+/// Build the request to create a text push. The body contains the payload,
+/// so it should be wiped after use.
+pub fn text_push_request(instance: &Instance, ppc_text: &args::PPCText) -> PPCRequest {
+    body_push_request(instance, api_body::push_form(ppc_text))
+}
+
+/// Build the request to create a text push from its form body.
+fn body_push_request(instance: &Instance, body: String) -> PPCRequest {
+    // no error handling is needed for the url at this point. the instance
+    // URL was validated and normalized when the instance was resolved, see
+    // `api_url`. If the host does not exist, the transport will let the user
+    // know later in the process (e.g. `failed to lookup address`).
+    let url = api_url::endpoint(&instance.base_url(), &["p"]);
+    debug!("URL for request: {}", url);
+
+    authenticate(instance, PPCRequest::new(Method::Post, url)).body(body)
+}
+
+/// Create a text push from a body made by `api_body::push_form`, with the
+/// credentials of the instance.
+pub fn create_text_push_from_body(
+    transport: &dyn Transport,
+    instance: &Instance,
+    body: &str,
+) -> Result<models::Push, PPCError> {
+    let mut request = body_push_request(instance, String::from(body));
+    let response = transport.send(&request);
+    request.body.zeroize();
+
+    api_body::parse_json(&check_status(response?)?.body, "push")
+}
+
+/// Send the request to create a text push. Shared by `push_text` and
+/// `create_text_push`, which differ only in how they handle the response.
+fn send_text_push(
+    transport: &dyn Transport,
+    instance: &Instance,
+    ppc_text: &args::PPCText,
+) -> Result<PPCResponse, PPCError> {
+    debug!("start push text");
+
+    let mut request = text_push_request(instance, ppc_text);
+    let response = transport.send(&request);
+
+    // the body contains the payload, so it is wiped as soon as it is no longer
+    // needed instead of lingering in freed memory.
+    request.body.zeroize();
+
+    check_status(response?)
+}
+
+pub fn create_text_push(
+    transport: &dyn Transport,
+    instance: &Instance,
+    ppc_text: &args::PPCText,
+) -> Result<models::Push, PPCError> {
+    parse_push(send_text_push(transport, instance, ppc_text)?)
+}
+
+/// The push in the answer to a new push, or the error of the instance.
+/// Shared with the async API of the library, see `client`, which sends the
+/// requests of this module with another transport.
+pub fn parse_push(response: PPCResponse) -> Result<models::Push, PPCError> {
+    api_body::parse_json(&check_status(response)?.body, "push")
+}
 
 /// Use the password pusher API to publish a single text secret, usually a
 /// password. All settings and information are contained in the structs that
@@ -13,244 +95,1141 @@ use crate::{args, errors::PPCError};
 /// On error, this function bails out with a `PPCError` with a human-readable
 /// message. On success, the output is printed to stdout, either as human-
 /// readable text or as JSON if requested with the `-j` option.
-pub fn push_text(args: &args::PPCArgs, ppc_text: &args::PPCText) -> Result<(), PPCError> {
-    debug!("start push text");
+/// Create a text push. If a queue is given and the instance cannot be
+/// reached, the request is stored in the queue instead, to be sent later with
+/// `queue flush`. No push is returned in this case.
+pub fn push_text(
+    transport: &dyn Transport,
+    instance: &Instance,
+    ppc_text: &args::PPCText,
+    queue: Option<&Queue>,
+) -> Result<Option<models::Push>, PPCError> {
+    let response = match queue {
+        None => send_text_push(transport, instance, ppc_text)?,
+        Some(queue) => {
+            debug!("start push text");
+            let mut request = text_push_request(instance, ppc_text);
+            let sent = transport.send(&request);
+            let queued = match &sent {
+                Err(e) => {
+                    warn!(
+                        "{}",
+                        i18n::format("push-unreachable", &[("error", e.to_string().into())])
+                    );
+                    Some(queue.add(&request))
+                }
+                Ok(_) => None,
+            };
+            request.body.zeroize();
+
+            if let Some(id) = queued {
+                println!("{}", i18n::format("push-queued", &[("id", id?.into())]));
+                return Ok(None);
+            }
+            check_status(sent?)?
+        }
+    };
+
+    let push = api_body::parse_json(&response.body, "push")?;
+    debug!("completed push text normally");
+    Ok(Some(push))
+}
 
-    let client = reqwest::blocking::Client::new();
+/// Upload files as a new file push. The files are streamed from disk while
+/// the request is sent, see `multipart`.
+pub fn push_file(
+    transport: &dyn Transport,
+    instance: &Instance,
+    ppc_file: &args::PPCFile,
+    archive: Option<&Archive>,
+) -> Result<models::Push, PPCError> {
+    debug!("start push file");
+    let form = api_body::file_push_form(ppc_file, archive)?;
+    let url = api_url::endpoint(
+        &instance.base_url(),
+        &[endpoint_prefix(args::PushKind::File)],
+    );
+    let request = authenticate(instance, PPCRequest::new(Method::Post, url)).multipart(form);
+
+    let response = transport.send(&request)?;
+    // instances without file pushes do not know the endpoint at all.
+    if response.status == 404 {
+        return Err(PPCError::from(i18n::text("error-file-pushes-disabled"))
+            .with_hint(i18n::text("hint-file-pushes-disabled")));
+    }
+    let response = check_status(response)?;
+    let push = api_body::parse_json(&response.body, "push")?;
+    debug!("completed push file normally");
+    Ok(push)
+}
+
+/// Download a file of a file push to `path`. The URLs are signed by the
+/// instance, so no credentials are sent, which would otherwise follow the
+/// redirect to the storage service.
+pub fn download_file(
+    transport: &dyn Transport,
+    instance: &Instance,
+    file: &models::PushFile,
+    path: &Path,
+) -> Result<(), PPCError> {
+    debug!("start download of {}", file.filename);
+    let url = Url::parse(&instance.base_url())
+        .and_then(|base| base.join(&file.url))
+        .map_err(|e| PPCError::from(format!("invalid URL of {}: {}", file.filename, e)))?;
+    let request = PPCRequest::new(Method::Get, url.into()).download_to(path);
+
+    check_status(transport.send(&request)?)?;
+    debug!("completed download of {}", file.filename);
+    Ok(())
+}
 
-    // no error handling is needed for the url at this point. the format will
-    // always be valid, as the clap parser makes sure that the protocol can only
-    // be http or https, and the free-text part in the middle can be virtually
-    // anything. If the final URL contains invalid characters or no hostname at
-    // all, the URL implementation is solid enough to consume it anyways, and
-    // let the user know later in the process what exactly went wrong (e.g.
-    // `failed to lookup address` or `empty host`).
-    let url = format!("{}://{}/p.json", args.instance_protocol, args.instance_url);
+/// Time the instance gets to expire a push before the request is sent again.
+const EXPIRE_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// Most pages of the list of active pushes that are looked through to find a
+/// push, see `push_state`.
+const MAX_STATE_PAGES: usize = 100;
+
+/// Whether a push is still active, as far as the instance tells.
+#[derive(Debug, PartialEq, Eq)]
+enum PushState {
+    Active,
+    Expired,
+
+    /// The state could not be checked, e.g. without credentials, or because
+    /// the list of active pushes did not end
+    Unknown,
+}
+
+/// Send the request to expire a push, and verify that it is gone if the answer
+/// does not confirm it, expiring it once more if needed, see
+/// `expire_verified`. Used by `expire_push`.
+fn send_expire(
+    transport: &dyn Transport,
+    instance: &Instance,
+    kind: args::PushKind,
+    url_token: &str,
+) -> Result<PPCResponse, PPCError> {
+    expire_verified(transport, instance, kind, url_token, EXPIRE_RETRY_DELAY)
+}
+
+/// Expire a push and make sure it is gone. The instance answers with the
+/// expired push, but proxies in front of it have been seen to swallow DELETE
+/// requests or their answers and still report success. So if the answer does
+/// not confirm the expiration, the push is looked up, and expired once more if
+/// it is still active after `delay`.
+fn expire_verified(
+    transport: &dyn Transport,
+    instance: &Instance,
+    kind: args::PushKind,
+    url_token: &str,
+    delay: Duration,
+) -> Result<PPCResponse, PPCError> {
+    debug!("start expire {}", kind);
+
+    let url = token_url(instance, kind, url_token, None);
     debug!("URL for request: {}", url);
-    let mut builder = client.post(url);
+    let delete = || {
+        check_status(transport.send(&authenticate(
+            instance,
+            PPCRequest::new(Method::Delete, url.clone()),
+        ))?)
+    };
 
-    // only need to check for email or token, as clap ensures that both or none
-    // are given. This also means that unwrap() can be used because values are
-    // ensured to never be empty.
-    if args.email.is_some() {
-        builder = builder
-            .header("X-User-Email", args.email.as_ref().unwrap())
-            .header("X-User-Token", args.token.as_ref().unwrap());
+    let mut response = delete()?;
+    for retry in [false, true] {
+        if confirms_expiration(&response) {
+            return Ok(response);
+        }
+        match push_state(transport, instance, kind, url_token) {
+            PushState::Expired => return Ok(response),
+            PushState::Unknown => {
+                warn!(
+                    "{}",
+                    i18n::format(
+                        "push-expire-unverified",
+                        &[
+                            ("kind", kind.to_string().into()),
+                            ("url_token", url_token.into())
+                        ]
+                    )
+                );
+                return Ok(response);
+            }
+            PushState::Active if retry => break,
+            PushState::Active => {
+                debug!("push {} is still active, expiring it again", url_token);
+                thread::sleep(delay);
+                response = delete()?;
+            }
+        }
     }
 
-    builder = builder.body(build_body_string(ppc_text));
+    Err(PPCError::from(format!(
+        "{} push {} is still active, although the instance accepted its expiration",
+        kind, url_token
+    ))
+    .with_hint(String::from(
+        "a proxy in front of the instance may drop DELETE requests, check its configuration \
+         or expire the push in the web interface",
+    )))
+}
+
+/// True if the answer to an expiration is the expired push.
+fn confirms_expiration(response: &PPCResponse) -> bool {
+    serde_json::from_str::<serde_json::Value>(&response.body)
+        .is_ok_and(|push| push["expired"] == true)
+}
+
+/// Look up whether a push is still active, without viewing it. Pushes of the
+/// account are looked up in the list of active pushes, page by page. Without
+/// credentials, the only way to look at a push is to retrieve it, which would
+/// count as a view of a push that is still active, so the state is unknown.
+fn push_state(
+    transport: &dyn Transport,
+    instance: &Instance,
+    kind: args::PushKind,
+    url_token: &str,
+) -> PushState {
+    if instance.token.is_none() {
+        return PushState::Unknown;
+    }
 
-    match builder.send() {
-        Ok(response) => {
-            println!("response status: {}", response.status());
-            println!("response content: {}", response.text().unwrap());
+    let mut seen = HashSet::new();
+    for page in 1..=MAX_STATE_PAGES {
+        let pushes = match list_page(transport, instance, kind, args::PushState::Active, page) {
+            Ok(pushes) => pushes,
+            Err(e) => {
+                debug!("could not look up push {}: {}", url_token, e);
+                return PushState::Unknown;
+            }
+        };
+        if pushes.iter().any(|push| push.url_token == url_token) {
+            return PushState::Active;
         }
-        Err(e) => return Err(PPCError::from(format!("{}", e).as_str())),
+        if pushes.is_empty() {
+            return PushState::Expired;
+        }
+        // an instance that ignores the page answers the same pushes again.
+        let known = seen.len();
+        seen.extend(pushes.into_iter().map(|push| push.url_token));
+        if seen.len() == known {
+            debug!("the instance ignores the page of the list of active pushes");
+            return PushState::Unknown;
+        }
+    }
+    PushState::Unknown
+}
+
+/// Expire a push without printing anything, see `expire` and `bulk`.
+pub fn expire_push(
+    transport: &dyn Transport,
+    instance: &Instance,
+    kind: args::PushKind,
+    url_token: &str,
+) -> Result<(), PPCError> {
+    send_expire(transport, instance, kind, url_token).map(|_| ())
+}
+
+/// Retrieve the audit log of a push. Only the creator of a push is allowed to
+/// see its audit log, so this needs authentication.
+pub fn audit(
+    transport: &dyn Transport,
+    instance: &Instance,
+    kind: args::PushKind,
+    url_token: &str,
+) -> Result<models::AuditLog, PPCError> {
+    debug!("start audit {}", kind);
+
+    let response = check_status(transport.send(&audit_request(instance, kind, url_token))?)?;
+
+    api_body::parse_json(&response.body, "audit log")
+}
+
+/// Outcome of retrieving a push that might be protected by a passphrase.
+#[derive(Debug)]
+pub enum Retrieval {
+    Push(Box<models::Push>),
+
+    /// The passphrase was not given or is incorrect
+    PassphraseRequired,
+}
+
+/// Retrieve a push including its payload. Note that this counts as a view, just
+/// like opening the secret link in a browser. Fails if the push is protected
+/// by a passphrase, see `retrieve_with_passphrase` for these.
+pub fn retrieve(
+    transport: &dyn Transport,
+    instance: &Instance,
+    kind: args::PushKind,
+    url_token: &str,
+) -> Result<models::Push, PPCError> {
+    match retrieve_with_passphrase(transport, instance, kind, url_token, None)? {
+        Retrieval::Push(push) => Ok(*push),
+        Retrieval::PassphraseRequired => Err(PPCError::from(format!(
+            "push {} is protected by a passphrase",
+            url_token
+        ))),
+    }
+}
+
+/// Retrieve the preview of a push, which contains the URL to share. Unlike
+/// retrieving the push, this does not count as a view.
+pub fn preview(
+    transport: &dyn Transport,
+    instance: &Instance,
+    kind: args::PushKind,
+    url_token: &str,
+) -> Result<models::Preview, PPCError> {
+    debug!("start preview {}", kind);
+
+    parse_preview(transport.send(&preview_request(instance, kind, url_token))?)
+}
+
+/// Build the request for the preview of a push, see `preview`.
+pub fn preview_request(instance: &Instance, kind: args::PushKind, url_token: &str) -> PPCRequest {
+    let url = token_url(instance, kind, url_token, Some("preview"));
+    debug!("URL for request: {}", url);
+
+    authenticate(instance, PPCRequest::new(Method::Get, url))
+}
+
+/// The preview in the answer to `preview_request`.
+pub fn parse_preview(response: PPCResponse) -> Result<models::Preview, PPCError> {
+    api_body::parse_json(&check_status(response)?.body, "preview")
+}
+
+/// Build the request for the audit log of a push.
+pub fn audit_request(instance: &Instance, kind: args::PushKind, url_token: &str) -> PPCRequest {
+    let url = token_url(instance, kind, url_token, Some("audit"));
+    debug!("URL for request: {}", url);
+
+    authenticate(instance, PPCRequest::new(Method::Get, url).cacheable())
+}
+
+/// Build the request to retrieve a push. The URL contains the passphrase, so
+/// it should be wiped after use.
+pub fn retrieve_request(
+    instance: &Instance,
+    kind: args::PushKind,
+    url_token: &str,
+    passphrase: Option<&str>,
+) -> PPCRequest {
+    let url = token_url(instance, kind, url_token, None);
+    debug!("URL for request: {}", url);
+
+    // the API only accepts the passphrase as query parameter. The transports
+    // take care to keep it out of logs and recordings.
+    let url = match passphrase {
+        Some(passphrase) => format!("{}?passphrase={}", url, urlencoding::encode(passphrase)),
+        None => url,
     };
 
-    debug!("completed push text normally");
-    Ok(())
+    authenticate(instance, PPCRequest::new(Method::Get, url))
 }
 
-/// Helper function to build the body text with the correct format for the API.
-/// For the request bodies, the API does not use a standard format like JSON,
-/// but rather some kind of serialized version of a ruby hash, encoded similar
-/// to URL parameters.
-///
-/// Apart from the payload itself, all of the parameters are optional and only
-/// included in the request if the user specified them. This is important, as
-/// each instance has some server-side defaults, and the user might want to
-/// accept these defaults. If we sent all of the parameters for each request,
-/// we would not enable users to do so.
-///
-/// In addition, the API requires us to send strings, notably payload,
-/// passphrase and note, in an urlencoded format. The strings in the parameter
-/// are plain strings that are encoded on the fly here. We cannot enocde the
-/// final resulting string with urlencoding, because this would encode the
-/// square brackets as well, but we need them literally. Remember, this string
-/// will go into the body, so we encoding things that are explicitly required
-/// by the API to be encoded.
-///
-/// The order of the parameters is not strictly specified and could probably be
-/// changed, but tests (see below) can assume that the order of the parameters
-/// will be the same as the fields in the `PPCText` struct.
-fn build_body_string(ppc_text: &args::PPCText) -> String {
-    // at the moment there are 7 possible parameters, so might as well use
-    // the idiomatic `with_capacity`.
-    // As most of the parameters are optional, we build them in a vec and join
-    // them in the end.
-    let mut args = Vec::with_capacity(7);
-
-    add_option(&mut args, "payload", &Some(&ppc_text.password_payload));
-    add_option(&mut args, "passphrase", &ppc_text.passphrase);
-    add_option(&mut args, "note", &ppc_text.note);
-    add_option(&mut args, "expire_after_days", &ppc_text.expire_after_days);
-    add_option(
-        &mut args,
-        "expire_after_views",
-        &ppc_text.expire_after_views,
-    );
-    add_option(
-        &mut args,
-        "deletable_by_viewer",
-        &ppc_text.deletable_by_viewer,
+/// Retrieve a push, passing the passphrase to the instance if one is given. A
+/// missing or incorrect passphrase is not an error, so callers can ask the user
+/// and try again. Unknown pushes fail with `EXIT_EXPIRED`, as instances answer
+/// deleted pushes with 404 as well.
+pub fn retrieve_with_passphrase(
+    transport: &dyn Transport,
+    instance: &Instance,
+    kind: args::PushKind,
+    url_token: &str,
+    passphrase: Option<&str>,
+) -> Result<Retrieval, PPCError> {
+    debug!("start retrieve {}", kind);
+
+    let mut request = retrieve_request(instance, kind, url_token, passphrase);
+    let response = transport.send(&request);
+    request.url.zeroize();
+
+    let response = response?;
+    if requires_passphrase(&response) {
+        debug!("push {} requires a passphrase", url_token);
+        return Ok(Retrieval::PassphraseRequired);
+    }
+    if response.status == 404 {
+        return Err(PPCError::with_exit_code(
+            format!(
+                "push {} does not exist, it may have been deleted",
+                url_token
+            ),
+            EXIT_EXPIRED,
+        ));
+    }
+
+    let response = check_status(response)?;
+    api_body::parse_json(&response.body, "push").map(|push| Retrieval::Push(Box::new(push)))
+}
+
+/// Outcome of changing a push. Instances without the endpoint answer 404 like
+/// for unknown pushes, so callers have to tell these apart.
+#[derive(Debug)]
+pub enum Update {
+    Push(Box<models::Push>),
+
+    /// The instance knows neither the push nor the endpoint
+    NotFound,
+
+    /// The instance does not allow changing pushes, i.e. answered 405 or 501
+    Unsupported,
+}
+
+/// Change the note or the viewer settings of a push of the account.
+pub fn update(
+    transport: &dyn Transport,
+    instance: &Instance,
+    ppc_update: &args::PPCUpdate,
+) -> Result<Update, PPCError> {
+    debug!("start update {}", ppc_update.kind);
+
+    let url = token_url(instance, ppc_update.kind, &ppc_update.url_token, None);
+    debug!("URL for request: {}", url);
+    let request = authenticate(instance, PPCRequest::new(Method::Patch, url))
+        .body(api_body::update_form(ppc_update));
+
+    let response = transport.send(&request)?;
+    match response.status {
+        404 => Ok(Update::NotFound),
+        405 | 501 => Ok(Update::Unsupported),
+        _ => {
+            let response = check_status(response)?;
+            api_body::parse_json(&response.body, "push").map(|push| Update::Push(Box::new(push)))
+        }
+    }
+}
+
+/// Instances answer requests for a protected push without the correct
+/// passphrase with an error object instead of the push, e.g.
+/// `{"error":"This push has a passphrase that was incorrect or not provided."}`,
+/// in the language of the instance, see `localized`. Depending on the server
+/// version, the status code is 200 or 4xx, so only the body is inspected.
+fn requires_passphrase(response: &PPCResponse) -> bool {
+    localized::classify_body(&response.body) == Some(InstanceError::PassphraseRequired)
+}
+
+/// List the active or expired pushes of the authenticated user.
+pub fn list(
+    transport: &dyn Transport,
+    instance: &Instance,
+    kind: args::PushKind,
+    state: args::PushState,
+) -> Result<Vec<models::Push>, PPCError> {
+    listing(transport, instance, kind, state).map(|listing| listing.pushes)
+}
+
+/// A single page of a list of pushes, starting with 1. The page after the
+/// last one is empty.
+fn list_page(
+    transport: &dyn Transport,
+    instance: &Instance,
+    kind: args::PushKind,
+    state: args::PushState,
+    page: usize,
+) -> Result<Vec<models::Push>, PPCError> {
+    let state = state.to_string();
+    let url = format!(
+        "{}?page={}",
+        api_url::endpoint(&instance.base_url(), &[endpoint_prefix(kind), &state]),
+        page
     );
-    add_option(&mut args, "retrieval_step", &ppc_text.retrieval_step);
-
-    // `join` makes the args into a single string, and we do not have to
-    // bother an extra separator at the start or the end of the result.
-    let final_body = args.join("&");
-    debug!("final body string: {}", final_body);
-
-    final_body
-}
-
-/// Helper for the helper - push the contents of the data to the args if data is
-/// not None, using the format `password[<key>]=<d>`. Value strings get URL
-/// encoded as required by the API.
-/// This function is only called for strings that should be urlencoded, for
-/// bools and for some numbers (usize), so everything is made into a string and
-/// the urlencoding is applied, and nothing should break.
-fn add_option<T: fmt::Display>(args: &mut Vec<String>, key: &str, data: &Option<T>) {
-    if let Some(d) = data.as_ref() {
-        let d = format!("{}", d);
-        let formatted = format!(
-            "password[{}]={}",
-            key,
-            &urlencoding::encode(d.as_str())
-        );
-        debug!("appending `{}` request parameters", formatted);
-        args.push(formatted);
+    debug!("URL for request: {}", url);
+
+    let request = authenticate(instance, PPCRequest::new(Method::Get, url));
+    let response = check_status(transport.send(&request)?)?;
+    api_body::parse_json(&response.body, "push list")
+}
+
+/// A list of pushes, and whether the instance reported it as unchanged.
+pub struct Listing {
+    pub pushes: Vec<models::Push>,
+
+    /// The instance answered "304 Not Modified", see `cache`
+    pub unchanged: bool,
+
+    /// How far the clock of the instance is ahead of the local one, see
+    /// `skew`. Unknown for cached lists, which have the date of the first
+    /// answer
+    pub skew: Option<chrono::Duration>,
+}
+
+/// Like `list`, but also tells whether the list changed since it was last
+/// fetched.
+pub fn listing(
+    transport: &dyn Transport,
+    instance: &Instance,
+    kind: args::PushKind,
+    state: args::PushState,
+) -> Result<Listing, PPCError> {
+    debug!("start list {} {}", state, kind);
+
+    let state = state.to_string();
+    let url = api_url::endpoint(&instance.base_url(), &[endpoint_prefix(kind), &state]);
+    debug!("URL for request: {}", url);
+
+    let request = authenticate(instance, PPCRequest::new(Method::Get, url).cacheable());
+    let response = check_status(transport.send(&request)?)?;
+    let skew = match response.not_modified {
+        true => None,
+        false => skew::measure(response.header("Date"), chrono::Utc::now()),
+    };
+    Ok(Listing {
+        pushes: api_body::parse_json(&response.body, "push list")?,
+        unchanged: response.not_modified,
+        skew,
+    })
+}
+
+/// Ask the instance for its server version. This endpoint does not depend on
+/// the kind of push, and is not available on older servers.
+pub fn server_version(
+    transport: &dyn Transport,
+    instance: &Instance,
+) -> Result<models::ServerVersion, PPCError> {
+    let response = check_status(transport.send(&version_request(instance))?)?;
+    api_body::parse_json(&response.body, "version")
+}
+
+/// Whether the instance has accounts, by asking for its sign in page. Instances
+/// with logins disabled have no such page and answer with 404. Redirects, e.g.
+/// to a page with the locale in its path, mean the page exists.
+pub fn logins_enabled(transport: &dyn Transport, instance: &Instance) -> Result<bool, PPCError> {
+    let url = api_url::page(&instance.base_url(), &["users", "sign_in"]);
+    debug!("URL for request: {}", url);
+
+    let response = transport.send(&PPCRequest::new(Method::Get, url))?;
+    match response.status {
+        404 => Ok(false),
+        200..=399 => Ok(true),
+        _ => Err(PPCError::from(status_message(&response))),
+    }
+}
+
+/// Build the request for the server version.
+pub fn version_request(instance: &Instance) -> PPCRequest {
+    let url = api_url::endpoint(&instance.base_url(), &["api", "v1", "version"]);
+    debug!("URL for request: {}", url);
+
+    authenticate(instance, PPCRequest::new(Method::Get, url))
+}
+
+/// URL of an endpoint that operates on an existing push, e.g.
+/// `https://pwpush.com/p/<url_token>.json` or, with an action,
+/// `https://pwpush.com/p/<url_token>/audit.json`.
+fn token_url(
+    instance: &Instance,
+    kind: args::PushKind,
+    url_token: &str,
+    action: Option<&str>,
+) -> String {
+    let base_url = instance.base_url();
+    match action {
+        Some(action) => api_url::endpoint(&base_url, &[endpoint_prefix(kind), url_token, action]),
+        None => api_url::endpoint(&base_url, &[endpoint_prefix(kind), url_token]),
+    }
+}
+
+/// Turn responses with error status codes into errors, so callers only need to
+/// deal with successful responses. Common failures get a hint how to fix them.
+/// Some instances answer missing credentials with other status codes than
+/// 401, which are recognized by their message.
+fn check_status(response: PPCResponse) -> Result<PPCResponse, PPCError> {
+    let unauthenticated =
+        || localized::classify_body(&response.body) == Some(InstanceError::Unauthenticated);
+    let error = match response.status {
+        _ if response.is_success() => return Ok(response),
+        _ if response.status == 401 || unauthenticated() => {
+            PPCError::with_exit_code(i18n::text("error-unauthorized"), EXIT_UNAUTHORIZED)
+                .with_hint(i18n::text("hint-unauthorized"))
+        }
+        413 => {
+            PPCError::from(i18n::text("error-too-large")).with_hint(i18n::text("hint-too-large"))
+        }
+        429 => PPCError::from(i18n::text("error-rate-limited"))
+            .with_hint(i18n::text("hint-rate-limited")),
+        status => {
+            let error = PPCError::from(status_message(&response));
+            match status {
+                404 => error.with_hint(i18n::text("hint-not-found")),
+                500..=599 => error.with_hint(i18n::text("hint-server-error")),
+                _ => error,
+            }
+        }
+    };
+    Err(error)
+}
+
+/// The status and the body of the response. Error pages of proxies and of the
+/// instance itself are HTML, which is left out.
+fn status_message(response: &PPCResponse) -> String {
+    let body = response.body.trim();
+    if body.is_empty() || body.starts_with('<') {
+        i18n::format("error-status-page", &[("status", response.status.into())])
+    } else {
+        i18n::format(
+            "error-status",
+            &[("status", response.status.into()), ("body", body.into())],
+        )
+    }
+}
+
+/// Each endpoint (except for creating pushes) is prefixed with a single letter
+/// that indicates the kind of push, see the API description in main.
+fn endpoint_prefix(kind: args::PushKind) -> &'static str {
+    match kind {
+        args::PushKind::Text => "p",
+        args::PushKind::File => "f",
+        args::PushKind::Url => "r",
+    }
+}
+
+/// Add the authentication headers to the request, if the user gave
+/// credentials.
+///
+/// The instance ensures that both or none of email and token are given, so a
+/// request is either fully authenticated or not at all.
+fn authenticate(instance: &Instance, request: PPCRequest) -> PPCRequest {
+    match (&instance.email, &instance.token, instance.auth_scheme) {
+        (_, Some(token), Some(args::AuthScheme::Bearer)) => request.header(
+            "Authorization",
+            &format!("Bearer {}", token.expose_secret()),
+        ),
+        (Some(email), Some(token), _) => request
+            .header("X-User-Email", email)
+            .header("X-User-Token", token.expose_secret()),
+        _ => request,
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::args::PPCText;
+    use std::process::ExitCode;
+    use std::time::Duration;
 
-    use super::build_body_string;
+    use clap::Parser;
 
-    // not super useful in practice, but what does the build_body_string
-    // function care?
-    #[test]
-    fn build_body_string_empty_pw() {
-        let text = PPCText {
-            password_payload: String::from(""),
-            passphrase: None,
-            note: None,
-            expire_after_days: None,
-            expire_after_views: None,
-            deletable_by_viewer: None,
-            retrieval_step: None,
+    use crate::args::{PPCAction, PPCArgs, PPCObject, PPCText, PushKind, PushState};
+    use crate::config::PPCConfig;
+    use crate::errors::{EXIT_EXPIRED, EXIT_UNAUTHORIZED};
+    use crate::http::mock::MockTransport;
+    use crate::http::Method;
+    use crate::instance::Instance;
+    use crate::recording::ReplayTransport;
+
+    use super::{
+        audit, create_text_push, expire_push, expire_verified, list, push_file, push_state,
+        push_text, retrieve, retrieve_with_passphrase, server_version, token_url,
+        PushState as State, Retrieval,
+    };
+    use crate::queue::Queue;
+
+    /// Parse a command line the same way main does, and pull out the text
+    /// push arguments. Panics if the command line is not a text push, as that
+    /// would be an error in the test itself.
+    fn text_push_args(cmdline: &[&str]) -> (PPCArgs, PPCText) {
+        let mut args = PPCArgs::parse_from(cmdline);
+        let text = match std::mem::replace(&mut args.action, PPCAction::Version) {
+            PPCAction::Push(PPCObject::Text(text)) => text,
+            other => panic!("not a text push: {:?}", other),
         };
+        (args, *text)
+    }
 
-        let actual = build_body_string(&text);
-        let expected = String::from("password[payload]=");
+    /// The instance main would resolve for the command line, without a
+    /// configuration file.
+    fn instance(args: &PPCArgs) -> Instance {
+        Instance::resolve(args, &PPCConfig::default()).unwrap()
+    }
 
-        assert_eq!(actual, expected);
+    #[test]
+    fn push_text_sends_anonymous_request() {
+        let (args, text) = text_push_args(&["pwpush-cli", "push", "text", "secret"]);
+        let transport = MockTransport::new().respond(201, r#"{"url_token":"abc"}"#);
+
+        assert!(push_text(&transport, &instance(&args), &text, None).is_ok());
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, Method::Post);
+        assert_eq!(requests[0].url, "https://pwpush.com/p.json");
+        assert!(requests[0].headers.is_empty());
+        assert_eq!(
+            requests[0].body.as_deref(),
+            Some("password[payload]=secret")
+        );
     }
 
     #[test]
-    fn build_body_string_simple_pw() {
-        let text = PPCText {
-            password_payload: String::from("password"),
-            passphrase: None,
-            note: None,
-            expire_after_days: None,
-            expire_after_views: None,
-            deletable_by_viewer: None,
-            retrieval_step: None,
+    fn push_file_uploads_multipart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("backup.tar");
+        std::fs::write(&path, "archive").unwrap();
+
+        let path_arg = path.to_str().unwrap();
+        let args = PPCArgs::parse_from([
+            "pwpush-cli",
+            "push",
+            "file",
+            path_arg,
+            "--note",
+            "nightly",
+            "--passphrase",
+            "open",
+        ]);
+        let PPCAction::Push(PPCObject::File(file)) = &args.action else {
+            panic!("not a file push: {:?}", args.action);
         };
+        let transport = MockTransport::new().respond(201, r#"{"url_token":"abc"}"#);
 
-        let actual = build_body_string(&text);
-        let expected = String::from("password[payload]=password");
+        let push = push_file(&transport, &instance(&args), file, None).unwrap();
+        assert_eq!(push.url_token, "abc");
 
-        assert_eq!(actual, expected);
+        let requests = transport.requests();
+        assert_eq!(requests[0].url, "https://pwpush.com/f.json");
+        assert_eq!(requests[0].body, None);
+        let multipart = requests[0].multipart.as_ref().unwrap();
+        assert_eq!(
+            multipart.summary(),
+            "file_push[passphrase]=REDACTED&file_push[note]=nightly\
+             &file_push[files][]=@backup.tar (7 bytes)"
+        );
     }
 
-    // see <https://docs.rs/urlencoding/latest/urlencoding/fn.encode.html>
-    // for details on the URL encoding. There it explicitly says that some
-    // characters (including `_`) are not encoded, so they are also not expected
-    // to be here in the test case.
-    // Obviously, we do not want to write tests for the urlencoding crate, we
-    // just want to make sure that our helper function applies it at the
-    // correct places.
     #[test]
-    fn build_body_string_pw_urlencoded() {
-        let text = PPCText {
-            password_payload: String::from("random_§$%&%$_characters with spaces"),
-            passphrase: None,
-            note: None,
-            expire_after_days: None,
-            expire_after_views: None,
-            deletable_by_viewer: None,
-            retrieval_step: None,
-        };
+    fn push_text_sends_authenticated_request() {
+        let (args, text) = text_push_args(&[
+            "pwpush-cli",
+            "--url",
+            "pwpush.example.com",
+            "--protocol",
+            "http",
+            "--email",
+            "user@example.com",
+            "--token",
+            "t0k3n",
+            "push",
+            "text",
+            "secret",
+        ]);
+        let transport = MockTransport::new().respond(201, r#"{"url_token":"abc"}"#);
 
-        let actual = build_body_string(&text);
-        let expected = String::from(
-            "password[payload]=random_%C2%A7%24%25%26%25%24_characters%20with%20spaces",
+        assert!(push_text(&transport, &instance(&args), &text, None).is_ok());
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].url, "http://pwpush.example.com/p.json");
+        assert_eq!(
+            requests[0].headers,
+            vec![
+                (
+                    String::from("X-User-Email"),
+                    String::from("user@example.com")
+                ),
+                (String::from("X-User-Token"), String::from("t0k3n")),
+            ]
         );
+    }
 
-        assert_eq!(actual, expected);
+    #[test]
+    fn push_text_sends_bearer_token() {
+        let (args, text) =
+            text_push_args(&["pwpush-cli", "--token", "t0k3n", "push", "text", "secret"]);
+        let transport = MockTransport::new().respond(201, r#"{"url_token":"abc"}"#);
+
+        assert!(push_text(&transport, &instance(&args), &text, None).is_ok());
+
+        assert_eq!(
+            transport.requests()[0].headers,
+            vec![(String::from("Authorization"), String::from("Bearer t0k3n"))]
+        );
     }
 
     #[test]
-    fn build_body_string_pw_with_passphrase() {
-        let text = PPCText {
-            password_payload: String::from("password"),
-            passphrase: Some(String::from("passphrase")),
-            note: None,
-            expire_after_days: None,
-            expire_after_views: None,
-            deletable_by_viewer: None,
-            retrieval_step: None,
-        };
+    fn push_text_propagates_transport_errors() {
+        let (args, text) = text_push_args(&["pwpush-cli", "push", "text", "secret"]);
+        let transport = MockTransport::new().fail("failed to lookup address");
+
+        let err = push_text(&transport, &instance(&args), &text, None).unwrap_err();
+        assert_eq!(format!("{}", err), "failed to lookup address");
+    }
+
+    #[test]
+    fn push_text_queues_on_transport_errors() {
+        let (args, text) = text_push_args(&["pwpush-cli", "push", "text", "secret"]);
+        let transport = MockTransport::new()
+            .fail("failed to lookup address")
+            .respond(422, "{}");
+        let dir = tempfile::tempdir().unwrap();
+        let keys = tempfile::tempdir().unwrap();
+        let queue = Queue::open(dir.path(), keys.path()).unwrap();
 
-        let actual = build_body_string(&text);
-        let expected = String::from("password[payload]=password&password[passphrase]=passphrase");
+        assert!(push_text(&transport, &instance(&args), &text, Some(&queue)).is_ok());
+        assert_eq!(queue.ids().unwrap().len(), 1);
 
-        assert_eq!(actual, expected);
+        // only unreachable instances are queued, not rejected pushes.
+        assert!(push_text(&transport, &instance(&args), &text, Some(&queue)).is_err());
+        assert_eq!(queue.ids().unwrap().len(), 1);
     }
 
     #[test]
-    fn build_body_string_with_multiple_options() {
-        let text = PPCText {
-            password_payload: String::from("password"),
-            passphrase: Some(String::from("passphrase")),
-            note: Some(String::from("this is a note")),
-            expire_after_days: Some(5),
-            expire_after_views: Some(2),
-            deletable_by_viewer: None,
-            retrieval_step: None,
-        };
+    fn push_text_replays_recorded_exchange() {
+        let (args, text) = text_push_args(&[
+            "pwpush-cli",
+            "push",
+            "text",
+            "secret",
+            "--expire-after-views",
+            "2",
+        ]);
+        let fixtures = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+        let transport = ReplayTransport::new(&fixtures.join("push_text")).unwrap();
+
+        assert!(push_text(&transport, &instance(&args), &text, None).is_ok());
+    }
+
+    #[test]
+    fn expire_sends_delete_request() {
+        let args = PPCArgs::parse_from([
+            "pwpush-cli",
+            "--email",
+            "user@example.com",
+            "--token",
+            "t0k3n",
+            "expire",
+            "file",
+            "abc",
+        ]);
+        let transport = MockTransport::new().respond(200, r#"{"expired":true}"#);
 
-        let actual = build_body_string(&text);
-        let expected = String::from("password[payload]=password&password[passphrase]=passphrase&\
-                                             password[note]=this%20is%20a%20note&password[expire_after_days]=5&\
-                                             password[expire_after_views]=2");
+        assert!(expire_push(&transport, &instance(&args), PushKind::File, "abc").is_ok());
 
-        assert_eq!(actual, expected);
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, Method::Delete);
+        assert_eq!(requests[0].url, "https://pwpush.com/f/abc.json");
+        assert_eq!(requests[0].headers.len(), 2);
+        assert_eq!(requests[0].body, None);
     }
 
     #[test]
-    fn build_body_string_with_all_options() {
-        let text = PPCText {
-            password_payload: String::from("password"),
-            passphrase: Some(String::from("passphrase")),
-            note: Some(String::from("this is a note")),
-            expire_after_days: Some(5),
-            expire_after_views: Some(2),
-            deletable_by_viewer: Some(true),
-            retrieval_step: Some(false),
+    fn unconfirmed_expiration_is_verified() {
+        let args = PPCArgs::parse_from([
+            "pwpush-cli",
+            "--email",
+            "user@example.com",
+            "--token",
+            "t0k3n",
+            "expire",
+            "text",
+            "abc",
+        ]);
+        let instance = instance(&args);
+        let swallowed = || {
+            MockTransport::new()
+                .respond(200, "")
+                .respond(200, r#"[{"url_token":"abc"}]"#)
         };
 
-        let actual = build_body_string(&text);
-        let expected = String::from("password[payload]=password&password[passphrase]=passphrase&\
-                                             password[note]=this%20is%20a%20note&password[expire_after_days]=5&\
-                                             password[expire_after_views]=2&password[deletable_by_viewer]=true&\
-                                             password[retrieval_step]=false");
+        // the push is gone once it was expired a second time, which takes
+        // all pages of the list to tell.
+        let transport = swallowed()
+            .respond(200, "")
+            .respond(200, r#"[{"url_token":"other"}]"#)
+            .respond(200, "[]");
+        assert!(
+            expire_verified(&transport, &instance, PushKind::Text, "abc", Duration::ZERO).is_ok()
+        );
+        let requests = transport.requests();
+        let methods = requests.iter().map(|r| r.method).collect::<Vec<_>>();
+        assert_eq!(
+            methods,
+            [
+                Method::Delete,
+                Method::Get,
+                Method::Delete,
+                Method::Get,
+                Method::Get
+            ]
+        );
+        assert_eq!(requests[4].url, "https://pwpush.com/p/active.json?page=2");
+
+        let transport = swallowed()
+            .respond(200, "")
+            .respond(200, r#"[{"url_token":"abc"}]"#);
+        let error = expire_verified(&transport, &instance, PushKind::Text, "abc", Duration::ZERO)
+            .unwrap_err();
+        assert!(error.to_string().contains("still active"));
+        assert!(error.hint().unwrap().contains("proxy"));
+    }
+
+    #[test]
+    fn push_state_pages_without_viewing() {
+        let authenticated =
+            PPCArgs::parse_from(["pwpush-cli", "--token", "t0k3n", "expire", "text", "abc"]);
+        let transport = MockTransport::new()
+            .respond(200, r#"[{"url_token":"other"}]"#)
+            .respond(200, r#"[{"url_token":"abc"}]"#);
+        assert_eq!(
+            push_state(&transport, &instance(&authenticated), PushKind::Text, "abc"),
+            State::Active
+        );
+
+        // the same page again means the instance does not page at all.
+        let transport = MockTransport::new()
+            .respond(200, r#"[{"url_token":"other"}]"#)
+            .respond(200, r#"[{"url_token":"other"}]"#);
+        assert_eq!(
+            push_state(&transport, &instance(&authenticated), PushKind::Text, "abc"),
+            State::Unknown
+        );
+
+        // retrieving the push would count as a view.
+        let anonymous = PPCArgs::parse_from(["pwpush-cli", "expire", "text", "abc"]);
+        let transport = MockTransport::new();
+        assert_eq!(
+            push_state(&transport, &instance(&anonymous), PushKind::Text, "abc"),
+            State::Unknown
+        );
+        assert!(transport.requests().is_empty());
+    }
+
+    #[test]
+    fn error_status_gets_hints() {
+        let args = PPCArgs::parse_from(["pwpush-cli", "expire", "text", "abc"]);
+        let transport = MockTransport::new()
+            .respond(
+                413,
+                "<html><body>413 Request Entity Too Large</body></html>",
+            )
+            .respond(502, "<html><body>Bad Gateway</body></html>");
+
+        let err = expire_push(&transport, &instance(&args), PushKind::Text, "abc").unwrap_err();
+        assert!(err.to_string().contains("too large"));
+        assert!(err.hint().unwrap().contains("push file"));
+
+        let err = expire_push(&transport, &instance(&args), PushKind::Text, "abc").unwrap_err();
+        assert_eq!(err.to_string(), "instance responded with status 502");
+        assert!(err.hint().is_some());
+    }
+
+    #[test]
+    fn expire_fails_on_error_status() {
+        let args = PPCArgs::parse_from(["pwpush-cli", "expire", "text", "abc"]);
+        let transport = MockTransport::new().respond(404, "not found");
+
+        let err = expire_push(&transport, &instance(&args), PushKind::Text, "abc").unwrap_err();
+        assert_eq!(
+            format!("{}", err),
+            "instance responded with status 404: not found"
+        );
+    }
+
+    #[test]
+    fn audit_parses_views() {
+        let args = PPCArgs::parse_from(["pwpush-cli", "audit", "text", "abc"]);
+        let transport = MockTransport::new().respond(
+            200,
+            r#"{"views":[{"ip":"10.0.0.1","user_agent":"curl/8.0","referrer":"",
+                "successful":true,"created_at":"2023-12-01T10:15:23.121Z","kind":0}]}"#,
+        );
+
+        let log = audit(&transport, &instance(&args), PushKind::Text, "abc").unwrap();
+
+        assert_eq!(transport.requests()[0].method, Method::Get);
+        assert_eq!(
+            transport.requests()[0].url,
+            "https://pwpush.com/p/abc/audit.json"
+        );
+        assert_eq!(log.views.len(), 1);
+        assert_eq!(log.views[0].ip.as_deref(), Some("10.0.0.1"));
+    }
+
+    #[test]
+    fn subpath_instance_prefixes_all_endpoints() {
+        let (args, text) = text_push_args(&[
+            "pwpush-cli",
+            "--url",
+            "intranet.example.com/pwpush/",
+            "push",
+            "text",
+            "secret",
+        ]);
+        let instance = instance(&args);
+        let transport = MockTransport::new()
+            .respond(201, r#"{"url_token":"abc"}"#)
+            .respond(200, r#"{"url_token":"abc"}"#)
+            .respond(200, r#"{"url_token":"abc"}"#)
+            .respond(200, r#"{"url_token":"abc"}"#)
+            .respond(200, r#"{"views":[]}"#)
+            .respond(200, "[]")
+            .respond(200, "[]")
+            .respond(200, r#"{"expired":true}"#);
+
+        push_text(&transport, &instance, &text, None).unwrap();
+        for kind in [PushKind::Text, PushKind::File, PushKind::Url] {
+            retrieve(&transport, &instance, kind, "abc").unwrap();
+        }
+        audit(&transport, &instance, PushKind::Text, "abc").unwrap();
+        list(&transport, &instance, PushKind::File, PushState::Active).unwrap();
+        list(&transport, &instance, PushKind::Url, PushState::Expired).unwrap();
+        expire_push(&transport, &instance, PushKind::Url, "abc").unwrap();
+
+        let urls = transport
+            .requests()
+            .iter()
+            .map(|r| r.url.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            urls,
+            vec![
+                "https://intranet.example.com/pwpush/p.json",
+                "https://intranet.example.com/pwpush/p/abc.json",
+                "https://intranet.example.com/pwpush/f/abc.json",
+                "https://intranet.example.com/pwpush/r/abc.json",
+                "https://intranet.example.com/pwpush/p/abc/audit.json",
+                "https://intranet.example.com/pwpush/f/active.json",
+                "https://intranet.example.com/pwpush/r/expired.json",
+                "https://intranet.example.com/pwpush/r/abc.json",
+            ]
+        );
+        assert_eq!(
+            token_url(&instance, PushKind::File, "abc", Some("preview")),
+            "https://intranet.example.com/pwpush/f/abc/preview.json"
+        );
+    }
+
+    #[test]
+    fn audit_rejects_unexpected_format() {
+        let args = PPCArgs::parse_from(["pwpush-cli", "audit", "text", "abc"]);
+        let transport = MockTransport::new().respond(200, "<html></html>");
+
+        assert!(audit(&transport, &instance(&args), PushKind::Text, "abc").is_err());
+    }
+
+    #[test]
+    fn list_parses_pushes() {
+        let args = PPCArgs::parse_from(["pwpush-cli", "audit", "text", "abc"]);
+        let transport = MockTransport::new().respond(
+            200,
+            r#"[{"url_token":"abc","days_remaining":3,"views_remaining":1,"note":"n"},
+                {"url_token":"def","expired":true}]"#,
+        );
+
+        let pushes = list(
+            &transport,
+            &instance(&args),
+            PushKind::Url,
+            PushState::Expired,
+        )
+        .unwrap();
+
+        assert_eq!(
+            transport.requests()[0].url,
+            "https://pwpush.com/r/expired.json"
+        );
+        assert_eq!(pushes.len(), 2);
+        assert_eq!(pushes[0].days_remaining, Some(3));
+        assert_eq!(pushes[1].expired, Some(true));
+    }
+
+    #[test]
+    fn retrieve_passes_passphrase() {
+        let args = PPCArgs::parse_from(["pwpush-cli", "audit", "text", "abc"]);
+        let transport =
+            MockTransport::new().respond(200, r#"{"url_token":"abc","payload":"secret"}"#);
+
+        let retrieval = retrieve_with_passphrase(
+            &transport,
+            &instance(&args),
+            PushKind::Text,
+            "abc",
+            Some("open sesame"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            transport.requests()[0].url,
+            "https://pwpush.com/p/abc.json?passphrase=open%20sesame"
+        );
+        assert!(
+            matches!(retrieval, Retrieval::Push(push) if push.payload.as_deref() == Some("secret"))
+        );
+    }
+
+    #[test]
+    fn retrieve_detects_missing_passphrase() {
+        let args = PPCArgs::parse_from(["pwpush-cli", "audit", "text", "abc"]);
+        let body = r#"{"error":"This push has a passphrase that was incorrect or not provided."}"#;
+        let transport = MockTransport::new().respond(200, body).respond(401, body);
+
+        let retrieval =
+            retrieve_with_passphrase(&transport, &instance(&args), PushKind::Text, "abc", None)
+                .unwrap();
+        assert!(matches!(retrieval, Retrieval::PassphraseRequired));
+
+        assert!(retrieve(&transport, &instance(&args), PushKind::Text, "abc").is_err());
+    }
+
+    #[test]
+    fn retrieve_unknown_push() {
+        let args = PPCArgs::parse_from(["pwpush-cli", "audit", "text", "abc"]);
+        let transport = MockTransport::new().respond(404, r#"{"error":"not found"}"#);
+
+        let error = retrieve(&transport, &instance(&args), PushKind::Text, "abc").unwrap_err();
+
+        assert_eq!(error.exit_code(), ExitCode::from(EXIT_EXPIRED));
+        assert!(error.to_string().contains("may have been deleted"));
+    }
+
+    #[test]
+    fn server_version_parses_response() {
+        let args = PPCArgs::parse_from(["pwpush-cli", "version"]);
+        let transport = MockTransport::new().respond(
+            200,
+            r#"{"application_version":"1.49.0","api_version":"1.1","edition":"oss"}"#,
+        );
+
+        let version = server_version(&transport, &instance(&args)).unwrap();
+
+        assert_eq!(
+            transport.requests()[0].url,
+            "https://pwpush.com/api/v1/version.json"
+        );
+        assert_eq!(version.application_version.as_deref(), Some("1.49.0"));
+    }
+
+    #[test]
+    fn create_text_push_returns_metadata() {
+        let (args, text) = text_push_args(&["pwpush-cli", "push", "text", "secret"]);
+        let transport = MockTransport::new().respond(201, r#"{"url_token":"new"}"#);
+
+        let push = create_text_push(&transport, &instance(&args), &text).unwrap();
+
+        assert_eq!(push.url_token, "new");
+    }
+
+    #[test]
+    fn push_text_fails_on_error_status() {
+        let (args, text) = text_push_args(&["pwpush-cli", "push", "text", "secret"]);
+        let transport = MockTransport::new().respond(422, r#"{"error":"invalid"}"#);
+
+        let err = push_text(&transport, &instance(&args), &text, None).unwrap_err();
+        assert_eq!(
+            format!("{}", err),
+            r#"instance responded with status 422: {"error":"invalid"}"#
+        );
+    }
+
+    #[test]
+    fn localized_sign_in_errors_are_unauthorized() {
+        let (args, text) = text_push_args(&["pwpush-cli", "push", "text", "secret"]);
+        let transport = MockTransport::new().respond(
+            403,
+            r#"{"error":"Vous devez vous connecter ou vous inscrire pour continuer."}"#,
+        );
 
-        assert_eq!(actual, expected);
+        let err = push_text(&transport, &instance(&args), &text, None).unwrap_err();
+        assert_eq!(err.code(), EXIT_UNAUTHORIZED);
     }
 }
@@ -0,0 +1,78 @@
+//! QR codes in the terminal.
+//!
+//! `--qr-link` prints the link of a new push as QR code, so a recipient who
+//! sits next to the sender, e.g. during onboarding, opens it by scanning the
+//! screen with their phone, instead of having it sent through chat or email.
+//!
+//! Two rows of modules are drawn per line with half block characters. The
+//! light modules are drawn, and the dark ones left empty, which gives a code
+//! with the right colors on the dark background of most terminals. It goes
+//! to stderr, so the link on stdout can still be piped.
+//!
+//! Drawing the codes needs the `qr` feature. Without it, `--qr-link` is
+//! refused before anything is pushed, see `check`.
+
+use std::io::{self, Write};
+
+#[cfg(feature = "qr")]
+use qrcode::render::unicode::Dense1x2;
+#[cfg(feature = "qr")]
+use qrcode::QrCode;
+
+use crate::errors::PPCError;
+
+/// Fail early if QR codes cannot be drawn, before the push is created.
+pub fn check() -> Result<(), PPCError> {
+    if cfg!(feature = "qr") {
+        Ok(())
+    } else {
+        Err(PPCError::from(
+            "--qr-link is not available, as pwpush-cli was built without the qr feature",
+        ))
+    }
+}
+
+/// The text as QR code, one line per two rows of modules.
+#[cfg(feature = "qr")]
+pub fn render(text: &str) -> Result<String, PPCError> {
+    let code = QrCode::new(text)
+        .map_err(|e| PPCError::from(format!("cannot encode as QR code: {}", e)))?;
+    Ok(code
+        .render::<Dense1x2>()
+        .dark_color(Dense1x2::Light)
+        .light_color(Dense1x2::Dark)
+        .quiet_zone(true)
+        .build())
+}
+
+#[cfg(not(feature = "qr"))]
+pub fn render(_text: &str) -> Result<String, PPCError> {
+    check().map(|()| unreachable!("QR codes are refused by check without the qr feature"))
+}
+
+/// Print the text as QR code to stderr.
+pub fn print(text: &str) -> Result<(), PPCError> {
+    let code = render(text)?;
+    writeln!(io::stderr(), "{}", code)
+        .map_err(|e| PPCError::from(format!("could not print the QR code: {}", e)))
+}
+
+#[cfg(all(test, feature = "qr"))]
+mod test {
+    use super::render;
+
+    #[test]
+    fn links_are_drawn_with_half_blocks() {
+        let code = render("https://pwpush.com/p/fkwjfvhall92").unwrap();
+        let lines = code.lines().collect::<Vec<_>>();
+
+        // version 3 has 29 modules, plus a quiet zone of 4 on each side.
+        assert_eq!(lines[0].chars().count(), 37);
+        assert_eq!(lines.len(), 19);
+        assert!(code
+            .chars()
+            .all(|c| matches!(c, ' ' | '\u{2580}' | '\u{2584}' | '\u{2588}' | '\n')));
+        // the quiet zone is light, so it is drawn.
+        assert!(lines[0].chars().all(|c| c == '\u{2588}'));
+    }
+}
@@ -0,0 +1,333 @@
+//! Offline queue for pushes that could not be sent.
+//!
+//! With `push text --queue-on-failure`, a push is not lost if the instance
+//! cannot be reached, e.g. an internal instance while the VPN is down. The
+//! complete request is stored in the queue directory instead, and sent later
+//! with `queue flush`. Only pushes are queued that never reached the instance,
+//! so a push is never created twice.
+//!
+//! Queued requests contain the payload and the credentials, so they are
+//! encrypted with XChaCha20-Poly1305. The key is generated on first use and
+//! stored in the key directory, outside of the data directory and readable
+//! only by the user, see `state`. A backup or synced copy of the queue alone
+//! does not reveal the requests, but someone who can read all files of the
+//! user can decrypt them.
+//!
+//! The queue lives in `queue` in the data directory, e.g.
+//! `~/.local/share/pwpush-cli/queue` on Linux (see `paths`), with one file per
+//! request. File names start with the time the request was queued,
+//! so they are flushed in the order they were queued.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chacha20poly1305::XChaCha20Poly1305;
+use chrono::Utc;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{debug, error, warn};
+use zeroize::Zeroizing;
+
+use crate::args::{PPCArgs, QueueCommand};
+use crate::errors::PPCError;
+use crate::http::{Method, PPCRequest, Transport};
+use crate::models::Push;
+use crate::output;
+use crate::redact::sanitize_url;
+use crate::{i18n, paths, state};
+
+const KEY_FILE: &str = "queue.key";
+const EXTENSION: &str = "queued";
+
+/// On-disk format of a queued request, before encryption.
+#[derive(Serialize, Deserialize)]
+struct QueuedRequest {
+    queued_at: String,
+    method: String,
+    url: String,
+    headers: Vec<(String, String)>,
+    body: Option<String>,
+}
+
+impl QueuedRequest {
+    fn to_request(&self) -> Result<PPCRequest, PPCError> {
+        let method = match self.method.as_str() {
+            "GET" => Method::Get,
+            "POST" => Method::Post,
+            "PATCH" => Method::Patch,
+            "DELETE" => Method::Delete,
+            other => return Err(PPCError::from(format!("unknown method `{}`", other))),
+        };
+
+        let mut request = PPCRequest::new(method, self.url.clone());
+        request.headers = self.headers.clone();
+        request.body = self.body.clone();
+        Ok(request)
+    }
+}
+
+/// A directory of encrypted requests.
+pub struct Queue {
+    dir: PathBuf,
+    cipher: XChaCha20Poly1305,
+}
+
+impl Queue {
+    /// Open the queue in the given directory, with its key in the key
+    /// directory `keys`, creating the directories and the key if needed.
+    pub fn open(dir: &Path, keys: &Path) -> Result<Self, PPCError> {
+        state::create_private_dir(dir)?;
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            cipher: state::cipher(&state::key_file(keys, dir, KEY_FILE)?)?,
+        })
+    }
+
+    /// Store the request, and return the ID of the new entry.
+    pub fn add(&self, request: &PPCRequest) -> Result<String, PPCError> {
+        let now = Utc::now();
+        let id = format!(
+            "{}-{:08x}",
+            now.format("%Y%m%dT%H%M%S%9f"),
+            rand::thread_rng().next_u32()
+        );
+
+        let queued = QueuedRequest {
+            queued_at: now.to_rfc3339(),
+            method: request.method.to_string(),
+            url: request.url.clone(),
+            headers: request.headers.clone(),
+            body: request.body.clone(),
+        };
+        // the request only consists of strings, so serialization cannot fail.
+        let plaintext = Zeroizing::new(serde_json::to_vec(&queued).unwrap());
+
+        let content = state::seal(&self.cipher, &plaintext)?;
+        state::write_private(&self.path(&id), &content)?;
+
+        debug!("queued request {}", id);
+        Ok(id)
+    }
+
+    /// IDs of all queued requests, oldest first.
+    pub fn ids(&self) -> Result<Vec<String>, PPCError> {
+        let read_err = |e: std::io::Error| PPCError::from(format!("could not read queue: {}", e));
+
+        let mut ids = Vec::new();
+        for entry in fs::read_dir(&self.dir).map_err(read_err)? {
+            let path = entry.map_err(read_err)?.path();
+            if path.extension().and_then(|e| e.to_str()) == Some(EXTENSION) {
+                if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+                    ids.push(String::from(stem));
+                }
+            }
+        }
+
+        ids.sort();
+        Ok(ids)
+    }
+
+    fn get(&self, id: &str) -> Result<QueuedRequest, PPCError> {
+        let path = self.path(id);
+        let content = fs::read(&path)
+            .map_err(|e| PPCError::from(format!("could not read {}: {}", path.display(), e)))?;
+        let plaintext = state::unseal(&self.cipher, &content)
+            .map_err(|e| PPCError::from(format!("{}: {}", path.display(), e)))?;
+
+        serde_json::from_slice(&plaintext)
+            .map_err(|e| PPCError::from(format!("invalid queued request {}: {}", id, e)))
+    }
+
+    fn remove(&self, id: &str) -> Result<(), PPCError> {
+        let path = self.path(id);
+        fs::remove_file(&path)
+            .map_err(|e| PPCError::from(format!("could not remove {}: {}", path.display(), e)))
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", id, EXTENSION))
+    }
+}
+
+/// Default location of the queue.
+pub fn default_dir() -> Result<PathBuf, PPCError> {
+    state::dir("queue")
+}
+
+/// Entry point for the `queue` subcommands.
+pub fn run(
+    transport: &dyn Transport,
+    args: &PPCArgs,
+    command: &QueueCommand,
+) -> Result<(), PPCError> {
+    let queue = Queue::open(&default_dir()?, &paths::key_dir()?)?;
+
+    match command {
+        QueueCommand::List => list(&queue, args),
+        QueueCommand::Flush => {
+            let sent = flush(transport, &queue)?;
+            if args.json_output {
+                let sent = sent
+                    .iter()
+                    .map(|(id, url_token)| json!({ "id": id, "url_token": url_token }))
+                    .collect::<Vec<_>>();
+                output::print_json_line(&sent);
+            } else {
+                for (id, url_token) in &sent {
+                    println!("{} -> {}", id, url_token);
+                }
+            }
+
+            match queue.ids()?.len() {
+                0 => Ok(()),
+                remaining => Err(PPCError::from(format!(
+                    "{} requests remain in the queue",
+                    remaining
+                ))),
+            }
+        }
+    }
+}
+
+fn list(queue: &Queue, args: &PPCArgs) -> Result<(), PPCError> {
+    let mut entries = Vec::new();
+    for id in queue.ids()? {
+        let queued = queue.get(&id)?;
+        entries.push((id, queued.queued_at, sanitize_url(&queued.url)));
+    }
+
+    if args.json_output {
+        let entries = entries
+            .iter()
+            .map(|(id, queued_at, url)| json!({ "id": id, "queued_at": queued_at, "url": url }))
+            .collect::<Vec<_>>();
+        output::print_json_line(&entries);
+    } else {
+        for (id, queued_at, url) in &entries {
+            println!("{}  {}  {}", id, queued_at, url);
+        }
+    }
+
+    Ok(())
+}
+
+/// Send all queued requests in order, and return the IDs and URL tokens of
+/// the created pushes. Stops at the first request that still cannot reach its
+/// instance. Requests that were rejected by the instance are removed, as
+/// sending them again would not change the outcome. Only server errors and
+/// rate-limited requests are kept for another try.
+fn flush(transport: &dyn Transport, queue: &Queue) -> Result<Vec<(String, String)>, PPCError> {
+    let mut sent = Vec::new();
+
+    for id in queue.ids()? {
+        let request = queue.get(&id)?.to_request()?;
+        let response = match transport.send(&request) {
+            Ok(response) => response,
+            Err(e) => {
+                warn!(
+                    "{}",
+                    i18n::format(
+                        "queue-unreachable",
+                        &[("id", id.as_str().into()), ("error", e.to_string().into())]
+                    )
+                );
+                break;
+            }
+        };
+
+        if response.is_success() {
+            let url_token = serde_json::from_str::<Push>(&response.body)
+                .map(|push| push.url_token)
+                .unwrap_or_default();
+            queue.remove(&id)?;
+            sent.push((id, url_token));
+        } else if response.status >= 500 || response.status == 429 {
+            warn!(
+                "{}",
+                i18n::format(
+                    "queue-kept",
+                    &[
+                        ("id", id.as_str().into()),
+                        ("status", response.status.into())
+                    ]
+                )
+            );
+        } else {
+            error!(
+                "{}",
+                i18n::format(
+                    "queue-rejected",
+                    &[
+                        ("id", id.as_str().into()),
+                        ("status", response.status.into()),
+                        ("body", response.body.as_str().into())
+                    ]
+                )
+            );
+            queue.remove(&id)?;
+        }
+    }
+
+    Ok(sent)
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::{flush, Queue, KEY_FILE};
+    use crate::http::mock::MockTransport;
+    use crate::http::{Method, PPCRequest};
+
+    fn request(payload: &str) -> PPCRequest {
+        PPCRequest::new(Method::Post, String::from("https://pwpush.com/p.json"))
+            .header("X-User-Token", "t0k3n")
+            .body(format!("password[payload]={}", payload))
+    }
+
+    #[test]
+    fn queued_requests_are_encrypted() {
+        let dir = tempfile::tempdir().unwrap();
+        let keys = tempfile::tempdir().unwrap();
+        let queue = Queue::open(dir.path(), keys.path()).unwrap();
+
+        let id = queue.add(&request("secret")).unwrap();
+
+        let content = fs::read(queue.path(&id)).unwrap();
+        assert!(!String::from_utf8_lossy(&content).contains("secret"));
+        assert!(!String::from_utf8_lossy(&content).contains("t0k3n"));
+        // the key is never stored next to the queue.
+        assert!(!dir.path().join(KEY_FILE).exists());
+        assert!(keys.path().join(KEY_FILE).exists());
+
+        let reopened = Queue::open(dir.path(), keys.path()).unwrap();
+        let queued = reopened.get(&id).unwrap().to_request().unwrap();
+        assert_eq!(queued, request("secret"));
+    }
+
+    #[test]
+    fn flush_sends_in_order_and_keeps_unsent() {
+        let dir = tempfile::tempdir().unwrap();
+        let keys = tempfile::tempdir().unwrap();
+        let queue = Queue::open(dir.path(), keys.path()).unwrap();
+        let first = queue.add(&request("first")).unwrap();
+        let second = queue.add(&request("second")).unwrap();
+        let third = queue.add(&request("third")).unwrap();
+        let transport = MockTransport::new()
+            .respond(201, r#"{"url_token":"abc"}"#)
+            .respond(422, "{}")
+            .fail("failed to lookup address");
+
+        let sent = flush(&transport, &queue).unwrap();
+
+        assert_eq!(sent, vec![(first, String::from("abc"))]);
+        assert_eq!(
+            transport.requests()[1].body.as_deref(),
+            Some("password[payload]=second")
+        );
+        assert_eq!(queue.ids().unwrap(), vec![third]);
+        assert!(!queue.path(&second).exists());
+    }
+}
@@ -0,0 +1,123 @@
+//! Passthrough of server responses with `--raw`.
+//!
+//! Newer servers may answer with responses the models do not understand yet.
+//! In raw mode, the request of the command is sent as usual, but the response
+//! body is written to stdout byte for byte instead of being parsed. Logging
+//! still goes to stderr, so the output can be piped into other tools as is.
+//!
+//! Only commands that consist of a single request support raw mode. Retrieval
+//! of protected pushes does not ask for a passphrase, the response of the
+//! server telling that one is required is printed instead.
+
+use std::io::{self, Write};
+
+use secrecy::ExposeSecret;
+use zeroize::Zeroize;
+
+use crate::args::{PPCAction, PPCAudit};
+use crate::errors::PPCError;
+use crate::http::{PPCRequest, Transport};
+use crate::instance::Instance;
+use crate::pwpush_api;
+
+pub fn run(
+    transport: &dyn Transport,
+    instance: &Instance,
+    action: &PPCAction,
+) -> Result<(), PPCError> {
+    let mut request = request(instance, action)?;
+    let response = transport.send(&request);
+    // the URL of a retrieval contains the passphrase.
+    request.url.zeroize();
+    let response = response?;
+
+    let mut stdout = io::stdout().lock();
+    stdout
+        .write_all(response.body.as_bytes())
+        .and_then(|()| stdout.flush())
+        .map_err(|e| PPCError::from(format!("could not write response: {}", e)))?;
+
+    if response.is_success() {
+        Ok(())
+    } else {
+        Err(PPCError::from(format!(
+            "instance responded with status {}",
+            response.status
+        )))
+    }
+}
+
+/// The single request that the command would send.
+fn request(instance: &Instance, action: &PPCAction) -> Result<PPCRequest, PPCError> {
+    match action {
+        PPCAction::Get(ppc_get) => Ok(pwpush_api::retrieve_request(
+            instance,
+            ppc_get.object.kind(),
+            &ppc_get.object.token().url_token,
+            ppc_get.passphrase.as_ref().map(|p| p.expose_secret()),
+        )),
+        PPCAction::Audit(PPCAudit {
+            watch: false,
+            object: Some(object),
+            ..
+        }) => Ok(pwpush_api::audit_request(
+            instance,
+            object.kind(),
+            &object.token().url_token,
+        )),
+        PPCAction::Version => Ok(pwpush_api::version_request(instance)),
+        _ => Err(PPCError::from(
+            "--raw is only supported by get, audit of a push without --watch, and version",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+
+    use super::request;
+    use crate::args::PPCArgs;
+    use crate::config::PPCConfig;
+    use crate::instance::Instance;
+
+    fn request_url(cmdline: &[&str]) -> Option<String> {
+        let args = PPCArgs::parse_from(cmdline);
+        let instance = Instance::resolve(&args, &PPCConfig::default()).unwrap();
+        request(&instance, &args.action).ok().map(|r| r.url)
+    }
+
+    #[test]
+    fn requests_of_supported_commands() {
+        assert_eq!(
+            request_url(&[
+                "pwpush-cli",
+                "--raw",
+                "get",
+                "--passphrase",
+                "open",
+                "text",
+                "abc"
+            ])
+            .as_deref(),
+            Some("https://pwpush.com/p/abc.json?passphrase=open")
+        );
+        assert_eq!(
+            request_url(&["pwpush-cli", "--raw", "audit", "url", "abc"]).as_deref(),
+            Some("https://pwpush.com/r/abc/audit.json")
+        );
+        assert_eq!(
+            request_url(&["pwpush-cli", "--raw", "version"]).as_deref(),
+            Some("https://pwpush.com/api/v1/version.json")
+        );
+    }
+
+    #[test]
+    fn unsupported_commands() {
+        assert_eq!(request_url(&["pwpush-cli", "--raw", "summary"]), None);
+        assert_eq!(
+            request_url(&["pwpush-cli", "--raw", "audit", "--watch", "text", "abc"]),
+            None
+        );
+    }
+}
@@ -0,0 +1,170 @@
+//! Pushing a secret once per recipient.
+//!
+//! Sharing one link with several people hides who opened it, and the first
+//! view can use up the push for everybody else. `push text --recipients
+//! alice,bob,carol` creates a separate push for every recipient instead, each
+//! with its own link, and prints which link goes to whom. Unless
+//! `--expire-after-views` is given, every push expires after a single view.
+//!
+//! The note of every push names its recipient, so the audit logs of the
+//! account tell who opened their link. A `{recipient}` placeholder in the note
+//! is replaced by the name, otherwise the name is appended to the note.
+//!
+//! Like bulk operations, a failure for one recipient does not stop the others,
+//! and the command fails at the end if any push failed. The recipients that
+//! got no push are kept as a job, so `bulk resume` pushes to them without
+//! pushing to the others a second time, see `jobs`.
+
+use crate::args::{PPCArgs, PPCText};
+use crate::config::PPCConfig;
+use crate::errors::PPCError;
+use crate::http::Transport;
+use crate::instance::Instance;
+use crate::{api_body, bulk, jobs, paths};
+
+/// Views of a push per recipient, unless given on the command line.
+const DEFAULT_VIEWS: usize = 1;
+
+/// Refuse lists that would create surprising pushes, before anything is
+/// pushed.
+pub fn check(recipients: &[String]) -> Result<(), PPCError> {
+    for (index, recipient) in recipients.iter().enumerate() {
+        if recipient.trim().is_empty() {
+            return Err(PPCError::from("--recipients contains an empty name"));
+        }
+        if recipients[..index].contains(recipient) {
+            return Err(PPCError::from(format!(
+                "--recipients contains {} twice",
+                recipient
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// The note of the push for a recipient.
+pub fn note_for(note: Option<&str>, recipient: &str) -> String {
+    match note {
+        Some(note) if note.contains("{recipient}") => note.replace("{recipient}", recipient),
+        Some(note) => format!("{} (for {})", note, recipient),
+        None => format!("for {}", recipient),
+    }
+}
+
+/// The push options for every recipient. `given` are the options from the
+/// command line, `merged` the ones with defaults and the payload applied.
+pub fn options_for(given: &PPCText, merged: &PPCText) -> Vec<PPCText> {
+    given
+        .recipients
+        .iter()
+        .map(|recipient| {
+            let mut ppc_text = merged.clone();
+            ppc_text.note = Some(note_for(merged.note.as_deref(), recipient));
+            if given.expire_after_views.is_none() {
+                ppc_text.expire_after_views = Some(DEFAULT_VIEWS);
+            }
+            ppc_text
+        })
+        .collect()
+}
+
+/// Entry point for `push text --recipients`.
+pub fn run(
+    transport: &dyn Transport,
+    instance: &Instance,
+    args: &PPCArgs,
+    config: &PPCConfig,
+    given: &PPCText,
+    merged: &PPCText,
+) -> Result<(), PPCError> {
+    let items = given
+        .recipients
+        .iter()
+        .zip(options_for(given, merged))
+        .map(|(recipient, ppc_text)| {
+            let mut item = jobs::Item::new(recipient.clone());
+            item.body = Some(api_body::push_form(&ppc_text));
+            item.note = ppc_text.note;
+            item
+        })
+        .collect();
+    let mut job = jobs::Job::new(
+        instance,
+        jobs::Operation::Push {
+            notify_webhook: merged.notify_webhook.clone(),
+        },
+        items,
+    );
+
+    let store = jobs::Jobs::open(&jobs::default_dir()?, &paths::key_dir()?)?;
+    jobs::execute(
+        transport,
+        instance,
+        args,
+        config,
+        &store,
+        &mut job,
+        bulk::DEFAULT_JOBS,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+
+    use super::{check, note_for, options_for};
+    use crate::args::{PPCAction, PPCArgs, PPCObject, PPCText};
+
+    fn ppc_text(extra: &[&str]) -> PPCText {
+        let mut argv = vec![
+            "pwpush-cli",
+            "push",
+            "text",
+            "secret",
+            "--recipients",
+            "alice,bob",
+        ];
+        argv.extend_from_slice(extra);
+        let PPCAction::Push(PPCObject::Text(ppc_text)) = PPCArgs::parse_from(argv).action else {
+            panic!("not a text push");
+        };
+        *ppc_text
+    }
+
+    #[test]
+    fn notes_name_the_recipient() {
+        assert_eq!(note_for(None, "alice"), "for alice");
+        assert_eq!(note_for(Some("db"), "alice"), "db (for alice)");
+        assert_eq!(
+            note_for(Some("db for {recipient}"), "alice"),
+            "db for alice"
+        );
+    }
+
+    #[test]
+    fn one_view_unless_given() {
+        let given = ppc_text(&[]);
+        let mut merged = given.clone();
+        // e.g. from the push defaults of the configuration file.
+        merged.expire_after_views = Some(5);
+
+        let options = options_for(&given, &merged);
+
+        assert_eq!(options.len(), 2);
+        assert_eq!(options[0].expire_after_views, Some(1));
+        assert_eq!(options[1].note.as_deref(), Some("for bob"));
+
+        let given = ppc_text(&["--expire-after-views", "3"]);
+        let options = options_for(&given, &given);
+        assert_eq!(options[0].expire_after_views, Some(3));
+    }
+
+    #[test]
+    fn duplicates_are_refused() {
+        let names = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+        assert!(check(&names(&["alice", "bob"])).is_ok());
+        assert!(check(&names(&["alice", "bob", "alice"])).is_err());
+        assert!(check(&names(&["alice", " "])).is_err());
+    }
+}
@@ -0,0 +1,252 @@
+//! Record and replay of API interactions.
+//!
+//! With `--record <dir>`, every request and the matching response are written
+//! to the given directory as numbered JSON files (`000.json`, `001.json`, ...).
+//! Before anything touches the disk, secrets are removed: the authentication
+//! headers, as well as payload and passphrase in URLs, request bodies and JSON
+//! responses, are replaced by a placeholder. The resulting directory can be
+//! attached to a bug report as is.
+//!
+//! With `--replay <dir>`, no network connection is made at all. Instead, the
+//! recorded responses are handed out in order, and each incoming request is
+//! checked against the recorded method and URL. The same mechanism is used by
+//! tests, with fixtures living in `tests/fixtures`.
+
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::errors::PPCError;
+use crate::http::{PPCRequest, PPCResponse, Transport};
+use crate::redact::{sanitize_body, sanitize_headers, sanitize_json, sanitize_url};
+
+/// On-disk format of a single recorded request/response pair.
+#[derive(Debug, Serialize, Deserialize)]
+struct Exchange {
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RecordedRequest {
+    method: String,
+    url: String,
+    headers: Vec<(String, String)>,
+    body: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RecordedResponse {
+    status: u16,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+/// Wraps another transport and writes a sanitized copy of every exchange to
+/// the recording directory.
+pub struct RecordingTransport<T: Transport> {
+    inner: T,
+    dir: PathBuf,
+    counter: AtomicUsize,
+}
+
+impl<T: Transport> RecordingTransport<T> {
+    pub fn new(inner: T, dir: &Path) -> Result<Self, PPCError> {
+        fs::create_dir_all(dir).map_err(|e| {
+            PPCError::from(format!(
+                "could not create recording directory {}: {}",
+                dir.display(),
+                e
+            ))
+        })?;
+
+        Ok(Self {
+            inner,
+            dir: dir.to_path_buf(),
+            counter: AtomicUsize::new(0),
+        })
+    }
+}
+
+impl<T: Transport> Transport for RecordingTransport<T> {
+    fn send(&self, request: &PPCRequest) -> Result<PPCResponse, PPCError> {
+        let response = self.inner.send(request)?;
+
+        let exchange = Exchange {
+            request: sanitize_request(request),
+            response: RecordedResponse {
+                status: response.status,
+                headers: sanitize_headers(&response.headers),
+                body: sanitize_json(&response.body),
+            },
+        };
+
+        let number = self.counter.fetch_add(1, Ordering::Relaxed);
+        let path = self.dir.join(format!("{:03}.json", number));
+
+        // the json is always serializable, as it only consists of strings and
+        // numbers.
+        let content = serde_json::to_string_pretty(&exchange).unwrap();
+        fs::write(&path, content).map_err(|e| {
+            PPCError::from(format!(
+                "could not write recording {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        debug!("recorded exchange to {}", path.display());
+
+        Ok(response)
+    }
+}
+
+/// Answers requests from a previously recorded directory instead of the
+/// network.
+pub struct ReplayTransport {
+    exchanges: Mutex<VecDeque<Exchange>>,
+}
+
+impl ReplayTransport {
+    pub fn new(dir: &Path) -> Result<Self, PPCError> {
+        let read_err = |e: std::io::Error| {
+            PPCError::from(format!(
+                "could not read replay directory {}: {}",
+                dir.display(),
+                e
+            ))
+        };
+
+        // the file names are zero-padded, so sorting them gives the order in
+        // which they were recorded.
+        let mut paths = fs::read_dir(dir)
+            .map_err(read_err)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect::<Vec<_>>();
+        paths.sort();
+
+        let mut exchanges = VecDeque::with_capacity(paths.len());
+        for path in paths {
+            let content = fs::read_to_string(&path).map_err(read_err)?;
+            let exchange = serde_json::from_str(&content).map_err(|e| {
+                PPCError::from(format!("invalid recording {}: {}", path.display(), e))
+            })?;
+            exchanges.push_back(exchange);
+        }
+
+        Ok(Self {
+            exchanges: Mutex::new(exchanges),
+        })
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn send(&self, request: &PPCRequest) -> Result<PPCResponse, PPCError> {
+        let exchange = self.exchanges.lock().unwrap().pop_front().ok_or_else(|| {
+            PPCError::from(format!(
+                "no recorded response left for {} {}",
+                request.method, request.url
+            ))
+        })?;
+
+        // recordings only contain sanitized URLs, so the request has to be
+        // sanitized the same way before comparing.
+        let recorded = &exchange.request;
+        if recorded.method != request.method.to_string()
+            || recorded.url != sanitize_url(&request.url)
+        {
+            return Err(PPCError::from(format!(
+                "request {} {} does not match recording {} {}",
+                request.method,
+                sanitize_url(&request.url),
+                recorded.method,
+                recorded.url
+            )));
+        }
+
+        let mut response = PPCResponse {
+            status: exchange.response.status,
+            headers: exchange.response.headers,
+            body: exchange.response.body,
+            not_modified: false,
+        };
+        // the content of downloads is never recorded, so replayed downloads
+        // get the recorded body, which is empty.
+        if let Some(path) = request
+            .download
+            .as_deref()
+            .filter(|_| response.is_success())
+        {
+            fs::write(path, &response.body).map_err(|e| {
+                PPCError::from(format!("could not download to {}: {}", path.display(), e))
+            })?;
+            response.body.clear();
+        }
+        Ok(response)
+    }
+}
+
+fn sanitize_request(request: &PPCRequest) -> RecordedRequest {
+    RecordedRequest {
+        method: request.method.to_string(),
+        url: sanitize_url(&request.url),
+        headers: sanitize_headers(&request.headers),
+        body: match &request.multipart {
+            Some(multipart) => Some(multipart.summary()),
+            None => request.body.as_deref().map(sanitize_body),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::{RecordingTransport, ReplayTransport};
+    use crate::http::mock::MockTransport;
+    use crate::http::{Method, PPCRequest, Transport};
+
+    fn fixture(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join(name)
+    }
+
+    #[test]
+    fn record_then_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let request = PPCRequest::new(Method::Post, String::from("https://pwpush.com/p.json"))
+            .header("X-User-Token", "t0k3n")
+            .body(String::from("password[payload]=secret"));
+
+        let mock = MockTransport::new().respond(201, r#"{"url_token":"abc"}"#);
+        let recorder = RecordingTransport::new(mock, dir.path()).unwrap();
+        recorder.send(&request).unwrap();
+
+        let recorded = std::fs::read_to_string(dir.path().join("000.json")).unwrap();
+        assert!(!recorded.contains("t0k3n"));
+        assert!(!recorded.contains("secret"));
+
+        let replay = ReplayTransport::new(dir.path()).unwrap();
+        let response = replay.send(&request).unwrap();
+        assert_eq!(response.status, 201);
+        assert_eq!(response.body, r#"{"url_token":"abc"}"#);
+
+        assert!(replay.send(&request).is_err());
+    }
+
+    #[test]
+    fn replay_rejects_mismatching_request() {
+        let replay = ReplayTransport::new(&fixture("push_text")).unwrap();
+        let request = PPCRequest::new(Method::Post, String::from("https://example.com/p.json"));
+
+        assert!(replay.send(&request).is_err());
+    }
+}
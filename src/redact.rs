@@ -0,0 +1,228 @@
+//! Removal of secrets from everything that leaves the application by other
+//! means than the API itself: recordings, logs and `Debug` output (which also
+//! ends up in panic messages).
+//!
+//! Fields of log lines and spans are redacted by their name, see `log_field`,
+//! so a secret attached to a span by mistake never reaches a log file.
+//!
+//! Secret values are replaced by a placeholder rather than removed, so it is
+//! still visible that a value was present.
+
+use std::fmt;
+
+/// Placeholder that replaces every secret value.
+pub const REDACTED: &str = "REDACTED";
+
+/// Headers whose values must never be shown.
+const SECRET_HEADERS: [&str; 5] = [
+    "Authorization",
+    "X-User-Email",
+    "X-User-Token",
+    "X-Vault-Token",
+    "Set-Cookie",
+];
+
+/// Body parameters, query parameters and JSON keys whose values must never be
+/// shown.
+const SECRET_FIELDS: [&str; 2] = ["payload", "passphrase"];
+
+/// Stands in for a secret value in `Debug` implementations.
+pub struct Redacted;
+
+impl fmt::Debug for Redacted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+/// Debug representation of an optional secret, which only shows whether a
+/// value is present.
+pub fn option<T>(value: &Option<T>) -> Option<Redacted> {
+    value.as_ref().map(|_| Redacted)
+}
+
+pub fn sanitize_headers(headers: &[(String, String)]) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            if SECRET_HEADERS.iter().any(|h| h.eq_ignore_ascii_case(name)) {
+                (name.clone(), String::from(REDACTED))
+            } else {
+                (name.clone(), value.clone())
+            }
+        })
+        .collect()
+}
+
+/// Redact secret query parameters of a URL, e.g. the passphrase of a
+/// retrieval.
+pub fn sanitize_url(url: &str) -> String {
+    match url.split_once('?') {
+        Some((base, query)) => format!(
+            "{}?{}",
+            base,
+            redact_pairs(query, |key| SECRET_FIELDS.contains(&key))
+        ),
+        None => String::from(url),
+    }
+}
+
+/// Redact secret parameters in a request body of the form
+/// `password[payload]=...&password[note]=...`. Only the values are replaced,
+/// so the output still shows which options were sent.
+pub fn sanitize_body(body: &str) -> String {
+    redact_pairs(body, is_secret_param)
+}
+
+/// Replace the values of all `key=value` pairs in an urlencoded string for
+/// which `is_secret` returns true.
+fn redact_pairs(pairs: &str, is_secret: impl Fn(&str) -> bool) -> String {
+    pairs
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((key, _)) if is_secret(key) => format!("{}={}", key, REDACTED),
+            _ => String::from(pair),
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn is_secret_param(key: &str) -> bool {
+    SECRET_FIELDS
+        .iter()
+        .any(|field| key.ends_with(&format!("[{}]", field)))
+}
+
+/// Redact secret keys anywhere in a JSON response. Bodies that are not valid
+/// JSON (e.g. HTML error pages) are kept as they are, as they do not contain
+/// any push data.
+pub fn sanitize_json(body: &str) -> String {
+    match serde_json::from_str::<serde_json::Value>(body) {
+        Ok(mut value) => {
+            redact_value(&mut value);
+            value.to_string()
+        }
+        Err(_) => String::from(body),
+    }
+}
+
+/// Whether a field of a log line or span holds a secret, by its name: the
+/// secret body fields and headers, e.g. `passphrase` or `x_user_token`, and
+/// anything named like a token or password. URL tokens of pushes are in every
+/// URL that is logged, so they are kept.
+pub fn is_secret_log_field(name: &str) -> bool {
+    let name = name.to_ascii_lowercase().replace('-', "_");
+    SECRET_FIELDS.contains(&name.as_str())
+        || SECRET_HEADERS
+            .iter()
+            .any(|header| header.to_ascii_lowercase().replace('-', "_") == name)
+        || (name.ends_with("token") && name != "url_token")
+        || name.ends_with("password")
+}
+
+/// The value of a field of a log line or span as it may be logged. Secrets
+/// are replaced, and URLs and request bodies are sanitized like in
+/// recordings.
+pub fn log_field(name: &str, value: &str) -> String {
+    match name {
+        _ if is_secret_log_field(name) => String::from(REDACTED),
+        "url" | "path" => sanitize_url(value),
+        "body" => sanitize_body(value),
+        _ => String::from(value),
+    }
+}
+
+fn redact_value(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, v) in map.iter_mut() {
+                if SECRET_FIELDS.contains(&key.as_str()) && !v.is_null() {
+                    *v = serde_json::Value::from(REDACTED);
+                } else {
+                    redact_value(v);
+                }
+            }
+        }
+        serde_json::Value::Array(values) => values.iter_mut().for_each(redact_value),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{log_field, option, sanitize_body, sanitize_headers, sanitize_json, sanitize_url};
+
+    #[test]
+    fn option_shows_presence_only() {
+        assert_eq!(format!("{:?}", option(&Some("secret"))), "Some(REDACTED)");
+        assert_eq!(format!("{:?}", option::<&str>(&None)), "None");
+    }
+
+    #[test]
+    fn sanitize_headers_redacts_credentials() {
+        let headers = vec![
+            (String::from("x-user-token"), String::from("t0k3n")),
+            (String::from("Accept"), String::from("application/json")),
+        ];
+
+        assert_eq!(
+            sanitize_headers(&headers),
+            vec![
+                (String::from("x-user-token"), String::from("REDACTED")),
+                (String::from("Accept"), String::from("application/json")),
+            ]
+        );
+    }
+
+    #[test]
+    fn sanitize_body_redacts_secrets_only() {
+        let actual = sanitize_body(
+            "password[payload]=secret&password[passphrase]=pass&password[expire_after_days]=2",
+        );
+        let expected = "password[payload]=REDACTED&password[passphrase]=REDACTED&\
+                        password[expire_after_days]=2";
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn sanitize_url_redacts_passphrase() {
+        assert_eq!(
+            sanitize_url("https://pwpush.com/p/abc.json?passphrase=s3cr3t&x=1"),
+            "https://pwpush.com/p/abc.json?passphrase=REDACTED&x=1"
+        );
+        assert_eq!(
+            sanitize_url("https://pwpush.com/p/abc.json"),
+            "https://pwpush.com/p/abc.json"
+        );
+    }
+
+    #[test]
+    fn sanitize_json_redacts_nested_secrets() {
+        let actual =
+            sanitize_json(r#"{"url_token":"abc","payload":"secret","x":[{"passphrase":"p"}]}"#);
+        let expected =
+            r#"{"url_token":"abc","payload":"REDACTED","x":[{"passphrase":"REDACTED"}]}"#;
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn sanitize_json_keeps_non_json() {
+        assert_eq!(sanitize_json("<html></html>"), "<html></html>");
+    }
+
+    #[test]
+    fn log_fields_are_redacted_by_name() {
+        assert_eq!(log_field("passphrase", "s3cret"), "REDACTED");
+        assert_eq!(log_field("X-User-Token", "t0k3n"), "REDACTED");
+        assert_eq!(log_field("api_token", "t0k3n"), "REDACTED");
+        assert_eq!(log_field("smtp_password", "pw"), "REDACTED");
+        assert_eq!(
+            log_field("url", "https://pwpush.com/p/abc.json?passphrase=s3cr3t"),
+            "https://pwpush.com/p/abc.json?passphrase=REDACTED"
+        );
+        assert_eq!(log_field("command", "push"), "push");
+        assert_eq!(log_field("url_token", "abc"), "abc");
+    }
+}
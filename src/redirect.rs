@@ -0,0 +1,227 @@
+//! Redirects of the instance, e.g. from `http://` to `https://`, or from
+//! `www.` to the apex domain.
+//!
+//! reqwest would follow them on its own, and send the `X-User-Email` and
+//! `X-User-Token` headers and the payload along to wherever the instance
+//! points. Requests to the instance are therefore sent without redirects, see
+//! `ReqwestTransport::without_redirects`, and `RedirectTransport` decides:
+//!   - GET requests follow redirects. If the target has another origin, only
+//!     headers that are known to carry no credentials are sent there, see
+//!     `SAFE_HEADERS`. This is also how downloads reach the storage service.
+//!   - Other requests carry credentials or payloads. They follow redirects
+//!     that keep method and body (307 and 308) within the same origin, and
+//!     fail for all others, before anything is sent to the new address.
+//!
+//! A permanent redirect (301 or 308) to another origin means that the
+//! instance moved, so the new address is shown, to be used with `--url` or in
+//! the profile. Following the redirect costs a round trip for every request,
+//! and with the credentials left behind, most API calls fail there anyway.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tracing::{debug, warn};
+use url::Url;
+use zeroize::Zeroize;
+
+use crate::errors::PPCError;
+use crate::http::{Method, PPCRequest, PPCResponse, Transport};
+use crate::i18n;
+use crate::redact::sanitize_url;
+
+/// Redirects followed for one request, like browsers do.
+const MAX_REDIRECTS: usize = 10;
+
+/// Headers that are sent to other origins, compared ignoring case.
+const SAFE_HEADERS: [&str; 4] = ["Accept", "Accept-Language", "User-Agent", "X-Request-Id"];
+
+/// Wraps the transport to the instance and handles its redirects.
+pub struct RedirectTransport<T: Transport> {
+    inner: T,
+
+    /// The move of the instance was shown already, so it is not repeated for
+    /// every request of bulk operations.
+    warned: AtomicBool,
+}
+
+impl<T: Transport> RedirectTransport<T> {
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            warned: AtomicBool::new(false),
+        }
+    }
+}
+
+impl<T: Transport> Transport for RedirectTransport<T> {
+    fn send(&self, request: &PPCRequest) -> Result<PPCResponse, PPCError> {
+        let mut response = self.inner.send(request)?;
+        let mut current: Option<PPCRequest> = None;
+
+        for _ in 0..MAX_REDIRECTS {
+            let from = current.as_ref().unwrap_or(request);
+            let Some(target) = location(&from.url, &response) else {
+                break;
+            };
+            let next = match follow(from, &target, response.status) {
+                Ok(next) => next,
+                Err(error) => {
+                    zeroize(&mut current);
+                    return Err(error);
+                }
+            };
+            if moved(response.status, &from.url, &target)
+                && !self.warned.swap(true, Ordering::Relaxed)
+            {
+                warn!(
+                    "{}",
+                    i18n::format(
+                        "redirect-moved",
+                        &[
+                            ("from", origin(&from.url).into()),
+                            ("to", origin(&target).into()),
+                            ("hint", hint(&target).into())
+                        ]
+                    )
+                );
+            }
+            debug!(
+                "following redirect with status {} to {}",
+                response.status,
+                sanitize_url(&target)
+            );
+            zeroize(&mut current);
+            current = Some(next);
+            response = self.inner.send(current.as_ref().unwrap())?;
+        }
+
+        zeroize(&mut current);
+        Ok(response)
+    }
+}
+
+/// The absolute target of a redirect, if the response is one.
+fn location(url: &str, response: &PPCResponse) -> Option<String> {
+    if !matches!(response.status, 301 | 302 | 303 | 307 | 308) {
+        return None;
+    }
+    let location = response.header("Location")?;
+    Url::parse(url)
+        .and_then(|base| base.join(location))
+        .ok()
+        .map(String::from)
+}
+
+/// The request to send to `target`, or the error if it must not be sent.
+fn follow(request: &PPCRequest, target: &str, status: u16) -> Result<PPCRequest, PPCError> {
+    let same_origin = origin(&request.url) == origin(target);
+    let mut next = request.clone();
+    next.url = String::from(target);
+
+    match request.method {
+        Method::Get if !same_origin => {
+            next.headers.retain(|(name, _)| {
+                SAFE_HEADERS
+                    .iter()
+                    .any(|safe| name.eq_ignore_ascii_case(safe))
+            });
+            Ok(next)
+        }
+        Method::Get => Ok(next),
+        _ if same_origin && matches!(status, 307 | 308) => Ok(next),
+        method => {
+            next.body.zeroize();
+            Err(PPCError::from(i18n::format(
+                "error-redirect",
+                &[
+                    ("method", method.to_string().into()),
+                    ("target", sanitize_url(target).into()),
+                ],
+            ))
+            .with_hint(hint(target)))
+        }
+    }
+}
+
+/// True if the instance moved permanently to another origin.
+fn moved(status: u16, url: &str, target: &str) -> bool {
+    matches!(status, 301 | 308) && origin(url) != origin(target)
+}
+
+/// Scheme, host and port of a URL, e.g. `https://pwpush.com`, which is also
+/// the address to use with `--url`.
+fn origin(url: &str) -> String {
+    Url::parse(url)
+        .map(|url| url.origin().ascii_serialization())
+        .unwrap_or_default()
+}
+
+fn hint(target: &str) -> String {
+    i18n::format("hint-redirect", &[("target", origin(target).into())])
+}
+
+/// Copies of requests may contain the payload, see `pwpush_api::send_text_push`.
+fn zeroize(request: &mut Option<PPCRequest>) {
+    if let Some(request) = request {
+        request.body.zeroize();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RedirectTransport;
+    use crate::http::mock::MockTransport;
+    use crate::http::{Method, PPCRequest, Transport};
+
+    #[test]
+    fn follows_get_without_credentials() {
+        let transport = RedirectTransport::new(
+            MockTransport::new()
+                .respond_with_headers(
+                    301,
+                    &[("Location", "https://pwpush.example.com/p.json")],
+                    "",
+                )
+                .respond_with_headers(302, &[("Location", "/p/abc.json")], "")
+                .respond(200, "{}"),
+        );
+        let request = PPCRequest::new(
+            Method::Get,
+            String::from("http://pwpush.example.com/p.json"),
+        )
+        .header("X-User-Token", "t0k3n")
+        .header("User-Agent", "pwpush-cli");
+
+        assert_eq!(transport.send(&request).unwrap().status, 200);
+        let requests = transport.inner.requests();
+        assert_eq!(requests[1].url, "https://pwpush.example.com/p.json");
+        assert_eq!(requests[2].url, "https://pwpush.example.com/p/abc.json");
+        assert_eq!(
+            requests[1].headers,
+            vec![(String::from("User-Agent"), String::from("pwpush-cli"))]
+        );
+    }
+
+    #[test]
+    fn payloads_are_not_resent_to_other_origins() {
+        let transport = RedirectTransport::new(
+            MockTransport::new()
+                .respond_with_headers(308, &[("Location", "/api/p.json")], "")
+                .respond(201, "{}"),
+        );
+        let request = PPCRequest::new(Method::Post, String::from("https://pwpush.com/p.json"))
+            .body(String::from("password[payload]=secret"));
+        assert_eq!(transport.send(&request).unwrap().status, 201);
+        assert_eq!(transport.inner.requests()[1].body, request.body);
+
+        let transport = RedirectTransport::new(MockTransport::new().respond_with_headers(
+            301,
+            &[("Location", "https://pwpush.com/p.json")],
+            "",
+        ));
+        let request = PPCRequest::new(Method::Post, String::from("http://pwpush.com/p.json"))
+            .body(String::from("password[payload]=secret"));
+        let error = transport.send(&request).unwrap_err();
+        assert!(error.hint().unwrap().contains("https://pwpush.com"));
+        assert_eq!(transport.inner.requests().len(), 1);
+    }
+}
@@ -0,0 +1,221 @@
+//! Reminders for pushes that are about to expire.
+//!
+//! `remind` lists all active pushes that expire within the given window and
+//! exits with `EXIT_EXPIRING` if there are any. This makes it easy to run from
+//! cron and get notified before an important link stops working.
+//!
+//! The expiration time is computed from the creation time and the configured
+//! number of days. If the instance does not report these, the remaining days
+//! are used instead, which are only accurate to a day. Either way, the window
+//! starts at the time of the instance, see `skew`.
+
+use std::process::ExitCode;
+
+use chrono::{DateTime, Duration, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::args::{PPCArgs, PPCRemind, PushKind, PushState};
+use crate::errors::PPCError;
+use crate::http::Transport;
+use crate::instance::Instance;
+use crate::models::Push;
+use crate::{duration, output, pwpush_api, skew};
+
+/// Exit code if at least one push expires within the window. Errors exit
+/// with 1, so scripts can tell both apart.
+pub const EXIT_EXPIRING: u8 = 2;
+
+#[derive(Debug, PartialEq, Serialize, JsonSchema)]
+pub struct Reminder {
+    pub kind: PushKind,
+    pub url_token: String,
+    pub expires_at: DateTime<Utc>,
+    pub views_remaining: Option<i64>,
+    pub note: Option<String>,
+}
+
+pub fn run(
+    transport: &dyn Transport,
+    instance: &Instance,
+    args: &PPCArgs,
+    ppc_remind: &PPCRemind,
+) -> Result<ExitCode, PPCError> {
+    let window = window(ppc_remind.within)?;
+    let mut lists = Vec::new();
+    for kind in [PushKind::Text, PushKind::File, PushKind::Url] {
+        lists.push((
+            kind,
+            pwpush_api::listing(transport, instance, kind, PushState::Active)?,
+        ));
+    }
+    let now = skew::now(lists.iter().find_map(|(_, listing)| listing.skew));
+
+    let mut reminders = Vec::new();
+    for (kind, listing) in &lists {
+        reminders.extend(expiring(*kind, &listing.pushes, now, window));
+    }
+    reminders.sort_by_key(|r| r.expires_at);
+
+    if args.json_output {
+        output::print_json(&reminders);
+    } else {
+        for reminder in &reminders {
+            println!("{}", format_reminder(reminder, now));
+        }
+    }
+
+    if reminders.is_empty() {
+        Ok(ExitCode::SUCCESS)
+    } else {
+        Ok(ExitCode::from(EXIT_EXPIRING))
+    }
+}
+
+/// The window of `--within` for chrono, which covers a smaller range than the
+/// parser.
+fn window(within: std::time::Duration) -> Result<Duration, PPCError> {
+    Duration::from_std(within)
+        .map_err(|_| PPCError::from(format!("--within {} is too long", duration::format(within))))
+}
+
+/// The pushes that expire no later than `now + window`. Windows that reach
+/// beyond the dates chrono can represent include all pushes.
+fn expiring(
+    kind: PushKind,
+    pushes: &[Push],
+    now: DateTime<Utc>,
+    window: Duration,
+) -> Vec<Reminder> {
+    let until = now.checked_add_signed(window);
+    pushes
+        .iter()
+        .filter_map(|push| {
+            let expires_at = expires_at(push, now)?;
+            until
+                .is_none_or(|until| expires_at <= until)
+                .then(|| Reminder {
+                    kind,
+                    url_token: push.url_token.clone(),
+                    expires_at,
+                    views_remaining: push.views_remaining,
+                    note: push.note.clone(),
+                })
+        })
+        .collect()
+}
+
+/// When the push expires by days. Limits too far out to represent, as broken
+/// instances may report, count as never.
+fn expires_at(push: &Push, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let created_at = push
+        .created_at
+        .as_deref()
+        .and_then(|created_at| DateTime::parse_from_rfc3339(created_at).ok());
+
+    match (created_at, push.expire_after_days) {
+        (Some(created_at), Some(days)) => created_at
+            .with_timezone(&Utc)
+            .checked_add_signed(Duration::try_days(i64::try_from(days).ok()?)?),
+        _ => now.checked_add_signed(Duration::try_days(push.days_remaining?)?),
+    }
+}
+
+fn format_reminder(reminder: &Reminder, now: DateTime<Utc>) -> String {
+    let left = reminder.expires_at - now;
+    let left = if left.num_hours() >= 1 {
+        format!("{}h", left.num_hours())
+    } else {
+        format!("{}m", left.num_minutes().max(0))
+    };
+
+    let line = format!(
+        "{} {} expires in {} ({})",
+        reminder.kind,
+        reminder.url_token,
+        left,
+        reminder.expires_at.to_rfc3339()
+    );
+    match &reminder.note {
+        Some(note) if !note.is_empty() => format!("{}: {}", line, note),
+        _ => line,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{DateTime, Duration, Utc};
+
+    use super::{expiring, format_reminder, window};
+    use crate::args::PushKind;
+    use crate::models::Push;
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2023-12-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn push(url_token: &str, created_at: Option<&str>, days: u64, remaining: i64) -> Push {
+        Push {
+            url_token: String::from(url_token),
+            created_at: created_at.map(String::from),
+            expire_after_days: Some(days),
+            days_remaining: Some(remaining),
+            ..Push::default()
+        }
+    }
+
+    #[test]
+    fn expiring_uses_creation_time() {
+        let pushes = [
+            push("soon", Some("2023-11-28T14:00:00Z"), 3, 1),
+            push("later", Some("2023-11-29T14:00:00Z"), 3, 2),
+        ];
+
+        let reminders = expiring(PushKind::Text, &pushes, now(), Duration::hours(24));
+
+        assert_eq!(reminders.len(), 1);
+        assert_eq!(reminders[0].url_token, "soon");
+        assert_eq!(reminders[0].expires_at, now() + Duration::hours(2));
+        assert_eq!(
+            format_reminder(&reminders[0], now()),
+            "text soon expires in 2h (2023-12-01T14:00:00+00:00)"
+        );
+    }
+
+    #[test]
+    fn expiring_falls_back_to_remaining_days() {
+        let pushes = [push("a", None, 7, 1), push("b", None, 7, 2)];
+
+        let reminders = expiring(PushKind::Url, &pushes, now(), Duration::days(1));
+
+        assert_eq!(reminders.len(), 1);
+        assert_eq!(reminders[0].url_token, "a");
+    }
+
+    #[test]
+    fn expiring_ignores_limits_out_of_range() {
+        let pushes = [
+            push("a", Some("2023-11-28T14:00:00Z"), u64::MAX, 1),
+            push("b", None, 7, i64::MAX),
+            push("c", None, 7, i64::MIN),
+        ];
+
+        let reminders = expiring(PushKind::Text, &pushes, now(), Duration::days(1));
+
+        assert_eq!(reminders.len(), 0);
+    }
+
+    #[test]
+    fn huge_windows_do_not_panic() {
+        let pushes = [push("a", Some("2023-11-28T14:00:00Z"), 7, 4)];
+
+        let reminders = expiring(PushKind::Text, &pushes, now(), Duration::MAX);
+
+        assert_eq!(reminders.len(), 1);
+        let err = window(std::time::Duration::from_secs(100_000_000_000 * 7 * 86_400)).unwrap_err();
+        assert!(err.to_string().contains("--within"));
+        assert!(err.to_string().contains("is too long"));
+    }
+}
@@ -0,0 +1,310 @@
+//! Output of commands in the format selected with `--output`.
+//!
+//! Commands that support renderers do not print their result themselves.
+//! They return a typed result, like `account::Account`, that implements
+//! `CommandOutput`, and `main` hands it to the `Renderer` that was selected
+//! once from the arguments with `from_args`:
+//!   - `human`, the default, prints text for people, e.g. tables that fit the
+//!     terminal, see `table::width`.
+//!   - `json`, the same as `--json`, prints the versioned JSON of `output`.
+//!   - `quiet` prints only the one value scripts usually need, e.g. the link
+//!     of a new push, or nothing at all.
+//!   - `csv` prints the JSON as table, one row per object of a list or a
+//!     single row for an object. Nested values are JSON in their cell.
+//!   - `plain`, also selected with `--plain`, prints the JSON as `label:
+//!     value` lines, with a blank line between the objects of a list. There
+//!     are no colors, no aligned columns and no symbols, so screen readers
+//!     read each value with its label, and narrow terminals just wrap lines.
+//!
+//! As CSV and plain output are derived from the JSON, and quiet defaults to
+//! no output, a new result only has to describe its JSON and its text.
+//! Commands that still print their output directly only support human and
+//! JSON output, and fail early with quiet and csv, see `check`. With plain,
+//! they print their text, but without colors, QR codes in the terminal, or
+//! columns cut off with `…`, see `is_plain`.
+
+use std::io::{self, Write};
+
+use serde_json::Value;
+
+use crate::args::{OutputFormat, PPCAction, PPCArgs, PPCAudit, PPCExpire};
+use crate::errors::PPCError;
+use crate::{output, table};
+
+/// The result of a command, in every format it can be rendered in.
+pub trait CommandOutput {
+    /// The result as JSON, without `schema_version`, which is added by
+    /// `output`.
+    fn json(&self) -> Value;
+
+    /// The result as text for people. Tables must fit into `width` columns if
+    /// it is given.
+    fn human(&self, width: Option<usize>) -> String;
+
+    /// The single value of the result that scripts need, if any, with a
+    /// trailing newline.
+    fn quiet(&self) -> String {
+        String::new()
+    }
+}
+
+/// Prints results of commands in one format.
+pub trait Renderer {
+    fn render(&self, output: &dyn CommandOutput) -> Result<(), PPCError>;
+}
+
+pub struct HumanRenderer {
+    width: Option<usize>,
+}
+
+pub struct JsonRenderer;
+
+pub struct QuietRenderer;
+
+pub struct CsvRenderer;
+
+pub struct PlainRenderer;
+
+impl Renderer for HumanRenderer {
+    fn render(&self, output: &dyn CommandOutput) -> Result<(), PPCError> {
+        print!("{}", output.human(self.width));
+        Ok(())
+    }
+}
+
+impl Renderer for JsonRenderer {
+    fn render(&self, output: &dyn CommandOutput) -> Result<(), PPCError> {
+        output::print_json(&output.json());
+        Ok(())
+    }
+}
+
+impl Renderer for QuietRenderer {
+    fn render(&self, output: &dyn CommandOutput) -> Result<(), PPCError> {
+        print!("{}", output.quiet());
+        Ok(())
+    }
+}
+
+impl Renderer for CsvRenderer {
+    fn render(&self, output: &dyn CommandOutput) -> Result<(), PPCError> {
+        let csv = to_csv(&output.json())?;
+        io::stdout()
+            .write_all(csv.as_bytes())
+            .map_err(|e| PPCError::from(format!("could not write csv: {}", e)))
+    }
+}
+
+impl Renderer for PlainRenderer {
+    fn render(&self, output: &dyn CommandOutput) -> Result<(), PPCError> {
+        print!("{}", to_plain(&output.json()));
+        Ok(())
+    }
+}
+
+/// The renderer of `--output`, or of `--json`.
+pub fn from_args(args: &PPCArgs) -> Box<dyn Renderer> {
+    match args.output_format {
+        _ if args.json_output => Box::new(JsonRenderer),
+        OutputFormat::Human => Box::new(HumanRenderer {
+            width: table::width(args),
+        }),
+        OutputFormat::Json => Box::new(JsonRenderer),
+        OutputFormat::Quiet => Box::new(QuietRenderer),
+        OutputFormat::Csv => Box::new(CsvRenderer),
+        OutputFormat::Plain => Box::new(PlainRenderer),
+    }
+}
+
+/// True with `--plain`, for output that does not go through a renderer, like
+/// errors, tables and QR codes.
+pub fn is_plain(args: &PPCArgs) -> bool {
+    args.output_format == OutputFormat::Plain
+}
+
+/// Fail if the command does not support the format of `--output`, before it
+/// changes anything.
+pub fn check(args: &PPCArgs) -> Result<(), PPCError> {
+    #[cfg(feature = "tui")]
+    if is_plain(args) && matches!(args.action, PPCAction::Browse(_)) {
+        return Err(PPCError::from("browse is not available with --plain")
+            .with_hint(String::from("use list and info instead")));
+    }
+    let supported = matches!(
+        args.action,
+        PPCAction::Push(_)
+            | PPCAction::Wizard
+            | PPCAction::Audit(PPCAudit { all: true, .. })
+            | PPCAction::Expire(PPCExpire::Push(_))
+            | PPCAction::Account
+            | PPCAction::Summary
+            | PPCAction::Version
+            | PPCAction::Paths
+            | PPCAction::Config(_)
+    );
+    match args.output_format {
+        OutputFormat::Quiet | OutputFormat::Csv if !supported => Err(PPCError::from(format!(
+            "this command does not support --output {}",
+            args.output_format
+        ))
+        .with_hint(String::from("use --output human or --output json"))),
+        _ => Ok(()),
+    }
+}
+
+/// The rows of a JSON result, with the keys of the first object as header.
+fn to_csv(value: &Value) -> Result<String, PPCError> {
+    let records = match value {
+        Value::Array(items) => items.iter().collect::<Vec<_>>(),
+        other => vec![other],
+    };
+    let csv_err = |e: csv::Error| PPCError::from(format!("could not write csv: {}", e));
+    let mut writer = csv::Writer::from_writer(Vec::new());
+
+    let header = match records.first() {
+        Some(Value::Object(object)) => object.keys().cloned().collect::<Vec<_>>(),
+        Some(_) => vec![String::from("value")],
+        None => return Ok(String::new()),
+    };
+    writer.write_record(&header).map_err(csv_err)?;
+    for record in records {
+        let row = match record {
+            Value::Object(object) => header
+                .iter()
+                .map(|key| cell(object.get(key).unwrap_or(&Value::Null)))
+                .collect::<Vec<_>>(),
+            other => vec![cell(other)],
+        };
+        writer.write_record(row).map_err(csv_err)?;
+    }
+
+    let bytes = writer
+        .into_inner()
+        .map_err(|e| PPCError::from(format!("could not write csv: {}", e)))?;
+    // all cells are valid UTF-8 strings, and so is the csv made of them.
+    Ok(String::from_utf8(bytes).unwrap())
+}
+
+/// The JSON as `label: value` lines, see the module docs.
+fn to_plain(value: &Value) -> String {
+    let mut out = String::new();
+    match value {
+        Value::Array(items) if items.iter().all(Value::is_object) => {
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    out.push('\n');
+                }
+                plain_lines("", item, &mut out);
+            }
+        }
+        other => plain_lines("", other, &mut out),
+    }
+    out
+}
+
+/// Lines for the value. Labels of nested values start with the labels of
+/// their parents, e.g. `server version: 1.50.3`, or their position in lists.
+/// Missing values are left out.
+fn plain_lines(label: &str, value: &Value, out: &mut String) {
+    let nested = |name: &str| match label {
+        "" => name.replace('_', " "),
+        _ => format!("{} {}", label, name.replace('_', " ")),
+    };
+    let line = |text: &str, out: &mut String| match label {
+        "" => out.push_str(&format!("{}\n", text)),
+        _ => out.push_str(&format!("{}: {}\n", label, text)),
+    };
+
+    match value {
+        Value::Null => {}
+        Value::Object(object) => {
+            for (key, value) in object {
+                plain_lines(&nested(key), value, out);
+            }
+        }
+        Value::Array(items) if items.iter().any(|item| item.is_object() || item.is_array()) => {
+            for (index, item) in items.iter().enumerate() {
+                plain_lines(&nested(&(index + 1).to_string()), item, out);
+            }
+        }
+        Value::Array(items) if items.is_empty() => line("none", out),
+        Value::Array(items) => {
+            let texts = items.iter().map(plain_text).collect::<Vec<_>>();
+            line(&texts.join(", "), out);
+        }
+        other => line(&plain_text(other), out),
+    }
+}
+
+fn plain_text(value: &Value) -> String {
+    match value {
+        Value::Bool(true) => String::from("yes"),
+        Value::Bool(false) => String::from("no"),
+        other => cell(other),
+    }
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+    use serde_json::json;
+
+    use super::{check, to_csv, to_plain};
+    use crate::args::PPCArgs;
+
+    #[test]
+    fn csv_is_derived_from_json() {
+        let list = json!([
+            {"kind": "text", "views": 2, "note": null, "tags": ["a", "b"]},
+            {"kind": "file", "views": 0, "note": "x,y"},
+        ]);
+        assert_eq!(
+            to_csv(&list).unwrap(),
+            "kind,views,note,tags\ntext,2,,\"[\"\"a\"\",\"\"b\"\"]\"\nfile,0,\"x,y\",\n"
+        );
+        assert_eq!(
+            to_csv(&json!({"config": "/etc/pwpush"})).unwrap(),
+            "config\n/etc/pwpush\n"
+        );
+        assert_eq!(to_csv(&json!([])).unwrap(), "");
+    }
+
+    #[test]
+    fn plain_is_labelled_lines() {
+        let list = json!([
+            {"url_token": "abc", "expired": false, "note": null, "tags": ["a", "b"]},
+            {"url_token": "def", "expired": true, "server": {"version": "1.50.3"}, "tags": []},
+        ]);
+        assert_eq!(
+            to_plain(&list),
+            "url token: abc\nexpired: no\ntags: a, b\n\n\
+             url token: def\nexpired: yes\nserver version: 1.50.3\ntags: none\n"
+        );
+        assert_eq!(
+            to_plain(&json!({"views": [{"ip": "10.0.0.1"}]})),
+            "views 1 ip: 10.0.0.1\n"
+        );
+
+        let args = PPCArgs::parse_from(["pwpush-cli", "--plain", "summary"]);
+        assert!(super::is_plain(&args));
+        assert!(PPCArgs::try_parse_from(["pwpush-cli", "--plain", "--json", "summary"]).is_err());
+    }
+
+    #[test]
+    fn unsupported_formats_fail_early() {
+        let args = |cmdline: &[&str]| PPCArgs::parse_from([&["pwpush-cli"], cmdline].concat());
+
+        assert!(check(&args(&["--output", "csv", "summary"])).is_ok());
+        assert!(check(&args(&["--output", "quiet", "push", "text", "--stdin"])).is_ok());
+        assert!(check(&args(&["--output", "json", "history", "list"])).is_ok());
+        assert!(check(&args(&["--output", "csv", "history", "list"])).is_err());
+    }
+}
@@ -0,0 +1,92 @@
+//! Presentation of errors that end the CLI.
+//!
+//! People see the message of the error, and below it a hint how to fix the
+//! cause, if one is known, e.g. for rejected credentials or a mistyped
+//! instance URL:
+//!
+//! ```text
+//! error: could not find the host pwpush.exmaple.com
+//!   hint: check the instance URL given with --url or in the profile for typos
+//! ```
+//!
+//! On terminals, both are colored. Colors are left out when stderr is
+//! redirected, when `NO_COLOR` is set, or with `--plain`.
+//!
+//! With `--request-id`, the trace ID of the run follows, to look up the
+//! requests in the logs of the instance, see `trace`.
+//!
+//! With `--log-file` or `--log-format json`, the error is logged instead, as
+//! in every other log line, with the hint as `hint` field of JSON lines.
+
+use std::env;
+use std::io::{self, IsTerminal, Write};
+
+use tracing::error;
+
+use crate::args::{LogFormat, PPCArgs};
+use crate::errors::PPCError;
+use crate::render;
+use crate::trace;
+
+/// Show the error on stderr, or log it, see the module docs.
+pub fn print(args: &PPCArgs, error: &PPCError) {
+    if args.log_file.is_some() || matches!(args.log_format, LogFormat::Json) {
+        error!(
+            hint = error.hint().unwrap_or_default(),
+            "task could not complete successfully: {}", error
+        );
+        return;
+    }
+
+    let colors =
+        io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none() && !render::is_plain(args);
+    // there is nothing sensible to do if stderr is gone.
+    let _ = io::stderr()
+        .lock()
+        .write_all(format(error, args.request_id.then(trace::id), colors).as_bytes());
+}
+
+/// The error, its hint and the trace ID as lines.
+fn format(error: &PPCError, trace_id: Option<&str>, colors: bool) -> String {
+    let paint = |code: &str, text: &str| {
+        if colors {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            String::from(text)
+        }
+    };
+
+    let mut out = format!("{} {}\n", paint("1;31", "error:"), error);
+    if let Some(hint) = error.hint() {
+        out += &format!("  {} {}\n", paint("1;36", "hint:"), hint);
+    }
+    if let Some(trace_id) = trace_id {
+        out += &format!("  {} {}\n", paint("2", "trace:"), trace_id);
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::format;
+    use crate::errors::PPCError;
+
+    #[test]
+    fn errors_are_shown_with_hints() {
+        let error = PPCError::from("invalid credentials")
+            .with_hint(String::from("did you forget --email and --token?"));
+
+        assert_eq!(
+            format(&error, None, false),
+            "error: invalid credentials\n  hint: did you forget --email and --token?\n"
+        );
+        assert_eq!(
+            format(&PPCError::from("no payload"), None, true),
+            "\x1b[1;31merror:\x1b[0m no payload\n"
+        );
+        assert_eq!(
+            format(&PPCError::from("no payload"), Some("3f9a0c1e"), false),
+            "error: no payload\n  trace: 3f9a0c1e\n"
+        );
+    }
+}
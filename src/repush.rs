@@ -0,0 +1,562 @@
+//! Re-creating existing pushes, possibly on a different instance.
+//!
+//! The API has no way to move or duplicate a push. Instead, the payload is
+//! retrieved (which counts as a view of the original push), and a new push is
+//! created with the same note and viewer settings. The remaining days and views
+//! of the original push are used as new limits, so the copy never lives longer
+//! than the original.
+//!
+//! `rotate` does the same on the original instance, and expires the original
+//! afterwards. The old link stops working, and only the new link has to be
+//! shared again. The original is only expired once the new push exists, so
+//! the payload is never lost.
+//!
+//! `clone` creates a new push next to the original, with the limits the
+//! original was created with and optional overrides, e.g. to share a secret
+//! again whose link expired. The payload of an expired push cannot be
+//! retrieved anymore, it is then read with `--stdin` or `--prompt` instead.
+
+use secrecy::{ExposeSecret, SecretString};
+use serde_json::json;
+use tracing::info;
+
+use crate::args::{PPCArgs, PPCClone, PPCCopy, PPCRotate, PPCText, PushKind};
+use crate::config::PPCConfig;
+use crate::dedupe;
+use crate::errors::PPCError;
+use crate::http::Transport;
+use crate::instance::Instance;
+use crate::models::Push;
+use crate::policy::Policy;
+use crate::pwpush_api::{self, Retrieval};
+use crate::{history, i18n, output, payload, prompt, share};
+
+/// The push options to re-create a push that includes its payload, or the
+/// reason why it cannot be re-created.
+pub fn options_from(push: &Push) -> Result<PPCText, &'static str> {
+    if push.payload.is_none() {
+        return Err("push has no payload");
+    }
+
+    // the API reports the remaining values as signed numbers, and anything
+    // below one would be rejected by the instance anyways.
+    let remaining = |value: Option<i64>| value.filter(|v| *v > 0).map(|v| v as usize);
+
+    let mut ppc_text = settings_from(push);
+    ppc_text.expire_after_days = remaining(push.days_remaining);
+    ppc_text.expire_after_views = remaining(push.views_remaining);
+    Ok(ppc_text)
+}
+
+/// The push options to create a push like the given one, with the limits it
+/// was created with. The payload is taken over if the push includes it.
+fn settings_from(push: &Push) -> PPCText {
+    let limit = |value: Option<u64>| value.filter(|v| *v > 0).map(|v| v as usize);
+
+    PPCText {
+        password_payload: push.payload.clone().map(SecretString::from),
+        payload_file: None,
+        stdin: false,
+        prompt: false,
+        from_op: None,
+        from_bw: None,
+        from_vault: None,
+        from_env: Vec::new(),
+        base64: false,
+        normalize_newlines: false,
+        encrypt_for: None,
+        no_warn: false,
+        passphrase: None,
+        qr: false,
+        qr_link: false,
+        note: push.note.clone(),
+        expire_after_days: limit(push.expire_after_days),
+        expire_after_views: limit(push.expire_after_views),
+        deletable_by_viewer: push.deletable_by_viewer,
+        retrieval_step: push.retrieval_step,
+        no_defaults: true,
+        preset: None,
+        queue_on_failure: false,
+        paranoid: false,
+        enforce_strong_passphrase: false,
+        hold: false,
+        hold_for: None,
+        ephemeral: false,
+        expire_at: None,
+        expire_after: None,
+        exact: false,
+        to_profiles: Vec::new(),
+        recipients: Vec::new(),
+        notify_webhook: None,
+        email_to: None,
+        shorten: false,
+        validate: false,
+        tags: Vec::new(),
+        expire_after_first_view_confirmed: false,
+        dedupe: None,
+        dedupe_window: dedupe::DEFAULT_WINDOW,
+        idempotency_key: None,
+    }
+}
+
+/// Entry point for the `copy` command. The push is read from the instance
+/// selected by the global options, and created on the instance of the target
+/// profile.
+pub fn copy(
+    transport: &dyn Transport,
+    source: &Instance,
+    args: &PPCArgs,
+    config: &PPCConfig,
+    ppc_copy: &PPCCopy,
+) -> Result<(), PPCError> {
+    let mut target = Instance::from_profile(config, &ppc_copy.to_profile)?;
+    target.detect_auth_scheme(transport);
+    let push = copy_push(
+        transport,
+        source,
+        &target,
+        &ppc_copy.url_token,
+        &config.policy,
+    )?;
+
+    if args.json_output {
+        let output = json!({
+            "source": ppc_copy.url_token,
+            "profile": ppc_copy.to_profile,
+            "url_token": push.url_token,
+        });
+        output::print_json_line(&output);
+    } else {
+        println!(
+            "{}",
+            i18n::format(
+                "copy-done",
+                &[
+                    ("url_token", ppc_copy.url_token.as_str().into()),
+                    ("copy", push.url_token.as_str().into()),
+                    ("url", target.url.as_str().into())
+                ]
+            )
+        );
+    }
+
+    Ok(())
+}
+
+fn copy_push(
+    transport: &dyn Transport,
+    source: &Instance,
+    target: &Instance,
+    url_token: &str,
+    policy: &Policy,
+) -> Result<Push, PPCError> {
+    let original = pwpush_api::retrieve(transport, source, PushKind::Text, url_token)?;
+    if original.expired == Some(true) {
+        return Err(PPCError::from(format!("push {} is expired", url_token)));
+    }
+
+    let mut ppc_text = options_from(&original)
+        .map_err(|reason| PPCError::from(format!("cannot copy {}: {}", url_token, reason)))?;
+    policy.enforce(&mut ppc_text)?;
+
+    let push = pwpush_api::create_text_push(transport, target, &ppc_text)?;
+    info!(
+        "copied push {} from {} to {} on {}",
+        url_token, source.url, push.url_token, target.url
+    );
+    Ok(push)
+}
+
+/// Entry point for the `rotate` command.
+pub fn rotate(
+    transport: &dyn Transport,
+    instance: &Instance,
+    args: &PPCArgs,
+    config: &PPCConfig,
+    ppc_rotate: &PPCRotate,
+) -> Result<(), PPCError> {
+    let question = i18n::format(
+        "rotate-question",
+        &[
+            ("url_token", ppc_rotate.url_token.as_str().into()),
+            ("url", instance.url.as_str().into()),
+        ],
+    );
+    if !prompt::confirm(&question, args.assume_yes)? {
+        eprintln!("{}", i18n::text("rotate-aborted"));
+        return Ok(());
+    }
+
+    let push = rotate_push(
+        transport,
+        instance,
+        &ppc_rotate.url_token,
+        ppc_rotate.passphrase.as_ref(),
+        &config.policy,
+    )?;
+    let url = share::url(transport, instance, PushKind::Text, &push);
+
+    if args.json_output {
+        let output = json!({
+            "source": ppc_rotate.url_token,
+            "url_token": push.url_token,
+            "url": url,
+        });
+        output::print_json_line(&output);
+    } else {
+        println!(
+            "{}",
+            i18n::format(
+                "rotate-done",
+                &[
+                    ("url_token", ppc_rotate.url_token.as_str().into()),
+                    ("url", url.as_str().into())
+                ]
+            )
+        );
+    }
+
+    Ok(())
+}
+
+fn rotate_push(
+    transport: &dyn Transport,
+    instance: &Instance,
+    url_token: &str,
+    passphrase: Option<&SecretString>,
+    policy: &Policy,
+) -> Result<Push, PPCError> {
+    let original = retrieve(transport, instance, PushKind::Text, url_token, passphrase)?;
+    if original.expired == Some(true) {
+        return Err(PPCError::from(format!("push {} is expired", url_token)));
+    }
+
+    let mut ppc_text = options_from(&original)
+        .map_err(|reason| PPCError::from(format!("cannot rotate {}: {}", url_token, reason)))?;
+    ppc_text.passphrase = passphrase.cloned();
+    policy.enforce(&mut ppc_text)?;
+
+    let push = pwpush_api::create_text_push(transport, instance, &ppc_text)?;
+    pwpush_api::expire_push(transport, instance, PushKind::Text, url_token).map_err(|e| {
+        PPCError::from(format!(
+            "created new push {}, but could not expire {}: {}",
+            push.url_token, url_token, e
+        ))
+    })?;
+
+    info!("rotated push {} to {}", url_token, push.url_token);
+    Ok(push)
+}
+
+/// Entry point for the `clone` command.
+pub fn clone(
+    transport: &dyn Transport,
+    instance: &Instance,
+    args: &PPCArgs,
+    config: &PPCConfig,
+    ppc_clone: &PPCClone,
+) -> Result<(), PPCError> {
+    let ppc_text = clone_options(transport, instance, config, ppc_clone)?;
+    let push = pwpush_api::create_text_push(transport, instance, &ppc_text)?;
+    info!("cloned push {} to {}", ppc_clone.url_token, push.url_token);
+
+    let url = share::print(
+        transport,
+        instance,
+        args,
+        PushKind::Text,
+        &push,
+        None,
+        false,
+    )?;
+    history::record(
+        config,
+        instance,
+        &push,
+        ppc_text.note.as_deref(),
+        &ppc_text.tags,
+        &url,
+    );
+    Ok(())
+}
+
+/// The options of the new push: the settings of the original, the overrides
+/// from the command line, and the payload of the original unless it is read
+/// from stdin or a prompt.
+fn clone_options(
+    transport: &dyn Transport,
+    instance: &Instance,
+    config: &PPCConfig,
+    ppc_clone: &PPCClone,
+) -> Result<PPCText, PPCError> {
+    let url_token = &ppc_clone.url_token;
+    let original = retrieve(
+        transport,
+        instance,
+        PushKind::Text,
+        url_token,
+        ppc_clone.passphrase.as_ref(),
+    )?;
+
+    let mut ppc_text = settings_from(&original);
+    if original.payload.is_none() && !ppc_clone.stdin && !ppc_clone.prompt {
+        let reason = if original.expired == Some(true) {
+            "is expired, so its payload cannot be retrieved anymore"
+        } else {
+            "has no payload"
+        };
+        return Err(PPCError::from(format!(
+            "push {} {}. Give the payload with --stdin or --prompt",
+            url_token, reason
+        )));
+    }
+
+    ppc_text.stdin = ppc_clone.stdin;
+    ppc_text.prompt = ppc_clone.prompt;
+    // the payload of the original never was on the command line.
+    ppc_text.no_warn = true;
+    ppc_text.passphrase = ppc_clone.passphrase.clone();
+    ppc_text.note = ppc_clone.note.clone().or(ppc_text.note);
+    ppc_text.expire_after_days = ppc_clone.expire_after_days.or(ppc_text.expire_after_days);
+    ppc_text.expire_after_views = ppc_clone.expire_after_views.or(ppc_text.expire_after_views);
+    ppc_text.deletable_by_viewer = ppc_clone
+        .deletable_by_viewer
+        .or(ppc_text.deletable_by_viewer);
+    ppc_text.retrieval_step = ppc_clone.retrieval_step.or(ppc_text.retrieval_step);
+    config.policy.enforce(&mut ppc_text)?;
+
+    ppc_text.password_payload = Some(payload::resolve(&ppc_text, config)?);
+    Ok(ppc_text)
+}
+
+/// Retrieve a push, with the passphrase if one was given.
+pub fn retrieve(
+    transport: &dyn Transport,
+    instance: &Instance,
+    kind: PushKind,
+    url_token: &str,
+    passphrase: Option<&SecretString>,
+) -> Result<Push, PPCError> {
+    let exposed = passphrase.map(|p| p.expose_secret());
+    match pwpush_api::retrieve_with_passphrase(transport, instance, kind, url_token, exposed)? {
+        Retrieval::Push(push) => Ok(*push),
+        Retrieval::PassphraseRequired if passphrase.is_some() => Err(PPCError::from(format!(
+            "incorrect passphrase for push {}",
+            url_token
+        ))),
+        Retrieval::PassphraseRequired => Err(PPCError::from(format!(
+            "push {} is protected by a passphrase, use --passphrase",
+            url_token
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use secrecy::ExposeSecret;
+
+    use secrecy::SecretString;
+
+    use super::{clone_options, copy_push, options_from, rotate_push};
+    use crate::args::{InstanceProtocol, PPCClone};
+    use crate::config::PPCConfig;
+    use crate::http::mock::MockTransport;
+    use crate::http::Method;
+    use crate::instance::Instance;
+    use crate::models::Push;
+    use crate::policy::Policy;
+
+    fn instance(url: &str) -> Instance {
+        Instance {
+            protocol: InstanceProtocol::Https,
+            url: String::from(url),
+            email: None,
+            token: None,
+            auth_scheme: None,
+        }
+    }
+
+    #[test]
+    fn options_from_uses_remaining_limits() {
+        let push = Push {
+            payload: Some(String::from("secret")),
+            days_remaining: Some(2),
+            views_remaining: Some(0),
+            retrieval_step: Some(true),
+            ..Push::default()
+        };
+
+        let ppc_text = options_from(&push).unwrap();
+
+        assert_eq!(
+            ppc_text
+                .password_payload
+                .as_ref()
+                .map(|p| p.expose_secret()),
+            Some("secret")
+        );
+        assert_eq!(ppc_text.expire_after_days, Some(2));
+        assert_eq!(ppc_text.expire_after_views, None);
+        assert_eq!(ppc_text.retrieval_step, Some(true));
+        assert!(options_from(&Push::default()).is_err());
+    }
+
+    #[test]
+    fn copy_retrieves_and_pushes() {
+        let transport = MockTransport::new()
+            .respond(
+                200,
+                r#"{"url_token":"abc","payload":"secret","note":"db","days_remaining":3}"#,
+            )
+            .respond(201, r#"{"url_token":"new"}"#);
+
+        let push = copy_push(
+            &transport,
+            &instance("pwpush.com"),
+            &instance("pwpush.example.com"),
+            "abc",
+            &Policy::default(),
+        )
+        .unwrap();
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].url, "https://pwpush.com/p/abc.json");
+        assert_eq!(requests[1].url, "https://pwpush.example.com/p.json");
+        assert_eq!(
+            requests[1].body.as_deref(),
+            Some("password[payload]=secret&password[note]=db&password[expire_after_days]=3")
+        );
+        assert_eq!(push.url_token, "new");
+    }
+
+    #[test]
+    fn copy_refuses_expired_push() {
+        let transport = MockTransport::new().respond(200, r#"{"url_token":"abc","expired":true}"#);
+
+        assert!(copy_push(
+            &transport,
+            &instance("pwpush.com"),
+            &instance("pwpush.example.com"),
+            "abc",
+            &Policy::default()
+        )
+        .is_err());
+        assert_eq!(transport.requests().len(), 1);
+    }
+
+    #[test]
+    fn rotate_creates_new_push_and_expires_original() {
+        let transport = MockTransport::new()
+            .respond(200, r#"{"url_token":"abc","payload":"secret"}"#)
+            .respond(201, r#"{"url_token":"new"}"#)
+            .respond(200, r#"{"expired":true}"#);
+        let passphrase = SecretString::from("open");
+
+        let push = rotate_push(
+            &transport,
+            &instance("pwpush.com"),
+            "abc",
+            Some(&passphrase),
+            &Policy::default(),
+        )
+        .unwrap();
+
+        let requests = transport.requests();
+        assert_eq!(
+            requests[0].url,
+            "https://pwpush.com/p/abc.json?passphrase=open"
+        );
+        assert_eq!(
+            requests[1].body.as_deref(),
+            Some("password[payload]=secret&password[passphrase]=open")
+        );
+        assert_eq!(requests[2].method, Method::Delete);
+        assert_eq!(requests[2].url, "https://pwpush.com/p/abc.json");
+        assert_eq!(push.url_token, "new");
+    }
+
+    #[test]
+    fn rotate_reports_new_push_if_expiring_fails() {
+        let transport = MockTransport::new()
+            .respond(200, r#"{"url_token":"abc","payload":"secret"}"#)
+            .respond(201, r#"{"url_token":"new"}"#)
+            .respond(500, "{}");
+
+        let err = rotate_push(
+            &transport,
+            &instance("pwpush.com"),
+            "abc",
+            None,
+            &Policy::default(),
+        )
+        .unwrap_err();
+
+        assert!(err.to_string().contains("created new push new"));
+    }
+
+    fn ppc_clone() -> PPCClone {
+        PPCClone {
+            url_token: String::from("abc"),
+            passphrase: None,
+            stdin: false,
+            prompt: false,
+            note: None,
+            expire_after_days: None,
+            expire_after_views: None,
+            deletable_by_viewer: None,
+            retrieval_step: None,
+        }
+    }
+
+    #[test]
+    fn clone_uses_original_settings_and_overrides() {
+        let transport = MockTransport::new().respond(
+            200,
+            r#"{"url_token":"abc","payload":"secret","note":"db","expire_after_days":7,"days_remaining":2,"expire_after_views":5,"retrieval_step":true}"#,
+        );
+        let ppc_clone = PPCClone {
+            expire_after_days: Some(1),
+            ..ppc_clone()
+        };
+
+        let ppc_text = clone_options(
+            &transport,
+            &instance("pwpush.com"),
+            &PPCConfig::default(),
+            &ppc_clone,
+        )
+        .unwrap();
+
+        assert_eq!(
+            ppc_text
+                .password_payload
+                .as_ref()
+                .map(|p| p.expose_secret()),
+            Some("secret")
+        );
+        assert_eq!(ppc_text.note.as_deref(), Some("db"));
+        assert_eq!(ppc_text.expire_after_days, Some(1));
+        assert_eq!(ppc_text.expire_after_views, Some(5));
+        assert_eq!(ppc_text.retrieval_step, Some(true));
+        assert!(ppc_text.no_defaults);
+    }
+
+    #[test]
+    fn clone_of_expired_push_needs_payload() {
+        let transport = MockTransport::new().respond(
+            200,
+            r#"{"url_token":"abc","expired":true,"expire_after_days":7}"#,
+        );
+
+        let err = clone_options(
+            &transport,
+            &instance("pwpush.com"),
+            &PPCConfig::default(),
+            &ppc_clone(),
+        )
+        .unwrap_err();
+
+        assert!(err.to_string().contains("is expired"));
+        assert!(err.to_string().contains("--stdin"));
+    }
+}
@@ -0,0 +1,251 @@
+//! Polling in intervals, for everything that waits for the instance to change.
+//!
+//! The API has no events, so `audit --watch`, `list --watch`,
+//! `--expire-after-first-view-confirmed` and the watchlist of `serve` ask the
+//! instance again and again. The `Scheduler` decides when:
+//!   - intervals are jittered by up to 10% either way, so watchers that were
+//!     started together, e.g. by cron, do not hit the instance in lockstep
+//!   - failed polls are most likely temporary network issues, so they are
+//!     retried, but every failure in a row doubles the wait, up to five
+//!     minutes or the interval if that is longer. The first successful poll
+//!     restores the interval
+//!   - waiting ends early once a signal was received, see `signals`, and
+//!     polling stops, so commands that listen for signals can clean up
+//!
+//! Waiting goes through a `Clock`, which tests replace, so the schedule can be
+//! checked without actually waiting.
+
+use std::ops::ControlFlow;
+use std::thread;
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use tracing::{debug, warn};
+
+use crate::errors::PPCError;
+use crate::i18n;
+use crate::signals;
+
+/// Largest share of the interval that is added or taken away.
+const JITTER: f64 = 0.1;
+
+/// Longest wait after failed polls, unless the interval is longer.
+const MAX_BACKOFF: Duration = Duration::from_secs(5 * 60);
+
+/// How often a waiting scheduler checks for signals.
+const SIGNAL_CHECK: Duration = Duration::from_millis(200);
+
+/// Waiting, replaced in tests.
+pub trait Clock {
+    /// Wait for the duration. False if waiting was cancelled, and polling
+    /// should stop.
+    fn sleep(&self, duration: Duration) -> bool;
+}
+
+/// The actual time, cancelled by signals.
+struct SystemClock;
+
+impl Clock for SystemClock {
+    fn sleep(&self, duration: Duration) -> bool {
+        let mut left = duration;
+        while !left.is_zero() {
+            if signals::interrupted() {
+                return false;
+            }
+            let step = left.min(SIGNAL_CHECK);
+            thread::sleep(step);
+            left -= step;
+        }
+        !signals::interrupted()
+    }
+}
+
+/// Calls a poll function in intervals, see the module docs.
+pub struct Scheduler {
+    /// ID of the message that names what is polled, for the warnings about
+    /// failed polls.
+    what: &'static str,
+    interval: Duration,
+    failures: u32,
+    clock: Box<dyn Clock>,
+    rng: StdRng,
+}
+
+impl Scheduler {
+    pub fn new(what: &'static str, interval: Duration) -> Self {
+        Self {
+            what,
+            interval,
+            failures: 0,
+            clock: Box::new(SystemClock),
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    /// Replace the clock and the randomness, so tests are deterministic.
+    #[cfg(test)]
+    fn with_clock(mut self, clock: impl Clock + 'static, seed: u64) -> Self {
+        self.clock = Box::new(clock);
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    /// Wait, then poll, until the poll function breaks with a value or the
+    /// wait is cancelled, which returns `None`. Failed polls are logged as
+    /// warnings and retried.
+    pub fn run<T>(
+        &mut self,
+        mut poll: impl FnMut() -> Result<ControlFlow<T>, PPCError>,
+    ) -> Option<T> {
+        loop {
+            let wait = self.next_wait();
+            debug!("polling {} in {:?}", self.what, wait);
+            if !self.clock.sleep(wait) {
+                debug!("polling {} was cancelled", self.what);
+                return None;
+            }
+
+            match poll() {
+                Ok(ControlFlow::Break(value)) => return Some(value),
+                Ok(ControlFlow::Continue(())) => self.failures = 0,
+                Err(e) => {
+                    self.failures = self.failures.saturating_add(1);
+                    warn!(
+                        "{}",
+                        i18n::format(
+                            "poll-failed",
+                            &[
+                                ("what", i18n::text(self.what).into()),
+                                ("error", e.to_string().into())
+                            ]
+                        )
+                    );
+                }
+            }
+        }
+    }
+
+    /// The interval, backed off after failures, with jitter.
+    fn next_wait(&mut self) -> Duration {
+        let base = if self.failures == 0 {
+            self.interval
+        } else {
+            let limit = self.interval.max(MAX_BACKOFF);
+            // 2^failures, without overflowing after many failures.
+            let factor = 1u32 << self.failures.min(16);
+            self.interval.saturating_mul(factor).min(limit)
+        };
+        base.mul_f64(1.0 + self.rng.gen_range(-JITTER..=JITTER))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::ops::ControlFlow;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    use super::{Clock, Scheduler, MAX_BACKOFF};
+    use crate::errors::PPCError;
+
+    /// Records the waits, and cancels after the given number of them.
+    struct MockClock {
+        sleeps: Rc<RefCell<Vec<Duration>>>,
+        cancel_after: usize,
+    }
+
+    impl Clock for MockClock {
+        fn sleep(&self, duration: Duration) -> bool {
+            let mut sleeps = self.sleeps.borrow_mut();
+            sleeps.push(duration);
+            sleeps.len() <= self.cancel_after
+        }
+    }
+
+    fn scheduler(
+        interval: Duration,
+        cancel_after: usize,
+    ) -> (Scheduler, Rc<RefCell<Vec<Duration>>>) {
+        let sleeps = Rc::new(RefCell::new(Vec::new()));
+        let clock = MockClock {
+            sleeps: Rc::clone(&sleeps),
+            cancel_after,
+        };
+        let scheduler = Scheduler::new("test", interval).with_clock(clock, 7);
+        (scheduler, sleeps)
+    }
+
+    fn within_jitter(wait: Duration, base: Duration) -> bool {
+        wait >= base.mul_f64(0.9) && wait <= base.mul_f64(1.1)
+    }
+
+    #[test]
+    fn polls_in_jittered_intervals_until_done() {
+        let interval = Duration::from_secs(30);
+        let (mut scheduler, sleeps) = scheduler(interval, usize::MAX);
+        let mut polls = 0;
+
+        let result = scheduler.run(|| {
+            polls += 1;
+            Ok(if polls == 20 {
+                ControlFlow::Break(polls)
+            } else {
+                ControlFlow::Continue(())
+            })
+        });
+
+        assert_eq!(result, Some(20));
+        let sleeps = sleeps.borrow();
+        assert_eq!(sleeps.len(), 20);
+        assert!(sleeps.iter().all(|wait| within_jitter(*wait, interval)));
+        // jitter actually varies the waits.
+        assert!(sleeps.iter().any(|wait| *wait != sleeps[0]));
+    }
+
+    #[test]
+    fn failures_back_off_up_to_the_maximum() {
+        let interval = Duration::from_secs(30);
+        let (mut scheduler, sleeps) = scheduler(interval, 10);
+        let mut polls = 0;
+
+        let result = scheduler.run(|| -> Result<ControlFlow<()>, PPCError> {
+            polls += 1;
+            match polls {
+                1..=7 => Err(PPCError::from("connection refused")),
+                _ => Ok(ControlFlow::Continue(())),
+            }
+        });
+
+        assert_eq!(result, None);
+        let sleeps = sleeps.borrow();
+        let bases = [1, 2, 4, 8, 16, 32, 64, 128, 1, 1, 1]
+            .map(|factor| (interval * factor).min(MAX_BACKOFF));
+        assert_eq!(sleeps.len(), bases.len());
+        for (wait, base) in sleeps.iter().zip(bases) {
+            assert!(
+                within_jitter(*wait, base),
+                "{:?} is not around {:?}",
+                wait,
+                base
+            );
+        }
+    }
+
+    #[test]
+    fn long_intervals_are_not_cut_by_the_backoff_limit() {
+        let interval = Duration::from_secs(3600);
+        let (mut scheduler, sleeps) = scheduler(interval, 2);
+
+        let result = scheduler.run(|| -> Result<ControlFlow<()>, PPCError> {
+            Err(PPCError::from("connection refused"))
+        });
+
+        assert_eq!(result, None);
+        assert!(sleeps
+            .borrow()
+            .iter()
+            .all(|wait| within_jitter(*wait, interval)));
+    }
+}
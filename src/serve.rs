@@ -0,0 +1,847 @@
+//! Local HTTP/JSON API that proxies to the configured instance.
+//!
+//! `serve --listen 127.0.0.1:8089` keeps running and accepts requests from
+//! desktop tools and editors, which can then create pushes with the stored
+//! credentials without ever handling the token themselves:
+//!
+//! - `GET /health` shows the instance requests go to
+//! - `POST /push` creates a text push from a JSON object with `payload` and the
+//!   optional `passphrase`, `note`, `expire_after_days`, `expire_after_views`,
+//!   `deletable_by_viewer`, `retrieval_step` and `preset`. Defaults and presets
+//!   from the configuration file apply, just like for `push text`
+//! - `GET /pushes/active` and `GET /pushes/expired` list the text pushes of the
+//!   account
+//! - `DELETE /pushes/<url_token>` expires a text push
+//! - `GET /metrics` shows metrics for Prometheus, see `metrics`
+//! - `POST /watch` tracks a text push given as `{"url_token": "..."}`, and
+//!   `GET /watch` lists the tracked pushes. Only with `--event-webhook`, which
+//!   reports tracked pushes that ended, see `watchlist`
+//!
+//! Responses are JSON, except for the metrics, errors are reported as
+//! `{"error": "..."}`.
+//!
+//! Anybody who can reach the API can push with the account, so it only listens
+//! on loopback addresses. To keep web pages from using it through the browser,
+//! requests must be addressed to a loopback host, which defeats DNS rebinding,
+//! and pushes must be sent as `application/json`, which browsers do not allow
+//! across origins without a preflight request that is never answered.
+//!
+//! Requests are handled one after another, each on a new connection that is
+//! closed after the response. Tracked pushes are polled on a thread of their
+//! own, so slow webhooks do not hold up requests.
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::{debug, info, info_span, warn};
+use zeroize::Zeroizing;
+
+use crate::args::{PPCServe, PPCText, PushKind, PushState};
+use crate::config::PPCConfig;
+use crate::errors::PPCError;
+use crate::http::Transport;
+use crate::instance::Instance;
+use crate::metrics::{self, Metrics};
+use crate::payload::{self, DEFAULT_MAX_PAYLOAD_SIZE};
+use crate::watchlist::Watchlist;
+use crate::{dedupe, history, passphrase, platform, pwpush_api, share, template};
+
+/// Default address of `--listen`.
+pub const DEFAULT_LISTEN: &str = "127.0.0.1:8089";
+
+/// Maximum number of request headers that are parsed.
+const MAX_HEADERS: usize = 64;
+
+/// Largest request head that is accepted, the API has no use for big headers.
+const MAX_HEAD_SIZE: usize = 16 * 1024;
+
+/// Clients that do not send their request within this time are dropped, so a
+/// stuck client cannot block the API for everyone else.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Host names of loopback addresses that requests may be addressed to.
+const LOOPBACK_HOSTS: [&str; 3] = ["localhost", "127.0.0.1", "[::1]"];
+
+/// A request to the local API. The body may contain a payload, so it is wiped
+/// when the request is dropped.
+#[derive(Debug)]
+struct HttpRequest {
+    method: String,
+    path: String,
+    host: Option<String>,
+    content_type: Option<String>,
+    body: Zeroizing<Vec<u8>>,
+}
+
+/// An answer of the API. The body may contain a passphrase, so it is wiped
+/// when the response is dropped.
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: Zeroizing<String>,
+}
+
+impl Response {
+    fn json(status: u16, body: &Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: Zeroizing::new(body.to_string()),
+        }
+    }
+}
+
+/// Body of `POST /push`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PushRequest {
+    payload: SecretString,
+
+    #[serde(default)]
+    passphrase: Option<SecretString>,
+
+    #[serde(default)]
+    note: Option<String>,
+
+    #[serde(default)]
+    expire_after_days: Option<usize>,
+
+    #[serde(default)]
+    expire_after_views: Option<usize>,
+
+    #[serde(default)]
+    deletable_by_viewer: Option<bool>,
+
+    #[serde(default)]
+    retrieval_step: Option<bool>,
+
+    #[serde(default)]
+    preset: Option<String>,
+}
+
+/// Body of `POST /watch`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WatchRequest {
+    url_token: String,
+}
+
+/// Listen for requests until the process is terminated.
+pub fn run(
+    transport: &dyn Transport,
+    instance: &Instance,
+    config: &PPCConfig,
+    ppc_serve: &PPCServe,
+) -> Result<(), PPCError> {
+    check_listen(ppc_serve.listen)?;
+    let watchlist = if ppc_serve.event_webhooks.is_empty() {
+        None
+    } else {
+        Some(Watchlist::new(
+            instance,
+            ppc_serve.event_webhooks.clone(),
+            ppc_serve.watch_interval,
+        )?)
+    };
+    let webhooks = platform::transport()?;
+
+    let listener = TcpListener::bind(ppc_serve.listen)
+        .map_err(|e| PPCError::from(format!("could not listen on {}: {}", ppc_serve.listen, e)))?;
+    info!(
+        "serving the API for {} on http://{}",
+        instance.base_url(),
+        ppc_serve.listen
+    );
+    eprintln!("listening on http://{}", ppc_serve.listen);
+
+    thread::scope(|scope| {
+        if let Some(watchlist) = &watchlist {
+            scope.spawn(|| watchlist.run(transport, webhooks, instance));
+        }
+        accept(transport, instance, config, watchlist.as_ref(), &listener);
+    });
+    Ok(())
+}
+
+/// Answer connections one after another.
+fn accept(
+    transport: &dyn Transport,
+    instance: &Instance,
+    config: &PPCConfig,
+    watchlist: Option<&Watchlist>,
+    listener: &TcpListener,
+) {
+    let mut metrics = Metrics::new();
+
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!("could not accept connection: {}", e);
+                continue;
+            }
+        };
+        if let Err(e) = stream.set_read_timeout(Some(READ_TIMEOUT)) {
+            warn!("could not set timeout for connection: {}", e);
+            continue;
+        }
+
+        let response = match read_request(&mut stream, max_body_size(config)) {
+            Ok(request) => {
+                let _span = info_span!(
+                    "serve_request",
+                    method = %request.method,
+                    path = %request.path,
+                )
+                .entered();
+                let started = Instant::now();
+                let response = respond(transport, instance, config, watchlist, &metrics, &request);
+                metrics.observe(
+                    &request.method,
+                    &request.path,
+                    response.status,
+                    started.elapsed(),
+                );
+                info!("{} {} -> {}", request.method, request.path, response.status);
+                response
+            }
+            Err(e) => Response::json(400, &json!({ "error": e.to_string() })),
+        };
+        if let Err(e) = write_response(&mut stream, &response) {
+            warn!("could not send response: {}", e);
+        }
+    }
+}
+
+/// Only loopback addresses are allowed, as the API uses the credentials of the
+/// account without asking for any.
+fn check_listen(listen: SocketAddr) -> Result<(), PPCError> {
+    if listen.ip().is_loopback() {
+        Ok(())
+    } else {
+        Err(PPCError::from(format!(
+            "refusing to listen on {}, only loopback addresses like {} are allowed",
+            listen, DEFAULT_LISTEN
+        )))
+    }
+}
+
+/// The payload is sent as JSON string, where escaping may double its size.
+fn max_body_size(config: &PPCConfig) -> usize {
+    let payload = config
+        .push
+        .max_payload_size
+        .unwrap_or(DEFAULT_MAX_PAYLOAD_SIZE);
+    payload * 2 + MAX_HEAD_SIZE
+}
+
+fn read_request(stream: &mut impl Read, max_body: usize) -> Result<HttpRequest, PPCError> {
+    let mut raw = Zeroizing::new(Vec::new());
+    let mut chunk = [0u8; 4096];
+
+    let (mut request, head_length, content_length) = loop {
+        let read = stream
+            .read(&mut chunk)
+            .map_err(|e| PPCError::from(format!("could not read request: {}", e)))?;
+        if read == 0 {
+            return Err(PPCError::from(
+                "connection closed before the request was complete",
+            ));
+        }
+        raw.extend_from_slice(&chunk[..read]);
+
+        let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+        let mut parsed = httparse::Request::new(&mut headers);
+        match parsed.parse(&raw) {
+            Ok(httparse::Status::Complete(length)) => {
+                let header = |name: &str| {
+                    parsed
+                        .headers
+                        .iter()
+                        .find(|header| header.name.eq_ignore_ascii_case(name))
+                        .map(|header| String::from_utf8_lossy(header.value).trim().to_string())
+                };
+                let content_length = match header("Content-Length") {
+                    Some(value) => value
+                        .parse::<usize>()
+                        .map_err(|_| PPCError::from("invalid Content-Length header"))?,
+                    None => 0,
+                };
+                let request = HttpRequest {
+                    // the parser only completes with method and path.
+                    method: String::from(parsed.method.unwrap_or_default()),
+                    path: String::from(parsed.path.unwrap_or_default()),
+                    host: header("Host"),
+                    content_type: header("Content-Type"),
+                    body: Zeroizing::new(Vec::new()),
+                };
+                break (request, length, content_length);
+            }
+            Ok(httparse::Status::Partial) if raw.len() > MAX_HEAD_SIZE => {
+                return Err(PPCError::from("request head is too large"))
+            }
+            Ok(httparse::Status::Partial) => continue,
+            Err(e) => return Err(PPCError::from(format!("invalid request: {}", e))),
+        }
+    };
+
+    if content_length > max_body {
+        return Err(PPCError::from(format!(
+            "request body is too large, at most {} bytes are accepted",
+            max_body
+        )));
+    }
+    while raw.len() < head_length + content_length {
+        let read = stream
+            .read(&mut chunk)
+            .map_err(|e| PPCError::from(format!("could not read request: {}", e)))?;
+        if read == 0 {
+            return Err(PPCError::from(
+                "connection closed before the request was complete",
+            ));
+        }
+        raw.extend_from_slice(&chunk[..read]);
+    }
+    chunk.fill(0);
+
+    request
+        .body
+        .extend_from_slice(&raw[head_length..head_length + content_length]);
+    Ok(request)
+}
+
+/// Answer a request, with the metrics or from `handle`.
+fn respond(
+    transport: &dyn Transport,
+    instance: &Instance,
+    config: &PPCConfig,
+    watchlist: Option<&Watchlist>,
+    metrics: &Metrics,
+    request: &HttpRequest,
+) -> Response {
+    let path = request.path.split('?').next().unwrap_or_default();
+    if path == "/metrics" && request.method == "GET" && is_loopback_host(request.host.as_deref()) {
+        return Response {
+            status: 200,
+            content_type: metrics::CONTENT_TYPE,
+            body: Zeroizing::new(metrics.render()),
+        };
+    }
+    let (status, body) = handle(transport, instance, config, watchlist, request);
+    Response::json(status, &body)
+}
+
+/// Answer a request with a status code and a JSON body.
+fn handle(
+    transport: &dyn Transport,
+    instance: &Instance,
+    config: &PPCConfig,
+    watchlist: Option<&Watchlist>,
+    request: &HttpRequest,
+) -> (u16, Value) {
+    debug!("handling {} {}", request.method, request.path);
+
+    if !is_loopback_host(request.host.as_deref()) {
+        return error(403, "requests must be addressed to a loopback host");
+    }
+
+    let segments = request
+        .path
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+    let result = match (request.method.as_str(), segments.as_slice()) {
+        ("GET", ["health"]) => Ok((
+            200,
+            json!({
+                "status": "ok",
+                "instance": instance.base_url(),
+                "authenticated": instance.token.is_some(),
+            }),
+        )),
+        ("POST", ["push"]) => {
+            if !is_json(request) {
+                return error(415, "pushes must be sent as application/json");
+            }
+            return push(transport, instance, config, watchlist, &request.body);
+        }
+        ("GET", ["pushes", "active"]) => list(transport, instance, PushState::Active),
+        ("GET", ["pushes", "expired"]) => list(transport, instance, PushState::Expired),
+        ("DELETE", ["pushes", url_token]) => {
+            pwpush_api::expire_push(transport, instance, PushKind::Text, url_token)
+                .map(|()| (200, json!({ "url_token": url_token, "expired": true })))
+        }
+        (_, ["watch"]) => return watch(watchlist, request),
+        (_, ["health"] | ["metrics"] | ["push"] | ["pushes", _]) => {
+            return error(405, "method not allowed");
+        }
+        _ => return error(404, "not found"),
+    };
+
+    // the request was fine, so failures are on the side of the instance.
+    result.unwrap_or_else(|e| error(502, &e.to_string()))
+}
+
+/// Bodies must be JSON, see the module docs.
+fn is_json(request: &HttpRequest) -> bool {
+    request
+        .content_type
+        .as_deref()
+        .is_some_and(|value| value.starts_with("application/json"))
+}
+
+fn error(status: u16, message: &str) -> (u16, Value) {
+    (status, json!({ "error": message }))
+}
+
+/// True if the Host header names a loopback address, with or without port.
+fn is_loopback_host(host: Option<&str>) -> bool {
+    let Some(host) = host else {
+        return false;
+    };
+    let name = match host.rsplit_once(':') {
+        Some((name, port)) if !name.is_empty() && port.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => host,
+    };
+    LOOPBACK_HOSTS
+        .iter()
+        .any(|loopback| name.eq_ignore_ascii_case(loopback))
+}
+
+/// Create a text push, like `push text` with the options of the request.
+/// Invalid requests are rejected before anything is sent to the instance.
+fn push(
+    transport: &dyn Transport,
+    instance: &Instance,
+    config: &PPCConfig,
+    watchlist: Option<&Watchlist>,
+    body: &[u8],
+) -> (u16, Value) {
+    let merged = match serde_json::from_slice::<PushRequest>(body)
+        .map_err(|e| PPCError::from(format!("invalid push request: {}", e)))
+        .and_then(|request| push_options(config, request))
+    {
+        Ok(merged) => merged,
+        Err(e) => return error(400, &e.to_string()),
+    };
+
+    let push = match pwpush_api::create_text_push(transport, instance, &merged) {
+        Ok(push) => push,
+        Err(e) => return error(502, &e.to_string()),
+    };
+    let url = share::url(transport, instance, PushKind::Text, &push);
+    history::record(
+        config,
+        instance,
+        &push,
+        merged.note.as_deref(),
+        &merged.tags,
+        &url,
+    );
+    if let Some(watchlist) = watchlist {
+        watchlist.watch(&push.url_token);
+    }
+
+    // the push only consists of strings, numbers and bools, so serialization
+    // cannot fail.
+    let mut output = serde_json::to_value(&push).unwrap();
+    output["url"] = Value::from(url);
+    // the passphrase may have been generated from a preset, and nobody would
+    // be able to open the push without it.
+    if let Some(passphrase) = &merged.passphrase {
+        output["passphrase"] = Value::from(passphrase.expose_secret());
+    }
+    (201, output)
+}
+
+/// Track a push, or list the tracked pushes. Nothing is sent to the instance,
+/// the pushes are looked up by the watchlist.
+fn watch(watchlist: Option<&Watchlist>, request: &HttpRequest) -> (u16, Value) {
+    let Some(watchlist) = watchlist else {
+        return error(404, "not watching pushes, start serve with --event-webhook");
+    };
+    match request.method.as_str() {
+        "GET" => (200, json!({ "url_tokens": watchlist.tokens() })),
+        "POST" if !is_json(request) => {
+            error(415, "pushes to watch must be sent as application/json")
+        }
+        "POST" => match serde_json::from_slice::<WatchRequest>(&request.body) {
+            Ok(watch) => {
+                watchlist.watch(&watch.url_token);
+                (
+                    200,
+                    json!({ "url_token": watch.url_token, "watched": true }),
+                )
+            }
+            Err(e) => error(400, &format!("invalid watch request: {}", e)),
+        },
+        _ => error(405, "method not allowed"),
+    }
+}
+
+/// The effective options of the push, with defaults and presets applied.
+fn push_options(config: &PPCConfig, request: PushRequest) -> Result<PPCText, PPCError> {
+    let limit = config
+        .push
+        .max_payload_size
+        .unwrap_or(DEFAULT_MAX_PAYLOAD_SIZE);
+    payload::check_size(request.payload.expose_secret().len(), limit)?;
+    if let Some(given) = &request.passphrase {
+        passphrase::check(
+            given.expose_secret(),
+            config.push.warn_weak_passphrase.unwrap_or(true),
+            false,
+        )?;
+    }
+
+    let ppc_text = PPCText {
+        password_payload: Some(request.payload),
+        payload_file: None,
+        stdin: false,
+        prompt: false,
+        from_op: None,
+        from_bw: None,
+        from_vault: None,
+        from_env: Vec::new(),
+        base64: false,
+        normalize_newlines: false,
+        encrypt_for: None,
+        no_warn: true,
+        passphrase: request.passphrase,
+        qr: false,
+        qr_link: false,
+        note: request.note,
+        expire_after_days: request.expire_after_days,
+        expire_after_views: request.expire_after_views,
+        deletable_by_viewer: request.deletable_by_viewer,
+        retrieval_step: request.retrieval_step,
+        no_defaults: false,
+        preset: request.preset,
+        queue_on_failure: false,
+        paranoid: false,
+        enforce_strong_passphrase: false,
+        hold: false,
+        hold_for: None,
+        ephemeral: false,
+        expire_at: None,
+        expire_after: None,
+        exact: false,
+        to_profiles: Vec::new(),
+        recipients: Vec::new(),
+        notify_webhook: None,
+        email_to: None,
+        shorten: false,
+        validate: false,
+        tags: Vec::new(),
+        expire_after_first_view_confirmed: false,
+        dedupe: None,
+        dedupe_window: dedupe::DEFAULT_WINDOW,
+        idempotency_key: None,
+    };
+    let mut merged = config.apply(&ppc_text)?;
+    merged.note = merged.note.as_deref().map(template::expand);
+    Ok(merged)
+}
+
+fn list(
+    transport: &dyn Transport,
+    instance: &Instance,
+    state: PushState,
+) -> Result<(u16, Value), PPCError> {
+    let pushes = pwpush_api::list(transport, instance, PushKind::Text, state)?;
+    // see above, serialization cannot fail.
+    Ok((200, serde_json::to_value(pushes).unwrap()))
+}
+
+fn write_response(stream: &mut impl Write, response: &Response) -> std::io::Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        415 => "Unsupported Media Type",
+        _ => "Bad Gateway",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+         Cache-Control: no-store\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        reason,
+        response.content_type,
+        response.body.len(),
+        response.body.as_str()
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+    use std::net::SocketAddr;
+    use std::time::Duration;
+
+    use zeroize::Zeroizing;
+
+    use super::{
+        check_listen, handle, is_loopback_host, read_request, respond, write_response, HttpRequest,
+        Response,
+    };
+    use crate::args::InstanceProtocol;
+    use crate::config::PPCConfig;
+    use crate::http::mock::MockTransport;
+    use crate::http::Method;
+    use crate::instance::Instance;
+    use crate::metrics::Metrics;
+    use crate::watchlist::Watchlist;
+
+    fn instance() -> Instance {
+        Instance {
+            protocol: InstanceProtocol::Https,
+            url: String::from("pwpush.example.com"),
+            email: Some(String::from("user@example.com")),
+            token: Some("t0k3n".into()),
+            auth_scheme: None,
+        }
+    }
+
+    fn request(method: &str, path: &str, body: &str) -> HttpRequest {
+        HttpRequest {
+            method: String::from(method),
+            path: String::from(path),
+            host: Some(String::from("127.0.0.1:8089")),
+            content_type: Some(String::from("application/json")),
+            body: Zeroizing::new(body.as_bytes().to_vec()),
+        }
+    }
+
+    #[test]
+    fn only_loopback_addresses() {
+        assert!(check_listen("127.0.0.1:8089".parse::<SocketAddr>().unwrap()).is_ok());
+        assert!(check_listen("[::1]:8089".parse::<SocketAddr>().unwrap()).is_ok());
+        assert!(check_listen("0.0.0.0:8089".parse::<SocketAddr>().unwrap()).is_err());
+    }
+
+    #[test]
+    fn loopback_hosts() {
+        assert!(is_loopback_host(Some("localhost:8089")));
+        assert!(is_loopback_host(Some("127.0.0.1")));
+        assert!(is_loopback_host(Some("[::1]:8089")));
+        assert!(!is_loopback_host(Some("evil.example.com:8089")));
+        assert!(!is_loopback_host(None));
+    }
+
+    #[test]
+    fn reads_request_with_body() {
+        let mut stream = Cursor::new(
+            "POST /push HTTP/1.1\r\nHost: localhost:8089\r\nContent-Type: application/json\r\n\
+             Content-Length: 20\r\n\r\n{\"payload\":\"s3cret\"}",
+        );
+
+        let request = read_request(&mut stream, 1024).unwrap();
+
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/push");
+        assert_eq!(request.host.as_deref(), Some("localhost:8089"));
+        assert_eq!(request.content_type.as_deref(), Some("application/json"));
+        assert_eq!(request.body.as_slice(), b"{\"payload\":\"s3cret\"}");
+    }
+
+    #[test]
+    fn rejects_incomplete_and_large_requests() {
+        let mut incomplete = Cursor::new("POST /push HTTP/1.1\r\nContent-Length: 20\r\n\r\n{}");
+        assert!(read_request(&mut incomplete, 1024).is_err());
+
+        let mut large = Cursor::new("POST /push HTTP/1.1\r\nContent-Length: 2048\r\n\r\n");
+        assert!(read_request(&mut large, 1024).is_err());
+    }
+
+    #[test]
+    fn push_with_stored_credentials() {
+        let transport = MockTransport::new()
+            .respond(201, r#"{"url_token":"abc","expire_after_views":1}"#)
+            .respond(200, r#"{"url":"https://pwpush.example.com/en/p/abc"}"#);
+
+        let (status, body) = handle(
+            &transport,
+            &instance(),
+            &PPCConfig::default(),
+            None,
+            &request(
+                "POST",
+                "/push",
+                r#"{"payload":"s3cret","expire_after_views":1}"#,
+            ),
+        );
+
+        assert_eq!(status, 201);
+        assert_eq!(body["url"], "https://pwpush.example.com/en/p/abc");
+        assert_eq!(body["url_token"], "abc");
+        let requests = transport.requests();
+        assert_eq!(requests[0].method, Method::Post);
+        assert!(requests[0]
+            .headers
+            .contains(&(String::from("X-User-Token"), String::from("t0k3n"))));
+        assert!(requests[0].body.as_deref().unwrap().contains("s3cret"));
+    }
+
+    #[test]
+    fn push_requires_json() {
+        let transport = MockTransport::new();
+        let mut form = request("POST", "/push", "payload=s3cret");
+        form.content_type = Some(String::from("application/x-www-form-urlencoded"));
+
+        let (status, _) = handle(&transport, &instance(), &PPCConfig::default(), None, &form);
+
+        assert_eq!(status, 415);
+        assert!(transport.requests().is_empty());
+    }
+
+    #[test]
+    fn rejects_foreign_hosts_and_bad_requests() {
+        let transport = MockTransport::new();
+        let config = PPCConfig::default();
+        let mut rebound = request("GET", "/health", "");
+        rebound.host = Some(String::from("attacker.example.com"));
+
+        assert_eq!(
+            handle(&transport, &instance(), &config, None, &rebound).0,
+            403
+        );
+        assert_eq!(
+            handle(
+                &transport,
+                &instance(),
+                &config,
+                None,
+                &request("GET", "/nope", "")
+            )
+            .0,
+            404
+        );
+        assert_eq!(
+            handle(
+                &transport,
+                &instance(),
+                &config,
+                None,
+                &request("GET", "/push", "")
+            )
+            .0,
+            405
+        );
+        assert_eq!(
+            handle(
+                &transport,
+                &instance(),
+                &config,
+                None,
+                &request("POST", "/push", r#"{"note":"no payload"}"#)
+            )
+            .0,
+            400
+        );
+        assert!(transport.requests().is_empty());
+    }
+
+    #[test]
+    fn lists_and_expires_pushes() {
+        let transport = MockTransport::new()
+            .respond(200, r#"[{"url_token":"abc"}]"#)
+            .respond(200, r#"{"expired":true}"#);
+        let config = PPCConfig::default();
+
+        let (status, body) = handle(
+            &transport,
+            &instance(),
+            &config,
+            None,
+            &request("GET", "/pushes/active", ""),
+        );
+        assert_eq!(status, 200);
+        assert_eq!(body[0]["url_token"], "abc");
+
+        let (status, _) = handle(
+            &transport,
+            &instance(),
+            &config,
+            None,
+            &request("DELETE", "/pushes/abc", ""),
+        );
+        assert_eq!(status, 200);
+        assert_eq!(transport.requests()[1].method, Method::Delete);
+    }
+
+    #[test]
+    fn pushes_are_watched_with_event_webhooks() {
+        let transport = MockTransport::new()
+            .respond(201, r#"{"url_token":"abc"}"#)
+            .respond(200, r#"{"url":"https://pwpush.example.com/en/p/abc"}"#);
+        let config = PPCConfig::default();
+        let webhooks = vec![String::from("https://hooks.example.com/a")];
+        let watchlist = Watchlist::new(&instance(), webhooks, Duration::from_secs(60)).unwrap();
+
+        let push = request("POST", "/push", r#"{"payload":"s3cret"}"#);
+        let (status, _) = handle(&transport, &instance(), &config, Some(&watchlist), &push);
+        assert_eq!(status, 201);
+        let watch = request("POST", "/watch", r#"{"url_token":"def"}"#);
+        let (status, _) = handle(&transport, &instance(), &config, Some(&watchlist), &watch);
+        assert_eq!(status, 200);
+
+        let list = request("GET", "/watch", "");
+        let (status, body) = handle(&transport, &instance(), &config, Some(&watchlist), &list);
+        assert_eq!(status, 200);
+        assert_eq!(body["url_tokens"], serde_json::json!(["abc", "def"]));
+        assert_eq!(handle(&transport, &instance(), &config, None, &list).0, 404);
+    }
+
+    #[test]
+    fn metrics_count_requests() {
+        let transport = MockTransport::new().respond(200, r#"{"expired":true}"#);
+        let config = PPCConfig::default();
+        let mut metrics = Metrics::new();
+
+        let expire = request("DELETE", "/pushes/abc", "");
+        let response = respond(&transport, &instance(), &config, None, &metrics, &expire);
+        metrics.observe("DELETE", "/pushes/abc", response.status, Duration::ZERO);
+
+        let response = respond(
+            &transport,
+            &instance(),
+            &config,
+            None,
+            &metrics,
+            &request("GET", "/metrics", ""),
+        );
+        assert_eq!(response.status, 200);
+        assert!(response.content_type.starts_with("text/plain"));
+        assert!(response.body.contains("pwpush_serve_expirations_total 1\n"));
+
+        let mut rebound = request("GET", "/metrics", "");
+        rebound.host = Some(String::from("attacker.example.com"));
+        let response = respond(&transport, &instance(), &config, None, &metrics, &rebound);
+        assert_eq!(response.status, 403);
+    }
+
+    #[test]
+    fn writes_json_response() {
+        let mut output = Vec::new();
+
+        let response = Response::json(404, &serde_json::json!({"error": "not found"}));
+        write_response(&mut output, &response).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        assert!(output.ends_with("\r\n\r\n{\"error\":\"not found\"}"));
+    }
+}
@@ -0,0 +1,517 @@
+//! Where the settings of a command come from, for `config resolve`.
+//!
+//! Settings are layered. For each of them, the first layer that sets it wins:
+//!   1. options on the command line, e.g. `--url` or `--expire-after-days`
+//!   2. environment variables, like `LANG` for the language of messages
+//!   3. the profile selected with `--profile`, see `instance`
+//!   4. `--paranoid` and the preset selected with `--preset`
+//!   5. the `[push]` defaults of the configuration file, unless
+//!      `--no-defaults` is given
+//!   6. built-in defaults, or the defaults of the instance for push options
+//!
+//! The policy file comes last and can still lower limits, see `policy`. With
+//! several layers, it is not obvious which value a push ends up with, so
+//! `config resolve push text --preset handoff` shows each setting with the
+//! layer it came from, without sending anything or reading the payload. Text
+//! pushes need one of their payload options, like `--stdin`.
+//!
+//! The push options are taken from `PPCConfig::apply`, the same code that a
+//! push runs, so the shown values are the ones the push would be made with.
+//! The layers only name the source. If the value differs from the one of its
+//! layer, the policy changed it. Tokens and passphrases are never shown, only
+//! whether they are set.
+
+use std::env;
+
+use clap::Parser;
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::args::{ConfigCommand, PPCAction, PPCArgs, PPCFile, PPCObject, PPCText};
+use crate::config::{PPCConfig, Profile};
+use crate::errors::PPCError;
+use crate::paths::CONFIG_DIR_VAR;
+use crate::render::CommandOutput;
+use crate::table::{Column, Table};
+use crate::{aliases, config, i18n, instance, paranoid};
+
+const DEFAULT: &str = "default";
+
+const INSTANCE_DEFAULT: &str = "instance default";
+
+const PUSH_DEFAULTS: &str = "[push] of the configuration file";
+
+/// A setting, its value, and the layer it came from. Settings without value
+/// are left to the instance.
+#[derive(Debug, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct Setting {
+    pub name: &'static str,
+    pub value: Option<String>,
+    pub source: String,
+}
+
+/// Result of `config resolve`.
+pub struct Settings(Vec<Setting>);
+
+impl CommandOutput for Settings {
+    fn json(&self) -> Value {
+        // settings only consist of strings.
+        serde_json::to_value(&self.0).unwrap()
+    }
+
+    fn human(&self, width: Option<usize>) -> String {
+        let mut table = Table::new(vec![
+            Column::fixed(),
+            Column::shrink_to(12),
+            Column::shrink_to(10),
+        ]);
+        for setting in &self.0 {
+            table.row(vec![
+                String::from(setting.name),
+                setting.value.clone().unwrap_or_else(|| String::from("-")),
+                setting.source.clone(),
+            ]);
+        }
+        table.render(width)
+    }
+}
+
+/// Entry point for `config`.
+pub fn run(
+    args: &PPCArgs,
+    config: &PPCConfig,
+    command: &ConfigCommand,
+) -> Result<Settings, PPCError> {
+    let ConfigCommand::Resolve { command } = command;
+    let command = if command.is_empty() {
+        vec![
+            String::from("push"),
+            String::from("text"),
+            String::from("--stdin"),
+        ]
+    } else {
+        command.clone()
+    };
+    let cmdline = aliases::expand(
+        ["pwpush-cli"]
+            .into_iter()
+            .map(String::from)
+            .chain(command)
+            .map(Into::into)
+            .collect(),
+    )?;
+    let resolved = PPCArgs::try_parse_from(cmdline).map_err(|e| {
+        PPCError::from(format!(
+            "not a valid command: {}",
+            // without the usage, which is the one of the hypothetical command.
+            e.to_string()
+                .trim_start_matches("error: ")
+                .split("\n\n")
+                .next()
+                .unwrap_or_default()
+        ))
+    })?;
+
+    resolve(args, &resolved, config, |name| env::var(name).ok())
+}
+
+/// The settings of the command `resolved`, with the configuration file of
+/// this invocation and `var` looking up environment variables.
+fn resolve(
+    args: &PPCArgs,
+    resolved: &PPCArgs,
+    config: &PPCConfig,
+    var: impl Fn(&str) -> Option<String>,
+) -> Result<Settings, PPCError> {
+    let mut settings = vec![Setting {
+        name: "config_file",
+        value: Some(
+            config::path(args.config_file.as_deref())?
+                .display()
+                .to_string(),
+        ),
+        source: if args.config_file.is_some() {
+            String::from("--config")
+        } else if var(CONFIG_DIR_VAR).is_some_and(|dir| !dir.is_empty()) {
+            String::from(CONFIG_DIR_VAR)
+        } else {
+            String::from(DEFAULT)
+        },
+    }];
+
+    let (language, source) = i18n::selected(resolved, var);
+    settings.push(Setting {
+        name: "language",
+        value: Some(String::from(language.code())),
+        source,
+    });
+
+    settings.extend(instance_settings(resolved, config)?);
+    match &resolved.action {
+        PPCAction::Push(PPCObject::Text(ppc_text)) => {
+            settings.extend(text_settings(ppc_text, config)?);
+        }
+        PPCAction::Push(PPCObject::File(ppc_file)) => {
+            settings.extend(file_settings(ppc_file, config)?);
+        }
+        _ => {}
+    }
+    Ok(Settings(settings))
+}
+
+/// The first layer that sets the value, or `default`.
+fn layered(
+    name: &'static str,
+    layers: Vec<(Option<String>, String)>,
+    default: (Option<&str>, &str),
+) -> Setting {
+    layers
+        .into_iter()
+        .find(|(value, _)| value.is_some())
+        .map(|(value, source)| Setting {
+            name,
+            value,
+            source,
+        })
+        .unwrap_or_else(|| Setting {
+            name,
+            value: default.0.map(String::from),
+            source: String::from(default.1),
+        })
+}
+
+/// Like `layered`, for the options of a push, whose final value is `value`.
+/// A value that differs from its layer was changed by the policy.
+fn push_option(
+    name: &'static str,
+    layers: Vec<(Option<String>, String)>,
+    value: Option<String>,
+) -> Setting {
+    let setting = layered(name, layers, (None, INSTANCE_DEFAULT));
+    if setting.value == value {
+        setting
+    } else {
+        Setting {
+            name,
+            value,
+            source: String::from("policy"),
+        }
+    }
+}
+
+fn instance_settings(args: &PPCArgs, config: &PPCConfig) -> Result<Vec<Setting>, PPCError> {
+    let (profile, from_profile) = match &args.profile {
+        Some(name) => (config.profile(name)?.clone(), format!("profile {}", name)),
+        None => (Profile::default(), String::new()),
+    };
+    let hidden = |token: Option<_>| token.map(|_| String::from("(hidden)"));
+
+    // credentials are taken over as a whole, see `Instance::resolve`.
+    let (email, token) = match &args.token {
+        Some(_) => (
+            layered(
+                "email",
+                vec![(args.email.clone(), String::from("--email"))],
+                (None, DEFAULT),
+            ),
+            layered(
+                "token",
+                vec![(hidden(args.token.as_ref()), String::from("--token"))],
+                (None, DEFAULT),
+            ),
+        ),
+        None => (
+            layered(
+                "email",
+                vec![(profile.email.clone(), from_profile.clone())],
+                (None, DEFAULT),
+            ),
+            layered(
+                "token",
+                vec![(hidden(profile.token.as_ref()), from_profile.clone())],
+                (None, DEFAULT),
+            ),
+        ),
+    };
+    let auth_scheme_default = match (&email.value, &token.value) {
+        (None, Some(_)) => (Some("bearer"), DEFAULT),
+        (_, Some(_)) => (None, "detected from the server version"),
+        (_, None) => (None, DEFAULT),
+    };
+
+    Ok(vec![
+        layered(
+            "url",
+            vec![
+                (args.instance_url.clone(), String::from("--url")),
+                (profile.url, from_profile.clone()),
+            ],
+            (Some(instance::DEFAULT_URL), DEFAULT),
+        ),
+        layered(
+            "protocol",
+            vec![
+                (
+                    args.instance_protocol.map(|p| p.to_string()),
+                    String::from("--protocol"),
+                ),
+                (
+                    profile.protocol.map(|p| p.to_string()),
+                    from_profile.clone(),
+                ),
+            ],
+            (Some("https"), DEFAULT),
+        ),
+        email,
+        token,
+        layered(
+            "auth_scheme",
+            vec![
+                (
+                    args.auth_scheme.map(|s| s.to_string()),
+                    String::from("--auth-scheme"),
+                ),
+                (profile.auth_scheme.map(|s| s.to_string()), from_profile),
+            ],
+            auth_scheme_default,
+        ),
+    ])
+}
+
+/// The options of a push that are layered, and their options on the command
+/// line.
+const PUSH_OPTIONS: [(&str, &str); 4] = [
+    ("expire_after_days", "--expire-after-days"),
+    ("expire_after_views", "--expire-after-views"),
+    ("deletable_by_viewer", "--deletable-by-viewer"),
+    ("retrieval_step", "--retrieval-step"),
+];
+
+/// The values of `PUSH_OPTIONS` in one layer.
+fn options(
+    expire_after_days: Option<usize>,
+    expire_after_views: Option<usize>,
+    deletable_by_viewer: Option<bool>,
+    retrieval_step: Option<bool>,
+) -> [Option<String>; 4] {
+    [
+        expire_after_days.map(|v| v.to_string()),
+        expire_after_views.map(|v| v.to_string()),
+        deletable_by_viewer.map(|v| v.to_string()),
+        retrieval_step.map(|v| v.to_string()),
+    ]
+}
+
+/// The settings of `PUSH_OPTIONS`, from the command line, the other `layers`
+/// in order, and the final values of the push.
+fn push_options(
+    given: [Option<String>; 4],
+    layers: Vec<([Option<String>; 4], String)>,
+    merged: [Option<String>; 4],
+) -> Vec<Setting> {
+    given
+        .into_iter()
+        .zip(merged)
+        .zip(PUSH_OPTIONS)
+        .enumerate()
+        .map(|(index, ((given, value), (name, option)))| {
+            let mut option_layers = vec![(given, String::from(option))];
+            option_layers.extend(
+                layers
+                    .iter()
+                    .map(|(values, source)| (values[index].clone(), source.clone())),
+            );
+            push_option(name, option_layers, value)
+        })
+        .collect()
+}
+
+fn text_settings(ppc_text: &PPCText, config: &PPCConfig) -> Result<Vec<Setting>, PPCError> {
+    let merged = config.apply(ppc_text)?;
+
+    let mut layers = Vec::new();
+    let mut generated_by = Vec::new();
+    if ppc_text.paranoid {
+        let mut hardened = ppc_text.clone();
+        paranoid::apply(&mut hardened);
+        layers.push((
+            options(
+                hardened.expire_after_days,
+                hardened.expire_after_views,
+                hardened.deletable_by_viewer,
+                hardened.retrieval_step,
+            ),
+            String::from("--paranoid"),
+        ));
+        generated_by.push(String::from("--paranoid"));
+    }
+    if let Some(name) = &ppc_text.preset {
+        // `apply` fails for presets that do not exist.
+        let preset = &config.presets[name];
+        let source = format!("preset {}", name);
+        layers.push((
+            options(
+                preset.expire_after_days,
+                preset.expire_after_views,
+                preset.deletable_by_viewer,
+                preset.retrieval_step,
+            ),
+            source.clone(),
+        ));
+        if preset.passphrase_length.is_some() {
+            generated_by.push(source);
+        }
+    }
+    if !ppc_text.no_defaults {
+        let defaults = &config.push;
+        layers.push((
+            options(
+                defaults.expire_after_days,
+                defaults.expire_after_views,
+                defaults.deletable_by_viewer,
+                defaults.retrieval_step,
+            ),
+            String::from(PUSH_DEFAULTS),
+        ));
+    }
+
+    let mut settings = push_options(
+        options(
+            ppc_text.expire_after_days,
+            ppc_text.expire_after_views,
+            ppc_text.deletable_by_viewer,
+            ppc_text.retrieval_step,
+        ),
+        layers,
+        options(
+            merged.expire_after_days,
+            merged.expire_after_views,
+            merged.deletable_by_viewer,
+            merged.retrieval_step,
+        ),
+    );
+    let mut passphrase = vec![(
+        ppc_text
+            .passphrase
+            .as_ref()
+            .map(|_| String::from("(hidden)")),
+        String::from("--passphrase"),
+    )];
+    passphrase.extend(
+        generated_by
+            .into_iter()
+            .map(|source| (Some(String::from("(generated)")), source)),
+    );
+    settings.push(layered("passphrase", passphrase, (None, DEFAULT)));
+    Ok(settings)
+}
+
+fn file_settings(ppc_file: &PPCFile, config: &PPCConfig) -> Result<Vec<Setting>, PPCError> {
+    let merged = config.apply_file(ppc_file)?;
+
+    let mut layers = Vec::new();
+    if !ppc_file.no_defaults {
+        let defaults = &config.push;
+        layers.push((
+            options(
+                defaults.expire_after_days,
+                defaults.expire_after_views,
+                defaults.deletable_by_viewer,
+                defaults.retrieval_step,
+            ),
+            String::from(PUSH_DEFAULTS),
+        ));
+    }
+
+    let mut settings = push_options(
+        options(
+            ppc_file.expire_after_days,
+            ppc_file.expire_after_views,
+            ppc_file.deletable_by_viewer,
+            ppc_file.retrieval_step,
+        ),
+        layers,
+        options(
+            merged.expire_after_days,
+            merged.expire_after_views,
+            merged.deletable_by_viewer,
+            merged.retrieval_step,
+        ),
+    );
+    settings.push(layered(
+        "passphrase",
+        vec![(
+            ppc_file
+                .passphrase
+                .as_ref()
+                .map(|_| String::from("(hidden)")),
+            String::from("--passphrase"),
+        )],
+        (None, DEFAULT),
+    ));
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+
+    use super::resolve;
+    use crate::args::PPCArgs;
+    use crate::config::PPCConfig;
+
+    #[test]
+    fn each_setting_names_its_layer() {
+        let config: PPCConfig = toml::from_str(
+            r#"
+            [push]
+            expire_after_days = 3
+            expire_after_views = 10
+            [presets.handoff]
+            expire_after_views = 2
+            passphrase_length = 12
+            [profiles.work]
+            url = "pwpush.example.com"
+            "#,
+        )
+        .unwrap();
+        let args = PPCArgs::parse_from(["pwpush-cli", "config", "resolve"]);
+        let resolved = PPCArgs::parse_from([
+            "pwpush-cli",
+            "--profile",
+            "work",
+            "push",
+            "text",
+            "--stdin",
+            "--preset",
+            "handoff",
+            "--retrieval-step",
+            "true",
+        ]);
+
+        let settings = resolve(&args, &resolved, &config, |name| {
+            (name == "LC_ALL").then(|| String::from("de_CH.UTF-8"))
+        })
+        .unwrap();
+        let source = |name| {
+            let setting = settings.0.iter().find(|s| s.name == name).unwrap();
+            (setting.value.as_deref(), setting.source.as_str())
+        };
+
+        assert_eq!(source("language"), (Some("de"), "LC_ALL"));
+        assert_eq!(source("url"), (Some("pwpush.example.com"), "profile work"));
+        assert_eq!(source("protocol"), (Some("https"), "default"));
+        assert_eq!(source("retrieval_step"), (Some("true"), "--retrieval-step"));
+        assert_eq!(source("expire_after_views"), (Some("2"), "preset handoff"));
+        assert_eq!(
+            source("expire_after_days"),
+            (Some("3"), "[push] of the configuration file")
+        );
+        assert_eq!(source("deletable_by_viewer"), (None, "instance default"));
+        assert_eq!(
+            source("passphrase"),
+            (Some("(generated)"), "preset handoff")
+        );
+    }
+}
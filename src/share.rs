@@ -0,0 +1,244 @@
+//! Share URLs of pushes.
+//!
+//! The API returns the URL token of a new push, but what users need is the
+//! link they can send to the recipient. The instance knows that link best, as
+//! it may add a locale or a retrieval step, so it is taken from the preview
+//! endpoint. If the instance does not offer previews, the link is built from
+//! the instance URL instead: `/p/<token>` for text pushes, with `/r` appended
+//! for pushes with a retrieval step.
+//!
+//! Instances behind reverse proxies sometimes answer the API, but hand out
+//! links that do not work, e.g. with an internal host name or a path the
+//! proxy does not forward. With `--validate`, the preview page of the link is
+//! opened before the link is printed, which does not count as a view, see
+//! `validate`.
+
+use serde_json::Value;
+use tracing::{debug, warn};
+
+use crate::api_url;
+use crate::args::{PPCArgs, PushKind};
+use crate::errors::PPCError;
+use crate::fail_on;
+use crate::http::{Method, PPCRequest, Transport};
+use crate::i18n;
+use crate::instance::Instance;
+use crate::models::{Preview, Push};
+use crate::pwpush_api;
+use crate::render::{self, CommandOutput};
+use crate::shortener::Shortener;
+
+/// The link to share for the push.
+pub fn url(transport: &dyn Transport, instance: &Instance, kind: PushKind, push: &Push) -> String {
+    let preview = pwpush_api::preview(transport, instance, kind, &push.url_token);
+    from_preview(instance, kind, push, preview)
+}
+
+/// The link to share, from the answer to the preview request if there is
+/// one. Shared with the async API of the library, see `client`.
+pub fn from_preview(
+    instance: &Instance,
+    kind: PushKind,
+    push: &Push,
+    preview: Result<Preview, PPCError>,
+) -> String {
+    match preview {
+        Ok(preview) => preview.url,
+        Err(e) => {
+            debug!("no preview available, building the URL instead: {}", e);
+            built_url(instance, kind, push)
+        }
+    }
+}
+
+/// The link to share, built from the instance URL.
+fn built_url(instance: &Instance, kind: PushKind, push: &Push) -> String {
+    let prefix = match kind {
+        PushKind::Text => "p",
+        PushKind::File => "f",
+        PushKind::Url => "r",
+    };
+
+    let base_url = instance.base_url();
+    if push.retrieval_step == Some(true) {
+        api_url::page(&base_url, &[prefix, &push.url_token, "r"])
+    } else {
+        api_url::page(&base_url, &[prefix, &push.url_token])
+    }
+}
+
+/// Check that the link of a new push opens, by requesting its preview page
+/// without credentials, like a recipient would. The push exists either way,
+/// so the error names it.
+pub fn validate(transport: &dyn Transport, push: &Push, url: &str) -> Result<(), PPCError> {
+    let link = url.trim_end_matches('/');
+    let preview = format!("{}/preview", link.strip_suffix("/r").unwrap_or(link));
+    debug!("validating the link with {}", preview);
+
+    let failure = match transport.send(&PPCRequest::new(Method::Get, preview)) {
+        Ok(response) if response.is_success() => return Ok(()),
+        Ok(response) => format!("status {}", response.status),
+        Err(e) => e.to_string(),
+    };
+    Err(PPCError::from(format!(
+        "push {} was created, but its link {} does not open: {}",
+        push.url_token, url, failure
+    ))
+    .with_hint(String::from(
+        "check the reverse proxy in front of the instance, and the URL the instance \
+         uses for links, e.g. PWP__OVERRIDE_BASE_URL",
+    )))
+}
+
+/// Print a newly created push, and return the link. Human output is just the
+/// link, so it can be copied or piped right away, followed by the short link
+/// if one was requested. JSON output is the push, with the links added, and
+/// quiet output is the shortest link. With `validate`, nothing is printed if
+/// the link does not open.
+pub fn print(
+    transport: &dyn Transport,
+    instance: &Instance,
+    args: &PPCArgs,
+    kind: PushKind,
+    push: &Push,
+    shortener: Option<&Shortener>,
+    validate: bool,
+) -> Result<String, PPCError> {
+    let url = url(transport, instance, kind, push);
+    if validate {
+        self::validate(transport, push, &url)?;
+    }
+    let short_url = shortener.and_then(|shortener| match shortener.shorten(&url) {
+        Ok(short_url) => Some(short_url),
+        Err(e) => {
+            warn!(
+                "{}",
+                i18n::format("push-not-shortened", &[("error", e.to_string().into())])
+            );
+            fail_on::soft_failure();
+            None
+        }
+    });
+
+    let created = CreatedPush {
+        push,
+        url,
+        short_url,
+    };
+    if let Err(e) = render::from_args(args).render(&created) {
+        warn!(
+            "{}",
+            i18n::format("push-not-printed", &[("error", e.to_string().into())])
+        );
+        fail_on::soft_failure();
+    }
+    Ok(created.url)
+}
+
+/// A newly created push with its links, see `print`.
+struct CreatedPush<'a> {
+    push: &'a Push,
+    url: String,
+    short_url: Option<String>,
+}
+
+impl CommandOutput for CreatedPush<'_> {
+    fn json(&self) -> Value {
+        // the push only consists of strings, numbers and bools, so
+        // serialization cannot fail.
+        let mut output = serde_json::to_value(self.push).unwrap();
+        output["url"] = Value::from(self.url.as_str());
+        if let Some(short_url) = &self.short_url {
+            output["short_url"] = Value::from(short_url.as_str());
+        }
+        output
+    }
+
+    fn human(&self, _width: Option<usize>) -> String {
+        match &self.short_url {
+            Some(short_url) => format!("{}\n{}\n", self.url, short_url),
+            None => format!("{}\n", self.url),
+        }
+    }
+
+    fn quiet(&self) -> String {
+        format!("{}\n", self.short_url.as_ref().unwrap_or(&self.url))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{url, validate};
+    use crate::args::{InstanceProtocol, PushKind};
+    use crate::http::mock::MockTransport;
+    use crate::instance::Instance;
+    use crate::models::Push;
+
+    fn instance() -> Instance {
+        Instance {
+            protocol: InstanceProtocol::Https,
+            url: String::from("intranet.example.com/pwpush"),
+            email: None,
+            token: None,
+            auth_scheme: None,
+        }
+    }
+
+    fn push(retrieval_step: bool) -> Push {
+        Push {
+            url_token: String::from("abc"),
+            retrieval_step: Some(retrieval_step),
+            ..Push::default()
+        }
+    }
+
+    #[test]
+    fn url_from_preview() {
+        let transport = MockTransport::new().respond(
+            200,
+            r#"{"url":"https://intranet.example.com/pwpush/en/p/abc/r"}"#,
+        );
+
+        assert_eq!(
+            url(&transport, &instance(), PushKind::Text, &push(true)),
+            "https://intranet.example.com/pwpush/en/p/abc/r"
+        );
+        assert_eq!(
+            transport.requests()[0].url,
+            "https://intranet.example.com/pwpush/p/abc/preview.json"
+        );
+    }
+
+    #[test]
+    fn url_without_preview() {
+        let transport = MockTransport::new()
+            .respond(404, "{}")
+            .fail("connection reset");
+
+        assert_eq!(
+            url(&transport, &instance(), PushKind::Text, &push(false)),
+            "https://intranet.example.com/pwpush/p/abc"
+        );
+        assert_eq!(
+            url(&transport, &instance(), PushKind::Url, &push(true)),
+            "https://intranet.example.com/pwpush/r/abc/r"
+        );
+    }
+
+    #[test]
+    fn validate_opens_the_preview_page() {
+        let transport = MockTransport::new().respond(200, "<html>").respond(502, "");
+        let link = "https://intranet.example.com/pwpush/en/p/abc/r";
+
+        assert!(validate(&transport, &push(true), link).is_ok());
+        let error = validate(&transport, &push(true), link).unwrap_err();
+        assert!(error.to_string().contains("status 502"));
+
+        let request = &transport.requests()[0];
+        assert_eq!(
+            request.url,
+            "https://intranet.example.com/pwpush/en/p/abc/preview"
+        );
+        assert!(request.headers.is_empty());
+    }
+}
@@ -0,0 +1,188 @@
+//! Short links of new pushes, for links that are read out or typed.
+//!
+//! Share links contain a long random token, which is hard to spell over the
+//! phone. With `--shorten`, the link of a new push is sent to the link
+//! shortener of the `[shortener]` section of the configuration file, and the
+//! short link is printed below the full one.
+//!
+//! Shorteners differ in their APIs, so the request is a template: the
+//! endpoint, the method, and for POST the body, each with `{url}` for the
+//! link. The short link is either the whole response, or a field of a JSON
+//! response, given as dotted path.
+//!
+//! The shortener is a third party, so it is asked with a transport of its own,
+//! see `platform::transport`, without the credentials and `--header` values of
+//! the instance. It learns the link, and anyone who knows the short link can open
+//! the push. Only use a shortener that you trust as much as the instance,
+//! ideally one run by the same organization.
+//!
+//! The push exists when the link is shortened, so a failing shortener does
+//! not fail the command, the full link is printed anyway with a warning.
+
+use serde_json::Value;
+
+use crate::config::{ShortenerMethod, ShortenerSettings};
+use crate::errors::PPCError;
+use crate::http::{Method, PPCRequest, Transport};
+
+/// A configured shortener.
+pub struct Shortener<'a> {
+    settings: &'a ShortenerSettings,
+    endpoint: &'a str,
+
+    /// Not the transport of the instance, see the module docs.
+    transport: &'a dyn Transport,
+}
+
+impl<'a> Shortener<'a> {
+    /// The shortener of the configuration, asked with `transport`. Fails if
+    /// there is none, so this is checked before pushing.
+    pub fn new(
+        settings: &'a ShortenerSettings,
+        transport: &'a dyn Transport,
+    ) -> Result<Self, PPCError> {
+        let endpoint = settings.url.as_deref().ok_or_else(|| {
+            PPCError::from("--shorten needs a shortener, configure its url in [shortener]")
+        })?;
+        Ok(Self {
+            settings,
+            endpoint,
+            transport,
+        })
+    }
+
+    /// The short link for the link.
+    pub fn shorten(&self, url: &str) -> Result<String, PPCError> {
+        let endpoint = self.endpoint.replace("{url}", &urlencoding::encode(url));
+        let mut request = match self.settings.method {
+            ShortenerMethod::Get => PPCRequest::new(Method::Get, endpoint),
+            ShortenerMethod::Post => {
+                let body = self.settings.body.as_deref().unwrap_or_default();
+                let content_type = self
+                    .settings
+                    .content_type
+                    .as_deref()
+                    .unwrap_or("application/json");
+                PPCRequest::new(Method::Post, endpoint)
+                    .header("Content-Type", content_type)
+                    .body(body.replace("{url}", url))
+            }
+        };
+        for (name, value) in &self.settings.headers {
+            request = request.header(name, value);
+        }
+
+        let response = self.transport.send(&request)?;
+        if !response.is_success() {
+            return Err(PPCError::from(format!(
+                "the shortener answered with status {}",
+                response.status
+            )));
+        }
+        short_url(&response.body, self.settings.response_field.as_deref())
+    }
+}
+
+/// The short link in the response, see the module docs.
+fn short_url(body: &str, field: Option<&str>) -> Result<String, PPCError> {
+    let short = match field {
+        None => String::from(body.trim()),
+        Some(field) => {
+            let value = serde_json::from_str::<Value>(body)
+                .map_err(|e| PPCError::from(format!("the shortener answered no JSON: {}", e)))?;
+            field
+                .split('.')
+                .try_fold(&value, |value, key| value.get(key))
+                .and_then(Value::as_str)
+                .map(String::from)
+                .ok_or_else(|| {
+                    PPCError::from(format!("the answer of the shortener has no {}", field))
+                })?
+        }
+    };
+
+    if !short.starts_with("https://") && !short.starts_with("http://") {
+        return Err(PPCError::from(format!(
+            "the shortener answered {:?} instead of a link",
+            short
+        )));
+    }
+    Ok(short)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use super::{short_url, Shortener};
+    use crate::config::{ShortenerMethod, ShortenerSettings};
+    use crate::http::mock::MockTransport;
+    use crate::http::Method;
+
+    const LINK: &str = "https://pwpush.example.com/en/p/abc?x=1";
+
+    #[test]
+    fn get_with_link_in_query() {
+        let settings = ShortenerSettings {
+            url: Some(String::from("https://s.example.com/api?link={url}")),
+            ..ShortenerSettings::default()
+        };
+        let transport = MockTransport::new().respond(200, "https://s.example.com/x1\n");
+
+        let short = Shortener::new(&settings, &transport)
+            .unwrap()
+            .shorten(LINK)
+            .unwrap();
+
+        assert_eq!(short, "https://s.example.com/x1");
+        assert_eq!(
+            transport.requests()[0].url,
+            "https://s.example.com/api?link=https%3A%2F%2Fpwpush.example.com%2Fen%2Fp%2Fabc%3Fx%3D1"
+        );
+    }
+
+    #[test]
+    fn post_with_json_body() {
+        let settings = ShortenerSettings {
+            url: Some(String::from("https://s.example.com/rest/v3/short-urls")),
+            method: ShortenerMethod::Post,
+            body: Some(String::from(r#"{"longUrl": "{url}"}"#)),
+            headers: BTreeMap::from([(String::from("X-Api-Key"), String::from("k3y"))]),
+            response_field: Some(String::from("shortUrl")),
+            ..ShortenerSettings::default()
+        };
+        let transport =
+            MockTransport::new().respond(200, r#"{"shortUrl":"https://s.example.com/x1"}"#);
+
+        let short = Shortener::new(&settings, &transport)
+            .unwrap()
+            .shorten(LINK)
+            .unwrap();
+
+        assert_eq!(short, "https://s.example.com/x1");
+        let request = &transport.requests()[0];
+        assert_eq!(request.method, Method::Post);
+        assert_eq!(
+            request.body.as_deref(),
+            Some(r#"{"longUrl": "https://pwpush.example.com/en/p/abc?x=1"}"#)
+        );
+        assert!(request
+            .headers
+            .contains(&(String::from("X-Api-Key"), String::from("k3y"))));
+    }
+
+    #[test]
+    fn unexpected_answers_fail() {
+        assert_eq!(
+            short_url(
+                r#"{"data":{"link":"https://s.example.com/x1"}}"#,
+                Some("data.link")
+            )
+            .unwrap(),
+            "https://s.example.com/x1"
+        );
+        assert!(short_url(r#"{"data":{}}"#, Some("data.link")).is_err());
+        assert!(short_url("<html>", None).is_err());
+        assert!(Shortener::new(&ShortenerSettings::default(), &MockTransport::new()).is_err());
+    }
+}
@@ -0,0 +1,224 @@
+//! Audit events in the formats of log management systems.
+//!
+//! With `audit --format`, every event of the audit log is printed as a single
+//! line that Splunk, Elastic and similar systems ingest without custom parsing,
+//! so access to pushes can be watched next to other security events:
+//!   - `cef`: ArcSight Common Event Format
+//!   - `syslog`: RFC 5424 messages with key=value pairs, for syslog relays
+//!   - `ndjson`: one JSON object per line, with Elastic Common Schema fields
+//!
+//! Together with `--watch`, new events are written as they arrive, e.g. into
+//! a file that a log shipper follows.
+
+use chrono::DateTime;
+use serde_json::json;
+
+use crate::args::{AuditFormat, PushKind};
+use crate::models::AuditView;
+
+/// Application name in all formats.
+const PRODUCT: &str = "pwpush-cli";
+
+/// The push the events belong to.
+pub struct Source<'a> {
+    pub instance: &'a str,
+    pub kind: PushKind,
+    pub url_token: &'a str,
+    /// Host name of this machine, as sender of syslog messages
+    pub hostname: &'a str,
+}
+
+/// A single event as line of the given format, without line break.
+pub fn format(format: AuditFormat, source: &Source, view: &AuditView) -> String {
+    match format {
+        AuditFormat::Cef => cef(source, view),
+        AuditFormat::Syslog => syslog(source, view),
+        AuditFormat::Ndjson => ndjson(source, view),
+    }
+}
+
+fn successful(view: &AuditView) -> bool {
+    view.successful != Some(false)
+}
+
+fn outcome(view: &AuditView) -> &'static str {
+    if successful(view) {
+        "success"
+    } else {
+        "failure"
+    }
+}
+
+/// Kind of the event as identifier without spaces, e.g. "manual_deletion".
+fn action(view: &AuditView) -> String {
+    view.kind_name().replace(' ', "_")
+}
+
+fn cef(source: &Source, view: &AuditView) -> String {
+    // header fields escape pipes, extension values equal signs.
+    let header = |value: &str| value.replace('\\', "\\\\").replace('|', "\\|");
+    let value = |value: &str| {
+        value
+            .replace('\\', "\\\\")
+            .replace('=', "\\=")
+            .replace('\r', "\\r")
+            .replace('\n', "\\n")
+    };
+
+    let mut extension = Vec::new();
+    if let Ok(date) = DateTime::parse_from_rfc3339(&view.created_at) {
+        extension.push(format!("rt={}", date.timestamp_millis()));
+    }
+    extension.push(format!("dhost={}", value(source.instance)));
+    if let Some(ip) = &view.ip {
+        extension.push(format!("src={}", value(ip)));
+    }
+    if let Some(user_agent) = &view.user_agent {
+        extension.push(format!("requestClientApplication={}", value(user_agent)));
+    }
+    if let Some(referrer) = &view.referrer {
+        extension.push(format!("requestContext={}", value(referrer)));
+    }
+    extension.push(format!("outcome={}", outcome(view)));
+    extension.push(String::from("cs1Label=urlToken"));
+    extension.push(format!("cs1={}", value(source.url_token)));
+    extension.push(String::from("cs2Label=pushKind"));
+    extension.push(format!("cs2={}", source.kind));
+
+    format!(
+        "CEF:0|{}|{}|{}|{}|{} push {}|{}|{}",
+        PRODUCT,
+        PRODUCT,
+        env!("CARGO_PKG_VERSION"),
+        header(&action(view)),
+        source.kind,
+        header(&view.kind_name()),
+        // failed attempts, e.g. guessed passphrases, deserve more attention.
+        if successful(view) { 3 } else { 6 },
+        extension.join(" ")
+    )
+}
+
+fn syslog(source: &Source, view: &AuditView) -> String {
+    // facility auth (4), severity notice (5) or warning (4).
+    let priority = if successful(view) {
+        4 * 8 + 5
+    } else {
+        4 * 8 + 4
+    };
+    let quoted = |value: &str| format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""));
+
+    let mut fields = vec![
+        format!("instance={}", quoted(source.instance)),
+        format!("push_kind={}", source.kind),
+        format!("url_token={}", quoted(source.url_token)),
+        format!("action={}", action(view)),
+        format!("outcome={}", outcome(view)),
+    ];
+    if let Some(ip) = &view.ip {
+        fields.push(format!("ip={}", quoted(ip)));
+    }
+    if let Some(user_agent) = &view.user_agent {
+        fields.push(format!("user_agent={}", quoted(user_agent)));
+    }
+    if let Some(referrer) = &view.referrer {
+        fields.push(format!("referrer={}", quoted(referrer)));
+    }
+
+    format!(
+        "<{}>1 {} {} {} - {} - {}",
+        priority,
+        view.created_at,
+        source.hostname,
+        PRODUCT,
+        action(view),
+        fields.join(" ")
+    )
+}
+
+fn ndjson(source: &Source, view: &AuditView) -> String {
+    json!({
+        "@timestamp": view.created_at,
+        "event": {
+            "kind": "event",
+            "action": action(view),
+            "outcome": outcome(view),
+            "provider": PRODUCT,
+        },
+        "source": { "ip": view.ip },
+        "user_agent": { "original": view.user_agent },
+        "http": { "request": { "referrer": view.referrer } },
+        "destination": { "domain": source.instance },
+        "pwpush": {
+            "push_kind": source.kind.to_string(),
+            "url_token": source.url_token,
+        },
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{format, Source};
+    use crate::args::{AuditFormat, PushKind};
+    use crate::models::AuditView;
+
+    fn source() -> Source<'static> {
+        Source {
+            instance: "pwpush.example.com",
+            kind: PushKind::Text,
+            url_token: "abc",
+            hostname: "workstation",
+        }
+    }
+
+    fn view(json: &str) -> AuditView {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn cef_escapes_values() {
+        let view = view(
+            r#"{"ip":"10.0.0.1","user_agent":"a=b","successful":false,
+                "created_at":"2023-12-01T10:00:00Z","kind":0}"#,
+        );
+
+        assert_eq!(
+            format(AuditFormat::Cef, &source(), &view),
+            format!(
+                "CEF:0|pwpush-cli|pwpush-cli|{}|view|text push view|6|rt=1701424800000 \
+                 dhost=pwpush.example.com src=10.0.0.1 requestClientApplication=a\\=b \
+                 outcome=failure cs1Label=urlToken cs1=abc cs2Label=pushKind cs2=text",
+                env!("CARGO_PKG_VERSION")
+            )
+        );
+    }
+
+    #[test]
+    fn syslog_quotes_values() {
+        let view = view(
+            r#"{"ip":"10.0.0.1","user_agent":"say \"hi\"","created_at":"2023-12-01T10:00:00Z","kind":1}"#,
+        );
+
+        assert_eq!(
+            format(AuditFormat::Syslog, &source(), &view),
+            "<37>1 2023-12-01T10:00:00Z workstation pwpush-cli - manual_deletion - \
+             instance=\"pwpush.example.com\" push_kind=text url_token=\"abc\" \
+             action=manual_deletion outcome=success ip=\"10.0.0.1\" user_agent=\"say \\\"hi\\\"\""
+        );
+    }
+
+    #[test]
+    fn ndjson_uses_ecs_fields() {
+        let view = view(r#"{"ip":"10.0.0.1","created_at":"2023-12-01T10:00:00Z"}"#);
+
+        let line = format(AuditFormat::Ndjson, &source(), &view);
+        assert!(!line.contains('\n'));
+
+        let event: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(event["@timestamp"], "2023-12-01T10:00:00Z");
+        assert_eq!(event["event"]["outcome"], "success");
+        assert_eq!(event["source"]["ip"], "10.0.0.1");
+        assert_eq!(event["pwpush"]["url_token"], "abc");
+    }
+}
@@ -0,0 +1,216 @@
+//! Graceful handling of Ctrl-C (SIGINT) and SIGTERM.
+//!
+//! By default, a signal ends the CLI right away with exit code 130, after
+//! temporary files were removed, so an interrupted upload or state update does
+//! not leave half-written files behind. The request that was in flight is
+//! simply dropped, and nothing is retried.
+//!
+//! Commands that have something to finish register a `Listener` instead, and
+//! are told about the signal rather than being ended:
+//!   - `--hold` stops holding, and expires `--ephemeral` pushes
+//!   - `expire bulk` and `--recipients` finish the requests in flight, start
+//!     no new ones, and report what was left out
+//!
+//! A second signal always ends the CLI at once, in case finishing hangs.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Mutex, MutexGuard};
+
+use tracing::{debug, warn};
+
+use crate::errors::EXIT_INTERRUPTED;
+use crate::i18n;
+use crate::platform;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry::new());
+
+/// What to do about a signal.
+#[derive(Debug, PartialEq, Eq)]
+enum Reaction {
+    /// Listeners were told and finish on their own
+    Notified,
+    /// Nobody listens, so the CLI ends
+    Exit,
+}
+
+/// Listeners and temporary files of the running command.
+struct Registry {
+    next_id: u64,
+    listeners: Vec<(u64, Sender<()>)>,
+    temp_files: Vec<(u64, PathBuf)>,
+}
+
+impl Registry {
+    const fn new() -> Self {
+        Self {
+            next_id: 0,
+            listeners: Vec::new(),
+            temp_files: Vec::new(),
+        }
+    }
+
+    fn id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn interrupt(&mut self) -> Reaction {
+        // listeners that went away without unregistering do not count.
+        self.listeners.retain(|(_, sender)| sender.send(()).is_ok());
+        if !self.listeners.is_empty() {
+            return Reaction::Notified;
+        }
+
+        // temporary files of listeners are still in use, so they are only
+        // removed on exit.
+        for (_, path) in self.temp_files.drain(..) {
+            debug!("removing {}", path.display());
+            let _ = fs::remove_file(path);
+        }
+        Reaction::Exit
+    }
+}
+
+fn registry() -> MutexGuard<'static, Registry> {
+    // the registry stays consistent even if a holder panicked.
+    REGISTRY.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Handle signals from now on. Without it, signals end the CLI without any
+/// cleanup, so a failure is only logged.
+pub fn install() {
+    if let Err(e) = platform::on_interrupt(handle) {
+        warn!(
+            "{}",
+            i18n::format("interrupt-unavailable", &[("error", e.to_string().into())])
+        );
+    }
+}
+
+fn handle() {
+    if INTERRUPTED.swap(true, Ordering::SeqCst) {
+        eprintln!("{}", i18n::text("interrupted-again"));
+        process::exit(EXIT_INTERRUPTED.into());
+    }
+
+    if registry().interrupt() == Reaction::Exit {
+        eprintln!("{}", i18n::text("interrupted"));
+        process::exit(EXIT_INTERRUPTED.into());
+    }
+    eprintln!("{}", i18n::text("interrupted-finishing"));
+}
+
+/// Whether a signal was received. Long-running loops stop starting new work
+/// once it is set.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Receives a message on the first signal, instead of the CLI being ended.
+/// Signals end the CLI again once all listeners were dropped.
+pub struct Listener {
+    id: u64,
+    receiver: Receiver<()>,
+}
+
+impl Listener {
+    pub fn receiver(&self) -> &Receiver<()> {
+        &self.receiver
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        registry().listeners.retain(|(id, _)| *id != self.id);
+    }
+}
+
+/// Be told about signals instead of being ended by them.
+pub fn listen() -> Listener {
+    let (sender, receiver) = mpsc::channel();
+    let mut registry = registry();
+    let id = registry.id();
+    registry.listeners.push((id, sender));
+    Listener { id, receiver }
+}
+
+/// A file that only exists while it is written, e.g. before it is renamed to
+/// its final name. It is removed when the CLI is ended by a signal, and when
+/// the guard is dropped, if it still exists then.
+pub struct TempFile {
+    id: u64,
+    path: PathBuf,
+}
+
+impl TempFile {
+    pub fn new(path: PathBuf) -> Self {
+        let mut registry = registry();
+        let id = registry.id();
+        registry.temp_files.push((id, path.clone()));
+        Self { id, path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        registry().temp_files.retain(|(id, _)| *id != self.id);
+        if self.path.exists() {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::sync::mpsc;
+
+    use super::{Reaction, Registry, TempFile};
+
+    #[test]
+    fn listeners_keep_the_cli_running() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("upload.tmp");
+        fs::write(&path, "partial").unwrap();
+
+        let mut registry = Registry::new();
+        let (sender, receiver) = mpsc::channel();
+        registry.listeners.push((1, sender));
+        registry.temp_files.push((2, path.clone()));
+
+        assert_eq!(registry.interrupt(), Reaction::Notified);
+        assert!(receiver.try_recv().is_ok());
+        assert!(path.exists());
+
+        drop(receiver);
+        assert_eq!(registry.interrupt(), Reaction::Exit);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn temp_files_are_removed_when_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.tmp");
+
+        let temp = TempFile::new(path.clone());
+        fs::write(temp.path(), "partial").unwrap();
+        drop(temp);
+        assert!(!path.exists());
+
+        let temp = TempFile::new(path.clone());
+        fs::write(temp.path(), "complete").unwrap();
+        fs::rename(temp.path(), dir.path().join("state")).unwrap();
+        drop(temp);
+        assert!(dir.path().join("state").exists());
+    }
+}
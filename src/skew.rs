@@ -0,0 +1,106 @@
+//! Differences between the clock of this machine and that of the instance.
+//!
+//! Pushes expire by the clock of the instance, which reports creation times
+//! and expiry dates by it. Computing "expires in" against the local clock is
+//! off by the skew of both clocks, up to negative countdowns for pushes that
+//! the instance still considers active, or `remind --within` missing pushes
+//! that are about to expire.
+//!
+//! The instance tells its time in the `Date` header of each response, so the
+//! skew is measured from the push lists, and times are computed against the
+//! clock of the instance instead. The header only has whole seconds and is
+//! delayed by the request, which is negligible for expiry in days.
+//!
+//! Skews beyond a minute are reported once as warning, as they usually
+//! mean that the local clock is not synchronized, which breaks other things,
+//! like TLS certificates and TOTP codes, as well.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use chrono::{DateTime, Duration, Utc};
+use tracing::warn;
+
+use crate::i18n;
+
+/// Skews beyond this are warned about.
+const THRESHOLD: Duration = Duration::minutes(1);
+
+static WARNED: AtomicBool = AtomicBool::new(false);
+
+/// How far the clock of the instance is ahead of `local`, the time the
+/// response with the `Date` header was received. Negative if it is behind.
+pub fn measure(date: Option<&str>, local: DateTime<Utc>) -> Option<Duration> {
+    let date = DateTime::parse_from_rfc2822(date?.trim()).ok()?;
+    Some(date.with_timezone(&Utc) - local)
+}
+
+/// The current time by the clock of the instance, if the skew is known, and
+/// by the local clock otherwise.
+pub fn now(skew: Option<Duration>) -> DateTime<Utc> {
+    corrected(Utc::now(), skew)
+}
+
+fn corrected(local: DateTime<Utc>, skew: Option<Duration>) -> DateTime<Utc> {
+    let Some(skew) = skew else {
+        return local;
+    };
+    if skew.abs() > THRESHOLD && !WARNED.swap(true, Ordering::Relaxed) {
+        let id = if skew > Duration::zero() {
+            "clock-behind"
+        } else {
+            "clock-ahead"
+        };
+        warn!(
+            "{}",
+            i18n::format(id, &[("skew", describe(skew.abs()).into())])
+        );
+    }
+    local + skew
+}
+
+/// A skew in the largest unit that makes sense, e.g. `5m`.
+fn describe(skew: Duration) -> String {
+    if skew.num_hours() > 0 {
+        format!("{}h", skew.num_hours())
+    } else if skew.num_minutes() > 0 {
+        format!("{}m", skew.num_minutes())
+    } else {
+        format!("{}s", skew.num_seconds())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{DateTime, Duration, Utc};
+
+    use super::{corrected, measure};
+
+    fn local() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-03-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn skew_is_measured_from_the_date_header() {
+        assert_eq!(
+            measure(Some("Fri, 01 Mar 2024 12:07:30 GMT"), local()),
+            Some(Duration::seconds(450))
+        );
+        assert_eq!(
+            measure(Some("Fri, 01 Mar 2024 11:59:58 GMT"), local()),
+            Some(Duration::seconds(-2))
+        );
+        assert_eq!(measure(Some("yesterday"), local()), None);
+        assert_eq!(measure(None, local()), None);
+    }
+
+    #[test]
+    fn time_follows_the_instance() {
+        assert_eq!(corrected(local(), None), local());
+        assert_eq!(
+            corrected(local(), Some(Duration::minutes(-10))),
+            local() - Duration::minutes(10)
+        );
+    }
+}
@@ -0,0 +1,248 @@
+//! Files the CLI keeps between runs, e.g. the offline queue.
+//!
+//! They live below the data directory of the platform, e.g.
+//! `~/.local/share/pwpush-cli` on Linux, see `paths`. As they may
+//! reveal secrets or at least which secrets were shared, directories and files
+//! are only accessible by the user. Files that contain secrets are also
+//! encrypted with XChaCha20-Poly1305, with a random key that is generated on
+//! first use. The keys are kept in the key directory, e.g.
+//! `~/.local/state/pwpush-cli` on Linux, never next to the files they
+//! encrypt, so a backup or synced copy of the data or cache directory alone
+//! does not reveal them. Keys of earlier versions, which were stored next to
+//! the files, are moved there on first use. This does not protect the files
+//! from someone who can read all files of the user anyway, e.g. a backup of
+//! the whole home directory.
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+use tracing::debug;
+use zeroize::Zeroizing;
+
+use crate::errors::PPCError;
+use crate::paths;
+use crate::signals::TempFile;
+
+/// Length of generated keys in bytes.
+const KEY_LENGTH: usize = 32;
+
+const NONCE_LENGTH: usize = 24;
+
+/// Directory for the given kind of state.
+pub fn dir(name: &str) -> Result<PathBuf, PPCError> {
+    Ok(paths::data_dir()?.join(name))
+}
+
+/// Create the directory if needed, and make sure only the user can access it.
+pub fn create_private_dir(dir: &Path) -> Result<(), PPCError> {
+    fs::create_dir_all(dir).map_err(|e| {
+        PPCError::from(format!(
+            "could not create directory {}: {}",
+            dir.display(),
+            e
+        ))
+    })?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o700)).map_err(|e| {
+            PPCError::from(format!(
+                "could not restrict permissions of {}: {}",
+                dir.display(),
+                e
+            ))
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Whether users other than the owner have any access to the file or
+/// directory. Always false where permissions work differently.
+#[cfg(unix)]
+pub fn readable_by_others(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|metadata| metadata.permissions().mode() & 0o077 != 0)
+}
+
+#[cfg(not(unix))]
+pub fn readable_by_others(_path: &Path) -> bool {
+    false
+}
+
+/// Write a new file that only the user can read.
+pub fn write_private(path: &Path, content: &[u8]) -> Result<(), PPCError> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(content))
+        .map_err(|e| PPCError::from(format!("could not write {}: {}", path.display(), e)))
+}
+
+/// Append to a file that only the user can read, creating it if needed.
+pub fn append_private(path: &Path, content: &[u8]) -> Result<(), PPCError> {
+    let mut options = OpenOptions::new();
+    options.append(true).create(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(content))
+        .map_err(|e| PPCError::from(format!("could not write {}: {}", path.display(), e)))
+}
+
+/// Replace a file that only the user can read. The content is written to a
+/// new file first, so the previous content survives if writing fails.
+pub fn replace_private(path: &Path, content: &[u8]) -> Result<(), PPCError> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let temporary = TempFile::new(PathBuf::from(temporary));
+
+    // left over from a write that could not be cleaned up, e.g. after kill -9.
+    let _ = fs::remove_file(temporary.path());
+    write_private(temporary.path(), content)?;
+    fs::rename(temporary.path(), path)
+        .map_err(|e| PPCError::from(format!("could not write {}: {}", path.display(), e)))
+}
+
+/// Path of the key of the given name in the key directory `keys`, which is
+/// created if needed. A key that an earlier version stored next to the state
+/// in `dir` is moved there.
+pub fn key_file(keys: &Path, dir: &Path, name: &str) -> Result<PathBuf, PPCError> {
+    create_private_dir(keys)?;
+    let path = keys.join(name);
+    let legacy = dir.join(name);
+    if !path.exists() && legacy.exists() {
+        debug!("moving key {} to {}", legacy.display(), path.display());
+        let key =
+            Zeroizing::new(fs::read(&legacy).map_err(|e| {
+                PPCError::from(format!("could not read {}: {}", legacy.display(), e))
+            })?);
+        write_private(&path, &key)?;
+        fs::remove_file(&legacy)
+            .map_err(|e| PPCError::from(format!("could not remove {}: {}", legacy.display(), e)))?;
+    }
+    Ok(path)
+}
+
+/// Read the key from the file, or generate a random one and store it there.
+pub fn key(path: &Path) -> Result<Zeroizing<Vec<u8>>, PPCError> {
+    if path.exists() {
+        return Ok(Zeroizing::new(fs::read(path).map_err(|e| {
+            PPCError::from(format!("could not read {}: {}", path.display(), e))
+        })?));
+    }
+
+    debug!("generating new key {}", path.display());
+    let mut key = Zeroizing::new(vec![0u8; KEY_LENGTH]);
+    rand::thread_rng().fill_bytes(&mut key);
+    write_private(path, &key)?;
+    Ok(key)
+}
+
+/// Cipher with the key from the file, which is generated if needed.
+pub fn cipher(key_path: &Path) -> Result<XChaCha20Poly1305, PPCError> {
+    let key = key(key_path)?;
+    XChaCha20Poly1305::new_from_slice(&key)
+        .map_err(|_| PPCError::from(format!("invalid key {}", key_path.display())))
+}
+
+/// Encrypt with a random nonce, which is prepended to the ciphertext.
+pub fn seal(cipher: &XChaCha20Poly1305, plaintext: &[u8]) -> Result<Vec<u8>, PPCError> {
+    let mut nonce = [0u8; NONCE_LENGTH];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let ciphertext = cipher
+        .encrypt(XNonce::from_slice(&nonce), plaintext)
+        .map_err(|_| PPCError::from("could not encrypt"))?;
+
+    let mut content = nonce.to_vec();
+    content.extend_from_slice(&ciphertext);
+    Ok(content)
+}
+
+/// Decrypt what was encrypted with `seal`. Fails if the content was changed
+/// or truncated, or encrypted with a different key.
+pub fn unseal(cipher: &XChaCha20Poly1305, content: &[u8]) -> Result<Zeroizing<Vec<u8>>, PPCError> {
+    if content.len() < NONCE_LENGTH {
+        return Err(PPCError::from("truncated"));
+    }
+
+    let (nonce, ciphertext) = content.split_at(NONCE_LENGTH);
+    cipher
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        .map(Zeroizing::new)
+        .map_err(|_| PPCError::from("could not decrypt, the key might have changed"))
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::{cipher, key, key_file, replace_private, seal, unseal, write_private};
+
+    #[test]
+    fn key_is_generated_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.key");
+
+        let first = key(&path).unwrap();
+        let second = key(&path).unwrap();
+
+        assert_eq!(first.len(), 32);
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn legacy_keys_are_moved() {
+        let data = tempfile::tempdir().unwrap();
+        let keys = tempfile::tempdir().unwrap();
+        let legacy = key(&data.path().join("test.key")).unwrap();
+
+        let path = key_file(&keys.path().join("keys"), data.path(), "test.key").unwrap();
+
+        assert_eq!(path, keys.path().join("keys").join("test.key"));
+        assert_eq!(key(&path).unwrap(), legacy);
+        assert!(!data.path().join("test.key").exists());
+    }
+
+    #[test]
+    fn sealed_content_is_authenticated() {
+        let dir = tempfile::tempdir().unwrap();
+        let cipher = cipher(&dir.path().join("test.key")).unwrap();
+
+        let mut sealed = seal(&cipher, b"secret").unwrap();
+        assert_eq!(unseal(&cipher, &sealed).unwrap().as_slice(), b"secret");
+
+        *sealed.last_mut().unwrap() ^= 1;
+        assert!(unseal(&cipher, &sealed).is_err());
+        assert!(unseal(&cipher, &sealed[..10]).is_err());
+    }
+
+    #[test]
+    fn replace_overwrites_and_write_does_not() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+
+        write_private(&path, b"first").unwrap();
+        assert!(write_private(&path, b"second").is_err());
+
+        replace_private(&path, b"second").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"second");
+    }
+}
@@ -0,0 +1,239 @@
+//! JSON request documents on stdin, for programs that drive the CLI.
+//!
+//! With `--stdin-json`, the command is not taken from the command line, but
+//! from a JSON document on stdin:
+//!
+//! ```json
+//! {
+//!   "command": ["push", "text"],
+//!   "options": {"expire-after-views": 1, "retrieval-step": true, "base64": false},
+//!   "arguments": ["the secret"]
+//! }
+//! ```
+//!
+//! `command` names the subcommands, `options` the long options without dashes,
+//! and `arguments` the positional arguments. Flags take a bool, all other
+//! options a string, number or bool, or a list of them for options that can
+//! be given multiple times. Global options like `--profile` can be given in
+//! the document or on the command line.
+//!
+//! Output is always JSON, and failures are reported as
+//! `{"error": "...", "exit_code": 1}` on stdout as well, so the calling
+//! program only has to parse stdout. As nothing passes through a shell,
+//! there are no quoting issues, and secrets do not show up in the process
+//! list.
+
+use std::ffi::OsString;
+use std::io::{self, Read};
+
+use clap::{Command, CommandFactory, Parser};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use zeroize::Zeroizing;
+
+use crate::args::PPCArgs;
+use crate::errors::PPCError;
+use crate::output;
+
+pub const FLAG: &str = "--stdin-json";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Request {
+    command: Vec<String>,
+
+    #[serde(default)]
+    options: serde_json::Map<String, Value>,
+
+    #[serde(default)]
+    arguments: Vec<String>,
+}
+
+/// Arguments for the request on stdin, if `--stdin-json` was given. The
+/// original command line is kept, so global options can be given there.
+pub fn args(cmdline: &[OsString]) -> Option<Result<PPCArgs, PPCError>> {
+    if !cmdline.iter().any(|arg| arg == FLAG) {
+        return None;
+    }
+
+    let mut input = Zeroizing::new(String::new());
+    if let Err(e) = io::stdin().read_to_string(&mut input) {
+        return Some(Err(PPCError::from(format!(
+            "could not read request from stdin: {}",
+            e
+        ))));
+    }
+    Some(parse(cmdline, &input))
+}
+
+/// Report an error in the format the driving program expects, with the
+/// trace ID of `--request-id`, see `trace`.
+pub fn print_error(error: &PPCError, exit_code: u8, trace_id: Option<&str>) {
+    let mut output = json!({ "error": error.to_string(), "exit_code": exit_code });
+    if let Some(hint) = error.hint() {
+        output["hint"] = json!(hint);
+    }
+    if let Some(trace_id) = trace_id {
+        output["trace_id"] = json!(trace_id);
+    }
+    output::print_json_line(&output);
+}
+
+fn parse(cmdline: &[OsString], input: &str) -> Result<PPCArgs, PPCError> {
+    let request = serde_json::from_str::<Request>(input)
+        .map_err(|e| PPCError::from(format!("invalid request document: {}", e)))?;
+
+    let argv = Zeroizing::new(to_argv(cmdline, &request)?);
+    PPCArgs::try_parse_from(argv.iter()).map_err(|e| {
+        // clap renders errors for the terminal, the first line is enough.
+        let rendered = e.to_string();
+        let message = rendered.lines().next().unwrap_or_default();
+        PPCError::from(message.trim_start_matches("error: ").to_string())
+    })
+}
+
+/// Translate the request into a command line, looking up in the definition
+/// of the command whether an option is a flag or takes a value.
+fn to_argv(cmdline: &[OsString], request: &Request) -> Result<Vec<String>, PPCError> {
+    let mut argv = cmdline
+        .iter()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    if !argv.iter().any(|arg| arg == "--json") && !request.options.contains_key("json") {
+        argv.push(String::from("--json"));
+    }
+
+    let root = PPCArgs::command();
+    let mut command = &root;
+    let mut commands = vec![&root];
+    for name in &request.command {
+        command = command
+            .find_subcommand(name)
+            .ok_or_else(|| PPCError::from(format!("unknown command `{}`", name)))?;
+        commands.push(command);
+    }
+
+    // options go right after the subcommand they belong to, global options
+    // before all subcommands.
+    let mut positions = vec![Vec::new(); commands.len()];
+    for (name, value) in &request.options {
+        let (level, flag) = find_option(&commands, name)?;
+        positions[level].extend(option_args(name, flag, value)?);
+    }
+
+    argv.extend(positions.remove(0));
+    for (name, options) in request.command.iter().zip(positions) {
+        argv.push(name.clone());
+        argv.extend(options);
+    }
+
+    // positional arguments may start with a dash, e.g. a generated password.
+    if !request.arguments.is_empty() {
+        argv.push(String::from("--"));
+        argv.extend(request.arguments.iter().cloned());
+    }
+    Ok(argv)
+}
+
+/// Level of the command that defines the option, and whether it is a flag.
+fn find_option(commands: &[&Command], name: &str) -> Result<(usize, bool), PPCError> {
+    commands
+        .iter()
+        .enumerate()
+        .rev()
+        .find_map(|(level, command)| {
+            command
+                .get_arguments()
+                .find(|arg| arg.get_long() == Some(name))
+                .map(|arg| (level, !arg.get_action().takes_values()))
+        })
+        .ok_or_else(|| PPCError::from(format!("unknown option `{}`", name)))
+}
+
+fn option_args(name: &str, flag: bool, value: &Value) -> Result<Vec<String>, PPCError> {
+    let option = format!("--{}", name);
+    if flag {
+        return match value {
+            Value::Bool(true) => Ok(vec![option]),
+            Value::Bool(false) => Ok(Vec::new()),
+            _ => Err(PPCError::from(format!("option `{}` takes a bool", name))),
+        };
+    }
+
+    let values = match value {
+        Value::Array(values) => values.iter().collect(),
+        value => vec![value],
+    };
+    values
+        .into_iter()
+        .map(|value| match value {
+            Value::String(text) => Ok(format!("{}={}", option, text)),
+            Value::Number(number) => Ok(format!("{}={}", option, number)),
+            Value::Bool(b) => Ok(format!("{}={}", option, b)),
+            _ => Err(PPCError::from(format!(
+                "option `{}` takes a string, number or bool",
+                name
+            ))),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::ffi::OsString;
+
+    use secrecy::ExposeSecret;
+
+    use super::parse;
+    use crate::args::{PPCAction, PPCObject};
+
+    fn cmdline(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn push_request() {
+        let args = parse(
+            &cmdline(&["pwpush-cli", "--stdin-json", "--profile", "work"]),
+            r#"{"command": ["push", "text"],
+                "options": {"expire-after-views": 1, "retrieval-step": true,
+                            "no-warn": true, "base64": false, "url": "pwpush.example.com"},
+                "arguments": ["-secret with spaces"]}"#,
+        )
+        .unwrap();
+
+        assert!(args.json_output);
+        assert_eq!(args.profile.as_deref(), Some("work"));
+        assert_eq!(args.instance_url.as_deref(), Some("pwpush.example.com"));
+        let PPCAction::Push(PPCObject::Text(text)) = &args.action else {
+            panic!("not a text push");
+        };
+        assert_eq!(
+            text.password_payload.as_ref().unwrap().expose_secret(),
+            "-secret with spaces"
+        );
+        assert_eq!(text.expire_after_views, Some(1));
+        assert_eq!(text.retrieval_step, Some(true));
+        assert!(text.no_warn);
+        assert!(!text.base64);
+    }
+
+    #[test]
+    fn invalid_requests() {
+        let cmdline = cmdline(&["pwpush-cli", "--stdin-json"]);
+
+        assert!(parse(&cmdline, "not json").is_err());
+        assert!(parse(&cmdline, r#"{"command": ["frobnicate"]}"#).is_err());
+        assert!(parse(
+            &cmdline,
+            r#"{"command": ["summary"], "options": {"bogus": 1}}"#
+        )
+        .is_err());
+        assert!(parse(
+            &cmdline,
+            r#"{"command": ["push", "text"], "options": {"no-warn": 1}}"#
+        )
+        .is_err());
+        assert!(parse(&cmdline, r#"{"command": ["push", "text"]}"#).is_err());
+    }
+}
@@ -0,0 +1,111 @@
+//! Text pushes with payloads in a format that other software reads.
+//!
+//! Some secrets are only useful in a specific format: phones join WiFi
+//! networks from `WIFI:` strings, authenticator apps import `otpauth://`
+//! URIs, see `wifi` and `totp`. Their commands take the parts as options and
+//! build the payload, instead of leaving the format to the user.
+//!
+//! The secret part is given with its own option, read from the first line of
+//! stdin with `--stdin`, or asked for with hidden input, see `read_option`.
+//! The payload is then pushed like with `push text`, so defaults, presets and
+//! the output are the same, with the push options of `PPCStructuredPush`.
+//! Both formats are usually scanned as QR code, so `--qr` shows the payload
+//! as QR code to recipients, and `--qr-link` prints the link as QR code in
+//! the terminal, see `qr`.
+
+use std::io::{self, BufRead, IsTerminal};
+
+use secrecy::SecretString;
+use tracing::warn;
+use zeroize::Zeroizing;
+
+use crate::args::{PPCStructuredPush, PPCText};
+use crate::config::PPCConfig;
+use crate::dedupe;
+use crate::errors::PPCError;
+use crate::i18n;
+use crate::prompt;
+
+/// The secret part of a payload: given with `option`, read from the first
+/// line of stdin with `--stdin`, or asked for with `question` if neither is
+/// given. Like payloads, secrets given on the command line are warned about.
+pub fn read_option(
+    option: &str,
+    given: Option<&SecretString>,
+    stdin: bool,
+    push: &PPCStructuredPush,
+    question: &str,
+    config: &PPCConfig,
+) -> Result<SecretString, PPCError> {
+    if stdin {
+        let mut line = Zeroizing::new(String::new());
+        io::stdin()
+            .lock()
+            .read_line(&mut line)
+            .map_err(|e| PPCError::from(format!("could not read {} from stdin: {}", option, e)))?;
+        return Ok(SecretString::from(line.trim_end_matches(['\r', '\n'])));
+    }
+    let Some(given) = given else {
+        return prompt::secret(question);
+    };
+
+    if !push.no_warn
+        && config.push.warn_positional_secret.unwrap_or(true)
+        && io::stdin().is_terminal()
+    {
+        warn!(
+            "{}",
+            i18n::format("push-option-argument", &[("option", option.into())])
+        );
+    }
+    Ok(given.clone())
+}
+
+/// The text push of the payload.
+pub fn text(payload: SecretString, push: &PPCStructuredPush) -> PPCText {
+    PPCText {
+        password_payload: Some(payload),
+        payload_file: None,
+        stdin: false,
+        prompt: false,
+        from_op: None,
+        from_bw: None,
+        from_vault: None,
+        from_env: Vec::new(),
+        base64: false,
+        normalize_newlines: false,
+        encrypt_for: None,
+        // secrets on the command line were warned about when they were read.
+        no_warn: true,
+        passphrase: push.passphrase.clone(),
+        qr: push.qr,
+        qr_link: push.qr_link,
+        note: push.note.clone(),
+        expire_after_days: push.expire_after_days,
+        expire_after_views: push.expire_after_views,
+        deletable_by_viewer: None,
+        retrieval_step: None,
+        no_defaults: push.no_defaults,
+        preset: push.preset.clone(),
+        queue_on_failure: false,
+        paranoid: false,
+        enforce_strong_passphrase: false,
+        hold: false,
+        hold_for: None,
+        ephemeral: false,
+        expire_at: None,
+        expire_after: None,
+        exact: false,
+        to_profiles: Vec::new(),
+        recipients: Vec::new(),
+        notify_webhook: None,
+        email_to: None,
+        shorten: false,
+        validate: false,
+        tags: Vec::new(),
+        expire_after_first_view_confirmed: false,
+        dedupe: None,
+        dedupe_window: dedupe::DEFAULT_WINDOW,
+        idempotency_key: None,
+    }
+}
@@ -0,0 +1,271 @@
+//! One-screen overview of the pushes of an account.
+//!
+//! The API has no endpoint for statistics, so the summary is aggregated from
+//! the active and expired lists of all three push kinds. These contain only
+//! metadata, so no push is viewed by creating a summary.
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::Value;
+use unicode_width::UnicodeWidthStr;
+
+use crate::args::{PushKind, PushState};
+use crate::errors::PPCError;
+use crate::http::Transport;
+use crate::i18n;
+use crate::instance::Instance;
+use crate::inventory::{self, InventoryRecord};
+use crate::render::CommandOutput;
+use crate::table::{Column, Table};
+
+/// Number of pushes shown in the lists of upcoming expirations and most viewed
+/// pushes.
+const TOP_PUSHES: usize = 5;
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Summary {
+    pub counts: Vec<KindCounts>,
+    pub expiring_soon: Vec<SummaryEntry>,
+    pub most_viewed: Vec<SummaryEntry>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct KindCounts {
+    pub kind: PushKind,
+    pub active: usize,
+    pub expired: usize,
+}
+
+/// The fields of a push that are shown in the summary lists.
+#[derive(Debug, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct SummaryEntry {
+    pub kind: PushKind,
+    pub url_token: String,
+    pub days_remaining: Option<i64>,
+    pub views_remaining: Option<i64>,
+    pub views: Option<i64>,
+    pub note: Option<String>,
+}
+
+pub fn run(transport: &dyn Transport, instance: &Instance) -> Result<Summary, PPCError> {
+    let records = inventory::collect(transport, instance, false)?;
+    Ok(summarize(&records))
+}
+
+impl CommandOutput for Summary {
+    fn json(&self) -> Value {
+        // the summary only consists of strings and numbers.
+        serde_json::to_value(self).unwrap()
+    }
+
+    fn human(&self, width: Option<usize>) -> String {
+        format_summary(self, width)
+    }
+}
+
+fn summarize(records: &[InventoryRecord]) -> Summary {
+    let counts = [PushKind::Text, PushKind::File, PushKind::Url]
+        .into_iter()
+        .map(|kind| {
+            let count = |state| {
+                records
+                    .iter()
+                    .filter(|r| r.kind == kind && r.state == state)
+                    .count()
+            };
+            KindCounts {
+                kind,
+                active: count(PushState::Active),
+                expired: count(PushState::Expired),
+            }
+        })
+        .collect();
+
+    let entry = |record: &InventoryRecord| SummaryEntry {
+        kind: record.kind,
+        url_token: record.push.url_token.clone(),
+        days_remaining: record.push.days_remaining,
+        views_remaining: record.push.views_remaining,
+        views: record.push.views(),
+        note: record.push.note.clone(),
+    };
+
+    // sorting is stable, so pushes with equal values keep the order of the
+    // lists.
+    let mut active = records
+        .iter()
+        .filter(|r| r.state == PushState::Active && r.push.days_remaining.is_some())
+        .collect::<Vec<_>>();
+    active.sort_by_key(|r| r.push.days_remaining);
+    let expiring_soon = active.into_iter().take(TOP_PUSHES).map(entry).collect();
+
+    let mut viewed = records
+        .iter()
+        .filter(|r| r.push.views().is_some_and(|views| views > 0))
+        .collect::<Vec<_>>();
+    viewed.sort_by_key(|r| std::cmp::Reverse(r.push.views()));
+    let most_viewed = viewed.into_iter().take(TOP_PUSHES).map(entry).collect();
+
+    Summary {
+        counts,
+        expiring_soon,
+        most_viewed,
+    }
+}
+
+fn format_summary(summary: &Summary, width: Option<usize>) -> String {
+    // the numbers are right-aligned below their headers, whose length
+    // depends on the language.
+    let (active, expired) = (i18n::text("summary-active"), i18n::text("summary-expired"));
+    let (active_width, expired_width) = (active.width() + 2, expired.width() + 2);
+    let mut out = format!(
+        "{:<8}{:>active_width$}{:>expired_width$}\n",
+        i18n::text("summary-pushes"),
+        active,
+        expired
+    );
+    for counts in &summary.counts {
+        out += &format!(
+            "{:<8}{:>active_width$}{:>expired_width$}\n",
+            counts.kind.to_string(),
+            counts.active,
+            counts.expired
+        );
+    }
+
+    let none = format!("  {}\n", i18n::text("summary-none"));
+    out += &format!("\n{}\n", i18n::text("summary-expiring-soon"));
+    if summary.expiring_soon.is_empty() {
+        out += &none;
+    }
+    let mut expiring_soon = entries();
+    for entry in &summary.expiring_soon {
+        let remaining = i18n::format(
+            "summary-remaining",
+            &[
+                ("days", number(entry.days_remaining).into()),
+                ("views", number(entry.views_remaining).into()),
+            ],
+        );
+        expiring_soon.row(cells(entry, remaining));
+    }
+    out += &expiring_soon.render(width);
+
+    out += &format!("\n{}\n", i18n::text("summary-most-viewed"));
+    if summary.most_viewed.is_empty() {
+        out += &none;
+    }
+    let mut most_viewed = entries();
+    for entry in &summary.most_viewed {
+        let views = i18n::format("summary-views", &[("views", number(entry.views).into())]);
+        most_viewed.row(cells(entry, views));
+    }
+    out += &most_viewed.render(width);
+
+    out
+}
+
+/// A table of pushes, indented by an empty first column.
+fn entries() -> Table {
+    Table::new(vec![
+        Column::fixed(),
+        Column::fixed(),
+        Column::fixed(),
+        Column::shrink_to(10),
+        Column::shrink_to(12),
+    ])
+}
+
+fn cells(entry: &SummaryEntry, details: String) -> Vec<String> {
+    let note = match &entry.note {
+        Some(note) if !note.is_empty() => format!("({})", note),
+        _ => String::new(),
+    };
+    vec![
+        String::new(),
+        entry.kind.to_string(),
+        entry.url_token.clone(),
+        details,
+        note,
+    ]
+}
+
+fn number(value: Option<i64>) -> String {
+    value.map_or(String::from("?"), |v| v.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{format_summary, summarize, KindCounts};
+    use crate::args::{PushKind, PushState};
+    use crate::inventory::InventoryRecord;
+    use crate::models::Push;
+
+    fn record(
+        kind: PushKind,
+        state: PushState,
+        url_token: &str,
+        days_remaining: i64,
+        views: (u64, i64),
+    ) -> InventoryRecord {
+        InventoryRecord {
+            kind,
+            state,
+            push: Push {
+                url_token: String::from(url_token),
+                days_remaining: Some(days_remaining),
+                expire_after_views: Some(views.0),
+                views_remaining: Some(views.1),
+                ..Push::default()
+            },
+        }
+    }
+
+    fn records() -> Vec<InventoryRecord> {
+        vec![
+            record(PushKind::Text, PushState::Active, "t1", 5, (5, 5)),
+            record(PushKind::Text, PushState::Active, "t2", 1, (5, 2)),
+            record(PushKind::Text, PushState::Expired, "t3", 0, (3, 0)),
+            record(PushKind::Url, PushState::Active, "u1", 3, (10, 9)),
+        ]
+    }
+
+    #[test]
+    fn summarize_counts_per_kind() {
+        let summary = summarize(&records());
+
+        assert_eq!(
+            summary.counts[0],
+            KindCounts {
+                kind: PushKind::Text,
+                active: 2,
+                expired: 1
+            }
+        );
+        assert_eq!(summary.counts[1].active + summary.counts[1].expired, 0);
+        assert_eq!(summary.counts[2].active, 1);
+    }
+
+    #[test]
+    fn summarize_orders_lists() {
+        let summary = summarize(&records());
+
+        let tokens = |entries: &[super::SummaryEntry]| {
+            entries
+                .iter()
+                .map(|e| e.url_token.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(tokens(&summary.expiring_soon), vec!["t2", "u1", "t1"]);
+        assert_eq!(tokens(&summary.most_viewed), vec!["t2", "t3", "u1"]);
+        assert_eq!(summary.most_viewed[0].views, Some(3));
+    }
+
+    #[test]
+    fn format_empty_summary() {
+        let actual = format_summary(&summarize(&[]), None);
+
+        assert!(actual.starts_with("pushes    active  expired\ntext           0        0\n"));
+        assert!(actual.contains("expiring soon\n  none\n"));
+    }
+}
@@ -0,0 +1,264 @@
+//! Tables of human-readable output, fitted to the width of the terminal.
+//!
+//! `list`, `audit` and `summary` print one row per push or event. Each column
+//! is as wide as its widest cell, so wide terminals show everything. If a row
+//! does not fit on the terminal, columns are narrowed from right to left, as
+//! the most relevant information comes first:
+//!   - the last column, e.g. the note or the user agent, is wrapped onto
+//!     continuation lines below itself, so nothing of it is lost
+//!   - other columns are cut off with `…`, but never below their minimum
+//!     width. Columns that are needed to act on a row, like URL tokens, are
+//!     never cut
+//!
+//! The width is taken from `$COLUMNS` if it is set, otherwise from the
+//! terminal. Output that does not go to a terminal, and output with
+//! `--no-truncate` or `--plain`, is printed at full width.
+
+use std::env;
+use std::io::{self, IsTerminal};
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::args::PPCArgs;
+use crate::platform;
+use crate::render;
+
+const SEPARATOR: &str = "  ";
+
+/// The width to fit tables to, if any.
+pub fn width(args: &PPCArgs) -> Option<usize> {
+    if args.no_truncate || render::is_plain(args) {
+        return None;
+    }
+    if let Some(columns) = env::var("COLUMNS").ok().and_then(|c| c.parse().ok()) {
+        return Some(columns);
+    }
+    if !io::stdout().is_terminal() {
+        return None;
+    }
+    platform::terminal_size().map(|(width, _)| width)
+}
+
+/// How a column may be narrowed.
+#[derive(Debug, Clone, Copy)]
+pub struct Column {
+    min: usize,
+}
+
+impl Column {
+    /// A column that is cut off down to the given width.
+    pub const fn shrink_to(min: usize) -> Self {
+        Self { min }
+    }
+
+    /// A column that is never cut.
+    pub const fn fixed() -> Self {
+        Self { min: usize::MAX }
+    }
+}
+
+/// Rows of cells, rendered with aligned columns.
+pub struct Table {
+    columns: Vec<Column>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(columns: Vec<Column>) -> Self {
+        Self {
+            columns,
+            rows: Vec::new(),
+        }
+    }
+
+    /// Add a row with a cell per column.
+    pub fn row(&mut self, cells: Vec<String>) {
+        debug_assert_eq!(cells.len(), self.columns.len());
+        self.rows.push(cells);
+    }
+
+    /// The rows as lines, fitted to the width if one is given.
+    pub fn render(&self, width: Option<usize>) -> String {
+        let widths = self.widths(width);
+        let last = widths.len().saturating_sub(1);
+
+        let mut out = String::new();
+        for row in &self.rows {
+            let mut line = String::new();
+            for (cell, &w) in row.iter().zip(&widths).take(last) {
+                let cell = truncate(cell, w);
+                line += &cell;
+                line += &" ".repeat(w.saturating_sub(cell.width()));
+                line += SEPARATOR;
+            }
+
+            // the last column is wrapped, with continuation lines indented to
+            // where it starts.
+            let indent = line.width();
+            let wrapped = match row.get(last) {
+                Some(cell) => wrap(cell, widths[last]),
+                None => Vec::new(),
+            };
+            let mut lines = wrapped.into_iter();
+            line += &lines.next().unwrap_or_default();
+            out += line.trim_end();
+            out.push('\n');
+            for continuation in lines {
+                out += &" ".repeat(indent);
+                out += &continuation;
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    /// The width of each column: as wide as its widest cell, unless the rows
+    /// would not fit, see the module docs.
+    fn widths(&self, width: Option<usize>) -> Vec<usize> {
+        let mut widths = (0..self.columns.len())
+            .map(|i| {
+                self.rows
+                    .iter()
+                    .map(|row| row[i].width())
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect::<Vec<_>>();
+        let Some(width) = width else {
+            return widths;
+        };
+
+        let separators = SEPARATOR.len() * widths.len().saturating_sub(1);
+        let mut excess = (widths.iter().sum::<usize>() + separators).saturating_sub(width);
+        for (w, column) in widths.iter_mut().zip(&self.columns).rev() {
+            if excess == 0 {
+                break;
+            }
+            let shrink = w.saturating_sub(column.min).min(excess);
+            *w -= shrink;
+            excess -= shrink;
+        }
+        widths
+    }
+}
+
+/// Fit a single line to the width, e.g. of watch mode, where later lines are
+/// not known yet.
+pub fn fit(line: &str, width: Option<usize>) -> String {
+    match width {
+        Some(width) => truncate(line, width),
+        None => String::from(line),
+    }
+}
+
+/// The text cut off with `…` if it is wider than the width.
+fn truncate(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return String::from(text);
+    }
+    let mut out = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w + 1 > width {
+            break;
+        }
+        out.push(c);
+        used += w;
+    }
+    out.truncate(out.trim_end().len());
+    out.push('…');
+    out
+}
+
+/// The text split into lines of at most the width, at spaces where possible.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split(' ') {
+        let separator = usize::from(!line.is_empty());
+        if !line.is_empty() && line.width() + separator + word.width() > width {
+            lines.push(std::mem::take(&mut line));
+        } else if separator == 1 {
+            line.push(' ');
+        }
+
+        // words that are too long on their own are split anywhere.
+        for c in word.chars() {
+            if line.width() + c.width().unwrap_or(0) > width && !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            line.push(c);
+        }
+    }
+    lines.push(line);
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use super::{fit, wrap, Column, Table};
+
+    fn table() -> Table {
+        let mut table = Table::new(vec![
+            Column::fixed(),
+            Column::shrink_to(6),
+            Column::shrink_to(8),
+        ]);
+        table.row(vec![
+            String::from("abc"),
+            String::from("in 2 days"),
+            String::from("database password of the staging system"),
+        ]);
+        table.row(vec![
+            String::from("defghi"),
+            String::from("today"),
+            String::new(),
+        ]);
+        table
+    }
+
+    #[test]
+    fn wide_tables_are_aligned() {
+        assert_eq!(
+            table().render(None),
+            "abc     in 2 days  database password of the staging system\n\
+             defghi  today\n"
+        );
+        assert_eq!(table().render(Some(200)), table().render(None));
+    }
+
+    #[test]
+    fn narrow_tables_wrap_the_last_column() {
+        assert_eq!(
+            table().render(Some(30)),
+            "abc     in 2 days  database\n\
+             \x20                  password of\n\
+             \x20                  the staging\n\
+             \x20                  system\n\
+             defghi  today\n"
+        );
+    }
+
+    #[test]
+    fn columns_are_cut_down_to_their_minimum() {
+        assert_eq!(
+            table().render(Some(10)),
+            "abc     in 2…   database\n\
+             \x20               password\n\
+             \x20               of the\n\
+             \x20               staging\n\
+             \x20               system\n\
+             defghi  today\n"
+        );
+    }
+
+    #[test]
+    fn long_words_are_split() {
+        assert_eq!(wrap("abcdefgh ij", 3), vec!["abc", "def", "gh", "ij"]);
+        assert_eq!(wrap("", 3), vec![""]);
+        assert_eq!(fit("+ abcdef", Some(5)), "+ ab…");
+        assert_eq!(fit("+ abcdef", None), "+ abcdef");
+    }
+}
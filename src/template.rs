@@ -0,0 +1,120 @@
+//! Expansion of placeholders in push notes.
+//!
+//! Notes may contain placeholders in curly braces that are replaced before the
+//! push is sent, so automated pushes can carry information about where and when
+//! they were created:
+//!   - `{date}`: current local date, e.g. `2023-12-01`
+//!   - `{time}`: current local time, e.g. `14:03:12`
+//!   - `{datetime}`: current local date and time in RFC 3339 format
+//!   - `{hostname}`: name of the machine running the CLI
+//!   - `{user}`: name of the user running the CLI
+//!
+//! `{recipient}` is kept here and replaced per push by `--recipients`, see
+//! `recipients`.
+//!
+//! Anything in braces that is not a known placeholder is kept as it is, so
+//! notes that already contain braces (e.g. snippets of JSON) are not broken.
+
+use std::env;
+
+use chrono::{DateTime, Local, SecondsFormat};
+
+use crate::platform;
+
+/// Values for the placeholders. Separated from the expansion itself so tests do
+/// not depend on the clock or the machine they run on.
+struct Context {
+    now: DateTime<Local>,
+    hostname: String,
+    user: String,
+}
+
+impl Context {
+    fn current() -> Self {
+        Self {
+            now: Local::now(),
+            hostname: platform::hostname(),
+            // USER is set on unix-like systems, USERNAME on windows.
+            user: env::var("USER")
+                .or_else(|_| env::var("USERNAME"))
+                .unwrap_or_default(),
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<String> {
+        match name {
+            "date" => Some(self.now.format("%Y-%m-%d").to_string()),
+            "time" => Some(self.now.format("%H:%M:%S").to_string()),
+            "datetime" => Some(self.now.to_rfc3339_opts(SecondsFormat::Secs, false)),
+            "hostname" => Some(self.hostname.clone()),
+            "user" => Some(self.user.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// Replace all known placeholders in the note with their current values.
+pub fn expand(note: &str) -> String {
+    expand_with(note, &Context::current())
+}
+
+fn expand_with(note: &str, context: &Context) -> String {
+    let mut result = String::with_capacity(note.len());
+    let mut rest = note;
+
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        let candidate = &rest[start..];
+
+        match candidate[1..]
+            .find('}')
+            .and_then(|end| context.lookup(&candidate[1..end + 1]).map(|v| (end, v)))
+        {
+            Some((end, value)) => {
+                result.push_str(&value);
+                rest = &candidate[end + 2..];
+            }
+            None => {
+                result.push('{');
+                rest = &candidate[1..];
+            }
+        }
+    }
+
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Local, TimeZone};
+
+    use super::{expand_with, Context};
+
+    fn context() -> Context {
+        Context {
+            now: Local.with_ymd_and_hms(2023, 12, 1, 14, 3, 12).unwrap(),
+            hostname: String::from("workstation"),
+            user: String::from("alice"),
+        }
+    }
+
+    #[test]
+    fn expand_without_placeholders() {
+        assert_eq!(expand_with("plain note", &context()), "plain note");
+    }
+
+    #[test]
+    fn expand_all_placeholders() {
+        let actual = expand_with("{user}@{hostname} on {date} {time}", &context());
+
+        assert_eq!(actual, "alice@workstation on 2023-12-01 14:03:12");
+    }
+
+    #[test]
+    fn expand_keeps_unknown_placeholders() {
+        let actual = expand_with(r#"{"key": "{user}"} {unknown} {"#, &context());
+
+        assert_eq!(actual, r#"{"key": "alice"} {unknown} {"#);
+    }
+}
@@ -0,0 +1,122 @@
+//! Differences between the terminals of the platforms.
+//!
+//! The console of Windows only interprets ANSI escape sequences, e.g. for
+//! colors, after virtual terminal processing was turned on for it, otherwise
+//! they show up as garbage. It is turned on at start, and consoles that do not
+//! support it (before Windows 10) keep working without escape sequences.
+//!
+//! Hidden input reads from the console directly. Terminals of MSYS2 and Git
+//! Bash (mintty) are no consoles, so hidden input is refused there with a
+//! hint, instead of waiting for input that never arrives.
+//!
+//! Links are copied to the clipboard with an OSC 52 escape sequence, which
+//! most terminal emulators support, also over SSH. The console of Windows does
+//! not, there the link is passed to `clip.exe` instead.
+
+#[cfg(windows)]
+use tracing::debug;
+
+/// Turn on the interpretation of escape sequences where needed, and tell
+/// whether they work. Call once at start, before anything is written.
+pub fn enable_ansi() -> bool {
+    #[cfg(windows)]
+    {
+        use windows_sys::Win32::System::Console::{
+            GetConsoleMode, GetStdHandle, SetConsoleMode, ENABLE_VIRTUAL_TERMINAL_PROCESSING,
+            STD_ERROR_HANDLE, STD_OUTPUT_HANDLE,
+        };
+
+        let mut enabled = true;
+        for handle in [STD_OUTPUT_HANDLE, STD_ERROR_HANDLE] {
+            // SAFETY: the handle is only passed to console functions, which
+            // fail for handles that are no consoles, e.g. redirected output.
+            unsafe {
+                let handle = GetStdHandle(handle);
+                let mut mode = 0;
+                enabled &= GetConsoleMode(handle, &mut mode) != 0
+                    && SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) != 0;
+            }
+        }
+        debug!("ANSI escape sequences enabled: {}", enabled);
+        enabled
+    }
+    #[cfg(not(windows))]
+    true
+}
+
+/// Whether hidden input can be read from the terminal. On Windows, stdin can
+/// look like a terminal without being a console, see the module docs.
+pub fn supports_hidden_input() -> bool {
+    #[cfg(windows)]
+    {
+        use windows_sys::Win32::System::Console::{GetConsoleMode, GetStdHandle, STD_INPUT_HANDLE};
+
+        let mut mode = 0;
+        // SAFETY: see enable_ansi.
+        let console = unsafe { GetConsoleMode(GetStdHandle(STD_INPUT_HANDLE), &mut mode) != 0 };
+        if !console {
+            debug!("stdin is no console, e.g. the terminal of Git Bash");
+        }
+        console
+    }
+    #[cfg(not(windows))]
+    true
+}
+
+/// Copy text to the clipboard of the user.
+#[cfg(feature = "tui")]
+pub fn copy_to_clipboard(text: &str) -> std::io::Result<()> {
+    #[cfg(windows)]
+    {
+        use std::io::Write;
+        use std::process::{Command, Stdio};
+
+        let mut clip = Command::new("clip").stdin(Stdio::piped()).spawn()?;
+        clip.stdin
+            .take()
+            .expect("stdin is piped")
+            .write_all(text.as_bytes())?;
+        let status = clip.wait()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(std::io::Error::other(format!(
+                "clip exited with {}",
+                status
+            )))
+        }
+    }
+    #[cfg(not(windows))]
+    {
+        use std::io::{self, Write};
+
+        use base64::prelude::{Engine, BASE64_STANDARD};
+
+        let mut stdout = io::stdout();
+        write!(stdout, "\x1b]52;c;{}\x07", BASE64_STANDARD.encode(text))?;
+        stdout.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::enable_ansi;
+
+    #[test]
+    #[cfg(windows)]
+    fn redirected_output_is_no_console() {
+        use std::io::{self, IsTerminal};
+
+        // in CI, output goes to a pipe, which must not be an error.
+        let enabled = enable_ansi();
+        if !io::stdout().is_terminal() {
+            assert!(!enabled);
+        }
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn escape_sequences_need_no_setup() {
+        assert!(enable_ansi());
+    }
+}
@@ -0,0 +1,228 @@
+//! Rate limiting of API requests.
+//!
+//! Instances answer with status 429 if an account sends too many requests,
+//! often with a `Retry-After` header telling when to try again. The
+//! `ThrottledTransport` waits as told and retries, until the retry budget
+//! (`--retry-budget`) is used up. Without a `Retry-After` header, the wait
+//! time starts at one second and doubles with every attempt.
+//!
+//! To not get throttled in the first place, `--max-rate` spaces out requests,
+//! which is mostly useful for bulk operations like `import`.
+
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use tracing::{info, warn};
+
+use crate::errors::PPCError;
+use crate::http::{PPCRequest, PPCResponse, Transport};
+use crate::i18n;
+
+const TOO_MANY_REQUESTS: u16 = 429;
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Wraps another transport, limits the rate of requests and retries
+/// rate-limited requests.
+pub struct ThrottledTransport<T: Transport> {
+    inner: T,
+
+    /// Minimum time between the start of two requests.
+    interval: Option<Duration>,
+
+    /// Total time to wait for rate-limited requests.
+    budget: Duration,
+    waited: Mutex<Duration>,
+
+    /// Earliest time the next request may be sent.
+    next_slot: Mutex<Option<Instant>>,
+    sleep: Box<dyn Fn(Duration) + Send + Sync>,
+}
+
+impl<T: Transport> ThrottledTransport<T> {
+    pub fn new(inner: T, max_rate: Option<f64>, budget: Duration) -> Self {
+        Self {
+            inner,
+            interval: max_rate.map(|rate| Duration::from_secs_f64(1.0 / rate)),
+            budget,
+            waited: Mutex::new(Duration::ZERO),
+            next_slot: Mutex::new(None),
+            sleep: Box::new(thread::sleep),
+        }
+    }
+
+    /// Replace the actual sleeping, so tests do not have to wait.
+    #[cfg(test)]
+    fn with_sleep(mut self, sleep: impl Fn(Duration) + Send + Sync + 'static) -> Self {
+        self.sleep = Box::new(sleep);
+        self
+    }
+
+    /// Wait until the next request may be sent according to `--max-rate`.
+    /// Concurrent requests each reserve their own slot, so the rate holds
+    /// across threads.
+    fn pace(&self) {
+        let Some(interval) = self.interval else {
+            return;
+        };
+
+        let now = Instant::now();
+        let slot = {
+            let mut next_slot = self.next_slot.lock().unwrap();
+            let slot = next_slot.map_or(now, |next| next.max(now));
+            *next_slot = Some(slot + interval);
+            slot
+        };
+
+        if slot > now {
+            (self.sleep)(slot - now);
+        }
+    }
+}
+
+impl<T: Transport> Transport for ThrottledTransport<T> {
+    fn send(&self, request: &PPCRequest) -> Result<PPCResponse, PPCError> {
+        let mut backoff = INITIAL_BACKOFF;
+
+        loop {
+            self.pace();
+            let response = self.inner.send(request)?;
+            if response.status != TOO_MANY_REQUESTS {
+                return Ok(response);
+            }
+
+            let wait = response
+                .header("Retry-After")
+                .and_then(|value| retry_after(value, Utc::now()))
+                .unwrap_or(backoff);
+            {
+                let mut waited = self.waited.lock().unwrap();
+                if wait > self.budget.saturating_sub(*waited) {
+                    warn!(
+                        "{}",
+                        i18n::format(
+                            "retry-budget-used",
+                            &[("seconds", self.budget.as_secs().into())]
+                        )
+                    );
+                    return Ok(response);
+                }
+                *waited += wait;
+            }
+
+            info!(
+                "rate limited by the instance, retrying in {}s",
+                wait.as_secs_f64()
+            );
+            (self.sleep)(wait);
+            backoff *= 2;
+        }
+    }
+}
+
+/// Parse a `Retry-After` header, which is either a number of seconds or an
+/// HTTP date.
+fn retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    // dates in the past mean that the request can be retried right away.
+    Some(
+        (date.with_timezone(&Utc) - now)
+            .to_std()
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use chrono::{TimeZone, Utc};
+
+    use super::{retry_after, ThrottledTransport};
+    use crate::http::mock::MockTransport;
+    use crate::http::{Method, PPCRequest, Transport};
+
+    fn request() -> PPCRequest {
+        PPCRequest::new(
+            Method::Get,
+            String::from("https://pwpush.com/p/active.json"),
+        )
+    }
+
+    fn recording_sleep() -> (Arc<Mutex<Vec<Duration>>>, impl Fn(Duration) + Send + Sync) {
+        let sleeps = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&sleeps);
+        (sleeps, move |d| recorded.lock().unwrap().push(d))
+    }
+
+    #[test]
+    fn parse_retry_after() {
+        let now = Utc.with_ymd_and_hms(2015, 10, 21, 7, 28, 0).unwrap();
+
+        assert_eq!(retry_after("120", now), Some(Duration::from_secs(120)));
+        assert_eq!(
+            retry_after("Wed, 21 Oct 2015 07:28:30 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn retries_rate_limited_requests() {
+        let (sleeps, sleep) = recording_sleep();
+        let inner = MockTransport::new()
+            .respond_with_headers(429, &[("retry-after", "3")], "")
+            .respond(429, "")
+            .respond(200, "[]");
+        let transport =
+            ThrottledTransport::new(inner, None, Duration::from_secs(60)).with_sleep(sleep);
+
+        let response = transport.send(&request()).unwrap();
+
+        assert_eq!(response.status, 200);
+        assert_eq!(
+            *sleeps.lock().unwrap(),
+            vec![Duration::from_secs(3), Duration::from_secs(2)]
+        );
+    }
+
+    #[test]
+    fn gives_up_when_budget_is_used_up() {
+        let (sleeps, sleep) = recording_sleep();
+        let inner = MockTransport::new()
+            .respond_with_headers(429, &[("Retry-After", "5")], "")
+            .respond_with_headers(429, &[("Retry-After", "10")], "");
+        let transport =
+            ThrottledTransport::new(inner, None, Duration::from_secs(10)).with_sleep(sleep);
+
+        let response = transport.send(&request()).unwrap();
+
+        assert_eq!(response.status, 429);
+        assert_eq!(*sleeps.lock().unwrap(), vec![Duration::from_secs(5)]);
+    }
+
+    #[test]
+    fn max_rate_spaces_out_requests() {
+        let (sleeps, sleep) = recording_sleep();
+        let inner = MockTransport::new().respond(200, "[]").respond(200, "[]");
+        let transport = ThrottledTransport::new(inner, Some(2.0), Duration::ZERO).with_sleep(sleep);
+
+        transport.send(&request()).unwrap();
+        transport.send(&request()).unwrap();
+
+        let sleeps = sleeps.lock().unwrap();
+        assert_eq!(sleeps.len(), 1);
+        assert!(sleeps[0] > Duration::from_millis(400) && sleeps[0] <= Duration::from_millis(500));
+    }
+}
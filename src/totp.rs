@@ -0,0 +1,147 @@
+//! Pushing the seeds of TOTP second factors.
+//!
+//! During onboarding, admins enroll second factors for shared or service
+//! accounts, and have to hand the seed to whoever uses the account. `push
+//! totp --issuer <ISSUER> --account <ACCOUNT>` builds the `otpauth://` URI
+//! that authenticator apps import, e.g.
+//!
+//! ```text
+//! otpauth://totp/Example%20Corp:alice%40example.com?secret=JBSWY3DPEHPK3PXP&issuer=Example%20Corp
+//! ```
+//!
+//! and pushes it as text, see `structured`. With `--qr`, the recipient sees
+//! the URI as QR code and adds it by scanning. `algorithm`, `digits` and
+//! `period` are only part of the URI if they differ from the defaults, as some
+//! apps ignore them and show wrong codes for anything else.
+//!
+//! Services show the secret in base32, often in groups and in lower case, so
+//! spaces and dashes are removed and letters are made upper case.
+
+use secrecy::{ExposeSecret, SecretString};
+use zeroize::Zeroizing;
+
+use crate::args::{PPCText, PPCTotp, TotpAlgorithm};
+use crate::config::PPCConfig;
+use crate::errors::PPCError;
+use crate::structured;
+
+/// The text push with the `otpauth://` URI as payload.
+pub fn text(ppc_totp: &PPCTotp, config: &PPCConfig) -> Result<PPCText, PPCError> {
+    if ppc_totp.issuer.contains(':') {
+        return Err(PPCError::from(
+            "the issuer must not contain a colon, which separates it from the account",
+        ));
+    }
+    let secret = structured::read_option(
+        "--secret",
+        ppc_totp.secret.as_ref(),
+        ppc_totp.stdin,
+        &ppc_totp.push,
+        "Shared secret (base32): ",
+        config,
+    )?;
+    let secret = normalize(secret.expose_secret())?;
+
+    Ok(structured::text(uri(ppc_totp, &secret), &ppc_totp.push))
+}
+
+/// The secret without grouping, in upper case, and without padding. Fails if
+/// it is not base32.
+fn normalize(secret: &str) -> Result<SecretString, PPCError> {
+    let normalized = Zeroizing::new(
+        secret
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '-')
+            .map(|c| c.to_ascii_uppercase())
+            .collect::<String>(),
+    );
+    let normalized = normalized.trim_end_matches('=');
+    if normalized.is_empty()
+        || !normalized
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || ('2'..='7').contains(&c))
+    {
+        return Err(PPCError::from(
+            "the secret is not base32, services show it as letters and digits from 2 to 7",
+        ));
+    }
+    Ok(SecretString::from(normalized))
+}
+
+/// The `otpauth://` URI of the second factor, see the module docs.
+fn uri(ppc_totp: &PPCTotp, secret: &SecretString) -> SecretString {
+    let issuer = urlencoding::encode(&ppc_totp.issuer);
+    let mut uri = Zeroizing::new(format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}",
+        issuer,
+        urlencoding::encode(&ppc_totp.account),
+        secret.expose_secret(),
+        issuer
+    ));
+    match ppc_totp.algorithm {
+        TotpAlgorithm::Sha1 => {}
+        TotpAlgorithm::Sha256 => uri.push_str("&algorithm=SHA256"),
+        TotpAlgorithm::Sha512 => uri.push_str("&algorithm=SHA512"),
+    }
+    if ppc_totp.digits != 6 {
+        uri.push_str(&format!("&digits={}", ppc_totp.digits));
+    }
+    if ppc_totp.period != 30 {
+        uri.push_str(&format!("&period={}", ppc_totp.period));
+    }
+    SecretString::from(uri.as_str())
+}
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+    use secrecy::{ExposeSecret, SecretString};
+
+    use super::{normalize, uri};
+    use crate::args::{PPCAction, PPCArgs, PPCObject, PPCTotp};
+
+    fn totp(cmdline: &[&str]) -> PPCTotp {
+        let argv = [&["pwpush-cli", "push", "totp"], cmdline].concat();
+        match PPCArgs::parse_from(argv).action {
+            PPCAction::Push(PPCObject::Totp(totp)) => *totp,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn uris_only_name_options_that_differ_from_the_defaults() {
+        let secret = SecretString::from("JBSWY3DPEHPK3PXP");
+        let defaults = totp(&["--issuer", "Example Corp", "--account", "alice@example.com"]);
+        let custom = totp(&[
+            "--issuer",
+            "ACME",
+            "--account",
+            "ops",
+            "--algorithm",
+            "sha256",
+            "--digits",
+            "8",
+            "--period",
+            "60",
+        ]);
+
+        assert_eq!(
+            uri(&defaults, &secret).expose_secret(),
+            "otpauth://totp/Example%20Corp:alice%40example.com?secret=JBSWY3DPEHPK3PXP&issuer=Example%20Corp"
+        );
+        assert_eq!(
+            uri(&custom, &secret).expose_secret(),
+            "otpauth://totp/ACME:ops?secret=JBSWY3DPEHPK3PXP&issuer=ACME&algorithm=SHA256&digits=8&period=60"
+        );
+    }
+
+    #[test]
+    fn secrets_are_normalized_to_base32() {
+        assert_eq!(
+            normalize("jbsw y3dp-ehpk 3pxp==").unwrap().expose_secret(),
+            "JBSWY3DPEHPK3PXP"
+        );
+        assert!(normalize("JBSWY3DPEHPK3PX1").is_err());
+        assert!(normalize(" ").is_err());
+    }
+}
@@ -0,0 +1,101 @@
+//! Trace IDs, to find the requests of one invocation in logs.
+//!
+//! Each invocation of the CLI gets a short random trace ID, e.g. `3f9a0c1e`.
+//! It is part of every log line, in text and JSON, see `logging`, so the lines
+//! of one run can be told apart in a shared log file.
+//!
+//! With `--request-id`, every request to the instance also carries an
+//! `X-Request-Id` header made of the trace ID and a running number, e.g.
+//! `3f9a0c1e-2`. Password Pusher, like most Rails applications, logs that ID
+//! with each request, and reverse proxies can log it as well. Errors then show
+//! the trace ID, so a failure on a self-hosted instance can be looked up in
+//! its logs by searching for it. The header is off by default, as it tells
+//! the instance which requests came from the same run.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+use rand::Rng;
+use zeroize::Zeroize;
+
+use crate::errors::PPCError;
+use crate::http::{PPCRequest, PPCResponse, Transport};
+
+const HEADER: &str = "X-Request-Id";
+
+static TRACE_ID: OnceLock<String> = OnceLock::new();
+
+/// The trace ID of this invocation.
+pub fn id() -> &'static str {
+    TRACE_ID.get_or_init(|| format!("{:08x}", rand::thread_rng().gen::<u32>()))
+}
+
+/// Wraps another transport and sets the X-Request-Id of every request if
+/// `--request-id` was given, like `user_agent::UserAgentTransport`.
+pub struct RequestIdTransport<T: Transport> {
+    inner: T,
+    enabled: bool,
+    count: AtomicUsize,
+}
+
+impl<T: Transport> RequestIdTransport<T> {
+    pub fn new(inner: T, enabled: bool) -> Self {
+        Self {
+            inner,
+            enabled,
+            count: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl<T: Transport> Transport for RequestIdTransport<T> {
+    fn send(&self, request: &PPCRequest) -> Result<PPCResponse, PPCError> {
+        if !self.enabled {
+            return self.inner.send(request);
+        }
+        let number = self.count.fetch_add(1, Ordering::Relaxed) + 1;
+        let mut request = request.clone();
+        request
+            .headers
+            .retain(|(name, _)| !name.eq_ignore_ascii_case(HEADER));
+        request
+            .headers
+            .push((String::from(HEADER), format!("{}-{}", id(), number)));
+
+        let response = self.inner.send(&request);
+        // the copy may contain the payload, see `pwpush_api::send_text_push`.
+        request.body.zeroize();
+        response
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{id, RequestIdTransport};
+    use crate::http::mock::MockTransport;
+    use crate::http::{Method, PPCRequest, Transport};
+
+    #[test]
+    fn requests_are_numbered_within_the_trace() {
+        let transport = RequestIdTransport::new(
+            MockTransport::new().respond(200, "{}").respond(200, "{}"),
+            true,
+        );
+        let request = PPCRequest::new(Method::Get, String::from("https://pwpush.com/"));
+        transport.send(&request).unwrap();
+        transport.send(&request).unwrap();
+
+        let ids = transport
+            .inner
+            .requests()
+            .iter()
+            .map(|request| request.headers[0].1.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(id().len(), 8);
+        assert_eq!(ids, [format!("{}-1", id()), format!("{}-2", id())]);
+
+        let disabled = RequestIdTransport::new(MockTransport::new().respond(200, "{}"), false);
+        disabled.send(&request).unwrap();
+        assert!(disabled.inner.requests()[0].headers.is_empty());
+    }
+}
@@ -0,0 +1,288 @@
+//! Transport over a Unix domain socket.
+//!
+//! Local instances, e.g. a Password Pusher container on the same machine, are
+//! often exposed through a reverse proxy that listens on a Unix domain socket
+//! instead of a TCP port. With `--unix-socket <path>`, all requests are sent
+//! to that socket instead. The instance URL is still used for the `Host`
+//! header and the request path, so path prefixes keep working.
+//!
+//! reqwest cannot connect to Unix domain sockets, so this transport speaks
+//! plain HTTP/1.1 on its own. Every request uses a new connection, which is
+//! closed by the server after the response. As the socket is local, there is
+//! no TLS, and the protocol of the instance is ignored. Responses are read
+//! into memory as a whole, also those of downloads.
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+
+use tracing::{debug, debug_span};
+use url::Url;
+use zeroize::Zeroizing;
+
+use crate::errors::PPCError;
+use crate::http::{PPCRequest, PPCResponse, Transport};
+use crate::redact::sanitize_url;
+
+/// Maximum number of response headers that are parsed.
+const MAX_HEADERS: usize = 64;
+
+/// Sends requests to an HTTP server listening on a Unix domain socket.
+pub struct UnixSocketTransport {
+    path: PathBuf,
+}
+
+impl UnixSocketTransport {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+}
+
+impl Transport for UnixSocketTransport {
+    fn send(&self, request: &PPCRequest) -> Result<PPCResponse, PPCError> {
+        let _span = debug_span!(
+            "request",
+            method = %request.method,
+            url = %sanitize_url(&request.url),
+            socket = %self.path.display(),
+        )
+        .entered();
+        debug!(
+            "sending {} request to {} via {}",
+            request.method,
+            sanitize_url(&request.url),
+            self.path.display()
+        );
+
+        let message = serialize(request)?;
+        let mut stream = UnixStream::connect(&self.path).map_err(|e| {
+            PPCError::from(format!(
+                "could not connect to {}: {}",
+                self.path.display(),
+                e
+            ))
+        })?;
+        stream
+            .write_all(&message)
+            .map_err(|e| PPCError::from(format!("could not send request: {}", e)))?;
+        if let Some(multipart) = &request.multipart {
+            io::copy(&mut multipart.reader(), &mut stream)
+                .map_err(|e| PPCError::from(format!("could not send request: {}", e)))?;
+        }
+
+        let mut raw = Zeroizing::new(Vec::new());
+        stream
+            .read_to_end(&mut raw)
+            .map_err(|e| PPCError::from(format!("could not read response: {}", e)))?;
+
+        let response = parse_response(&raw, request.download.as_deref())?;
+        debug!(
+            status = response.status,
+            "received response with status {}", response.status
+        );
+        Ok(response)
+    }
+}
+
+/// The request as HTTP/1.1 message. It contains the payload, so it is wiped
+/// after use.
+fn serialize(request: &PPCRequest) -> Result<Zeroizing<Vec<u8>>, PPCError> {
+    let url = Url::parse(&request.url)
+        .map_err(|e| PPCError::from(format!("invalid request URL: {}", e)))?;
+    let host = match (url.host_str(), url.port()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => String::from(host),
+        (None, _) => return Err(PPCError::from("request URL has no host")),
+    };
+    let target = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => String::from(url.path()),
+    };
+
+    let mut head = Zeroizing::new(format!(
+        "{} {} HTTP/1.1\r\nHost: {}\r\nAccept: */*\r\nConnection: close\r\n",
+        request.method, target, host
+    ));
+    for (name, value) in &request.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    if let Some(body) = &request.body {
+        head.push_str(&format!("Content-Length: {}\r\n", body.len()));
+    }
+    // the files of uploads are streamed after the head, see `send`.
+    if let Some(multipart) = &request.multipart {
+        head.push_str(&format!(
+            "Content-Type: {}\r\nContent-Length: {}\r\n",
+            multipart.content_type(),
+            multipart.content_length()
+        ));
+    }
+    head.push_str("\r\n");
+
+    let mut message = Zeroizing::new(head.as_bytes().to_vec());
+    if let Some(body) = &request.body {
+        message.extend_from_slice(body.as_bytes());
+    }
+    Ok(message)
+}
+
+/// Parse the response. The body of a successful response is written to
+/// `download` instead, if given.
+fn parse_response(raw: &[u8], download: Option<&Path>) -> Result<PPCResponse, PPCError> {
+    let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+    let mut response = httparse::Response::new(&mut headers);
+    let head_length = match response.parse(raw) {
+        Ok(httparse::Status::Complete(length)) => length,
+        Ok(httparse::Status::Partial) => {
+            return Err(PPCError::from(
+                "connection closed before the response was complete",
+            ))
+        }
+        Err(e) => return Err(PPCError::from(format!("invalid response: {}", e))),
+    };
+
+    // the parser only completes with a status code.
+    let status = response.code.unwrap_or_default();
+    let headers = response
+        .headers
+        .iter()
+        .map(|header| {
+            (
+                String::from(header.name),
+                String::from_utf8_lossy(header.value).into_owned(),
+            )
+        })
+        .collect::<Vec<_>>();
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    };
+
+    let rest = &raw[head_length..];
+    let body = if header("Transfer-Encoding").is_some_and(|v| v.eq_ignore_ascii_case("chunked")) {
+        dechunk(rest)?
+    } else {
+        match header("Content-Length").and_then(|v| v.trim().parse::<usize>().ok()) {
+            Some(length) if length <= rest.len() => rest[..length].to_vec(),
+            Some(_) => {
+                return Err(PPCError::from(
+                    "connection closed before the response was complete",
+                ))
+            }
+            None => rest.to_vec(),
+        }
+    };
+
+    let body = match download {
+        Some(path) if (200..300).contains(&status) => {
+            fs::write(path, &body).map_err(|e| {
+                PPCError::from(format!("could not download to {}: {}", path.display(), e))
+            })?;
+            String::new()
+        }
+        _ => String::from_utf8_lossy(&body).into_owned(),
+    };
+
+    Ok(PPCResponse {
+        status,
+        body,
+        headers,
+        not_modified: false,
+    })
+}
+
+/// Decode a body with chunked transfer encoding. Trailers are ignored.
+fn dechunk(mut data: &[u8]) -> Result<Vec<u8>, PPCError> {
+    let invalid = || PPCError::from("invalid chunked response body");
+    let mut body = Vec::new();
+
+    loop {
+        let line_end = data
+            .windows(2)
+            .position(|w| w == b"\r\n")
+            .ok_or_else(invalid)?;
+        let size_line = std::str::from_utf8(&data[..line_end]).map_err(|_| invalid())?;
+        // chunk extensions after `;` carry no meaning for us.
+        let size_hex = size_line.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size_hex, 16).map_err(|_| invalid())?;
+        data = &data[line_end + 2..];
+
+        if size == 0 {
+            return Ok(body);
+        }
+        if data.len() < size + 2 {
+            return Err(invalid());
+        }
+        body.extend_from_slice(&data[..size]);
+        data = &data[size + 2..];
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixListener;
+    use std::thread;
+
+    use super::{dechunk, UnixSocketTransport};
+    use crate::http::{Method, PPCRequest, Transport};
+
+    #[test]
+    fn dechunk_body() {
+        assert_eq!(
+            dechunk(b"4\r\n{\"a\"\r\n3;ext=1\r\n:1}\r\n0\r\n\r\n").unwrap(),
+            b"{\"a\":1}"
+        );
+        assert!(dechunk(b"zz\r\n").is_err());
+        assert!(dechunk(b"10\r\nshort\r\n").is_err());
+    }
+
+    #[test]
+    fn request_roundtrip_over_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pwpush.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 1024];
+            // the request is complete once the body of known length arrived.
+            while !String::from_utf8_lossy(&request).ends_with("password[payload]=secret") {
+                let read = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..read]);
+            }
+            stream
+                .write_all(
+                    b"HTTP/1.1 201 Created\r\nContent-Type: application/json\r\n\
+                      Transfer-Encoding: chunked\r\n\r\n\
+                      10\r\n{\"url_token\":\"ab\r\n3\r\nc\"}\r\n0\r\n\r\n",
+                )
+                .unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        let transport = UnixSocketTransport::new(&path);
+        let request = PPCRequest::new(
+            Method::Post,
+            String::from("https://localhost:5100/pwpush/p.json"),
+        )
+        .header("X-User-Token", "t0k3n")
+        .body(String::from("password[payload]=secret"));
+
+        let response = transport.send(&request).unwrap();
+        let received = server.join().unwrap();
+
+        assert!(received.starts_with("POST /pwpush/p.json HTTP/1.1\r\nHost: localhost:5100\r\n"));
+        assert!(received.contains("X-User-Token: t0k3n\r\n"));
+        assert!(received.contains("Content-Length: 24\r\n"));
+        assert_eq!(response.status, 201);
+        assert_eq!(response.header("content-type"), Some("application/json"));
+        assert_eq!(response.body, r#"{"url_token":"abc"}"#);
+    }
+}
@@ -0,0 +1,176 @@
+//! Changing pushes of the account.
+//!
+//! `update <url_token>` changes the note, whether viewers may delete the push
+//! and the retrieval step of an existing push, without creating a new link
+//! like `rotate` does. Only attributes given on the command line are sent.
+//!
+//! Not all instances allow editing pushes. Whether one does is found out from
+//! the answer: instances without the endpoint answer 405 or 501, or 404 like
+//! for unknown pushes. A 404 for a push that is in the lists of the account
+//! therefore means the instance cannot change it, and fails with an error
+//! that says so instead of claiming that the push does not exist.
+
+use crate::args::{PPCArgs, PPCUpdate};
+use crate::errors::{PPCError, EXIT_UNAUTHORIZED};
+use crate::http::Transport;
+use crate::instance::Instance;
+use crate::models::Push;
+use crate::pwpush_api::{self, Update};
+use crate::{i18n, info, output};
+
+/// Entry point for the `update` command.
+pub fn run(
+    transport: &dyn Transport,
+    instance: &Instance,
+    args: &PPCArgs,
+    ppc_update: &PPCUpdate,
+) -> Result<(), PPCError> {
+    let push = update(transport, instance, ppc_update)?;
+
+    if args.json_output {
+        output::print_json(&push);
+    } else {
+        println!(
+            "{}",
+            i18n::format(
+                "update-done",
+                &[
+                    ("kind", ppc_update.kind.to_string().into()),
+                    ("url_token", push.url_token.as_str().into())
+                ]
+            )
+        );
+    }
+    Ok(())
+}
+
+/// Change the push, and tell instances that cannot do it apart from unknown
+/// pushes.
+pub fn update(
+    transport: &dyn Transport,
+    instance: &Instance,
+    ppc_update: &PPCUpdate,
+) -> Result<Push, PPCError> {
+    if instance.token.is_none() {
+        return Err(PPCError::with_exit_code(
+            String::from("no credentials given, use --email and --token or a profile with both"),
+            EXIT_UNAUTHORIZED,
+        ));
+    }
+
+    match pwpush_api::update(transport, instance, ppc_update)? {
+        Update::Push(push) => Ok(*push),
+        Update::Unsupported => Err(unsupported(instance)),
+        Update::NotFound => {
+            info::lookup(transport, instance, ppc_update.kind, &ppc_update.url_token)?;
+            Err(unsupported(instance))
+        }
+    }
+}
+
+fn unsupported(instance: &Instance) -> PPCError {
+    PPCError::from(format!(
+        "{} does not support changing pushes",
+        instance.base_url()
+    ))
+    .with_hint(String::from(
+        "change the push in the web interface, or use `rotate` to replace it by a new push",
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::update;
+    use crate::args::{AuthScheme, InstanceProtocol, PPCUpdate, PushKind};
+    use crate::errors::EXIT_UNAUTHORIZED;
+    use crate::http::mock::MockTransport;
+    use crate::http::Method;
+    use crate::instance::Instance;
+
+    fn instance() -> Instance {
+        Instance {
+            protocol: InstanceProtocol::Https,
+            url: String::from("pwpush.example.com"),
+            email: Some(String::from("user@example.com")),
+            token: Some("t0k3n".into()),
+            auth_scheme: Some(AuthScheme::HeaderPair),
+        }
+    }
+
+    fn ppc_update() -> PPCUpdate {
+        PPCUpdate {
+            url_token: String::from("abc"),
+            kind: PushKind::Text,
+            note: None,
+            deletable_by_viewer: Some(false),
+            retrieval_step: None,
+        }
+    }
+
+    #[test]
+    fn changes_the_push() {
+        let transport =
+            MockTransport::new().respond(200, r#"{"url_token":"abc","deletable_by_viewer":false}"#);
+
+        let push = update(&transport, &instance(), &ppc_update()).unwrap();
+
+        assert_eq!(push.deletable_by_viewer, Some(false));
+        let requests = transport.requests();
+        assert_eq!(requests[0].method, Method::Patch);
+        assert_eq!(requests[0].url, "https://pwpush.example.com/p/abc.json");
+        assert_eq!(
+            requests[0].body.as_deref(),
+            Some("password[deletable_by_viewer]=false")
+        );
+    }
+
+    #[test]
+    fn method_not_allowed_is_unsupported() {
+        let transport = MockTransport::new().respond(405, "");
+
+        let err = update(&transport, &instance(), &ppc_update()).unwrap_err();
+
+        assert!(err
+            .to_string()
+            .contains("https://pwpush.example.com does not support changing pushes"));
+        assert_eq!(transport.requests().len(), 1);
+    }
+
+    #[test]
+    fn not_found_for_known_push_is_unsupported() {
+        let transport = MockTransport::new()
+            .respond(404, "<html>Not Found</html>")
+            .respond(200, r#"[{"url_token":"abc"}]"#);
+
+        let err = update(&transport, &instance(), &ppc_update()).unwrap_err();
+
+        assert!(err.to_string().contains("does not support changing pushes"));
+    }
+
+    #[test]
+    fn not_found_for_unknown_push() {
+        let transport = MockTransport::new()
+            .respond(404, "")
+            .respond(200, "[]")
+            .respond(200, "[]");
+
+        let err = update(&transport, &instance(), &ppc_update()).unwrap_err();
+
+        assert!(err
+            .to_string()
+            .contains("text push abc was not found in the pushes of the account"));
+    }
+
+    #[test]
+    fn requires_authentication() {
+        let anonymous = Instance {
+            email: None,
+            token: None,
+            ..instance()
+        };
+
+        let err = update(&MockTransport::new(), &anonymous, &ppc_update()).unwrap_err();
+
+        assert_eq!(err.code(), EXIT_UNAUTHORIZED);
+    }
+}
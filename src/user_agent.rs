@@ -0,0 +1,84 @@
+//! The User-Agent header of requests.
+//!
+//! Requests identify themselves as `pwpush-cli/<version>`, so the operators of
+//! self-hosted instances can tell them apart in their logs, and filter or
+//! allow them in front of the instance. A different value can be given with
+//! `--user-agent`, e.g. for deployments that only let through known clients.
+//!
+//! The header carries the version and nothing else. pwpush-cli sends no
+//! telemetry: requests only go to the instance, and to the password managers
+//! and webhooks the user configured.
+
+use zeroize::Zeroize;
+
+use crate::errors::PPCError;
+use crate::http::{PPCRequest, PPCResponse, Transport};
+use crate::version::VERSION;
+
+const HEADER: &str = "User-Agent";
+
+/// The User-Agent unless `--user-agent` is given.
+pub fn default() -> String {
+    format!("pwpush-cli/{}", VERSION)
+}
+
+/// Wraps another transport and sets the User-Agent of every request. It is
+/// added as an explicit header, so it also shows up in recordings, and is
+/// sent to Unix sockets as well.
+pub struct UserAgentTransport<T: Transport> {
+    inner: T,
+    user_agent: String,
+}
+
+impl<T: Transport> UserAgentTransport<T> {
+    pub fn new(inner: T, user_agent: Option<&str>) -> Self {
+        Self {
+            inner,
+            user_agent: user_agent.map(String::from).unwrap_or_else(default),
+        }
+    }
+}
+
+impl<T: Transport> Transport for UserAgentTransport<T> {
+    fn send(&self, request: &PPCRequest) -> Result<PPCResponse, PPCError> {
+        let mut request = request.clone();
+        request
+            .headers
+            .retain(|(name, _)| !name.eq_ignore_ascii_case(HEADER));
+        request
+            .headers
+            .push((String::from(HEADER), self.user_agent.clone()));
+
+        let response = self.inner.send(&request);
+        // the copy may contain the payload, see `pwpush_api::send_text_push`.
+        request.body.zeroize();
+        response
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::UserAgentTransport;
+    use crate::http::mock::MockTransport;
+    use crate::http::{Method, PPCRequest, Transport};
+
+    fn user_agent(requested: Option<&str>) -> String {
+        let transport = UserAgentTransport::new(MockTransport::new().respond(200, "{}"), requested);
+        transport
+            .send(&PPCRequest::new(
+                Method::Get,
+                String::from("https://pwpush.com/"),
+            ))
+            .unwrap();
+
+        let headers = transport.inner.requests()[0].headers.clone();
+        assert_eq!(headers.len(), 1);
+        headers[0].1.clone()
+    }
+
+    #[test]
+    fn sets_user_agent() {
+        assert!(user_agent(None).starts_with("pwpush-cli/"));
+        assert_eq!(user_agent(Some("internal-tool/1.0")), "internal-tool/1.0");
+    }
+}
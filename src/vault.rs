@@ -0,0 +1,357 @@
+//! Reading and writing secrets in HashiCorp Vault.
+//!
+//! `push text --from-vault <path>#<key>` pushes a value from Vault, and
+//! `get --to-vault <path>#<key>` stores a retrieved payload in Vault, so a
+//! secret can be handed from one team's Vault to another's without ever being
+//! shown. The address and token are taken from `VAULT_ADDR` and `VAULT_TOKEN`
+//! like the vault CLI does, and `VAULT_NAMESPACE` is honored as well.
+//!
+//! The path is used as given in the HTTP API. For the KV version 2 engine,
+//! this includes `data`, e.g. `secret/data/db#password`. Such paths are
+//! recognized, and the value is read from and written to the nested `data`
+//! object accordingly. Writing keeps the other keys of the secret, like
+//! `vault kv patch`: the secret is read first, so the token needs to be
+//! allowed to read it too. For KV version 2, it is written back with
+//! check-and-set, so a change in between fails instead of being overwritten.
+//!
+//! Requests to Vault do not go through the transport of the instance, so they
+//! are neither recorded nor throttled.
+
+use std::env;
+use std::fmt::{self, Display};
+
+use secrecy::{ExposeSecret, SecretString};
+use serde_json::{json, Map, Value};
+use tracing::debug;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::api_url;
+use crate::errors::PPCError;
+use crate::http::{Method, PPCRequest, Transport};
+
+/// A single value in Vault, given as `<path>#<key>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretRef {
+    pub path: String,
+    pub key: String,
+}
+
+impl SecretRef {
+    /// KV version 2 keeps the values in a nested `data` object.
+    fn is_kv2(&self) -> bool {
+        self.path.split('/').nth(1) == Some("data")
+    }
+}
+
+impl Display for SecretRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}#{}", self.path, self.key)
+    }
+}
+
+/// Parse a secret reference. Used as value parser for clap, hence the plain
+/// string as error.
+pub fn parse_ref(value: &str) -> Result<SecretRef, String> {
+    match value.rsplit_once('#') {
+        Some((path, key)) if !path.trim_matches('/').is_empty() && !key.is_empty() => {
+            Ok(SecretRef {
+                path: String::from(path.trim_matches('/')),
+                key: String::from(key),
+            })
+        }
+        _ => Err(format!(
+            "`{}` is not a Vault reference like secret/data/db#password",
+            value
+        )),
+    }
+}
+
+pub struct Vault {
+    addr: String,
+    token: SecretString,
+    namespace: Option<String>,
+}
+
+impl Vault {
+    pub fn from_env() -> Result<Self, PPCError> {
+        let addr = env::var("VAULT_ADDR")
+            .map_err(|_| PPCError::from("VAULT_ADDR is not set, it is needed to reach Vault"))?;
+        let token = env::var("VAULT_TOKEN")
+            .map_err(|_| PPCError::from("VAULT_TOKEN is not set, log in with `vault login`"))?;
+
+        Ok(Self {
+            addr,
+            token: SecretString::from(token),
+            namespace: env::var("VAULT_NAMESPACE").ok(),
+        })
+    }
+
+    /// Read the value of `secret`. Only string values can be pushed.
+    pub fn read(
+        &self,
+        transport: &dyn Transport,
+        secret: &SecretRef,
+    ) -> Result<SecretString, PPCError> {
+        debug!("reading {} from vault", secret);
+        let response = transport.send(&self.request(Method::Get, secret))?;
+        let body = Zeroizing::new(response.body);
+        if !(200..300).contains(&response.status) {
+            return Err(PPCError::from(format!(
+                "Vault responded to reading {} with status {}",
+                secret.path, response.status
+            )));
+        }
+
+        let value = serde_json::from_str::<Value>(&body)
+            .map_err(|e| PPCError::from(format!("unexpected response from Vault: {}", e)))?;
+        let data = if secret.is_kv2() {
+            &value["data"]["data"]
+        } else {
+            &value["data"]
+        };
+
+        match &data[&secret.key] {
+            Value::String(text) => Ok(SecretString::from(text.as_str())),
+            Value::Null => Err(PPCError::from(format!(
+                "{} has no key {}",
+                secret.path, secret.key
+            ))),
+            _ => Err(PPCError::from(format!(
+                "{} is not a string, it cannot be pushed",
+                secret
+            ))),
+        }
+    }
+
+    /// Store `value` under `secret`, keeping the other keys of the secret, see
+    /// the module docs.
+    pub fn write(
+        &self,
+        transport: &dyn Transport,
+        secret: &SecretRef,
+        value: &str,
+    ) -> Result<(), PPCError> {
+        debug!("writing {} to vault", secret);
+        let (mut data, version) = self.current(transport, secret)?;
+        data.insert(secret.key.clone(), Value::from(value));
+        let data = match secret.is_kv2() {
+            // version 0 only writes a secret that does not exist yet.
+            true => json!({ "options": { "cas": version.unwrap_or(0) }, "data": data }),
+            false => Value::Object(data),
+        };
+
+        let mut request = self
+            .request(Method::Post, secret)
+            .header("Content-Type", "application/json")
+            .body(data.to_string());
+        let response = transport.send(&request);
+        request.body.zeroize();
+
+        let response = response?;
+        if !(200..300).contains(&response.status) {
+            return Err(PPCError::from(format!(
+                "Vault responded to writing {} with status {}: {}",
+                secret.path, response.status, response.body
+            )));
+        }
+        Ok(())
+    }
+
+    /// The keys of `secret`, none if it does not exist yet, and for KV version
+    /// 2 its current version, also of a deleted secret.
+    fn current(
+        &self,
+        transport: &dyn Transport,
+        secret: &SecretRef,
+    ) -> Result<(Map<String, Value>, Option<u64>), PPCError> {
+        let response = transport.send(&self.request(Method::Get, secret))?;
+        let body = Zeroizing::new(response.body);
+        if response.status != 404 && !(200..300).contains(&response.status) {
+            return Err(PPCError::from(format!(
+                "Vault responded to reading {} with status {}",
+                secret.path, response.status
+            )));
+        }
+
+        // Vault answers 404 without a body for secrets that never existed.
+        let mut value = match serde_json::from_str::<Value>(&body) {
+            Ok(value) => value,
+            Err(_) if response.status == 404 => return Ok((Map::new(), None)),
+            Err(e) => {
+                return Err(PPCError::from(format!(
+                    "unexpected response from Vault: {}",
+                    e
+                )))
+            }
+        };
+        let (data, version) = if secret.is_kv2() {
+            let version = value["data"]["metadata"]["version"].as_u64();
+            (value["data"]["data"].take(), version)
+        } else {
+            (value["data"].take(), None)
+        };
+        match data {
+            Value::Object(data) => Ok((data, version)),
+            Value::Null => Ok((Map::new(), version)),
+            _ => Err(PPCError::from(format!(
+                "unexpected response from Vault for {}",
+                secret.path
+            ))),
+        }
+    }
+
+    fn request(&self, method: Method, secret: &SecretRef) -> PPCRequest {
+        let mut segments = vec!["v1"];
+        segments.extend(secret.path.split('/'));
+        let url = api_url::page(self.addr.trim_end_matches('/'), &segments);
+
+        let mut request =
+            PPCRequest::new(method, url).header("X-Vault-Token", self.token.expose_secret());
+        if let Some(namespace) = &self.namespace {
+            request = request.header("X-Vault-Namespace", namespace);
+        }
+        request
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use secrecy::{ExposeSecret, SecretString};
+
+    use super::{parse_ref, Vault};
+    use crate::http::mock::MockTransport;
+    use crate::http::Method;
+
+    fn vault() -> Vault {
+        Vault {
+            addr: String::from("https://vault.example.com:8200/"),
+            token: SecretString::from("s.t0k3n"),
+            namespace: None,
+        }
+    }
+
+    #[test]
+    fn parse_refs() {
+        let secret = parse_ref("secret/data/db#password").unwrap();
+        assert_eq!(secret.path, "secret/data/db");
+        assert_eq!(secret.key, "password");
+        assert!(secret.is_kv2());
+        assert!(!parse_ref("kv/db#password").unwrap().is_kv2());
+
+        assert!(parse_ref("secret/data/db").is_err());
+        assert!(parse_ref("#password").is_err());
+        assert!(parse_ref("secret/db#").is_err());
+    }
+
+    #[test]
+    fn read_kv2() {
+        let transport = MockTransport::new().respond(
+            200,
+            r#"{"data":{"data":{"password":"s3cret"},"metadata":{"version":3}}}"#,
+        );
+        let secret = parse_ref("secret/data/db#password").unwrap();
+
+        let value = vault().read(&transport, &secret).unwrap();
+
+        assert_eq!(value.expose_secret(), "s3cret");
+        let request = &transport.requests()[0];
+        assert_eq!(request.method, Method::Get);
+        assert_eq!(
+            request.url,
+            "https://vault.example.com:8200/v1/secret/data/db"
+        );
+        assert_eq!(
+            request.headers,
+            [(String::from("X-Vault-Token"), String::from("s.t0k3n"))]
+        );
+    }
+
+    #[test]
+    fn read_kv1_and_missing_keys() {
+        let transport = MockTransport::new()
+            .respond(200, r#"{"data":{"password":"s3cret","port":5432}}"#)
+            .respond(200, r#"{"data":{"password":"s3cret","port":5432}}"#)
+            .respond(403, r#"{"errors":["permission denied"]}"#);
+
+        let value = vault()
+            .read(&transport, &parse_ref("kv/db#password").unwrap())
+            .unwrap();
+        assert_eq!(value.expose_secret(), "s3cret");
+
+        assert!(vault()
+            .read(&transport, &parse_ref("kv/db#port").unwrap())
+            .is_err());
+        assert!(vault()
+            .read(&transport, &parse_ref("kv/db#password").unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn write_kv2_keeps_other_keys() {
+        let transport = MockTransport::new()
+            .respond(
+                200,
+                r#"{"data":{"data":{"password":"old","user":"app"},"metadata":{"version":3}}}"#,
+            )
+            .respond(200, "{}");
+        let secret = parse_ref("secret/data/handoff#password").unwrap();
+
+        vault().write(&transport, &secret, "s3cret").unwrap();
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].method, Method::Get);
+        assert_eq!(requests[1].method, Method::Post);
+        let body: serde_json::Value =
+            serde_json::from_str(requests[1].body.as_deref().unwrap()).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "options": { "cas": 3 },
+                "data": { "password": "s3cret", "user": "app" },
+            })
+        );
+    }
+
+    #[test]
+    fn write_new_secrets() {
+        let transport = MockTransport::new()
+            .respond(404, r#"{"errors":[]}"#)
+            .respond(200, "{}")
+            .respond(404, "")
+            .respond(204, "");
+
+        vault()
+            .write(
+                &transport,
+                &parse_ref("secret/data/handoff#password").unwrap(),
+                "s3cret",
+            )
+            .unwrap();
+        vault()
+            .write(
+                &transport,
+                &parse_ref("kv/handoff#password").unwrap(),
+                "s3cret",
+            )
+            .unwrap();
+
+        let requests = transport.requests();
+        assert_eq!(
+            requests[1].body.as_deref(),
+            Some(r#"{"options":{"cas":0},"data":{"password":"s3cret"}}"#)
+        );
+        assert_eq!(
+            requests[3].body.as_deref(),
+            Some(r#"{"password":"s3cret"}"#)
+        );
+    }
+
+    #[test]
+    fn write_fails_if_the_secret_cannot_be_read() {
+        let transport = MockTransport::new().respond(403, r#"{"errors":["permission denied"]}"#);
+
+        let secret = parse_ref("secret/data/handoff#password").unwrap();
+        assert!(vault().write(&transport, &secret, "s3cret").is_err());
+        assert_eq!(transport.requests().len(), 1);
+    }
+}
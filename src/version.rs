@@ -0,0 +1,200 @@
+//! Version and build information, and compatibility with the instance.
+//!
+//! Besides the version of the CLI itself, the `version` command asks the
+//! instance for its server version. Features of the CLI that need a newer
+//! server than the instance runs are listed, so users know upfront which
+//! commands are going to fail. An unreachable instance is not an error, as
+//! the command is also useful to find out which build is installed. The build
+//! information includes the TLS backend, see the `native-tls` and `rustls`
+//! features.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::args::PPCArgs;
+use crate::errors::PPCError;
+use crate::http::{Transport, TLS_BACKEND};
+use crate::instance::Instance;
+use crate::models::ServerVersion;
+use crate::render::CommandOutput;
+use crate::{pwpush_api, user_agent};
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+pub const GIT_HASH: &str = env!("PWPUSH_CLI_GIT_HASH");
+pub const BUILD_DATE: &str = env!("PWPUSH_CLI_BUILD_DATE");
+
+/// Features of the CLI and the first server version that supports them.
+const REQUIREMENTS: [(&str, &str); 4] = [
+    ("1.23.0", "audit logs via the JSON API (audit)"),
+    (
+        "1.27.0",
+        "retrieval of passphrase-protected pushes (get --passphrase)",
+    ),
+    (
+        "1.41.0",
+        "lists of active and expired pushes (export, summary, remind)",
+    ),
+    (
+        BEARER_AUTH_VERSION,
+        "tokens without email (--auth-scheme bearer)",
+    ),
+];
+
+/// First server version that accepts bearer tokens, see `instance`.
+pub const BEARER_AUTH_VERSION: &str = "1.49.0";
+
+/// A feature that is not available on the instance.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Incompatibility {
+    pub feature: &'static str,
+    pub required_version: &'static str,
+}
+
+/// Result of the `version` command.
+pub struct VersionInfo {
+    user_agent: String,
+    instance: String,
+    server: Result<ServerVersion, PPCError>,
+    incompatibilities: Vec<Incompatibility>,
+}
+
+pub fn run(transport: &dyn Transport, instance: &Instance, args: &PPCArgs) -> VersionInfo {
+    let server = pwpush_api::server_version(transport, instance);
+    let incompatibilities = match &server {
+        Ok(ServerVersion {
+            application_version: Some(version),
+            ..
+        }) => incompatibilities(version),
+        _ => Vec::new(),
+    };
+
+    VersionInfo {
+        user_agent: args.user_agent.clone().unwrap_or_else(user_agent::default),
+        instance: instance.base_url(),
+        server,
+        incompatibilities,
+    }
+}
+
+impl CommandOutput for VersionInfo {
+    fn json(&self) -> Value {
+        let server = match &self.server {
+            Ok(version) => json!(version),
+            Err(e) => json!({ "error": e.to_string() }),
+        };
+        json!({
+            "version": VERSION,
+            "git_hash": GIT_HASH,
+            "build_date": BUILD_DATE,
+            "tls_backend": TLS_BACKEND,
+            "user_agent": self.user_agent,
+            "instance": self.instance,
+            "server": server,
+            "incompatibilities": self.incompatibilities,
+        })
+    }
+
+    fn human(&self, _width: Option<usize>) -> String {
+        let mut out = format!(
+            "pwpush-cli {} ({}, built {}, {})\nuser agent: {}\n",
+            VERSION, GIT_HASH, BUILD_DATE, TLS_BACKEND, self.user_agent
+        );
+        out += &match &self.server {
+            Ok(version) => format!(
+                "instance {}: {} (api {}, {} edition)\n",
+                self.instance,
+                version.application_version.as_deref().unwrap_or("unknown"),
+                version.api_version.as_deref().unwrap_or("unknown"),
+                version.edition.as_deref().unwrap_or("unknown"),
+            ),
+            Err(e) => format!("instance {}: version unknown ({})\n", self.instance, e),
+        };
+        for incompatibility in &self.incompatibilities {
+            out += &format!(
+                "not supported by the instance, needs server {}: {}\n",
+                incompatibility.required_version, incompatibility.feature
+            );
+        }
+        out
+    }
+
+    fn quiet(&self) -> String {
+        format!("{}\n", VERSION)
+    }
+}
+
+/// Features that need a newer server than `server_version`. Versions that
+/// cannot be parsed are assumed to be compatible.
+fn incompatibilities(server_version: &str) -> Vec<Incompatibility> {
+    let Some(server) = parse_version(server_version) else {
+        return Vec::new();
+    };
+
+    REQUIREMENTS
+        .iter()
+        .filter(|(required, _)| parse_version(required).is_some_and(|r| server < r))
+        .map(|(required, feature)| Incompatibility {
+            feature,
+            required_version: required,
+        })
+        .collect()
+}
+
+/// True if the server version is at least the required one. Versions that
+/// cannot be parsed are assumed to be older.
+pub fn supports(server_version: &str, required: &str) -> bool {
+    match (parse_version(server_version), parse_version(required)) {
+        (Some(server), Some(required)) => server >= required,
+        _ => false,
+    }
+}
+
+/// Numeric components of a version like `1.41.2` or `v1.41`, for comparison.
+/// Pre-release suffixes are ignored.
+fn parse_version(version: &str) -> Option<Vec<u64>> {
+    let version = version.trim().trim_start_matches('v');
+    let version = version.split(['-', '+']).next()?;
+
+    let mut parts = version
+        .split('.')
+        .map(|part| part.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    // 1.41 and 1.41.0 are the same version.
+    while parts.len() < 3 {
+        parts.push(0);
+    }
+    Some(parts)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{incompatibilities, parse_version, supports};
+
+    #[test]
+    fn parse_versions() {
+        assert_eq!(parse_version("1.41.2"), Some(vec![1, 41, 2]));
+        assert_eq!(parse_version("v1.41"), Some(vec![1, 41, 0]));
+        assert_eq!(parse_version("2.0.0-beta.1"), Some(vec![2, 0, 0]));
+        assert_eq!(parse_version("latest"), None);
+    }
+
+    #[test]
+    fn incompatibilities_of_old_server() {
+        let features = incompatibilities("1.25.3")
+            .iter()
+            .map(|i| i.required_version)
+            .collect::<Vec<_>>();
+
+        assert_eq!(features, vec!["1.27.0", "1.41.0", "1.49.0"]);
+        assert!(incompatibilities("1.49.0").is_empty());
+        assert!(incompatibilities("unknown").is_empty());
+    }
+
+    #[test]
+    fn supports_compares_versions() {
+        assert!(supports("1.49.1", "1.49.0"));
+        assert!(supports("2.0", "1.49.0"));
+        assert!(!supports("1.48.9", "1.49.0"));
+        assert!(!supports("latest", "1.49.0"));
+    }
+}
@@ -0,0 +1,206 @@
+//! Events of pushes that end, for the local API, see `serve`.
+//!
+//! The API of Password Pusher has no events, clients only learn that a push
+//! was viewed for the last time or expired by asking for it. With
+//! `serve --event-webhook <URL>`, the local API does the asking: pushes that
+//! were created through `POST /push` or added with `POST /watch` are tracked,
+//! and the expired text pushes of the account are fetched every
+//! `--watch-interval`. Once a tracked push shows up there, each webhook gets
+//! an event, and the push is no longer tracked:
+//!
+//! ```json
+//! {"event": "fully_viewed", "text": "Secret link for db was viewed for the last time",
+//!  "url_token": "fkwjfvhall92", "expired_on": "2024-02-01T10:00:00Z", "note": "db", ...}
+//! ```
+//!
+//! `event` is `fully_viewed` if the views ran out, `deleted` if the creator or
+//! a viewer deleted the push, and `expired` if the days ran out. Like the
+//! announcements of `--notify-webhook`, the events only contain metadata, and
+//! a `text` field that chat webhooks post as message. The webhooks are posted
+//! with a transport of their own, not with the one of the instance, see
+//! `hooks`.
+//!
+//! The list is only fetched while pushes are tracked, and only the lists of
+//! the account contain expired pushes, so events need credentials. Tracked
+//! pushes are kept in memory: pushes that end while the API is not running
+//! are not reported.
+
+use std::collections::BTreeSet;
+use std::ops::ControlFlow;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tracing::debug;
+
+use crate::args::{PushKind, PushState};
+use crate::errors::PPCError;
+use crate::http::Transport;
+use crate::instance::Instance;
+use crate::models::Push;
+use crate::scheduler::Scheduler;
+use crate::{hooks, pwpush_api};
+
+/// Pushes that are tracked, and where their events go.
+#[derive(Debug)]
+pub struct Watchlist {
+    webhooks: Vec<String>,
+    interval: Duration,
+    tokens: Mutex<BTreeSet<String>>,
+}
+
+impl Watchlist {
+    /// A watchlist for the instance, which fails without credentials, see the
+    /// module docs.
+    pub fn new(
+        instance: &Instance,
+        webhooks: Vec<String>,
+        interval: Duration,
+    ) -> Result<Self, PPCError> {
+        if instance.token.is_none() {
+            return Err(PPCError::from(
+                "--event-webhook needs credentials, as only the account knows its expired pushes",
+            ));
+        }
+        Ok(Self {
+            webhooks,
+            interval,
+            tokens: Mutex::new(BTreeSet::new()),
+        })
+    }
+
+    /// Track a push until it ended.
+    pub fn watch(&self, url_token: &str) {
+        debug!("watching push {}", url_token);
+        self.lock().insert(String::from(url_token));
+    }
+
+    /// The tracked pushes.
+    pub fn tokens(&self) -> Vec<String> {
+        self.lock().iter().cloned().collect()
+    }
+
+    /// Look for ended pushes every interval, until a signal is received. The
+    /// list is fetched with `transport`, the events are posted with
+    /// `webhooks`.
+    pub fn run(&self, transport: &dyn Transport, webhooks: &dyn Transport, instance: &Instance) {
+        Scheduler::new("poll-expired-pushes", self.interval).run(
+            || -> Result<ControlFlow<()>, PPCError> {
+                self.poll(transport, webhooks, instance)?;
+                Ok(ControlFlow::Continue(()))
+            },
+        );
+    }
+
+    /// Report the tracked pushes that are in the list of expired pushes. If
+    /// the list cannot be fetched, the pushes stay tracked for the next poll.
+    fn poll(
+        &self,
+        transport: &dyn Transport,
+        webhooks: &dyn Transport,
+        instance: &Instance,
+    ) -> Result<(), PPCError> {
+        if self.lock().is_empty() {
+            return Ok(());
+        }
+        let expired = pwpush_api::list(transport, instance, PushKind::Text, PushState::Expired)?;
+
+        // the lock is not held while the webhooks are sent, so the API can
+        // keep adding pushes.
+        let ended = {
+            let mut tokens = self.lock();
+            expired
+                .into_iter()
+                .filter(|push| tokens.remove(&push.url_token))
+                .collect::<Vec<_>>()
+        };
+        for push in &ended {
+            hooks::notify_expiry(webhooks, &self.webhooks, event(push), push);
+        }
+        Ok(())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeSet<String>> {
+        // the set is consistent after every operation, even if a thread
+        // panicked while holding the lock.
+        self.tokens.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Why an expired push ended, see the module docs.
+fn event(push: &Push) -> &'static str {
+    if push.deleted == Some(true) {
+        "deleted"
+    } else if push.views_remaining == Some(0) {
+        "fully_viewed"
+    } else {
+        "expired"
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::Watchlist;
+    use crate::args::InstanceProtocol;
+    use crate::http::mock::MockTransport;
+    use crate::instance::Instance;
+
+    fn instance(token: Option<&str>) -> Instance {
+        Instance {
+            protocol: InstanceProtocol::Https,
+            url: String::from("pwpush.example.com"),
+            email: Some(String::from("user@example.com")),
+            token: token.map(Into::into),
+            auth_scheme: None,
+        }
+    }
+
+    #[test]
+    fn ended_pushes_are_reported_once() {
+        let instance = instance(Some("t0k3n"));
+        let webhooks = vec![String::from("https://hooks.example.com/a")];
+        let watchlist = Watchlist::new(&instance, webhooks, Duration::ZERO).unwrap();
+        watchlist.watch("abc");
+        watchlist.watch("def");
+        watchlist.watch("ghi");
+        let transport = MockTransport::new().respond(
+            200,
+            r#"[{"url_token":"abc","views_remaining":0,"note":"db"},
+                {"url_token":"def","deleted":true},
+                {"url_token":"xyz","days_remaining":0}]"#,
+        );
+        let hooks = MockTransport::new().respond(200, "{}").respond(200, "{}");
+
+        watchlist.poll(&transport, &hooks, &instance).unwrap();
+
+        assert_eq!(watchlist.tokens(), vec!["ghi"]);
+        // only the list goes to the instance.
+        assert_eq!(transport.requests().len(), 1);
+        let requests = hooks.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].url, "https://hooks.example.com/a");
+        let event: serde_json::Value =
+            serde_json::from_str(requests[0].body.as_deref().unwrap()).unwrap();
+        assert_eq!(event["event"], "fully_viewed");
+        assert_eq!(
+            event["text"],
+            "Secret link for db was viewed for the last time"
+        );
+        assert!(requests[1].body.as_deref().unwrap().contains("\"deleted\""));
+    }
+
+    #[test]
+    fn nothing_is_fetched_without_tracked_pushes() {
+        let authenticated = instance(Some("t0k3n"));
+        let watchlist = Watchlist::new(&authenticated, Vec::new(), Duration::ZERO).unwrap();
+        let transport = MockTransport::new();
+
+        watchlist
+            .poll(&transport, &transport, &authenticated)
+            .unwrap();
+
+        assert!(transport.requests().is_empty());
+        assert!(Watchlist::new(&instance(None), Vec::new(), Duration::ZERO).is_err());
+    }
+}
@@ -0,0 +1,149 @@
+//! Pushing the credentials of WiFi networks.
+//!
+//! Handing out the WiFi password to guests and new colleagues is one of the
+//! most common uses of a helpdesk. `push wifi --ssid <SSID>` formats the
+//! credentials in the `WIFI:` format that phones understand when scanning a
+//! QR code, e.g. `WIFI:T:WPA;S:Office;P:s3cret;;`, and pushes it as text.
+//! With `--qr`, the instance shows the push as QR code, so recipients can join
+//! the network by scanning the screen with their phone, without typing the
+//! password. This needs an instance that has QR code pushes enabled.
+//!
+//! The password is asked for with hidden input if neither `--psk` nor
+//! `--stdin` is given, as it is as secret as any other payload. Apart from the
+//! payload, the push is created exactly like `push text`, see `structured`.
+
+use secrecy::{ExposeSecret, SecretString};
+use zeroize::Zeroizing;
+
+use crate::args::{PPCText, PPCWifi, WifiSecurity};
+use crate::config::PPCConfig;
+use crate::errors::PPCError;
+use crate::structured;
+
+/// The text push with the credentials of the network as payload.
+pub fn text(ppc_wifi: &PPCWifi, config: &PPCConfig) -> Result<PPCText, PPCError> {
+    let psk = match ppc_wifi.security {
+        WifiSecurity::Nopass if ppc_wifi.psk.is_some() || ppc_wifi.stdin => {
+            return Err(PPCError::from(
+                "open networks have no password, leave out --psk and --stdin",
+            ));
+        }
+        WifiSecurity::Nopass => None,
+        WifiSecurity::Wpa | WifiSecurity::Wep => Some(structured::read_option(
+            "--psk",
+            ppc_wifi.psk.as_ref(),
+            ppc_wifi.stdin,
+            &ppc_wifi.push,
+            "Password of the network: ",
+            config,
+        )?),
+    };
+    if let Some(psk) = &psk {
+        check_psk(ppc_wifi.security, psk.expose_secret())?;
+    }
+
+    let payload = payload(
+        &ppc_wifi.ssid,
+        ppc_wifi.security,
+        psk.as_ref(),
+        ppc_wifi.hidden,
+    );
+    Ok(structured::text(payload, &ppc_wifi.push))
+}
+
+/// Refuse passwords that no network accepts, which are most likely typos.
+fn check_psk(security: WifiSecurity, psk: &str) -> Result<(), PPCError> {
+    let valid = match security {
+        // passphrases, or the key itself as 64 hex digits.
+        WifiSecurity::Wpa => {
+            (8..=63).contains(&psk.chars().count())
+                || (psk.len() == 64 && psk.chars().all(|c| c.is_ascii_hexdigit()))
+        }
+        WifiSecurity::Wep => !psk.is_empty(),
+        WifiSecurity::Nopass => true,
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(PPCError::from(match security {
+            WifiSecurity::Wpa => "WPA passwords have 8 to 63 characters",
+            _ => "the password of the network is empty",
+        }))
+    }
+}
+
+/// The credentials in the `WIFI:` format, e.g. `WIFI:T:WPA;S:Office;P:s3cret;;`.
+fn payload(
+    ssid: &str,
+    security: WifiSecurity,
+    psk: Option<&SecretString>,
+    hidden: bool,
+) -> SecretString {
+    let mut payload = Zeroizing::new(String::from("WIFI:"));
+    payload.push_str(match security {
+        WifiSecurity::Wpa => "T:WPA;",
+        WifiSecurity::Wep => "T:WEP;",
+        WifiSecurity::Nopass => "T:nopass;",
+    });
+    payload.push_str(&format!("S:{};", escape(ssid)));
+    if let Some(psk) = psk {
+        payload.push_str(&format!("P:{};", escape(psk.expose_secret())));
+    }
+    if hidden {
+        payload.push_str("H:true;");
+    }
+    payload.push(';');
+    SecretString::from(payload.as_str())
+}
+
+/// Escape the characters that separate the fields of the format.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | ';' | ',' | ':' | '"') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use secrecy::{ExposeSecret, SecretString};
+
+    use super::{check_psk, payload};
+    use crate::args::WifiSecurity;
+
+    #[test]
+    fn credentials_are_formatted_and_escaped() {
+        let format = |ssid, security, psk: Option<&str>, hidden| {
+            let psk = psk.map(SecretString::from);
+            payload(ssid, security, psk.as_ref(), hidden)
+                .expose_secret()
+                .to_string()
+        };
+
+        assert_eq!(
+            format("Office", WifiSecurity::Wpa, Some("s3cret!!"), false),
+            "WIFI:T:WPA;S:Office;P:s3cret!!;;"
+        );
+        assert_eq!(
+            format("Bob's \"Net\"", WifiSecurity::Wpa, Some(r"a;b,c:d\e"), true),
+            r#"WIFI:T:WPA;S:Bob's \"Net\";P:a\;b\,c\:d\\e;H:true;;"#
+        );
+        assert_eq!(
+            format("Guests", WifiSecurity::Nopass, None, false),
+            "WIFI:T:nopass;S:Guests;;"
+        );
+    }
+
+    #[test]
+    fn impossible_passwords_are_refused() {
+        assert!(check_psk(WifiSecurity::Wpa, "short").is_err());
+        assert!(check_psk(WifiSecurity::Wpa, "long enough").is_ok());
+        assert!(check_psk(WifiSecurity::Wpa, &"f".repeat(64)).is_ok());
+        assert!(check_psk(WifiSecurity::Wpa, &"x".repeat(64)).is_err());
+        assert!(check_psk(WifiSecurity::Wep, "").is_err());
+    }
+}
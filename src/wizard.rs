@@ -0,0 +1,314 @@
+//! Interactive walk-through for occasional users.
+//!
+//! `wizard` asks for the kind of push, the secret, the expiration and an
+//! optional passphrase and note, one question at a time. Answers are validated
+//! right away, and invalid answers are asked again with a hint, instead of
+//! failing the whole command. Before pushing, a summary is shown for
+//! confirmation.
+//!
+//! The questions go to stderr and the secrets are read without echo, like the
+//! other prompts. The push itself is done exactly like `push text`, so
+//! defaults, presets and the output are the same.
+
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use secrecy::{ExposeSecret, SecretString};
+
+use crate::args::PPCText;
+use crate::config::{PPCConfig, PassphrasePolicy};
+use crate::dedupe;
+use crate::errors::PPCError;
+use crate::payload::{self, DEFAULT_MAX_PAYLOAD_SIZE};
+use crate::prompt;
+
+/// Ask all questions, and return the push to create, or `None` if the user
+/// did not confirm it.
+pub fn run(config: &PPCConfig) -> Result<Option<PPCText>, PPCError> {
+    if !io::stdin().is_terminal() {
+        return Err(PPCError::from(
+            "the wizard is interactive, use `push text` in non-interactive mode",
+        ));
+    }
+
+    Wizard {
+        input: &mut io::stdin().lock(),
+        output: &mut io::stderr(),
+        secret: &prompt::secret,
+    }
+    .run(config)
+}
+
+struct Wizard<'a> {
+    input: &'a mut dyn BufRead,
+    output: &'a mut dyn Write,
+    /// Reads hidden input. Replaced in tests, as there is no terminal.
+    secret: &'a dyn Fn(&str) -> Result<SecretString, PPCError>,
+}
+
+impl Wizard<'_> {
+    fn run(&mut self, config: &PPCConfig) -> Result<Option<PPCText>, PPCError> {
+        self.ask("What do you want to share? [text]", parse_kind)?;
+
+        let limit = config
+            .push
+            .max_payload_size
+            .unwrap_or(DEFAULT_MAX_PAYLOAD_SIZE);
+        let payload = self.ask_secret("Secret (hidden): ", |secret| {
+            if secret.expose_secret().is_empty() {
+                return Err(String::from("the secret must not be empty"));
+            }
+            payload::check_size(secret.expose_secret().len(), limit).map_err(|e| e.to_string())
+        })?;
+
+        let days = self.ask(
+            &format!(
+                "Expire after how many days? [{}]",
+                default(config.push.expire_after_days)
+            ),
+            parse_count,
+        )?;
+        let views = self.ask(
+            &format!(
+                "Expire after how many views? [{}]",
+                default(config.push.expire_after_views)
+            ),
+            parse_count,
+        )?;
+
+        let required = config.push.passphrase_policy == PassphrasePolicy::Required;
+        let passphrase = self.ask_secret("Passphrase (hidden, empty for none): ", |secret| {
+            if required && secret.expose_secret().is_empty() {
+                Err(String::from("the configuration requires a passphrase"))
+            } else {
+                Ok(())
+            }
+        })?;
+        let passphrase = Some(passphrase).filter(|p| !p.expose_secret().is_empty());
+
+        let note = self.ask("Note, only visible to you (optional)", |note| {
+            Ok(Some(String::from(note)).filter(|note| !note.is_empty()))
+        })?;
+
+        writeln!(
+            self.output,
+            "\nPushing a text secret that expires after {} days or {} views, {}.",
+            default(days.or(config.push.expire_after_days)),
+            default(views.or(config.push.expire_after_views)),
+            if passphrase.is_some() {
+                "protected by a passphrase"
+            } else {
+                "without passphrase"
+            }
+        )
+        .map_err(io_err)?;
+        let confirmed = self.ask("Push now? [Y/n]", |answer| {
+            match answer.to_lowercase().as_str() {
+                "" | "y" | "yes" => Ok(true),
+                "n" | "no" => Ok(false),
+                _ => Err(String::from("answer yes or no")),
+            }
+        })?;
+        if !confirmed {
+            return Ok(None);
+        }
+
+        Ok(Some(PPCText {
+            password_payload: Some(payload),
+            payload_file: None,
+            stdin: false,
+            prompt: false,
+            from_op: None,
+            from_bw: None,
+            from_vault: None,
+            from_env: Vec::new(),
+            base64: false,
+            normalize_newlines: false,
+            encrypt_for: None,
+            // the secret was typed in hidden, so it is not in the process list.
+            no_warn: true,
+            passphrase,
+            qr: false,
+            qr_link: false,
+            note,
+            expire_after_days: days,
+            expire_after_views: views,
+            deletable_by_viewer: None,
+            retrieval_step: None,
+            no_defaults: false,
+            preset: None,
+            queue_on_failure: false,
+            paranoid: false,
+            enforce_strong_passphrase: false,
+            hold: false,
+            hold_for: None,
+            ephemeral: false,
+            expire_at: None,
+            expire_after: None,
+            exact: false,
+            to_profiles: Vec::new(),
+            recipients: Vec::new(),
+            notify_webhook: None,
+            email_to: None,
+            shorten: false,
+            validate: false,
+            tags: Vec::new(),
+            expire_after_first_view_confirmed: false,
+            dedupe: None,
+            dedupe_window: dedupe::DEFAULT_WINDOW,
+            idempotency_key: None,
+        }))
+    }
+
+    /// Ask until `parse` accepts the answer.
+    fn ask<T>(
+        &mut self,
+        question: &str,
+        parse: impl Fn(&str) -> Result<T, String>,
+    ) -> Result<T, PPCError> {
+        loop {
+            write!(self.output, "{} ", question).map_err(io_err)?;
+            self.output.flush().map_err(io_err)?;
+
+            let mut answer = String::new();
+            if self.input.read_line(&mut answer).map_err(io_err)? == 0 {
+                return Err(PPCError::from("input ended before the wizard was done"));
+            }
+
+            match parse(answer.trim()) {
+                Ok(value) => return Ok(value),
+                Err(hint) => writeln!(self.output, "  {}", hint).map_err(io_err)?,
+            }
+        }
+    }
+
+    /// Ask for hidden input until `check` accepts it.
+    fn ask_secret(
+        &mut self,
+        question: &str,
+        check: impl Fn(&SecretString) -> Result<(), String>,
+    ) -> Result<SecretString, PPCError> {
+        loop {
+            let secret = (self.secret)(question)?;
+            match check(&secret) {
+                Ok(()) => return Ok(secret),
+                Err(hint) => writeln!(self.output, "  {}", hint).map_err(io_err)?,
+            }
+        }
+    }
+}
+
+fn parse_kind(answer: &str) -> Result<(), String> {
+    match answer.to_lowercase().as_str() {
+        "" | "text" => Ok(()),
+        kind @ ("file" | "url") => Err(format!("{} pushes are not supported yet, only text", kind)),
+        _ => Err(String::from("choose text, file or url")),
+    }
+}
+
+/// Empty answers keep the default of the configuration or the instance.
+fn parse_count(answer: &str) -> Result<Option<usize>, String> {
+    if answer.is_empty() {
+        return Ok(None);
+    }
+    match answer.parse::<usize>() {
+        Ok(count) if count > 0 => Ok(Some(count)),
+        _ => Err(format!("`{}` is not a positive number", answer)),
+    }
+}
+
+fn default(value: Option<usize>) -> String {
+    value.map_or_else(|| String::from("instance default"), |v| v.to_string())
+}
+
+fn io_err(e: io::Error) -> PPCError {
+    PPCError::from(format!("could not run the wizard: {}", e))
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+
+    use secrecy::{ExposeSecret, SecretString};
+
+    use super::Wizard;
+    use crate::args::PPCText;
+    use crate::config::{PPCConfig, PassphrasePolicy};
+    use crate::errors::PPCError;
+
+    /// Run the wizard with the given line answers and hidden answers.
+    fn wizard(
+        config: &PPCConfig,
+        answers: &str,
+        secrets: &[&str],
+    ) -> (Result<Option<PPCText>, PPCError>, String) {
+        let secrets = RefCell::new(secrets.iter().map(|s| s.to_string()).collect::<Vec<_>>());
+        let secret = |_: &str| Ok(SecretString::from(secrets.borrow_mut().remove(0)));
+        let mut output = Vec::new();
+
+        let result = Wizard {
+            input: &mut answers.as_bytes(),
+            output: &mut output,
+            secret: &secret,
+        }
+        .run(config);
+
+        (result, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn walks_through_a_push() {
+        let (result, _) = wizard(
+            &PPCConfig::default(),
+            "text\n3\n\ndb password\ny\n",
+            &["s3cret", "word"],
+        );
+
+        let text = result.unwrap().unwrap();
+        assert_eq!(text.password_payload.unwrap().expose_secret(), "s3cret");
+        assert_eq!(text.expire_after_days, Some(3));
+        assert_eq!(text.expire_after_views, None);
+        assert_eq!(text.passphrase.unwrap().expose_secret(), "word");
+        assert_eq!(text.note.as_deref(), Some("db password"));
+    }
+
+    #[test]
+    fn invalid_answers_are_asked_again() {
+        let (result, output) = wizard(
+            &PPCConfig::default(),
+            "file\n\n0\n2\n\n\n\n",
+            &["", "s3cret", ""],
+        );
+
+        let text = result.unwrap().unwrap();
+        assert_eq!(text.expire_after_days, Some(2));
+        assert!(text.passphrase.is_none());
+        assert!(output.contains("file pushes are not supported yet"));
+        assert!(output.contains("the secret must not be empty"));
+        assert!(output.contains("`0` is not a positive number"));
+    }
+
+    #[test]
+    fn required_passphrase() {
+        let mut config = PPCConfig::default();
+        config.push.passphrase_policy = PassphrasePolicy::Required;
+
+        let (result, output) = wizard(&config, "\n\n\n\n\n", &["s3cret", "", "word"]);
+
+        assert!(result.unwrap().unwrap().passphrase.is_some());
+        assert!(output.contains("the configuration requires a passphrase"));
+    }
+
+    #[test]
+    fn declined_push() {
+        let (result, _) = wizard(&PPCConfig::default(), "\n\n\n\nn\n", &["s3cret", ""]);
+
+        assert!(result.unwrap().is_none());
+    }
+
+    #[test]
+    fn ended_input() {
+        let (result, _) = wizard(&PPCConfig::default(), "\n", &["s3cret"]);
+
+        assert!(result.is_err());
+    }
+}